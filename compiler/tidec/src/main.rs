@@ -1,27 +1,131 @@
+use std::collections::BTreeMap;
 use std::num::NonZero;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 // #[macro_use] extern crate tidec_utils;
 //
-use tidec_abi::target::BackendKind;
-use tidec_codegen_llvm::entry::llvm_codegen_lir_unit;
-use tidec_lir::basic_blocks::BasicBlockData;
+use tidec_abi::target::{
+    BackendKind, CodeModel, LinkOutputKind, LirTarget, RelocModel, TargetTriple,
+};
+use tidec_abi::targets;
+use tidec_codegen_llvm::entry::LlvmCodegenBackend;
+use tidec_codegen_ssa::backend::{BackendOutput, BackendRegistry, CodegenBackendDriver};
+use tidec_codegen_ssa::ice::{codegen_context, take_lir_dumps};
+use tidec_incremental::cache::DEFAULT_INCREMENTAL_DIR;
+use tidec_interp::entry::InterpBackend;
+use tidec_lir::basic_blocks::{BasicBlockData, BasicBlockKind};
 use tidec_lir::lir::{
-    CallConv, DefId, EmitKind, Linkage, LirBody, LirBodyKind, LirBodyMetadata, LirCtx, LirItemKind,
-    LirUnit, LirUnitMetadata, UnnamedAddress, Visibility,
+    CallConv, DefId, FnAttribute, Linkage, LirBody, LirBodyKind, LirBodyMetadata, LirCtx,
+    LirItemKind, LirUnit, LirUnitMetadata, UnnamedAddress, Visibility,
 };
+use tidec_lir::metadata::{encode_metadata, METADATA_EXTENSION};
+use tidec_lir::parse::parse_lir_source_file_with_externs;
+use tidec_lir::pretty::LirPrinter;
 use tidec_lir::syntax::{
-    ConstOperand, ConstScalar, ConstValue, LirTy, LocalData, Place, RValue, RawScalarValue,
-    Statement, Terminator, RETURN_LOCAL,
+    ConstOperand, ConstScalar, ConstValue, Generics, LirTy, LocalData, Operand, Place, RValue,
+    RawScalarValue, Statement, StatementKind, Terminator, TerminatorKind, RETURN_LOCAL,
+};
+use tidec_lir::transform::run_lir_passes;
+use tidec_monomorphize::collector::collect_and_monomorphize;
+use tidec_session::config::{
+    AliasMetadata, DebugInfo, EmitKind, Lto, OptLevel, Options, SanitizerSet, SplitDebugInfo,
+    StackProtector,
 };
-use tidec_utils::index_vec::IdxVec;
+use tidec_session::diagnostics::DiagCtxt;
+use tidec_session::Session;
+use tidec_span::Span;
+use tidec_utils::{idx::Idx, index_vec::IdxVec};
 use tracing::debug;
 
 // TIDEC_LOG=debug cargo run; cc main.o -o a.out; ./a.out; echo $?
 fn main() {
-    init_tidec_logger();
-    debug!("Logging initialized");
+    // The diagnostics of the command line are reported before the session exists.
+    let dcx = DiagCtxt::new();
+    install_ice_hook();
 
     // TODO: check valitiy of TideArgs
-    let lir_ctx = LirCtx::new(BackendKind::Llvm, EmitKind::Object);
+    // The LIR files can be given as arguments, e.g., `cargo run -- main.lir`, where
+    // each file is a unit. With `--lto=thin|fat`, the units are linked and optimized
+    // together into a single object, while each unit is also written as bitcode.
+    // The output is chosen with `--emit=obj|asm|exe|shared` (an object by default),
+    // where `exe` and `shared` link the objects with the system linker (or the one of
+    // the `TIDEC_LINKER` env variable) into an executable or a shared library. The
+    // outputs are written to `--out-dir=DIR` (the current directory by default), and
+    // `-o PATH` names the output, if a single file is emitted (the objects of `exe` and
+    // `shared` are temporary files, removed after linking).
+    // With `--emit-metadata`, the interface of each unit (i.e., the signatures of its
+    // exported functions) is also written to `<unit>.rmeta` in the output directory.
+    // A unit can call the functions of a unit compiled before, whose metadata is given
+    // with `--extern=PATH` (once for each metadata file).
+    // The optimization level is set with `--opt-level=0|1|2|3|s|z` (`2` by default),
+    // and the default pass pipeline of the backend can be replaced with `--passes=PIPELINE`.
+    // The debug info is emitted with `--debuginfo=0|1|2` or `-g0|-g1|-g2` (none by default,
+    // and `-g` is `-g2`), where `1` only emits the line tables, and the source of a body is
+    // the LIR file it is parsed from. The DWARF version is set with `--dwarf-version=2..5`
+    // (`4` by default). The debug info is not split into `.dwo` files, so only
+    // `--split-debuginfo=off` is accepted.
+    // The loads and stores of scalars are annotated with TBAA metadata with
+    // `--alias-metadata=tbaa` (`none` by default). The code is instrumented for the
+    // sanitizers of `--sanitizer=address,undefined` (none by default). The frame pointers
+    // are kept with `--force-frame-pointers`, and the stack of the functions is protected
+    // with `--stack-protector=none|basic|strong|all` (`none` by default). The indices of
+    // the arrays are checked against their length with `--bounds-checks`. The relocation
+    // model is set with `--relocation-model=static|pic|pie` and the code model with
+    // `--code-model=small|kernel|medium|large` (the defaults of the target by default).
+    // A unit is split into N codegen units, compiled in parallel, with
+    // `--codegen-units=N` (`1` by default, and ignored with LTO). With `--incremental[=DIR]`,
+    // the codegen units whose bodies did not change since the previous run are reused
+    // from `DIR` (`target/tidec-incremental` by default, and ignored with LTO). The inlining can be
+    // tuned with `--inline-threshold=N` (`0` disables it), and the LIR can be dumped
+    // after each pass with `--dump-lir=DIR`. The CFGs of the
+    // bodies can be written as Graphviz files with `--dump-dot=DIR`. The target triple
+    // is taken from `--target=TRIPLE`, or else from the `TIDEC_TARGET` env variable.
+    // The backend is selected by its name in the registry with `--backend=NAME` (LLVM
+    // by default), where `interp` runs the `main` function and exits with its return value.
+    // If the compiler panics, it reports an internal compiler error with the body and the
+    // basic block being codegened, and writes the LIR of the body to `tidec-ice-<body>.lir`.
+    // With `--time-passes`, the time spent in each pass, in the codegen of each body and
+    // in each emission step is printed at the end of the compilation. The same spans are
+    // written as a Chrome trace to the file of the `TIDEC_LOG_TRACE_FILE` env variable.
+    let backend_registry = backend_registry();
+    let backend_name = std::env::args()
+        .filter_map(|arg| arg.strip_prefix("--backend=").map(str::to_string))
+        .next_back()
+        .unwrap_or_else(|| "llvm".to_string());
+    let backend = backend_registry.get(&backend_name).unwrap_or_else(|| {
+        dcx.fatal(format_args!(
+            "Unknown backend: {} (available: {})",
+            backend_name,
+            backend_registry.names().collect::<Vec<_>>().join(", ")
+        ))
+    });
+    let (sess, lir_paths) = session_from_args(backend, dcx);
+    // The logger is initialized once the options are known, as it also times the passes.
+    if let Err(err) = sess.init_logger() {
+        sess.dcx()
+            .fatal(format_args!("Error initializing logger: {:?}", err));
+    }
+    debug!("Logging initialized");
+    // The session is shared by the `LirCtx` of every unit.
+    let sess = Arc::new(sess);
+
+    if !lir_paths.is_empty() {
+        let externs = read_extern_files(&sess);
+        // Each unit has its own `LirCtx`, as the backend takes ownership of it. All
+        // the files are parsed, to report all of their errors, before aborting.
+        let lir_units: Vec<_> = lir_paths
+            .iter()
+            .filter_map(|path| {
+                let mut lir_ctx = LirCtx::new(sess.clone());
+                let lir_unit = parse_lir_file(&mut lir_ctx, Path::new(path), &externs)?;
+                Some((lir_ctx, lir_unit))
+            })
+            .collect();
+        sess.dcx().abort_if_errors();
+        codegen_lir_units(&sess, backend, lir_units);
+        return;
+    }
 
     // Create a simple main function that returns 0.
     // ```c
@@ -33,34 +137,40 @@ fn main() {
         def_id: DefId(0),
         name: "main".to_string(),
         kind: LirBodyKind::Item(LirItemKind::Function),
-        inlined: false,
+        attrs: FnAttribute::default(),
         linkage: Linkage::External, // TODO(bruzzone): Check the correct linkage
         visibility: Visibility::Default,
         unnamed_address: UnnamedAddress::None,
         call_conv: CallConv::C,
+        is_variadic: false,
+        personality: None,
+        span: Span::DUMMY,
     };
     let lir_bodies = IdxVec::from_raw(vec![LirBody {
         metadata: lir_body_metadata,
+        generics: Generics::default(),
         ret_and_args: IdxVec::from_raw(vec![LocalData {
             ty: LirTy::I32,
             mutable: false,
+            name: None,
         }]),
         locals: IdxVec::new(),
         basic_blocks: IdxVec::from_raw(vec![BasicBlockData {
-            statements: vec![Statement::Assign(Box::new((
+            statements: vec![Statement::dummy(StatementKind::Assign(Box::new((
                 Place {
                     local: RETURN_LOCAL,
                     projection: vec![],
                 },
-                RValue::Const(ConstOperand::Value(
+                RValue::Use(Operand::Const(ConstOperand::Value(
                     ConstValue::Scalar(ConstScalar::Value(RawScalarValue {
                         data: 7u128,
                         size: NonZero::new(4).unwrap(), // 4 bytes for i32
                     })),
                     LirTy::I32,
-                )),
-            )))],
-            terminator: Terminator::Return,
+                ))),
+            ))))],
+            terminator: Terminator::dummy(TerminatorKind::Return),
+            kind: BasicBlockKind::Regular,
         }]),
     }]);
     let lit_unit_metadata = LirUnitMetadata {
@@ -70,27 +180,427 @@ fn main() {
     let lir_unit: LirUnit = LirUnit {
         metadata: lit_unit_metadata,
         bodies: lir_bodies,
+        imports: BTreeMap::new(),
     };
 
-    codegen_lir_unit(lir_ctx, lir_unit);
+    codegen_lir_units(&sess, backend, vec![(LirCtx::new(sess.clone()), lir_unit)]);
+}
+
+/// Returns the session of the compilation for the given backend, configured by the
+/// command line and the environment, and the LIR files given on the command line.
+/// The session reports its diagnostics to `dcx`. It exits on errors.
+fn session_from_args(backend: &dyn CodegenBackendDriver, dcx: DiagCtxt) -> (Session, Vec<String>) {
+    let mut target = LirTarget::new(backend.backend_kind());
+    let mut opts = Options::default();
+
+    if let Ok(triple) = std::env::var("TIDEC_TARGET") {
+        target = select_target(&dcx, backend.backend_kind(), &triple);
+    }
+    let mut lir_paths = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "-o" {
+            let output = args
+                .next()
+                .unwrap_or_else(|| dcx.fatal("Missing the path of the output after -o"));
+            opts.output = Some(PathBuf::from(output));
+        } else if arg == "--emit-metadata" {
+            opts.emit_metadata = true;
+        } else if let Some(extern_path) = arg.strip_prefix("--extern=") {
+            opts.externs.push(PathBuf::from(extern_path));
+        } else if let Some(out_dir) = arg.strip_prefix("--out-dir=") {
+            opts.out_dir = Some(PathBuf::from(out_dir));
+        } else if let Some(threshold) = arg.strip_prefix("--inline-threshold=") {
+            let threshold = threshold.parse().unwrap_or_else(|_| {
+                dcx.fatal(format_args!("Invalid inline threshold: {}", threshold))
+            });
+            opts.inline_threshold = threshold;
+        } else if let Some(opt_level) = arg.strip_prefix("--opt-level=") {
+            opts.opt_level = parse_opt_level(&dcx, opt_level);
+        } else if let Some(pass_pipeline) = arg.strip_prefix("--passes=") {
+            opts.pass_pipeline = Some(pass_pipeline.to_string());
+        } else if let Some(emit_kind) = arg.strip_prefix("--emit=") {
+            opts.emit_kind = parse_emit_kind(&dcx, emit_kind);
+        } else if let Some(codegen_units) = arg.strip_prefix("--codegen-units=") {
+            match codegen_units.parse() {
+                Ok(codegen_units) if codegen_units > 0 => opts.codegen_units = codegen_units,
+                _ => dcx.fatal(format_args!(
+                    "Invalid number of codegen units: {}",
+                    codegen_units
+                )),
+            }
+        } else if arg == "--incremental" {
+            opts.incremental_dir = Some(PathBuf::from(DEFAULT_INCREMENTAL_DIR));
+        } else if let Some(incremental_dir) = arg.strip_prefix("--incremental=") {
+            opts.incremental_dir = Some(PathBuf::from(incremental_dir));
+        } else if let Some(debuginfo) = arg.strip_prefix("--debuginfo=") {
+            opts.debuginfo = parse_debuginfo(&dcx, debuginfo);
+        } else if arg == "-g" {
+            opts.debuginfo = DebugInfo::Full;
+        } else if let Some(debuginfo @ ("0" | "1" | "2")) = arg.strip_prefix("-g") {
+            opts.debuginfo = parse_debuginfo(&dcx, debuginfo);
+        } else if let Some(dwarf_version) = arg.strip_prefix("--dwarf-version=") {
+            match dwarf_version.parse() {
+                Ok(dwarf_version @ 2..=5) => opts.dwarf_version = Some(dwarf_version),
+                _ => dcx.fatal(format_args!("Invalid DWARF version: {}", dwarf_version)),
+            }
+        } else if let Some(split_debuginfo) = arg.strip_prefix("--split-debuginfo=") {
+            opts.split_debuginfo = parse_split_debuginfo(&dcx, split_debuginfo);
+        } else if let Some(alias_metadata) = arg.strip_prefix("--alias-metadata=") {
+            opts.alias_metadata = parse_alias_metadata(&dcx, alias_metadata);
+        } else if let Some(sanitizers) = arg.strip_prefix("--sanitizer=") {
+            opts.sanitizers = parse_sanitizers(&dcx, sanitizers);
+        } else if arg == "--force-frame-pointers" {
+            opts.force_frame_pointers = true;
+        } else if let Some(stack_protector) = arg.strip_prefix("--stack-protector=") {
+            opts.stack_protector = parse_stack_protector(&dcx, stack_protector);
+        } else if arg == "--bounds-checks" {
+            opts.bounds_checks = true;
+        } else if arg == "--time-passes" {
+            opts.time_passes = true;
+        } else if let Some(reloc_model) = arg.strip_prefix("--relocation-model=") {
+            opts.reloc_model = Some(parse_reloc_model(&dcx, reloc_model));
+        } else if let Some(code_model) = arg.strip_prefix("--code-model=") {
+            opts.code_model = Some(parse_code_model(&dcx, code_model));
+        } else if let Some(lto) = arg.strip_prefix("--lto=") {
+            opts.lto = parse_lto(&dcx, lto);
+        } else if let Some(triple) = arg.strip_prefix("--target=") {
+            target = select_target(&dcx, backend.backend_kind(), triple);
+        } else if let Some(dump_dir) = arg.strip_prefix("--dump-lir=") {
+            opts.dump_lir_dir = Some(PathBuf::from(dump_dir));
+        } else if let Some(dump_dir) = arg.strip_prefix("--dump-dot=") {
+            opts.dump_dot_dir = Some(PathBuf::from(dump_dir));
+        } else if arg.starts_with("--backend=") {
+            // The backend is selected before, as the target depends on it.
+        } else {
+            lir_paths.push(arg);
+        }
+    }
+    (Session::new(target, opts, dcx), lir_paths)
+}
+
+/// Returns the registry of the built-in backends.
+fn backend_registry() -> BackendRegistry {
+    let mut backend_registry = BackendRegistry::new();
+    backend_registry.register(Box::new(LlvmCodegenBackend));
+    backend_registry.register(Box::new(InterpBackend));
+    backend_registry
 }
 
-pub fn codegen_lir_unit(lir_ctx: LirCtx, lir_unit: LirUnit) {
-    match lir_ctx.backend_kind() {
-        BackendKind::Llvm => llvm_codegen_lir_unit(lir_ctx, lir_unit),
-        BackendKind::Cranelift => todo!(),
-        BackendKind::Gcc => todo!(),
+pub fn codegen_lir_units(
+    sess: &Session,
+    backend: &dyn CodegenBackendDriver,
+    lir_units: Vec<(LirCtx, LirUnit)>,
+) {
+    let lir_units = lir_units
+        .into_iter()
+        .map(|(lir_ctx, lir_unit)| {
+            if sess.opts.emit_metadata {
+                write_metadata(&lir_ctx, &lir_unit);
+            }
+            let mut lir_unit = collect_and_monomorphize(&lir_ctx, lir_unit);
+            run_lir_passes(&lir_ctx, &mut lir_unit);
+            if let Some(dump_dir) = lir_ctx.dump_dot_dir() {
+                dump_dot_files(&lir_ctx, &lir_unit, dump_dir);
+            }
+            (lir_ctx, lir_unit)
+        })
+        .collect();
+    // The panic hook has already reported the ICE, only the LIR of the bodies is left.
+    let output = panic::catch_unwind(AssertUnwindSafe(|| backend.codegen_lir_units(lir_units)))
+        .unwrap_or_else(|_| {
+            write_ice_dumps();
+            sess.finish_profiling();
+            std::process::exit(101);
+        });
+    match output {
+        Ok(BackendOutput::Emitted(artifacts)) => {
+            for artifact in artifacts {
+                debug!("Emitted {:?} {}", artifact.kind, artifact.path.display());
+            }
+            sess.finish_profiling();
+        }
+        Ok(BackendOutput::Exited(exit_code)) => {
+            sess.finish_profiling();
+            std::process::exit(exit_code)
+        }
+        Err(err) => sess.dcx().fatal(format_args!(
+            "Error in the {} backend: {}",
+            backend.name(),
+            err
+        )),
     }
 }
 
-/// Initialize the logger for the tidec project.
-fn init_tidec_logger() {
-    if let Err(err) = tidec_log::Logger::init_logger(
-        tidec_log::LoggerConfig::from_prefix("TIDEC").unwrap(),
-        tidec_log::FallbackDefaultEnv::No,
-    ) {
-        eprintln!("Error initializing logger: {:?}", err);
-        std::process::exit(1);
+/// Returns the optimization level with the given name. It exits on errors.
+fn parse_opt_level(dcx: &DiagCtxt, name: &str) -> OptLevel {
+    match name {
+        "0" => OptLevel::No,
+        "1" => OptLevel::Less,
+        "2" => OptLevel::More,
+        "3" => OptLevel::Aggressive,
+        "s" => OptLevel::Size,
+        "z" => OptLevel::SizeMin,
+        _ => dcx.fatal(format_args!("Invalid optimization level: {}", name)),
+    }
+}
+
+/// Returns the emit kind with the given name. It exits on errors.
+fn parse_emit_kind(dcx: &DiagCtxt, name: &str) -> EmitKind {
+    match name {
+        "obj" => EmitKind::Object,
+        "asm" => EmitKind::Assembly,
+        "exe" => EmitKind::Link(LinkOutputKind::Executable),
+        "shared" => EmitKind::Link(LinkOutputKind::SharedLibrary),
+        _ => dcx.fatal(format_args!("Invalid emit kind: {}", name)),
+    }
+}
+
+/// Parses the value of `--debuginfo=`, as `-C debuginfo` of rustc. It exits on errors.
+fn parse_debuginfo(dcx: &DiagCtxt, level: &str) -> DebugInfo {
+    match level {
+        "0" | "none" => DebugInfo::None,
+        "1" | "line-tables-only" => DebugInfo::LineTablesOnly,
+        "2" | "full" => DebugInfo::Full,
+        _ => dcx.fatal(format_args!("Invalid debug info level: {}", level)),
+    }
+}
+
+/// Returns the split debug info mode with the given name. It exits on errors.
+fn parse_split_debuginfo(dcx: &DiagCtxt, name: &str) -> SplitDebugInfo {
+    match name {
+        "off" => SplitDebugInfo::Off,
+        // The LLVM backend cannot write the `.dwo` files (see `SplitDebugInfo`).
+        "unpacked" | "packed" => dcx.fatal(format_args!(
+            "The split debug info mode {} is not supported",
+            name
+        )),
+        _ => dcx.fatal(format_args!("Invalid split debug info mode: {}", name)),
+    }
+}
+
+/// Returns the aliasing metadata mode with the given name. It exits on errors.
+fn parse_alias_metadata(dcx: &DiagCtxt, name: &str) -> AliasMetadata {
+    match name {
+        "none" => AliasMetadata::None,
+        "tbaa" => AliasMetadata::Tbaa,
+        _ => dcx.fatal(format_args!("Invalid alias metadata mode: {}", name)),
+    }
+}
+
+/// Returns the stack protector with the given name. It exits on errors.
+fn parse_stack_protector(dcx: &DiagCtxt, name: &str) -> StackProtector {
+    match name {
+        "none" => StackProtector::None,
+        "basic" => StackProtector::Basic,
+        "strong" => StackProtector::Strong,
+        "all" => StackProtector::All,
+        _ => dcx.fatal(format_args!("Invalid stack protector: {}", name)),
+    }
+}
+
+/// Returns the set of the sanitizers in the given comma-separated list of names. It
+/// exits on errors.
+fn parse_sanitizers(dcx: &DiagCtxt, names: &str) -> SanitizerSet {
+    let mut sanitizers = SanitizerSet::default();
+    for name in names.split(',') {
+        match SanitizerSet::from_name(name) {
+            Some(sanitizer) => sanitizers.insert(sanitizer),
+            None => dcx.fatal(format_args!("Invalid sanitizer: {}", name)),
+        }
+    }
+    sanitizers
+}
+
+/// Returns the relocation model with the given name. It exits on errors.
+fn parse_reloc_model(dcx: &DiagCtxt, name: &str) -> RelocModel {
+    match name {
+        "static" => RelocModel::Static,
+        "pic" => RelocModel::Pic,
+        "pie" => RelocModel::Pie,
+        _ => dcx.fatal(format_args!("Invalid relocation model: {}", name)),
+    }
+}
+
+/// Returns the code model with the given name. It exits on errors.
+fn parse_code_model(dcx: &DiagCtxt, name: &str) -> CodeModel {
+    match name {
+        "small" => CodeModel::Small,
+        "kernel" => CodeModel::Kernel,
+        "medium" => CodeModel::Medium,
+        "large" => CodeModel::Large,
+        _ => dcx.fatal(format_args!("Invalid code model: {}", name)),
+    }
+}
+
+/// Returns the LTO mode with the given name. It exits on errors.
+fn parse_lto(dcx: &DiagCtxt, name: &str) -> Lto {
+    match name {
+        "no" => Lto::No,
+        "thin" => Lto::Thin,
+        "fat" => Lto::Fat,
+        _ => dcx.fatal(format_args!("Invalid LTO mode: {}", name)),
+    }
+}
+
+/// Writes the CFG of each body of the unit to `<dump_dir>/<body>.dot`. It exits on errors.
+fn dump_dot_files(lir_ctx: &LirCtx, lir_unit: &LirUnit, dump_dir: &Path) {
+    let printer = LirPrinter::new(lir_ctx, lir_unit);
+    let write_dot = |lir_body: &LirBody| {
+        std::fs::create_dir_all(dump_dir)?;
+        let path = dump_dir.join(format!("{}.dot", lir_body.metadata.name));
+        debug!(
+            "Writing the CFG of {} to {}",
+            lir_body.metadata.name,
+            path.display()
+        );
+        std::fs::write(path, lir_body.to_dot(&printer))
+    };
+    for lir_body in lir_unit.bodies.iter() {
+        if let Err(err) = write_dot(lir_body) {
+            lir_ctx.sess().dcx().fatal(format_args!(
+                "Error writing the CFG of {}: {}",
+                lir_body.metadata.name, err
+            ));
+        }
+    }
+}
+
+/// Writes the metadata of the unit to `<out-dir>/<unit>.rmeta` (see
+/// `tidec_lir::metadata`). It exits on errors.
+fn write_metadata(lir_ctx: &LirCtx, lir_unit: &LirUnit) {
+    let sess = lir_ctx.sess();
+    let path = sess.out_dir().join(format!(
+        "{}.{}",
+        lir_unit.metadata.unit_name, METADATA_EXTENSION
+    ));
+    debug!(
+        "Writing the metadata of {} to {}",
+        lir_unit.metadata.unit_name,
+        path.display()
+    );
+    let write_metadata = || {
+        std::fs::create_dir_all(sess.out_dir())?;
+        std::fs::write(&path, encode_metadata(lir_ctx, lir_unit))
+    };
+    if let Err(err) = write_metadata() {
+        sess.dcx().fatal(format_args!(
+            "Error writing the metadata {}: {}",
+            path.display(),
+            err
+        ));
+    }
+}
+
+/// Reads the metadata files of `--extern`, with their paths. It exits on errors.
+fn read_extern_files(sess: &Session) -> Vec<(String, String)> {
+    sess.opts
+        .externs
+        .iter()
+        .map(|path| match std::fs::read_to_string(path) {
+            Ok(src) => (path.display().to_string(), src),
+            Err(err) => sess.dcx().fatal(format_args!(
+                "Error reading the metadata {}: {}",
+                path.display(),
+                err
+            )),
+        })
+        .collect()
+}
+
+/// Parses the LIR unit in the given file, named after the file, after the given
+/// metadata files (see `read_extern_files`). The spans of the unit refer to the path
+/// of the file. The errors are reported to the diagnostics handler of the session,
+/// and `None` is returned.
+fn parse_lir_file(
+    lir_ctx: &mut LirCtx,
+    path: &Path,
+    externs: &[(String, String)],
+) -> Option<LirUnit> {
+    let src = match std::fs::read_to_string(path) {
+        Ok(src) => src,
+        Err(err) => {
+            lir_ctx
+                .sess()
+                .dcx()
+                .err(format_args!("Error reading {}: {}", path.display(), err));
+            return None;
+        }
+    };
+    let unit_name = path
+        .file_stem()
+        .map_or("main".into(), |stem| stem.to_string_lossy());
+    let file_name = path.display().to_string();
+    parse_lir_source_file_with_externs(lir_ctx, &unit_name, file_name, src, externs.to_vec())
+        .map_err(|err| {
+            lir_ctx
+                .sess()
+                .dcx()
+                .err(format_args!("Error parsing {}:{}", path.display(), err))
+        })
+        .ok()
+}
+
+/// Returns the target of the given triple, which is a built-in target of
+/// `tidec_abi::targets` or else has the default data layout. It exits on errors.
+fn select_target(dcx: &DiagCtxt, codegen_backend: BackendKind, triple: &str) -> LirTarget {
+    let target_triple = TargetTriple::parse(triple)
+        .unwrap_or_else(|err| dcx.fatal(format_args!("Invalid target triple {}: {}", triple, err)));
+    targets::lookup(&target_triple, codegen_backend).unwrap_or_else(|| {
+        dcx.warn(format_args!(
+            "{} is not a built-in target, using the default data layout",
+            target_triple
+        ));
+        LirTarget {
+            target_triple: Some(target_triple),
+            ..LirTarget::new(codegen_backend)
+        }
+    })
+}
+
+/// Installs a panic hook that reports the panics as internal compiler errors (ICEs),
+/// after the message of the default hook, with the body and the basic block being
+/// codegened by the panicking thread.
+// It corresponds to `install_ice_hook` in rustc_driver_impl/src/lib.rs
+fn install_ice_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        eprintln!();
+        eprintln!("error: internal compiler error: the compiler unexpectedly panicked");
+        if let Some(context) = codegen_context() {
+            match context.basic_block {
+                Some(bb) => eprintln!(
+                    "note: while codegening bb{} of `{}` ({:?})",
+                    bb.idx(),
+                    context.body_name,
+                    context.def_id
+                ),
+                None => eprintln!(
+                    "note: while codegening `{}` ({:?})",
+                    context.body_name, context.def_id
+                ),
+            }
+        }
+        eprintln!("note: please attach the LIR of the failing body to the bug report");
+    }));
+}
+
+/// Writes the LIR of the bodies whose codegen panicked to `tidec-ice-<body>.lir`, in
+/// the current directory.
+fn write_ice_dumps() {
+    for lir_dump in take_lir_dumps() {
+        let path = format!("tidec-ice-{}.lir", lir_dump.body_name);
+        match std::fs::write(&path, &lir_dump.lir) {
+            Ok(()) => eprintln!(
+                "note: the LIR of `{}` ({:?}) was written to {}",
+                lir_dump.body_name, lir_dump.def_id, path
+            ),
+            Err(err) => eprintln!(
+                "note: error writing the LIR of `{}` to {}: {}",
+                lir_dump.body_name, path, err
+            ),
+        }
     }
 }
 
@@ -145,7 +655,7 @@ fn init_tidec_logger() {
 //     // ========= TESTS =========
 //     // =========================
 //
-//     let int_value = LirTy::I8.into_basic_type(codegen.ctx()).size_of().unwrap();
+//     let int_value = codegen.ctx().type_i8().size_of().unwrap();
 //     let align = int_value.get_type().get_alignment();
 //     println!("Size of i8: {}", int_value);
 //     println!("Alignment of i8: {}", align);
@@ -1,4 +1,9 @@
-use crate::layout::TyAndLayout;
+use crate::{
+    calling_convention::{CallConv, x86_64, x86_win64},
+    layout::{TyAbiInterface, TyAndLayout},
+    size_and_align::{Align, Size},
+    target::LirTarget,
+};
 
 /// Describes the full application binary interface (ABI) of a function.
 ///
@@ -16,22 +21,37 @@ use crate::layout::TyAndLayout;
 /// ```ignore
 /// FnAbi {
 ///     args: [
-///         ArgAbi { layout: i32, mode: PassMode::Direct },
-///         ArgAbi { layout: i32, mode: PassMode::Direct },
+///         ArgAbi { layout: i32, mode: PassMode::Direct(attrs) },
+///         ArgAbi { layout: i32, mode: PassMode::Direct(attrs) },
 ///     ],
-///     ret: ArgAbi { layout: i32, mode: PassMode::Direct },
+///     ret: ArgAbi { layout: i32, mode: PassMode::Direct(attrs) },
+///     is_variadic: false,
+///     fixed_count: 2,
+///     conv: CallConv::C,
 /// }
 /// ```
 ///
 /// In contrast, a function returning a large struct `fn foo() -> BigStruct`
 /// may use `PassMode::Indirect` for the return value, indicating that the
 /// caller allocates space and passes a hidden pointer where the result is stored.
-pub struct FnAbi<T> {
+pub struct FnAbi<'a, T> {
     /// The type, layout, and passing convention for each argument.
-    pub args: Box<[ArgAbi<T>]>,
+    pub args: Box<[ArgAbi<'a, T>]>,
 
     /// The type, layout, and passing convention for the return value.
-    pub ret: ArgAbi<T>,
+    pub ret: ArgAbi<'a, T>,
+
+    /// Whether the function is variadic (e.g., `printf`), that is, whether it takes
+    /// more arguments than the fixed ones.
+    pub is_variadic: bool,
+
+    /// The number of the fixed arguments. In the ABI of a call of a variadic function,
+    /// the other `args` are the variadic arguments of the call.
+    pub fixed_count: usize,
+
+    /// The calling convention of the function. The calls of the function must use the
+    /// same calling convention of its declaration.
+    pub conv: CallConv,
 }
 
 /// Describes how a single argument or return value is represented
@@ -39,34 +59,75 @@ pub struct FnAbi<T> {
 ///
 /// Each argument has a memory layout (`TyAndLayout`) and a `PassMode`
 /// describing how it is lowered to machine code.
-pub struct ArgAbi<T> {
+pub struct ArgAbi<'a, T> {
     /// The memory layout of the argument or return value
     /// (size, alignment, and type information).
-    pub layout: TyAndLayout<T>,
+    pub layout: TyAndLayout<'a, T>,
 
     /// The convention for passing this value to/from the backend.
     pub mode: PassMode,
 }
 
-impl<T> ArgAbi<T> {
-    pub fn new(layout: TyAndLayout<T>, mode: PassMode) -> Self {
+impl<'a, T> FnAbi<'a, T> {
+    /// Adjusts the passing of the arguments and of the return value to the C ABI of
+    /// the given target (or of the host, if the target has no triple), so that the
+    /// functions can call and be called by C code. The targets whose C ABI is not
+    /// implemented keep the lowering given by the backend representations.
+    // It corresponds to `adjust_for_foreign_abi` in rustc_target/src/callconv/mod.rs
+    pub fn adjust_for_foreign_abi<C>(&mut self, cx: &'a C, target: &LirTarget)
+    where
+        T: TyAbiInterface<'a, C>,
+    {
+        let Some(target_triple) = target.triple_or_host() else {
+            return;
+        };
+        match (target_triple.arch.as_str(), target_triple.os.as_str()) {
+            ("x86_64", "windows" | "uefi") => x86_win64::compute_abi_info(self),
+            ("x86_64", _) => x86_64::compute_abi_info(cx, self),
+            _ => {}
+        }
+    }
+}
+
+impl<'a, T> ArgAbi<'a, T> {
+    pub fn new(layout: TyAndLayout<'a, T>, mode: PassMode) -> Self {
         ArgAbi { layout, mode }
     }
+
+    /// Returns `true` if the argument is ignored.
+    pub fn is_ignore(&self) -> bool {
+        matches!(self.mode, PassMode::Ignore)
+    }
+
+    /// Passes the argument indirectly, through a pointer to its memory.
+    pub fn make_indirect(&mut self) {
+        let mut attrs = ArgAttributes::new();
+        attrs.set(ArgAttribute::NON_NULL | ArgAttribute::NO_UNDEF);
+        attrs.pointee_size = self.layout.size;
+        attrs.pointee_align = Some(self.layout.align.abi);
+        self.mode = PassMode::Indirect {
+            attrs,
+            on_stack: false,
+        };
+    }
+
+    /// Passes the argument indirectly, through a copy of it on the stack.
+    pub fn make_indirect_byval(&mut self) {
+        self.make_indirect();
+        if let PassMode::Indirect { on_stack, .. } = &mut self.mode {
+            *on_stack = true;
+        }
+    }
+
+    /// Passes the argument as the registers of the given cast target.
+    pub fn cast_to(&mut self, cast: CastTarget) {
+        self.mode = PassMode::Cast(Box::new(cast));
+    }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 /// The possible ways in which an argument or return value
 /// can be passed across the ABI boundary.
-//
-// TODO: pub struct ArgAttributes {
-//     pub regular: ArgAttribute,
-//     pub arg_ext: ArgExtension,
-//     /// The minimum size of the pointee, guaranteed to be valid for the duration of the whole call
-//     /// (corresponding to LLVM's dereferenceable_or_null attributes, i.e., it is okay for this to be
-//     /// set on a null pointer, but all non-null pointers must be dereferenceable).
-//     pub pointee_size: Size,
-//     /// The minimum alignment of the pointee, if any.
-//     pub pointee_align: Option<Align>,
-// }
 pub enum PassMode {
     /// The argument is ignored (e.g., a zero-sized type).
     Ignore,
@@ -76,11 +137,25 @@ pub enum PassMode {
     /// # Example
     /// A parameter of type `i32` is usually passed in a register
     /// as `PassMode::Direct`.
-    // TODO(bruzzone): Consider adding more details to Direct, such as:
-    // - `attrs`: Attributes like `signext`, `zeroext`, etc.
-    Direct,
+    Direct(ArgAttributes),
+    /// The argument is a scalar pair, passed as two separate immediate values.
+    /// As a return value, the two scalars are returned together as an aggregate.
+    ///
+    /// # Example
+    /// A parameter of type `(i32, i64)` is passed as an `i32` and an `i64`.
+    Pair(ArgAttributes, ArgAttributes),
+    /// The argument is passed as a sequence of registers of the given kinds and
+    /// sizes, which are loaded from (or stored into) its memory. It is used for the
+    /// aggregates passed in registers by the C ABIs.
+    ///
+    /// # Example
+    /// On x86_64 SysV, a struct `{ i32, i32, f64 }` is passed as an `i64` and a `f64`.
+    Cast(Box<CastTarget>),
     /// The argument is passed indirectly, via a hidden pointer
-    /// to memory allocated by the caller or callee.
+    /// to memory allocated by the caller or callee. The attributes are the
+    /// ones of the pointer. If `on_stack` is `true`, the argument is copied on the
+    /// stack of the callee (i.e., LLVM's `byval`), instead of being passed as a pointer
+    /// to the memory of the caller.
     ///
     /// # Example
     /// A large struct parameter may be passed by reference instead
@@ -92,8 +167,145 @@ pub enum PassMode {
     /// fn foo(x: BigStruct); // `x` is passed as PassMode::Indirect
     /// ```
     // TODO(bruzzone): Consider adding more details to Indirect, such as:
-    // - `attrs`: Attributes like `noalias`, `readonly`, etc.
     // - `meta_attrs`: Metadata attributes for optimization hints.
-    // - `on_stack`: Whether the argument must be passed on the stack.
-    Indirect,
+    Indirect {
+        attrs: ArgAttributes,
+        on_stack: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// The kind of a register used to pass an argument.
+// It corresponds to `RegKind` in rustc_target/src/callconv/mod.rs
+pub enum RegKind {
+    /// A general-purpose register.
+    Integer,
+    /// A floating-point register.
+    Float,
+    /// A vector register.
+    Vector,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// A register used to pass (a part of) an argument.
+// It corresponds to `Reg` in rustc_target/src/callconv/mod.rs
+pub struct Reg {
+    pub kind: RegKind,
+    /// The number of bytes of the argument held by the register. An integer register
+    /// may hold fewer bytes than its width (e.g., the last 3 bytes of a struct).
+    pub size: Size,
+}
+
+impl Reg {
+    pub fn i64() -> Self {
+        Reg {
+            kind: RegKind::Integer,
+            size: Size::from_bytes(8),
+        }
+    }
+
+    pub fn f64() -> Self {
+        Reg {
+            kind: RegKind::Float,
+            size: Size::from_bytes(8),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// The registers an argument is cast to when it is passed (see `PassMode::Cast`).
+/// The registers hold consecutive parts of the argument, as the fields of a C struct.
+// It corresponds to `CastTarget` in rustc_target/src/callconv/mod.rs
+pub struct CastTarget {
+    pub regs: Vec<Reg>,
+}
+
+impl From<Reg> for CastTarget {
+    fn from(reg: Reg) -> Self {
+        CastTarget { regs: vec![reg] }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+/// A set of the attributes of an argument (or of the return value) which are
+/// plain flags.
+// It corresponds to `ArgAttribute` in rustc_target/src/callconv/mod.rs
+pub struct ArgAttribute(u8);
+
+impl ArgAttribute {
+    /// The pointer is the only way to access the pointee during the call.
+    pub const NO_ALIAS: ArgAttribute = ArgAttribute(1 << 0);
+    /// The pointer is not null.
+    pub const NON_NULL: ArgAttribute = ArgAttribute(1 << 1);
+    /// The pointee is not written through the pointer.
+    pub const READ_ONLY: ArgAttribute = ArgAttribute(1 << 2);
+    /// The value is not undefined (nor poison).
+    pub const NO_UNDEF: ArgAttribute = ArgAttribute(1 << 3);
+
+    /// Returns `true` if all the attributes of `other` are in `self`.
+    pub fn contains(self, other: ArgAttribute) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl std::ops::BitOr for ArgAttribute {
+    type Output = ArgAttribute;
+
+    fn bitor(self, rhs: ArgAttribute) -> ArgAttribute {
+        ArgAttribute(self.0 | rhs.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+/// How an integer smaller than a register is extended when it is passed.
+// It corresponds to `ArgExtension` in rustc_target/src/callconv/mod.rs
+pub enum ArgExtension {
+    /// The value is not extended.
+    #[default]
+    None,
+    /// The value is zero-extended (e.g., an unsigned integer or a boolean).
+    Zext,
+    /// The value is sign-extended.
+    Sext,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+/// The attributes of an argument or of the return value, which tell the backend
+/// more about the passed values (e.g., LLVM's `zeroext` or `nonnull`).
+// It corresponds to `ArgAttributes` in rustc_target/src/callconv/mod.rs
+pub struct ArgAttributes {
+    /// The attributes which are plain flags.
+    pub regular: ArgAttribute,
+    /// The extension of a small integer.
+    pub arg_ext: ArgExtension,
+    /// The minimum size of the pointee, guaranteed to be valid for the duration of the whole call
+    /// (corresponding to LLVM's `dereferenceable` attribute). A zero size means that nothing
+    /// is known about the pointee.
+    pub pointee_size: Size,
+    /// The minimum alignment of the pointee, if any.
+    pub pointee_align: Option<Align>,
+}
+
+impl ArgAttributes {
+    /// Returns the attributes with no attribute set.
+    pub fn new() -> Self {
+        ArgAttributes::default()
+    }
+
+    /// Sets the given extension.
+    pub fn ext(&mut self, arg_ext: ArgExtension) -> &mut Self {
+        self.arg_ext = arg_ext;
+        self
+    }
+
+    /// Sets the given attributes.
+    pub fn set(&mut self, attr: ArgAttribute) -> &mut Self {
+        self.regular = self.regular | attr;
+        self
+    }
+
+    /// Returns `true` if all the given attributes are set.
+    pub fn contains(&self, attr: ArgAttribute) -> bool {
+        self.regular.contains(attr)
+    }
 }
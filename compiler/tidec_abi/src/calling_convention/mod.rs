@@ -1 +1,80 @@
 pub mod function;
+pub mod x86_64;
+pub mod x86_win64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The calling convention of a function.
+///
+/// The calling convention is a low-level detail that specifies how
+/// arguments are passed to a function and how the return value is obtained.
+/// It is important for the backend to know the calling convention in order
+/// to generate the correct code for function calls and returns.
+///
+/// For more details, see the LLVM documentation on calling conventions:
+/// https://llvm.org/docs/LangRef.html#call-conventions
+pub enum CallConv {
+    C = 0,
+    Rust = 1, // Added by Tidec
+    Fast = 8,
+    Cold = 9,
+    GHC = 10,
+    HiPE = 11,
+    AnyReg = 13,
+    PreserveMost = 14,
+    PreserveAll = 15,
+    Swift = 16,
+    CxxFastTls = 17,
+    Tail = 18,
+    CfguardCheck = 19,
+    SwiftTail = 20,
+    PreserveNone = 21,
+    FirstTargetCC = 63, // TODO: In the LLVM documentation it was 64. Overlapping with the X86StdCall
+    X86StdCall = 64,
+    X86FastCall = 65,
+    ArmApcs = 66,
+    ArmAapcs = 67,
+    ArmAapcsVfp = 68,
+    Msp430Intr = 69,
+    X86ThisCall = 70,
+    PtxKernel = 71,
+    PtxDevice = 72,
+    SpirFunc = 75,
+    SpirKernel = 76,
+    IntelOclBi = 77,
+    X86_64SysV = 78,
+    Win64 = 79,
+    X86VectorCall = 80,
+    DummyHhvm = 81,
+    DummyHhvmC = 82,
+    X86Intr = 83,
+    AvrIntr = 84,
+    AvrSignal = 85,
+    AvrBuiltin = 86,
+    AmdgpuVs = 87,
+    AmdgpuGs = 88,
+    AmdgpuPs = 89,
+    AmdgpuCs = 90,
+    AmdgpuKernel = 91,
+    X86RegCall = 92,
+    AmdgpuHs = 93,
+    Msp430Builtin = 94,
+    AmdgpuLs = 95,
+    AmdgpuEs = 96,
+    Aarch64VectorCall = 97,
+    Aarch64SveVectorCall = 98,
+    WasmEmscriptenInvoke = 99,
+    AmdgpuGfx = 100,
+    M68kIntr = 101,
+    Aarch64SmeAbiSupportRoutinesPreserveMostFromX0 = 102,
+    Aarch64SmeAbiSupportRoutinesPreserveMostFromX2 = 103,
+    AmdgpuCsChain = 104,
+    AmdgpuCsChainPreserve = 105,
+    M68kRtd = 106,
+    GRAAL = 107,
+    Arm64ecThunkX64 = 108,
+    Arm64ecThunkNative = 109,
+    RiscvVectorCall = 110,
+    Aarch64SmeAbiSupportRoutinesPreserveMostFromX1 = 111,
+    MaxID = 1023,
+}
@@ -0,0 +1,259 @@
+//! The C ABI of the x86_64 targets other than Windows, i.e., the System V AMD64 ABI.
+//!
+//! Each eightbyte of an argument is classified as `INTEGER` (passed in a general-purpose
+//! register), `SSE` (passed in a vector register, possibly with the next `SSEUP`
+//! eightbytes) or `MEMORY` (the whole argument is passed on the stack). The arguments
+//! bigger than two eightbytes are passed in memory, unless they are a single vector.
+// It corresponds to rustc_target/src/callconv/x86_64.rs
+
+use crate::{
+    calling_convention::function::{ArgAbi, CastTarget, FnAbi, Reg, RegKind},
+    layout::{BackendRepr, TyAbiInterface, TyAndLayout, VariantIdx, Variants},
+    size_and_align::Size,
+};
+use tidec_utils::idx::Idx;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+/// The class of an eightbyte. When two classes are merged, the smallest one wins.
+enum Class {
+    Int,
+    Sse,
+    SseUp,
+}
+
+#[derive(Clone, Copy, Debug)]
+/// The `MEMORY` class, which applies to the whole argument.
+struct Memory;
+
+/// The size of the largest vector register, in bits.
+const LARGEST_VECTOR_SIZE: u64 = 512;
+/// The number of eightbytes of the largest argument which can be passed in registers.
+const MAX_EIGHTBYTES: usize = (LARGEST_VECTOR_SIZE / 64) as usize;
+
+/// The number of general-purpose registers used to pass the arguments.
+const MAX_INT_REGS: usize = 6; // RDI, RSI, RDX, RCX, R8, R9
+/// The number of vector registers used to pass the arguments.
+const MAX_SSE_REGS: usize = 8; // XMM0-7
+
+/// Returns the classes of the eightbytes of the given argument, or `Memory` if it
+/// is passed in memory.
+fn classify_arg<'a, T, C>(
+    cx: &'a C,
+    arg: &ArgAbi<'a, T>,
+) -> Result<[Option<Class>; MAX_EIGHTBYTES], Memory>
+where
+    T: TyAbiInterface<'a, C>,
+{
+    fn classify<'a, T, C>(
+        cx: &'a C,
+        layout: &TyAndLayout<'a, T>,
+        cls: &mut [Option<Class>],
+        off: Size,
+    ) -> Result<(), Memory>
+    where
+        T: TyAbiInterface<'a, C>,
+    {
+        // A misaligned field is passed in memory, unless it is zero-sized.
+        if !off.bytes().is_multiple_of(layout.align.abi.bytes().max(1)) {
+            if !layout.is_zst() {
+                return Err(Memory);
+            }
+            return Ok(());
+        }
+
+        let mut c = match layout.backend_repr {
+            BackendRepr::Scalar(scalar) if scalar.primitive.is_float() => Class::Sse,
+            BackendRepr::Scalar(_) => Class::Int,
+            BackendRepr::ScalarPair(..) | BackendRepr::Memory => {
+                match &layout.variants {
+                    Variants::Single { .. } => {
+                        for (field_off, field) in layout.fields(cx) {
+                            let off = Size::from_bytes(off.bytes() + field_off.bytes());
+                            classify(cx, &field, cls, off)?;
+                        }
+                    }
+                    // The variants of an enum are treated as the members of a union.
+                    Variants::Multiple { variants, .. } => {
+                        for variant_idx in 0..variants.len() {
+                            let variant = layout.for_variant(VariantIdx::new(variant_idx));
+                            classify(cx, &variant, cls, off)?;
+                        }
+                    }
+                }
+                return Ok(());
+            }
+        };
+
+        // A scalar bigger than an eightbyte (e.g., an `i128` or an `f128`) spans more
+        // eightbytes, where the ones after the first `SSE` are `SSEUP`.
+        let first = (off.bytes() / 8) as usize;
+        let last = ((off.bytes() + layout.size.bytes() - 1) / 8) as usize;
+        for cls in &mut cls[first..=last] {
+            *cls = Some(cls.map_or(c, |old| old.min(c)));
+            if c == Class::Sse {
+                c = Class::SseUp;
+            }
+        }
+        Ok(())
+    }
+
+    let n = arg.layout.size.bytes().div_ceil(8) as usize;
+    if n > MAX_EIGHTBYTES {
+        return Err(Memory);
+    }
+
+    let mut cls = [None; MAX_EIGHTBYTES];
+    classify(cx, &arg.layout, &mut cls, Size::from_bytes(0))?;
+    if n > 2 {
+        // Only a vector can be passed in registers if it is bigger than two eightbytes.
+        if cls[0] != Some(Class::Sse) || cls[1..n].iter().any(|&c| c != Some(Class::SseUp)) {
+            return Err(Memory);
+        }
+    } else {
+        // An `SSEUP` which does not follow an `SSE` (or another `SSEUP`) is an `SSE`.
+        let mut i = 0;
+        while i < n {
+            if cls[i] == Some(Class::SseUp) {
+                cls[i] = Some(Class::Sse);
+            } else if cls[i] == Some(Class::Sse) {
+                i += 1;
+                while i != n && cls[i] == Some(Class::SseUp) {
+                    i += 1;
+                }
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    Ok(cls)
+}
+
+/// Returns the register of the eightbytes starting from `cls[*i]`, of which `size`
+/// bytes are left in the argument, and moves `i` past them.
+fn reg_component(cls: &[Option<Class>], i: &mut usize, size: Size) -> Option<Reg> {
+    if *i >= cls.len() {
+        return None;
+    }
+
+    match cls[*i] {
+        None => None,
+        Some(Class::Int) => {
+            *i += 1;
+            Some(if size.bytes() < 8 {
+                Reg {
+                    kind: RegKind::Integer,
+                    size,
+                }
+            } else {
+                Reg::i64()
+            })
+        }
+        Some(Class::Sse) => {
+            let vec_len = 1 + cls[*i + 1..]
+                .iter()
+                .take_while(|&&c| c == Some(Class::SseUp))
+                .count();
+            *i += vec_len;
+            Some(if vec_len == 1 {
+                match size.bytes() {
+                    2 | 4 => Reg {
+                        kind: RegKind::Float,
+                        size,
+                    },
+                    _ => Reg::f64(),
+                }
+            } else {
+                Reg {
+                    kind: RegKind::Vector,
+                    size: Size::from_bytes(8 * vec_len as u64),
+                }
+            })
+        }
+        Some(c) => unreachable!("reg_component: unhandled class {:?}", c),
+    }
+}
+
+/// Returns the registers of an argument of the given size and classes.
+fn cast_target(cls: &[Option<Class>], size: Size) -> CastTarget {
+    let mut i = 0;
+    let lo = reg_component(cls, &mut i, size).unwrap();
+    let offset = Size::from_bytes(8 * i as u64);
+    let mut target = CastTarget::from(lo);
+    if size.bytes() > offset.bytes() {
+        let rest = Size::from_bytes(size.bytes() - offset.bytes());
+        if let Some(hi) = reg_component(cls, &mut i, rest) {
+            target.regs.push(hi);
+        }
+    }
+    assert_eq!(reg_component(cls, &mut i, Size::from_bytes(0)), None);
+    target
+}
+
+/// Adjusts the given function ABI to the System V AMD64 ABI.
+///
+/// The aggregates passed in registers are cast to their registers, while the ones
+/// classified as `MEMORY` are passed on the stack (`byval`) or, as a return value,
+/// through a hidden pointer (`sret`). An aggregate which does not fit in the
+/// registers left by the previous arguments is passed on the stack as well.
+pub fn compute_abi_info<'a, T, C>(cx: &'a C, fn_abi: &mut FnAbi<'a, T>)
+where
+    T: TyAbiInterface<'a, C>,
+{
+    let mut int_regs = MAX_INT_REGS;
+    let mut sse_regs = MAX_SSE_REGS;
+
+    let mut x86_64_arg_or_ret = |arg: &mut ArgAbi<'a, T>, is_arg: bool| {
+        let mut cls_or_mem = classify_arg(cx, arg);
+
+        if is_arg && let Ok(cls) = cls_or_mem {
+            let needed_int = cls.iter().filter(|&&c| c == Some(Class::Int)).count();
+            let needed_sse = cls.iter().filter(|&&c| c == Some(Class::Sse)).count();
+            match (
+                int_regs.checked_sub(needed_int),
+                sse_regs.checked_sub(needed_sse),
+            ) {
+                (Some(left_int), Some(left_sse)) => {
+                    int_regs = left_int;
+                    sse_regs = left_sse;
+                }
+                // Not enough registers for this argument, so it is passed on the
+                // stack. Only the aggregates are marked as such, as the backend puts
+                // the scalars on the stack by itself.
+                _ => {
+                    if arg.layout.is_aggregate() {
+                        cls_or_mem = Err(Memory);
+                    }
+                }
+            }
+        }
+
+        match cls_or_mem {
+            Err(Memory) => {
+                if is_arg {
+                    arg.make_indirect_byval();
+                } else {
+                    // The hidden pointer to the return value takes an integer register.
+                    arg.make_indirect();
+                    int_regs -= 1;
+                }
+            }
+            Ok(ref cls) => {
+                if arg.layout.is_aggregate() {
+                    let size = arg.layout.size;
+                    arg.cast_to(cast_target(cls, size));
+                }
+            }
+        }
+    };
+
+    if !fn_abi.ret.is_ignore() {
+        x86_64_arg_or_ret(&mut fn_abi.ret, false);
+    }
+    for arg in fn_abi.args.iter_mut() {
+        if arg.is_ignore() {
+            continue;
+        }
+        x86_64_arg_or_ret(arg, true);
+    }
+}
@@ -0,0 +1,57 @@
+//! The C ABI of the x86_64 Windows targets, i.e., the Microsoft x64 calling convention.
+//!
+//! Each argument takes one of the four register slots (RCX, RDX, R8 and R9, or
+//! XMM0-3 for the floats) by its position, and the following ones are passed on the
+//! stack, above the 32 bytes of shadow space reserved by the caller for the slots.
+//! Hence, an argument never spans more than one slot: the aggregates of 1, 2, 4 or 8
+//! bytes are passed as an integer of the same size, while the other ones are passed
+//! by reference to a copy made by the caller (which the callee may write).
+// It corresponds to rustc_target/src/callconv/x86_win64.rs
+
+use crate::{
+    calling_convention::function::{ArgAbi, CastTarget, FnAbi, Reg, RegKind},
+    layout::BackendRepr,
+    size_and_align::Size,
+};
+
+/// Adjusts the given function ABI to the Microsoft x64 calling convention.
+///
+/// The assignment of the slots and the shadow space are left to the backend, which
+/// also passes the pointer to an indirect return value in the first slot.
+pub fn compute_abi_info<T>(fn_abi: &mut FnAbi<'_, T>) {
+    let fixup = |arg: &mut ArgAbi<'_, T>, is_ret: bool| match arg.layout.backend_repr {
+        BackendRepr::ScalarPair(..) | BackendRepr::Memory => match arg.layout.size.bytes() {
+            1 | 2 | 4 | 8 => {
+                let size = arg.layout.size;
+                arg.cast_to(CastTarget::from(Reg {
+                    kind: RegKind::Integer,
+                    size,
+                }));
+            }
+            _ => arg.make_indirect(),
+        },
+        // The scalars bigger than a slot (e.g., an `i128`) are passed by reference,
+        // while they are returned in XMM0.
+        BackendRepr::Scalar(scalar) if arg.layout.size.bytes() > 8 => {
+            if !is_ret {
+                arg.make_indirect();
+            } else if !scalar.primitive.is_float() {
+                arg.cast_to(CastTarget::from(Reg {
+                    kind: RegKind::Vector,
+                    size: Size::from_bytes(16),
+                }));
+            }
+        }
+        BackendRepr::Scalar(_) => {}
+    };
+
+    if !fn_abi.ret.is_ignore() {
+        fixup(&mut fn_abi.ret, true);
+    }
+    for arg in fn_abi.args.iter_mut() {
+        if arg.is_ignore() {
+            continue;
+        }
+        fixup(arg, false);
+    }
+}
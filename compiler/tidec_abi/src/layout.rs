@@ -1,3 +1,8 @@
+use std::ops::RangeInclusive;
+
+use tidec_utils::{idx::Idx, newtype_index};
+use tracing::debug;
+
 use crate::{
     size_and_align::{AbiAndPrefAlign, Size},
     target::AddressSpace,
@@ -8,29 +13,70 @@ use crate::{
 ///
 /// This is commonly used during codegen and layout computation to reason about
 /// how values should be represented in memory on the target platform.
-pub struct TyAndLayout<T> {
+pub struct TyAndLayout<'a, T> {
     /// The type this layout refers to.
     ///
     /// This is usually a LIR type, but can be any type that has a size and alignment.
     pub ty: T,
     /// The layout information for the type, including size and alignment.
-    pub layout: Layout,
+    /// It is interned by the type context (e.g., `LirCtx::intern_layout`), so it
+    /// is shared by all the types with the same layout.
+    pub layout: &'a Layout,
 }
 
-impl<T> std::ops::Deref for TyAndLayout<T> {
+impl<'a, T: Clone> TyAndLayout<'a, T> {
+    /// Returns the type and layout of the given variant of this type.
+    ///
+    /// The type is unchanged, while the layout is the one of the variant. This is used,
+    /// for instance, to access the fields of an enum variant after a downcast.
+    pub fn for_variant(&self, variant_idx: VariantIdx) -> Self {
+        let layout = match &self.layout.variants {
+            Variants::Single { index } => {
+                assert_eq!(*index, variant_idx, "Downcast to a variant of another type");
+                self.layout
+            }
+            Variants::Multiple { variants, .. } => &variants[variant_idx.idx()],
+        };
+        TyAndLayout {
+            ty: self.ty.clone(),
+            layout,
+        }
+    }
+}
+
+impl<'a, T> TyAndLayout<'a, T> {
+    /// Returns the fields of this type and layout, with their offset.
+    pub fn fields<C>(&self, cx: &'a C) -> Vec<(Size, TyAndLayout<'a, T>)>
+    where
+        T: TyAbiInterface<'a, C>,
+    {
+        T::fields(self, cx)
+    }
+}
+
+/// The interface used by the ABI computations to inspect the fields of a type,
+/// which are not described by its `Layout`.
+// It corresponds to `TyAbiInterface` in rustc_abi/src/layout/ty.rs
+pub trait TyAbiInterface<'a, C>: Sized + Clone {
+    /// Returns the fields of the given type and layout, with their offset. The layout
+    /// of an enum has no fields, while the one of its variants (see `for_variant`) has
+    /// the fields of the variant, including the tag (if it is not a niche).
+    fn fields(this: &TyAndLayout<'a, Self>, cx: &'a C) -> Vec<(Size, TyAndLayout<'a, Self>)>;
+}
+
+impl<T> std::ops::Deref for TyAndLayout<'_, T> {
     type Target = Layout;
 
     fn deref(&self) -> &Self::Target {
-        &self.layout
+        self.layout
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 /// Represents the layout of a type in the target architecture.
 ///
 /// This struct contains the size, alignment, and backend representation
 /// of a type, which is essential for code generation and memory layout decisions.
-// TODO(bruzzone): Add fields and variants (tag union, struct, etc.).
 pub struct Layout {
     /// The size of the type in bytes.
     pub size: Size,
@@ -45,30 +91,552 @@ pub struct Layout {
     /// Therefore, even when `backend_repr` is not `Memory`, you must still consider
     /// `fields` and `variants` to fully understand and access all parts of the layout.
     pub backend_repr: BackendRepr,
+    /// The offsets of the fields of the type.
+    pub fields: FieldsShape,
+    /// The variants of the type. Every type has a single variant, except enums.
+    pub variants: Variants,
+    /// The niche with the largest number of invalid values, if any. It is used to
+    /// encode the discriminant of an enum without a separate tag.
+    pub largest_niche: Option<Niche>,
 }
 
 impl Layout {
+    /// Returns the layout of a scalar of the given size and alignment. The invalid
+    /// values of the scalar, if any, are its niche.
+    pub fn scalar(scalar: Scalar, size: Size, align: AbiAndPrefAlign) -> Self {
+        let largest_niche = (!scalar.is_always_valid(size)).then(|| Niche {
+            offset: Size::from_bytes(0),
+            primitive: scalar.primitive,
+            size,
+            valid_range: scalar.valid_range,
+        });
+        Layout {
+            size,
+            align,
+            backend_repr: BackendRepr::Scalar(scalar),
+            fields: FieldsShape::Primitive,
+            variants: Variants::Single {
+                index: VariantIdx::new(0),
+            },
+            largest_niche,
+        }
+    }
+
+    /// Returns the layout of an aggregate with the given fields.
+    ///
+    /// Each field is placed at the first offset that satisfies its alignment, and the
+    /// size of the aggregate is rounded up to its alignment, which is the largest
+    /// alignment of its fields. Unless `repr` inhibits it, the fields are placed by
+    /// decreasing alignment, which leaves no padding between them; otherwise, they are
+    /// laid out in declaration order, as in C.
+    // It corresponds to `univariant` in rustc_abi/src/layout.rs
+    pub fn univariant(fields: &[Layout], repr: ReprOptions) -> Self {
+        Layout::univariant_with_prefix(fields, repr, 0)
+    }
+
+    /// Returns the layout of an aggregate with the given fields, where the first
+    /// `prefix` fields are never reordered (e.g., the tag of an enum variant).
+    fn univariant_with_prefix(fields: &[Layout], repr: ReprOptions, prefix: usize) -> Self {
+        // The fields in the order in which they are placed in memory.
+        let mut inverse_memory_index = (0..fields.len()).collect::<Vec<_>>();
+        if !repr.inhibit_struct_field_reordering() {
+            // The sorts are stable, so the fields with the same alignment keep their order.
+            let optimizing = &mut inverse_memory_index[prefix..];
+            if prefix == 0 {
+                optimizing.sort_by_key(|&i| std::cmp::Reverse(fields[i].align.abi));
+            } else {
+                // After a prefix, the least aligned fields come first, so that they
+                // fill the padding which follows the prefix.
+                optimizing.sort_by_key(|&i| fields[i].align.abi);
+            }
+        }
+
+        let mut offset = Size::from_bytes(0);
+        let mut offsets = vec![Size::from_bytes(0); fields.len()];
+        let mut memory_index = vec![0; fields.len()];
+        let mut align = AbiAndPrefAlign::new(1, 1);
+        let mut largest_niche: Option<Niche> = None;
+        let mut non_zst_fields = Vec::new();
+
+        for (position, &i) in inverse_memory_index.iter().enumerate() {
+            let field = &fields[i];
+            align.abi = align.abi.max(field.align.abi);
+            align.pref = align.pref.max(field.align.pref);
+            offset = offset.align_to(field.align.abi);
+            debug!("Field {} at offset {:?}", i, offset);
+            // Keep the niche of the field with the most invalid values.
+            if let Some(niche) = &field.largest_niche
+                && largest_niche
+                    .as_ref()
+                    .is_none_or(|largest| niche.available() > largest.available())
+            {
+                largest_niche = Some(Niche {
+                    offset: Size::from_bytes(offset.bytes() + niche.offset.bytes()),
+                    ..niche.clone()
+                });
+            }
+            if !field.is_zst() {
+                non_zst_fields.push((offset, field));
+            }
+            offsets[i] = offset;
+            memory_index[i] = position as u32;
+            offset = Size::from_bytes(offset.bytes() + field.size.bytes());
+        }
+        let size = offset.align_to(align.abi);
+
+        // An aggregate of exactly two scalars is a scalar pair, as long as it is laid
+        // out as the pair itself (i.e., without extra padding). The scalars of the pair
+        // are in memory order.
+        let backend_repr = match non_zst_fields[..] {
+            [(a_offset, a), (b_offset, b)] => match (a.backend_repr, b.backend_repr) {
+                (BackendRepr::Scalar(a_scalar), BackendRepr::Scalar(b_scalar))
+                    if a_offset.bytes() == 0
+                        && b_offset == a.size.align_to(b.align.abi)
+                        && size
+                            == Size::from_bytes(b_offset.bytes() + b.size.bytes())
+                                .align_to(align.abi) =>
+                {
+                    BackendRepr::ScalarPair(a_scalar, b_scalar)
+                }
+                _ => BackendRepr::Memory,
+            },
+            _ => BackendRepr::Memory,
+        };
+
+        Layout {
+            size,
+            align,
+            backend_repr,
+            fields: FieldsShape::Arbitrary {
+                offsets,
+                memory_index,
+            },
+            variants: Variants::Single {
+                index: VariantIdx::new(0),
+            },
+            largest_niche,
+        }
+    }
+
+    /// Returns the layout of a tagged union (e.g., an enum) with the given variants,
+    /// where each variant is given by the layouts of its fields.
+    ///
+    /// The tag is stored at offset 0, and the fields of each variant are laid out
+    /// after it (as the first field of the variant). The size of the union is the size of its largest variant. The values
+    /// of the tag that do not correspond to any variant are a niche.
+    pub fn tagged_union(tag: Primitive, tag_layout: &Layout, variants: &[Vec<Layout>]) -> Self {
+        let mut variant_layouts = variants
+            .iter()
+            .enumerate()
+            .map(|(i, fields)| {
+                let fields = std::iter::once(tag_layout.clone())
+                    .chain(fields.iter().cloned())
+                    .collect::<Vec<_>>();
+                let mut layout = Layout::univariant_with_prefix(&fields, ReprOptions::default(), 1);
+                layout.variants = Variants::Single {
+                    index: VariantIdx::new(i),
+                };
+                layout
+            })
+            .collect::<Vec<_>>();
+
+        let mut size = tag_layout.size;
+        let mut align = tag_layout.align;
+        for layout in &variant_layouts {
+            size = Size::from_bytes(size.bytes().max(layout.size.bytes()));
+            align.abi = align.abi.max(layout.align.abi);
+            align.pref = align.pref.max(layout.align.pref);
+        }
+        let size = size.align_to(align.abi);
+
+        // All the variants occupy the whole union, which lives in memory.
+        for layout in &mut variant_layouts {
+            layout.size = size;
+            layout.align = align;
+            layout.backend_repr = BackendRepr::Memory;
+        }
+
+        let largest_niche = Niche {
+            offset: Size::from_bytes(0),
+            primitive: tag,
+            size: tag_layout.size,
+            valid_range: WrappingRange {
+                start: 0,
+                end: variants.len().saturating_sub(1) as u128,
+            },
+        };
+
+        Layout {
+            size,
+            align,
+            backend_repr: BackendRepr::Memory,
+            fields: FieldsShape::Primitive,
+            variants: Variants::Multiple {
+                tag,
+                tag_offset: Size::from_bytes(0),
+                tag_encoding: TagEncoding::Direct,
+                variants: variant_layouts,
+            },
+            largest_niche: Some(largest_niche),
+        }
+    }
+
+    /// Returns the layout of an enum with the given variants that stores its discriminant
+    /// in a niche of its only non-zero-sized variant (the untagged variant), if possible.
+    ///
+    /// For instance, the variants of an `Option<bool>`-like enum can be encoded as the
+    /// invalid values of the boolean: `Some(false) = 0`, `Some(true) = 1` and `None = 2`.
+    // It corresponds to `layout_of_enum` (the niche-filling part) in rustc_abi/src/layout.rs
+    pub fn niche_filling(variants: &[Vec<Layout>]) -> Option<Self> {
+        if variants.len() < 2 {
+            return None;
+        }
+
+        // Only one variant can hold data; all the others must be zero-sized.
+        let mut untagged_variant = None;
+        for (i, fields) in variants.iter().enumerate() {
+            if fields.iter().all(|field| field.is_zst()) {
+                continue;
+            }
+            if untagged_variant.is_some() {
+                return None;
+            }
+            untagged_variant = Some(i);
+        }
+        let untagged_variant = untagged_variant?;
+
+        // The variants encoded in the niche are all but the untagged one. The range may
+        // include the untagged variant, whose niche value is then just never used.
+        let first = (0..variants.len()).find(|&i| i != untagged_variant)?;
+        let last = (0..variants.len()).rfind(|&i| i != untagged_variant)?;
+        let count = (last - first + 1) as u128;
+
+        let untagged_layout =
+            Layout::univariant(&variants[untagged_variant], ReprOptions::default());
+        let niche = untagged_layout.largest_niche.clone()?;
+        let (niche_start, largest_niche) = niche.reserve(count)?;
+        debug!(
+            "Niche-encoding {} variants at offset {:?} starting from {}",
+            count, niche.offset, niche_start
+        );
+
+        let mut variant_layouts = variants
+            .iter()
+            .enumerate()
+            .map(|(i, fields)| {
+                let mut layout = Layout::univariant(fields, ReprOptions::default());
+                layout.variants = Variants::Single {
+                    index: VariantIdx::new(i),
+                };
+                layout
+            })
+            .collect::<Vec<_>>();
+
+        let mut align = untagged_layout.align;
+        for layout in &variant_layouts {
+            align.abi = align.abi.max(layout.align.abi);
+            align.pref = align.pref.max(layout.align.pref);
+        }
+        let size = untagged_layout.size.align_to(align.abi);
+
+        // All the variants occupy the whole enum, which lives in memory.
+        for layout in &mut variant_layouts {
+            layout.size = size;
+            layout.align = align;
+            layout.backend_repr = BackendRepr::Memory;
+        }
+
+        Some(Layout {
+            size,
+            align,
+            backend_repr: BackendRepr::Memory,
+            fields: FieldsShape::Primitive,
+            variants: Variants::Multiple {
+                tag: niche.primitive,
+                tag_offset: niche.offset,
+                tag_encoding: TagEncoding::Niche {
+                    untagged_variant: VariantIdx::new(untagged_variant),
+                    niche_variants: VariantIdx::new(first)..=VariantIdx::new(last),
+                    niche_start,
+                },
+                variants: variant_layouts,
+            },
+            largest_niche: Some(largest_niche),
+        })
+    }
+
+    /// Returns `true` if the variants of the layout start with a separate tag field,
+    /// that is, if the layout is an enum with a `Direct` tag encoding.
+    pub fn has_tag_field(&self) -> bool {
+        matches!(
+            self.variants,
+            Variants::Multiple {
+                tag_encoding: TagEncoding::Direct,
+                ..
+            }
+        )
+    }
+
+    /// Returns the offset of the `i`-th field, in declaration order.
+    pub fn field_offset(&self, i: usize) -> Size {
+        self.fields.offset(i)
+    }
+
     /// Returns true if the layout represents a zero-sized type.
     pub fn is_zst(&self) -> bool {
         match self.backend_repr {
-            BackendRepr::Scalar(_) /* | BackendRepr::ScalarPair(_, _) */ => false,
+            BackendRepr::Scalar(_) | BackendRepr::ScalarPair(_, _) => false,
             BackendRepr::Memory => self.size.bytes() == 0,
         }
     }
 
     pub fn is_immediate(&self) -> bool {
         match self.backend_repr {
-            BackendRepr::Scalar(_)  => true,
-            BackendRepr::Memory /* | BackendRepr::ScalarPair(_, _) */ => false,
+            BackendRepr::Scalar(_) => true,
+            BackendRepr::Memory | BackendRepr::ScalarPair(_, _) => false,
         }
     }
 
+    pub fn is_scalar_pair(&self) -> bool {
+        matches!(self.backend_repr, BackendRepr::ScalarPair(_, _))
+    }
+
     pub fn is_memory(&self) -> bool {
         matches!(self.backend_repr, BackendRepr::Memory)
     }
+
+    /// Returns `true` if the layout is not a single scalar, i.e., if it is passed as
+    /// an aggregate by the C ABIs.
+    pub fn is_aggregate(&self) -> bool {
+        !self.is_immediate()
+    }
+}
+
+newtype_index! {
+    /// The index of a variant of an enum.
+    pub struct VariantIdx {}
+}
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Describes how the fields of a type are laid out.
+// It corresponds to `FieldsShape` in rustc_abi/src/lib.rs
+pub enum FieldsShape {
+    /// The type has no fields, e.g., a scalar. The fields of an enum with multiple
+    /// variants are the ones of its variants (see `TyAndLayout::for_variant`).
+    Primitive,
+    /// The fields are the elements of an array, which are `stride` bytes apart.
+    Array { stride: Size, count: u64 },
+    /// The fields are at arbitrary offsets, e.g., the ones of a struct or a tuple.
+    Arbitrary {
+        /// The offset of each field, in declaration order.
+        offsets: Vec<Size>,
+        /// The position in memory of each field (in declaration order). That is,
+        /// `memory_index[i] < memory_index[j]` if the field `i` comes before `j`.
+        memory_index: Vec<u32>,
+    },
+}
+
+impl FieldsShape {
+    /// Returns the number of fields.
+    pub fn count(&self) -> usize {
+        match self {
+            FieldsShape::Primitive => 0,
+            FieldsShape::Array { count, .. } => *count as usize,
+            FieldsShape::Arbitrary { offsets, .. } => offsets.len(),
+        }
+    }
+
+    /// Returns the offset of the `i`-th field, in declaration order.
+    pub fn offset(&self, i: usize) -> Size {
+        match self {
+            FieldsShape::Primitive => panic!("A primitive layout has no field {}", i),
+            FieldsShape::Array { stride, count } => {
+                assert!((i as u64) < *count, "Element {} out of {}", i, count);
+                Size::from_bytes(stride.bytes() * i as u64)
+            }
+            FieldsShape::Arbitrary { offsets, .. } => offsets[i],
+        }
+    }
+
+    /// Returns the indices of the fields in memory order, i.e., by increasing offset.
+    pub fn index_by_increasing_offset(&self) -> Vec<usize> {
+        match self {
+            FieldsShape::Primitive => vec![],
+            FieldsShape::Array { count, .. } => (0..*count as usize).collect(),
+            FieldsShape::Arbitrary { memory_index, .. } => {
+                let mut inverse_memory_index = vec![0; memory_index.len()];
+                for (i, &position) in memory_index.iter().enumerate() {
+                    inverse_memory_index[position as usize] = i;
+                }
+                inverse_memory_index
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The options of the representation of an aggregate, given by its `#[repr]`.
+// It corresponds to `ReprOptions` in rustc_abi/src/lib.rs
+pub struct ReprOptions {
+    /// The fields are laid out in declaration order, as in C (`#[repr(C)]`).
+    pub c: bool,
+}
+
+impl ReprOptions {
+    /// Returns `true` if the fields must be laid out in declaration order.
+    pub fn inhibit_struct_field_reordering(&self) -> bool {
+        self.c
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Describes the variants of a type.
+pub enum Variants {
+    /// A type with a single variant (e.g., a struct, a tuple or a scalar), or a
+    /// specific variant of an enum (e.g., after a downcast).
+    Single { index: VariantIdx },
+    /// An enum with multiple variants, discriminated by a tag.
+    Multiple {
+        /// The primitive type of the tag.
+        tag: Primitive,
+        /// The offset of the tag from the start of the enum.
+        tag_offset: Size,
+        /// How the discriminant is encoded in the tag.
+        tag_encoding: TagEncoding,
+        /// The layouts of the variants. With a `Direct` encoding, they include the tag.
+        variants: Vec<Layout>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Describes how the discriminant of an enum is encoded in its tag.
+// It corresponds to `TagEncoding` in rustc_abi/src/lib.rs
+pub enum TagEncoding {
+    /// The tag is a separate field, and its value is the variant index.
+    Direct,
+    /// The tag is a niche of the untagged variant. The variants in `niche_variants`
+    /// are encoded as `niche_start + (variant_index - niche_variants.start())`, while any
+    /// other value of the tag means that the enum is `untagged_variant`.
+    Niche {
+        untagged_variant: VariantIdx,
+        niche_variants: RangeInclusive<VariantIdx>,
+        niche_start: u128,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// An inclusive range of valid values of a scalar, which may wrap around.
+///
+/// For instance, a boolean has the valid range `0..=1`, while a range `start > end`
+/// means that the valid values are `start..=MAX` and `0..=end`.
+// It corresponds to `WrappingRange` in rustc_abi/src/lib.rs
+pub struct WrappingRange {
+    pub start: u128,
+    pub end: u128,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+impl WrappingRange {
+    /// Returns the range of all the values of a scalar of the given size.
+    pub fn full(size: Size) -> Self {
+        WrappingRange {
+            start: 0,
+            end: u128::MAX >> (128 - size.bits()),
+        }
+    }
+
+    /// Returns `true` if the range contains all the values of a scalar of the given size.
+    pub fn is_full_for(&self, size: Size) -> bool {
+        let max = u128::MAX >> (128 - size.bits());
+        self.start == self.end.wrapping_add(1) & max
+    }
+
+    /// Returns `true` if the given value is in the range.
+    pub fn contains(&self, value: u128) -> bool {
+        if self.start <= self.end {
+            self.start <= value && value <= self.end
+        } else {
+            self.start <= value || value <= self.end
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// A scalar value, with the range of its valid values.
+///
+/// For instance, a boolean is a `u8` whose valid values are `0..=1`, while a function
+/// pointer is never null. Producing an invalid value is undefined behavior, so the
+/// backend can assume that the loaded values are in the range.
+// It corresponds to `Scalar` in rustc_abi/src/lib.rs
+pub struct Scalar {
+    /// The primitive type of the scalar.
+    pub primitive: Primitive,
+    /// The valid values of the scalar.
+    pub valid_range: WrappingRange,
+}
+
+impl Scalar {
+    /// Returns a scalar of the given size whose values are all valid.
+    pub fn full(primitive: Primitive, size: Size) -> Self {
+        Scalar {
+            primitive,
+            valid_range: WrappingRange::full(size),
+        }
+    }
+
+    /// Returns `true` if all the values of the scalar, of the given size, are valid.
+    pub fn is_always_valid(&self, size: Size) -> bool {
+        self.valid_range.is_full_for(size)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// A scalar within a type whose invalid values can be used to store other
+/// information, such as the discriminant of an enum.
+// It corresponds to `Niche` in rustc_abi/src/lib.rs
+pub struct Niche {
+    /// The offset of the scalar from the start of the type.
+    pub offset: Size,
+    /// The primitive type of the scalar.
+    pub primitive: Primitive,
+    /// The size of the scalar.
+    pub size: Size,
+    /// The valid values of the scalar.
+    pub valid_range: WrappingRange,
+}
+
+impl Niche {
+    /// Returns the number of invalid values of the scalar.
+    pub fn available(&self) -> u128 {
+        let max = self.max_value();
+        let WrappingRange { start, end } = self.valid_range;
+        start.wrapping_sub(end).wrapping_sub(1) & max
+    }
+
+    /// Reserves `count` invalid values of the scalar, right after the end of its
+    /// valid range. Returns the first reserved value and the niche that remains.
+    pub fn reserve(&self, count: u128) -> Option<(u128, Niche)> {
+        if count == 0 || count > self.available() {
+            return None;
+        }
+        let max = self.max_value();
+        let niche_start = self.valid_range.end.wrapping_add(1) & max;
+        let niche = Niche {
+            valid_range: WrappingRange {
+                start: self.valid_range.start,
+                end: self.valid_range.end.wrapping_add(count) & max,
+            },
+            ..self.clone()
+        };
+        Some((niche_start, niche))
+    }
+
+    /// Returns the largest value that fits in the scalar.
+    fn max_value(&self) -> u128 {
+        u128::MAX >> (128 - self.size.bytes() * 8)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 /// Represents how values are passed to the backend during code generation.
 ///
 /// This is *not* the same as the platform's ABI.
@@ -84,34 +652,36 @@ impl Layout {
 /// actual calling convention — that is determined separately by the ABI implementation.
 pub enum BackendRepr {
     /// The value is represented as a scalar, such as an integer or float.
-    Scalar(Primitive),
+    Scalar(Scalar),
     /// The value is represented as a memory reference, such as a pointer or
     /// a reference to a struct or array.
     Memory,
-    // Scalar pair, which is a pair of scalars. It is often used for
-    // returning multiple values from a function. This allows the backend to
-    // optimize the representation of multiple return values. Additionally,
-    // it is used for "fat pointers", which are pointers that include extra
-    // metadata, such as a pointer to a slice or a trait object. For example,
-    // a slice `&str` is represented as a pair of a pointer to the data
-    // and a length.
-    // ScalarPair(Primitive, Primitive),
+    /// Scalar pair, which is a pair of scalars. It is often used for
+    /// returning multiple values from a function. This allows the backend to
+    /// optimize the representation of multiple return values. Additionally,
+    /// it is used for "fat pointers", which are pointers that include extra
+    /// metadata, such as a pointer to a slice or a trait object. For example,
+    /// a slice `&str` is represented as a pair of a pointer to the data
+    /// and a length.
+    ScalarPair(Scalar, Scalar),
 }
 
 impl BackendRepr {
     /// Converts the `BackendRepr` to its corresponding `Primitive` type if it is a scalar.
     pub fn to_primitive(&self) -> Primitive {
         match self {
-            BackendRepr::Scalar(p) => *p,
-            // BackendRepr::ScalarPair(p1, p2) => Some((*p1, *p2)),
-            BackendRepr::Memory => {
-                panic!("Memory backend representation does not have a primitive type")
+            BackendRepr::Scalar(scalar) => scalar.primitive,
+            BackendRepr::ScalarPair(_, _) | BackendRepr::Memory => {
+                panic!(
+                    "{:?} backend representation does not have a primitive type",
+                    self
+                )
             }
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 /// Represents primitive types that can be used in the backend representation.
 pub enum Primitive {
     /// A signed integer type.
@@ -134,3 +704,13 @@ pub enum Primitive {
     /// A pointer type.
     Pointer(AddressSpace),
 }
+
+impl Primitive {
+    /// Returns `true` if the primitive is a floating-point type.
+    pub fn is_float(&self) -> bool {
+        matches!(
+            self,
+            Primitive::F16 | Primitive::F32 | Primitive::F64 | Primitive::F128
+        )
+    }
+}
@@ -2,3 +2,4 @@ pub mod calling_convention;
 pub mod layout;
 pub mod size_and_align;
 pub mod target;
+pub mod targets;
@@ -1,4 +1,4 @@
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 /// Specifies both the ABI-required and preferred alignment for a type, in bytes.
 ///
 /// Both `abi` and `pref` are powers of two. The ABI alignment (`abi`) is the minimum
@@ -29,7 +29,8 @@ impl AbiAndPrefAlign {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Size of a type in bytes.
 pub struct Size(u64);
 
@@ -42,13 +43,30 @@ impl Size {
         Size(bits / 8 + (bits % 8).div_ceil(8))
     }
 
+    /// Creates a `Size` from a byte count.
+    pub fn from_bytes(bytes: impl TryInto<u64>) -> Size {
+        Size(bytes.try_into().ok().unwrap())
+    }
+
     /// Returns the size in bytes.
     pub fn bytes(&self) -> u64 {
         self.0
     }
+
+    /// Returns the size in bits.
+    pub fn bits(&self) -> u64 {
+        self.0 * 8
+    }
+
+    /// Rounds the size up to the next multiple of `align`.
+    pub fn align_to(self, align: Align) -> Size {
+        let mask = align.bytes().max(1) - 1;
+        Size((self.0 + mask) & !mask)
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Alignment of a type in bytes (always a power of two).
 pub struct Align(u64);
 
@@ -59,6 +77,9 @@ pub enum AlignError {
 }
 
 impl Align {
+    /// The alignment of a byte.
+    pub const ONE: Align = Align(1);
+
     #[inline]
     pub fn from_bits(bits: u64) -> Result<Align, AlignError> {
         Align::from_bytes(Size::from_bits(bits).bytes())
@@ -101,4 +122,13 @@ impl Align {
     pub const fn bytes(&self) -> u64 {
         self.0
     }
+
+    /// Returns the alignment of an address that is `offset` bytes after an address
+    /// aligned to `self`. That is, the largest power of two dividing both.
+    pub fn restrict_for_offset(self, offset: Size) -> Align {
+        match offset.bytes() {
+            0 => self,
+            offset => Align(self.0.min(1 << offset.trailing_zeros())),
+        }
+    }
 }
@@ -1,8 +1,10 @@
+use std::{fmt, str::FromStr};
+
 use tracing::{info, instrument};
 
 use crate::size_and_align::{AbiAndPrefAlign, Size};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 /// Describes the target configuration used during code generation.
 ///
 /// This struct encapsulates information about the backend, data layout,
@@ -23,14 +25,29 @@ pub struct LirTarget {
 }
 
 impl LirTarget {
+    /// Creates a target without triple (i.e., the default target of the backend).
+    /// Its data layout is the one of the host, if it is a built-in target (see
+    /// `crate::targets`), or else the default one.
     pub fn new(codegen_backend: BackendKind) -> Self {
+        let data_layout = match crate::targets::host(codegen_backend) {
+            Some(host) => host.data_layout,
+            None => TargetDataLayout::new(),
+        };
         LirTarget {
-            data_layout: TargetDataLayout::new(),
+            data_layout,
             codegen_backend,
             target_triple: None,
         }
     }
 
+    /// Returns the target triple, or the one of the host if it is not set (as the
+    /// backends default to the host). It is `None` if the host is not a built-in target.
+    pub fn triple_or_host(&self) -> Option<TargetTriple> {
+        self.target_triple.clone().or_else(|| {
+            crate::targets::host(self.codegen_backend).and_then(|host| host.target_triple)
+        })
+    }
+
     // TODO: make it better. Perhaps by using a specific TargetDataLayout for each
     // compiler backend.
     pub fn data_layout_string(&self) -> String {
@@ -38,6 +55,9 @@ impl LirTarget {
             BackendKind::Llvm => self.data_layout.as_llvm_datalayout_string(),
             BackendKind::Cranelift => self.data_layout.as_cranelift_datalayout_string(),
             BackendKind::Gcc => self.data_layout.as_gcc_datalayout_string(),
+            // The interpreter reads the data layout directly, so the LLVM string is
+            // only used to describe it.
+            BackendKind::Interp => self.data_layout.as_llvm_datalayout_string(),
         }
     }
 
@@ -65,11 +85,17 @@ impl LirTarget {
                     .unwrap()
                     .into_gcc_triple_string(),
             ),
+            BackendKind::Interp => Some(
+                self.target_triple
+                    .as_ref()
+                    .unwrap()
+                    .into_llvm_triple_string(),
+            ),
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 /// The backend kind for code generation.
 ///
 /// This enum represents the different backends that can be used for code generation.
@@ -82,9 +108,54 @@ pub enum BackendKind {
 
     /// The GCC (GNU Compiler Collection) backend.
     Gcc,
+
+    /// The LIR interpreter (`tidec_interp`), which runs the program instead of
+    /// generating code for it.
+    Interp,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// The kind of the file produced by linking the objects of the units.
+// It corresponds to `LinkOutputKind` in rustc_target/src/spec/mod.rs
+pub enum LinkOutputKind {
+    /// An executable (e.g., `main` or `main.exe`).
+    Executable,
+    /// A shared library (e.g., `libmain.so`, `libmain.dylib` or `main.dll`).
+    SharedLibrary,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// The relocation model of the generated code, that is, how it addresses its code and
+/// its data.
+// It corresponds to `RelocModel` in rustc_target/src/spec/mod.rs
+pub enum RelocModel {
+    /// The code and the data are at addresses fixed at link time (e.g., in a non-PIE
+    /// executable).
+    Static,
+    /// Position-independent code, which can be loaded at any address (e.g., in a
+    /// shared library).
+    Pic,
+    /// Position-independent code of an executable, whose symbols cannot be
+    /// interposed.
+    Pie,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// The code model of the generated code, which bounds the size of its code and data.
+// It corresponds to `CodeModel` in rustc_target/src/spec/mod.rs
+pub enum CodeModel {
+    /// The code and the data are in the lower 2 GB of the address space.
+    Small,
+    /// The code and the data are in the upper 2 GB of the address space, as in the
+    /// kernels.
+    Kernel,
+    /// The code is in the lower 2 GB of the address space, but the data can be anywhere.
+    Medium,
+    /// The code and the data can be anywhere.
+    Large,
+}
+
+#[derive(Debug, Clone)]
 /// Describes the target platform's data layout, including type alignments, pointer size,
 /// and other ABI-related information used during code generation.
 ///
@@ -108,12 +179,16 @@ pub struct TargetDataLayout {
     pub f64_align: AbiAndPrefAlign,
     pub f128_align: AbiAndPrefAlign,
 
-    /// The size of pointers in bytes.
+    /// The size of pointers in bits, in the default address space.
     pub pointer_size: u64,
 
-    /// The ABI and preferred alignment for pointers.
+    /// The ABI and preferred alignment for pointers, in the default address space.
     pub pointer_align: AbiAndPrefAlign,
 
+    /// The pointers of the other address spaces whose size or alignment differ
+    /// from the ones of the default address space.
+    pub address_space_pointers: Vec<(AddressSpace, PointerSpec)>,
+
     /// The minimum and preferred alignment for aggregate types (e.g., structs, arrays).
     pub aggregate_align: AbiAndPrefAlign,
 
@@ -142,6 +217,7 @@ impl Default for TargetDataLayout {
             f128_align: AbiAndPrefAlign::new(128, 128),
             pointer_size: 64,
             pointer_align: AbiAndPrefAlign::new(64, 64),
+            address_space_pointers: vec![],
             aggregate_align: AbiAndPrefAlign::new(0, 64),
             vector_align: vec![
                 (Size::from_bits(64), AbiAndPrefAlign::new(64, 64)),
@@ -160,6 +236,29 @@ impl TargetDataLayout {
         target_data_layout
     }
 
+    /// Returns the size in bits of the pointers in the given address space.
+    pub fn pointer_size_in(&self, addr_space: AddressSpace) -> u64 {
+        self.pointer_spec_in(addr_space).size
+    }
+
+    /// Returns the alignment of the pointers in the given address space.
+    pub fn pointer_align_in(&self, addr_space: AddressSpace) -> AbiAndPrefAlign {
+        self.pointer_spec_in(addr_space).align
+    }
+
+    fn pointer_spec_in(&self, addr_space: AddressSpace) -> PointerSpec {
+        self.address_space_pointers
+            .iter()
+            .find(|(space, _)| *space == addr_space)
+            .map_or(
+                PointerSpec {
+                    size: self.pointer_size,
+                    align: self.pointer_align,
+                },
+                |(_, pointer_spec)| *pointer_spec,
+            )
+    }
+
     /// For example, for x86_64-unknown-linux-gnu, the data layout string could be:
     /// `e-m:e-p270:32:32-p271:32:32-p272:64:64-i64:64-i128:128-f80:128-n8:16:32:64-S128`
     pub fn as_llvm_datalayout_string(&self) -> String {
@@ -183,6 +282,15 @@ impl TargetDataLayout {
             self.pointer_align.abi.bytes(),
             self.pointer_align.pref.bytes()
         ));
+        for (addr_space, pointer_spec) in &self.address_space_pointers {
+            s.push_str(&format!(
+                "-p{}:{}:{}:{}",
+                addr_space.0,
+                pointer_spec.size,
+                pointer_spec.align.abi.bytes(),
+                pointer_spec.align.pref.bytes()
+            ));
+        }
 
         // Format for integer types
         s.push_str(&format_align("i1", &self.i1_align));
@@ -205,7 +313,7 @@ impl TargetDataLayout {
         for (size, align) in &self.vector_align {
             s.push_str(&format!(
                 "-v{}:{}:{}",
-                size.bytes(),
+                size.bits(),
                 align.abi.bytes(),
                 align.pref.bytes()
             ));
@@ -327,7 +435,7 @@ impl TargetDataLayout {
     // }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 /// The endianness of the target architecture.
 pub enum Endianess {
     /// Little-endian.
@@ -337,7 +445,7 @@ pub enum Endianess {
     Big,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 /// Represents a target triple, which uniquely identifies a compilation target.
 ///
 /// A target triple is a string that encodes information about the target architecture,
@@ -373,12 +481,31 @@ impl TargetTriple {
         }
     }
 
+    /// Parses a target triple string, such as `"x86_64-unknown-linux-gnu"`.
+    ///
+    /// See the `FromStr` implementation for the accepted forms.
+    pub fn parse(triple: &str) -> Result<Self, TargetTripleError> {
+        triple.parse()
+    }
+
     // ARCHITECTURE-VENDOR-OPERATING_SYSTEM-ENVIRONMENT
+    // The trailing empty components (e.g., the environment of `aarch64-apple-darwin`)
+    // are omitted.
     pub fn into_llvm_triple_string(&self) -> String {
-        format!(
-            "{}-{}-{}-{}-{}",
-            self.arch, self.vendor, self.os, self.env, self.abi
-        )
+        let mut components = vec![
+            self.arch.as_str(),
+            self.vendor.as_str(),
+            self.os.as_str(),
+            self.env.as_str(),
+            self.abi.as_str(),
+        ];
+        while components
+            .last()
+            .is_some_and(|component| component.is_empty())
+        {
+            components.pop();
+        }
+        components.join("-")
     }
 
     pub fn into_cranelift_triple_string(&self) -> String {
@@ -390,17 +517,142 @@ impl TargetTriple {
     }
 }
 
-// TODO: Other address spaces.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub enum AddressSpace {
+/// The architectures accepted as the first component of a target triple.
+const KNOWN_ARCHS: &[&str] = &[
+    "x86_64",
+    "i386",
+    "i586",
+    "i686",
+    "aarch64",
+    "arm",
+    "armv7",
+    "riscv32",
+    "riscv64",
+    "wasm32",
+    "wasm64",
+    "powerpc",
+    "powerpc64",
+    "powerpc64le",
+    "mips",
+    "mips64",
+    "s390x",
+];
+
+/// The operating systems recognized in the second component of a triple without
+/// vendor, such as `x86_64-linux-gnu`.
+const KNOWN_OSES: &[&str] = &[
+    "linux", "windows", "darwin", "macos", "ios", "freebsd", "none",
+];
+
+impl FromStr for TargetTriple {
+    type Err = TargetTripleError;
+
+    /// Parses a target triple made of 3, 4 or 5 components separated by `-`:
+    /// - `arch-vendor-os` (e.g., `aarch64-apple-darwin`), or `arch-os-env` if the
+    ///   second component is a known operating system (e.g., `x86_64-linux-gnu`,
+    ///   whose vendor is `unknown`);
+    /// - `arch-vendor-os-env` (e.g., `x86_64-unknown-linux-gnu`);
+    /// - `arch-vendor-os-env-abi`.
+    ///
+    /// The missing components are empty.
+    // It corresponds to the constructor of `Triple` in llvm/lib/TargetParser/Triple.cpp
+    fn from_str(triple: &str) -> Result<Self, Self::Err> {
+        let components: Vec<&str> = triple.split('-').collect();
+        if let Some(idx) = components.iter().position(|component| component.is_empty()) {
+            return Err(TargetTripleError::EmptyComponent(idx));
+        }
+        if let Some(component) = components.iter().find(|component| {
+            !component
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+        }) {
+            return Err(TargetTripleError::InvalidComponent(component.to_string()));
+        }
+
+        let triple = match components.as_slice() {
+            [arch, os, env] if KNOWN_OSES.contains(os) => {
+                TargetTriple::new(arch, "unknown", os, env, "")
+            }
+            [arch, vendor, os] => TargetTriple::new(arch, vendor, os, "", ""),
+            [arch, vendor, os, env] => TargetTriple::new(arch, vendor, os, env, ""),
+            [arch, vendor, os, env, abi] => TargetTriple::new(arch, vendor, os, env, abi),
+            _ => return Err(TargetTripleError::WrongComponentCount(components.len())),
+        };
+        if !KNOWN_ARCHS.contains(&triple.arch.as_str()) {
+            return Err(TargetTripleError::UnknownArch(triple.arch));
+        }
+        Ok(triple)
+    }
+}
+
+impl fmt::Display for TargetTriple {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.into_llvm_triple_string())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// An error raised while parsing a target triple.
+pub enum TargetTripleError {
+    /// The triple does not have 3, 4 or 5 components.
+    WrongComponentCount(usize),
+    /// The component at the given index is empty (e.g., `x86_64--linux`).
+    EmptyComponent(usize),
+    /// The component contains a character other than an ASCII letter, a digit,
+    /// `_` or `.`.
+    InvalidComponent(String),
+    /// The architecture is not supported.
+    UnknownArch(String),
+}
+
+impl fmt::Display for TargetTripleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TargetTripleError::WrongComponentCount(count) => write!(
+                f,
+                "expected a target triple of 3 to 5 components, found {} components",
+                count
+            ),
+            TargetTripleError::EmptyComponent(idx) => {
+                write!(f, "the component {} of the target triple is empty", idx)
+            }
+            TargetTripleError::InvalidComponent(component) => {
+                write!(f, "invalid target triple component `{}`", component)
+            }
+            TargetTripleError::UnknownArch(arch) => {
+                write!(f, "unknown target architecture `{}`", arch)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TargetTripleError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// An address space, as numbered by the target.
+///
+/// The meaning of the non-zero address spaces depends on the target, e.g., the
+/// global, shared and constant memories of the GPUs, or the program memory of the
+/// Harvard architectures.
+pub struct AddressSpace(pub u32);
+
+impl AddressSpace {
     /// The default address space.
-    DATA = 0,
+    pub const DATA: AddressSpace = AddressSpace(0);
 }
 
 impl From<&AddressSpace> for u32 {
     fn from(addr_space: &AddressSpace) -> Self {
-        match *addr_space {
-            AddressSpace::DATA => 0,
-        }
+        addr_space.0
     }
 }
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// The size and alignment of the pointers in an address space.
+pub struct PointerSpec {
+    /// The size of the pointers in bits.
+    pub size: u64,
+    /// The ABI and preferred alignment of the pointers.
+    pub align: AbiAndPrefAlign,
+}
@@ -0,0 +1,171 @@
+//! The built-in target specifications.
+//!
+//! Each function returns the `LirTarget` of a common target, with its triple and
+//! the data layout that LLVM uses for it. As in `TargetDataLayout::default`, the
+//! alignments and the pointer size are given in bits, as in the LLVM data layout
+//! strings.
+// It corresponds to the target specifications in rustc_target/src/spec/targets
+
+use crate::{
+    size_and_align::{AbiAndPrefAlign, Size},
+    target::{
+        AddressSpace, BackendKind, Endianess, LirTarget, PointerSpec, TargetDataLayout,
+        TargetTriple,
+    },
+};
+
+/// A function which returns a built-in target for the given backend.
+pub type TargetFn = fn(BackendKind) -> LirTarget;
+
+/// The built-in targets, by their (normalized) triple.
+pub const TARGETS: &[(&str, TargetFn)] = &[
+    ("x86_64-unknown-linux-gnu", x86_64_unknown_linux_gnu),
+    ("x86_64-pc-windows-msvc", x86_64_pc_windows_msvc),
+    ("aarch64-apple-darwin", aarch64_apple_darwin),
+    ("aarch64-unknown-linux-gnu", aarch64_unknown_linux_gnu),
+    ("wasm32-unknown-unknown", wasm32_unknown_unknown),
+];
+
+/// Returns the built-in target with the given triple, if any.
+pub fn lookup(target_triple: &TargetTriple, codegen_backend: BackendKind) -> Option<LirTarget> {
+    let triple = target_triple.into_llvm_triple_string();
+    TARGETS
+        .iter()
+        .find(|(name, _)| *name == triple)
+        .map(|(_, target)| target(codegen_backend))
+}
+
+/// Returns the built-in target of the host, if any.
+pub fn host(codegen_backend: BackendKind) -> Option<LirTarget> {
+    let target = if cfg!(all(target_arch = "x86_64", target_os = "linux")) {
+        x86_64_unknown_linux_gnu
+    } else if cfg!(all(target_arch = "x86_64", target_os = "windows")) {
+        x86_64_pc_windows_msvc
+    } else if cfg!(all(target_arch = "aarch64", target_os = "macos")) {
+        aarch64_apple_darwin
+    } else if cfg!(all(target_arch = "aarch64", target_os = "linux")) {
+        aarch64_unknown_linux_gnu
+    } else {
+        return None;
+    };
+    Some(target(codegen_backend))
+}
+
+/// The data layout of LLVM is `e-m:e-p270:32:32-p271:32:32-p272:64:64-i64:64-i128:128-f80:128-n8:16:32:64-S128`.
+pub fn x86_64_unknown_linux_gnu(codegen_backend: BackendKind) -> LirTarget {
+    target(
+        codegen_backend,
+        TargetTriple::new("x86_64", "unknown", "linux", "gnu", ""),
+        data_layout_x86_64(),
+    )
+}
+
+/// The data layout of LLVM is `e-m:w-p270:32:32-p271:32:32-p272:64:64-i64:64-i128:128-f80:128-n8:16:32:64-S128`.
+pub fn x86_64_pc_windows_msvc(codegen_backend: BackendKind) -> LirTarget {
+    target(
+        codegen_backend,
+        TargetTriple::new("x86_64", "pc", "windows", "msvc", ""),
+        data_layout_x86_64(),
+    )
+}
+
+/// The data layout of LLVM is `e-m:o-i64:64-i128:128-n32:64-S128-Fn32`.
+pub fn aarch64_apple_darwin(codegen_backend: BackendKind) -> LirTarget {
+    target(
+        codegen_backend,
+        TargetTriple::new("aarch64", "apple", "darwin", "", ""),
+        data_layout_64(),
+    )
+}
+
+/// The data layout of LLVM is `e-m:e-i8:8:32-i16:16:32-i64:64-i128:128-n32:64-S128-Fn32`.
+pub fn aarch64_unknown_linux_gnu(codegen_backend: BackendKind) -> LirTarget {
+    target(
+        codegen_backend,
+        TargetTriple::new("aarch64", "unknown", "linux", "gnu", ""),
+        TargetDataLayout {
+            i8_align: AbiAndPrefAlign::new(8, 32),
+            i16_align: AbiAndPrefAlign::new(16, 32),
+            ..data_layout_64()
+        },
+    )
+}
+
+/// The data layout of LLVM is `e-m:e-p:32:32-p10:8:8-p20:8:8-i64:64-i128:128-n32:64-S128-ni:1:10:20`.
+pub fn wasm32_unknown_unknown(codegen_backend: BackendKind) -> LirTarget {
+    target(
+        codegen_backend,
+        TargetTriple::new("wasm32", "unknown", "unknown", "", ""),
+        TargetDataLayout {
+            pointer_size: 32,
+            pointer_align: AbiAndPrefAlign::new(32, 32),
+            // The address spaces of the `externref` and `funcref` references.
+            address_space_pointers: vec![
+                (AddressSpace(10), pointer_spec(8, 8)),
+                (AddressSpace(20), pointer_spec(8, 8)),
+            ],
+            ..data_layout_64()
+        },
+    )
+}
+
+fn target(
+    codegen_backend: BackendKind,
+    target_triple: TargetTriple,
+    data_layout: TargetDataLayout,
+) -> LirTarget {
+    LirTarget {
+        codegen_backend,
+        data_layout,
+        target_triple: Some(target_triple),
+    }
+}
+
+/// The data layout of the x86_64 targets, with the address spaces of the 32-bit
+/// signed (`__ptr32_sptr`), 32-bit unsigned (`__ptr32_uptr`) and 64-bit (`__ptr64`)
+/// pointers.
+fn data_layout_x86_64() -> TargetDataLayout {
+    TargetDataLayout {
+        address_space_pointers: vec![
+            (AddressSpace(270), pointer_spec(32, 32)),
+            (AddressSpace(271), pointer_spec(32, 32)),
+            (AddressSpace(272), pointer_spec(64, 64)),
+        ],
+        ..data_layout_64()
+    }
+}
+
+fn pointer_spec(size: u64, align: u64) -> PointerSpec {
+    PointerSpec {
+        size,
+        align: AbiAndPrefAlign::new(align, align),
+    }
+}
+
+/// The data layout shared by the little-endian 64-bit targets, i.e., the default
+/// LLVM data layout with 64-bit pointers and naturally aligned 64- and 128-bit
+/// integers.
+fn data_layout_64() -> TargetDataLayout {
+    TargetDataLayout {
+        endianess: Endianess::Little,
+        i1_align: AbiAndPrefAlign::new(8, 8),
+        i8_align: AbiAndPrefAlign::new(8, 8),
+        i16_align: AbiAndPrefAlign::new(16, 16),
+        i32_align: AbiAndPrefAlign::new(32, 32),
+        i64_align: AbiAndPrefAlign::new(64, 64),
+        i128_align: AbiAndPrefAlign::new(128, 128),
+        f16_align: AbiAndPrefAlign::new(16, 16),
+        f32_align: AbiAndPrefAlign::new(32, 32),
+        f64_align: AbiAndPrefAlign::new(64, 64),
+        f128_align: AbiAndPrefAlign::new(128, 128),
+        pointer_size: 64,
+        pointer_align: AbiAndPrefAlign::new(64, 64),
+        address_space_pointers: vec![],
+        aggregate_align: AbiAndPrefAlign::new(0, 64),
+        vector_align: vec![
+            (Size::from_bits(64), AbiAndPrefAlign::new(64, 64)),
+            (Size::from_bits(128), AbiAndPrefAlign::new(128, 128)),
+        ],
+        instruction_address_space: AddressSpace::DATA,
+    }
+}
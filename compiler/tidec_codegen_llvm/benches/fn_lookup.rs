@@ -0,0 +1,89 @@
+//! Benchmarks of the lookups of the functions by `DefId` on a unit full of calls.
+//!
+//! Run them with `cargo bench -p tidec_codegen_llvm`. The `codegen` benchmark codegens
+//! a unit of `FNS` functions, each calling every other one, so that most of its time
+//! is spent looking up the callees (see `CodegenMethods::get_fn_by_def_id`), while the
+//! `lookup` one only looks up the functions of the codegened unit.
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use std::sync::Arc;
+
+use inkwell::context::Context;
+use tidec_abi::target::{BackendKind, LirTarget};
+use tidec_codegen_llvm::{builder::CodegenBuilder, context::CodegenCtx};
+use tidec_codegen_ssa::traits::CodegenMethods;
+use tidec_lir::{
+    lir::{DefId, LirCtx, LirUnit},
+    parse::parse_lir_unit,
+};
+use tidec_session::{config::Options, diagnostics::DiagCtxt, Session};
+
+/// The number of functions of the benchmarked unit.
+const FNS: usize = 200;
+
+/// Parses a unit of `FNS` functions, where the function `fI` calls all the others,
+/// and returns its context and the unit.
+fn parse_unit() -> (LirCtx, LirUnit) {
+    let mut src = String::new();
+    for i in 0..FNS {
+        src.push_str(&format!("fn f{}(_1: i32) -> i32 {{\n", i));
+        for j in 0..FNS {
+            src.push_str(&format!("    bb{}: {{\n", j));
+            src.push_str(&format!("        _0 = f{}(copy _1) -> bb{};\n", j, j + 1));
+            src.push_str("    }\n");
+        }
+        src.push_str(&format!("    bb{}: {{\n        return;\n    }}\n}}\n", FNS));
+    }
+
+    let sess = Session::new(
+        LirTarget::new(BackendKind::Llvm),
+        Options::default(),
+        DiagCtxt::new(),
+    );
+    let mut lir_ctx = LirCtx::new(Arc::new(sess));
+    let lir_unit = parse_lir_unit(&mut lir_ctx, "bench", &src).expect("Invalid benchmark unit");
+    (lir_ctx, lir_unit)
+}
+
+fn bench_fn_lookup(c: &mut Criterion) {
+    let mut group = c.benchmark_group(format!("fn_lookup_{}_fns", FNS));
+    group.sample_size(10);
+
+    group.bench_function("codegen", |b| {
+        b.iter_batched(
+            parse_unit,
+            |(mut lir_ctx, lir_unit)| {
+                let ll_context = Context::create();
+                let unit_metadata = lir_ctx.define_unit(lir_unit);
+                let ll_module = ll_context.create_module(&unit_metadata.unit_name);
+                let ctx = CodegenCtx::new(lir_ctx, &ll_context, ll_module);
+                ctx.compile_lir_unit::<CodegenBuilder>()
+                    .expect("The codegen failed");
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    let (mut lir_ctx, lir_unit) = parse_unit();
+    let ll_context = Context::create();
+    let unit_metadata = lir_ctx.define_unit(lir_unit);
+    let ll_module = ll_context.create_module(&unit_metadata.unit_name);
+    let ctx = CodegenCtx::new(lir_ctx, &ll_context, ll_module);
+    ctx.compile_lir_unit::<CodegenBuilder>()
+        .expect("The codegen failed");
+    let def_ids = (0..FNS)
+        .map(|i| ctx.lir_ctx.def_id_of(&format!("f{}", i)).unwrap())
+        .collect::<Vec<DefId>>();
+    group.bench_function("lookup", |b| {
+        b.iter(|| {
+            for def_id in &def_ids {
+                black_box(ctx.get_fn_by_def_id(*def_id));
+            }
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_fn_lookup);
+criterion_main!(benches);
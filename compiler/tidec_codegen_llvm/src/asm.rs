@@ -0,0 +1,195 @@
+//! The inline assembly of the LLVM modules (see `AsmBuilderMethods`).
+//!
+//! An inline assembly is lowered to a call to an LLVM `asm` value, whose constraint
+//! string lists the outputs, then the inputs and then the clobbers. The outputs are
+//! returned by the call, as a struct if there are many of them.
+// It corresponds to rustc_codegen_llvm/src/asm.rs
+
+use inkwell::attributes::{Attribute, AttributeLoc};
+use inkwell::types::BasicMetadataTypeEnum;
+use inkwell::values::BasicMetadataValueEnum;
+use inkwell::InlineAsmDialect;
+use tidec_codegen_ssa::error::CodegenResult;
+use tidec_codegen_ssa::traits::{
+    AsmBuilderMethods, BuilderMethods, InlineAsmOperandRef, TypeMethods,
+};
+use tidec_lir::syntax::{InlineAsmOptions, InlineAsmRegOrRegClass};
+
+use crate::builder::{build_failed, CodegenBuilder};
+
+impl<'a, 'll> AsmBuilderMethods<'a, 'll> for CodegenBuilder<'a, 'll> {
+    fn codegen_inline_asm(
+        &mut self,
+        template: &str,
+        operands: &[InlineAsmOperandRef<Self::Value, Self::Type>],
+        clobbers: &[String],
+        options: InlineAsmOptions,
+    ) -> CodegenResult<Vec<Self::Value>> {
+        let is_x86 = self
+            .lir_ctx
+            .target()
+            .triple_or_host()
+            .is_some_and(|triple| matches!(triple.arch.as_str(), "x86" | "i686" | "x86_64"));
+
+        // The LLVM operands are the outputs, followed by the inputs. An input tied
+        // to an output (i.e., of an `InOut`) refers to it by its index.
+        let mut llvm_idxs = vec![None; operands.len()];
+        let mut output_constraints = vec![];
+        let mut output_tys = vec![];
+        for (idx, operand) in operands.iter().enumerate() {
+            match operand {
+                InlineAsmOperandRef::Out { reg, ty: Some(ty) } => {
+                    // The output is early-clobbered, as it may be written before the
+                    // inputs are read.
+                    llvm_idxs[idx] = Some(output_tys.len());
+                    output_constraints.push(format!("=&{}", reg_constraint(reg)));
+                    output_tys.push(*ty);
+                }
+                InlineAsmOperandRef::InOut { reg, value } => {
+                    llvm_idxs[idx] = Some(output_tys.len());
+                    output_constraints.push(format!("={}", reg_constraint(reg)));
+                    output_tys.push(value.get_type());
+                }
+                InlineAsmOperandRef::Out { ty: None, .. } | InlineAsmOperandRef::In { .. } => {}
+            }
+        }
+        let mut input_constraints = vec![];
+        let mut inputs = vec![];
+        let mut clobber_constraints = vec![];
+        for (idx, operand) in operands.iter().enumerate() {
+            match operand {
+                InlineAsmOperandRef::In { reg, value } => {
+                    llvm_idxs[idx] = Some(output_tys.len() + inputs.len());
+                    input_constraints.push(reg_constraint(reg));
+                    inputs.push(*value);
+                }
+                InlineAsmOperandRef::InOut { value, .. } => {
+                    let output_idx = llvm_idxs[idx].expect("The output of an inout is numbered");
+                    input_constraints.push(output_idx.to_string());
+                    inputs.push(*value);
+                }
+                InlineAsmOperandRef::Out { reg, ty: None } => match reg {
+                    InlineAsmRegOrRegClass::Reg(reg) => {
+                        clobber_constraints.push(format!("~{{{}}}", reg))
+                    }
+                    InlineAsmRegOrRegClass::RegClass(class) => {
+                        panic!("A discarded output in a register of class `{}`", class)
+                    }
+                },
+                InlineAsmOperandRef::Out { ty: Some(_), .. } => {}
+            }
+        }
+        clobber_constraints.extend(clobbers.iter().map(|reg| format!("~{{{}}}", reg)));
+        if !options.contains(InlineAsmOptions::NOMEM) {
+            clobber_constraints.push("~{memory}".to_string());
+        }
+        if is_x86 && !options.contains(InlineAsmOptions::PRESERVES_FLAGS) {
+            // TODO(bruzzone): clobber the flags of the other architectures.
+            clobber_constraints.extend(["~{dirflag}", "~{fpsr}", "~{flags}"].map(String::from));
+        }
+        let constraints = [output_constraints, input_constraints, clobber_constraints]
+            .concat()
+            .join(",");
+
+        let ll_context = self.ll_context;
+        let ret_ty = match output_tys.as_slice() {
+            [] => None,
+            [ty] => Some(*ty),
+            tys => Some(self.ctx().type_struct(tys, false)),
+        };
+        let param_tys = inputs
+            .iter()
+            .map(|input| BasicMetadataTypeEnum::from(input.get_type()))
+            .collect::<Vec<_>>();
+        let fn_ty = self.declare_fn(ret_ty, &param_tys, false);
+        let dialect = if is_x86 && !options.contains(InlineAsmOptions::ATT_SYNTAX) {
+            InlineAsmDialect::Intel
+        } else {
+            InlineAsmDialect::ATT
+        };
+        let asm = ll_context.create_inline_asm(
+            fn_ty,
+            llvm_template(template, &llvm_idxs),
+            constraints,
+            !options.contains(InlineAsmOptions::PURE),
+            !options.contains(InlineAsmOptions::NOSTACK),
+            Some(dialect),
+            false,
+        );
+
+        let args = inputs
+            .iter()
+            .map(|input| BasicMetadataValueEnum::from(*input))
+            .collect::<Vec<_>>();
+        let call_site = self
+            .ll_builder
+            .build_indirect_call(fn_ty, asm, &args, "")
+            .map_err(build_failed("call"))?;
+        let nounwind = Attribute::get_named_enum_kind_id("nounwind");
+        call_site.add_attribute(
+            AttributeLoc::Function,
+            ll_context.create_enum_attribute(nounwind, 0),
+        );
+        // TODO(bruzzone): add the memory effects of the pure assemblies (i.e.,
+        // `memory(none)` with `nomem` and `memory(read)` with `readonly`).
+
+        let Some(ret) = call_site.try_as_basic_value().left() else {
+            return Ok(vec![]);
+        };
+        match output_tys.len() {
+            1 => Ok(vec![ret]),
+            outputs => (0..outputs as u64)
+                .map(|idx| self.build_extract_value(ret, idx))
+                .collect(),
+        }
+    }
+}
+
+/// Returns the LLVM constraint code of the given register, e.g., `r` for a general
+/// purpose register or `{eax}` for an explicit one.
+fn reg_constraint(reg: &InlineAsmRegOrRegClass) -> String {
+    let class = match reg {
+        InlineAsmRegOrRegClass::Reg(reg) => return format!("{{{}}}", reg),
+        InlineAsmRegOrRegClass::RegClass(class) => class.as_str(),
+    };
+    let code = match class {
+        "reg" => "r",
+        // x86
+        "reg_abcd" => "Q",
+        "xmm_reg" | "ymm_reg" => "x",
+        "zmm_reg" => "v",
+        // AArch64
+        "vreg" => "w",
+        // RISC-V
+        "freg" => "f",
+        _ => panic!("Unknown register class `{}`", class),
+    };
+    code.to_string()
+}
+
+/// Translates the given template, whose operands are `{N}`, into the one of LLVM,
+/// whose operands are `${M}`, where `M` is `llvm_idxs[N]`. The `$` are escaped.
+fn llvm_template(template: &str, llvm_idxs: &[Option<usize>]) -> String {
+    let mut llvm_template = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '$' => llvm_template.push_str("$$"),
+            '{' if chars.next_if_eq(&'{').is_some() => llvm_template.push('{'),
+            '}' if chars.next_if_eq(&'}').is_some() => llvm_template.push('}'),
+            '{' => {
+                let idx: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                let llvm_idx = idx
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|idx| *llvm_idxs.get(idx)?)
+                    .unwrap_or_else(|| {
+                        panic!("The asm operand `{{{}}}` is missing or discarded", idx)
+                    });
+                llvm_template.push_str(&format!("${{{}}}", llvm_idx));
+            }
+            c => llvm_template.push(c),
+        }
+    }
+    llvm_template
+}
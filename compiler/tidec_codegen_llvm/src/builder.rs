@@ -1,16 +1,32 @@
+use std::fmt::Display;
 use std::ops::Deref;
 
-use inkwell::values::{BasicValue, BasicValueEnum, FunctionValue};
+use inkwell::types::{BasicType, BasicTypeEnum, StructType};
+use inkwell::values::{
+    BasicMetadataValueEnum, BasicValue, BasicValueEnum, CallSiteValue, FunctionValue,
+    InstructionValue, PhiValue,
+};
 use inkwell::{basic_block::BasicBlock, builder::Builder};
-use tidec_abi::layout::{BackendRepr, Primitive, TyAndLayout};
+use tidec_abi::calling_convention::function::{FnAbi, PassMode};
+use tidec_abi::layout::{BackendRepr, Primitive, Scalar};
 use tidec_abi::size_and_align::{Align, Size};
-use tidec_codegen_ssa::lir::{OperandRef, PlaceRef};
-use tidec_codegen_ssa::traits::{BuilderMethods, CodegenBackendTypes};
-use tidec_lir::syntax::{ConstScalar, LirTy};
+use tidec_codegen_ssa::common::IntPredicate;
+use tidec_codegen_ssa::error::{CodegenError, CodegenResult};
+use tidec_codegen_ssa::lir::{self, OperandRef, OperandVal, PlaceRef};
+use tidec_codegen_ssa::traits::{
+    BuilderMethods, CodegenBackendTypes, ConstCodegenMethods, MetadataMethods, TypeMethods,
+};
+use tidec_lir::syntax::{BranchHint, LirTy};
 use tracing::instrument;
 
 use crate::context::CodegenCtx;
-use crate::lir::lir_ty::BasicTypesUtils;
+use crate::lir::lir_body_metadata::CallConvUtils;
+
+/// The weights of the likely and of the unlikely branch of a hinted conditional
+/// branch. They are the ones of `__builtin_expect` in Clang.
+// It corresponds to `cond_br_with_cold_br` in rustc_codegen_llvm/src/builder.rs
+const LIKELY_BRANCH_WEIGHT: u64 = 2000;
+const UNLIKELY_BRANCH_WEIGHT: u64 = 1;
 
 /// A builder for generating LLVM IR code.
 ///
@@ -35,22 +51,158 @@ impl<'ll> CodegenBackendTypes for CodegenBuilder<'_, 'll> {
     type Value = <CodegenCtx<'ll> as CodegenBackendTypes>::Value;
     type FunctionType = <CodegenCtx<'ll> as CodegenBackendTypes>::FunctionType;
     type FunctionValue = <CodegenCtx<'ll> as CodegenBackendTypes>::FunctionValue;
+    type GlobalValue = <CodegenCtx<'ll> as CodegenBackendTypes>::GlobalValue;
     type MetadataType = <CodegenCtx<'ll> as CodegenBackendTypes>::MetadataType;
     type MetadataValue = <CodegenCtx<'ll> as CodegenBackendTypes>::MetadataValue;
 }
 
+/// Implements the `BuilderMethods` for integer binary instructions, which
+/// all share the same shape: two integer operands and an integer result.
+///
+/// It corresponds to the `builder_methods_for_value_instructions!` macro in
+/// `rustc_codegen_llvm/src/builder.rs`.
+macro_rules! builder_methods_for_int_binops {
+    ($($name:ident($($extra:expr),*) => $ll_method:ident($inst:literal)),+ $(,)?) => {
+        $(fn $name(
+            &mut self,
+            lhs: BasicValueEnum<'ll>,
+            rhs: BasicValueEnum<'ll>,
+        ) -> CodegenResult<BasicValueEnum<'ll>> {
+            self.ll_builder
+                .$ll_method(lhs.into_int_value(), rhs.into_int_value(), $($extra,)* "")
+                .map(|val| val.into())
+                .map_err(build_failed($inst))
+        })+
+    };
+}
+
 impl<'a, 'll> CodegenBuilder<'a, 'll> {
     #[instrument(skip(ctx))]
     pub fn with_ctx(ctx: &'a CodegenCtx<'ll>) -> Self {
         let ll_builder = ctx.ll_context.create_builder();
         CodegenBuilder { ll_builder, ctx }
     }
+
+    /// Builds a store of `val` to `ptr`, with the given alignment, and returns it.
+    pub(crate) fn store(
+        &mut self,
+        val: BasicValueEnum<'ll>,
+        ptr: BasicValueEnum<'ll>,
+        align: Align,
+    ) -> CodegenResult<InstructionValue<'ll>> {
+        let store_inst = self
+            .ll_builder
+            .build_store(ptr.into_pointer_value(), val)
+            .map_err(build_failed("store"))?;
+
+        store_inst
+            .set_alignment(align.bytes() as u32)
+            .map_err(build_failed("store"))?;
+        Ok(store_inst)
+    }
+
+    /// Attaches to the given load of a scalar the metadata of its valid range: `!nonnull`
+    /// for a pointer which is never null, and `!range` for an integer.
+    // It corresponds to `scalar_load_metadata` in rustc_codegen_llvm/src/builder.rs
+    fn scalar_load_metadata(
+        &self,
+        load: BasicValueEnum<'ll>,
+        scalar: Scalar,
+        size: Size,
+    ) -> CodegenResult<()> {
+        if scalar.is_always_valid(size) {
+            return Ok(());
+        }
+        let load_inst = load
+            .as_instruction_value()
+            .expect("The loaded value should be a load instruction");
+        let ll_context = self.ctx.ll_context;
+
+        match (scalar.primitive, load) {
+            (Primitive::Pointer(_), _) if !scalar.valid_range.contains(0) => {
+                let kind_id = ll_context.get_kind_id("nonnull");
+                load_inst
+                    .set_metadata(ll_context.metadata_node(&[]), kind_id)
+                    .map_err(build_failed("load"))?;
+            }
+            (Primitive::Pointer(_), _) => {}
+            // A boolean is loaded as an `i1`, whose values are all valid.
+            (_, BasicValueEnum::IntValue(int_val))
+                if int_val.get_type().get_bit_width() as u64 == size.bits() =>
+            {
+                let int_ty = int_val.get_type();
+                // The range is half-open, and it wraps around as the valid range.
+                let max = u128::MAX >> (128 - size.bits());
+                let bound = |value: u128| {
+                    let words = [value as u64, (value >> 64) as u64];
+                    int_ty.const_int_arbitrary_precision(&words).into()
+                };
+                let range = ll_context.metadata_node(&[
+                    bound(scalar.valid_range.start),
+                    bound(scalar.valid_range.end.wrapping_add(1) & max),
+                ]);
+                let kind_id = ll_context.get_kind_id("range");
+                load_inst
+                    .set_metadata(range, kind_id)
+                    .map_err(build_failed("load"))?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Returns the function containing the current insertion block.
+    fn current_fn(&self) -> FunctionValue<'ll> {
+        self.ll_builder
+            .get_insert_block()
+            .and_then(|bb| bb.get_parent())
+            .expect("The builder should be positioned in a function")
+    }
+
+    /// Returns the type of the values of the landing pads, that is, `{ ptr, i32 }`
+    /// for the pointer to the exception and its selector.
+    fn exception_type(&self) -> StructType<'ll> {
+        self.ctx
+            .type_struct(
+                &[self.ctx.type_ptr(), self.ctx.ll_context.i32_type().into()],
+                false,
+            )
+            .into_struct_type()
+    }
+
+    /// Checks that the ABI of a direct call agrees with the declaration of the callee,
+    /// that is, that they have the same calling convention and the same type. A
+    /// mismatch is undefined behavior, which the LLVM verifier does not detect.
+    fn check_callee_abi(&self, fn_abi: &FnAbi<LirTy>, fn_value: FunctionValue<'ll>) {
+        let name = fn_value.get_name().to_string_lossy();
+        if fn_value.get_call_conventions() != fn_abi.conv.into_call_conv() {
+            panic!(
+                "The call of `{}` uses the calling convention {:?}, which is not the one of its declaration",
+                name, fn_abi.conv
+            );
+        }
+        if fn_value.get_type() != self.ctx.fn_type_of_abi(fn_abi) {
+            panic!(
+                "The ABI of the call of `{}` does not match the type of its declaration",
+                name
+            );
+        }
+    }
+
+    /// Sets the calling convention and the attributes of the arguments of the given
+    /// call site, according to the ABI of the callee.
+    fn set_call_site_abi(&self, fn_abi: &FnAbi<LirTy>, call_site: CallSiteValue<'ll>) {
+        call_site.set_call_convention(fn_abi.conv.into_call_conv());
+        for (loc, attr) in self.ctx.fn_abi_attributes(fn_abi) {
+            call_site.add_attribute(loc, attr);
+        }
+    }
 }
 
 impl<'a, 'll> BuilderMethods<'a, 'll> for CodegenBuilder<'a, 'll> {
     type CodegenCtx = CodegenCtx<'ll>;
 
-    fn ctx(&self) -> &Self::CodegenCtx {
+    fn ctx(&self) -> &'a Self::CodegenCtx {
         self.ctx
     }
 
@@ -63,35 +215,51 @@ impl<'a, 'll> BuilderMethods<'a, 'll> for CodegenBuilder<'a, 'll> {
         builder
     }
 
+    fn build_before_terminator(ctx: &'a CodegenCtx<'ll>, llbb: BasicBlock) -> Self {
+        let builder = CodegenBuilder::with_ctx(ctx);
+        match llbb.get_terminator() {
+            Some(terminator) => builder.ll_builder.position_before(&terminator),
+            None => builder.ll_builder.position_at_end(llbb),
+        }
+        builder
+    }
+
     #[instrument(skip(self))]
     /// Allocate memory for a value of the given size and alignment.
     ///
-    /// We do not track the first basic block, so the caller should ensure
-    /// that the allocation is done at the beginning of the function.
-    fn alloca(&self, size: Size, align: Align) -> Self::Value {
-        let builder = self;
-        let ty = self
-            .ctx
-            .ll_context
-            .i8_type()
-            .array_type(size.bytes() as u32);
-        let name = ""; // Generate a unique name for the alloca
-
-        match builder.ll_builder.build_alloca(ty, name) {
-            Ok(pointer_value) => {
-                if let Err(err) = pointer_value
-                    .as_instruction()
-                    .unwrap()
-                    .set_alignment(align.bytes() as u32)
-                {
-                    panic!("Failed to set alignment: {}", err);
-                }
-                pointer_value.into()
-            }
-            Err(err) => {
-                panic!("Failed to allocate memory: {}", err);
-            }
-        }
+    /// The builder is expected to be positioned in the entry block, as the allocas
+    /// anywhere else are dynamic (e.g., they grow the stack in each iteration of a
+    /// loop). See `FnCtx::alloca`.
+    fn alloca(&self, size: Size, align: Align) -> CodegenResult<Self::Value> {
+        let ty = self.ctx.type_i8().array_type(size.bytes() as u32);
+        self.typed_alloca(ty.into(), align)
+    }
+
+    fn typed_alloca(&self, ty: BasicTypeEnum<'ll>, align: Align) -> CodegenResult<Self::Value> {
+        // The alloca is named by `set_var_name`, if it is the memory of a named value.
+        let pointer_value = self
+            .ll_builder
+            .build_alloca(ty, "")
+            .map_err(build_failed("alloca"))?;
+        pointer_value
+            .as_instruction()
+            .unwrap()
+            .set_alignment(align.bytes() as u32)
+            .map_err(build_failed("alloca"))?;
+        Ok(pointer_value.into())
+    }
+
+    fn set_var_name(&self, value: BasicValueEnum<'ll>, name: &str) {
+        value.set_name(name);
+    }
+
+    /// Returns the `idx`-th parameter of the function the builder is positioned in.
+    fn get_param(&mut self, idx: usize) -> BasicValueEnum<'ll> {
+        self.ll_builder
+            .get_insert_block()
+            .and_then(|bb| bb.get_parent())
+            .and_then(|fn_value| fn_value.get_nth_param(idx as u32))
+            .unwrap_or_else(|| panic!("The function has no parameter {}", idx))
     }
 
     /// Append a new basic block to the function.
@@ -108,14 +276,17 @@ impl<'a, 'll> BuilderMethods<'a, 'll> for CodegenBuilder<'a, 'll> {
     }
 
     #[instrument(level = "trace", skip(self))]
-    fn load_operand(&mut self, place_ref: &PlaceRef<Self::Value>) -> OperandRef<Self::Value> {
+    fn load_operand(
+        &mut self,
+        place_ref: &PlaceRef<'a, Self::Value>,
+    ) -> CodegenResult<OperandRef<'a, Self::Value>> {
         if place_ref.ty_layout.is_zst() {
-            return OperandRef::new_zst(place_ref.ty_layout);
+            return Ok(OperandRef::new_zst(place_ref.ty_layout.clone()));
         }
 
         if place_ref.ty_layout.is_immediate() {
             let mut ll_global_const: Option<BasicValueEnum> = None;
-            let llty = place_ref.ty_layout.ty.into_basic_type(self.ctx);
+            let llty = self.ctx.type_from_layout(&place_ref.ty_layout);
 
             // ```rust
             // unsafe {
@@ -139,87 +310,651 @@ impl<'a, 'll> BuilderMethods<'a, 'll> for CodegenBuilder<'a, 'll> {
                 }
             }
 
-            let llval = ll_global_const.unwrap_or_else(|| {
-                // TODO: Here we should call:
-                //
-                // 1) scalar_load_metadata(...)
-                // Attaches LLVM metadata to the load instruction (the one that just pulled load from memory).
-                // This metadata guides LLVM optimizations and correctness:
-                // e.g. alignment info, nonnull if it’s a pointer, range for integers, noalias hints, etc.
-                // So if you load an &T, the compiler may add metadata saying “this pointer is non-null”.
-                //
-                // 2) self.to_immediate_scalar(load, scalar)
-                // Converts the loaded LLVM value (load) into an immediate scalar representation in Tide’s codegen world.
-                // Why? Because some scalars (e.g., booleans) need normalization: Tide booleans are guaranteed to be 0 or 1,
-                // but LLVM might treat them as any non-zero integer. to_immediate_scalar ensures consistency with Tide’s semantics.
-                self.build_load(llty, place_ref.place_val.value, place_ref.place_val.align)
-            });
-
-            OperandRef::new_immediate(llval, place_ref.ty_layout)
+            let llval = match ll_global_const {
+                Some(llval) => llval,
+                None => {
+                    let place_val = place_ref.place_val;
+                    let load = match place_ref.ty_layout.backend_repr {
+                        BackendRepr::Scalar(scalar) => {
+                            let load = self.build_scalar_load(
+                                llty,
+                                scalar,
+                                place_val.value,
+                                place_val.align,
+                            )?;
+                            self.scalar_load_metadata(load, scalar, place_ref.ty_layout.size)?;
+                            load
+                        }
+                        _ => self.build_load(llty, place_val.value, place_val.align)?,
+                    };
+                    // TODO: Here we should call self.to_immediate_scalar(load, scalar)
+                    // Converts the loaded LLVM value (load) into an immediate scalar representation in Tide’s codegen world.
+                    // Why? Because some scalars (e.g., booleans) need normalization: Tide booleans are guaranteed to be 0 or 1,
+                    // but LLVM might treat them as any non-zero integer. to_immediate_scalar ensures consistency with Tide’s semantics.
+                    load
+                }
+            };
+
+            Ok(OperandRef::new_immediate(
+                llval,
+                place_ref.ty_layout.clone(),
+            ))
+        } else if place_ref.ty_layout.is_memory() {
+            // A value in memory is not loaded: the operand refers to its place.
+            Ok(OperandRef {
+                operand_val: OperandVal::Ref(place_ref.place_val),
+                ty_layout: place_ref.ty_layout.clone(),
+            })
         } else {
-            todo!("Handle non-immediate types — when the layout is, for example, `Memory`");
+            // A scalar pair is loaded as its two scalar fields.
+            let [(a_idx, a_layout), (b_idx, b_layout)] =
+                lir::scalar_pair_fields(self.ctx, &place_ref.ty_layout);
+            let a_field = place_ref.project_field(self, a_idx, a_layout)?;
+            let a = self.load_operand(&a_field)?.immediate();
+            let b_field = place_ref.project_field(self, b_idx, b_layout)?;
+            let b = self.load_operand(&b_field)?.immediate();
+            Ok(OperandRef {
+                operand_val: OperandVal::Pair(a, b),
+                ty_layout: place_ref.ty_layout.clone(),
+            })
         }
     }
 
     /// Build a return instruction for the given builder.
     /// If the return value is `None`, it means that the function returns `void`,
     /// otherwise it returns the given value.
-    fn build_return(&mut self, ret_val: Option<Self::Value>) {
-        match ret_val {
-            None => {
-                let _ = self.ll_builder.build_return(None);
-            }
-            Some(val) => {
-                let _ = self.ll_builder.build_return(Some(&val));
-            }
+    fn build_return(&mut self, ret_val: Option<Self::Value>) -> CodegenResult<()> {
+        let ret_val = ret_val.as_ref().map(|val| val as &dyn BasicValue<'ll>);
+        self.ll_builder
+            .build_return(ret_val)
+            .map_err(build_failed("ret"))?;
+        Ok(())
+    }
+
+    /// Build an unconditional branch to the given basic block.
+    fn build_br(&mut self, dest: BasicBlock<'ll>) -> CodegenResult<()> {
+        self.ll_builder
+            .build_unconditional_branch(dest)
+            .map_err(build_failed("br"))?;
+        Ok(())
+    }
+
+    /// Build a conditional branch. Conditions wider than `i1` are compared
+    /// against zero first. A hinted branch gets the `!prof` branch weights.
+    fn build_cond_br(
+        &mut self,
+        cond: BasicValueEnum<'ll>,
+        then_bb: BasicBlock<'ll>,
+        else_bb: BasicBlock<'ll>,
+        hint: Option<BranchHint>,
+    ) -> CodegenResult<()> {
+        let mut cond = cond.into_int_value();
+        if cond.get_type().get_bit_width() != 1 {
+            let zero = cond.get_type().const_zero();
+            cond = self
+                .ll_builder
+                .build_int_compare(inkwell::IntPredicate::NE, cond, zero, "")
+                .map_err(build_failed("icmp"))?;
         }
+
+        let br = self
+            .ll_builder
+            .build_conditional_branch(cond, then_bb, else_bb)
+            .map_err(build_failed("br"))?;
+        if let Some(hint) = hint {
+            let (then_weight, else_weight) = match hint {
+                BranchHint::Likely => (LIKELY_BRANCH_WEIGHT, UNLIKELY_BRANCH_WEIGHT),
+                BranchHint::Unlikely => (UNLIKELY_BRANCH_WEIGHT, LIKELY_BRANCH_WEIGHT),
+            };
+            let ll_context = self.ctx.ll_context;
+            let i32_type = ll_context.i32_type();
+            let branch_weights = ll_context.metadata_node(&[
+                ll_context.metadata_string("branch_weights").into(),
+                i32_type.const_int(then_weight, false).into(),
+                i32_type.const_int(else_weight, false).into(),
+            ]);
+            br.set_metadata(branch_weights, ll_context.get_kind_id("prof"))
+                .map_err(build_failed("br"))?;
+        }
+        Ok(())
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    /// Build a call to the panic handler (`__tidec_panic`) with the given message,
+    /// followed by an `unreachable` instruction.
+    ///
+    /// The message is emitted as a private, unnamed-address global constant.
+    fn build_panic(&mut self, msg: &str, catch: Option<BasicBlock<'ll>>) -> CodegenResult<()> {
+        let panic_fn = self.get_or_declare_panic_fn();
+        let (msg_ptr, msg_len) = self.ctx.const_str(msg);
+        let args = [msg_ptr.into(), msg_len.into()];
+
+        let Some(catch) = catch else {
+            self.ll_builder
+                .build_call(panic_fn, &args, "")
+                .map_err(build_failed("call"))?;
+            return self.build_unreachable();
+        };
+
+        // The panic handler never returns, so the normal destination of the invoke
+        // is unreachable.
+        let fn_value = self.current_fn();
+        let then = self.ll_context.append_basic_block(fn_value, "unreachable");
+        self.ll_builder
+            .build_direct_invoke(panic_fn, &args, then, catch, "")
+            .map_err(build_failed("invoke"))?;
+        self.ll_builder.position_at_end(then);
+        self.build_unreachable()
+    }
+
+    /// Build an `unreachable` instruction.
+    fn build_unreachable(&mut self) -> CodegenResult<()> {
+        self.ll_builder
+            .build_unreachable()
+            .map_err(build_failed("unreachable"))?;
+        Ok(())
+    }
+
+    #[instrument(level = "trace", skip(self, fn_abi))]
+    /// Build a call instruction to the given function value.
+    ///
+    /// The call site gets the calling convention and the argument attributes of
+    /// `fn_abi`, which must agree with the declaration of the callee. The returned value
+    /// is extracted only if the return value is passed `Direct`ly; for `Ignore` and
+    /// `Indirect` (i.e., `sret`) returns, the call produces no value.
+    fn build_call(
+        &mut self,
+        fn_abi: &FnAbi<LirTy>,
+        fn_value: FunctionValue<'ll>,
+        args: &[BasicValueEnum<'ll>],
+    ) -> CodegenResult<Option<BasicValueEnum<'ll>>> {
+        let args = args
+            .iter()
+            .map(|arg| BasicMetadataValueEnum::from(*arg))
+            .collect::<Vec<_>>();
+
+        self.check_callee_abi(fn_abi, fn_value);
+        let call_site = self
+            .ll_builder
+            .build_call(fn_value, &args, "")
+            .map_err(build_failed("call"))?;
+        self.set_call_site_abi(fn_abi, call_site);
+
+        Ok(call_site_ret_val(fn_abi, call_site))
+    }
+
+    #[instrument(level = "trace", skip(self, fn_abi))]
+    /// Build a call instruction through the given function pointer. The type and the
+    /// calling convention of the called function are derived from `fn_abi`.
+    fn build_indirect_call(
+        &mut self,
+        fn_abi: &FnAbi<LirTy>,
+        fn_ptr: BasicValueEnum<'ll>,
+        args: &[BasicValueEnum<'ll>],
+    ) -> CodegenResult<Option<BasicValueEnum<'ll>>> {
+        let args = args
+            .iter()
+            .map(|arg| BasicMetadataValueEnum::from(*arg))
+            .collect::<Vec<_>>();
+
+        let fn_ty = self.ctx.fn_type_of_abi(fn_abi);
+        let call_site = self
+            .ll_builder
+            .build_indirect_call(fn_ty, fn_ptr.into_pointer_value(), &args, "")
+            .map_err(build_failed("call"))?;
+        self.set_call_site_abi(fn_abi, call_site);
+
+        Ok(call_site_ret_val(fn_abi, call_site))
+    }
+
+    #[instrument(level = "trace", skip(self, fn_abi))]
+    /// Build an invoke instruction of the given function value. The call site is
+    /// built as in `build_call`.
+    fn build_invoke(
+        &mut self,
+        fn_abi: &FnAbi<LirTy>,
+        fn_value: FunctionValue<'ll>,
+        args: &[BasicValueEnum<'ll>],
+        then: BasicBlock<'ll>,
+        catch: BasicBlock<'ll>,
+    ) -> CodegenResult<Option<BasicValueEnum<'ll>>> {
+        let args = args
+            .iter()
+            .map(|arg| BasicMetadataValueEnum::from(*arg))
+            .collect::<Vec<_>>();
+
+        self.check_callee_abi(fn_abi, fn_value);
+        let call_site = self
+            .ll_builder
+            .build_direct_invoke(fn_value, &args, then, catch, "")
+            .map_err(build_failed("invoke"))?;
+        self.set_call_site_abi(fn_abi, call_site);
+
+        Ok(call_site_ret_val(fn_abi, call_site))
+    }
+
+    #[instrument(level = "trace", skip(self, fn_abi))]
+    /// Build an invoke instruction through the given function pointer. The call site
+    /// is built as in `build_indirect_call`.
+    fn build_indirect_invoke(
+        &mut self,
+        fn_abi: &FnAbi<LirTy>,
+        fn_ptr: BasicValueEnum<'ll>,
+        args: &[BasicValueEnum<'ll>],
+        then: BasicBlock<'ll>,
+        catch: BasicBlock<'ll>,
+    ) -> CodegenResult<Option<BasicValueEnum<'ll>>> {
+        let args = args
+            .iter()
+            .map(|arg| BasicMetadataValueEnum::from(*arg))
+            .collect::<Vec<_>>();
+
+        let fn_ty = self.ctx.fn_type_of_abi(fn_abi);
+        let call_site = self
+            .ll_builder
+            .build_indirect_invoke(fn_ty, fn_ptr.into_pointer_value(), &args, then, catch, "")
+            .map_err(build_failed("invoke"))?;
+        self.set_call_site_abi(fn_abi, call_site);
+
+        Ok(call_site_ret_val(fn_abi, call_site))
+    }
+
+    /// Build a cleanup `landingpad` of type `{ ptr, i32 }`, with the personality
+    /// function of the current function.
+    fn build_cleanup_landing_pad(
+        &mut self,
+    ) -> CodegenResult<(BasicValueEnum<'ll>, BasicValueEnum<'ll>)> {
+        let personality_fn = self
+            .current_fn()
+            .get_personality_function()
+            .expect("A function with landing pads must have a personality function");
+        let landing_pad = self
+            .ll_builder
+            .build_landing_pad(self.exception_type(), personality_fn, &[], true, "")
+            .map(|landing_pad| landing_pad.into_struct_value())
+            .map_err(build_failed("landingpad"))?;
+        let exn = self.build_extract_value(landing_pad.into(), 0)?;
+        let selector = self.build_extract_value(landing_pad.into(), 1)?;
+        Ok((exn, selector))
+    }
+
+    fn build_resume(
+        &mut self,
+        exn: BasicValueEnum<'ll>,
+        selector: BasicValueEnum<'ll>,
+    ) -> CodegenResult<()> {
+        let exception = self.exception_type().get_undef();
+        let exception = self.build_insert_value(exception.into(), exn, 0)?;
+        let exception = self.build_insert_value(exception, selector, 1)?;
+        self.ll_builder
+            .build_resume(exception)
+            .map_err(build_failed("resume"))?;
+        Ok(())
     }
 
     /// Build a load instruction to load a value from the given pointer. It also creates
     /// a new variable to hold the loaded value.
-    fn build_load(&mut self, ty: Self::Type, ptr: Self::Value, align: Align) -> Self::Value {
-        let load_inst = match self.ll_builder.build_load(ty, ptr.into_pointer_value(), "") {
-            Ok(v) => v,
-            Err(err) => panic!("Failed to build load instruction: {}", err),
-        };
+    fn build_load(
+        &mut self,
+        ty: Self::Type,
+        ptr: Self::Value,
+        align: Align,
+    ) -> CodegenResult<Self::Value> {
+        let load_inst = self
+            .ll_builder
+            .build_load(ty, ptr.into_pointer_value(), "")
+            .map_err(build_failed("load"))?;
 
         load_inst
             .as_instruction_value()
             .unwrap()
             .set_alignment(align.bytes() as u32)
-            .expect("Failed to set alignment");
+            .map_err(build_failed("load"))?;
 
-        load_inst
+        Ok(load_inst)
     }
 
-    fn const_scalar_to_backend_value(
-        &self,
-        const_scalar: ConstScalar,
-        ty_layout: TyAndLayout<LirTy>,
-    ) -> Self::Value {
-        assert!(matches!(ty_layout.backend_repr, BackendRepr::Scalar(_)));
-        let llty = ty_layout.ty.into_basic_type(self.ctx);
-        let be_repr = ty_layout.backend_repr.to_primitive();
-
-        match const_scalar {
-            /* TODO: ConstScalar::Ptr(...) */
-            ConstScalar::Value(raw_scalar_value) => {
-                let bits = raw_scalar_value.to_bits(ty_layout.size);
-                // TODO: Consider moving i128_type method to ctx
-                let int_128 = self.ctx().ll_context.i128_type();
-                //
-                // Split the 128-bit integer into two 64-bit words for LLVM
-                let words = [(bits & u64::MAX as u128) as u64, (bits >> 64) as u64];
-                let llval = int_128.const_int_arbitrary_precision(&words);
-
-                if let Primitive::Pointer(_) = be_repr {
-                    llval.const_to_pointer(llty.into_pointer_type()).into()
-                } else {
-                    llval
-                        .const_truncate_or_bit_cast(llty.into_int_type())
-                        .into()
-                }
-            }
-        }
+    fn build_inbounds_gep(
+        &mut self,
+        ty: Self::Type,
+        ptr: Self::Value,
+        indices: &[Self::Value],
+    ) -> CodegenResult<Self::Value> {
+        let indices = indices
+            .iter()
+            .map(|idx| idx.into_int_value())
+            .collect::<Vec<_>>();
+        // SAFETY: the indices are computed from the LIR projections, which are
+        // expected to stay within the bounds of the pointed-to allocation.
+        let gep = unsafe {
+            self.ll_builder
+                .build_in_bounds_gep(ty, ptr.into_pointer_value(), &indices, "")
+        };
+        gep.map(|ptr| ptr.into())
+            .map_err(build_failed("getelementptr"))
+    }
+
+    fn build_struct_gep(
+        &mut self,
+        ty: Self::Type,
+        ptr: Self::Value,
+        idx: u64,
+    ) -> CodegenResult<Self::Value> {
+        self.ll_builder
+            .build_struct_gep(ty, ptr.into_pointer_value(), idx as u32, "")
+            .map(|ptr| ptr.into())
+            .map_err(build_failed("getelementptr"))
+    }
+
+    builder_methods_for_int_binops! {
+        build_add() => build_int_add("add"),
+        build_sub() => build_int_sub("sub"),
+        build_mul() => build_int_mul("mul"),
+        build_sdiv() => build_int_signed_div("sdiv"),
+        build_udiv() => build_int_unsigned_div("udiv"),
+        build_srem() => build_int_signed_rem("srem"),
+        build_urem() => build_int_unsigned_rem("urem"),
+        build_and() => build_and("and"),
+        build_or() => build_or("or"),
+        build_xor() => build_xor("xor"),
+        build_shl() => build_left_shift("shl"),
+        build_ashr(true) => build_right_shift("ashr"),
+        build_lshr(false) => build_right_shift("lshr"),
+    }
+
+    fn build_neg(&mut self, val: BasicValueEnum<'ll>) -> CodegenResult<BasicValueEnum<'ll>> {
+        self.ll_builder
+            .build_int_neg(val.into_int_value(), "")
+            .map(|val| val.into())
+            .map_err(build_failed("neg"))
+    }
+
+    fn build_fneg(&mut self, val: BasicValueEnum<'ll>) -> CodegenResult<BasicValueEnum<'ll>> {
+        self.ll_builder
+            .build_float_neg(val.into_float_value(), "")
+            .map(|val| val.into())
+            .map_err(build_failed("fneg"))
+    }
+
+    fn build_not(&mut self, val: BasicValueEnum<'ll>) -> CodegenResult<BasicValueEnum<'ll>> {
+        self.ll_builder
+            .build_not(val.into_int_value(), "")
+            .map(|val| val.into())
+            .map_err(build_failed("not"))
+    }
+
+    fn build_zext(
+        &mut self,
+        val: BasicValueEnum<'ll>,
+        dest_ty: BasicTypeEnum<'ll>,
+    ) -> CodegenResult<BasicValueEnum<'ll>> {
+        self.ll_builder
+            .build_int_z_extend(val.into_int_value(), dest_ty.into_int_type(), "")
+            .map(|val| val.into())
+            .map_err(build_failed("zext"))
+    }
+
+    fn build_sext(
+        &mut self,
+        val: BasicValueEnum<'ll>,
+        dest_ty: BasicTypeEnum<'ll>,
+    ) -> CodegenResult<BasicValueEnum<'ll>> {
+        self.ll_builder
+            .build_int_s_extend(val.into_int_value(), dest_ty.into_int_type(), "")
+            .map(|val| val.into())
+            .map_err(build_failed("sext"))
+    }
+
+    fn build_trunc(
+        &mut self,
+        val: BasicValueEnum<'ll>,
+        dest_ty: BasicTypeEnum<'ll>,
+    ) -> CodegenResult<BasicValueEnum<'ll>> {
+        self.ll_builder
+            .build_int_truncate(val.into_int_value(), dest_ty.into_int_type(), "")
+            .map(|val| val.into())
+            .map_err(build_failed("trunc"))
+    }
+
+    fn build_fptosi(
+        &mut self,
+        val: BasicValueEnum<'ll>,
+        dest_ty: BasicTypeEnum<'ll>,
+    ) -> CodegenResult<BasicValueEnum<'ll>> {
+        self.ll_builder
+            .build_float_to_signed_int(val.into_float_value(), dest_ty.into_int_type(), "")
+            .map(|val| val.into())
+            .map_err(build_failed("fptosi"))
+    }
+
+    fn build_fptoui(
+        &mut self,
+        val: BasicValueEnum<'ll>,
+        dest_ty: BasicTypeEnum<'ll>,
+    ) -> CodegenResult<BasicValueEnum<'ll>> {
+        self.ll_builder
+            .build_float_to_unsigned_int(val.into_float_value(), dest_ty.into_int_type(), "")
+            .map(|val| val.into())
+            .map_err(build_failed("fptoui"))
+    }
+
+    fn build_sitofp(
+        &mut self,
+        val: BasicValueEnum<'ll>,
+        dest_ty: BasicTypeEnum<'ll>,
+    ) -> CodegenResult<BasicValueEnum<'ll>> {
+        self.ll_builder
+            .build_signed_int_to_float(val.into_int_value(), dest_ty.into_float_type(), "")
+            .map(|val| val.into())
+            .map_err(build_failed("sitofp"))
+    }
+
+    fn build_uitofp(
+        &mut self,
+        val: BasicValueEnum<'ll>,
+        dest_ty: BasicTypeEnum<'ll>,
+    ) -> CodegenResult<BasicValueEnum<'ll>> {
+        self.ll_builder
+            .build_unsigned_int_to_float(val.into_int_value(), dest_ty.into_float_type(), "")
+            .map(|val| val.into())
+            .map_err(build_failed("uitofp"))
+    }
+
+    fn build_fpext(
+        &mut self,
+        val: BasicValueEnum<'ll>,
+        dest_ty: BasicTypeEnum<'ll>,
+    ) -> CodegenResult<BasicValueEnum<'ll>> {
+        self.ll_builder
+            .build_float_ext(val.into_float_value(), dest_ty.into_float_type(), "")
+            .map(|val| val.into())
+            .map_err(build_failed("fpext"))
+    }
+
+    fn build_fptrunc(
+        &mut self,
+        val: BasicValueEnum<'ll>,
+        dest_ty: BasicTypeEnum<'ll>,
+    ) -> CodegenResult<BasicValueEnum<'ll>> {
+        self.ll_builder
+            .build_float_trunc(val.into_float_value(), dest_ty.into_float_type(), "")
+            .map(|val| val.into())
+            .map_err(build_failed("fptrunc"))
+    }
+
+    fn build_ptrtoint(
+        &mut self,
+        val: BasicValueEnum<'ll>,
+        dest_ty: BasicTypeEnum<'ll>,
+    ) -> CodegenResult<BasicValueEnum<'ll>> {
+        self.ll_builder
+            .build_ptr_to_int(val.into_pointer_value(), dest_ty.into_int_type(), "")
+            .map(|val| val.into())
+            .map_err(build_failed("ptrtoint"))
+    }
+
+    fn build_inttoptr(
+        &mut self,
+        val: BasicValueEnum<'ll>,
+        dest_ty: BasicTypeEnum<'ll>,
+    ) -> CodegenResult<BasicValueEnum<'ll>> {
+        self.ll_builder
+            .build_int_to_ptr(val.into_int_value(), dest_ty.into_pointer_type(), "")
+            .map(|val| val.into())
+            .map_err(build_failed("inttoptr"))
+    }
+
+    fn build_bitcast(
+        &mut self,
+        val: BasicValueEnum<'ll>,
+        dest_ty: BasicTypeEnum<'ll>,
+    ) -> CodegenResult<BasicValueEnum<'ll>> {
+        self.ll_builder
+            .build_bit_cast(val, dest_ty, "")
+            .map_err(build_failed("bitcast"))
+    }
+
+    fn build_icmp(
+        &mut self,
+        pred: IntPredicate,
+        lhs: BasicValueEnum<'ll>,
+        rhs: BasicValueEnum<'ll>,
+    ) -> CodegenResult<BasicValueEnum<'ll>> {
+        let ll_pred = match pred {
+            IntPredicate::IntEQ => inkwell::IntPredicate::EQ,
+            IntPredicate::IntNE => inkwell::IntPredicate::NE,
+            IntPredicate::IntUGT => inkwell::IntPredicate::UGT,
+            IntPredicate::IntUGE => inkwell::IntPredicate::UGE,
+            IntPredicate::IntULT => inkwell::IntPredicate::ULT,
+            IntPredicate::IntULE => inkwell::IntPredicate::ULE,
+            IntPredicate::IntSGT => inkwell::IntPredicate::SGT,
+            IntPredicate::IntSGE => inkwell::IntPredicate::SGE,
+            IntPredicate::IntSLT => inkwell::IntPredicate::SLT,
+            IntPredicate::IntSLE => inkwell::IntPredicate::SLE,
+        };
+
+        self.ll_builder
+            .build_int_compare(ll_pred, lhs.into_int_value(), rhs.into_int_value(), "")
+            .map(|val| val.into())
+            .map_err(build_failed("icmp"))
+    }
+
+    fn build_extract_value(
+        &mut self,
+        agg: BasicValueEnum<'ll>,
+        idx: u64,
+    ) -> CodegenResult<BasicValueEnum<'ll>> {
+        self.ll_builder
+            .build_extract_value(agg.into_struct_value(), idx as u32, "")
+            .map_err(build_failed("extractvalue"))
+    }
+
+    fn build_insert_value(
+        &mut self,
+        agg: BasicValueEnum<'ll>,
+        elem: BasicValueEnum<'ll>,
+        idx: u64,
+    ) -> CodegenResult<BasicValueEnum<'ll>> {
+        self.ll_builder
+            .build_insert_value(agg.into_struct_value(), elem, idx as u32, "")
+            .map(|val| val.into_struct_value().into())
+            .map_err(build_failed("insertvalue"))
+    }
+
+    fn build_select(
+        &mut self,
+        cond: BasicValueEnum<'ll>,
+        then_val: BasicValueEnum<'ll>,
+        else_val: BasicValueEnum<'ll>,
+    ) -> CodegenResult<BasicValueEnum<'ll>> {
+        self.ll_builder
+            .build_select(cond.into_int_value(), then_val, else_val, "")
+            .map_err(build_failed("select"))
+    }
+
+    fn llbb(&self) -> BasicBlock<'ll> {
+        self.ll_builder
+            .get_insert_block()
+            .expect("The builder is positioned in a basic block")
+    }
+
+    fn build_phi(&mut self, ty: BasicTypeEnum<'ll>) -> CodegenResult<BasicValueEnum<'ll>> {
+        self.ll_builder
+            .build_phi(ty, "")
+            .map(|phi| phi.as_basic_value())
+            .map_err(build_failed("phi"))
+    }
+
+    fn add_incoming_to_phi(
+        &mut self,
+        phi: BasicValueEnum<'ll>,
+        val: BasicValueEnum<'ll>,
+        bb: BasicBlock<'ll>,
+    ) {
+        let phi = phi
+            .as_instruction_value()
+            .and_then(|instruction| PhiValue::try_from(instruction).ok())
+            .expect("The value is a phi");
+        phi.add_incoming(&[(&val, bb)]);
+    }
+
+    /// Build a store instruction with the given alignment.
+    fn build_store(
+        &mut self,
+        val: BasicValueEnum<'ll>,
+        ptr: BasicValueEnum<'ll>,
+        align: Align,
+    ) -> CodegenResult<()> {
+        self.store(val, ptr, align)?;
+        Ok(())
+    }
+
+    fn build_memcpy(
+        &mut self,
+        dst: Self::Value,
+        dst_align: Align,
+        src: Self::Value,
+        src_align: Align,
+        size: Size,
+    ) -> CodegenResult<()> {
+        let size = self.ctx.usize_type().const_int(size.bytes(), false);
+        self.ll_builder
+            .build_memcpy(
+                dst.into_pointer_value(),
+                dst_align.bytes() as u32,
+                src.into_pointer_value(),
+                src_align.bytes() as u32,
+                size,
+            )
+            .map_err(build_failed("memcpy"))?;
+        Ok(())
+    }
+}
+
+/// Returns the conversion of an error of LLVM, raised while building the given
+/// instruction, into a `CodegenError`.
+pub(crate) fn build_failed<E: Display>(
+    instruction: &'static str,
+) -> impl FnOnce(E) -> CodegenError {
+    move |err| CodegenError::BuildFailed {
+        instruction,
+        message: err.to_string(),
+    }
+}
+
+/// Returns the value produced by the given call site, if its return value is passed
+/// `Direct`ly (or as a `Pair` or a `Cast`).
+fn call_site_ret_val<'ll>(
+    fn_abi: &FnAbi<LirTy>,
+    call_site: CallSiteValue<'ll>,
+) -> Option<BasicValueEnum<'ll>> {
+    match fn_abi.ret.mode {
+        PassMode::Ignore | PassMode::Indirect { .. } => None,
+        PassMode::Direct(_) | PassMode::Pair(..) | PassMode::Cast(_) => Some(
+            call_site
+                .try_as_basic_value()
+                .left()
+                .expect("A direct return should produce a value"),
+        ),
     }
 }
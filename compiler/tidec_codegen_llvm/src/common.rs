@@ -0,0 +1,187 @@
+//! The constants of the LLVM modules (see `ConstCodegenMethods`).
+// It corresponds to rustc_codegen_llvm/src/common.rs
+
+use inkwell::llvm_sys::core::LLVMConstBitCast;
+use inkwell::module::Linkage;
+use inkwell::types::{AsTypeRef, BasicTypeEnum};
+use inkwell::values::{AsValueRef, BasicValueEnum, FloatValue, UnnamedAddress};
+use tidec_abi::layout::{BackendRepr, Primitive, TyAndLayout};
+use tidec_codegen_ssa::traits::{ConstCodegenMethods, TypeMethods};
+use tidec_lir::syntax::{ConstScalar, LirTy};
+
+use crate::context::CodegenCtx;
+
+impl<'ll> ConstCodegenMethods for CodegenCtx<'ll> {
+    fn const_undef(&self, ty: BasicTypeEnum<'ll>) -> BasicValueEnum<'ll> {
+        match ty {
+            BasicTypeEnum::IntType(int_type) => int_type.get_undef().into(),
+            BasicTypeEnum::ArrayType(array_type) => array_type.get_undef().into(),
+            BasicTypeEnum::FloatType(float_type) => float_type.get_undef().into(),
+            BasicTypeEnum::PointerType(pointer_type) => pointer_type.get_undef().into(),
+            BasicTypeEnum::StructType(struct_type) => struct_type.get_undef().into(),
+            BasicTypeEnum::VectorType(vector_type) => vector_type.get_undef().into(),
+            BasicTypeEnum::ScalableVectorType(scalable_vector_type) => {
+                scalable_vector_type.get_undef().into()
+            }
+        }
+    }
+
+    fn const_int(&self, ty: BasicTypeEnum<'ll>, val: i64) -> BasicValueEnum<'ll> {
+        ty.into_int_type().const_int(val as u64, true).into()
+    }
+
+    fn const_uint_big(&self, ty: BasicTypeEnum<'ll>, val: u128) -> BasicValueEnum<'ll> {
+        // The value is split into two 64-bit words for LLVM, as an `i128` which is
+        // then truncated to the type.
+        let words = [(val & u64::MAX as u128) as u64, (val >> 64) as u64];
+        self.ll_context
+            .i128_type()
+            .const_int_arbitrary_precision(&words)
+            .const_truncate_or_bit_cast(ty.into_int_type())
+            .into()
+    }
+
+    fn const_bool(&self, val: bool) -> BasicValueEnum<'ll> {
+        self.ll_context
+            .bool_type()
+            .const_int(val as u64, false)
+            .into()
+    }
+
+    fn const_null_ptr(&self, ty: BasicTypeEnum<'ll>) -> BasicValueEnum<'ll> {
+        ty.into_pointer_type().const_null().into()
+    }
+
+    fn const_struct(
+        &self,
+        ty_layout: &TyAndLayout<LirTy>,
+        fields: &[BasicValueEnum<'ll>],
+    ) -> BasicValueEnum<'ll> {
+        // The fields of the LLVM struct are in memory order.
+        let fields = ty_layout
+            .fields
+            .index_by_increasing_offset()
+            .into_iter()
+            .map(|i| fields[i])
+            .collect::<Vec<_>>();
+        self.type_from_layout(ty_layout)
+            .into_struct_type()
+            .const_named_struct(&fields)
+            .into()
+    }
+
+    fn const_array(
+        &self,
+        elem_layout: &TyAndLayout<LirTy>,
+        elems: &[BasicValueEnum<'ll>],
+    ) -> BasicValueEnum<'ll> {
+        match self.type_from_layout(elem_layout) {
+            BasicTypeEnum::IntType(ty) => {
+                let elems = elems.iter().map(|v| v.into_int_value()).collect::<Vec<_>>();
+                ty.const_array(&elems).into()
+            }
+            BasicTypeEnum::FloatType(ty) => {
+                let elems = elems
+                    .iter()
+                    .map(|v| v.into_float_value())
+                    .collect::<Vec<_>>();
+                ty.const_array(&elems).into()
+            }
+            BasicTypeEnum::PointerType(ty) => {
+                let elems = elems
+                    .iter()
+                    .map(|v| v.into_pointer_value())
+                    .collect::<Vec<_>>();
+                ty.const_array(&elems).into()
+            }
+            BasicTypeEnum::ArrayType(ty) => {
+                let elems = elems
+                    .iter()
+                    .map(|v| v.into_array_value())
+                    .collect::<Vec<_>>();
+                ty.const_array(&elems).into()
+            }
+            BasicTypeEnum::StructType(ty) => {
+                let elems = elems
+                    .iter()
+                    .map(|v| v.into_struct_value())
+                    .collect::<Vec<_>>();
+                ty.const_array(&elems).into()
+            }
+            // LIR has no vector types.
+            ty => unreachable!("Constant array of the vector type {:?}", ty),
+        }
+    }
+
+    /// The bytes are emitted as a private global, shared by the identical strings.
+    fn const_str(&self, s: &str) -> (BasicValueEnum<'ll>, BasicValueEnum<'ll>) {
+        let len = self.usize_type().const_int(s.len() as u64, false).into();
+        if let Some(global) = self.const_str_cache.borrow().get(s) {
+            return (global.as_pointer_value().into(), len);
+        }
+
+        let str_val = self.ll_context.const_string(s.as_bytes(), false);
+        let global = self.ll_module.add_global(str_val.get_type(), None, "str");
+        global.set_initializer(&str_val);
+        global.set_constant(true);
+        global.set_linkage(Linkage::Private);
+        global.set_unnamed_address(UnnamedAddress::Global);
+        global.set_alignment(1);
+
+        self.const_str_cache
+            .borrow_mut()
+            .insert(s.to_string(), global);
+        (global.as_pointer_value().into(), len)
+    }
+
+    fn const_scalar_to_backend_value(
+        &self,
+        const_scalar: ConstScalar,
+        ty_layout: &TyAndLayout<LirTy>,
+    ) -> BasicValueEnum<'ll> {
+        assert!(matches!(ty_layout.backend_repr, BackendRepr::Scalar(_)));
+        let llty = self.type_from_layout(ty_layout);
+        let be_repr = ty_layout.backend_repr.to_primitive();
+
+        match const_scalar {
+            /* TODO: ConstScalar::Ptr(...) */
+            ConstScalar::Value(raw_scalar_value) => {
+                let bits = raw_scalar_value.to_bits(ty_layout.size);
+                if let Primitive::Pointer(_) = be_repr {
+                    let llval = self.const_uint_big(self.usize_type().into(), bits);
+                    llval
+                        .into_int_value()
+                        .const_to_pointer(llty.into_pointer_type())
+                        .into()
+                } else if be_repr.is_float() {
+                    let float_ty = llty.into_float_type();
+                    match be_repr {
+                        Primitive::F32 => float_ty
+                            .const_float(f32::from_bits(bits as u32) as f64)
+                            .into(),
+                        Primitive::F64 => float_ty.const_float(f64::from_bits(bits as u64)).into(),
+                        // There is no `f64` for every value of these types, so the
+                        // constant is the integer of its bits, cast to the float.
+                        _ => {
+                            let int_ty = self
+                                .ll_context
+                                .custom_width_int_type(ty_layout.size.bits() as u32);
+                            let int_val = self.const_uint_big(int_ty.into(), bits);
+                            // SAFETY: the integer and the float have the same width, and
+                            // the bitcast of a constant is a constant of the float type.
+                            unsafe {
+                                FloatValue::new(LLVMConstBitCast(
+                                    int_val.as_value_ref(),
+                                    float_ty.as_type_ref(),
+                                ))
+                            }
+                            .into()
+                        }
+                    }
+                } else {
+                    self.const_uint_big(llty, bits)
+                }
+            }
+        }
+    }
+}
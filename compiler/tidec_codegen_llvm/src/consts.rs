@@ -0,0 +1,50 @@
+//! The globals of the LLVM modules (see `StaticCodegenMethods`).
+// It corresponds to rustc_codegen_llvm/src/consts.rs
+
+use inkwell::module::Linkage;
+use inkwell::types::BasicTypeEnum;
+use inkwell::values::{BasicValueEnum, GlobalValue, UnnamedAddress};
+use tidec_abi::size_and_align::Align;
+use tidec_codegen_ssa::traits::StaticCodegenMethods;
+
+use crate::context::CodegenCtx;
+
+impl<'ll> StaticCodegenMethods for CodegenCtx<'ll> {
+    fn static_addr_of(&self, val: BasicValueEnum<'ll>, align: Align) -> BasicValueEnum<'ll> {
+        let global = self.ll_module.add_global(val.get_type(), None, "const");
+        self.set_initializer(global, val);
+        self.set_global_constant(global, true);
+        global.set_linkage(Linkage::Private);
+        global.set_unnamed_address(UnnamedAddress::Global);
+        self.set_global_alignment(global, align);
+        self.global_addr(global)
+    }
+
+    fn define_global(&self, name: &str, ty: BasicTypeEnum<'ll>) -> Option<GlobalValue<'ll>> {
+        match self.ll_module.get_global(name) {
+            Some(global) if global.get_initializer().is_some() => None,
+            Some(global) => Some(global),
+            None => Some(self.ll_module.add_global(ty, None, name)),
+        }
+    }
+
+    fn set_initializer(&self, global: GlobalValue<'ll>, val: BasicValueEnum<'ll>) {
+        global.set_initializer(&val);
+    }
+
+    fn set_global_constant(&self, global: GlobalValue<'ll>, is_constant: bool) {
+        global.set_constant(is_constant);
+    }
+
+    fn set_global_alignment(&self, global: GlobalValue<'ll>, align: Align) {
+        global.set_alignment(align.bytes() as u32);
+    }
+
+    fn set_global_section(&self, global: GlobalValue<'ll>, section: &str) {
+        global.set_section(Some(section));
+    }
+
+    fn global_addr(&self, global: GlobalValue<'ll>) -> BasicValueEnum<'ll> {
+        global.as_pointer_value().into()
+    }
+}
@@ -1,35 +1,61 @@
-use std::cell::RefCell;
+use std::cell::{OnceCell, RefCell};
 use std::collections::HashMap;
 use std::ops::Deref;
-use std::path::Path;
+use std::path::PathBuf;
 
+use inkwell::attributes::{Attribute, AttributeLoc};
 use inkwell::basic_block::BasicBlock;
 use inkwell::context::Context;
 use inkwell::module::Module;
+use inkwell::module::{FlagBehavior, Linkage};
+use inkwell::passes::PassBuilderOptions;
 use inkwell::targets::{
     CodeModel, FileType, InitializationConfig, RelocMode, Target, TargetData, TargetMachine,
     TargetTriple,
 };
-use inkwell::types::{BasicMetadataTypeEnum, BasicTypeEnum, FunctionType};
-use inkwell::values::{AnyValueEnum, BasicMetadataValueEnum, BasicValueEnum, FunctionValue};
-use inkwell::OptimizationLevel;
-use tidec_abi::calling_convention::function::{ArgAbi, FnAbi, PassMode};
-use tidec_abi::layout::{BackendRepr, TyAndLayout};
+use inkwell::types::{
+    AnyType, BasicMetadataTypeEnum, BasicTypeEnum, FunctionType, IntType, StructType,
+};
+use inkwell::values::{
+    BasicMetadataValueEnum, BasicValueEnum, FunctionValue, GlobalValue, MetadataValue,
+    UnnamedAddress,
+};
+use inkwell::AddressSpace;
+use tidec_abi::calling_convention::function::{
+    ArgAbi, ArgAttribute, ArgAttributes, ArgExtension, FnAbi, PassMode,
+};
+use tidec_abi::calling_convention::CallConv;
+use tidec_abi::layout::{BackendRepr, Primitive, Scalar, TyAndLayout, VariantIdx};
+use tidec_abi::target::RelocModel;
+use tidec_codegen_ssa::backend::CompiledModule;
+use tidec_codegen_ssa::error::{CodegenError, CodegenResult};
 use tidec_codegen_ssa::lir;
-use tidec_lir::layout_ctx::LayoutCtx;
-use tidec_utils::index_vec::IdxVec;
+use tidec_utils::{idx::Idx, index_vec::IdxVec};
 use tracing::{debug, instrument};
 
+use crate::builder::CodegenBuilder;
+use crate::debuginfo::DebugCtx;
+use crate::lir::lir_args::{
+    CodeModelUtils, OptLevelUtils, RelocModelUtils, SanitizerSetUtils, StackProtectorUtils,
+};
 use crate::lir::lir_body_metadata::{
     CallConvUtils, LinkageUtils, UnnamedAddressUtils, VisibilityUtils,
 };
-use crate::lir::lir_ty::BasicTypesUtils;
 use tidec_codegen_ssa::traits::{
-    BuilderMethods, CodegenBackend, CodegenBackendTypes, CodegenMethods, DefineCodegenMethods,
-    FnAbiOf, LayoutOf, PreDefineCodegenMethods,
+    AsmBuilderMethods, CodegenBackend, CodegenBackendTypes, CodegenMethods,
+    DebugInfoBuilderMethods, DefineCodegenMethods, FnAbiOf, IntrinsicCallBuilderMethods, LayoutOf,
+    MetadataMethods, PreDefineCodegenMethods, TypeMethods,
 };
-use tidec_lir::lir::{DefId, EmitKind, LirBody, LirBodyMetadata, LirCtx, LirUnit};
-use tidec_lir::syntax::{LirTy, Local, LocalData, RETURN_LOCAL};
+use tidec_lir::lir::{DefId, FnAttribute, Linkage as LirLinkage, LirBody, LirBodyMetadata, LirCtx};
+use tidec_lir::syntax::{AdtId, AllocId, LirTy, Local, LocalData, RETURN_LOCAL};
+use tidec_session::config::{DebugInfo, EmitKind};
+
+/// The signature of a function: the types of its return value and of its arguments,
+/// and its calling convention.
+type FnAbiKey = (Vec<LirTy>, CallConv);
+
+/// The passing modes of the return value and of the arguments of a function.
+type FnAbiModes = (PassMode, Box<[PassMode]>);
 
 // TODO: Add filelds from rustc/compiler/rustc_codegen_llvm/src/context.rs
 pub struct CodegenCtx<'ll> {
@@ -41,13 +67,32 @@ pub struct CodegenCtx<'ll> {
     /// The LIR type context.
     pub lir_ctx: LirCtx,
 
-    /// A map from DefId to the LLVM value (usually a function value).
-    //
-    // FIXME: Consider removing RefCell and using &mut
-    //
-    // TODO: Probably we could remove this and use only the module to find functions (more efficient?).
-    // Something like: `self.ll_module.get_function(<name>)` (see `get_fn`).
-    pub instances: RefCell<HashMap<DefId, AnyValueEnum<'ll>>>,
+    /// A cache of the constant strings emitted as globals (e.g., panic messages).
+    /// This avoids emitting the same string multiple times in the module.
+    pub const_str_cache: RefCell<HashMap<String, GlobalValue<'ll>>>,
+
+    /// A cache of the LLVM struct types of the ADT variants. Each variant is lowered
+    /// to a named struct type, which must be created only once.
+    pub adt_types: RefCell<HashMap<(AdtId, VariantIdx), StructType<'ll>>>,
+
+    /// The globals emitted for the allocations of the `LirCtx`. Each allocation is
+    /// emitted only once, so that all the constants pointing into it share its address.
+    pub alloc_globals: RefCell<HashMap<AllocId, GlobalValue<'ll>>>,
+
+    /// The state of the debug info of the module, set once its compile unit is
+    /// created (i.e., if the debug info is enabled).
+    pub debug_ctx: OnceCell<DebugCtx<'ll>>,
+
+    /// A cache of the TBAA access tags of the kinds of scalars (see `metadata`).
+    pub tbaa_tags: RefCell<HashMap<Primitive, MetadataValue<'ll>>>,
+
+    /// A cache of the passing modes of the return value and of the arguments of each
+    /// signature (the types of the return value and of the arguments, and the calling
+    /// convention), which are computed once by `fn_abi_of`. The layouts of the ABI are
+    /// cached by the `LirCtx`.
+    // It corresponds to the `fn_abi_of_instance` and `fn_abi_of_fn_ptr` queries in
+    // rustc_ty_utils/src/abi.rs
+    pub fn_abis: RefCell<HashMap<FnAbiKey, FnAbiModes>>,
 }
 
 impl<'ll> Deref for CodegenCtx<'ll> {
@@ -62,6 +107,7 @@ impl<'ll> CodegenBackendTypes for CodegenCtx<'ll> {
     type BasicBlock = BasicBlock<'ll>;
     type FunctionType = FunctionType<'ll>;
     type FunctionValue = FunctionValue<'ll>;
+    type GlobalValue = GlobalValue<'ll>;
     type Type = BasicTypeEnum<'ll>;
     type Value = BasicValueEnum<'ll>;
     type MetadataType = BasicMetadataTypeEnum<'ll>;
@@ -81,16 +127,51 @@ impl PreDefineCodegenMethods for CodegenCtx<'_> {
     ) {
         let name = lir_body_metadata.name.as_str();
 
-        let ret_ty = lir_body_ret_and_args[RETURN_LOCAL].ty.into_basic_type(self);
-        let formal_param_tys = lir_body_ret_and_args.as_slice()[RETURN_LOCAL.next()..]
-            .iter()
-            .map(|local_data| local_data.ty.into_basic_type_metadata(self))
-            .collect::<Vec<_>>();
-        let fn_ty = self.declare_fn(ret_ty, formal_param_tys.as_slice());
+        let fn_abi = if lir_body_metadata.is_variadic {
+            self.fn_abi_of_variadic(
+                &self.lir_ctx,
+                lir_body_ret_and_args,
+                &[],
+                lir_body_metadata.call_conv,
+            )
+        } else {
+            self.fn_abi_of(
+                &self.lir_ctx,
+                lir_body_ret_and_args,
+                lir_body_metadata.call_conv,
+            )
+        };
+        let (ret_ty, formal_param_tys) = self.fn_abi_types(&fn_abi);
+        let fn_ty = self.declare_fn(ret_ty, formal_param_tys.as_slice(), fn_abi.is_variadic);
         let linkage = lir_body_metadata.linkage.into_linkage();
-        let calling_convention = lir_body_metadata.call_conv.into_call_conv();
+        let calling_convention = fn_abi.conv.into_call_conv();
         let fn_val = self.ll_module.add_function(name, fn_ty, Some(linkage));
         fn_val.set_call_conventions(calling_convention);
+        for (loc, attr) in self.fn_abi_attributes(&fn_abi) {
+            fn_val.add_attribute(loc, attr);
+        }
+        for attr in self.fn_attributes(lir_body_metadata.attrs) {
+            fn_val.add_attribute(AttributeLoc::Function, attr);
+        }
+        let stack_protector = self.lir_ctx.stack_protector().fn_attribute_name();
+        for name in self
+            .lir_ctx
+            .sanitizers()
+            .fn_attribute_names()
+            .into_iter()
+            .chain(stack_protector)
+        {
+            let kind_id = Attribute::get_named_enum_kind_id(name);
+            let attr = self.ll_context.create_enum_attribute(kind_id, 0);
+            fn_val.add_attribute(AttributeLoc::Function, attr);
+        }
+        // It corresponds to `frame_pointer_type_attr` in rustc_codegen_llvm/src/attributes.rs
+        if self.lir_ctx.force_frame_pointers() {
+            let attr = self
+                .ll_context
+                .create_string_attribute("frame-pointer", "all");
+            fn_val.add_attribute(AttributeLoc::Function, attr);
+        }
 
         let fn_global_value = fn_val.as_global_value();
         let visibility = lir_body_metadata.visibility.into_visibility();
@@ -102,82 +183,570 @@ impl PreDefineCodegenMethods for CodegenCtx<'_> {
             "get_or_declare_fn((name: {}, ret_ty: {:?}, param_tys: {:?}, linkage: {:?}, visibility: {:?}, calling_convention: {:?}, unnamed_addr: {:?})) delared",
             name, ret_ty, formal_param_tys, linkage, visibility, calling_convention, unnamed_addr
         );
-
-        self.instances.borrow_mut().insert(
-            lir_body_metadata.def_id,
-            AnyValueEnum::FunctionValue(fn_val),
-        );
     }
 }
 
 impl DefineCodegenMethods for CodegenCtx<'_> {
     /// For LLVM, we are able to reuse the generic implementation of `define_lir_body`
     /// provided in the `lir` module, as it is generic over the `BuilderMethods` trait.
-    fn define_body(&self, lir_body: &LirBody) {
-        lir::codegen_lir_body::<'_, '_, crate::builder::CodegenBuilder<'_, '_>>(self, lir_body);
+    fn define_body(&self, lir_body: &LirBody) -> CodegenResult<()> {
+        lir::codegen_lir_body::<'_, '_, crate::builder::CodegenBuilder<'_, '_>>(self, lir_body)
     }
 }
 
 impl LayoutOf for CodegenCtx<'_> {
-    fn layout_of(&self, lir_ty: LirTy) -> TyAndLayout<LirTy> {
+    fn layout_of(&self, lir_ty: LirTy) -> TyAndLayout<'_, LirTy> {
         self.lir_ctx.layout_of(lir_ty)
     }
 }
 
 impl FnAbiOf for CodegenCtx<'_> {
     #[instrument(level = "debug", skip(self, lir_ty_ctx))]
-    fn fn_abi_of(
-        &self,
-        lir_ty_ctx: &LirCtx,
+    fn fn_abi_of<'a>(
+        &'a self,
+        lir_ty_ctx: &'a LirCtx,
         lir_ret_and_args: &IdxVec<Local, LocalData>,
-    ) -> FnAbi<LirTy> {
-        let layout_ctx = LayoutCtx::new(lir_ty_ctx);
-        let argument_of = |ty: LirTy| -> ArgAbi<LirTy> {
-            let layout = layout_ctx.compute_layout(ty);
-            let pass_mode = match layout.backend_repr {
-                BackendRepr::Scalar(_) => PassMode::Direct,
-                BackendRepr::Memory => PassMode::Indirect,
+        conv: CallConv,
+    ) -> FnAbi<'a, LirTy> {
+        let sig: Vec<LirTy> = lir_ret_and_args
+            .iter()
+            .map(|local_data| local_data.ty.clone())
+            .collect();
+        let key = (sig, conv);
+        if let Some((ret_mode, arg_modes)) = self.fn_abis.borrow().get(&key) {
+            let arg_abi = |ty: &LirTy, mode: &PassMode| ArgAbi {
+                layout: lir_ty_ctx.layout_of(ty.clone()),
+                mode: mode.clone(),
+            };
+            return FnAbi {
+                ret: arg_abi(&key.0[0], ret_mode),
+                args: key.0[1..]
+                    .iter()
+                    .zip(arg_modes)
+                    .map(|(ty, mode)| arg_abi(ty, mode))
+                    .collect(),
+                is_variadic: false,
+                fixed_count: key.0.len() - 1,
+                conv,
             };
-            let mut arg = ArgAbi::new(layout, pass_mode);
-            if arg.layout.is_zst() {
-                arg.mode = PassMode::Ignore;
+        }
+
+        let argument_of = |ty: LirTy| -> ArgAbi<'a, LirTy> {
+            let layout = lir_ty_ctx.layout_of(ty);
+            let mut arg = ArgAbi::new(layout.clone(), PassMode::Ignore);
+            match layout.backend_repr {
+                _ if layout.is_zst() => {}
+                BackendRepr::Scalar(scalar) => arg.mode = PassMode::Direct(scalar_attrs(scalar)),
+                BackendRepr::ScalarPair(a, b) => {
+                    arg.mode = PassMode::Pair(scalar_attrs(a), scalar_attrs(b));
+                }
+                BackendRepr::Memory => arg.make_indirect(),
             }
             arg
         };
 
-        let ret_arg_abi = argument_of(lir_ret_and_args[RETURN_LOCAL].ty);
+        let ret_arg_abi = argument_of(lir_ret_and_args[RETURN_LOCAL].ty.clone());
         let arg_abis = lir_ret_and_args.as_slice()[RETURN_LOCAL.next()..]
             .iter()
-            .map(|local_data| argument_of(local_data.ty))
+            .map(|local_data| argument_of(local_data.ty.clone()))
             .collect();
 
-        FnAbi {
+        let mut fn_abi = FnAbi {
             ret: ret_arg_abi,
             args: arg_abis,
+            is_variadic: false,
+            fixed_count: lir_ret_and_args.len() - 1,
+            conv,
+        };
+        // All the functions follow the C ABI of the target, since they may be called
+        // from C code (or call it), directly or through function pointers.
+        fn_abi.adjust_for_foreign_abi(lir_ty_ctx, lir_ty_ctx.target());
+
+        // The indirect arguments point to copies owned by the callee, and the return
+        // place is not accessed by the callee through other pointers.
+        for arg_abi in fn_abi.args.iter_mut().chain([&mut fn_abi.ret]) {
+            if let PassMode::Indirect { attrs, .. } = &mut arg_abi.mode {
+                attrs.set(ArgAttribute::NO_ALIAS);
+            }
         }
+
+        let arg_modes = fn_abi
+            .args
+            .iter()
+            .map(|arg_abi| arg_abi.mode.clone())
+            .collect();
+        self.fn_abis
+            .borrow_mut()
+            .insert(key, (fn_abi.ret.mode.clone(), arg_modes));
+        fn_abi
+    }
+
+    fn fn_abi_of_variadic<'a>(
+        &'a self,
+        lir_ty_ctx: &'a LirCtx,
+        fixed_ret_and_args: &IdxVec<Local, LocalData>,
+        variadic_args: &[LirTy],
+        conv: CallConv,
+    ) -> FnAbi<'a, LirTy> {
+        let ret_and_args = fixed_ret_and_args
+            .iter()
+            .cloned()
+            .chain(variadic_args.iter().map(|ty| LocalData {
+                ty: ty.clone(),
+                mutable: false,
+                name: None,
+            }))
+            .collect();
+        let mut fn_abi = self.fn_abi_of(lir_ty_ctx, &ret_and_args, conv);
+        fn_abi.is_variadic = true;
+        fn_abi.fixed_count = fixed_ret_and_args.len() - 1;
+        fn_abi
     }
 }
 
 impl<'ll> CodegenCtx<'ll> {
-    fn declare_fn(
+    /// Returns the LLVM return type (`None` for `void`) and parameter types of a function
+    /// with the given ABI. A value returned indirectly becomes a hidden first parameter,
+    /// while a pair argument becomes two parameters. The variadic arguments of a call
+    /// are not parameters.
+    fn fn_abi_types(
         &self,
-        ret_ty: BasicTypeEnum<'ll>,
+        fn_abi: &FnAbi<LirTy>,
+    ) -> (Option<BasicTypeEnum<'ll>>, Vec<BasicMetadataTypeEnum<'ll>>) {
+        let ptr_ty = self.ll_context.ptr_type(AddressSpace::default());
+        let mut param_tys = Vec::with_capacity(fn_abi.args.len() + 1);
+
+        let ret_ty = match &fn_abi.ret.mode {
+            PassMode::Ignore => None,
+            PassMode::Direct(_) | PassMode::Pair(..) => {
+                Some(self.immediate_backend_type(&fn_abi.ret.layout))
+            }
+            PassMode::Cast(cast) => Some(self.cast_backend_type(cast)),
+            PassMode::Indirect { .. } => {
+                param_tys.push(ptr_ty.into());
+                None
+            }
+        };
+
+        for arg_abi in fn_abi.args[..fn_abi.fixed_count].iter() {
+            match &arg_abi.mode {
+                PassMode::Ignore => {}
+                PassMode::Direct(_) => param_tys.push(self.backend_type(&arg_abi.layout).into()),
+                PassMode::Pair(..) => {
+                    for (_, field_layout) in lir::scalar_pair_fields(self, &arg_abi.layout) {
+                        param_tys.push(self.backend_type(&field_layout).into());
+                    }
+                }
+                PassMode::Cast(cast) => param_tys.push(self.cast_backend_type(cast).into()),
+                PassMode::Indirect { .. } => param_tys.push(ptr_ty.into()),
+            }
+        }
+
+        (ret_ty, param_tys)
+    }
+
+    /// Returns the LLVM attributes of the return value and of the parameters of a
+    /// function with the given ABI (and `noreturn` if it returns `!`), with their
+    /// location. They are added both to the declaration of the function and to the
+    /// call sites.
+    pub fn fn_abi_attributes(&self, fn_abi: &FnAbi<LirTy>) -> Vec<(AttributeLoc, Attribute)> {
+        let mut attributes = vec![];
+        let mut param_idx = 0;
+        let add = |attributes: &mut Vec<_>, loc: AttributeLoc, attrs: &ArgAttributes| {
+            for attr in self.ll_attributes(attrs) {
+                attributes.push((loc, attr));
+            }
+        };
+
+        // A function returning `!` never returns.
+        if fn_abi.ret.layout.ty == LirTy::Never {
+            let kind_id = Attribute::get_named_enum_kind_id("noreturn");
+            let attr = self.ll_context.create_enum_attribute(kind_id, 0);
+            attributes.push((AttributeLoc::Function, attr));
+        }
+
+        match &fn_abi.ret.mode {
+            // A pair is returned as an aggregate, which cannot have attributes.
+            PassMode::Ignore | PassMode::Pair(..) | PassMode::Cast(_) => {}
+            PassMode::Direct(attrs) => add(&mut attributes, AttributeLoc::Return, attrs),
+            PassMode::Indirect { attrs, .. } => {
+                let ty = self.backend_type(&fn_abi.ret.layout);
+                attributes.push((
+                    AttributeLoc::Param(param_idx),
+                    self.type_attribute("sret", ty),
+                ));
+                add(&mut attributes, AttributeLoc::Param(param_idx), attrs);
+                param_idx += 1;
+            }
+        }
+        for arg_abi in fn_abi.args.iter() {
+            match &arg_abi.mode {
+                PassMode::Ignore => {}
+                PassMode::Direct(attrs) => {
+                    add(&mut attributes, AttributeLoc::Param(param_idx), attrs);
+                    param_idx += 1;
+                }
+                PassMode::Pair(a, b) => {
+                    add(&mut attributes, AttributeLoc::Param(param_idx), a);
+                    add(&mut attributes, AttributeLoc::Param(param_idx + 1), b);
+                    param_idx += 2;
+                }
+                PassMode::Cast(_) => param_idx += 1,
+                PassMode::Indirect { attrs, on_stack } => {
+                    if *on_stack {
+                        let ty = self.backend_type(&arg_abi.layout);
+                        attributes.push((
+                            AttributeLoc::Param(param_idx),
+                            self.type_attribute("byval", ty),
+                        ));
+                    }
+                    add(&mut attributes, AttributeLoc::Param(param_idx), attrs);
+                    param_idx += 1;
+                }
+            }
+        }
+        attributes
+    }
+
+    /// Returns the LLVM function attributes corresponding to the given ones.
+    // It corresponds to `from_fn_attrs` in rustc_codegen_llvm/src/attributes.rs
+    fn fn_attributes(&self, attrs: FnAttribute) -> Vec<Attribute> {
+        let mut ll_attrs = vec![];
+        for (attr, name) in [
+            (FnAttribute::INLINE, "inlinehint"),
+            (FnAttribute::ALWAYS_INLINE, "alwaysinline"),
+            (FnAttribute::NO_INLINE, "noinline"),
+            (FnAttribute::COLD, "cold"),
+            (FnAttribute::NAKED, "naked"),
+            (FnAttribute::NO_UNWIND, "nounwind"),
+            (FnAttribute::OPT_SIZE, "optsize"),
+        ] {
+            if attrs.contains(attr) {
+                let kind_id = Attribute::get_named_enum_kind_id(name);
+                ll_attrs.push(self.ll_context.create_enum_attribute(kind_id, 0));
+            }
+        }
+        ll_attrs
+    }
+
+    /// Returns the attribute `name` with the given type, such as `byval(<ty>)`.
+    fn type_attribute(&self, name: &str, ty: BasicTypeEnum<'ll>) -> Attribute {
+        let kind_id = Attribute::get_named_enum_kind_id(name);
+        self.ll_context
+            .create_type_attribute(kind_id, ty.as_any_type_enum())
+    }
+
+    /// Returns the LLVM attributes corresponding to the given ones.
+    // It corresponds to `ArgAttributesExt::apply_attrs_to_llfn` in rustc_codegen_llvm/src/abi.rs
+    fn ll_attributes(&self, attrs: &ArgAttributes) -> Vec<Attribute> {
+        let enum_attribute = |name: &str, val: u64| {
+            let kind_id = Attribute::get_named_enum_kind_id(name);
+            self.ll_context.create_enum_attribute(kind_id, val)
+        };
+
+        let mut ll_attrs = vec![];
+        for (attr, name) in [
+            (ArgAttribute::NO_ALIAS, "noalias"),
+            (ArgAttribute::NON_NULL, "nonnull"),
+            (ArgAttribute::READ_ONLY, "readonly"),
+            (ArgAttribute::NO_UNDEF, "noundef"),
+        ] {
+            if attrs.contains(attr) {
+                ll_attrs.push(enum_attribute(name, 0));
+            }
+        }
+        match attrs.arg_ext {
+            ArgExtension::None => {}
+            ArgExtension::Zext => ll_attrs.push(enum_attribute("zeroext", 0)),
+            ArgExtension::Sext => ll_attrs.push(enum_attribute("signext", 0)),
+        }
+        if attrs.pointee_size.bytes() != 0 {
+            ll_attrs.push(enum_attribute(
+                "dereferenceable",
+                attrs.pointee_size.bytes(),
+            ));
+        }
+        if let Some(align) = attrs.pointee_align {
+            ll_attrs.push(enum_attribute("align", align.bytes()));
+        }
+        ll_attrs
+    }
+
+    /// Returns the LLVM function type of a function with the given ABI.
+    pub fn fn_type_of_abi(&self, fn_abi: &FnAbi<LirTy>) -> FunctionType<'ll> {
+        let (ret_ty, param_tys) = self.fn_abi_types(fn_abi);
+        self.declare_fn(ret_ty, &param_tys, fn_abi.is_variadic)
+    }
+
+    pub(crate) fn declare_fn(
+        &self,
+        ret_ty: Option<BasicTypeEnum<'ll>>,
         param_tys: &[BasicMetadataTypeEnum<'ll>],
+        is_var_args: bool,
     ) -> FunctionType<'ll> {
+        let Some(ret_ty) = ret_ty else {
+            return self.ll_context.void_type().fn_type(param_tys, is_var_args);
+        };
         let fn_ty = match ret_ty {
-            BasicTypeEnum::IntType(int_type) => int_type.fn_type(param_tys, false),
-            BasicTypeEnum::ArrayType(array_type) => array_type.fn_type(param_tys, false),
-            BasicTypeEnum::FloatType(float_type) => float_type.fn_type(param_tys, false),
-            BasicTypeEnum::PointerType(pointer_type) => pointer_type.fn_type(param_tys, false),
-            BasicTypeEnum::StructType(struct_type) => struct_type.fn_type(param_tys, false),
-            BasicTypeEnum::VectorType(vector_type) => vector_type.fn_type(param_tys, false),
+            BasicTypeEnum::IntType(int_type) => int_type.fn_type(param_tys, is_var_args),
+            BasicTypeEnum::ArrayType(array_type) => array_type.fn_type(param_tys, is_var_args),
+            BasicTypeEnum::FloatType(float_type) => float_type.fn_type(param_tys, is_var_args),
+            BasicTypeEnum::PointerType(pointer_type) => {
+                pointer_type.fn_type(param_tys, is_var_args)
+            }
+            BasicTypeEnum::StructType(struct_type) => struct_type.fn_type(param_tys, is_var_args),
+            BasicTypeEnum::VectorType(vector_type) => vector_type.fn_type(param_tys, is_var_args),
             BasicTypeEnum::ScalableVectorType(scalable_vector_type) => {
-                scalable_vector_type.fn_type(param_tys, false)
+                scalable_vector_type.fn_type(param_tys, is_var_args)
             }
         };
 
         fn_ty
     }
+
+    /// Returns the panic handler, declaring it if it does not exist yet.
+    ///
+    /// The panic handler is an external function provided by the runtime:
+    /// ```c
+    /// _Noreturn void __tidec_panic(const char *msg, size_t len);
+    /// ```
+    /// It may unwind, so it is invoked when the panic unwinds to a cleanup block.
+    pub fn get_or_declare_panic_fn(&self) -> FunctionValue<'ll> {
+        const PANIC_FN_NAME: &str = "__tidec_panic";
+
+        if let Some(fn_val) = self.ll_module.get_function(PANIC_FN_NAME) {
+            return fn_val;
+        }
+
+        let ptr_ty = self.ll_context.ptr_type(AddressSpace::default());
+        let usize_ty = self.usize_type();
+        let fn_ty = self
+            .ll_context
+            .void_type()
+            .fn_type(&[ptr_ty.into(), usize_ty.into()], false);
+        let fn_val = self
+            .ll_module
+            .add_function(PANIC_FN_NAME, fn_ty, Some(Linkage::External));
+
+        for attr in ["noreturn", "cold"] {
+            let kind_id = Attribute::get_named_enum_kind_id(attr);
+            let attr = self.ll_context.create_enum_attribute(kind_id, 0);
+            fn_val.add_attribute(AttributeLoc::Function, attr);
+        }
+
+        fn_val
+    }
+
+    /// Returns the LLVM type of the given ADT.
+    ///
+    /// A struct is lowered to a named struct type. An enum is lowered to an array of
+    /// integers with the size and the alignment of the enum, since its variants
+    /// overlap; the fields of a variant are accessed through `adt_variant_type`.
+    pub fn adt_type(&self, adt_id: AdtId) -> BasicTypeEnum<'ll> {
+        let adt_def = self.lir_ctx.adt_def(adt_id);
+        if !adt_def.is_enum() {
+            return self.adt_variant_type(adt_id, VariantIdx::new(0)).into();
+        }
+
+        let layout = self.lir_ctx.layout_of(LirTy::Adt(adt_id));
+        let align = layout.align.abi.bytes().max(1);
+        self.ll_context
+            .custom_width_int_type((align * 8) as u32)
+            .array_type((layout.size.bytes() / align) as u32)
+            .into()
+    }
+
+    /// Returns the LLVM struct type of the given variant of an ADT, creating it if it
+    /// does not exist yet. Its fields are in memory order, and the variant of an enum
+    /// starts with the tag, unless the tag is a niche of the variant.
+    pub fn adt_variant_type(&self, adt_id: AdtId, variant_idx: VariantIdx) -> StructType<'ll> {
+        if let Some(ty) = self.adt_types.borrow().get(&(adt_id, variant_idx)) {
+            return *ty;
+        }
+
+        let adt_def = self.lir_ctx.adt_def(adt_id);
+        let variant = &adt_def.variants[variant_idx];
+        let name = if adt_def.is_enum() {
+            format!("{}::{}", adt_def.name, variant.name)
+        } else {
+            adt_def.name.clone()
+        };
+        // The struct is cached before its body is set, so that a (pointer to the)
+        // ADT can appear among its own fields.
+        let ty = self.ll_context.opaque_struct_type(&name);
+        self.adt_types
+            .borrow_mut()
+            .insert((adt_id, variant_idx), ty);
+        let layout = self.lir_ctx.layout_of(LirTy::Adt(adt_id));
+        let tag = (adt_def.is_enum() && layout.has_tag_field()).then(|| adt_def.discr_ty());
+        let field_tys = tag
+            .into_iter()
+            .chain(variant.fields.iter().cloned())
+            .collect::<Vec<_>>();
+        // The fields are in memory order, as the ones of the layout of the variant.
+        let fields = layout
+            .for_variant(variant_idx)
+            .fields
+            .index_by_increasing_offset()
+            .into_iter()
+            .map(|i| self.type_from_layout(&self.lir_ctx.layout_of(field_tys[i].clone())))
+            .collect::<Vec<_>>();
+        ty.set_body(&fields, false);
+        ty
+    }
+
+    /// Returns the integer type with the same size of a pointer in the target.
+    pub fn usize_type(&self) -> IntType<'ll> {
+        let pointer_size = self.lir_ctx.target().data_layout.pointer_size;
+        self.ll_context.custom_width_int_type(pointer_size as u32)
+    }
+
+    /// Returns the target machine of the module, which generates code at the
+    /// optimization level of the `LirCtx`.
+    pub fn target_machine(&self) -> CodegenResult<TargetMachine> {
+        assert_ne!(self.ll_module.get_triple(), TargetTriple::create(""));
+
+        Target::initialize_all(&InitializationConfig::default());
+        let triple = self.ll_module.get_triple();
+        // The code is tuned for the CPU of the host only if it runs there.
+        let is_host = TargetMachine::normalize_triple(&triple)
+            == TargetMachine::normalize_triple(&TargetMachine::get_default_triple());
+        let (cpu, features) = if is_host {
+            (
+                TargetMachine::get_host_cpu_name().to_string(),
+                TargetMachine::get_host_cpu_features().to_string(),
+            )
+        } else {
+            ("generic".to_string(), String::new())
+        };
+        let target_failed = |message: String| CodegenError::BackendFailed {
+            step: format!(
+                "create the target machine of `{}`",
+                triple.as_str().to_string_lossy()
+            ),
+            message,
+        };
+        // The target may be missing from the LLVM build (see `LLVM_TARGETS_TO_BUILD`).
+        let target = Target::from_triple(&triple).map_err(|err| {
+            target_failed(format!(
+                "the target is not supported by this build of LLVM ({})",
+                err
+            ))
+        })?;
+        let reloc_mode = reloc_model(&self.lir_ctx).map_or(RelocMode::Default, |reloc_model| {
+            reloc_model.into_reloc_mode()
+        });
+        let code_model = self
+            .lir_ctx
+            .code_model()
+            .map_or(CodeModel::Default, |code_model| {
+                code_model.into_code_model()
+            });
+        target
+            .create_target_machine(
+                &triple,
+                &cpu,
+                &features,
+                self.lir_ctx.opt_level().into_optimization_level(),
+                reloc_mode,
+                code_model,
+            )
+            .ok_or_else(|| target_failed("LLVM could not create it".to_string()))
+    }
+
+    /// Returns the given pipeline followed by the instrumentation passes of the
+    /// sanitizers of the `LirCtx`, which instrument the optimized code.
+    pub fn with_sanitizer_passes(&self, pass_pipeline: String) -> String {
+        let passes = self.lir_ctx.sanitizers().instrumentation_passes();
+        if passes.is_empty() {
+            return pass_pipeline;
+        }
+        format!("{},{}", pass_pipeline, passes.join(","))
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    /// Runs the given pipeline of the new pass manager on the module.
+    pub fn run_passes(
+        &self,
+        pass_pipeline: &str,
+        target_machine: &TargetMachine,
+    ) -> CodegenResult<()> {
+        debug!("Running the LLVM passes {}", pass_pipeline);
+        self.ll_module
+            .run_passes(pass_pipeline, target_machine, PassBuilderOptions::create())
+            .map_err(|err| CodegenError::BackendFailed {
+                step: format!("run the LLVM passes `{}`", pass_pipeline),
+                message: err.to_string(),
+            })
+    }
+
+    /// Returns the given failure of `run_passes` as an error of the pipeline given
+    /// with `--passes`, if any, which is then part of the pipeline that failed.
+    pub fn user_pass_pipeline_error(&self, err: CodegenError) -> CodegenError {
+        match (self.lir_ctx.pass_pipeline(), err) {
+            (Some(pipeline), CodegenError::BackendFailed { message, .. }) => {
+                CodegenError::InvalidPassPipeline {
+                    pipeline: pipeline.to_string(),
+                    message,
+                }
+            }
+            (_, err) => err,
+        }
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    /// Writes the module to the file of the emit kind of the `LirCtx`, at the path
+    /// chosen by the session for the module (see `Session::module_output_path`), and
+    /// returns it. The object of a linked unit is linked later.
+    pub fn write_output(&self, target_machine: &TargetMachine) -> CodegenResult<PathBuf> {
+        let module_name = self.ll_module.get_name().to_str().unwrap();
+        let emit_kind = *self.lir_ctx().emit_kind();
+        let path = self
+            .lir_ctx()
+            .sess()
+            .module_output_path(module_name, emit_kind.module_extension());
+        let file_type = match emit_kind {
+            EmitKind::Object | EmitKind::Link(_) => FileType::Object,
+            EmitKind::Assembly => FileType::Assembly,
+        };
+        let write_failed = |message: String| CodegenError::WriteFailed {
+            path: path.clone(),
+            message,
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|err| write_failed(err.to_string()))?;
+        }
+        target_machine
+            .write_to_file(&self.ll_module, file_type, &path)
+            .map_err(|err| write_failed(err.to_string()))?;
+        debug!("Wrote the module {} to {}", module_name, path.display());
+        Ok(path)
+    }
+
+    /// Runs the LLVM verifier on the module. If the module is invalid, the defined
+    /// bodies are verified one by one, so that the error points to the first invalid
+    /// one. The error holds no body if the invalid code is outside of the functions
+    /// (e.g., in a global or in the debug info).
+    // It corresponds to the `verify` pass that rustc runs with `-Z verify-llvm-ir`
+    pub fn verify_module(&self) -> CodegenResult<()> {
+        let Err(message) = self.ll_module.verify() else {
+            return Ok(());
+        };
+        let body = self
+            .lir_ctx
+            .defs()
+            .filter(|lir_body| !lir_body.is_declaration())
+            .find(|lir_body| {
+                self.get_fn(&lir_body.metadata)
+                    .is_some_and(|fn_value| !fn_value.verify(false))
+            })
+            .map(|lir_body| (lir_body.metadata.def_id, lir_body.metadata.name.clone()));
+        Err(CodegenError::InvalidModule {
+            unit_name: self.ll_module.get_name().to_string_lossy().into_owned(),
+            body,
+            message: message.to_string(),
+        })
+    }
 }
 
 impl<'ll> CodegenMethods<'ll> for CodegenCtx<'ll> {
@@ -207,12 +776,36 @@ impl<'ll> CodegenMethods<'ll> for CodegenCtx<'ll> {
             let data_layout_string = internal_target.data_layout_string();
             ll_module.set_data_layout(&TargetData::create(&data_layout_string).get_data_layout());
         }
+        {
+            // The module flags tell the code generator (and the linked modules) how the
+            // code is addressed, as the relocation model and the code model of the
+            // target machine.
+            let i32_ty = ll_context.i32_type();
+            if let Some(reloc_model) = reloc_model(&lir_ctx) {
+                let (pic_level, pie_level) = reloc_model.pic_and_pie_levels();
+                for (key, level) in [("PIC Level", pic_level), ("PIE Level", pie_level)] {
+                    if let Some(level) = level {
+                        let level = i32_ty.const_int(level as u64, false);
+                        ll_module.add_basic_value_flag(key, FlagBehavior::Error, level);
+                    }
+                }
+            }
+            if let Some(code_model) = lir_ctx.code_model() {
+                let code_model = i32_ty.const_int(code_model.module_flag_value(), false);
+                ll_module.add_basic_value_flag("Code Model", FlagBehavior::Error, code_model);
+            }
+        }
 
         CodegenCtx {
             ll_context,
             ll_module,
             lir_ctx,
-            instances: RefCell::new(HashMap::new()),
+            const_str_cache: RefCell::new(HashMap::new()),
+            adt_types: RefCell::new(HashMap::new()),
+            alloc_globals: RefCell::new(HashMap::new()),
+            debug_ctx: OnceCell::new(),
+            tbaa_tags: RefCell::new(HashMap::new()),
+            fn_abis: RefCell::new(HashMap::new()),
         }
     }
 
@@ -220,16 +813,40 @@ impl<'ll> CodegenMethods<'ll> for CodegenCtx<'ll> {
         &self.lir_ctx
     }
 
-    #[instrument(skip(self, lir_unit))]
+    #[instrument(skip(self))]
     // TODO: Move as a method of `CodegenCtx`?
-    fn compile_lir_unit<'a, B: BuilderMethods<'a, 'll>>(&self, lir_unit: LirUnit) {
+    fn compile_lir_unit<
+        'a,
+        B: DebugInfoBuilderMethods<'a, 'll>
+            + MetadataMethods<'a, 'll>
+            + AsmBuilderMethods<'a, 'll>
+            + IntrinsicCallBuilderMethods<'a, 'll>,
+    >(
+        &self,
+    ) -> CodegenResult<()> {
+        let debuginfo = self.lir_ctx.debuginfo() != DebugInfo::None;
+        if debuginfo {
+            let unit_name = self.ll_module.get_name().to_string_lossy();
+            CodegenBuilder::create_compile_unit(self, &unit_name);
+        }
+
         // Predefine the functions. That is, create the function declarations.
-        for lir_body in &lir_unit.bodies {
-            self.predefine_body(&lir_body.metadata, &lir_body.ret_and_args);
+        for lir_body in self.lir_ctx.defs() {
+            if lir_body.is_declaration() {
+                let metadata = declaration_metadata(&lir_body.metadata);
+                self.predefine_body(&metadata, &lir_body.ret_and_args);
+            } else {
+                self.predefine_body(&lir_body.metadata, &lir_body.ret_and_args);
+            }
         }
 
-        // Now that all functions are pre-defined, we can compile the bodies.
-        for lir_body in &lir_unit.bodies {
+        // Now that all functions are pre-defined, we can compile the bodies. The
+        // declarations are defined in another module (e.g., another codegen unit).
+        for lir_body in self
+            .lir_ctx
+            .defs()
+            .filter(|lir_body| !lir_body.is_declaration())
+        {
             // It corresponds to:
             // ```rust
             // for &(mono_item, item_data) in &mono_items {
@@ -238,61 +855,36 @@ impl<'ll> CodegenMethods<'ll> for CodegenCtx<'ll> {
             // ```
             // in rustc_codegen_llvm/src/base.rs
             // lir::define_lir_body::<B>(ctx, lir_body);
-            self.define_body(lir_body);
+            self.define_body(lir_body)?;
         }
 
+        if debuginfo {
+            CodegenBuilder::finalize_debug_info(self);
+        }
         debug!("\n{}", self.ll_module.print_to_string().to_string());
+        self.verify_module()
     }
 
-    fn emit_output(&self) {
-        assert_ne!(self.ll_module.get_triple(), TargetTriple::create(""));
-
-        let target_machine = || {
-            Target::initialize_all(&InitializationConfig::default());
-            let triple = self.ll_module.get_triple();
-            let features = TargetMachine::get_host_cpu_features().to_string();
-            let cpu = TargetMachine::get_host_cpu_name().to_string();
-            let target = Target::from_triple(&triple).expect("Failed to get target from triple");
-            target
-                .create_target_machine(
-                    &triple,
-                    &cpu,
-                    &features,
-                    OptimizationLevel::Default,
-                    RelocMode::Default,
-                    CodeModel::Default,
-                )
-                .expect("Failed to create target machine")
+    fn emit_output(&self) -> CodegenResult<CompiledModule> {
+        let target_machine = self.target_machine()?;
+        let pass_pipeline = match self.lir_ctx.pass_pipeline() {
+            Some(pass_pipeline) => pass_pipeline.to_string(),
+            None => self.lir_ctx.opt_level().default_pass_pipeline(),
         };
-
-        match self.lir_ctx().emit_kind() {
-            EmitKind::Object => {
-                let target_machine = target_machine();
-                let obj_path = format!("{}.o", self.ll_module.get_name().to_str().unwrap());
-                target_machine
-                    .write_to_file(&self.ll_module, FileType::Object, Path::new(&obj_path))
-                    .expect("Failed to write object file");
-                debug!("Wrote object file to {}", obj_path);
-            }
-            EmitKind::Assembly => {
-                let target_machine = target_machine();
-                let asm_path = format!("{}.s", self.ll_module.get_name().to_str().unwrap());
-                target_machine
-                    .write_to_file(&self.ll_module, FileType::Assembly, Path::new(&asm_path))
-                    .expect("Failed to write assembly file");
-                debug!("Wrote assembly file to {}", asm_path);
-            }
-        }
+        let pass_pipeline = self.with_sanitizer_passes(pass_pipeline);
+        self.run_passes(&pass_pipeline, &target_machine)
+            .map_err(|err| self.user_pass_pipeline_error(err))?;
+        let path = self.write_output(&target_machine)?;
+        Ok(CompiledModule::new(
+            self.ll_module.get_name().to_string_lossy().into_owned(),
+            *self.lir_ctx.emit_kind(),
+            path,
+            self.lir_ctx.defs(),
+        ))
     }
 
     fn get_fn(&self, lir_body_metadata: &LirBodyMetadata) -> Option<FunctionValue<'ll>> {
         let name = lir_body_metadata.name.as_str();
-
-        if let Some(instance) = self.instances.borrow().get(&lir_body_metadata.def_id) {
-            debug!("get_fn(name: {}) found in instances", name);
-            return Some((*instance).into_function_value());
-        }
-
         if let Some(f) = self.ll_module.get_function(name) {
             debug!("get_fn(name: {}) found in module", name);
             return Some(f);
@@ -302,6 +894,65 @@ impl<'ll> CodegenMethods<'ll> for CodegenCtx<'ll> {
         None
     }
 
+    /// The functions are looked up in the symbol table of the module, by the interned
+    /// name of their `DefId` (see `LirCtx::def_symbol`).
+    fn get_fn_by_def_id(&self, def_id: DefId) -> Option<FunctionValue<'ll>> {
+        let symbol = self.lir_ctx.def_symbol(def_id)?;
+        if let Some(fn_val) = self.ll_module.get_function(self.lir_ctx.symbol_str(symbol)) {
+            return Some(fn_val);
+        }
+        // The functions of the other units are declared when they are first used.
+        let import = self.lir_ctx.import(def_id)?;
+        debug!(
+            "Declaring {} imported from {}",
+            import.declaration.metadata.name, import.unit_name
+        );
+        Some(self.get_or_define_fn(
+            &declaration_metadata(&import.declaration.metadata),
+            &import.declaration.ret_and_args,
+        ))
+    }
+
+    fn get_fn_addr(&self, def_id: DefId) -> BasicValueEnum<'ll> {
+        self.get_fn_by_def_id(def_id)
+            .unwrap_or_else(|| panic!("Function {:?} is not declared", def_id))
+            .as_global_value()
+            .as_pointer_value()
+            .into()
+    }
+
+    /// The personality function is declared as `i32 (...)`, since its signature is
+    /// only known by the runtime that provides it (e.g., `__gxx_personality_v0`).
+    fn set_personality_fn(&self, fn_value: FunctionValue<'ll>, personality: &str) {
+        let personality_fn = self.ll_module.get_function(personality).unwrap_or_else(|| {
+            let fn_ty = self.ll_context.i32_type().fn_type(&[], true);
+            self.ll_module
+                .add_function(personality, fn_ty, Some(Linkage::External))
+        });
+        fn_value.set_personality_function(personality_fn);
+    }
+
+    fn alloc_addr(&self, alloc_id: AllocId) -> BasicValueEnum<'ll> {
+        if let Some(global) = self.alloc_globals.borrow().get(&alloc_id) {
+            return global.as_pointer_value().into();
+        }
+
+        let alloc = self.lir_ctx.alloc(alloc_id);
+        let bytes = self.ll_context.const_string(&alloc.bytes, false);
+        let global = self.ll_module.add_global(bytes.get_type(), None, "alloc");
+        global.set_initializer(&bytes);
+        global.set_constant(!alloc.mutable);
+        global.set_linkage(Linkage::Private);
+        // The address of a mutable allocation is observable through its writes.
+        if !alloc.mutable {
+            global.set_unnamed_address(UnnamedAddress::Global);
+        }
+        global.set_alignment(alloc.align.bytes() as u32);
+
+        self.alloc_globals.borrow_mut().insert(alloc_id, global);
+        global.as_pointer_value().into()
+    }
+
     /// TODO(bruzzone): We expect this function returns a function value.
     fn get_or_define_fn(
         &self,
@@ -325,3 +976,50 @@ impl<'ll> CodegenMethods<'ll> for CodegenCtx<'ll> {
         fn_val
     }
 }
+
+/// Returns the metadata of the declaration of a function defined in another module.
+/// A declaration can only have an external linkage (e.g., a declaration of a
+/// `linkonce_odr` body of another codegen unit).
+fn declaration_metadata(lir_body_metadata: &LirBodyMetadata) -> LirBodyMetadata {
+    let linkage = match lir_body_metadata.linkage {
+        LirLinkage::ExternWeak => LirLinkage::ExternWeak,
+        _ => LirLinkage::External,
+    };
+    LirBodyMetadata {
+        linkage,
+        ..lir_body_metadata.clone()
+    }
+}
+
+/// Returns the attributes of a scalar argument, given by its type and its valid range.
+///
+/// As in the C ABIs, the integers smaller than 32 bits (including the booleans) are
+/// extended by the caller.
+// It corresponds to `adjust_for_rust_scalar` in rustc_ty_utils/src/abi.rs
+fn scalar_attrs(scalar: Scalar) -> ArgAttributes {
+    let mut attrs = ArgAttributes::new();
+    match scalar.primitive {
+        Primitive::I8 | Primitive::I16 => {
+            attrs.ext(ArgExtension::Sext);
+        }
+        Primitive::U8 | Primitive::U16 => {
+            attrs.ext(ArgExtension::Zext);
+        }
+        Primitive::Pointer(_) if !scalar.valid_range.contains(0) => {
+            attrs.set(ArgAttribute::NON_NULL);
+        }
+        _ => {}
+    }
+    attrs
+}
+
+/// Returns the relocation model of the code generated for the given context. The
+/// objects linked into an output are position independent by default, as the C
+/// compilers link position-independent executables by default.
+fn reloc_model(lir_ctx: &LirCtx) -> Option<RelocModel> {
+    match (lir_ctx.reloc_model(), lir_ctx.emit_kind()) {
+        (Some(reloc_model), _) => Some(reloc_model),
+        (None, EmitKind::Link(_)) => Some(RelocModel::Pic),
+        (None, EmitKind::Object | EmitKind::Assembly) => None,
+    }
+}
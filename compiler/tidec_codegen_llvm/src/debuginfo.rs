@@ -0,0 +1,330 @@
+//! The debug info of the LLVM modules, emitted as DWARF (see `DebugInfoBuilderMethods`).
+//!
+//! A body parsed from a source file is located in it by its spans (see
+//! `tidec_span::source_map`). Otherwise (e.g., if it has been built by hand), the
+//! source file of a body `foo` is `foo.lir`, that is, its textual representation (e.g.,
+//! as dumped by `--dump-lir`), and the lines of its statements are given by `BodyLines`.
+// It corresponds to rustc_codegen_llvm/src/debuginfo/mod.rs
+
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use inkwell::debug_info::{
+    debug_metadata_version, AsDIScope, DICompileUnit, DIFile, DIFlags, DIFlagsConstants,
+    DILocalVariable, DILocation, DIScope, DIType, DWARFEmissionKind, DWARFSourceLanguage,
+    DebugInfoBuilder,
+};
+use inkwell::module::FlagBehavior;
+use tidec_abi::layout::TyAndLayout;
+use tidec_codegen_ssa::traits::{DebugInfoBuilderMethods, LayoutOf};
+use tidec_lir::lir::{Linkage, LirBody, LirUnit, LirUnitMetadata};
+use tidec_lir::pretty::{BodyLines, LirPrinter};
+use tidec_lir::syntax::{LirTy, RETURN_LOCAL};
+use tidec_session::config::{DebugInfo, OptLevel};
+use tidec_utils::index_vec::IdxVec;
+use tracing::instrument;
+
+use crate::builder::CodegenBuilder;
+use crate::context::CodegenCtx;
+
+/// The DWARF version emitted if `Options::dwarf_version` is not given.
+const DEFAULT_DWARF_VERSION: u32 = 4;
+
+// The DWARF encodings of the base types (see `DW_ATE_*` in the DWARF specification).
+const DW_ATE_ADDRESS: u32 = 0x01;
+const DW_ATE_BOOLEAN: u32 = 0x02;
+const DW_ATE_FLOAT: u32 = 0x04;
+const DW_ATE_SIGNED: u32 = 0x05;
+const DW_ATE_UNSIGNED: u32 = 0x08;
+
+/// The state of the debug info of a module, created with its compile unit.
+// It corresponds to `CodegenUnitDebugContext` in rustc_codegen_llvm/src/debuginfo/mod.rs
+pub struct DebugCtx<'ll> {
+    pub di_builder: DebugInfoBuilder<'ll>,
+    pub compile_unit: DICompileUnit<'ll>,
+    /// The source file of the body being compiled, as the bodies of a module are
+    /// compiled one at a time.
+    pub body_file: Cell<Option<DIFile<'ll>>>,
+    /// A cache of the debug info types of the LIR types.
+    pub types: RefCell<HashMap<LirTy, DIType<'ll>>>,
+}
+
+impl<'ll> CodegenCtx<'ll> {
+    /// Returns the debug info state of the module. It panics if the compile unit has
+    /// not been created (see `DebugInfoBuilderMethods::create_compile_unit`).
+    pub fn debug_ctx(&self) -> &DebugCtx<'ll> {
+        self.debug_ctx
+            .get()
+            .expect("The compile unit of the debug info has not been created")
+    }
+
+    /// Returns the debug info type of the given type. The scalars and the pointers are
+    /// base types, while the other types are described by their size and alignment.
+    // It corresponds to `type_di_node` in rustc_codegen_llvm/src/debuginfo/metadata.rs
+    // TODO(bruzzone): describe the fields of the aggregates and the variants of the enums.
+    fn di_type(&self, ty_layout: &TyAndLayout<LirTy>) -> DIType<'ll> {
+        let debug_ctx = self.debug_ctx();
+        if let Some(di_type) = debug_ctx.types.borrow().get(&ty_layout.ty) {
+            return *di_type;
+        }
+
+        let printer = LirPrinter::new(
+            &self.lir_ctx,
+            &LirUnit {
+                metadata: LirUnitMetadata {
+                    unit_name: String::new(),
+                },
+                bodies: IdxVec::new(),
+                imports: BTreeMap::new(),
+            },
+        );
+        let name = printer.ty_to_string(&ty_layout.ty);
+        let encoding = match &ty_layout.ty {
+            LirTy::Bool => Some(DW_ATE_BOOLEAN),
+            LirTy::I8 | LirTy::I16 | LirTy::I32 | LirTy::I64 | LirTy::I128 => Some(DW_ATE_SIGNED),
+            LirTy::U8 | LirTy::U16 | LirTy::U32 | LirTy::U64 | LirTy::U128 => Some(DW_ATE_UNSIGNED),
+            LirTy::F16 | LirTy::F32 | LirTy::F64 | LirTy::F128 => Some(DW_ATE_FLOAT),
+            LirTy::Ptr(..) | LirTy::FnPtr(_) => Some(DW_ATE_ADDRESS),
+            _ => None,
+        };
+        let size_in_bits = ty_layout.size.bits();
+        let di_type = match encoding {
+            Some(encoding) => debug_ctx
+                .di_builder
+                .create_basic_type(&name, size_in_bits, encoding, DIFlags::ZERO)
+                .unwrap_or_else(|err| panic!("Failed to describe the type {}: {}", name, err))
+                .as_type(),
+            None => debug_ctx
+                .di_builder
+                .create_struct_type(
+                    debug_ctx.compile_unit.as_debug_info_scope(),
+                    &name,
+                    debug_ctx.compile_unit.get_file(),
+                    0,
+                    size_in_bits,
+                    (ty_layout.align.abi.bytes() * 8) as u32,
+                    DIFlags::ZERO,
+                    None,
+                    &[],
+                    0,
+                    None,
+                    &name,
+                )
+                .as_type(),
+        };
+        debug_ctx
+            .types
+            .borrow_mut()
+            .insert(ty_layout.ty.clone(), di_type);
+        di_type
+    }
+}
+
+impl<'a, 'll> DebugInfoBuilderMethods<'a, 'll> for CodegenBuilder<'a, 'll> {
+    type DIScope = DIScope<'ll>;
+    type DILocation = DILocation<'ll>;
+    type DIVariable = DILocalVariable<'ll>;
+
+    #[instrument(level = "debug", skip(ctx))]
+    // It corresponds to `compile_unit_metadata` in rustc_codegen_llvm/src/debuginfo/metadata.rs
+    // TODO(bruzzone): emit CodeView instead of DWARF for the `msvc` targets.
+    fn create_compile_unit(ctx: &'a CodegenCtx<'ll>, unit_name: &str) {
+        let lir_ctx = &ctx.lir_ctx;
+        let emission_kind = match lir_ctx.debuginfo() {
+            DebugInfo::None => DWARFEmissionKind::None,
+            DebugInfo::LineTablesOnly => DWARFEmissionKind::LineTablesOnly,
+            DebugInfo::Full => DWARFEmissionKind::Full,
+        };
+        let (di_builder, compile_unit) = ctx.ll_module.create_debug_info_builder(
+            true,
+            // DWARF has no code for the LIR, and C is the closest language to it.
+            DWARFSourceLanguage::C,
+            &format!("{}.lir", unit_name),
+            ".",
+            concat!("tidec ", env!("CARGO_PKG_VERSION")),
+            lir_ctx.opt_level() != OptLevel::No,
+            "",
+            0,
+            // The debug info is never split into a `.dwo` file (see `SplitDebugInfo`).
+            "",
+            emission_kind,
+            0,
+            false,
+            false,
+            "",
+            "",
+        );
+
+        let i32_type = ctx.ll_context.i32_type();
+        ctx.ll_module.add_basic_value_flag(
+            "Debug Info Version",
+            FlagBehavior::Warning,
+            i32_type.const_int(debug_metadata_version() as u64, false),
+        );
+        ctx.ll_module.add_basic_value_flag(
+            "Dwarf Version",
+            FlagBehavior::Warning,
+            i32_type.const_int(
+                u64::from(lir_ctx.dwarf_version().unwrap_or(DEFAULT_DWARF_VERSION)),
+                false,
+            ),
+        );
+
+        let debug_ctx = DebugCtx {
+            di_builder,
+            compile_unit,
+            body_file: Cell::new(None),
+            types: RefCell::new(HashMap::new()),
+        };
+        if ctx.debug_ctx.set(debug_ctx).is_err() {
+            panic!("The compile unit of {} is created twice", unit_name);
+        }
+    }
+
+    fn finalize_debug_info(ctx: &'a CodegenCtx<'ll>) {
+        ctx.debug_ctx().di_builder.finalize();
+    }
+
+    #[instrument(level = "debug", skip(ctx, lir_body), fields(body = %lir_body.metadata.name))]
+    // It corresponds to `dbg_scope_fn` in rustc_codegen_llvm/src/debuginfo/mod.rs
+    fn create_function_scope(
+        ctx: &'a CodegenCtx<'ll>,
+        lir_body: &LirBody,
+        fn_value: Self::FunctionValue,
+    ) -> DIScope<'ll> {
+        let debug_ctx = ctx.debug_ctx();
+        let metadata = &lir_body.metadata;
+        let source_map = ctx.lir_ctx.source_map();
+        let (file, fn_line) = match source_map.lookup(metadata.span) {
+            Some(loc) => {
+                let path = Path::new(&source_map.file(loc.file).name);
+                let file_name = path
+                    .file_name()
+                    .map_or(path.as_os_str(), |file_name| file_name)
+                    .to_string_lossy();
+                let directory = match path.parent() {
+                    Some(parent) if !parent.as_os_str().is_empty() => parent.to_string_lossy(),
+                    _ => ".".into(),
+                };
+                let file = debug_ctx.di_builder.create_file(&file_name, &directory);
+                (file, loc.line)
+            }
+            None => {
+                let file = debug_ctx
+                    .di_builder
+                    .create_file(&format!("{}.lir", metadata.name), ".");
+                (file, BodyLines::new(&ctx.lir_ctx, lir_body).fn_line())
+            }
+        };
+        debug_ctx.body_file.set(Some(file));
+
+        // The types of the signature are only described with the full debug info.
+        let (ret_type, param_types) = if ctx.lir_ctx.debuginfo() == DebugInfo::Full {
+            let ret_ty = &lir_body.ret_and_args[RETURN_LOCAL].ty;
+            let ret_type =
+                (*ret_ty != LirTy::unit()).then(|| ctx.di_type(&ctx.layout_of(ret_ty.clone())));
+            let param_types = lir_body.ret_and_args.as_slice()[RETURN_LOCAL.next()..]
+                .iter()
+                .map(|local_data| ctx.di_type(&ctx.layout_of(local_data.ty.clone())))
+                .collect();
+            (ret_type, param_types)
+        } else {
+            (None, vec![])
+        };
+        let subroutine_type = debug_ctx.di_builder.create_subroutine_type(
+            file,
+            ret_type,
+            &param_types,
+            DIFlags::ZERO,
+        );
+
+        let subprogram = debug_ctx.di_builder.create_function(
+            debug_ctx.compile_unit.as_debug_info_scope(),
+            &metadata.name,
+            None,
+            file,
+            fn_line,
+            subroutine_type,
+            matches!(metadata.linkage, Linkage::Private | Linkage::Internal),
+            true,
+            fn_line,
+            DIFlags::ZERO,
+            ctx.lir_ctx.opt_level() != OptLevel::No,
+        );
+        fn_value.set_subprogram(subprogram);
+        subprogram.as_debug_info_scope()
+    }
+
+    fn create_debug_loc(
+        ctx: &'a CodegenCtx<'ll>,
+        scope: DIScope<'ll>,
+        line: u32,
+        col: u32,
+    ) -> DILocation<'ll> {
+        ctx.debug_ctx()
+            .di_builder
+            .create_debug_location(ctx.ll_context, line, col, scope, None)
+    }
+
+    fn create_local_variable(
+        ctx: &'a CodegenCtx<'ll>,
+        scope: DIScope<'ll>,
+        name: &str,
+        ty_layout: &TyAndLayout<LirTy>,
+        arg_no: Option<u32>,
+        line: u32,
+    ) -> DILocalVariable<'ll> {
+        let debug_ctx = ctx.debug_ctx();
+        let file = debug_ctx
+            .body_file
+            .get()
+            .expect("The scope of the body has not been created");
+        let di_type = ctx.di_type(ty_layout);
+        match arg_no {
+            Some(arg_no) => debug_ctx.di_builder.create_parameter_variable(
+                scope,
+                name,
+                arg_no,
+                file,
+                line,
+                di_type,
+                true,
+                DIFlags::ZERO,
+            ),
+            None => debug_ctx.di_builder.create_auto_variable(
+                scope,
+                name,
+                file,
+                line,
+                di_type,
+                true,
+                DIFlags::ZERO,
+                (ty_layout.align.abi.bytes() * 8) as u32,
+            ),
+        }
+    }
+
+    fn set_debug_loc(&mut self, loc: DILocation<'ll>) {
+        self.ll_builder.set_current_debug_location(loc);
+    }
+
+    fn declare_local(
+        &mut self,
+        variable: DILocalVariable<'ll>,
+        ptr: Self::Value,
+        loc: DILocation<'ll>,
+    ) {
+        let block = self
+            .ll_builder
+            .get_insert_block()
+            .expect("The builder is not positioned in a block");
+        self.debug_ctx().di_builder.insert_declare_at_end(
+            ptr.into_pointer_value(),
+            Some(variable),
+            None,
+            loc,
+            block,
+        );
+    }
+}
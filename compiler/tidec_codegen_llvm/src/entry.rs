@@ -1,16 +1,257 @@
-use crate::{builder::CodegenBuilder, context::CodegenCtx};
+use std::error::Error;
+use std::num::NonZero;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::{builder::CodegenBuilder, context::CodegenCtx, lto::llvm_codegen_lir_units_lto};
 use inkwell::context::Context;
-use tidec_codegen_ssa::traits::CodegenMethods;
+use tidec_abi::target::{BackendKind, LinkOutputKind};
+use tidec_codegen_ssa::{
+    backend::{Artifact, ArtifactKind, BackendOutput, CodegenBackendDriver, CompiledModule},
+    error::CodegenError,
+    traits::CodegenMethods,
+};
+use tidec_incremental::{cache::IncrementalCache, fingerprint::UnitFingerprint};
+use tidec_link::linker::{output_filename, Linker};
 use tidec_lir::lir::{LirCtx, LirUnit};
-use tracing::instrument;
+use tidec_monomorphize::partitioning::partition;
+use tidec_session::config::{EmitKind, Lto};
+use tracing::{debug, instrument, warn};
 
 #[instrument(level = "info", skip(lir_ctx, lir_unit), fields(unit = %lir_unit.metadata.unit_name))]
 // TODO(bruzzone): try to move it to `tidec_codegen_ssa`
-pub fn llvm_codegen_lir_unit(lir_ctx: LirCtx, lir_unit: LirUnit) {
+pub fn llvm_codegen_lir_unit(
+    lir_ctx: LirCtx,
+    lir_unit: LirUnit,
+) -> Result<CompiledModule, CodegenError> {
     let ll_context = Context::create();
-    let ll_module = ll_context.create_module(&lir_unit.metadata.unit_name);
-    let ctx = CodegenCtx::new(lir_ctx, &ll_context, ll_module);
+    codegen_lir_unit_in(&ll_context, lir_ctx, lir_unit)
+}
+
+/// Codegens the given unit to a module of the given LLVM context, and returns the
+/// compiled module. It fails if the module is invalid.
+fn codegen_lir_unit_in(
+    ll_context: &Context,
+    mut lir_ctx: LirCtx,
+    lir_unit: LirUnit,
+) -> Result<CompiledModule, CodegenError> {
+    let lir_unit_metadata = lir_ctx.define_unit(lir_unit);
+    let ll_module = ll_context.create_module(&lir_unit_metadata.unit_name);
+    let ctx = CodegenCtx::new(lir_ctx, ll_context, ll_module);
+
+    ctx.compile_lir_unit::<CodegenBuilder>()?;
+    ctx.emit_output()
+}
+
+/// Codegens the given codegen unit like `codegen_lir_unit_in`, unless the
+/// incremental compilation cache is enabled and holds its output, which is then
+/// reused. A codegened output is stored in the cache for the next runs.
+fn codegen_cgu_in(
+    ll_context: &Context,
+    lir_ctx: LirCtx,
+    cgu: LirUnit,
+) -> Result<CompiledModule, CodegenError> {
+    let Some(incremental_dir) = lir_ctx.incremental_dir() else {
+        return codegen_lir_unit_in(ll_context, lir_ctx, cgu);
+    };
+    let cache = IncrementalCache::new(incremental_dir);
+    let fingerprint = UnitFingerprint::new(&lir_ctx, &cgu);
+    let output = lir_ctx.sess().module_output_path(
+        &cgu.metadata.unit_name,
+        lir_ctx.emit_kind().module_extension(),
+    );
+    if cache.try_reuse(&fingerprint, &output) {
+        return Ok(CompiledModule::new(
+            cgu.metadata.unit_name.clone(),
+            *lir_ctx.emit_kind(),
+            output,
+            &cgu.bodies,
+        ));
+    }
+
+    let module = codegen_lir_unit_in(ll_context, lir_ctx, cgu)?;
+    let Some(output) = module.output() else {
+        return Ok(module);
+    };
+    if let Err(err) = cache.save(&fingerprint, output) {
+        // The cache is only an optimization, so the compilation goes on without it.
+        warn!("Could not cache {}: {}", output.display(), err);
+    }
+    Ok(module)
+}
+
+#[instrument(level = "info", skip(lir_ctx, lir_unit), fields(unit = %lir_unit.metadata.unit_name))]
+/// Splits the given unit into `LirCtx::codegen_units` codegen units, and compiles them
+/// on a pool of threads. Each thread has its own LLVM context, as a context cannot be
+/// shared between threads. It returns the compiled modules, in the order of the
+/// codegen units. With the incremental compilation cache, the codegen units which did not
+/// change are reused instead (see `tidec_incremental`). It fails with the error of the
+/// first codegen unit which failed, once all the threads are done.
+// It corresponds to the coordinator of rustc_codegen_ssa/src/back/write.rs
+pub fn llvm_codegen_lir_unit_parallel(
+    lir_ctx: LirCtx,
+    lir_unit: LirUnit,
+) -> Result<Vec<CompiledModule>, CodegenError> {
+    let mut cgus = partition(&lir_ctx, lir_unit, lir_ctx.codegen_units());
+    if cgus.len() <= 1 {
+        return match cgus.pop() {
+            Some(cgu) => Ok(vec![codegen_cgu_in(&Context::create(), lir_ctx, cgu)?]),
+            None => Ok(vec![]),
+        };
+    }
+
+    // The contexts are forked here, as a `LirCtx` cannot be shared between threads.
+    let jobs: Vec<(usize, LirCtx, LirUnit)> = cgus
+        .into_iter()
+        .enumerate()
+        .map(|(idx, cgu)| (idx, lir_ctx.fork(), cgu))
+        .collect();
+    let threads = std::thread::available_parallelism()
+        .map_or(1, NonZero::get)
+        .min(jobs.len());
+    debug!(
+        "Compiling {} codegen units on {} threads",
+        jobs.len(),
+        threads
+    );
+    let jobs = Mutex::new(jobs);
+
+    let mut outputs: Vec<(usize, Result<CompiledModule, CodegenError>)> =
+        std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let ll_context = Context::create();
+                        let mut outputs = Vec::new();
+                        loop {
+                            let job = jobs.lock().unwrap().pop();
+                            let Some((idx, lir_ctx, cgu)) = job else {
+                                break;
+                            };
+                            outputs.push((idx, codegen_cgu_in(&ll_context, lir_ctx, cgu)));
+                        }
+                        outputs
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| {
+                    worker
+                        .join()
+                        .unwrap_or_else(|err| std::panic::resume_unwind(err))
+                })
+                .collect()
+        });
+    outputs.sort_by_key(|(idx, _)| *idx);
+    outputs.into_iter().map(|(_, output)| output).collect()
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+/// The LLVM backend, which emits the units as object files, and links them with
+/// the system linker if the emit kind is `EmitKind::Link`.
+pub struct LlvmCodegenBackend;
+
+impl CodegenBackendDriver for LlvmCodegenBackend {
+    fn name(&self) -> &'static str {
+        "llvm"
+    }
+
+    fn backend_kind(&self) -> BackendKind {
+        BackendKind::Llvm
+    }
+
+    fn codegen_lir_unit(
+        &self,
+        lir_ctx: LirCtx,
+        lir_unit: LirUnit,
+    ) -> Result<BackendOutput, Box<dyn Error>> {
+        self.codegen_lir_units(vec![(lir_ctx, lir_unit)])
+    }
+
+    fn codegen_lir_units(
+        &self,
+        lir_units: Vec<(LirCtx, LirUnit)>,
+    ) -> Result<BackendOutput, Box<dyn Error>> {
+        let Some((first_lir_ctx, first_lir_unit)) = lir_units.first() else {
+            return Ok(BackendOutput::Emitted(vec![]));
+        };
+        // The settings of the link are taken from the first unit, which names the output.
+        let sess = first_lir_ctx.sess_arc();
+        let emit_kind = *first_lir_ctx.emit_kind();
+        let triple = first_lir_ctx.target().triple_or_host();
+        let output_name = first_lir_unit.metadata.unit_name.clone();
+        let reloc_model = first_lir_ctx.reloc_model();
+
+        let mut modules: Vec<CompiledModule> = if first_lir_ctx.lto() != Lto::No {
+            llvm_codegen_lir_units_lto(lir_units)?
+        } else {
+            let mut modules = vec![];
+            for (lir_ctx, lir_unit) in lir_units {
+                modules.extend(llvm_codegen_lir_unit_parallel(lir_ctx, lir_unit)?);
+            }
+            modules
+        };
 
-    ctx.compile_lir_unit::<CodegenBuilder>(lir_unit);
-    ctx.emit_output();
+        let artifacts = match emit_kind {
+            EmitKind::Link(link_output_kind) => {
+                let output = sess.output_path(&output_filename(
+                    &output_name,
+                    link_output_kind,
+                    triple.as_ref(),
+                ));
+                let objects: Vec<PathBuf> = modules
+                    .iter()
+                    .filter_map(|module| module.object.clone())
+                    .collect();
+                let linker = Linker::detect(triple.as_ref())?;
+                linker.link(
+                    triple.as_ref(),
+                    &objects,
+                    &output,
+                    link_output_kind,
+                    reloc_model,
+                    first_lir_ctx.sanitizers(),
+                )?;
+                debug!("Linked {} from {} objects", output.display(), objects.len());
+                // The objects (and the bitcode) were only emitted to be linked.
+                if let Err(err) = sess.remove_temps() {
+                    sess.dcx().warn(format_args!(
+                        "Could not remove the temporary files in {}: {}",
+                        sess.temps_dir().display(),
+                        err
+                    ));
+                }
+                let kind = match link_output_kind {
+                    LinkOutputKind::Executable => ArtifactKind::Executable,
+                    LinkOutputKind::SharedLibrary => ArtifactKind::SharedLibrary,
+                };
+                vec![Artifact { kind, path: output }]
+            }
+            EmitKind::Object | EmitKind::Assembly => {
+                // As rustc, `-o` only names a single emitted file.
+                if let Some(output) = &sess.opts.output {
+                    let mut emitted: Vec<&mut PathBuf> = modules
+                        .iter_mut()
+                        .filter_map(|module| module.object.as_mut().or(module.assembly.as_mut()))
+                        .collect();
+                    match emitted.as_mut_slice() {
+                        [path] => {
+                            std::fs::rename(&**path, output).map_err(|err| {
+                                format!("Could not write {}: {}", output.display(), err)
+                            })?;
+                            **path = output.clone();
+                        }
+                        _ => sess.dcx().warn(format_args!(
+                            "Ignoring -o {}, as {} files were emitted",
+                            output.display(),
+                            emitted.len()
+                        )),
+                    }
+                }
+                modules.iter().flat_map(CompiledModule::artifacts).collect()
+            }
+        };
+        Ok(BackendOutput::Emitted(artifacts))
+    }
 }
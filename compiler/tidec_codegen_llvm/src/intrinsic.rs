@@ -0,0 +1,102 @@
+//! The intrinsics of the LLVM modules (see `IntrinsicCallBuilderMethods`).
+//!
+//! The memory intrinsics are built with the `memcpy`, `memmove` and `memset` of the
+//! LLVM builder, while the others are calls to the corresponding `llvm.*`
+//! intrinsics, overloaded on the type of their arguments (e.g., `llvm.ctpop.i32`).
+// It corresponds to rustc_codegen_llvm/src/intrinsic.rs
+
+use inkwell::intrinsics::Intrinsic as LlvmIntrinsic;
+use inkwell::values::{BasicMetadataValueEnum, BasicValueEnum};
+use tidec_codegen_ssa::error::CodegenResult;
+use tidec_codegen_ssa::traits::IntrinsicCallBuilderMethods;
+use tidec_lir::syntax::{Intrinsic, LirTy};
+
+use crate::builder::{build_failed, CodegenBuilder};
+
+impl<'a, 'll> IntrinsicCallBuilderMethods<'a, 'll> for CodegenBuilder<'a, 'll> {
+    fn codegen_intrinsic_call(
+        &mut self,
+        intrinsic: Intrinsic,
+        args: &[BasicValueEnum<'ll>],
+        arg_ty: &LirTy,
+    ) -> CodegenResult<Option<BasicValueEnum<'ll>>> {
+        // The memory regions have no known alignment.
+        // TODO(bruzzone): use the alignment of the pointee types.
+        let result = match intrinsic {
+            Intrinsic::Memcpy => self.ll_builder.build_memcpy(
+                args[0].into_pointer_value(),
+                1,
+                args[1].into_pointer_value(),
+                1,
+                args[2].into_int_value(),
+            ),
+            Intrinsic::Memmove => self.ll_builder.build_memmove(
+                args[0].into_pointer_value(),
+                1,
+                args[1].into_pointer_value(),
+                1,
+                args[2].into_int_value(),
+            ),
+            Intrinsic::Memset => self.ll_builder.build_memset(
+                args[0].into_pointer_value(),
+                1,
+                args[1].into_int_value(),
+                args[2].into_int_value(),
+            ),
+            _ => return self.call_llvm_intrinsic(intrinsic, args, arg_ty).map(Some),
+        };
+        result.map_err(build_failed(intrinsic.name()))?;
+        Ok(None)
+    }
+}
+
+impl<'a, 'll> CodegenBuilder<'a, 'll> {
+    /// Builds a call of the `llvm.*` intrinsic corresponding to the given (non-memory)
+    /// intrinsic, declared for the type of the given arguments.
+    fn call_llvm_intrinsic(
+        &mut self,
+        intrinsic: Intrinsic,
+        args: &[BasicValueEnum<'ll>],
+        arg_ty: &LirTy,
+    ) -> CodegenResult<BasicValueEnum<'ll>> {
+        let name = match intrinsic {
+            Intrinsic::Ctpop => "llvm.ctpop",
+            Intrinsic::Ctlz => "llvm.ctlz",
+            Intrinsic::Cttz => "llvm.cttz",
+            Intrinsic::Bswap => "llvm.bswap",
+            Intrinsic::Bitreverse => "llvm.bitreverse",
+            Intrinsic::SaturatingAdd if arg_ty.is_signed() => "llvm.sadd.sat",
+            Intrinsic::SaturatingAdd => "llvm.uadd.sat",
+            Intrinsic::SaturatingSub if arg_ty.is_signed() => "llvm.ssub.sat",
+            Intrinsic::SaturatingSub => "llvm.usub.sat",
+            Intrinsic::Sqrt => "llvm.sqrt",
+            Intrinsic::Fabs => "llvm.fabs",
+            Intrinsic::Fma => "llvm.fma",
+            Intrinsic::Memcpy | Intrinsic::Memmove | Intrinsic::Memset => {
+                unreachable!("The memory intrinsics are built by the LLVM builder")
+            }
+        };
+        let ty = args[0].get_type();
+        let declaration = LlvmIntrinsic::find(name)
+            .and_then(|llvm_intrinsic| llvm_intrinsic.get_declaration(&self.ll_module, &[ty]))
+            .unwrap_or_else(|| panic!("Failed to declare the intrinsic `{}`", name));
+
+        let mut call_args = args
+            .iter()
+            .map(|arg| BasicMetadataValueEnum::from(*arg))
+            .collect::<Vec<_>>();
+        if matches!(intrinsic, Intrinsic::Ctlz | Intrinsic::Cttz) {
+            // The result of zero is the bit width, not poison.
+            let is_zero_poison = self.ll_context.bool_type().const_zero();
+            call_args.push(is_zero_poison.into());
+        }
+        let call_site = self
+            .ll_builder
+            .build_call(declaration, &call_args, "")
+            .map_err(build_failed("call"))?;
+        Ok(call_site
+            .try_as_basic_value()
+            .left()
+            .unwrap_or_else(|| panic!("The intrinsic `{}` returns no value", name)))
+    }
+}
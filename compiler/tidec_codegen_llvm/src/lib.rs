@@ -1,4 +1,12 @@
+pub mod asm;
 pub mod builder;
+pub mod common;
+pub mod consts;
 pub mod context;
+pub mod debuginfo;
 pub mod entry;
+pub mod intrinsic;
 pub mod lir;
+pub mod lto;
+pub mod metadata;
+pub mod type_;
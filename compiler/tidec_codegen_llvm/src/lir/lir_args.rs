@@ -0,0 +1,156 @@
+use inkwell::targets::{CodeModel as LlvmCodeModel, RelocMode};
+use inkwell::OptimizationLevel;
+use tidec_abi::target::{CodeModel, RelocModel};
+use tidec_session::config::{OptLevel, SanitizerSet, StackProtector};
+
+/// A trait to convert LirOptLevel into the LLVM optimization level and pipeline.
+///
+/// We need to do this due to the orphan rule in Rust. This could cause the
+/// stop of the compilation process of an external crate.
+pub trait OptLevelUtils: Sized {
+    /// The optimization level of the code generator (i.e., the target machine).
+    fn into_optimization_level(self) -> OptimizationLevel;
+
+    /// The name of the level in the pipelines of the new pass manager (e.g., `O2`).
+    fn pass_pipeline_level(self) -> &'static str;
+
+    /// The default pipeline of the new pass manager run on the module.
+    fn default_pass_pipeline(self) -> String {
+        format!("default<{}>", self.pass_pipeline_level())
+    }
+}
+
+// It corresponds to `to_llvm_opt_settings` in rustc_codegen_llvm/src/back/write.rs
+impl OptLevelUtils for OptLevel {
+    fn into_optimization_level(self) -> OptimizationLevel {
+        match self {
+            OptLevel::No => OptimizationLevel::None,
+            OptLevel::Less => OptimizationLevel::Less,
+            OptLevel::More | OptLevel::Size | OptLevel::SizeMin => OptimizationLevel::Default,
+            OptLevel::Aggressive => OptimizationLevel::Aggressive,
+        }
+    }
+
+    fn pass_pipeline_level(self) -> &'static str {
+        match self {
+            OptLevel::No => "O0",
+            OptLevel::Less => "O1",
+            OptLevel::More => "O2",
+            OptLevel::Aggressive => "O3",
+            OptLevel::Size => "Os",
+            OptLevel::SizeMin => "Oz",
+        }
+    }
+}
+
+/// A trait to convert a `SanitizerSet` into the LLVM attributes and passes that
+/// instrument the generated code.
+pub trait SanitizerSetUtils: Sized {
+    /// The names of the attributes of the functions to instrument.
+    fn fn_attribute_names(self) -> Vec<&'static str>;
+
+    /// The instrumentation passes of the new pass manager, to run on the module after
+    /// the optimizations.
+    fn instrumentation_passes(self) -> Vec<&'static str>;
+}
+
+// It corresponds to `sanitize_attrs` in rustc_codegen_llvm/src/attributes.rs and to
+// the sanitizer options of rustc_codegen_llvm/src/back/write.rs
+impl SanitizerSetUtils for SanitizerSet {
+    fn fn_attribute_names(self) -> Vec<&'static str> {
+        let mut names = vec![];
+        if self.contains(SanitizerSet::ADDRESS) {
+            names.push("sanitize_address");
+        }
+        // The checks of the undefined behavior are not driven by an attribute.
+        names
+    }
+
+    fn instrumentation_passes(self) -> Vec<&'static str> {
+        let mut passes = vec![];
+        if self.contains(SanitizerSet::ADDRESS) {
+            passes.push("asan");
+        }
+        if self.contains(SanitizerSet::UNDEFINED) {
+            // It traps on the out-of-bounds accesses to the objects of known size, as
+            // `-fsanitize=local-bounds` of clang.
+            passes.push("function(bounds-checking)");
+        }
+        passes
+    }
+}
+
+/// A trait to convert a `StackProtector` into the LLVM attribute of the functions.
+pub trait StackProtectorUtils: Sized {
+    /// The name of the attribute of the protected functions, if any.
+    fn fn_attribute_name(self) -> Option<&'static str>;
+}
+
+// It corresponds to `stackprotector_attr` in rustc_codegen_llvm/src/attributes.rs
+impl StackProtectorUtils for StackProtector {
+    fn fn_attribute_name(self) -> Option<&'static str> {
+        match self {
+            StackProtector::None => None,
+            StackProtector::Basic => Some("ssp"),
+            StackProtector::Strong => Some("sspstrong"),
+            StackProtector::All => Some("sspreq"),
+        }
+    }
+}
+
+/// A trait to convert a `RelocModel` into the LLVM relocation mode and the level of
+/// the module flags of the position-independent code.
+pub trait RelocModelUtils: Sized {
+    fn into_reloc_mode(self) -> RelocMode;
+
+    /// The value of the `PIC Level` and `PIE Level` module flags (i.e., `2` for the
+    /// large PIC, which is not limited by the size of the GOT), if they are set.
+    fn pic_and_pie_levels(self) -> (Option<u32>, Option<u32>);
+}
+
+// It corresponds to `to_llvm_relocation_model` in rustc_codegen_llvm/src/back/write.rs
+impl RelocModelUtils for RelocModel {
+    fn into_reloc_mode(self) -> RelocMode {
+        match self {
+            RelocModel::Static => RelocMode::Static,
+            RelocModel::Pic | RelocModel::Pie => RelocMode::PIC,
+        }
+    }
+
+    fn pic_and_pie_levels(self) -> (Option<u32>, Option<u32>) {
+        match self {
+            RelocModel::Static => (None, None),
+            RelocModel::Pic => (Some(2), None),
+            RelocModel::Pie => (Some(2), Some(2)),
+        }
+    }
+}
+
+/// A trait to convert a `CodeModel` into the LLVM code model.
+pub trait CodeModelUtils: Sized {
+    fn into_code_model(self) -> LlvmCodeModel;
+
+    /// The value of the `Code Model` module flag (i.e., of `llvm::CodeModel::Model`).
+    fn module_flag_value(self) -> u64;
+}
+
+// It corresponds to `to_llvm_code_model` in rustc_codegen_llvm/src/back/write.rs
+impl CodeModelUtils for CodeModel {
+    fn into_code_model(self) -> LlvmCodeModel {
+        match self {
+            CodeModel::Small => LlvmCodeModel::Small,
+            CodeModel::Kernel => LlvmCodeModel::Kernel,
+            CodeModel::Medium => LlvmCodeModel::Medium,
+            CodeModel::Large => LlvmCodeModel::Large,
+        }
+    }
+
+    fn module_flag_value(self) -> u64 {
+        match self {
+            CodeModel::Small => 1,
+            CodeModel::Kernel => 2,
+            CodeModel::Medium => 3,
+            CodeModel::Large => 4,
+        }
+    }
+}
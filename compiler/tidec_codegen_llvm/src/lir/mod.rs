@@ -1,2 +1,2 @@
+pub mod lir_args;
 pub mod lir_body_metadata;
-pub mod lir_ty;
@@ -0,0 +1,142 @@
+//! The link-time optimization (LTO) of the units compiled together (see `Lto`).
+//!
+//! Each unit is compiled to its own module, in an LLVM context shared by all of them,
+//! optimized with the pre-link pipeline and written as bitcode (`<unit>.bc`, see
+//! `Session::module_output_path`). Then,
+//! the modules are linked into the module of the first unit, which is optimized as a
+//! whole with the LTO pipeline and emitted as a single object (named after it).
+// It corresponds to rustc_codegen_llvm/src/back/lto.rs
+
+use std::path::PathBuf;
+
+use inkwell::context::Context;
+use inkwell::module::Module;
+use tidec_codegen_ssa::{
+    backend::{exported_symbols, CompiledModule},
+    error::{CodegenError, CodegenResult},
+    traits::CodegenMethods,
+};
+use tidec_lir::lir::{LirCtx, LirUnit};
+use tidec_session::config::{Lto, OptLevel};
+use tracing::{debug, instrument};
+
+use crate::builder::CodegenBuilder;
+use crate::context::CodegenCtx;
+use crate::lir::lir_args::OptLevelUtils;
+
+/// Returns the pipeline run on each module before it is linked.
+fn pre_link_pass_pipeline(lto: Lto, opt_level: OptLevel) -> String {
+    match lto {
+        Lto::No => opt_level.default_pass_pipeline(),
+        Lto::Thin => format!("thinlto-pre-link<{}>", opt_level.pass_pipeline_level()),
+        Lto::Fat => format!("lto-pre-link<{}>", opt_level.pass_pipeline_level()),
+    }
+}
+
+/// Returns the pipeline run on the linked module.
+fn lto_pass_pipeline(lto: Lto, opt_level: OptLevel) -> String {
+    match lto {
+        Lto::No => opt_level.default_pass_pipeline(),
+        Lto::Thin => format!("thinlto<{}>", opt_level.pass_pipeline_level()),
+        Lto::Fat => format!("lto<{}>", opt_level.pass_pipeline_level()),
+    }
+}
+
+#[instrument(level = "debug", skip_all)]
+/// Writes the bitcode of the given module to `<module>.bc`, and returns its path.
+fn write_bitcode(lir_ctx: &LirCtx, ll_module: &Module) -> CodegenResult<PathBuf> {
+    let module_name = ll_module.get_name().to_str().unwrap();
+    let bc_path = lir_ctx.sess().module_output_path(module_name, "bc");
+    let write_failed = |message: String| CodegenError::WriteFailed {
+        path: bc_path.clone(),
+        message,
+    };
+    if let Some(dir) = bc_path.parent() {
+        std::fs::create_dir_all(dir).map_err(|err| write_failed(err.to_string()))?;
+    }
+    if !ll_module.write_bitcode_to_path(&bc_path) {
+        return Err(write_failed("LLVM could not write the bitcode".to_string()));
+    }
+    debug!("Wrote bitcode file to {}", bc_path.display());
+    Ok(bc_path)
+}
+
+#[instrument(level = "info", skip(lir_units), fields(units = lir_units.len()))]
+/// Compiles the given units with LTO, as described in the module documentation. The
+/// LTO mode, the optimization level and the pass pipeline are the ones of the
+/// `LirCtx` of the first unit. It returns the modules of the units, which only have
+/// their bitcode, followed by the linked module, if any, which has the emitted file
+/// and the symbols of all of them.
+// TODO(bruzzone): ThinLTO should keep the modules separate, importing only the
+// functions selected by their summaries, so that they can be optimized in parallel.
+// As inkwell does not expose the ThinLTO API, the modules are linked as with the
+// fat LTO, and only the (cheaper) pipelines differ.
+pub fn llvm_codegen_lir_units_lto(
+    lir_units: Vec<(LirCtx, LirUnit)>,
+) -> Result<Vec<CompiledModule>, CodegenError> {
+    let ll_context = Context::create();
+    let mut lto_ctx: Option<CodegenCtx> = None;
+    let mut modules = vec![];
+
+    for (mut lir_ctx, lir_unit) in lir_units {
+        let lir_unit_metadata = lir_ctx.define_unit(lir_unit);
+        let ll_module = ll_context.create_module(&lir_unit_metadata.unit_name);
+        let ctx = CodegenCtx::new(lir_ctx, &ll_context, ll_module);
+        ctx.compile_lir_unit::<CodegenBuilder>()?;
+
+        let target_machine = ctx.target_machine()?;
+        // The modules are instrumented before they are linked, as the linked one has
+        // the sanitizers of the first unit only.
+        let pre_link_pass_pipeline = ctx.with_sanitizer_passes(pre_link_pass_pipeline(
+            ctx.lir_ctx.lto(),
+            ctx.lir_ctx.opt_level(),
+        ));
+        ctx.run_passes(&pre_link_pass_pipeline, &target_machine)?;
+        modules.push(CompiledModule {
+            name: lir_unit_metadata.unit_name,
+            object: None,
+            bitcode: Some(write_bitcode(&ctx.lir_ctx, &ctx.ll_module)?),
+            assembly: None,
+            symbols: exported_symbols(ctx.lir_ctx.defs()),
+        });
+
+        match &lto_ctx {
+            None => lto_ctx = Some(ctx),
+            Some(lto_ctx) => {
+                let CodegenCtx { ll_module, .. } = ctx;
+                let unit_name = ll_module.get_name().to_str().unwrap().to_string();
+                lto_ctx.ll_module.link_in_module(ll_module).map_err(|err| {
+                    CodegenError::BackendFailed {
+                        step: format!("link the module `{}`", unit_name),
+                        message: err.to_string(),
+                    }
+                })?;
+                debug!("Linked the module {}", unit_name);
+            }
+        }
+    }
+
+    let Some(lto_ctx) = lto_ctx else {
+        return Ok(modules);
+    };
+    let target_machine = lto_ctx.target_machine()?;
+    let lto_pass_pipeline = match lto_ctx.lir_ctx.pass_pipeline() {
+        Some(pass_pipeline) => pass_pipeline.to_string(),
+        None => lto_pass_pipeline(lto_ctx.lir_ctx.lto(), lto_ctx.lir_ctx.opt_level()),
+    };
+    lto_ctx
+        .run_passes(&lto_pass_pipeline, &target_machine)
+        .map_err(|err| lto_ctx.user_pass_pipeline_error(err))?;
+    let mut lto_module = CompiledModule::new(
+        lto_ctx.ll_module.get_name().to_string_lossy().into_owned(),
+        *lto_ctx.lir_ctx.emit_kind(),
+        lto_ctx.write_output(&target_machine)?,
+        [],
+    );
+    lto_module.symbols = modules
+        .iter()
+        .flat_map(|module| module.symbols.iter().cloned())
+        .collect();
+    modules.push(lto_module);
+    Ok(modules)
+}
@@ -0,0 +1,105 @@
+//! The alias metadata of the loads and stores of the LLVM modules (see
+//! `MetadataMethods` and `tidec_lir::lir::AliasMetadata`).
+//!
+//! With `AliasMetadata::Tbaa`, every scalar access is tagged with the TBAA type of its
+//! kind of scalar, which are all children of the same root. Hence, LLVM assumes that
+//! the accesses of different kinds do not alias. The accesses without metadata (e.g.,
+//! the copies with `memcpy`) may alias any of them.
+// It corresponds to the TBAA emitted by clang in clang/lib/CodeGen/CodeGenTBAA.cpp
+
+use inkwell::values::{BasicValue, InstructionValue, MetadataValue};
+use tidec_abi::layout::{Primitive, Scalar};
+use tidec_abi::size_and_align::Align;
+use tidec_codegen_ssa::error::CodegenResult;
+use tidec_codegen_ssa::traits::{BuilderMethods, MetadataMethods};
+use tidec_session::config::AliasMetadata;
+
+use crate::builder::CodegenBuilder;
+use crate::context::CodegenCtx;
+
+/// The name of the root of the TBAA types.
+const TBAA_ROOT: &str = "tidec TBAA";
+
+impl<'ll> CodegenCtx<'ll> {
+    /// Returns the TBAA access tag of the scalars of the given kind, that is, the
+    /// struct-path tag `!{type, type, i64 0}` of its scalar type
+    /// `!{!"name", root, i64 0}`.
+    fn tbaa_tag(&self, primitive: Primitive) -> MetadataValue<'ll> {
+        if let Some(tag) = self.tbaa_tags.borrow().get(&primitive) {
+            return *tag;
+        }
+
+        let ll_context = self.ll_context;
+        let offset = ll_context.i64_type().const_zero();
+        let root = ll_context.metadata_node(&[ll_context.metadata_string(TBAA_ROOT).into()]);
+        let ty = ll_context.metadata_node(&[
+            ll_context
+                .metadata_string(&tbaa_type_name(primitive))
+                .into(),
+            root.into(),
+            offset.into(),
+        ]);
+        let tag = ll_context.metadata_node(&[ty.into(), ty.into(), offset.into()]);
+        self.tbaa_tags.borrow_mut().insert(primitive, tag);
+        tag
+    }
+
+    /// Attaches to the given load or store of a scalar its alias metadata, if any.
+    fn set_alias_metadata(&self, access: InstructionValue<'ll>, scalar: Scalar) {
+        match self.lir_ctx.alias_metadata() {
+            AliasMetadata::None => {}
+            AliasMetadata::Tbaa => {
+                let kind_id = self.ll_context.get_kind_id("tbaa");
+                access
+                    .set_metadata(self.tbaa_tag(scalar.primitive), kind_id)
+                    .expect("Failed to set the TBAA metadata");
+            }
+        }
+    }
+}
+
+/// Returns the name of the TBAA type of the given kind of scalar. The signed and the
+/// unsigned integers of the same size share their type, as they may alias.
+fn tbaa_type_name(primitive: Primitive) -> String {
+    match primitive {
+        Primitive::I8 | Primitive::U8 => "int8".to_string(),
+        Primitive::I16 | Primitive::U16 => "int16".to_string(),
+        Primitive::I32 | Primitive::U32 => "int32".to_string(),
+        Primitive::I64 | Primitive::U64 => "int64".to_string(),
+        Primitive::I128 | Primitive::U128 => "int128".to_string(),
+        Primitive::F16 => "f16".to_string(),
+        Primitive::F32 => "f32".to_string(),
+        Primitive::F64 => "f64".to_string(),
+        Primitive::F128 => "f128".to_string(),
+        Primitive::Pointer(addr_space) => format!("ptr addrspace({})", addr_space.0),
+    }
+}
+
+impl<'a, 'll> MetadataMethods<'a, 'll> for CodegenBuilder<'a, 'll> {
+    fn build_scalar_load(
+        &mut self,
+        ty: Self::Type,
+        scalar: Scalar,
+        ptr: Self::Value,
+        align: Align,
+    ) -> CodegenResult<Self::Value> {
+        let load = self.build_load(ty, ptr, align)?;
+        let load_inst = load
+            .as_instruction_value()
+            .expect("The loaded value should be a load instruction");
+        self.set_alias_metadata(load_inst, scalar);
+        Ok(load)
+    }
+
+    fn build_scalar_store(
+        &mut self,
+        val: Self::Value,
+        scalar: Scalar,
+        ptr: Self::Value,
+        align: Align,
+    ) -> CodegenResult<()> {
+        let store_inst = self.store(val, ptr, align)?;
+        self.set_alias_metadata(store_inst, scalar);
+        Ok(())
+    }
+}
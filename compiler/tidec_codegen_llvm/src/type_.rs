@@ -0,0 +1,139 @@
+//! The lowering of the LIR types to the LLVM ones (see `TypeMethods`).
+// It corresponds to rustc_codegen_llvm/src/type_.rs
+
+use inkwell::types::{BasicType, BasicTypeEnum};
+use tidec_abi::calling_convention::function::{CastTarget, Reg, RegKind};
+use tidec_abi::layout::{BackendRepr, TyAndLayout, Variants};
+use tidec_abi::target::AddressSpace;
+use tidec_codegen_ssa::lir;
+use tidec_codegen_ssa::traits::TypeMethods;
+use tidec_lir::syntax::LirTy;
+
+use crate::context::CodegenCtx;
+
+impl<'ll> TypeMethods for CodegenCtx<'ll> {
+    fn type_i8(&self) -> BasicTypeEnum<'ll> {
+        self.ll_context.i8_type().into()
+    }
+
+    fn type_ptr(&self) -> BasicTypeEnum<'ll> {
+        self.ll_context
+            .ptr_type(inkwell::AddressSpace::default())
+            .into()
+    }
+
+    fn type_struct(&self, fields: &[BasicTypeEnum<'ll>], packed: bool) -> BasicTypeEnum<'ll> {
+        self.ll_context.struct_type(fields, packed).into()
+    }
+
+    fn type_from_layout(&self, ty_layout: &TyAndLayout<LirTy>) -> BasicTypeEnum<'ll> {
+        // The fields of the structural aggregates (tuples and slices) are lowered in
+        // memory order, as anonymous (literal) structs.
+        let struct_of = |field_tys: &[LirTy]| {
+            let fields = ty_layout
+                .fields
+                .index_by_increasing_offset()
+                .into_iter()
+                .map(|i| self.type_from_layout(&self.lir_ctx.layout_of(field_tys[i].clone())))
+                .collect::<Vec<_>>();
+            self.type_struct(&fields, false)
+        };
+        match &ty_layout.ty {
+            LirTy::Bool => self.ll_context.bool_type().into(),
+            LirTy::I8 | LirTy::U8 => self.type_i8(),
+            LirTy::I16 | LirTy::U16 => self.ll_context.i16_type().into(),
+            LirTy::I32 | LirTy::U32 => self.ll_context.i32_type().into(),
+            LirTy::I64 | LirTy::U64 => self.ll_context.i64_type().into(),
+            LirTy::I128 | LirTy::U128 => self.ll_context.i128_type().into(),
+            LirTy::F16 => self.ll_context.f16_type().into(),
+            LirTy::F32 => self.ll_context.f32_type().into(),
+            LirTy::F64 => self.ll_context.f64_type().into(),
+            LirTy::F128 => self.ll_context.f128_type().into(),
+            // Pointers are opaque in LLVM, so the pointee type is not lowered.
+            LirTy::Ptr(_, addr_space) => self.ll_context.ptr_type(addr_space.into_ll()).into(),
+            LirTy::Array(elem_ty, count) => self
+                .type_from_layout(&self.lir_ctx.layout_of((**elem_ty).clone()))
+                .array_type(*count as u32)
+                .into(),
+            LirTy::Adt(adt_id) => self.adt_type(*adt_id),
+            LirTy::Tuple(field_tys) => struct_of(field_tys),
+            // Function pointers live in the address space of the instructions.
+            LirTy::FnPtr(_) => {
+                let addr_space = self.lir_ctx.target().data_layout.instruction_address_space;
+                self.ll_context.ptr_type(addr_space.into_ll()).into()
+            }
+            // A slice is lowered to the anonymous struct of its pointer and its length.
+            LirTy::Slice(elem_ty) => struct_of(&self.lir_ctx.slice_field_tys(elem_ty)),
+            // The never type is a ZST, lowered as the unit type.
+            LirTy::Never => self.type_struct(&[], false),
+            LirTy::Param(idx) => {
+                panic!(
+                    "Generic parameter {} must be instantiated before codegen",
+                    idx
+                )
+            }
+            LirTy::Metadata => panic!("Metadata type cannot be converted to BasicTypeEnum"),
+        }
+    }
+
+    fn backend_type(&self, ty_layout: &TyAndLayout<LirTy>) -> BasicTypeEnum<'ll> {
+        match (&ty_layout.ty, &ty_layout.variants) {
+            // A downcast enum is lowered to the struct type of its variant.
+            (LirTy::Adt(adt_id), Variants::Single { index })
+                if self.lir_ctx.adt_def(*adt_id).is_enum() =>
+            {
+                self.adt_variant_type(*adt_id, *index).into()
+            }
+            _ => self.type_from_layout(ty_layout),
+        }
+    }
+
+    fn immediate_backend_type(&self, ty_layout: &TyAndLayout<LirTy>) -> BasicTypeEnum<'ll> {
+        match ty_layout.backend_repr {
+            BackendRepr::ScalarPair(_, _) => {
+                let fields = lir::scalar_pair_fields(self, ty_layout)
+                    .map(|(_, field_layout)| self.backend_type(&field_layout));
+                self.type_struct(&fields, false)
+            }
+            BackendRepr::Scalar(_) | BackendRepr::Memory => self.backend_type(ty_layout),
+        }
+    }
+
+    fn cast_backend_type(&self, cast: &CastTarget) -> BasicTypeEnum<'ll> {
+        let reg_type = |reg: &Reg| -> BasicTypeEnum<'ll> {
+            let size = reg.size.bytes();
+            match reg.kind {
+                RegKind::Integer => self
+                    .ll_context
+                    .custom_width_int_type((size * 8) as u32)
+                    .into(),
+                RegKind::Float => match size {
+                    2 => self.ll_context.f16_type().into(),
+                    4 => self.ll_context.f32_type().into(),
+                    8 => self.ll_context.f64_type().into(),
+                    _ => panic!("Unsupported float register of {} bytes", size),
+                },
+                RegKind::Vector => self.ll_context.i8_type().vec_type(size as u32).into(),
+            }
+        };
+        match cast.regs.as_slice() {
+            [reg] => reg_type(reg),
+            regs => {
+                let fields: Vec<_> = regs.iter().map(reg_type).collect();
+                self.type_struct(&fields, false)
+            }
+        }
+    }
+}
+
+/// A trait to convert an `AddressSpace` into the LLVM one.
+trait AddressSpaceUtils {
+    fn into_ll(self) -> inkwell::AddressSpace;
+}
+
+impl AddressSpaceUtils for AddressSpace {
+    fn into_ll(self) -> inkwell::AddressSpace {
+        inkwell::AddressSpace::try_from(u32::from(&self))
+            .unwrap_or_else(|_| panic!("Invalid address space {:?}", self))
+    }
+}
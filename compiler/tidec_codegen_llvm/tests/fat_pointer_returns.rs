@@ -0,0 +1,88 @@
+//! Functions returning fat-pointer-like values (i.e., scalar pairs), run through the
+//! parser and the LLVM backend, which verifies the emitted module.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use tidec_abi::target::{BackendKind, LirTarget};
+use tidec_codegen_llvm::entry::llvm_codegen_lir_unit;
+use tidec_lir::{lir::LirCtx, parse::parse_lir_unit};
+use tidec_session::{
+    config::{EmitKind, Options},
+    diagnostics::DiagCtxt,
+    Session,
+};
+
+/// Parses the given unit and emits its assembly in a temporary directory named after
+/// the unit, returning the path of the assembly.
+fn emit_asm(unit_name: &str, src: &str) -> PathBuf {
+    let opts = Options {
+        emit_kind: EmitKind::Assembly,
+        out_dir: Some(std::env::temp_dir().join(format!("tidec-test-{}", unit_name))),
+        ..Options::default()
+    };
+    let sess = Session::new(LirTarget::new(BackendKind::Llvm), opts, DiagCtxt::new());
+    let mut lir_ctx = LirCtx::new(Arc::new(sess));
+    let lir_unit = parse_lir_unit(&mut lir_ctx, unit_name, src).expect("Invalid test unit");
+    let module = llvm_codegen_lir_unit(lir_ctx, lir_unit).expect("The codegen failed");
+    module.output().expect("The assembly is emitted").clone()
+}
+
+#[test]
+fn return_str() {
+    let src = r#"
+        fn greeting() -> &str {
+            bb0: {
+                _0 = const "hello";
+                return;
+            }
+        }
+
+        fn main() -> u64 {
+            let _1: &str;
+
+            bb0: {
+                _1 = greeting() -> bb1;
+            }
+
+            bb1: {
+                _0 = copy _1.1;
+                return;
+            }
+        }
+    "#;
+    let asm = emit_asm("return_str", src);
+    assert!(asm.exists());
+}
+
+#[test]
+fn return_ptr_len_pair_built_in_memory() {
+    // The return value is built field by field, so it lives in memory and it is
+    // loaded as a pair when it is returned.
+    let src = r#"
+        fn split(_1: &str) -> (*u8, u64) {
+            bb0: {
+                _0.0 = copy _1.0;
+                _0.1 = copy _1.1;
+                return;
+            }
+        }
+
+        fn main() -> u8 {
+            let _1: (*u8, u64);
+            let _2: *u8;
+
+            bb0: {
+                _1 = split(const "tide") -> bb1;
+            }
+
+            bb1: {
+                _2 = copy _1.0;
+                _0 = copy (*_2);
+                return;
+            }
+        }
+    "#;
+    let asm = emit_asm("return_ptr_len_pair", src);
+    assert!(asm.exists());
+}
@@ -0,0 +1,189 @@
+use tidec_lir::{
+    basic_blocks::{BasicBlock, ENTRY_BLOCK, Location},
+    cfg::{self, Dominators},
+    dataflow::{MaybeLiveLocals, iterate_to_fixpoint},
+    lir::LirBody,
+    ssa::{self, DefLocation, DefUseVisitor},
+    syntax::Local,
+};
+use tidec_utils::{bit_set::IdxBitSet, idx::Idx, index_vec::IdxVec};
+use tracing::{debug, instrument};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// How a local is codegened, if its layout allows it to be a backend value (i.e., it
+/// is immediate or a scalar pair). Otherwise, it always lives in memory.
+// It corresponds to `LocalKind` in rustc_codegen_ssa/src/mir/analyze.rs
+pub enum LocalKind {
+    /// The local needs a stack slot. Its address is taken (e.g., by an `RValue::Ref`,
+    /// or by a `Drop`, which passes it to the drop glue), or it is accessed through a
+    /// field or an index projection.
+    Memory,
+    /// The local is defined at most once (the arguments are defined on entry), and its
+    /// definition dominates all its uses. So, it is a single backend value.
+    Ssa,
+    /// The local is defined more than once, or its definition does not dominate all
+    /// its uses. Each definition is a new backend value, and the values reaching a
+    /// block from different predecessors are merged by a phi (see `LocalAnalysis`).
+    Promoted,
+}
+
+/// The result of the analysis of the locals of a body.
+pub struct LocalAnalysis {
+    pub kinds: IdxVec<Local, LocalKind>,
+    /// The promoted locals that need a phi at the start of each block, in increasing
+    /// order. In the other blocks, a promoted local has the value it has at the end of
+    /// the immediate dominator.
+    pub phis: IdxVec<BasicBlock, Vec<Local>>,
+    pub dominators: Dominators,
+}
+
+#[instrument(level = "debug", skip(lir_body), fields(body = %lir_body.metadata.name))]
+/// Returns how each local of the body is codegened (see `LocalKind`), and where the
+/// phis of the promoted locals are.
+///
+/// The phis of a promoted local are at the iterated dominance frontier of the blocks
+/// defining it, where the local is live (i.e., the pruned SSA form). A promoted local
+/// that would need a phi at the entry block (whose first predecessor is the function
+/// entry), or that is live into a cleanup block (which is entered from the landing
+/// pads), is kept in memory.
+// It corresponds to `non_ssa_locals` in rustc_codegen_ssa/src/mir/analyze.rs
+pub fn analyze_locals(lir_body: &LirBody) -> LocalAnalysis {
+    let basic_blocks = &lir_body.basic_blocks;
+    let num_locals = lir_body.ret_and_args.len() + lir_body.locals.len();
+    let dominators = Dominators::compute(basic_blocks);
+    let predecessors = cfg::predecessors(basic_blocks);
+
+    // The arguments are defined on entry.
+    let mut defs = IdxVec::from_elem_n(vec![], num_locals);
+    for local in (1..lir_body.ret_and_args.len()).map(Local::new) {
+        defs[local].push(DefLocation::Argument);
+    }
+    let mut analyzer = LocalAnalyzer {
+        dominators: &dominators,
+        predecessors: &predecessors,
+        kinds: IdxVec::from_elem_n(LocalKind::Ssa, num_locals),
+        defs,
+    };
+    ssa::visit_defs_and_uses(lir_body, &mut analyzer);
+    let LocalAnalyzer {
+        mut kinds, defs, ..
+    } = analyzer;
+
+    let mut phis = IdxVec::from_elem_n(vec![], basic_blocks.len());
+    if !kinds.iter().any(|kind| *kind == LocalKind::Promoted) {
+        return LocalAnalysis {
+            kinds,
+            phis,
+            dominators,
+        };
+    }
+
+    let frontiers = dominators.dominance_frontiers(&predecessors);
+    let liveness = iterate_to_fixpoint(MaybeLiveLocals, lir_body);
+    let live_in: IdxVec<BasicBlock, IdxBitSet<Local>> = basic_blocks
+        .indices()
+        .map(|bb| {
+            let location = Location {
+                block: bb,
+                statement_index: 0,
+            };
+            liveness.state_before(lir_body, location)
+        })
+        .collect();
+    for (local, kind) in kinds.iter_enumerated_mut() {
+        if *kind != LocalKind::Promoted {
+            continue;
+        }
+        let live_in_cleanup = basic_blocks.iter_enumerated().any(|(bb, bb_data)| {
+            bb_data.is_cleanup() && dominators.is_reachable(bb) && live_in[bb].contains(local)
+        });
+        let phi_blocks: Vec<BasicBlock> = iterated_frontier(
+            &frontiers,
+            defs[local].iter().filter_map(DefLocation::block),
+        )
+        .into_iter()
+        .filter(|bb| live_in[*bb].contains(local))
+        .collect();
+        if live_in_cleanup || phi_blocks.contains(&ENTRY_BLOCK) {
+            debug!(
+                "Local {:?} cannot be promoted; it must be kept in memory",
+                local
+            );
+            *kind = LocalKind::Memory;
+            continue;
+        }
+        debug!(
+            "Local {:?} is promoted with phis at {:?}",
+            local, phi_blocks
+        );
+        for bb in phi_blocks {
+            phis[bb].push(local);
+        }
+    }
+
+    LocalAnalysis {
+        kinds,
+        phis,
+        dominators,
+    }
+}
+
+/// Returns the iterated dominance frontier of the given blocks, i.e., the fixpoint of
+/// adding the dominance frontier of the blocks found so far.
+fn iterated_frontier(
+    frontiers: &IdxVec<BasicBlock, Vec<BasicBlock>>,
+    blocks: impl Iterator<Item = BasicBlock>,
+) -> Vec<BasicBlock> {
+    let mut in_frontier = IdxBitSet::new_empty(frontiers.len());
+    let mut worklist: Vec<BasicBlock> = blocks.collect();
+    while let Some(bb) = worklist.pop() {
+        for frontier_bb in &frontiers[bb] {
+            if in_frontier.insert(*frontier_bb) {
+                worklist.push(*frontier_bb);
+            }
+        }
+    }
+    in_frontier.iter().collect()
+}
+
+/// The visitor finding the kinds of the locals (see `analyze_locals`).
+struct LocalAnalyzer<'a> {
+    dominators: &'a Dominators,
+    predecessors: &'a IdxVec<BasicBlock, Vec<BasicBlock>>,
+    kinds: IdxVec<Local, LocalKind>,
+    /// The definitions of each local found so far.
+    defs: IdxVec<Local, Vec<DefLocation>>,
+}
+
+impl DefUseVisitor for LocalAnalyzer<'_> {
+    fn visit_def(&mut self, local: Local, def: DefLocation) {
+        let defs = &mut self.defs[local];
+        defs.push(def);
+        if defs.len() > 1 && self.kinds[local] == LocalKind::Ssa {
+            debug!("Local {:?} is defined more than once", local);
+            self.kinds[local] = LocalKind::Promoted;
+        }
+    }
+
+    fn visit_use(&mut self, local: Local, location: Location) {
+        if self.kinds[local] != LocalKind::Ssa {
+            return;
+        }
+        let dominated = match self.defs[local].as_slice() {
+            [def] => def.dominates(location, self.dominators, self.predecessors),
+            _ => false,
+        };
+        if !dominated {
+            debug!(
+                "Local {:?} is used at {:?}, which its definition does not dominate",
+                local, location
+            );
+            self.kinds[local] = LocalKind::Promoted;
+        }
+    }
+
+    fn visit_memory_access(&mut self, local: Local, reason: &str) {
+        debug!("Local {:?} is {}; it must be kept in memory", local, reason);
+        self.kinds[local] = LocalKind::Memory;
+    }
+}
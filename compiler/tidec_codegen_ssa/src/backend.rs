@@ -0,0 +1,182 @@
+//! The object-safe facade of the codegen backends, which the driver uses to select and
+//! run a backend at runtime. Unlike `traits::CodegenMethods`, it does not expose the
+//! types of the backend, so that a backend can be provided by any crate that
+//! registers it in a `BackendRegistry`.
+// It corresponds to `CodegenBackend` in rustc_codegen_ssa/src/traits/backend.rs
+
+use std::error::Error;
+use std::path::PathBuf;
+
+use tidec_abi::target::BackendKind;
+use tidec_lir::lir::{LirBody, LirCtx, LirUnit};
+use tidec_session::config::EmitKind;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The kind of a file produced by a backend.
+pub enum ArtifactKind {
+    Object,
+    Assembly,
+    /// The bitcode of a unit compiled with LTO.
+    Bitcode,
+    Executable,
+    SharedLibrary,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A file produced by a backend. The temporary files (e.g., the objects linked into
+/// an executable) are not artifacts, as they are removed.
+pub struct Artifact {
+    pub kind: ArtifactKind,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A backend unit (e.g., an LLVM module) once compiled: the files emitted for it, and
+/// the symbols it defines. The driver links the objects, and the incremental
+/// compilation cache stores the emitted file.
+// It corresponds to `CompiledModule` in rustc_codegen_ssa/src/lib.rs
+pub struct CompiledModule {
+    pub name: String,
+    pub object: Option<PathBuf>,
+    /// The bitcode written before the module is linked with the others (see
+    /// `tidec_session::config::Lto`).
+    pub bitcode: Option<PathBuf>,
+    pub assembly: Option<PathBuf>,
+    /// The symbols defined by the module that the other modules can refer to (see
+    /// `Linkage::is_exported`).
+    pub symbols: Vec<String>,
+}
+
+impl CompiledModule {
+    /// Creates the module whose file of the given emit kind is at `path`, and which
+    /// defines the exported symbols of the given bodies.
+    pub fn new<'a>(
+        name: String,
+        emit_kind: EmitKind,
+        path: PathBuf,
+        lir_bodies: impl IntoIterator<Item = &'a LirBody>,
+    ) -> Self {
+        let (object, assembly) = match emit_kind {
+            EmitKind::Object | EmitKind::Link(_) => (Some(path), None),
+            EmitKind::Assembly => (None, Some(path)),
+        };
+        CompiledModule {
+            name,
+            object,
+            bitcode: None,
+            assembly,
+            symbols: exported_symbols(lir_bodies),
+        }
+    }
+
+    /// Returns the object or the assembly of the module, if any (i.e., the file of
+    /// its emit kind).
+    pub fn output(&self) -> Option<&PathBuf> {
+        self.object.as_ref().or(self.assembly.as_ref())
+    }
+
+    /// Returns the files emitted for the module, the bitcode first.
+    pub fn artifacts(&self) -> impl Iterator<Item = Artifact> + '_ {
+        let files = [
+            (ArtifactKind::Bitcode, &self.bitcode),
+            (ArtifactKind::Object, &self.object),
+            (ArtifactKind::Assembly, &self.assembly),
+        ];
+        files
+            .into_iter()
+            .filter_map(|(kind, path)| path.clone().map(|path| Artifact { kind, path }))
+    }
+}
+
+/// Returns the names of the given bodies which are defined with an exported linkage.
+pub fn exported_symbols<'a>(lir_bodies: impl IntoIterator<Item = &'a LirBody>) -> Vec<String> {
+    lir_bodies
+        .into_iter()
+        .filter(|lir_body| !lir_body.is_declaration() && lir_body.metadata.linkage.is_exported())
+        .map(|lir_body| lir_body.metadata.name.clone())
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// What a backend produced for a unit.
+pub enum BackendOutput {
+    /// The unit has been emitted, and these are the produced files (i.e., the
+    /// manifest of the compilation).
+    Emitted(Vec<Artifact>),
+    /// The unit has been executed (e.g., by an interpreter or a JIT), and the program
+    /// exited with the given code.
+    Exited(i32),
+}
+
+/// A codegen backend, which turns a LIR unit into its output.
+pub trait CodegenBackendDriver {
+    /// The name of the backend, which selects it (e.g., `--backend=llvm`).
+    fn name(&self) -> &'static str;
+
+    /// The conventions of the target used by the backend (e.g., the format of the
+    /// data layout string). It is used to create the `LirCtx` of the units.
+    fn backend_kind(&self) -> BackendKind;
+
+    /// Codegens the given unit: it creates the backend unit (e.g., an LLVM module),
+    /// compiles the bodies of the unit into it and emits it.
+    ///
+    /// These steps are a single method because the backend unit usually borrows some
+    /// state of the backend (e.g., the LLVM context), so it cannot outlive the call.
+    fn codegen_lir_unit(
+        &self,
+        lir_ctx: LirCtx,
+        lir_unit: LirUnit,
+    ) -> Result<BackendOutput, Box<dyn Error>>;
+
+    /// Codegens the given units, compiled together. By default, each unit is
+    /// codegened on its own, and the artifacts of all of them are returned, unless a
+    /// unit is executed, whose output is returned. A backend overrides it to optimize
+    /// across the units (see `tidec_session::config::Lto`).
+    fn codegen_lir_units(
+        &self,
+        lir_units: Vec<(LirCtx, LirUnit)>,
+    ) -> Result<BackendOutput, Box<dyn Error>> {
+        let mut artifacts = vec![];
+        for (lir_ctx, lir_unit) in lir_units {
+            match self.codegen_lir_unit(lir_ctx, lir_unit)? {
+                BackendOutput::Emitted(unit_artifacts) => artifacts.extend(unit_artifacts),
+                exited @ BackendOutput::Exited(_) => return Ok(exited),
+            }
+        }
+        Ok(BackendOutput::Emitted(artifacts))
+    }
+}
+
+#[derive(Default)]
+/// The available backends, by their name.
+pub struct BackendRegistry {
+    backends: Vec<Box<dyn CodegenBackendDriver>>,
+}
+
+impl BackendRegistry {
+    pub fn new() -> Self {
+        BackendRegistry::default()
+    }
+
+    /// Adds the given backend. It panics if a backend with the same name has already
+    /// been registered.
+    pub fn register(&mut self, backend: Box<dyn CodegenBackendDriver>) {
+        if self.get(backend.name()).is_some() {
+            panic!("The backend {} is registered twice", backend.name());
+        }
+        self.backends.push(backend);
+    }
+
+    /// Returns the backend with the given name, if it has been registered.
+    pub fn get(&self, name: &str) -> Option<&dyn CodegenBackendDriver> {
+        self.backends
+            .iter()
+            .find(|backend| backend.name() == name)
+            .map(|backend| backend.as_ref())
+    }
+
+    /// Returns the names of the registered backends, in registration order.
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.backends.iter().map(|backend| backend.name())
+    }
+}
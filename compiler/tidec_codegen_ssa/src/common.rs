@@ -0,0 +1,39 @@
+use tidec_lir::syntax::BinOp;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+/// An integer comparison predicate, independent of the codegen backend.
+pub enum IntPredicate {
+    IntEQ,
+    IntNE,
+    IntUGT,
+    IntUGE,
+    IntULT,
+    IntULE,
+    IntSGT,
+    IntSGE,
+    IntSLT,
+    IntSLE,
+}
+
+impl IntPredicate {
+    /// Returns the predicate corresponding to the given comparison operator.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `op` is not a comparison operator.
+    pub fn from_bin_op(op: BinOp, is_signed: bool) -> Self {
+        match (op, is_signed) {
+            (BinOp::Eq, _) => IntPredicate::IntEQ,
+            (BinOp::Ne, _) => IntPredicate::IntNE,
+            (BinOp::Lt, true) => IntPredicate::IntSLT,
+            (BinOp::Lt, false) => IntPredicate::IntULT,
+            (BinOp::Le, true) => IntPredicate::IntSLE,
+            (BinOp::Le, false) => IntPredicate::IntULE,
+            (BinOp::Gt, true) => IntPredicate::IntSGT,
+            (BinOp::Gt, false) => IntPredicate::IntUGT,
+            (BinOp::Ge, true) => IntPredicate::IntSGE,
+            (BinOp::Ge, false) => IntPredicate::IntUGE,
+            _ => panic!("{:?} is not a comparison operator", op),
+        }
+    }
+}
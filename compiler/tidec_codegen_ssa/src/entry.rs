@@ -1,26 +1,54 @@
 use crate::{
-    lir::{OperandVal, PlaceRef},
-    traits::LayoutOf,
+    analyze::LocalAnalysis,
+    common::IntPredicate,
+    error::CodegenResult,
+    ice,
+    lir::{OperandVal, PlaceRef, PlaceVal, scalar_pair_fields},
+    traits::{
+        AsmBuilderMethods, CodegenBackendTypes, CodegenMethods, ConstCodegenMethods,
+        DebugInfoBuilderMethods, FnAbiOf, InlineAsmOperandRef, IntrinsicCallBuilderMethods,
+        LayoutOf, MetadataMethods, TypeMethods,
+    },
+};
+use std::{cmp::Ordering, fmt};
+
+use tidec_abi::{
+    calling_convention::{
+        CallConv,
+        function::{CastTarget, FnAbi, PassMode},
+    },
+    layout::{BackendRepr, Primitive, TagEncoding, TyAndLayout, VariantIdx, Variants},
+    size_and_align::Align,
 };
-use tidec_abi::calling_convention::function::{FnAbi, PassMode};
 use tidec_lir::{
     basic_blocks::{BasicBlock, BasicBlockData},
+    cfg,
     lir::LirBody,
-    syntax::{LirTy, Local, RETURN_LOCAL, RValue, Statement, Terminator},
+    pretty::BodyLines,
+    syntax::{
+        AssertKind, BinOp, BranchHint, Callee, CastKind, InlineAsm, InlineAsmOperand, LirTy, Local,
+        Operand, Place, PlaceTy, Projection, RETURN_LOCAL, RValue, Statement, StatementKind,
+        Terminator, TerminatorKind, UnOp, UnwindAction,
+    },
 };
-use tidec_utils::index_vec::IdxVec;
+use tidec_span::Span;
+use tidec_utils::{idx::Idx, index_vec::IdxVec};
 use tracing::{debug, info, instrument};
 
-use crate::{
-    lir::{LocalRef, OperandRef},
-    traits::BuilderMethods,
-};
+use crate::lir::{LocalRef, OperandRef};
 
-pub struct FnCtx<'a, 'be, B: BuilderMethods<'a, 'be>> {
+pub struct FnCtx<
+    'a,
+    'be,
+    B: DebugInfoBuilderMethods<'a, 'be>
+        + MetadataMethods<'a, 'be>
+        + AsmBuilderMethods<'a, 'be>
+        + IntrinsicCallBuilderMethods<'a, 'be>,
+> {
     /// The function ABI.
     /// This contains information about the calling convention,
     /// argument types, return type, etc.
-    pub fn_abi: FnAbi<LirTy>,
+    pub fn_abi: FnAbi<'a, LirTy>,
 
     /// The body of the function in LIR.
     pub lir_body: &'a LirBody,
@@ -29,34 +57,309 @@ pub struct FnCtx<'a, 'be, B: BuilderMethods<'a, 'be>> {
     /// This is the function that will be generated.
     pub fn_value: B::FunctionValue,
 
+    /// The entry block of the function, where all the memory of the function is
+    /// allocated (see `FnCtx::alloca`).
+    pub entry_bb: B::BasicBlock,
+
     /// The codegen context.
     pub ctx: &'a B::CodegenCtx,
 
     /// The allocated locals and temporaries for the function.
     ///
     /// Note that the `B::Value` type is used to represent the local references.
-    pub locals: IdxVec<Local, LocalRef<B::Value>>,
+    pub locals: IdxVec<Local, LocalRef<'a, B::Value>>,
 
     /// A cache of the basic blocks in the function.
     /// This is also used to avoid creating multiple basic blocks for the same LIR basic block.
     pub cached_bbs: IdxVec<BasicBlock, Option<B::BasicBlock>>,
+
+    /// The landing pads of the cleanup blocks, created when a terminator first
+    /// unwinds to them.
+    pub landing_pads: IdxVec<BasicBlock, Option<B::BasicBlock>>,
+
+    /// The memory where the landing pads store the exception being unwound, which
+    /// is resumed from it. It exists only if the body has cleanup blocks.
+    pub personality_slot: Option<PlaceRef<'a, B::Value>>,
+
+    /// The debug info scope of the function, if the debug info is enabled.
+    pub dbg_scope: Option<B::DIScope>,
+
+    /// The location of the instructions being built, if the debug info is enabled.
+    pub dbg_loc: Option<B::DILocation>,
+
+    /// The lines of the textual representation of the body, which are the locations of
+    /// the debug info if the body has no source.
+    pub body_lines: BodyLines,
+
+    /// The span of the statement (or terminator) being codegened, for the diagnostics.
+    pub span: Span,
+
+    /// The analysis of the locals, with the phis of the promoted locals and the
+    /// dominators of the blocks.
+    pub local_analysis: LocalAnalysis,
+
+    /// The promoted locals that are backend values (i.e., whose layout is immediate or
+    /// a scalar pair), in increasing order. Their values change across the blocks, so
+    /// each block starts from the values at the end of its immediate dominator, or
+    /// from phis (see `analyze::LocalKind::Promoted`).
+    pub promoted_locals: Vec<Local>,
+
+    /// The end of each codegened block, with the values of the promoted locals there.
+    pub block_exits: IdxVec<BasicBlock, Option<BlockExit<'a, B>>>,
+
+    /// The phis of the promoted locals built so far, with their block and the index of
+    /// their local in `promoted_locals`. Their incoming values are added once all the
+    /// blocks are codegened, as the back edges are codegened after the phis.
+    pub pending_phis: Vec<(BasicBlock, usize, OperandRef<'a, B::Value>)>,
 }
 
-impl<'ctx, 'll, B: BuilderMethods<'ctx, 'll>> FnCtx<'ctx, 'll, B> {
+/// The end of a codegened block.
+pub struct BlockExit<'a, T: CodegenBackendTypes> {
+    /// The backend block where the block ends, which is not the one where it starts if
+    /// its terminator needs more blocks (e.g., the normal destination of an invoke).
+    pub be_bb: T::BasicBlock,
+    /// The values of the promoted locals (see `FnCtx::promoted_locals`), or `None` for
+    /// the ones not defined yet.
+    pub values: Vec<Option<OperandRef<'a, T::Value>>>,
+}
+
+impl<
+    'ctx,
+    'll,
+    B: DebugInfoBuilderMethods<'ctx, 'll>
+        + MetadataMethods<'ctx, 'll>
+        + AsmBuilderMethods<'ctx, 'll>
+        + IntrinsicCallBuilderMethods<'ctx, 'll>,
+> FnCtx<'ctx, 'll, B>
+{
     /// Codegen the given LIR basic block.
     /// This creates a new builder for the basic block and generates the instructions in it.
     /// It also updates the `cached_bbs` field to avoid creating multiple basic blocks for the same LIR basic block.
     /// Note that this function does not handle unreachable blocks.
-    pub fn codegen_basic_block(&mut self, bb: BasicBlock) {
+    pub fn codegen_basic_block(&mut self, bb: BasicBlock) -> CodegenResult<()> {
+        ice::enter_basic_block(bb);
         let be_bb = self.get_or_insert_bb(bb);
         let mut builder = B::build(self.ctx, be_bb);
+        self.enter_promoted_locals(&mut builder, bb)?;
         let bb_data: &BasicBlockData = &self.lir_body.basic_blocks[bb];
         debug!("Codegen basic block {:?}: {:?}", bb, bb_data);
-        for stmt in &bb_data.statements {
-            self.codegen_statement(&mut builder, stmt);
+        for (idx, stmt) in bb_data.statements.iter().enumerate() {
+            self.span = stmt.span;
+            let line = self.body_lines.statement_line(bb, idx);
+            self.set_debug_loc(&mut builder, stmt.span, line);
+            self.codegen_statement(&mut builder, stmt)?;
         }
         let term = &bb_data.terminator;
-        self.codegen_terminator(&mut builder, term);
+        self.span = term.span;
+        let term_line = self.body_lines.statement_line(bb, bb_data.statements.len());
+        self.set_debug_loc(&mut builder, term.span, term_line);
+        self.codegen_terminator(&mut builder, term)?;
+        self.exit_promoted_locals(&builder, bb);
+        Ok(())
+    }
+
+    /// Sets the values of the promoted locals at the start of the given block: a phi
+    /// if the block has one for the local, otherwise the value at the end of its
+    /// immediate dominator, which is codegened before it (in reverse postorder). The
+    /// entry block starts with the arguments.
+    // It corresponds to the renaming of the SSA construction of Cytron et al., in
+    // "Efficiently Computing Static Single Assignment Form and the Control Dependence Graph"
+    fn enter_promoted_locals(&mut self, builder: &mut B, bb: BasicBlock) -> CodegenResult<()> {
+        let Some(idom) = self.local_analysis.dominators.immediate_dominator(bb) else {
+            return Ok(());
+        };
+        for i in 0..self.promoted_locals.len() {
+            let local = self.promoted_locals[i];
+            let local_ref = if self.local_analysis.phis[bb].contains(&local) {
+                let phi = self.build_phis(builder, local)?;
+                self.pending_phis.push((bb, i, phi.clone()));
+                LocalRef::OperandRef(phi)
+            } else {
+                let idom_exit = self.block_exits[idom]
+                    .as_ref()
+                    .expect("The immediate dominator is codegened first");
+                match &idom_exit.values[i] {
+                    Some(operand) => LocalRef::OperandRef(operand.clone()),
+                    None => LocalRef::PendingOperandRef,
+                }
+            };
+            self.overwrite_local(local, local_ref);
+        }
+        Ok(())
+    }
+
+    /// Builds the phis of the given promoted local: one for an immediate, and one for
+    /// each scalar of a scalar pair.
+    fn build_phis(
+        &mut self,
+        builder: &mut B,
+        local: Local,
+    ) -> CodegenResult<OperandRef<'ctx, B::Value>> {
+        let ty_layout = self.ctx.layout_of(self.local_ty(local).clone());
+        let operand_val = match ty_layout.backend_repr {
+            BackendRepr::Scalar(_) => OperandVal::Immediate(
+                builder.build_phi(self.ctx.immediate_backend_type(&ty_layout))?,
+            ),
+            BackendRepr::ScalarPair(..) => {
+                let [a, b] = scalar_pair_fields(self.ctx, &ty_layout)
+                    .map(|(_, field_layout)| self.ctx.immediate_backend_type(&field_layout));
+                OperandVal::Pair(builder.build_phi(a)?, builder.build_phi(b)?)
+            }
+            BackendRepr::Memory => {
+                self.span_bug(format!("The promoted local {:?} lives in memory", local))
+            }
+        };
+        Ok(OperandRef {
+            operand_val,
+            ty_layout,
+        })
+    }
+
+    /// Records the end of the given block (see `BlockExit`), once its terminator is
+    /// codegened by `builder`.
+    fn exit_promoted_locals(&mut self, builder: &B, bb: BasicBlock) {
+        if self.promoted_locals.is_empty() {
+            return;
+        }
+        let values = self
+            .promoted_locals
+            .iter()
+            .map(|local| match &self.locals[*local] {
+                LocalRef::OperandRef(operand) => Some(operand.clone()),
+                LocalRef::PendingOperandRef => None,
+                LocalRef::PlaceRef(_) => {
+                    self.span_bug(format!("The promoted local {:?} lives in memory", local))
+                }
+            })
+            .collect();
+        self.block_exits[bb] = Some(BlockExit {
+            be_bb: builder.llbb(),
+            values,
+        });
+    }
+
+    /// Adds the incoming values of the phis of the promoted locals, from the end of the
+    /// reachable predecessors of their blocks. The value of a local not defined yet at
+    /// the end of a predecessor is undefined.
+    pub fn codegen_phi_incomings(&mut self) -> CodegenResult<()> {
+        let predecessors = cfg::predecessors(&self.lir_body.basic_blocks);
+        for (bb, i, phi) in std::mem::take(&mut self.pending_phis) {
+            let mut builder = B::build(self.ctx, self.get_or_insert_bb(bb));
+            let mut bb_predecessors = predecessors[bb].clone();
+            bb_predecessors.sort();
+            bb_predecessors.dedup();
+            for pred in bb_predecessors {
+                let Some(pred_exit) = &self.block_exits[pred] else {
+                    continue;
+                };
+                let incoming = match &pred_exit.values[i] {
+                    Some(operand) => operand.operand_val,
+                    None => self.undef_operand_val(&phi.ty_layout),
+                };
+                match (phi.operand_val, incoming) {
+                    (OperandVal::Immediate(phi), OperandVal::Immediate(val)) => {
+                        builder.add_incoming_to_phi(phi, val, pred_exit.be_bb);
+                    }
+                    (OperandVal::Pair(phi_a, phi_b), OperandVal::Pair(a, b)) => {
+                        builder.add_incoming_to_phi(phi_a, a, pred_exit.be_bb);
+                        builder.add_incoming_to_phi(phi_b, b, pred_exit.be_bb);
+                    }
+                    (_, incoming) => self.span_bug(format!(
+                        "The value {:?} of the promoted local {:?} does not match its phi",
+                        incoming, self.promoted_locals[i]
+                    )),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the undefined value of an immediate or a scalar pair of the given layout.
+    fn undef_operand_val(&self, ty_layout: &TyAndLayout<'ctx, LirTy>) -> OperandVal<B::Value> {
+        match ty_layout.backend_repr {
+            BackendRepr::ScalarPair(..) => {
+                let [a, b] = scalar_pair_fields(self.ctx, ty_layout).map(|(_, field_layout)| {
+                    self.ctx
+                        .const_undef(self.ctx.immediate_backend_type(&field_layout))
+                });
+                OperandVal::Pair(a, b)
+            }
+            _ => OperandVal::Immediate(
+                self.ctx
+                    .const_undef(self.ctx.immediate_backend_type(ty_layout)),
+            ),
+        }
+    }
+
+    /// Returns the line and the column of the given span of the body, for the debug
+    /// info. If the body has no source, it is located in its textual representation,
+    /// at the given line of `body_lines` (the column is unknown, i.e., `0`). A dummy
+    /// span of a body with a source (e.g., of a statement built by the compiler) is
+    /// located at the start of the body.
+    fn debug_pos(&self, span: Span, fallback_line: u32) -> (u32, u32) {
+        if self.lir_body.metadata.span.is_dummy() {
+            return (fallback_line, 0);
+        }
+        let source_map = self.ctx.lir_ctx().source_map();
+        let loc = source_map
+            .lookup(span)
+            .or_else(|| source_map.lookup(self.lir_body.metadata.span))
+            .expect("The body has a source");
+        (loc.line, loc.col)
+    }
+
+    /// Sets the location of the instructions built next by `builder` to the given
+    /// span (or line of `body_lines`, see `debug_pos`), if the debug info is enabled.
+    pub fn set_debug_loc(&mut self, builder: &mut B, span: Span, fallback_line: u32) {
+        if let Some(dbg_scope) = self.dbg_scope {
+            let (line, col) = self.debug_pos(span, fallback_line);
+            let dbg_loc = B::create_debug_loc(self.ctx, dbg_scope, line, col);
+            builder.set_debug_loc(dbg_loc);
+            self.dbg_loc = Some(dbg_loc);
+        }
+    }
+
+    /// Aborts the compilation with an error about the LIR being codegened, located at
+    /// the span of the current statement (or terminator).
+    // It corresponds to `span_bug` in rustc_middle/src/util/bug.rs
+    #[track_caller]
+    pub fn span_bug(&self, msg: impl fmt::Display) -> ! {
+        let source_map = self.ctx.lir_ctx().source_map();
+        panic!(
+            "{}: {} (in {})",
+            source_map.span_to_string(self.span),
+            msg,
+            self.lir_body.metadata.name
+        )
+    }
+
+    /// Declares the debug info of the locals living in memory, in the entry block.
+    /// The return value is not declared, as it is not a variable of the body.
+    // It corresponds to `debug_introduce_locals` in rustc_codegen_ssa/src/mir/debuginfo.rs
+    // TODO(bruzzone): declare the SSA locals too (e.g., with `llvm.dbg.value`).
+    pub fn declare_locals(&self, builder: &mut B, dbg_scope: B::DIScope) {
+        let arg_count = self.lir_body.ret_and_args.len();
+        for (local, local_ref) in self.locals.iter_enumerated() {
+            let LocalRef::PlaceRef(place_ref) = local_ref else {
+                continue;
+            };
+            if local == RETURN_LOCAL {
+                continue;
+            }
+            let arg_no = (local.idx() < arg_count).then_some(local.idx() as u32);
+            // The locals have no span, so they are located at the start of the body.
+            let (line, col) = self.debug_pos(Span::DUMMY, self.body_lines.local_line(local));
+            let variable = B::create_local_variable(
+                self.ctx,
+                dbg_scope,
+                &self.lir_body.local_name(self.ctx.lir_ctx(), local),
+                &place_ref.ty_layout,
+                arg_no,
+                line,
+            );
+            let dbg_loc = B::create_debug_loc(self.ctx, dbg_scope, line, col);
+            builder.declare_local(variable, place_ref.place_val.value, dbg_loc);
+        }
     }
 
     /// Get the backend basic block for the given LIR basic block.
@@ -75,18 +378,23 @@ impl<'ctx, 'll, B: BuilderMethods<'ctx, 'll>> FnCtx<'ctx, 'll, B> {
     /// Codegen the given LIR statement.
     /// This function is called by `codegen_basic_block` for each statement in the basic block.
     /// It generates the corresponding instructions in the backend.
-    fn codegen_statement(&mut self, builder: &mut B, stmt: &Statement) {
-        // TODO(bruzzone): handle span for debugging here
-        match stmt {
-            Statement::Assign(assig) => {
+    fn codegen_statement(&mut self, builder: &mut B, stmt: &Statement) -> CodegenResult<()> {
+        match &stmt.kind {
+            StatementKind::Assign(assig) => {
                 let place = &assig.0;
                 let rvalue = &assig.1;
                 match place.try_local() {
                     Some(local) => {
                         debug!("Assigning to local {:?}", local);
-                        match self.locals[local] {
+                        match &self.locals[local] {
                             LocalRef::PlaceRef(place_ref) => {
-                                self.codegen_rvalue(builder, place_ref, rvalue)
+                                let place_ref = place_ref.clone();
+                                self.codegen_rvalue(builder, place_ref, rvalue)?
+                            }
+                            // A promoted local takes a new value at each definition.
+                            LocalRef::OperandRef(_) if self.is_promoted(local) => {
+                                let operand = self.codegen_rvalue_operand(builder, rvalue)?;
+                                self.overwrite_local(local, LocalRef::OperandRef(operand));
                             }
                             LocalRef::OperandRef(operand_ref) => {
                                 // We cannot assign to an operand ref that is not a ZST
@@ -95,7 +403,7 @@ impl<'ctx, 'll, B: BuilderMethods<'ctx, 'll>> FnCtx<'ctx, 'll, B> {
                                 // because it has no value.
                                 if !operand_ref.ty_layout.is_zst() {
                                     // TODO: handle this error properly
-                                    panic!("Cannot assign to non-ZST operand ref");
+                                    self.span_bug("Cannot assign to non-ZST operand ref");
                                 }
 
                                 // For ZST, we can just ignore the assignment
@@ -103,115 +411,1261 @@ impl<'ctx, 'll, B: BuilderMethods<'ctx, 'll>> FnCtx<'ctx, 'll, B> {
                                 // to handle any side effects it may have.
                                 // For example, if the rvalue is a function call
                                 // that may panic, we need to codegen it.
-                                self.codegen_rvalue_operand(builder, rvalue);
+                                self.codegen_rvalue_operand(builder, rvalue)?;
                             }
                             LocalRef::PendingOperandRef => {
-                                let operand = self.codegen_rvalue_operand(builder, rvalue);
+                                let operand = self.codegen_rvalue_operand(builder, rvalue)?;
                                 self.overwrite_local(local, LocalRef::OperandRef(operand));
                             }
                         }
                     }
                     None => {
-                        todo!(
-                            "Handle assignment to non-local places - we have to generate the place and the rvalue"
-                        );
-                        // let place_dest = self.codegen_place(bx, place.as_ref());
-                        // self.codegen_rvalue(bx, place_dest, rvalue);
+                        let place_dest = self.codegen_place(builder, place)?;
+                        self.codegen_rvalue(builder, place_dest, rvalue)?;
+                    }
+                }
+            }
+            StatementKind::SetDiscriminant { place, variant_idx } => {
+                self.codegen_set_discr(builder, place, *variant_idx)?;
+            }
+            StatementKind::InlineAsm(asm) => self.codegen_inline_asm(builder, asm)?,
+        }
+        Ok(())
+    }
+
+    /// Codegen the given inline assembly. Its inputs and outputs must be scalars,
+    /// which are passed in registers.
+    // It corresponds to `codegen_asm_terminator` in rustc_codegen_ssa/src/mir/block.rs
+    fn codegen_inline_asm(&mut self, builder: &mut B, asm: &InlineAsm) -> CodegenResult<()> {
+        let mut operands = Vec::with_capacity(asm.operands.len());
+        // The places of the outputs returned by the backend, if they are not discarded.
+        let mut out_places = vec![];
+        for operand in &asm.operands {
+            let reg = operand.reg().clone();
+            let operand_ref = match operand {
+                InlineAsmOperand::In { value, .. } => InlineAsmOperandRef::In {
+                    reg,
+                    value: self.codegen_asm_input(builder, value)?,
+                },
+                InlineAsmOperand::Out { place, .. } => {
+                    let ty = place.as_ref().map(|place| {
+                        let place_ty =
+                            place.ty(self.ctx.lir_ctx(), self.local_ty(place.local).clone());
+                        let ty_layout = self.ctx.layout_of(place_ty.ty);
+                        if !matches!(ty_layout.backend_repr, BackendRepr::Scalar(_)) {
+                            self.span_bug("The outputs of an inline assembly must be scalars");
+                        }
+                        self.ctx.immediate_backend_type(&ty_layout)
+                    });
+                    if place.is_some() {
+                        out_places.push(place.as_ref());
+                    }
+                    InlineAsmOperandRef::Out { reg, ty }
+                }
+                InlineAsmOperand::InOut {
+                    in_value,
+                    out_place,
+                    ..
+                } => {
+                    out_places.push(out_place.as_ref());
+                    InlineAsmOperandRef::InOut {
+                        reg,
+                        value: self.codegen_asm_input(builder, in_value)?,
                     }
                 }
+            };
+            operands.push(operand_ref);
+        }
+
+        let outputs =
+            builder.codegen_inline_asm(&asm.template, &operands, &asm.clobbers, asm.options)?;
+        for (place, val) in out_places.into_iter().zip(outputs) {
+            if let Some(place) = place {
+                let place_ty = place.ty(self.ctx.lir_ctx(), self.local_ty(place.local).clone());
+                let operand = OperandRef::new_immediate(val, self.ctx.layout_of(place_ty.ty));
+                self.codegen_assign_operand(builder, place, operand)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Codegen an input of an inline assembly, which must be a scalar.
+    fn codegen_asm_input(&mut self, builder: &mut B, operand: &Operand) -> CodegenResult<B::Value> {
+        let operand = self.codegen_operand(builder, operand)?;
+        match operand.operand_val {
+            OperandVal::Immediate(val)
+                if matches!(operand.ty_layout.backend_repr, BackendRepr::Scalar(_)) =>
+            {
+                Ok(val)
             }
+            _ => self.span_bug("The inputs of an inline assembly must be scalars"),
         }
     }
 
+    /// Writes the given operand to the given place, which may be a local kept as
+    /// an operand.
+    fn codegen_assign_operand(
+        &mut self,
+        builder: &mut B,
+        place: &Place,
+        operand: OperandRef<'ctx, B::Value>,
+    ) -> CodegenResult<()> {
+        let Some(local) = place.try_local() else {
+            let place_ref = self.codegen_place(builder, place)?;
+            return Self::store_operand(builder, &operand, &place_ref);
+        };
+        match &self.locals[local] {
+            LocalRef::PendingOperandRef => {
+                self.overwrite_local(local, LocalRef::OperandRef(operand));
+            }
+            LocalRef::OperandRef(operand_ref) if operand_ref.ty_layout.is_zst() => {}
+            LocalRef::OperandRef(_) if self.is_promoted(local) => {
+                self.overwrite_local(local, LocalRef::OperandRef(operand));
+            }
+            LocalRef::OperandRef(_) => self.span_bug("Cannot assign to non-ZST operand ref"),
+            LocalRef::PlaceRef(place_ref) => Self::store_operand(builder, &operand, place_ref)?,
+        }
+        Ok(())
+    }
+
+    /// Codegen the given rvalue and store the result into the given place.
     pub fn codegen_rvalue(
         &mut self,
-        _builder: &mut B,
-        _place_ref: PlaceRef<B::Value>,
-        _rvalue: &RValue,
-    ) {
-        todo!("Implement codegen_rvalue");
+        builder: &mut B,
+        place_ref: PlaceRef<'ctx, B::Value>,
+        rvalue: &RValue,
+    ) -> CodegenResult<()> {
+        let operand = self.codegen_rvalue_operand(builder, rvalue)?;
+        Self::store_operand(builder, &operand, &place_ref)?;
+        Ok(())
+    }
+
+    /// Store the given operand into the given place.
+    fn store_operand(
+        builder: &mut B,
+        operand: &OperandRef<'ctx, B::Value>,
+        place_ref: &PlaceRef<'ctx, B::Value>,
+    ) -> CodegenResult<()> {
+        match operand.operand_val {
+            OperandVal::Zst => {}
+            OperandVal::Immediate(val) => Self::store_immediate(builder, val, place_ref)?,
+            // Each scalar of the pair is stored into its own field.
+            OperandVal::Pair(a, b) => {
+                let fields = scalar_pair_fields(builder.ctx(), &place_ref.ty_layout);
+                for ((field_idx, field_layout), val) in fields.into_iter().zip([a, b]) {
+                    let field = place_ref.project_field(builder, field_idx, field_layout)?;
+                    Self::store_immediate(builder, val, &field)?;
+                }
+            }
+            OperandVal::Ref(place_val) => builder.build_memcpy(
+                place_ref.place_val.value,
+                place_ref.place_val.align,
+                place_val.value,
+                place_val.align,
+                operand.ty_layout.size,
+            )?,
+        }
+        Ok(())
+    }
+
+    /// Stores the given immediate into the given place, with the alias metadata of its
+    /// scalar (see `MetadataMethods`).
+    fn store_immediate(
+        builder: &mut B,
+        val: B::Value,
+        place_ref: &PlaceRef<'ctx, B::Value>,
+    ) -> CodegenResult<()> {
+        let place_val = place_ref.place_val;
+        match place_ref.ty_layout.backend_repr {
+            BackendRepr::Scalar(scalar) => {
+                builder.build_scalar_store(val, scalar, place_val.value, place_val.align)?
+            }
+            _ => builder.build_store(val, place_val.value, place_val.align)?,
+        }
+        Ok(())
+    }
+
+    /// Returns a builder positioned in the entry block of the function, before its
+    /// terminator (if it is already built), to build the allocas.
+    fn alloca_builder(&self) -> B {
+        B::build_before_terminator(self.ctx, self.entry_bb)
+    }
+
+    /// Allocates the memory of a value of the given layout in the entry block of the
+    /// function, so that it is allocated once per call wherever it is used (e.g., in
+    /// a loop).
+    // It corresponds to `alloca` in rustc_codegen_llvm/src/builder.rs, which builds the
+    // allocas at the start of the entry block.
+    pub fn alloca(
+        &self,
+        layout: TyAndLayout<'ctx, LirTy>,
+    ) -> CodegenResult<PlaceRef<'ctx, B::Value>> {
+        PlaceRef::alloca(&mut self.alloca_builder(), layout)
+    }
+
+    /// Allocates the memory through which a value of the given layout is cast to (or
+    /// from) the registers of a `PassMode::Cast`. The registers cover whole eightbytes,
+    /// so the memory is rounded up to them to never access it out of bounds.
+    fn cast_scratch(&self, layout: &TyAndLayout<'ctx, LirTy>) -> CodegenResult<PlaceVal<B::Value>> {
+        let eightbyte = Align::from_bytes(8).unwrap();
+        PlaceVal::alloca(
+            &mut self.alloca_builder(),
+            layout.size.align_to(eightbyte),
+            layout.align.abi.max(eightbyte),
+        )
+    }
+
+    /// Returns the given operand as the registers it is cast to.
+    fn load_cast(
+        &self,
+        builder: &mut B,
+        operand: &OperandRef<'ctx, B::Value>,
+        cast: &CastTarget,
+    ) -> CodegenResult<B::Value> {
+        let scratch = self.cast_scratch(&operand.ty_layout)?;
+        Self::store_operand(
+            builder,
+            operand,
+            &scratch.with_layout(operand.ty_layout.clone()),
+        )?;
+        let ty = builder.ctx().cast_backend_type(cast);
+        builder.build_load(ty, scratch.value, scratch.align)
+    }
+
+    /// Stores the registers a value is cast to into the given place.
+    fn store_cast(
+        &self,
+        builder: &mut B,
+        val: B::Value,
+        place_ref: &PlaceRef<'ctx, B::Value>,
+    ) -> CodegenResult<()> {
+        let scratch = self.cast_scratch(&place_ref.ty_layout)?;
+        builder.build_store(val, scratch.value, scratch.align)?;
+        builder.build_memcpy(
+            place_ref.place_val.value,
+            place_ref.place_val.align,
+            scratch.value,
+            scratch.align,
+            place_ref.ty_layout.size,
+        )?;
+        Ok(())
+    }
+
+    /// Bind the parameters of the function to the locals of its return value and arguments,
+    /// following the function ABI.
+    // It corresponds to `arg_local_refs` in rustc_codegen_ssa/src/mir/mod.rs
+    pub fn codegen_arguments(&mut self, builder: &mut B) -> CodegenResult<()> {
+        let mut param_idx = 0;
+        let mut next_param = |builder: &mut B| {
+            let param = builder.get_param(param_idx);
+            param_idx += 1;
+            param
+        };
+
+        // The caller provides the memory for the return value as a hidden first argument.
+        if let PassMode::Indirect { .. } = self.fn_abi.ret.mode {
+            let ret_layout = self.fn_abi.ret.layout.clone();
+            let place_val = PlaceVal {
+                value: next_param(builder),
+                align: ret_layout.align.abi,
+            };
+            self.overwrite_local(
+                RETURN_LOCAL,
+                LocalRef::PlaceRef(place_val.with_layout(ret_layout)),
+            );
+        }
+
+        for (i, arg_abi) in self.fn_abi.args.iter().enumerate() {
+            let local = Local::new(RETURN_LOCAL.idx() + 1 + i);
+            let layout = arg_abi.layout.clone();
+            let operand = match arg_abi.mode {
+                PassMode::Ignore => continue,
+                PassMode::Direct(_) => OperandRef::new_immediate(next_param(builder), layout),
+                PassMode::Pair(..) => {
+                    let a = next_param(builder);
+                    let b = next_param(builder);
+                    OperandRef {
+                        operand_val: OperandVal::Pair(a, b),
+                        ty_layout: layout,
+                    }
+                }
+                // The registers are stored into the memory of the argument, which is
+                // loaded back if the argument is an operand.
+                PassMode::Cast(_) => {
+                    let param = next_param(builder);
+                    if let LocalRef::PlaceRef(place_ref) = &self.locals[local] {
+                        self.store_cast(builder, param, place_ref)?;
+                        continue;
+                    }
+                    let place_ref = self.alloca(layout)?;
+                    self.store_cast(builder, param, &place_ref)?;
+                    builder.load_operand(&place_ref)?
+                }
+                // The argument already lives in memory, in a copy made for the callee.
+                PassMode::Indirect { .. } => {
+                    let place_val = PlaceVal {
+                        value: next_param(builder),
+                        align: layout.align.abi,
+                    };
+                    self.locals[local] = LocalRef::PlaceRef(place_val.with_layout(layout));
+                    continue;
+                }
+            };
+            match &self.locals[local] {
+                LocalRef::PendingOperandRef => self.locals[local] = LocalRef::OperandRef(operand),
+                LocalRef::PlaceRef(place_ref) => Self::store_operand(builder, &operand, place_ref)?,
+                LocalRef::OperandRef(_) => panic!("Argument {:?} is already defined", local),
+            }
+        }
+        Ok(())
     }
 
     pub fn codegen_rvalue_operand(
         &mut self,
         builder: &mut B,
         rvalue: &RValue,
-    ) -> OperandRef<B::Value> {
-        match rvalue {
-            RValue::Const(const_operand) => {
-                OperandRef::new_const(builder, const_operand.value(), const_operand.ty())
+    ) -> CodegenResult<OperandRef<'ctx, B::Value>> {
+        Ok(match rvalue {
+            RValue::Use(operand) => self.codegen_operand(builder, operand)?,
+            RValue::BinaryOp(bin_op, lhs, rhs) => {
+                let lhs = self.codegen_operand(builder, lhs)?;
+                let rhs = self.codegen_operand(builder, rhs)?;
+                let lhs_ty = &lhs.ty_layout.ty;
+                let be_val = self.codegen_scalar_binop(
+                    builder,
+                    *bin_op,
+                    lhs.immediate(),
+                    rhs.immediate(),
+                    lhs_ty,
+                )?;
+                let layout = builder.ctx().layout_of(bin_op.ty(lhs_ty));
+                OperandRef::new_immediate(be_val, layout)
             }
-        }
+            RValue::UnaryOp(un_op, operand) => {
+                let operand = self.codegen_operand(builder, operand)?;
+                let is_float = operand.ty_layout.backend_repr.to_primitive().is_float();
+                let val = operand.immediate();
+                let be_val = match un_op {
+                    UnOp::Not => builder.build_not(val)?,
+                    UnOp::Neg if is_float => builder.build_fneg(val)?,
+                    UnOp::Neg => builder.build_neg(val)?,
+                };
+                OperandRef::new_immediate(be_val, operand.ty_layout)
+            }
+            RValue::Ref(place) => {
+                let place_ref = self.codegen_place(builder, place)?;
+                let layout = builder
+                    .ctx()
+                    .layout_of(LirTy::ptr_to(place_ref.ty_layout.ty.clone()));
+                OperandRef::new_immediate(place_ref.place_val.value, layout)
+            }
+            RValue::Discriminant(place) => self.codegen_get_discr(builder, place)?,
+            RValue::Cast(cast_kind, operand, cast_ty) => {
+                let operand = self.codegen_operand(builder, operand)?;
+                let cast_layout = builder.ctx().layout_of(cast_ty.clone());
+                let be_val = self.codegen_cast(builder, *cast_kind, &operand, &cast_layout)?;
+                OperandRef::new_immediate(be_val, cast_layout)
+            }
+            RValue::Intrinsic(intrinsic, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.codegen_operand(builder, arg))
+                    .collect::<CodegenResult<Vec<_>>>()?;
+                let arg_ty = args[0].ty_layout.ty.clone();
+                let values = args.iter().map(OperandRef::immediate).collect::<Vec<_>>();
+                let layout = builder.ctx().layout_of(intrinsic.ty(&arg_ty));
+                match builder.codegen_intrinsic_call(*intrinsic, &values, &arg_ty)? {
+                    Some(be_val) => OperandRef::new_immediate(be_val, layout),
+                    None => OperandRef::new_zst(layout),
+                }
+            }
+        })
+    }
+
+    /// Codegen a cast of the given (immediate) operand to the type of `cast_layout`.
+    fn codegen_cast(
+        &mut self,
+        builder: &mut B,
+        cast_kind: CastKind,
+        operand: &OperandRef<'ctx, B::Value>,
+        cast_layout: &TyAndLayout<LirTy>,
+    ) -> CodegenResult<B::Value> {
+        let val = operand.immediate();
+        let src_ty = &operand.ty_layout.ty;
+        let dest_ty = builder.ctx().backend_type(cast_layout);
+
+        // The width in bits of an integer. A boolean is a 1-bit integer, even if it
+        // is stored as a byte.
+        let int_width = |ty_layout: &TyAndLayout<LirTy>| match ty_layout.ty {
+            LirTy::Bool => 1,
+            _ => ty_layout.size.bytes() * 8,
+        };
+
+        Ok(match cast_kind {
+            CastKind::IntToInt => {
+                let src_width = int_width(&operand.ty_layout);
+                let dest_width = int_width(cast_layout);
+                if src_width < dest_width {
+                    if src_ty.is_signed() {
+                        builder.build_sext(val, dest_ty)?
+                    } else {
+                        builder.build_zext(val, dest_ty)?
+                    }
+                } else if src_width > dest_width {
+                    builder.build_trunc(val, dest_ty)?
+                } else {
+                    val
+                }
+            }
+            CastKind::FloatToInt if cast_layout.ty.is_signed() => {
+                builder.build_fptosi(val, dest_ty)?
+            }
+            CastKind::FloatToInt => builder.build_fptoui(val, dest_ty)?,
+            CastKind::IntToFloat if src_ty.is_signed() => builder.build_sitofp(val, dest_ty)?,
+            CastKind::IntToFloat => builder.build_uitofp(val, dest_ty)?,
+            CastKind::FloatToFloat => {
+                let src_size = operand.ty_layout.size.bytes();
+                let dest_size = cast_layout.size.bytes();
+                if src_size < dest_size {
+                    builder.build_fpext(val, dest_ty)?
+                } else if src_size > dest_size {
+                    builder.build_fptrunc(val, dest_ty)?
+                } else {
+                    val
+                }
+            }
+            CastKind::PtrToInt => builder.build_ptrtoint(val, dest_ty)?,
+            CastKind::IntToPtr => builder.build_inttoptr(val, dest_ty)?,
+            // Pointers are opaque, so there is nothing to do.
+            CastKind::PtrToPtr => val,
+            CastKind::Transmute => {
+                assert_eq!(
+                    operand.ty_layout.size.bytes(),
+                    cast_layout.size.bytes(),
+                    "Transmute between types of different sizes"
+                );
+                builder.build_bitcast(val, dest_ty)?
+            }
+        })
+    }
+
+    pub fn codegen_operand(
+        &mut self,
+        builder: &mut B,
+        operand: &Operand,
+    ) -> CodegenResult<OperandRef<'ctx, B::Value>> {
+        Ok(match operand {
+            Operand::Copy(place) | Operand::Move(place) => self.codegen_consume(builder, place)?,
+            Operand::Const(const_operand) => {
+                OperandRef::new_const(builder, const_operand.value(), const_operand.ty())?
+            }
+        })
+    }
+
+    /// Codegen a binary operation between two scalars of type `lhs_ty`.
+    /// The signedness of `lhs_ty` selects the signed or unsigned variant of
+    /// divisions, remainders, right shifts and comparisons.
+    fn codegen_scalar_binop(
+        &mut self,
+        builder: &mut B,
+        bin_op: BinOp,
+        lhs: B::Value,
+        rhs: B::Value,
+        lhs_ty: &LirTy,
+    ) -> CodegenResult<B::Value> {
+        let is_signed = lhs_ty.is_signed();
+        Ok(match bin_op {
+            BinOp::Add => builder.build_add(lhs, rhs)?,
+            BinOp::Sub => builder.build_sub(lhs, rhs)?,
+            BinOp::Mul => builder.build_mul(lhs, rhs)?,
+            BinOp::Div if is_signed => builder.build_sdiv(lhs, rhs)?,
+            BinOp::Div => builder.build_udiv(lhs, rhs)?,
+            BinOp::Rem if is_signed => builder.build_srem(lhs, rhs)?,
+            BinOp::Rem => builder.build_urem(lhs, rhs)?,
+            BinOp::BitXor => builder.build_xor(lhs, rhs)?,
+            BinOp::BitAnd => builder.build_and(lhs, rhs)?,
+            BinOp::BitOr => builder.build_or(lhs, rhs)?,
+            BinOp::Shl => builder.build_shl(lhs, rhs)?,
+            BinOp::Shr if is_signed => builder.build_ashr(lhs, rhs)?,
+            BinOp::Shr => builder.build_lshr(lhs, rhs)?,
+            BinOp::Eq | BinOp::Lt | BinOp::Le | BinOp::Ne | BinOp::Ge | BinOp::Gt => {
+                let pred = IntPredicate::from_bin_op(bin_op, is_signed);
+                builder.build_icmp(pred, lhs, rhs)?
+            }
+        })
     }
 
-    fn overwrite_local(&mut self, local: Local, new_ref: LocalRef<B::Value>) {
+    fn overwrite_local(&mut self, local: Local, new_ref: LocalRef<'ctx, B::Value>) {
         self.locals[local] = new_ref;
     }
 
+    /// Returns `true` if the local is promoted (see `promoted_locals`).
+    fn is_promoted(&self, local: Local) -> bool {
+        self.promoted_locals.binary_search(&local).is_ok()
+    }
+
     /// Codegen the given LIR terminator.
     /// This function is called by `codegen_basic_block` for the terminator of the basic block.
     /// It generates the corresponding instructions in the backend.
-    fn codegen_terminator(&mut self, builder: &mut B, term: &Terminator) {
+    fn codegen_terminator(&mut self, builder: &mut B, term: &Terminator) -> CodegenResult<()> {
         debug!("Codegen terminator: {:?}", term);
-        match term {
-            Terminator::Return => self.codegen_return_terminator(builder),
+        match &term.kind {
+            TerminatorKind::Goto { target } => self.codegen_goto_terminator(builder, *target)?,
+            TerminatorKind::Return => self.codegen_return_terminator(builder)?,
+            TerminatorKind::Unreachable => builder.build_unreachable()?,
+            TerminatorKind::Assert {
+                cond,
+                expected,
+                msg,
+                target,
+                unwind,
+                hint,
+            } => self.codegen_assert_terminator(
+                builder, cond, *expected, *msg, *target, *unwind, *hint,
+            )?,
+            TerminatorKind::Call {
+                func,
+                args,
+                destination,
+                target,
+                unwind,
+            } => {
+                self.codegen_call_terminator(builder, func, args, destination, *target, *unwind)?
+            }
+            TerminatorKind::Drop {
+                place,
+                target,
+                unwind,
+            } => self.codegen_drop_terminator(builder, place, *target, *unwind)?,
+            TerminatorKind::UnwindResume => self.codegen_resume_terminator(builder)?,
+        }
+        Ok(())
+    }
+
+    /// Returns the backend block to unwind to for the given action, that is, the
+    /// landing pad of its cleanup block, if any. Otherwise, the unwinding continues
+    /// in the caller (or it is undefined behavior, for `UnwindAction::Unreachable`).
+    fn unwind_block(&mut self, unwind: UnwindAction) -> CodegenResult<Option<B::BasicBlock>> {
+        unwind
+            .cleanup()
+            .map(|cleanup| self.landing_pad_for(cleanup))
+            .transpose()
+    }
+
+    /// Returns the landing pad of the given cleanup block, creating it if needed. The
+    /// landing pad stores the exception in the personality slot, from which it is
+    /// resumed, and jumps to the cleanup block.
+    // It corresponds to `landing_pad_for` in rustc_codegen_ssa/src/mir/block.rs
+    fn landing_pad_for(&mut self, cleanup: BasicBlock) -> CodegenResult<B::BasicBlock> {
+        if let Some(landing_pad) = self.landing_pads[cleanup] {
+            return Ok(landing_pad);
+        }
+
+        let Some(slot) = self.personality_slot.clone() else {
+            self.span_bug(format!(
+                "Unwinding to the block {:?}, which is not a cleanup block",
+                cleanup
+            ));
+        };
+        let landing_pad = B::append_basic_block(self.ctx, self.fn_value, "cleanup");
+        let mut lp_builder = B::build(self.ctx, landing_pad);
+        if let Some(dbg_loc) = self.dbg_loc {
+            lp_builder.set_debug_loc(dbg_loc);
+        }
+        let (exn, selector) = lp_builder.build_cleanup_landing_pad()?;
+        let exception = OperandRef {
+            operand_val: OperandVal::Pair(exn, selector),
+            ty_layout: slot.ty_layout.clone(),
+        };
+        Self::store_operand(&mut lp_builder, &exception, &slot)?;
+        let cleanup_bb = self.get_or_insert_bb(cleanup);
+        lp_builder.build_br(cleanup_bb)?;
+
+        self.landing_pads[cleanup] = Some(landing_pad);
+        Ok(landing_pad)
+    }
+
+    /// Codegen a resume terminator, which continues unwinding the exception stored
+    /// in the personality slot by the landing pads.
+    fn codegen_resume_terminator(&mut self, builder: &mut B) -> CodegenResult<()> {
+        let Some(slot) = &self.personality_slot else {
+            self.span_bug("Resuming the unwinding outside of a cleanup block");
+        };
+        match builder.load_operand(slot)?.operand_val {
+            OperandVal::Pair(exn, selector) => builder.build_resume(exn, selector)?,
+            operand_val => self.span_bug(format!(
+                "The exception must be a pair, found {:?}",
+                operand_val
+            )),
+        }
+        Ok(())
+    }
+
+    /// Codegen a call terminator.
+    ///
+    /// The callee `FnAbi` is computed from the types at the call site (the destination
+    /// and the arguments), which must match the signature of the callee. The arguments
+    /// are lowered according to their `PassMode` and the result, if any, is written
+    /// to the destination place. Finally, it branches to the `target` block, or it is
+    /// followed by an unreachable instruction if the callee never returns.
+    ///
+    /// If the call unwinds to a cleanup block, it is lowered to an invoke whose
+    /// landing pad jumps to the cleanup block.
+    fn codegen_call_terminator(
+        &mut self,
+        builder: &mut B,
+        func: &Callee,
+        args: &[Operand],
+        destination: &Place,
+        target: Option<BasicBlock>,
+        unwind: UnwindAction,
+    ) -> CodegenResult<()> {
+        let mut arg_operands = args
+            .iter()
+            .map(|arg| self.codegen_operand(builder, arg))
+            .collect::<CodegenResult<Vec<_>>>()?;
+
+        let (sig, is_variadic, conv) = match func {
+            Callee::Generic(def_id, _) => self.span_bug(format!(
+                "Generic callee {:?} must be instantiated before codegen",
+                def_id
+            )),
+            // The ABI of a direct call comes from the signature of the callee.
+            Callee::Direct(def_id) => {
+                let callee = self.ctx.lir_ctx().callee(*def_id);
+                let fixed_count = callee.ret_and_args.len() - 1;
+                if arg_operands.len() < fixed_count
+                    || (arg_operands.len() > fixed_count && !callee.metadata.is_variadic)
+                {
+                    self.span_bug(format!(
+                        "Wrong number of arguments in the call to {}",
+                        callee.metadata.name
+                    ));
+                }
+                (
+                    callee.ret_and_args.iter().cloned().collect(),
+                    callee.metadata.is_variadic,
+                    callee.metadata.call_conv,
+                )
+            }
+            // The ABI of an indirect call comes from the signature of the function pointer,
+            // which is called with the C calling convention.
+            // TODO(bruzzone): add the calling convention to the function pointer types.
+            Callee::Indirect(fn_ptr) => {
+                let LirTy::FnPtr(fn_sig) = self.operand_ty(fn_ptr) else {
+                    self.span_bug(format!(
+                        "Indirect callee {:?} is not a function pointer",
+                        fn_ptr
+                    ));
+                };
+                if arg_operands.len() < fn_sig.args.len()
+                    || (arg_operands.len() > fn_sig.args.len() && !fn_sig.is_variadic)
+                {
+                    self.span_bug("Wrong number of arguments in the indirect call");
+                }
+                (fn_sig.ret_and_args(), fn_sig.is_variadic, CallConv::C)
+            }
+        };
+        let fn_abi = if is_variadic {
+            // The variadic arguments are passed after the C default argument promotions.
+            let fixed_count = sig.len() - 1;
+            for operand in &mut arg_operands[fixed_count..] {
+                *operand = self.promote_variadic_arg(builder, operand)?;
+            }
+            let variadic_args = arg_operands[fixed_count..]
+                .iter()
+                .map(|operand| operand.ty_layout.ty.clone())
+                .collect::<Vec<_>>();
+            self.ctx
+                .fn_abi_of_variadic(self.ctx.lir_ctx(), &sig, &variadic_args, conv)
+        } else {
+            self.ctx.fn_abi_of(self.ctx.lir_ctx(), &sig, conv)
+        };
+
+        let mut be_args = Vec::with_capacity(arg_operands.len() + 1);
+        // The memory of a value returned indirectly into a local kept as an operand,
+        // from which the value is loaded after the call.
+        let mut ret_scratch = None;
+        if let PassMode::Indirect { .. } = fn_abi.ret.mode {
+            // The caller provides the memory for the return value as a hidden first argument.
+            let ret_place = match destination.try_local().map(|local| &self.locals[local]) {
+                Some(LocalRef::PlaceRef(place_ref)) => place_ref.clone(),
+                Some(_) => {
+                    let scratch = self.alloca(fn_abi.ret.layout.clone())?;
+                    ret_scratch = Some(scratch.clone());
+                    scratch
+                }
+                None => self.codegen_place(builder, destination)?,
+            };
+            be_args.push(ret_place.place_val.value);
+        }
+        for (operand, arg_abi) in arg_operands.iter().zip(fn_abi.args.iter()) {
+            match &arg_abi.mode {
+                PassMode::Ignore => {}
+                PassMode::Direct(_) => match operand.operand_val {
+                    OperandVal::Immediate(val) => be_args.push(val),
+                    // The argument is loaded from its memory, as a whole if it is an
+                    // aggregate.
+                    OperandVal::Ref(place_val) => {
+                        let place_ref = place_val.with_layout(operand.ty_layout.clone());
+                        let val = match builder.load_operand(&place_ref)?.operand_val {
+                            OperandVal::Immediate(val) => val,
+                            OperandVal::Ref(place_val) => {
+                                let ty = builder.ctx().backend_type(&operand.ty_layout);
+                                builder.build_load(ty, place_val.value, place_val.align)?
+                            }
+                            operand_val => self.span_bug(format!(
+                                "Cannot pass {:?} as a direct argument",
+                                operand_val
+                            )),
+                        };
+                        be_args.push(val);
+                    }
+                    OperandVal::Zst => self.span_bug("ZST arguments should be ignored"),
+                    OperandVal::Pair(_, _) => {
+                        self.span_bug("Pair arguments must be passed as a pair")
+                    }
+                },
+                PassMode::Pair(..) => match operand.operand_val {
+                    OperandVal::Pair(a, b) => be_args.extend([a, b]),
+                    ref operand_val => {
+                        self.span_bug(format!("Expected a pair argument, found {:?}", operand_val))
+                    }
+                },
+                PassMode::Cast(cast) => be_args.push(self.load_cast(builder, operand, cast)?),
+                // The callee owns the memory of an indirect argument (and may write it),
+                // so it gets a copy, unless the backend copies it on the stack (`byval`).
+                PassMode::Indirect { on_stack, .. } => match operand.operand_val {
+                    OperandVal::Ref(place_val) if *on_stack => be_args.push(place_val.value),
+                    _ => {
+                        let copy = self.alloca(operand.ty_layout.clone())?;
+                        Self::store_operand(builder, operand, &copy)?;
+                        be_args.push(copy.place_val.value);
+                    }
+                },
+            }
+        }
+
+        // The value returned by an invoke is only available in its normal destination,
+        // which is a new block that continues at `target`.
+        let catch = self.unwind_block(unwind)?;
+        let then = catch.map(|_| B::append_basic_block(self.ctx, self.fn_value, "invoke_ret"));
+        let ret_val = match func {
+            Callee::Direct(def_id) => {
+                let fn_value = self.ctx.get_fn_by_def_id(*def_id).unwrap_or_else(|| {
+                    self.span_bug(format!("Callee {:?} is not declared", def_id))
+                });
+                debug!("Call {:?} with args {:?}", fn_value, be_args);
+                match then.zip(catch) {
+                    Some((then, catch)) => {
+                        builder.build_invoke(&fn_abi, fn_value, &be_args, then, catch)?
+                    }
+                    None => builder.build_call(&fn_abi, fn_value, &be_args)?,
+                }
+            }
+            Callee::Generic(..) => unreachable!("Generic callees are rejected above"),
+            Callee::Indirect(fn_ptr) => {
+                let fn_ptr = self.codegen_operand(builder, fn_ptr)?.immediate();
+                debug!("Indirect call {:?} with args {:?}", fn_ptr, be_args);
+                match then.zip(catch) {
+                    Some((then, catch)) => {
+                        builder.build_indirect_invoke(&fn_abi, fn_ptr, &be_args, then, catch)?
+                    }
+                    None => builder.build_indirect_call(&fn_abi, fn_ptr, &be_args)?,
+                }
+            }
+        };
+        if let Some(then) = then {
+            *builder = B::build(self.ctx, then);
+            if let Some(dbg_loc) = self.dbg_loc {
+                builder.set_debug_loc(dbg_loc);
+            }
+        }
+
+        if let Some(scratch) = ret_scratch {
+            let operand = builder.load_operand(&scratch)?;
+            self.codegen_assign_operand(builder, destination, operand)?;
+        } else if let Some(val) = ret_val {
+            let operand = match fn_abi.ret.mode {
+                // A pair is returned as an aggregate of its two scalars.
+                PassMode::Pair(..) => {
+                    let a = builder.build_extract_value(val, 0)?;
+                    let b = builder.build_extract_value(val, 1)?;
+                    OperandRef {
+                        operand_val: OperandVal::Pair(a, b),
+                        ty_layout: fn_abi.ret.layout,
+                    }
+                }
+                // The registers are stored to memory, from which the value is loaded.
+                PassMode::Cast(_) => {
+                    let place_ref = self.alloca(fn_abi.ret.layout)?;
+                    self.store_cast(builder, val, &place_ref)?;
+                    builder.load_operand(&place_ref)?
+                }
+                _ => OperandRef::new_immediate(val, fn_abi.ret.layout),
+            };
+            self.codegen_assign_operand(builder, destination, operand)?;
+        }
+
+        match target {
+            Some(target) => self.codegen_goto_terminator(builder, target)?,
+            // The callee never returns.
+            None => builder.build_unreachable()?,
+        }
+        Ok(())
+    }
+
+    /// Returns the given variadic argument of a call after the C default argument
+    /// promotions (see `LirTy::c_variadic_promoted`).
+    fn promote_variadic_arg(
+        &mut self,
+        builder: &mut B,
+        operand: &OperandRef<'ctx, B::Value>,
+    ) -> CodegenResult<OperandRef<'ctx, B::Value>> {
+        let promoted_ty = operand.ty_layout.ty.c_variadic_promoted();
+        if promoted_ty == operand.ty_layout.ty {
+            return Ok(operand.clone());
+        }
+        let cast_kind = if promoted_ty.is_floating_point() {
+            CastKind::FloatToFloat
+        } else {
+            CastKind::IntToInt
+        };
+        let promoted_layout = self.ctx.layout_of(promoted_ty);
+        let val = self.codegen_cast(builder, cast_kind, operand, &promoted_layout)?;
+        Ok(OperandRef::new_immediate(val, promoted_layout))
+    }
+
+    /// Codegen a drop terminator.
+    ///
+    /// It calls (or invokes, if it unwinds to a cleanup block) the drop glue of the
+    /// type of the place with the address of the place, and then it branches to the
+    /// `target` block. A type without drop glue is not dropped.
+    // It corresponds to `codegen_drop_terminator` in rustc_codegen_ssa/src/mir/block.rs
+    fn codegen_drop_terminator(
+        &mut self,
+        builder: &mut B,
+        place: &Place,
+        target: BasicBlock,
+        unwind: UnwindAction,
+    ) -> CodegenResult<()> {
+        let ty = place
+            .ty(self.ctx.lir_ctx(), self.local_ty(place.local).clone())
+            .ty;
+        let Some(drop_glue) = self.ctx.lir_ctx().drop_glue(&ty) else {
+            debug!("The type {:?} has no drop glue", ty);
+            return self.codegen_goto_terminator(builder, target);
+        };
+
+        let place_ref = self.codegen_place(builder, place)?;
+        let fn_value = self.ctx.get_fn_by_def_id(drop_glue).unwrap_or_else(|| {
+            self.span_bug(format!("The drop glue {:?} is not declared", drop_glue))
+        });
+        let drop_glue_body = self.ctx.lir_ctx().callee(drop_glue);
+        let fn_abi = self.ctx.fn_abi_of(
+            self.ctx.lir_ctx(),
+            &drop_glue_body.ret_and_args,
+            drop_glue_body.metadata.call_conv,
+        );
+        let args = [place_ref.place_val.value];
+        debug!("Drop {:?} with {:?}", place, fn_value);
+        match self.unwind_block(unwind)? {
+            Some(catch) => {
+                let then = B::append_basic_block(self.ctx, self.fn_value, "drop_ret");
+                builder.build_invoke(&fn_abi, fn_value, &args, then, catch)?;
+                *builder = B::build(self.ctx, then);
+                if let Some(dbg_loc) = self.dbg_loc {
+                    builder.set_debug_loc(dbg_loc);
+                }
+            }
+            None => {
+                builder.build_call(&fn_abi, fn_value, &args)?;
+            }
+        }
+
+        self.codegen_goto_terminator(builder, target)
+    }
+
+    /// Returns the type of the given operand.
+    fn operand_ty(&self, operand: &Operand) -> LirTy {
+        match operand {
+            Operand::Copy(place) | Operand::Move(place) => {
+                place
+                    .ty(self.ctx.lir_ctx(), self.local_ty(place.local).clone())
+                    .ty
+            }
+            Operand::Const(const_operand) => const_operand.ty(),
+        }
+    }
+
+    /// Returns the type of the given local, which can be either the return value,
+    /// an argument, or any other local of the body.
+    fn local_ty(&self, local: Local) -> &'ctx LirTy {
+        let ret_and_args_len = self.lir_body.ret_and_args.len();
+        if local.idx() < ret_and_args_len {
+            &self.lir_body.ret_and_args[local].ty
+        } else {
+            &self.lir_body.locals[Local::new(local.idx() - ret_and_args_len)].ty
+        }
+    }
+
+    /// Codegen a goto terminator.
+    /// This emits an unconditional branch to the backend block of `target`.
+    fn codegen_goto_terminator(
+        &mut self,
+        builder: &mut B,
+        target: BasicBlock,
+    ) -> CodegenResult<()> {
+        let target_bb = self.get_or_insert_bb(target);
+        builder.build_br(target_bb)?;
+        Ok(())
+    }
+
+    /// Codegen an assert terminator.
+    ///
+    /// The failure path is emitted in a new backend block that calls the panic
+    /// handler with the message of the given `AssertKind`, which is invoked if the
+    /// handler unwinds to a cleanup block. The `hint` is the likelihood of the success
+    /// path, if known.
+    #[allow(clippy::too_many_arguments)]
+    fn codegen_assert_terminator(
+        &mut self,
+        builder: &mut B,
+        cond: &Operand,
+        expected: bool,
+        msg: AssertKind,
+        target: BasicBlock,
+        unwind: UnwindAction,
+        hint: Option<BranchHint>,
+    ) -> CodegenResult<()> {
+        let cond = match self.codegen_operand(builder, cond)?.operand_val {
+            OperandVal::Immediate(val) => val,
+            operand_val => self.span_bug(format!(
+                "Assert condition must be an immediate, found {:?}",
+                operand_val
+            )),
+        };
+
+        let target_bb = self.get_or_insert_bb(target);
+        let panic_bb = B::append_basic_block(self.ctx, self.fn_value, "panic");
+        if expected {
+            builder.build_cond_br(cond, target_bb, panic_bb, hint)?;
+        } else {
+            builder.build_cond_br(cond, panic_bb, target_bb, hint.map(BranchHint::invert))?;
         }
+
+        let catch = self.unwind_block(unwind)?;
+        let mut panic_builder = B::build(self.ctx, panic_bb);
+        if let Some(dbg_loc) = self.dbg_loc {
+            panic_builder.set_debug_loc(dbg_loc);
+        }
+        panic_builder.build_panic(msg.description(), catch)?;
+        Ok(())
     }
 
     /// Codegen a return terminator.
     /// This function generates the return instruction for the function.
     /// It handles different return modes based on the function ABI.
-    fn codegen_return_terminator(&mut self, builder: &mut B) {
-        let be_val = match self.fn_abi.ret.mode {
-            PassMode::Ignore | PassMode::Indirect => {
+    fn codegen_return_terminator(&mut self, builder: &mut B) -> CodegenResult<()> {
+        let be_val = match &self.fn_abi.ret.mode {
+            PassMode::Ignore | PassMode::Indirect { .. } => {
                 info!("Handling ignored or indirect return");
-                builder.build_return(None);
-                return;
+                return builder.build_return(None);
             }
-            PassMode::Direct => {
+            PassMode::Direct(_) | PassMode::Pair(..) => {
                 info!("Handling direct return");
-                let operand_ref = self.codegen_consume(builder, RETURN_LOCAL);
+                let mut operand_ref = self.codegen_consume(builder, &Place::from(RETURN_LOCAL))?;
+                // The return value is returned in registers, so it is loaded from its
+                // memory (e.g., if the address of the return local is taken).
+                if let OperandVal::Ref(place_val) = operand_ref.operand_val {
+                    let place_ref = place_val.with_layout(operand_ref.ty_layout.clone());
+                    operand_ref = builder.load_operand(&place_ref)?;
+                }
                 match operand_ref.operand_val {
-                    OperandVal::Zst => todo!("Handle return of ZST. Should be unreachable?"),
-                    OperandVal::Ref(_) => todo!("Handle return by reference — load from place"),
-                    OperandVal::Pair(_, _) => {
-                        todo!("Handle return of pair. That is, create an LLVM pair and return it")
+                    OperandVal::Zst => self.span_bug("A ZST return value must be ignored"),
+                    OperandVal::Ref(_) => self.span_bug(format!(
+                        "Return value of memory type {:?} must be returned indirectly",
+                        operand_ref.ty_layout.ty
+                    )),
+                    // The two scalars are returned together as an aggregate.
+                    OperandVal::Pair(a, b) => {
+                        let ty = builder.ctx().immediate_backend_type(&operand_ref.ty_layout);
+                        let agg = builder.ctx().const_undef(ty);
+                        let agg = builder.build_insert_value(agg, a, 0)?;
+                        builder.build_insert_value(agg, b, 1)?
                     }
                     OperandVal::Immediate(val) => val,
                 }
             }
+            PassMode::Cast(cast) => {
+                info!("Handling cast return");
+                let cast = cast.clone();
+                let operand_ref = self.codegen_consume(builder, &Place::from(RETURN_LOCAL))?;
+                self.load_cast(builder, &operand_ref, &cast)?
+            }
         };
 
-        builder.build_return(Some(be_val));
+        builder.build_return(Some(be_val))
     }
 
-    fn codegen_consume(&mut self, builder: &mut B, local: Local) -> OperandRef<B::Value> {
-        let layout = builder
-            .ctx()
-            .layout_of(self.lir_body.ret_and_args[local].ty);
+    /// Codegen the read of the value stored in the given place.
+    /// A copy and a move of a place are both lowered to a read.
+    fn codegen_consume(
+        &mut self,
+        builder: &mut B,
+        place: &Place,
+    ) -> CodegenResult<OperandRef<'ctx, B::Value>> {
+        let Some(local) = place.try_local() else {
+            // For most places, to consume them we just load them out from their home.
+            let place_ref = self.codegen_place(builder, place)?;
+            return builder.load_operand(&place_ref);
+        };
 
+        let layout = builder.ctx().layout_of(self.local_ty(local).clone());
         if layout.is_zst() {
-            return OperandRef::new_zst(layout);
+            return Ok(OperandRef::new_zst(layout));
         }
 
         let local_ref = &self.locals[local];
         match local_ref {
-            LocalRef::OperandRef(operand_ref) => {
-                // TODO(bruzzone): we should handle projections here
-                *operand_ref
-            }
+            LocalRef::OperandRef(operand_ref) => Ok(operand_ref.clone()),
             LocalRef::PlaceRef(place_ref) => builder.load_operand(place_ref),
-            LocalRef::PendingOperandRef => {
-                panic!(
-                    "Cannot consume a pending operand ref {:?} before it is defined",
-                    local_ref
+            LocalRef::PendingOperandRef => self.span_bug(format!(
+                "Cannot consume the local {:?} before it is defined",
+                local
+            )),
+        }
+    }
+
+    #[instrument(level = "debug", skip(self, builder))]
+    /// Codegen the given place, walking its projections to compute the memory location
+    /// it refers to.
+    // It corresponds to `codegen_place` in rustc_codegen_ssa/src/mir/place.rs
+    pub fn codegen_place(
+        &mut self,
+        builder: &mut B,
+        place: &Place,
+    ) -> CodegenResult<PlaceRef<'ctx, B::Value>> {
+        let lir_ctx = self.ctx.lir_ctx();
+        let (mut place_ref, projection) = match &self.locals[place.local] {
+            LocalRef::PlaceRef(place_ref) => (place_ref.clone(), &place.projection[..]),
+            // A pointer kept as an operand has no home, but it can still be dereferenced.
+            LocalRef::OperandRef(operand_ref)
+                if matches!(place.projection.first(), Some(Projection::Deref)) =>
+            {
+                let pointee_ty = PlaceTy::from_ty(operand_ref.ty_layout.ty.clone())
+                    .projection_ty(lir_ctx, &Projection::Deref)
+                    .ty;
+                let pointee_layout = builder.ctx().layout_of(pointee_ty);
+                let place_val = PlaceVal {
+                    value: operand_ref.immediate(),
+                    align: pointee_layout.align.abi,
+                };
+                (
+                    place_val.with_layout(pointee_layout),
+                    &place.projection[1..],
+                )
+            }
+            local_ref => self.span_bug(format!(
+                "Cannot codegen place {:?}, its local is {:?}",
+                place, local_ref
+            )),
+        };
+        let mut place_ty = PlaceTy::from_ty(place_ref.ty_layout.ty.clone());
+
+        for projection in projection {
+            place_ty = place_ty.projection_ty(lir_ctx, projection);
+            place_ref = match *projection {
+                Projection::Deref => {
+                    let pointee_layout = builder.ctx().layout_of(place_ty.ty.clone());
+                    let ptr = builder.load_operand(&place_ref)?.immediate();
+                    PlaceVal {
+                        value: ptr,
+                        align: pointee_layout.align.abi,
+                    }
+                    .with_layout(pointee_layout)
+                }
+                Projection::Field(field_idx) => {
+                    let field_layout = builder.ctx().layout_of(place_ty.ty.clone());
+                    place_ref.project_field(builder, field_idx, field_layout)?
+                }
+                Projection::Index(index) => {
+                    let elem_layout = builder.ctx().layout_of(place_ty.ty.clone());
+                    let index = self
+                        .codegen_consume(builder, &Place::from(index))?
+                        .immediate();
+                    place_ref.project_index(builder, index, elem_layout)?
+                }
+                // The variant lives at the same address as the enum, only the layout changes.
+                Projection::Downcast(variant_idx) => PlaceRef {
+                    place_val: place_ref.place_val,
+                    ty_layout: place_ref.ty_layout.for_variant(variant_idx),
+                },
+            };
+        }
+
+        Ok(place_ref)
+    }
+
+    /// Codegen the read of the discriminant of the enum in the given place.
+    // It corresponds to `codegen_get_discr` in rustc_codegen_ssa/src/mir/place.rs
+    fn codegen_get_discr(
+        &mut self,
+        builder: &mut B,
+        place: &Place,
+    ) -> CodegenResult<OperandRef<'ctx, B::Value>> {
+        let place_ref = self.codegen_place(builder, place)?;
+        let discr_layout = builder
+            .ctx()
+            .layout_of(self.discr_ty(&place_ref.ty_layout.ty));
+        let discr_val = match &place_ref.ty_layout.variants {
+            // The variant is statically known (e.g., a struct).
+            Variants::Single { index } => {
+                Self::const_int(builder, &discr_layout, index.idx() as u128)
+            }
+            Variants::Multiple {
+                tag,
+                tag_offset,
+                tag_encoding,
+                ..
+            } => {
+                let tag_layout = builder.ctx().layout_of(self.tag_ty(*tag));
+                let tag_ptr = place_ref.place_val.offset_by(builder, *tag_offset)?.value;
+                let tag_val = builder.build_load(
+                    builder.ctx().backend_type(&tag_layout),
+                    tag_ptr,
+                    place_ref.place_val.align.min(tag_layout.align.abi),
+                )?;
+                let discr_val = match tag_encoding {
+                    // The value of the tag is the variant index.
+                    TagEncoding::Direct => tag_val,
+                    // The tag is `niche_start + (variant - niche_variants.start())` for the
+                    // niche variants, and any other value for the untagged variant.
+                    TagEncoding::Niche {
+                        untagged_variant,
+                        niche_variants,
+                        niche_start,
+                    } => {
+                        let niche_start = Self::const_int(builder, &tag_layout, *niche_start);
+                        let relative = builder.build_sub(tag_val, niche_start)?;
+                        let relative_max =
+                            (niche_variants.end().idx() - niche_variants.start().idx()) as u128;
+                        let relative_max = Self::const_int(builder, &tag_layout, relative_max);
+                        let is_niche =
+                            builder.build_icmp(IntPredicate::IntULE, relative, relative_max)?;
+                        let first_niche_variant = Self::const_int(
+                            builder,
+                            &tag_layout,
+                            niche_variants.start().idx() as u128,
+                        );
+                        let niche_discr = builder.build_add(relative, first_niche_variant)?;
+                        let untagged_discr =
+                            Self::const_int(builder, &tag_layout, untagged_variant.idx() as u128);
+                        builder.build_select(is_niche, niche_discr, untagged_discr)?
+                    }
+                };
+                // The tag and the discriminant may have different sizes.
+                let discr_ty = builder.ctx().backend_type(&discr_layout);
+                match tag_layout.size.bytes().cmp(&discr_layout.size.bytes()) {
+                    Ordering::Less => builder.build_zext(discr_val, discr_ty)?,
+                    Ordering::Greater => builder.build_trunc(discr_val, discr_ty)?,
+                    Ordering::Equal => discr_val,
+                }
+            }
+        };
+        Ok(OperandRef::new_immediate(discr_val, discr_layout))
+    }
+
+    /// Codegen the write of the discriminant of the enum in the given place.
+    // It corresponds to `codegen_set_discr` in rustc_codegen_ssa/src/mir/place.rs
+    fn codegen_set_discr(
+        &mut self,
+        builder: &mut B,
+        place: &Place,
+        variant_idx: VariantIdx,
+    ) -> CodegenResult<()> {
+        let place_ref = self.codegen_place(builder, place)?;
+        match &place_ref.ty_layout.variants {
+            Variants::Single { index } => {
+                assert_eq!(
+                    *index, variant_idx,
+                    "Cannot set the discriminant of a single-variant type to another variant"
                 );
             }
+            Variants::Multiple {
+                tag,
+                tag_offset,
+                tag_encoding,
+                ..
+            } => {
+                let tag_value = match tag_encoding {
+                    TagEncoding::Direct => variant_idx.idx() as u128,
+                    // The untagged variant is identified by its data, so there is nothing to write.
+                    TagEncoding::Niche {
+                        untagged_variant, ..
+                    } if *untagged_variant == variant_idx => return Ok(()),
+                    TagEncoding::Niche {
+                        niche_variants,
+                        niche_start,
+                        ..
+                    } => {
+                        let relative = (variant_idx.idx() - niche_variants.start().idx()) as u128;
+                        relative.wrapping_add(*niche_start)
+                    }
+                };
+                let tag_layout = builder.ctx().layout_of(self.tag_ty(*tag));
+                let tag_val = Self::const_int(builder, &tag_layout, tag_value);
+                let tag_ptr = place_ref.place_val.offset_by(builder, *tag_offset)?.value;
+                builder.build_store(
+                    tag_val,
+                    tag_ptr,
+                    place_ref.place_val.align.min(tag_layout.align.abi),
+                )?;
+            }
         }
+        Ok(())
+    }
 
-        // for most places, to consume them we just load them
-        // out from their home
-        // let place = self.codegen_place(bx, place_ref);
-        // bx.load_operand(place)
+    /// Returns the integer constant `value` of the given integer type, truncated to its size.
+    fn const_int(builder: &B, ty_layout: &TyAndLayout<LirTy>, value: u128) -> B::Value {
+        let ctx = builder.ctx();
+        ctx.const_uint_big(ctx.backend_type(ty_layout), value)
+    }
+
+    /// Returns the unsigned integer type used to read and write a tag of the given
+    /// primitive. A pointer tag (e.g., the niche of a function pointer) is accessed
+    /// as an integer of the same size.
+    fn tag_ty(&self, primitive: Primitive) -> LirTy {
+        match primitive {
+            Primitive::I8 | Primitive::U8 => LirTy::U8,
+            Primitive::I16 | Primitive::U16 => LirTy::U16,
+            Primitive::I32 | Primitive::U32 => LirTy::U32,
+            Primitive::I64 | Primitive::U64 => LirTy::U64,
+            Primitive::I128 | Primitive::U128 => LirTy::U128,
+            Primitive::Pointer(addr_space) => match self
+                .ctx
+                .lir_ctx()
+                .target()
+                .data_layout
+                .pointer_size_in(addr_space)
+            {
+                8 => LirTy::U8,
+                16 => LirTy::U16,
+                32 => LirTy::U32,
+                64 => LirTy::U64,
+                pointer_size => panic!("Unsupported pointer size {}", pointer_size),
+            },
+            Primitive::F16 | Primitive::F32 | Primitive::F64 | Primitive::F128 => {
+                panic!("A tag cannot be a floating-point number")
+            }
+        }
+    }
+
+    /// Returns the type of the discriminant of the given type.
+    fn discr_ty(&self, ty: &LirTy) -> LirTy {
+        match ty {
+            LirTy::Adt(adt_id) => self.ctx.lir_ctx().adt_def(*adt_id).discr_ty(),
+            _ => self.span_bug(format!("Type {:?} has no discriminant", ty)),
+        }
     }
 }
@@ -0,0 +1,86 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use tidec_lir::lir::DefId;
+
+#[derive(Debug)]
+/// An error raised while codegening a unit.
+pub enum CodegenError {
+    /// The backend rejected the code generated for a unit (e.g., the LLVM verifier
+    /// failed). It holds the body whose code is invalid, if the verifier could find
+    /// it, and the output of the verifier.
+    InvalidModule {
+        unit_name: String,
+        body: Option<(DefId, String)>,
+        message: String,
+    },
+    /// The backend failed to build an instruction (e.g., as its operands do not have
+    /// the expected types). It holds the instruction and the error of the backend.
+    BuildFailed {
+        instruction: &'static str,
+        message: String,
+    },
+    /// The backend failed to write a file (e.g., an object). It holds the path of
+    /// the file and the error of the backend.
+    WriteFailed { path: PathBuf, message: String },
+    /// The backend failed a step of the compilation of a unit other than building
+    /// its code (e.g., creating the target machine or running the passes). It holds
+    /// the step and the error of the backend.
+    BackendFailed { step: String, message: String },
+    /// The pass pipeline given with `--passes` is rejected by the backend (e.g., as
+    /// it names an unknown pass). It holds the pipeline and the error of the backend.
+    InvalidPassPipeline { pipeline: String, message: String },
+}
+
+/// The result of the codegen methods which may fail (see `traits::BuilderMethods`).
+pub type CodegenResult<T> = Result<T, CodegenError>;
+
+impl fmt::Display for CodegenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodegenError::InvalidModule {
+                unit_name,
+                body: Some((def_id, name)),
+                message,
+            } => write!(
+                f,
+                "invalid code generated for `{}` ({:?}) in the unit `{}`:\n{}",
+                name,
+                def_id,
+                unit_name,
+                message.trim_end()
+            ),
+            CodegenError::InvalidModule {
+                unit_name,
+                body: None,
+                message,
+            } => write!(
+                f,
+                "invalid code generated for the unit `{}`:\n{}",
+                unit_name,
+                message.trim_end()
+            ),
+            CodegenError::BuildFailed {
+                instruction,
+                message,
+            } => write!(
+                f,
+                "failed to build the `{}` instruction: {}",
+                instruction, message
+            ),
+            CodegenError::WriteFailed { path, message } => {
+                write!(f, "failed to write `{}`: {}", path.display(), message)
+            }
+            CodegenError::BackendFailed { step, message } => {
+                write!(f, "failed to {}: {}", step, message)
+            }
+            CodegenError::InvalidPassPipeline { pipeline, message } => write!(
+                f,
+                "invalid pass pipeline `{}` given with `--passes`: {}",
+                pipeline, message
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CodegenError {}
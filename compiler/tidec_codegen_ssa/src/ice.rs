@@ -0,0 +1,92 @@
+//! The breadcrumbs of the codegen, which locate an internal compiler error (ICE).
+//!
+//! The body and the basic block being codegened by a thread are recorded in a
+//! thread-local "codegen context", so that the panic hook of the driver can report
+//! where the codegen panicked. When a body is left while panicking, its LIR is
+//! printed and kept until the driver takes it to write it for the bug report.
+
+use std::cell::RefCell;
+use std::sync::{Mutex, PoisonError};
+
+use tidec_lir::basic_blocks::BasicBlock;
+use tidec_lir::lir::{DefId, LirBody, LirCtx};
+use tidec_lir::pretty::LirPrinter;
+
+thread_local! {
+    static CODEGEN_CONTEXT: RefCell<Option<CodegenContext>> = const { RefCell::new(None) };
+}
+
+/// The LIR of the bodies left while panicking, from any thread.
+static LIR_DUMPS: Mutex<Vec<LirDump>> = Mutex::new(Vec::new());
+
+#[derive(Debug, Clone)]
+/// The body (and the basic block) being codegened by the current thread.
+pub struct CodegenContext {
+    pub def_id: DefId,
+    pub body_name: String,
+    /// The basic block being codegened, if any (i.e., `None` while the locals and
+    /// the arguments are codegened).
+    pub basic_block: Option<BasicBlock>,
+}
+
+#[derive(Debug, Clone)]
+/// The LIR of a body whose codegen panicked.
+pub struct LirDump {
+    pub def_id: DefId,
+    pub body_name: String,
+    pub lir: String,
+}
+
+/// Returns the codegen context of the current thread, if it is codegening a body.
+pub fn codegen_context() -> Option<CodegenContext> {
+    CODEGEN_CONTEXT.with_borrow(|context| context.clone())
+}
+
+/// Takes the LIR of the bodies whose codegen panicked.
+pub fn take_lir_dumps() -> Vec<LirDump> {
+    std::mem::take(&mut *LIR_DUMPS.lock().unwrap_or_else(PoisonError::into_inner))
+}
+
+/// Records that the current thread is codegening the given body, until the returned
+/// guard is dropped.
+pub(crate) fn enter_body<'a>(lir_ctx: &'a LirCtx, lir_body: &'a LirBody) -> BodyGuard<'a> {
+    CODEGEN_CONTEXT.set(Some(CodegenContext {
+        def_id: lir_body.metadata.def_id,
+        body_name: lir_body.metadata.name.clone(),
+        basic_block: None,
+    }));
+    BodyGuard { lir_ctx, lir_body }
+}
+
+/// Records that the current thread is codegening the given basic block of its body.
+pub(crate) fn enter_basic_block(bb: BasicBlock) {
+    CODEGEN_CONTEXT.with_borrow_mut(|context| {
+        if let Some(context) = context {
+            context.basic_block = Some(bb);
+        }
+    });
+}
+
+/// Clears the codegen context when the codegen of a body ends. If it ends because
+/// of a panic, the LIR of the body is kept for the bug report.
+pub(crate) struct BodyGuard<'a> {
+    lir_ctx: &'a LirCtx,
+    lir_body: &'a LirBody,
+}
+
+impl Drop for BodyGuard<'_> {
+    fn drop(&mut self) {
+        CODEGEN_CONTEXT.set(None);
+        if std::thread::panicking() {
+            let lir = LirPrinter::from_defs(self.lir_ctx).body_to_string(self.lir_body);
+            LIR_DUMPS
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(LirDump {
+                    def_id: self.lir_body.metadata.def_id,
+                    body_name: self.lir_body.metadata.name.clone(),
+                    lir,
+                });
+        }
+    }
+}
@@ -1,3 +1,8 @@
+pub mod analyze;
+pub mod backend;
+pub mod common;
 pub mod entry;
+pub mod error;
+pub mod ice;
 pub mod lir;
 pub mod traits;
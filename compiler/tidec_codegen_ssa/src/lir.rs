@@ -1,23 +1,36 @@
+use crate::analyze::{self, LocalKind};
+use crate::error::CodegenResult;
+use crate::ice;
 use crate::traits::{FnAbiOf, LayoutOf};
 use crate::{
     entry::FnCtx,
-    traits::{BuilderMethods, CodegenMethods},
+    traits::{
+        AsmBuilderMethods, BuilderMethods, CodegenMethods, ConstCodegenMethods,
+        DebugInfoBuilderMethods, IntrinsicCallBuilderMethods, MetadataMethods,
+        StaticCodegenMethods, TypeMethods,
+    },
 };
+use std::num::NonZero;
+use tidec_abi::calling_convention::function::PassMode;
 use tidec_abi::layout::BackendRepr;
+use tidec_abi::target::Endianess;
 use tidec_abi::{
     layout::TyAndLayout,
     size_and_align::{Align, Size},
 };
-use tidec_lir::basic_blocks::ENTRY_BLOCK;
-use tidec_lir::syntax::ConstValue;
+use tidec_lir::basic_blocks::{BasicBlockData, ENTRY_BLOCK};
+use tidec_lir::cfg;
+use tidec_lir::syntax::{ConstScalar, ConstValue, RawScalarValue};
 use tidec_lir::{
     lir::LirBody,
-    syntax::{LirTy, Local, LocalData},
+    pretty::BodyLines,
+    syntax::{FieldIdx, LirTy, Local, LocalData},
 };
-use tidec_utils::index_vec::IdxVec;
+use tidec_session::config::DebugInfo;
+use tidec_utils::{idx::Idx, index_vec::IdxVec};
 use tracing::{debug, instrument};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 /// Represents a memory location or “place” during code generation.
 ///
 /// `PlaceRef` encapsulates both the **backend-level representation** of a place
@@ -27,7 +40,7 @@ use tracing::{debug, instrument};
 ///
 /// The type parameter `V` represents a backend-specific value, such as a machine
 /// register, LLVM value, or other intermediate representation used by the backend.
-pub struct PlaceRef<V: std::fmt::Debug> {
+pub struct PlaceRef<'a, V: std::fmt::Debug> {
     /// The backend value of this place.
     ///
     /// This corresponds to the actual value used by the backend for code generation,
@@ -40,16 +53,16 @@ pub struct PlaceRef<V: std::fmt::Debug> {
     /// Provides size, alignment, and ABI information, which is essential for
     /// correct code generation, especially for aggregates, unsized types,
     /// or types with nontrivial ABI requirements.
-    pub ty_layout: TyAndLayout<LirTy>,
+    pub ty_layout: TyAndLayout<'a, LirTy>,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 /// Represents a computed value or operand during code generation.
 ///
 /// `OperandRef` holds a value that can be used directly in computations,
 /// without necessarily having a memory location. This can include immediate
 /// scalars, scalar pairs (e.g., fat pointers), or references to memory locations.
-pub struct OperandRef<V: std::fmt::Debug> {
+pub struct OperandRef<'a, V: std::fmt::Debug> {
     /// The actual value of the operand in the backend.
     ///
     /// May be an immediate scalar, a pair of scalars, or a reference to a `PlaceVal`.
@@ -58,45 +71,265 @@ pub struct OperandRef<V: std::fmt::Debug> {
     ///
     /// Provides size, alignment, and ABI information needed for correct
     /// code generation and backend handling.
-    pub ty_layout: TyAndLayout<LirTy>,
+    pub ty_layout: TyAndLayout<'a, LirTy>,
 }
 
-impl<V: std::fmt::Debug> OperandRef<V> {
-    pub fn new_zst(ty_layout: TyAndLayout<LirTy>) -> Self {
+impl<'a, V: std::fmt::Debug> OperandRef<'a, V> {
+    pub fn new_zst(ty_layout: TyAndLayout<'a, LirTy>) -> Self {
         OperandRef {
             operand_val: OperandVal::Zst,
             ty_layout,
         }
     }
 
-    pub fn new_immediate(value: V, ty_layout: TyAndLayout<LirTy>) -> Self {
+    pub fn new_immediate(value: V, ty_layout: TyAndLayout<'a, LirTy>) -> Self {
         OperandRef {
             operand_val: OperandVal::Immediate(value),
             ty_layout,
         }
     }
 
-    pub fn new_const<'a, 'be, B: BuilderMethods<'a, 'be, Value = V>>(
+    pub fn new_const<'be, B: BuilderMethods<'a, 'be, Value = V>>(
         builder: &mut B,
-        const_val: ConstValue,
+        const_val: &ConstValue,
         lir_ty: LirTy,
-    ) -> Self {
+    ) -> CodegenResult<Self>
+    where
+        V: Copy + PartialEq,
+    {
         let ty_layout = builder.ctx().layout_of(lir_ty);
         let be_val = match const_val {
             ConstValue::Scalar(const_scalar) => {
                 assert!(matches!(ty_layout.backend_repr, BackendRepr::Scalar(_)));
-                let be_val = builder.const_scalar_to_backend_value(const_scalar, ty_layout);
+                let be_val = builder
+                    .ctx()
+                    .const_scalar_to_backend_value(*const_scalar, &ty_layout);
                 OperandVal::Immediate(be_val)
             }
             ConstValue::ZST => {
                 assert!(ty_layout.is_zst());
                 OperandVal::Zst
             }
+            ConstValue::FnPtr(def_id) => OperandVal::Immediate(builder.ctx().get_fn_addr(*def_id)),
+            ConstValue::Slice { .. } => {
+                let [data, len] = const_slice_pair(builder, const_val, &ty_layout);
+                OperandVal::Pair(data, len)
+            }
+            // The constant lives in the global of its allocation.
+            ConstValue::Indirect { alloc_id, offset } => {
+                let alloc = builder.ctx().lir_ctx().alloc(*alloc_id);
+                assert!(
+                    offset.bytes() + ty_layout.size.bytes() <= alloc.size().bytes(),
+                    "Constant out of the bounds of its allocation"
+                );
+                let base = PlaceVal {
+                    value: builder.ctx().alloc_addr(*alloc_id),
+                    align: alloc.align,
+                };
+                let place_val = base.offset_by(builder, *offset)?;
+                if ty_layout.is_memory() {
+                    OperandVal::Ref(place_val)
+                } else {
+                    return builder.load_operand(&place_val.with_layout(ty_layout));
+                }
+            }
+            ConstValue::Array(_) if ty_layout.is_zst() => OperandVal::Zst,
+            ConstValue::Array(_) => {
+                // An array lives in memory, so the constant is emitted as a global
+                // and the operand refers to it.
+                let be_val = const_to_backend_value(builder, const_val, &ty_layout);
+                let align = ty_layout.align.abi;
+                let ptr = builder.ctx().static_addr_of(be_val, align);
+                OperandVal::Ref(PlaceVal { value: ptr, align })
+            }
         };
-        OperandRef {
+        Ok(OperandRef {
             operand_val: be_val,
             ty_layout,
+        })
+    }
+}
+
+/// Returns the backend constant for the given constant value, whose type and layout
+/// is `ty_layout`. Unlike `OperandRef::new_const`, aggregates are not emitted as globals,
+/// so that they can be nested into other constants.
+fn const_to_backend_value<'a, 'be, B: BuilderMethods<'a, 'be>>(
+    builder: &mut B,
+    const_val: &ConstValue,
+    ty_layout: &TyAndLayout<LirTy>,
+) -> B::Value {
+    match const_val {
+        ConstValue::Scalar(const_scalar) => builder
+            .ctx()
+            .const_scalar_to_backend_value(*const_scalar, ty_layout),
+        ConstValue::Array(elems) => {
+            let LirTy::Array(elem_ty, count) = &ty_layout.ty else {
+                panic!("Array constant of non-array type {:?}", ty_layout.ty);
+            };
+            assert_eq!(elems.len() as u64, *count, "Wrong number of array elements");
+            let elem_layout = builder.ctx().layout_of((**elem_ty).clone());
+            let be_elems = elems
+                .iter()
+                .map(|elem| const_to_backend_value(builder, elem, &elem_layout))
+                .collect::<Vec<_>>();
+            builder.ctx().const_array(&elem_layout, &be_elems)
+        }
+        ConstValue::FnPtr(def_id) => builder.ctx().get_fn_addr(*def_id),
+        ConstValue::Slice { .. } => {
+            let fields = const_slice_pair(builder, const_val, ty_layout);
+            builder.ctx().const_struct(ty_layout, &fields)
+        }
+        // The bytes of the allocation are copied into the outer constant.
+        ConstValue::Indirect { alloc_id, offset } => {
+            let alloc = builder.ctx().lir_ctx().alloc(*alloc_id);
+            let start = offset.bytes() as usize;
+            let end = start + ty_layout.size.bytes() as usize;
+            assert!(
+                end <= alloc.bytes.len(),
+                "Constant out of the bounds of its allocation"
+            );
+            const_from_bytes(builder, &alloc.bytes[start..end], ty_layout)
+        }
+        ConstValue::ZST => panic!("A ZST constant has no backend value"),
+    }
+}
+
+/// Returns the backend constant of the value of type and layout `ty_layout` stored in
+/// the given bytes (in the target endianness), with the backend type of the layout.
+/// The pointers in the bytes have no provenance, so they are integers cast to pointers.
+fn const_from_bytes<'a, 'be, B: BuilderMethods<'a, 'be>>(
+    builder: &mut B,
+    bytes: &[u8],
+    ty_layout: &TyAndLayout<LirTy>,
+) -> B::Value {
+    let field_bytes = |offset: Size, layout: &TyAndLayout<LirTy>| {
+        let start = offset.bytes() as usize;
+        &bytes[start..start + layout.size.bytes() as usize]
+    };
+    let field_tys = match &ty_layout.ty {
+        LirTy::Array(elem_ty, count) => {
+            let elem_layout = builder.ctx().layout_of((**elem_ty).clone());
+            let be_elems = (0..*count)
+                .map(|i| {
+                    let elem_bytes =
+                        field_bytes(Size::from_bytes(elem_layout.size.bytes() * i), &elem_layout);
+                    const_from_bytes(builder, elem_bytes, &elem_layout)
+                })
+                .collect::<Vec<_>>();
+            return builder.ctx().const_array(&elem_layout, &be_elems);
+        }
+        // An enum is lowered to an array of integers as large as its alignment (see
+        // `CodegenMethods::backend_type`), as its variants overlap.
+        LirTy::Adt(adt_id) if builder.ctx().lir_ctx().adt_def(*adt_id).is_enum() => {
+            let int_ty = match ty_layout.align.abi.bytes() {
+                1 => LirTy::U8,
+                2 => LirTy::U16,
+                4 => LirTy::U32,
+                8 => LirTy::U64,
+                16 => LirTy::U128,
+                align => panic!("No integer type with the alignment {}", align),
+            };
+            let int_layout = builder.ctx().layout_of(int_ty);
+            let be_ints = bytes
+                .chunks(int_layout.size.bytes() as usize)
+                .map(|int_bytes| const_from_bytes(builder, int_bytes, &int_layout))
+                .collect::<Vec<_>>();
+            return builder.ctx().const_array(&int_layout, &be_ints);
+        }
+        LirTy::Adt(adt_id) => builder
+            .ctx()
+            .lir_ctx()
+            .adt_def(*adt_id)
+            .non_enum_variant()
+            .fields
+            .raw
+            .clone(),
+        LirTy::Tuple(field_tys) => field_tys.clone(),
+        LirTy::Slice(elem_ty) => builder.ctx().lir_ctx().slice_field_tys(elem_ty).to_vec(),
+        // The other types are scalars.
+        _ => {
+            let mut bits = 0u128;
+            let mut push_byte = |byte: &u8| bits = (bits << 8) | *byte as u128;
+            match builder.ctx().lir_ctx().target().data_layout.endianess {
+                Endianess::Little => bytes.iter().rev().for_each(&mut push_byte),
+                Endianess::Big => bytes.iter().for_each(&mut push_byte),
+            }
+            let raw_scalar_value = RawScalarValue {
+                data: bits,
+                size: NonZero::new(bytes.len() as u8).expect("A scalar is not zero-sized"),
+            };
+            return builder
+                .ctx()
+                .const_scalar_to_backend_value(ConstScalar::Value(raw_scalar_value), ty_layout);
         }
+    };
+    let be_fields = field_tys
+        .into_iter()
+        .enumerate()
+        .map(|(i, field_ty)| {
+            let field_layout = builder.ctx().layout_of(field_ty);
+            let field_bytes = field_bytes(ty_layout.field_offset(i), &field_layout);
+            const_from_bytes(builder, field_bytes, &field_layout)
+        })
+        .collect::<Vec<_>>();
+    builder.ctx().const_struct(ty_layout, &be_fields)
+}
+
+/// Returns the backend constants of the pointer and the length of the given slice
+/// constant, whose type and layout is `ty_layout`.
+fn const_slice_pair<'a, 'be, B: BuilderMethods<'a, 'be>>(
+    builder: &mut B,
+    const_val: &ConstValue,
+    ty_layout: &TyAndLayout<LirTy>,
+) -> [B::Value; 2] {
+    let ConstValue::Slice { alloc_id, len } = const_val else {
+        panic!("Expected a slice constant, got {:?}", const_val);
+    };
+    let LirTy::Slice(elem_ty) = &ty_layout.ty else {
+        panic!("Slice constant of non-slice type {:?}", ty_layout.ty);
+    };
+    let elem_size = builder.ctx().layout_of((**elem_ty).clone()).size;
+    let alloc_size = builder.ctx().lir_ctx().alloc(*alloc_id).size();
+    assert!(
+        elem_size.bytes() * len <= alloc_size.bytes(),
+        "Slice constant out of the bounds of its allocation"
+    );
+    let [_, (_, len_layout)] = scalar_pair_fields(builder.ctx(), ty_layout);
+    let ctx = builder.ctx();
+    let be_len = ctx.const_uint_big(ctx.backend_type(&len_layout), *len as u128);
+    [ctx.alloc_addr(*alloc_id), be_len]
+}
+
+/// Returns the indices and the layouts of the two scalar fields of the given scalar pair,
+/// in memory order (as the scalars of `BackendRepr::ScalarPair`).
+///
+/// The other fields, if any, are zero-sized and so they do not contribute to the pair.
+pub fn scalar_pair_fields<'a, 'be, C: CodegenMethods<'be>>(
+    ctx: &'a C,
+    ty_layout: &TyAndLayout<LirTy>,
+) -> [(FieldIdx, TyAndLayout<'a, LirTy>); 2] {
+    assert!(ty_layout.is_scalar_pair());
+    let field_tys = match &ty_layout.ty {
+        LirTy::Tuple(field_tys) => field_tys.clone(),
+        LirTy::Slice(elem_ty) => ctx.lir_ctx().slice_field_tys(elem_ty).to_vec(),
+        LirTy::Adt(adt_id) => ctx
+            .lir_ctx()
+            .adt_def(*adt_id)
+            .non_enum_variant()
+            .fields
+            .raw
+            .clone(),
+        ty => panic!("Type {:?} cannot be a scalar pair", ty),
+    };
+    let mut scalars = ty_layout
+        .fields
+        .index_by_increasing_offset()
+        .into_iter()
+        .map(|i| (FieldIdx::new(i), ctx.layout_of(field_tys[i].clone())))
+        .filter(|(_, field_layout)| !field_layout.is_zst());
+    match (scalars.next(), scalars.next(), scalars.next()) {
+        (Some(a), Some(b), None) => [a, b],
+        _ => panic!("Scalar pair {:?} has not exactly two scalars", ty_layout.ty),
     }
 }
 
@@ -120,18 +353,79 @@ pub enum OperandVal<V: std::fmt::Debug> {
     Ref(PlaceVal<V>),
 }
 
-impl<'a, 'be, V: Copy + PartialEq + std::fmt::Debug> PlaceRef<V> {
+impl<V: Copy + std::fmt::Debug> OperandRef<'_, V> {
+    /// Returns the immediate value of the operand.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the operand is not an immediate.
+    pub fn immediate(&self) -> V {
+        match self.operand_val {
+            OperandVal::Immediate(val) => val,
+            ref operand_val => panic!("Expected an immediate operand, found {:?}", operand_val),
+        }
+    }
+}
+
+impl<'a, 'be, V: Copy + PartialEq + std::fmt::Debug> PlaceRef<'a, V> {
     pub fn alloca<B: BuilderMethods<'a, 'be, Value = V>>(
         builder: &mut B,
-        ty_and_layout: TyAndLayout<LirTy>,
-    ) -> Self {
+        ty_and_layout: TyAndLayout<'a, LirTy>,
+    ) -> CodegenResult<Self> {
         assert!(!ty_and_layout.is_zst());
-        PlaceVal::alloca(
-            builder,
-            ty_and_layout.layout.size,
-            ty_and_layout.layout.align.abi,
-        )
-        .with_layout(ty_and_layout)
+        // The memory is typed by the lowering of the type, which is as large as its
+        // layout, so that the emitted code is readable. The layout of a downcast enum
+        // is the one of its variant, whose lowering may be smaller than the enum.
+        let ctx = builder.ctx();
+        let ty = ctx.backend_type(&ctx.layout_of(ty_and_layout.ty.clone()));
+        let align = ty_and_layout.layout.align.abi;
+        let value = builder.typed_alloca(ty, align)?;
+        Ok(PlaceVal { value, align }.with_layout(ty_and_layout))
+    }
+
+    /// Returns the place of the field `field_idx`, whose type and layout is `field_layout`.
+    pub fn project_field<B: BuilderMethods<'a, 'be, Value = V>>(
+        &self,
+        builder: &mut B,
+        field_idx: FieldIdx,
+        field_layout: TyAndLayout<'a, LirTy>,
+    ) -> CodegenResult<Self> {
+        // The fields of an enum variant come after the tag, unless the tag is a niche.
+        let layout_field_idx = match &self.ty_layout.ty {
+            LirTy::Adt(adt_id)
+                if builder.ctx().lir_ctx().adt_def(*adt_id).is_enum()
+                    && builder
+                        .ctx()
+                        .layout_of(self.ty_layout.ty.clone())
+                        .has_tag_field() =>
+            {
+                field_idx.idx() + 1
+            }
+            _ => field_idx.idx(),
+        };
+        let offset = self.ty_layout.field_offset(layout_field_idx);
+        let place_val = self.place_val.offset_by(builder, offset)?;
+        // The field is at least as aligned as its type, but it can not be more
+        // aligned than its offset in the aggregate it belongs to.
+        let align = place_val.align.min(field_layout.align.abi);
+        Ok(PlaceVal {
+            value: place_val.value,
+            align,
+        }
+        .with_layout(field_layout))
+    }
+
+    /// Returns the place of the element at `index`, whose type and layout is `elem_layout`.
+    pub fn project_index<B: BuilderMethods<'a, 'be, Value = V>>(
+        &self,
+        builder: &mut B,
+        index: V,
+        elem_layout: TyAndLayout<'a, LirTy>,
+    ) -> CodegenResult<Self> {
+        let ty = builder.ctx().backend_type(&elem_layout);
+        let value = builder.build_inbounds_gep(ty, self.place_val.value, &[index])?;
+        let align = self.place_val.align.min(elem_layout.align.abi);
+        Ok(PlaceVal { value, align }.with_layout(elem_layout))
     }
 }
 
@@ -160,12 +454,35 @@ impl<'a, 'be, V: Copy + PartialEq + std::fmt::Debug> PlaceVal<V> {
         builder: &mut B,
         size: Size,
         align: Align,
-    ) -> Self {
-        let value = builder.alloca(size, align);
-        PlaceVal { value, align }
+    ) -> CodegenResult<Self> {
+        let value = builder.alloca(size, align)?;
+        Ok(PlaceVal { value, align })
+    }
+
+    /// Returns the place `offset` bytes after this one. Its alignment is restricted
+    /// accordingly.
+    pub fn offset_by<B: BuilderMethods<'a, 'be, Value = V>>(
+        self,
+        builder: &mut B,
+        offset: Size,
+    ) -> CodegenResult<Self> {
+        if offset.bytes() == 0 {
+            return Ok(self);
+        }
+        let byte_ty = builder.ctx().type_i8();
+        let offset_ty = builder
+            .ctx()
+            .backend_type(&builder.ctx().layout_of(LirTy::U64));
+        let be_offset = builder
+            .ctx()
+            .const_uint_big(offset_ty, offset.bytes() as u128);
+        Ok(PlaceVal {
+            value: builder.build_inbounds_gep(byte_ty, self.value, &[be_offset])?,
+            align: self.align.restrict_for_offset(offset),
+        })
     }
 
-    pub fn with_layout(self, layout: TyAndLayout<LirTy>) -> PlaceRef<V> {
+    pub fn with_layout(self, layout: TyAndLayout<'a, LirTy>) -> PlaceRef<'a, V> {
         // TODO: Assert that the type is not unsized (through `TyAndLayout`).
         PlaceRef {
             place_val: self,
@@ -183,20 +500,20 @@ impl<'a, 'be, V: Copy + PartialEq + std::fmt::Debug> PlaceVal<V> {
 ///
 /// From a source-level perspective, locals can be thought of as
 /// variables declared within a function scope.
-pub enum LocalRef<V: std::fmt::Debug> {
+pub enum LocalRef<'a, V: std::fmt::Debug> {
     /// A local backed by a memory location with associated layout and alignment metadata.
     ///
     /// From a source-level perspective, this corresponds to variables
     /// that have a defined memory location, such as stack-allocated variables.
     /// See [`tided_lir::syntax::Place`] for more details.
-    PlaceRef(PlaceRef<V>),
+    PlaceRef(PlaceRef<'a, V>),
     /// A local represented as an operand value, which can be used directly in computations.
     ///
     /// From a source-level perspective, this corresponds to temporary values
     /// that do not have a dedicated memory location, such as intermediate
     /// results in expressions.
     /// See [`tidec_lir::syntax::Operand`] for more details.
-    OperandRef(OperandRef<V>),
+    OperandRef(OperandRef<'a, V>),
     /// A local that is yet to be assigned a value.
     /// This is a placeholder for locals that will be initialized later.
     /// It is used to represent uninitialized locals during code generation.
@@ -213,12 +530,28 @@ pub enum LocalRef<V: std::fmt::Debug> {
 // ) { ... }
 // ```
 // function in rustc_codegen_ssa/src/mir/mod.rs
-pub fn codegen_lir_body<'a, 'be, B: BuilderMethods<'a, 'be>>(
+pub fn codegen_lir_body<
+    'a,
+    'be,
+    B: DebugInfoBuilderMethods<'a, 'be>
+        + MetadataMethods<'a, 'be>
+        + AsmBuilderMethods<'a, 'be>
+        + IntrinsicCallBuilderMethods<'a, 'be>,
+>(
     ctx: &'a B::CodegenCtx,
     lir_body: &'a LirBody,
-) {
-    let fn_abi = ctx.fn_abi_of(ctx.lir_ctx(), &lir_body.ret_and_args);
+) -> CodegenResult<()> {
+    let _ice_guard = ice::enter_body(ctx.lir_ctx(), lir_body);
+    let fn_abi = ctx.fn_abi_of(
+        ctx.lir_ctx(),
+        &lir_body.ret_and_args,
+        lir_body.metadata.call_conv,
+    );
+    let local_analysis = analyze::analyze_locals(lir_body);
     let fn_value = ctx.get_or_define_fn(&lir_body.metadata, &lir_body.ret_and_args);
+    let debuginfo = ctx.lir_ctx().debuginfo();
+    let dbg_scope =
+        (debuginfo != DebugInfo::None).then(|| B::create_function_scope(ctx, lir_body, fn_value));
     let entry_bb = B::append_basic_block(ctx, fn_value, "entry");
     let mut start_builder = B::build(ctx, entry_bb);
 
@@ -238,50 +571,125 @@ pub fn codegen_lir_body<'a, 'be, B: BuilderMethods<'a, 'be>>(
         fn_abi,
         lir_body,
         fn_value,
+        entry_bb,
         ctx,
         locals: IdxVec::new(),
         cached_bbs,
+        landing_pads: IdxVec::from_elem_n(None, lir_body.basic_blocks.len()),
+        personality_slot: None,
+        dbg_scope,
+        dbg_loc: None,
+        body_lines: BodyLines::new(ctx.lir_ctx(), lir_body),
+        span: lir_body.metadata.span,
+        local_analysis,
+        promoted_locals: vec![],
+        block_exits: IdxVec::from_fn_n(|_| None, lir_body.basic_blocks.len()),
+        pending_phis: vec![],
     };
+    // The arguments are located at the signature of the body.
+    let fn_line = fn_ctx.body_lines.fn_line();
+    fn_ctx.set_debug_loc(&mut start_builder, lir_body.metadata.span, fn_line);
 
-    let mut allocate_locals =
-        |locals: &IdxVec<Local, LocalData>| -> IdxVec<Local, LocalRef<B::Value>> {
-            let mut local_allocas = IdxVec::new();
+    // The `offset` is the index of the first local in `locals` within the body.
+    let allocate_locals = |locals: &IdxVec<Local, LocalData>,
+                           offset: usize|
+     -> CodegenResult<IdxVec<Local, LocalRef<'a, B::Value>>> {
+        let mut local_allocas = IdxVec::new();
 
-            for (local, local_data) in locals.iter_enumerated() {
-                debug!("Allocating local {:?} of type {:?}", local, local_data.ty);
-                let layout = start_builder.ctx().layout_of(local_data.ty);
+        for (local, local_data) in locals.iter_enumerated() {
+            let local = Local::new(offset + local.idx());
+            debug!("Allocating local {:?} of type {:?}", local, local_data.ty);
+            let layout = start_builder.ctx().layout_of(local_data.ty.clone());
 
-                // Check if the local has to be stored in memory or can be an operand.
-                let local_ref = if layout.is_memory() {
-                    LocalRef::PlaceRef(PlaceRef::alloca(&mut start_builder, layout))
-                } else if layout.is_zst() {
-                    // ZSTs do not need to be allocated.
-                    LocalRef::OperandRef(OperandRef::new_zst(layout))
-                } else {
-                    LocalRef::PendingOperandRef
-                };
+            // An argument passed indirectly lives in the memory provided by the caller,
+            // which is bound when the arguments are codegened.
+            let is_indirect = match local.idx() {
+                0 => matches!(fn_ctx.fn_abi.ret.mode, PassMode::Indirect { .. }),
+                i => fn_ctx
+                    .fn_abi
+                    .args
+                    .get(i - 1)
+                    .is_some_and(|arg_abi| matches!(arg_abi.mode, PassMode::Indirect { .. })),
+            };
 
-                // let local_ref = LocalRef::PlaceRef(PlaceRef::alloca(&mut start_builder, layout));
-                local_allocas.push(local_ref);
-            }
+            // Check if the local has to be stored in memory or can be an operand.
+            let local_ref = if layout.is_zst() {
+                // ZSTs do not need to be allocated.
+                LocalRef::OperandRef(OperandRef::new_zst(layout))
+            } else if is_indirect {
+                LocalRef::PendingOperandRef
+            } else if layout.is_memory() || fn_ctx.local_analysis.kinds[local] == LocalKind::Memory
+            {
+                let place_ref = fn_ctx.alloca(layout)?;
+                let name = lir_body.local_name(ctx.lir_ctx(), local);
+                start_builder.set_var_name(place_ref.place_val.value, &name);
+                LocalRef::PlaceRef(place_ref)
+            } else {
+                LocalRef::PendingOperandRef
+            };
 
-            local_allocas
-        };
+            // let local_ref = LocalRef::PlaceRef(fn_ctx.alloca(layout));
+            local_allocas.push(local_ref);
+        }
+
+        Ok(local_allocas)
+    };
 
     // Allocate the return value and arguments
-    let mut locals = allocate_locals(&fn_ctx.lir_body.ret_and_args);
+    let mut locals = allocate_locals(&fn_ctx.lir_body.ret_and_args, 0)?;
     // Allocate the locals
-    locals.append(&mut allocate_locals(&fn_ctx.lir_body.locals));
+    locals.append(&mut allocate_locals(
+        &fn_ctx.lir_body.locals,
+        fn_ctx.lir_body.ret_and_args.len(),
+    )?);
 
     // Initialize the locals in the function context.
     fn_ctx.locals = locals;
 
+    // The landing pads of the cleanup blocks store the exception (i.e., a pointer to
+    // it and its selector) in the personality slot, to resume it at their end.
+    if lir_body.basic_blocks.iter().any(BasicBlockData::is_cleanup) {
+        let personality =
+            lir_body.metadata.personality.as_deref().unwrap_or_else(|| {
+                fn_ctx.span_bug("A body with cleanup blocks needs a personality")
+            });
+        ctx.set_personality_fn(fn_value, personality);
+        let exception_ty = LirTy::Tuple(vec![LirTy::ptr_to(LirTy::U8), LirTy::I32]);
+        let layout = start_builder.ctx().layout_of(exception_ty);
+        fn_ctx.personality_slot = Some(fn_ctx.alloca(layout)?);
+    }
+
+    if let Some(dbg_scope) = dbg_scope.filter(|_| debuginfo == DebugInfo::Full) {
+        fn_ctx.declare_locals(&mut start_builder, dbg_scope);
+    }
+
+    // Bind the parameters of the function to its return value and arguments.
+    fn_ctx.codegen_arguments(&mut start_builder)?;
+
+    // The promoted locals that are backend values are tracked across the blocks. The
+    // others (e.g., an argument passed indirectly) live in memory.
+    fn_ctx.promoted_locals = fn_ctx
+        .locals
+        .iter_enumerated()
+        .filter(|(local, local_ref)| {
+            fn_ctx.local_analysis.kinds[*local] == LocalKind::Promoted
+                && match local_ref {
+                    LocalRef::PendingOperandRef => true,
+                    LocalRef::OperandRef(operand) => !operand.ty_layout.is_zst(),
+                    LocalRef::PlaceRef(_) => false,
+                }
+        })
+        .map(|(local, _)| local)
+        .collect();
+
     // We can safely drop the builder now, as we will create new builders for each basic block.
     drop(start_builder);
 
-    // Codegen each basic block in the function body.
-    for bb in lir_body.basic_blocks.indices() {
-        fn_ctx.codegen_basic_block(bb);
-        // TODO(bruzzone): consider to remove unreached blocks here
+    // Codegen the basic blocks in reverse postorder, so that the SSA locals are defined
+    // before their uses. The blocks unreachable from the entry block are not codegened.
+    for bb in cfg::reverse_postorder(&lir_body.basic_blocks) {
+        fn_ctx.codegen_basic_block(bb)?;
     }
+    fn_ctx.codegen_phi_incomings()?;
+    Ok(())
 }
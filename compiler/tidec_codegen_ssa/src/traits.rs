@@ -1,30 +1,55 @@
 use tidec_abi::{
-    calling_convention::function::FnAbi,
-    layout::TyAndLayout,
+    calling_convention::{
+        CallConv,
+        function::{CastTarget, FnAbi},
+    },
+    layout::{Scalar, TyAndLayout},
     size_and_align::{Align, Size},
 };
 use tidec_lir::{
-    lir::{LirBody, LirBodyMetadata, LirCtx, LirUnit},
-    syntax::{ConstScalar, LirTy, Local, LocalData},
+    lir::{DefId, LirBody, LirBodyMetadata, LirCtx},
+    syntax::{
+        AllocId, BranchHint, ConstScalar, InlineAsmOptions, InlineAsmRegOrRegClass, Intrinsic,
+        LirTy, Local, LocalData,
+    },
 };
 use tidec_utils::index_vec::IdxVec;
 
+use crate::backend::CompiledModule;
+use crate::common::IntPredicate;
+use crate::error::CodegenResult;
 use crate::lir::{OperandRef, PlaceRef};
 
 /// This trait is used to get the layout of a type.
 /// It is used to get the layout of a type in the codegen backend.
 pub trait LayoutOf {
     /// Returns the layout of the given type.
-    fn layout_of(&self, ty: LirTy) -> TyAndLayout<LirTy>;
+    fn layout_of(&self, ty: LirTy) -> TyAndLayout<'_, LirTy>;
 }
 
 pub trait FnAbiOf {
-    /// Returns the function ABI for the given return type and argument types.
-    fn fn_abi_of(
-        &self,
-        lit_ty_ctx: &LirCtx,
+    /// Returns the function ABI for the given return type and argument types, with the
+    /// given calling convention.
+    /// The layouts of the ABI are interned in the given LIR type context, which must
+    /// be the one of the backend, as the ABI of each signature may be cached.
+    fn fn_abi_of<'a>(
+        &'a self,
+        lit_ty_ctx: &'a LirCtx,
         ret_and_args: &IdxVec<Local, LocalData>,
-    ) -> FnAbi<LirTy>;
+        conv: CallConv,
+    ) -> FnAbi<'a, LirTy>;
+
+    /// Returns the function ABI of a variadic function with the given return type and
+    /// fixed argument types, called with the given variadic arguments (none for the
+    /// declaration of the function). The variadic arguments are expected to be already
+    /// promoted (see `LirTy::c_variadic_promoted`).
+    fn fn_abi_of_variadic<'a>(
+        &'a self,
+        lit_ty_ctx: &'a LirCtx,
+        fixed_ret_and_args: &IdxVec<Local, LocalData>,
+        variadic_args: &[LirTy],
+        conv: CallConv,
+    ) -> FnAbi<'a, LirTy>;
 }
 
 /// This trait is used to define the types used in the codegen backend.
@@ -43,6 +68,8 @@ pub trait CodegenBackendTypes {
     type FunctionType: Copy + PartialEq + std::fmt::Debug;
     /// A `FunctionValue` is a function value in the codegen backend.
     type FunctionValue: Copy + PartialEq + std::fmt::Debug;
+    /// A `GlobalValue` is a global variable in the codegen backend.
+    type GlobalValue: Copy + PartialEq + std::fmt::Debug;
     /// A `MetadataType` is a metadata type in the codegen backend.
     type MetadataType: Copy + PartialEq + std::fmt::Debug;
     /// A `MetadataValue` is a metadata value in the codegen backend.
@@ -76,7 +103,7 @@ pub trait PreDefineCodegenMethods: Sized + CodegenBackendTypes {
 /// The definition methods for the codegen backend. It is used to define (compile) function bodies.
 /// The definition should be done after pre-defining all functions (see `PreDefineCodegenMethods`).
 pub trait DefineCodegenMethods: Sized + CodegenBackendTypes {
-    fn define_body(&self, lir_body: &LirBody);
+    fn define_body(&self, lir_body: &LirBody) -> CodegenResult<()>;
 }
 
 /// The codegen backend methods.
@@ -88,6 +115,9 @@ pub trait CodegenMethods<'be>:
     + CodegenBackend
     + PreDefineCodegenMethods
     + DefineCodegenMethods
+    + ConstCodegenMethods
+    + TypeMethods
+    + StaticCodegenMethods
 {
     /// Creates a new codegen context for the given LIR type context and module.
     fn new(lir_ty_ctx: LirCtx, context: &'be Self::Context, module: Self::Module) -> Self;
@@ -95,23 +125,158 @@ pub trait CodegenMethods<'be>:
     /// Return the LIR type context associated with this codegen context.
     fn lir_ctx(&self) -> &LirCtx;
 
-    /// Compile the given LIR unit.
-    fn compile_lir_unit<'a, B: BuilderMethods<'a, 'be>>(&self, lir_unit: LirUnit);
+    /// Compile the bodies defined in the LIR type context (see `LirCtx::define_unit`).
+    /// It returns an error if the backend rejects the compiled unit (e.g., if its
+    /// verifier finds some invalid code).
+    fn compile_lir_unit<
+        'a,
+        B: DebugInfoBuilderMethods<'a, 'be>
+            + MetadataMethods<'a, 'be>
+            + AsmBuilderMethods<'a, 'be>
+            + IntrinsicCallBuilderMethods<'a, 'be>,
+    >(
+        &self,
+    ) -> CodegenResult<()>;
 
     /// Emit the output of the codegen backend.
     /// This could be writing to a file ASM, object file, or JIT execution.
-    /// The output format is backend-specific.
-    fn emit_output(&self);
+    /// The output format is backend-specific. It returns the compiled module, with
+    /// the written files, whose paths are chosen by the session (see
+    /// `Session::module_output_path`). It fails if a file cannot be written.
+    fn emit_output(&self) -> CodegenResult<CompiledModule>;
 
     /// Returns the function value for the given LIR body if it exists.
     fn get_fn(&self, lir_body_metadata: &LirBodyMetadata) -> Option<Self::FunctionValue>;
 
+    /// Returns the function value for the given `DefId` if it has been pre-defined, or
+    /// else declares it if it is imported from another unit (see `LirCtx::import`).
+    /// This is used to resolve the callee of a direct call.
+    fn get_fn_by_def_id(&self, def_id: DefId) -> Option<Self::FunctionValue>;
+
+    /// Returns the address of the function with the given `DefId`, which must have been
+    /// pre-defined or imported. It is used to create function pointers.
+    fn get_fn_addr(&self, def_id: DefId) -> Self::Value;
+
+    /// Returns a pointer to the global of the given allocation, emitting it if needed.
+    fn alloc_addr(&self, alloc_id: AllocId) -> Self::Value;
+
     /// Returns the function value for the given LIR body or defines it if it does not exist.
     fn get_or_define_fn(
         &self,
         lir_fn_metadata: &LirBodyMetadata,
         lir_fn_ret_and_args: &IdxVec<Local, LocalData>,
     ) -> Self::FunctionValue;
+
+    /// Sets the personality function with the given symbol name, declaring it if it
+    /// does not exist yet, as the one of `fn_value`. It is required by the landing
+    /// pads of the function (see `LirBodyMetadata::personality`).
+    fn set_personality_fn(&self, fn_value: Self::FunctionValue, personality: &str);
+}
+
+/// The methods of the codegen backend to declare and define globals (e.g., the ones
+/// of the statics).
+// It corresponds to `StaticCodegenMethods` in rustc_codegen_ssa/src/traits/statics.rs
+pub trait StaticCodegenMethods: CodegenBackendTypes {
+    /// Emits the given constant as an immutable global with the given alignment
+    /// and returns a pointer to it.
+    fn static_addr_of(&self, val: Self::Value, align: Align) -> Self::Value;
+
+    /// Declares a global of the given type with the given symbol name, or returns the
+    /// existing declaration of it. It returns `None` if the global is already defined,
+    /// i.e., if it already has an initializer.
+    fn define_global(&self, name: &str, ty: Self::Type) -> Option<Self::GlobalValue>;
+
+    /// Sets the initializer of the given global, which makes it a definition.
+    fn set_initializer(&self, global: Self::GlobalValue, val: Self::Value);
+
+    /// Sets whether the given global is immutable.
+    fn set_global_constant(&self, global: Self::GlobalValue, is_constant: bool);
+
+    /// Sets the alignment of the given global.
+    fn set_global_alignment(&self, global: Self::GlobalValue, align: Align);
+
+    /// Places the given global in the given section of the object file.
+    fn set_global_section(&self, global: Self::GlobalValue, section: &str);
+
+    /// Returns a pointer to the given global.
+    fn global_addr(&self, global: Self::GlobalValue) -> Self::Value;
+}
+
+/// The methods of the codegen backend to build types, and to lower the LIR types to
+/// them.
+// It corresponds to `BaseTypeCodegenMethods` and `LayoutTypeCodegenMethods` in
+// rustc_codegen_ssa/src/traits/type_.rs
+pub trait TypeMethods: CodegenBackendTypes {
+    /// Returns the 8-bit integer type, i.e., the type of a byte.
+    fn type_i8(&self) -> Self::Type;
+
+    /// Returns the type of the pointers into the default address space.
+    fn type_ptr(&self) -> Self::Type;
+
+    /// Returns the anonymous struct type with the given fields, which are laid out
+    /// without padding if `packed`.
+    fn type_struct(&self, fields: &[Self::Type], packed: bool) -> Self::Type;
+
+    /// Returns the lowering of the type of the given type and layout, which is as large
+    /// as the layout. The fields of an aggregate are in memory order. Unlike
+    /// `backend_type`, a downcast enum is lowered as the whole enum.
+    fn type_from_layout(&self, ty_layout: &TyAndLayout<LirTy>) -> Self::Type;
+
+    /// Returns the backend type of the given type and layout.
+    fn backend_type(&self, ty_layout: &TyAndLayout<LirTy>) -> Self::Type;
+
+    /// Returns the backend type of the given type and layout when it is held as an
+    /// immediate value. It differs from `backend_type` only for scalar pairs, which
+    /// are lowered to an aggregate of their two scalars (e.g., to return them).
+    fn immediate_backend_type(&self, ty_layout: &TyAndLayout<LirTy>) -> Self::Type;
+
+    /// Returns the backend type of the registers an argument is cast to (see
+    /// `PassMode::Cast`), i.e., an aggregate of them if there are more than one.
+    fn cast_backend_type(&self, cast: &CastTarget) -> Self::Type;
+}
+
+/// The methods of the codegen backend to build constants, which are used by the
+/// codegen of the constants of the LIR (see `OperandRef::new_const`).
+// It corresponds to `ConstCodegenMethods` in rustc_codegen_ssa/src/traits/consts.rs
+pub trait ConstCodegenMethods: CodegenBackendTypes {
+    /// Returns an undefined value of the given type.
+    fn const_undef(&self, ty: Self::Type) -> Self::Value;
+
+    /// Returns the constant of the given integer type with the given value, which is
+    /// sign-extended or truncated to the width of the type.
+    fn const_int(&self, ty: Self::Type, val: i64) -> Self::Value;
+
+    /// Returns the constant of the given integer type with the given value, which is
+    /// truncated to the width of the type.
+    fn const_uint_big(&self, ty: Self::Type, val: u128) -> Self::Value;
+
+    /// Returns the boolean constant with the given value.
+    fn const_bool(&self, val: bool) -> Self::Value;
+
+    /// Returns the null pointer of the given pointer type.
+    fn const_null_ptr(&self, ty: Self::Type) -> Self::Value;
+
+    /// Returns the constant struct of type and layout `ty_layout` with the given field
+    /// constants, in declaration order.
+    fn const_struct(&self, ty_layout: &TyAndLayout<LirTy>, fields: &[Self::Value]) -> Self::Value;
+
+    /// Returns the constant array of the given element constants, whose type and
+    /// layout is `elem_layout`.
+    fn const_array(&self, elem_layout: &TyAndLayout<LirTy>, elems: &[Self::Value]) -> Self::Value;
+
+    /// Returns a pointer to the bytes of the given string, which are not null-terminated,
+    /// and their number, as a `usize`. Identical strings share the same bytes.
+    fn const_str(&self, s: &str) -> (Self::Value, Self::Value);
+
+    /// Returns the constant of the given scalar, whose type and layout is `ty_layout`.
+    ///
+    /// For instance, in LLVM this could correspond to `LLVMConstInt` or `LLVMConstReal`.
+    // It corresponds to `scalar_to_backend` in rustc_codegen_llvm/src/common.rs
+    fn const_scalar_to_backend_value(
+        &self,
+        const_scalar: ConstScalar,
+        ty_layout: &TyAndLayout<LirTy>,
+    ) -> Self::Value;
 }
 
 /// The builder methods for the codegen backend.
@@ -119,7 +284,8 @@ pub trait CodegenMethods<'be>:
 pub trait BuilderMethods<'a, 'be>: Sized + CodegenBackendTypes {
     /// The associated codegen context type.
     /// This ensures that the codegen context is compatible with the codegen backend types.
-    type CodegenCtx: CodegenMethods<
+    type CodegenCtx: 'a
+        + CodegenMethods<
             'be,
             BasicBlock = Self::BasicBlock,
             Type = Self::Type,
@@ -131,16 +297,35 @@ pub trait BuilderMethods<'a, 'be>: Sized + CodegenBackendTypes {
         >;
 
     /// Returns a reference to the codegen context.
-    fn ctx(&self) -> &Self::CodegenCtx;
+    fn ctx(&self) -> &'a Self::CodegenCtx;
 
     /// Allocate memory for a value of the given size and alignment.
     /// For instance, in LLVM this corresponds to the `alloca` instruction.
-    fn alloca(&self, size: Size, align: Align) -> Self::Value;
+    /// The builder is expected to be positioned in the entry block of the function
+    /// (see `FnCtx::alloca`), so that the memory is allocated once per call.
+    fn alloca(&self, size: Size, align: Align) -> CodegenResult<Self::Value>;
+
+    /// Allocate memory for a value of the given backend type, with the given alignment.
+    /// The type must be as large as the value (see `CodegenMethods::backend_type`).
+    fn typed_alloca(&self, ty: Self::Type, align: Align) -> CodegenResult<Self::Value>;
+
+    /// Names the given value in the emitted code (e.g., `%x = alloca i32` in LLVM).
+    // It corresponds to `set_var_name` in rustc_codegen_llvm/src/debuginfo/mod.rs
+    fn set_var_name(&self, value: Self::Value, name: &str);
+
+    /// Returns the `idx`-th parameter of the function the builder is positioned in.
+    /// The index refers to the backend parameters, which follow the function ABI.
+    fn get_param(&mut self, idx: usize) -> Self::Value;
 
     /// Create a new builder for the given codegen context and basic block.
     /// The builder is positioned at the end of the basic block.
     fn build(ctx: &'a Self::CodegenCtx, bb: Self::BasicBlock) -> Self;
 
+    /// Create a new builder for the given codegen context and basic block.
+    /// The builder is positioned before the terminator of the basic block, or at its
+    /// end if the basic block has no terminator yet.
+    fn build_before_terminator(ctx: &'a Self::CodegenCtx, bb: Self::BasicBlock) -> Self;
+
     /// Append a new basic block to the given function value with the given name.
     /// The name can be empty, in which case a unique name will be generated.
     /// The function value is assumed to be valid and belong to the same context as the codegen context.
@@ -158,25 +343,440 @@ pub trait BuilderMethods<'a, 'be>: Sized + CodegenBackendTypes {
     /// struct LargeStruct { a: [u8; 1024] }
     /// fn foo() -> LargeStruct { unimplemented!() }
     /// ```
-    fn build_return(&mut self, return_value: Option<Self::Value>);
+    fn build_return(&mut self, return_value: Option<Self::Value>) -> CodegenResult<()>;
+
+    /// Build an unconditional branch to the given basic block.
+    fn build_br(&mut self, dest: Self::BasicBlock) -> CodegenResult<()>;
+
+    /// Build a conditional branch. If `cond` is not zero, it jumps to `then_bb`,
+    /// otherwise it jumps to `else_bb`. The `hint` is the likelihood of jumping to
+    /// `then_bb`, if known.
+    fn build_cond_br(
+        &mut self,
+        cond: Self::Value,
+        then_bb: Self::BasicBlock,
+        else_bb: Self::BasicBlock,
+        hint: Option<BranchHint>,
+    ) -> CodegenResult<()>;
+
+    /// Build a call to the panic handler with the given message.
+    ///
+    /// The panic handler never returns, so the current block is terminated
+    /// (e.g., with an unreachable instruction). If it unwinds, it continues at the
+    /// landing pad `catch`, if any, or in the caller otherwise.
+    fn build_panic(&mut self, msg: &str, catch: Option<Self::BasicBlock>) -> CodegenResult<()>;
+
+    /// Build an unreachable instruction.
+    /// It tells the backend that this point of the program is never reached.
+    fn build_unreachable(&mut self) -> CodegenResult<()>;
+
+    /// Build a call to the given function value with the given arguments.
+    ///
+    /// The arguments are expected to be already lowered according to the callee's
+    /// `FnAbi`. That is, ignored arguments are not present, direct arguments are
+    /// immediates, and indirect arguments (including the hidden return pointer, if any)
+    /// are pointers. The call uses the calling convention of `fn_abi`, which must be the
+    /// one of the declaration of the callee.
+    ///
+    /// It returns the value produced by the call if the return value is passed
+    /// `Direct`ly, `None` otherwise.
+    fn build_call(
+        &mut self,
+        fn_abi: &FnAbi<LirTy>,
+        fn_value: Self::FunctionValue,
+        args: &[Self::Value],
+    ) -> CodegenResult<Option<Self::Value>>;
+
+    /// Build an indirect call through the function pointer `fn_ptr`, whose ABI is `fn_abi`.
+    /// The arguments must already be lowered according to the ABI, as for `build_call`.
+    fn build_indirect_call(
+        &mut self,
+        fn_abi: &FnAbi<LirTy>,
+        fn_ptr: Self::Value,
+        args: &[Self::Value],
+    ) -> CodegenResult<Option<Self::Value>>;
+
+    /// Build an invoke of the given function value, which terminates the current
+    /// block. It continues at `then` if the callee returns, and at the landing pad
+    /// `catch` if the callee unwinds. The arguments and the returned value are as in
+    /// `build_call`, but the returned value is only available in `then`.
+    fn build_invoke(
+        &mut self,
+        fn_abi: &FnAbi<LirTy>,
+        fn_value: Self::FunctionValue,
+        args: &[Self::Value],
+        then: Self::BasicBlock,
+        catch: Self::BasicBlock,
+    ) -> CodegenResult<Option<Self::Value>>;
+
+    /// Build an invoke through the function pointer `fn_ptr`, whose ABI is `fn_abi`,
+    /// as for `build_invoke`.
+    fn build_indirect_invoke(
+        &mut self,
+        fn_abi: &FnAbi<LirTy>,
+        fn_ptr: Self::Value,
+        args: &[Self::Value],
+        then: Self::BasicBlock,
+        catch: Self::BasicBlock,
+    ) -> CodegenResult<Option<Self::Value>>;
+
+    /// Build a landing pad that catches the unwinding to run the cleanups, at the
+    /// start of the current block. It returns the two values of the exception (i.e.,
+    /// the pointer to it and its selector), which are needed to resume unwinding.
+    ///
+    /// The personality function of the function must be set (see
+    /// `CodegenMethods::set_personality_fn`).
+    fn build_cleanup_landing_pad(&mut self) -> CodegenResult<(Self::Value, Self::Value)>;
+
+    /// Build a resume of the unwinding of the exception with the given values, as
+    /// returned by `build_cleanup_landing_pad`.
+    fn build_resume(&mut self, exn: Self::Value, selector: Self::Value) -> CodegenResult<()>;
 
     /// Load an operand from the given place reference.
     /// This is used to load a value from memory.
-    fn load_operand(&mut self, place_ref: &PlaceRef<Self::Value>) -> OperandRef<Self::Value>;
+    fn load_operand(
+        &mut self,
+        place_ref: &PlaceRef<'a, Self::Value>,
+    ) -> CodegenResult<OperandRef<'a, Self::Value>>;
 
     /// Build a store instruction to store the given value to the given place reference.
     /// This is used to store a value to memory.
     /// The value is assumed to be of the same type as the place reference.
     /// The alignment is the alignment of the place reference.
-    fn build_load(&mut self, ty: Self::Type, ptr: Self::Value, align: Align) -> Self::Value;
+    fn build_load(
+        &mut self,
+        ty: Self::Type,
+        ptr: Self::Value,
+        align: Align,
+    ) -> CodegenResult<Self::Value>;
 
-    /// Construct a backend value from a constant scalar and its LIR type.
-    /// This is used to create constant values in the backend.
-    ///
-    /// For instance, in LLVM this could correspond to `LLVMConstInt` or `LLVMConstReal`.
-    fn const_scalar_to_backend_value(
-        &self,
-        const_scalar: ConstScalar,
-        ty_layout: TyAndLayout<LirTy>,
-    ) -> Self::Value;
+    /// Build an in-bounds `getelementptr` instruction. That is, compute the address of
+    /// an element starting from `ptr`, where `ty` is the type the indices step over.
+    fn build_inbounds_gep(
+        &mut self,
+        ty: Self::Type,
+        ptr: Self::Value,
+        indices: &[Self::Value],
+    ) -> CodegenResult<Self::Value>;
+
+    /// Build a `getelementptr` instruction computing the address of the field `idx`
+    /// of the aggregate of type `ty` pointed to by `ptr`.
+    fn build_struct_gep(
+        &mut self,
+        ty: Self::Type,
+        ptr: Self::Value,
+        idx: u64,
+    ) -> CodegenResult<Self::Value>;
+
+    /// Build the extraction of the element at `idx` from the given aggregate value.
+    fn build_extract_value(&mut self, agg: Self::Value, idx: u64) -> CodegenResult<Self::Value>;
+
+    /// Build the insertion of `elem` at `idx` into the given aggregate value,
+    /// returning the new aggregate.
+    fn build_insert_value(
+        &mut self,
+        agg: Self::Value,
+        elem: Self::Value,
+        idx: u64,
+    ) -> CodegenResult<Self::Value>;
+
+    /// Build an integer addition (wrapping).
+    fn build_add(&mut self, lhs: Self::Value, rhs: Self::Value) -> CodegenResult<Self::Value>;
+
+    /// Build an integer subtraction (wrapping).
+    fn build_sub(&mut self, lhs: Self::Value, rhs: Self::Value) -> CodegenResult<Self::Value>;
+
+    /// Build an integer multiplication (wrapping).
+    fn build_mul(&mut self, lhs: Self::Value, rhs: Self::Value) -> CodegenResult<Self::Value>;
+
+    /// Build a signed integer division.
+    fn build_sdiv(&mut self, lhs: Self::Value, rhs: Self::Value) -> CodegenResult<Self::Value>;
+
+    /// Build an unsigned integer division.
+    fn build_udiv(&mut self, lhs: Self::Value, rhs: Self::Value) -> CodegenResult<Self::Value>;
+
+    /// Build a signed integer remainder.
+    fn build_srem(&mut self, lhs: Self::Value, rhs: Self::Value) -> CodegenResult<Self::Value>;
+
+    /// Build an unsigned integer remainder.
+    fn build_urem(&mut self, lhs: Self::Value, rhs: Self::Value) -> CodegenResult<Self::Value>;
+
+    /// Build a bitwise and.
+    fn build_and(&mut self, lhs: Self::Value, rhs: Self::Value) -> CodegenResult<Self::Value>;
+
+    /// Build a bitwise or.
+    fn build_or(&mut self, lhs: Self::Value, rhs: Self::Value) -> CodegenResult<Self::Value>;
+
+    /// Build a bitwise xor.
+    fn build_xor(&mut self, lhs: Self::Value, rhs: Self::Value) -> CodegenResult<Self::Value>;
+
+    /// Build a left shift.
+    fn build_shl(&mut self, lhs: Self::Value, rhs: Self::Value) -> CodegenResult<Self::Value>;
+
+    /// Build an arithmetic (sign-extending) right shift.
+    fn build_ashr(&mut self, lhs: Self::Value, rhs: Self::Value) -> CodegenResult<Self::Value>;
+
+    /// Build a logical (zero-extending) right shift.
+    fn build_lshr(&mut self, lhs: Self::Value, rhs: Self::Value) -> CodegenResult<Self::Value>;
+
+    /// Build an integer negation (wrapping).
+    fn build_neg(&mut self, val: Self::Value) -> CodegenResult<Self::Value>;
+
+    /// Build a floating-point negation.
+    fn build_fneg(&mut self, val: Self::Value) -> CodegenResult<Self::Value>;
+
+    /// Build a bitwise not.
+    fn build_not(&mut self, val: Self::Value) -> CodegenResult<Self::Value>;
+
+    /// Build a zero extension of an integer to the given (wider) integer type.
+    fn build_zext(&mut self, val: Self::Value, dest_ty: Self::Type) -> CodegenResult<Self::Value>;
+
+    /// Build a sign extension of an integer to the given (wider) integer type.
+    fn build_sext(&mut self, val: Self::Value, dest_ty: Self::Type) -> CodegenResult<Self::Value>;
+
+    /// Build a truncation of an integer to the given (narrower) integer type.
+    fn build_trunc(&mut self, val: Self::Value, dest_ty: Self::Type) -> CodegenResult<Self::Value>;
+
+    /// Build a conversion from a floating-point number to a signed integer.
+    fn build_fptosi(&mut self, val: Self::Value, dest_ty: Self::Type)
+    -> CodegenResult<Self::Value>;
+
+    /// Build a conversion from a floating-point number to an unsigned integer.
+    fn build_fptoui(&mut self, val: Self::Value, dest_ty: Self::Type)
+    -> CodegenResult<Self::Value>;
+
+    /// Build a conversion from a signed integer to a floating-point number.
+    fn build_sitofp(&mut self, val: Self::Value, dest_ty: Self::Type)
+    -> CodegenResult<Self::Value>;
+
+    /// Build a conversion from an unsigned integer to a floating-point number.
+    fn build_uitofp(&mut self, val: Self::Value, dest_ty: Self::Type)
+    -> CodegenResult<Self::Value>;
+
+    /// Build an extension of a floating-point number to the given (wider) type.
+    fn build_fpext(&mut self, val: Self::Value, dest_ty: Self::Type) -> CodegenResult<Self::Value>;
+
+    /// Build a truncation of a floating-point number to the given (narrower) type.
+    fn build_fptrunc(
+        &mut self,
+        val: Self::Value,
+        dest_ty: Self::Type,
+    ) -> CodegenResult<Self::Value>;
+
+    /// Build a conversion from a pointer to an integer.
+    fn build_ptrtoint(
+        &mut self,
+        val: Self::Value,
+        dest_ty: Self::Type,
+    ) -> CodegenResult<Self::Value>;
+
+    /// Build a conversion from an integer to a pointer.
+    fn build_inttoptr(
+        &mut self,
+        val: Self::Value,
+        dest_ty: Self::Type,
+    ) -> CodegenResult<Self::Value>;
+
+    /// Build a reinterpretation of the bits of the value as the given type.
+    fn build_bitcast(
+        &mut self,
+        val: Self::Value,
+        dest_ty: Self::Type,
+    ) -> CodegenResult<Self::Value>;
+
+    /// Build an integer comparison with the given predicate.
+    /// The result is a boolean value.
+    fn build_icmp(
+        &mut self,
+        pred: IntPredicate,
+        lhs: Self::Value,
+        rhs: Self::Value,
+    ) -> CodegenResult<Self::Value>;
+
+    /// Build a select, which returns `then_val` if the boolean `cond` is true,
+    /// and `else_val` otherwise.
+    fn build_select(
+        &mut self,
+        cond: Self::Value,
+        then_val: Self::Value,
+        else_val: Self::Value,
+    ) -> CodegenResult<Self::Value>;
+
+    /// Returns the basic block where the builder is positioned.
+    fn llbb(&self) -> Self::BasicBlock;
+
+    /// Build a phi of the given type, with no incoming values yet (see
+    /// `add_incoming_to_phi`). The builder must be positioned at the start of the basic
+    /// block, or after its other phis.
+    fn build_phi(&mut self, ty: Self::Type) -> CodegenResult<Self::Value>;
+
+    /// Adds to the given phi the value it takes when the control flow comes from `bb`.
+    fn add_incoming_to_phi(&mut self, phi: Self::Value, val: Self::Value, bb: Self::BasicBlock);
+
+    /// Build a store instruction to store the given value to the given pointer,
+    /// with the given alignment.
+    fn build_store(
+        &mut self,
+        val: Self::Value,
+        ptr: Self::Value,
+        align: Align,
+    ) -> CodegenResult<()>;
+
+    /// Build a copy of `size` bytes from the memory pointed to by `src` to the memory
+    /// pointed to by `dst`. The two memory regions must not overlap.
+    fn build_memcpy(
+        &mut self,
+        dst: Self::Value,
+        dst_align: Align,
+        src: Self::Value,
+        src_align: Align,
+        size: Size,
+    ) -> CodegenResult<()>;
+}
+
+/// The methods to emit the debug info of the bodies (e.g., DWARF), whose amount is
+/// given by `LirCtx::debuginfo`. The methods without a receiver act on the module of
+/// the given codegen context.
+///
+/// The locations are given by the spans of the bodies, or, for a body without a
+/// source, by its lines as printed by `LirPrinter` (see `tidec_lir::pretty::BodyLines`).
+// It corresponds to `DebugInfoCodegenMethods` and `DebugInfoBuilderMethods` in
+// rustc_codegen_ssa/src/traits/debuginfo.rs
+pub trait DebugInfoBuilderMethods<'a, 'be>: BuilderMethods<'a, 'be> {
+    /// The scope of the debug info of a function (e.g., a `DISubprogram` in LLVM).
+    type DIScope: Copy + PartialEq + std::fmt::Debug;
+    /// A source location within a scope (e.g., a `DILocation` in LLVM).
+    type DILocation: Copy + PartialEq + std::fmt::Debug;
+    /// The debug info of a local variable (e.g., a `DILocalVariable` in LLVM).
+    type DIVariable: Copy + PartialEq + std::fmt::Debug;
+
+    /// Creates the compile unit of the module, which is the root of its debug info.
+    /// It is called once, before the bodies are compiled.
+    fn create_compile_unit(ctx: &'a Self::CodegenCtx, unit_name: &str);
+
+    /// Finalizes the debug info of the module, once all the bodies are compiled.
+    fn finalize_debug_info(ctx: &'a Self::CodegenCtx);
+
+    /// Creates the scope of the given body and attaches it to its function value.
+    fn create_function_scope(
+        ctx: &'a Self::CodegenCtx,
+        lir_body: &LirBody,
+        fn_value: Self::FunctionValue,
+    ) -> Self::DIScope;
+
+    /// Returns the location at the given line and column of the given scope. The
+    /// column is `0` if it is unknown.
+    fn create_debug_loc(
+        ctx: &'a Self::CodegenCtx,
+        scope: Self::DIScope,
+        line: u32,
+        col: u32,
+    ) -> Self::DILocation;
+
+    /// Creates the debug info of the local `name` of the body of `scope`, whose type
+    /// and layout is `ty_layout`. If the local is an argument, `arg_no` is its
+    /// position (starting from `1`).
+    fn create_local_variable(
+        ctx: &'a Self::CodegenCtx,
+        scope: Self::DIScope,
+        name: &str,
+        ty_layout: &TyAndLayout<LirTy>,
+        arg_no: Option<u32>,
+        line: u32,
+    ) -> Self::DIVariable;
+
+    /// Sets the location of the instructions built next.
+    fn set_debug_loc(&mut self, loc: Self::DILocation);
+
+    /// Declares that the given variable lives in the memory pointed to by `ptr`
+    /// (e.g., an `alloca`), from the given location.
+    fn declare_local(
+        &mut self,
+        variable: Self::DIVariable,
+        ptr: Self::Value,
+        loc: Self::DILocation,
+    );
+}
+
+/// The methods to attach to the loads and stores of the scalars the metadata which
+/// tells what memory they may alias (e.g., the TBAA metadata of LLVM), as selected
+/// by `LirCtx::alias_metadata`. The metadata is only a hint for the optimizations, so
+/// the default methods build the accesses without it, and a backend can ignore it.
+pub trait MetadataMethods<'a, 'be>: BuilderMethods<'a, 'be> {
+    /// Builds a load of the given scalar from `ptr`, as `build_load`, with the alias
+    /// metadata of the scalar.
+    fn build_scalar_load(
+        &mut self,
+        ty: Self::Type,
+        scalar: Scalar,
+        ptr: Self::Value,
+        align: Align,
+    ) -> CodegenResult<Self::Value> {
+        let _ = scalar;
+        self.build_load(ty, ptr, align)
+    }
+
+    /// Builds a store of the given scalar to `ptr`, as `build_store`, with the alias
+    /// metadata of the scalar.
+    fn build_scalar_store(
+        &mut self,
+        val: Self::Value,
+        scalar: Scalar,
+        ptr: Self::Value,
+        align: Align,
+    ) -> CodegenResult<()> {
+        let _ = scalar;
+        self.build_store(val, ptr, align)
+    }
+}
+
+/// An operand of an inline assembly, whose inputs are lowered to backend values.
+// It corresponds to `InlineAsmOperandRef` in rustc_codegen_ssa/src/traits/asm.rs
+pub enum InlineAsmOperandRef<V, T> {
+    /// An input, in a register of the given class or in the given register.
+    In {
+        reg: InlineAsmRegOrRegClass,
+        value: V,
+    },
+    /// An output of the given type, or `None` if it is discarded (i.e., the register
+    /// is only clobbered).
+    Out {
+        reg: InlineAsmRegOrRegClass,
+        ty: Option<T>,
+    },
+    /// An input overwritten by an output of the same type, in the same register.
+    InOut {
+        reg: InlineAsmRegOrRegClass,
+        value: V,
+    },
+}
+
+/// The methods to build the inline assembly (see `tidec_lir::syntax::InlineAsm`).
+// It corresponds to `AsmBuilderMethods` in rustc_codegen_ssa/src/traits/asm.rs
+pub trait AsmBuilderMethods<'a, 'be>: BuilderMethods<'a, 'be> {
+    /// Builds the given inline assembly, whose template refers to the operands by
+    /// their position (e.g., `{0}`). It returns the values of the outputs, that is,
+    /// of the `Out` operands that are not discarded and of the `InOut` ones, in order.
+    fn codegen_inline_asm(
+        &mut self,
+        template: &str,
+        operands: &[InlineAsmOperandRef<Self::Value, Self::Type>],
+        clobbers: &[String],
+        options: InlineAsmOptions,
+    ) -> CodegenResult<Vec<Self::Value>>;
+}
+
+/// The methods to build the calls of the intrinsics (see
+/// `tidec_lir::syntax::Intrinsic`).
+// It corresponds to `IntrinsicCallBuilderMethods` in rustc_codegen_ssa/src/traits/intrinsic.rs
+pub trait IntrinsicCallBuilderMethods<'a, 'be>: BuilderMethods<'a, 'be> {
+    /// Builds a call of the given intrinsic with the given (immediate) arguments,
+    /// whose first one is of type `arg_ty`. It returns the result of the call, or
+    /// `None` if the intrinsic returns `()`.
+    fn codegen_intrinsic_call(
+        &mut self,
+        intrinsic: Intrinsic,
+        args: &[Self::Value],
+        arg_ty: &LirTy,
+    ) -> CodegenResult<Option<Self::Value>>;
 }
@@ -0,0 +1,117 @@
+use std::num::NonZero;
+
+use tidec_abi::{layout::BackendRepr, size_and_align::Size};
+use tidec_interp::{error::InterpResult, interpreter::Interpreter, unsupported};
+use tidec_lir::{
+    lir::{DefId, LirCtx},
+    syntax::{Allocation, ConstScalar, ConstValue, LirTy, RawScalarValue, RETURN_LOCAL},
+};
+use tracing::instrument;
+
+#[instrument(level = "debug", skip(lir_ctx))]
+/// Returns the value of the constant computed by the body `def_id`. The body is
+/// interpreted the first time that the constant is evaluated, and its value is recorded
+/// in the `LirCtx` for the next evaluations.
+///
+/// The bodies called by the constant must be defined in the `LirCtx`.
+// It corresponds to the `eval_to_const_value_raw` query in rustc_const_eval/src/const_eval/eval_queries.rs
+pub fn eval_const(lir_ctx: &mut LirCtx, def_id: DefId) -> InterpResult<ConstValue> {
+    if let Some(value) = lir_ctx.const_value(def_id) {
+        return Ok(value.clone());
+    }
+    let lir_body = lir_ctx.def(def_id);
+    if !lir_body.is_const() {
+        panic!(
+            "The body {} is evaluated, but it is not a constant",
+            lir_body.metadata.name
+        );
+    }
+    let ret_ty = lir_body.ret_and_args[RETURN_LOCAL].ty.clone();
+    // TODO(bruzzone): support the pointers to the allocations of the constant (see
+    // `ConstScalar`), once the interpreter tracks the provenance of the pointers.
+    if has_pointers(lir_ctx, &ret_ty) {
+        return Err(unsupported!(
+            "The constant {} holds a pointer (its type is {:?})",
+            lir_body.metadata.name,
+            ret_ty
+        ));
+    }
+    let layout = lir_ctx.layout_of(ret_ty.clone());
+    let (is_zst, size, align, backend_repr) = (
+        layout.is_zst(),
+        layout.size,
+        layout.align.abi,
+        layout.backend_repr,
+    );
+
+    let mut interpreter = Interpreter::new(lir_ctx);
+    let bytes = interpreter.call(def_id, &[])?;
+    let scalar = match backend_repr {
+        BackendRepr::Scalar(_) => Some(interpreter.memory().bytes_to_uint(&bytes)),
+        BackendRepr::ScalarPair(..) | BackendRepr::Memory => None,
+    };
+
+    let value = if is_zst {
+        ConstValue::ZST
+    } else if let Some(data) = scalar {
+        ConstValue::Scalar(ConstScalar::Value(RawScalarValue {
+            data,
+            size: NonZero::new(size.bytes() as u8).expect("A scalar is not zero-sized"),
+        }))
+    } else {
+        ConstValue::Indirect {
+            alloc_id: lir_ctx.create_alloc(Allocation::from_bytes(bytes, align)),
+            offset: Size::from_bytes(0),
+        }
+    };
+    lir_ctx.record_const_value(def_id, value.clone());
+    Ok(value)
+}
+
+/// Evaluates all the constants defined in the `LirCtx` (see `eval_const`), e.g., to
+/// report the errors of the constants that are not used by the frontend.
+pub fn eval_consts(lir_ctx: &mut LirCtx) -> InterpResult<()> {
+    let consts = lir_ctx
+        .defs()
+        .filter(|lir_body| lir_body.is_const())
+        .map(|lir_body| lir_body.metadata.def_id)
+        .collect::<Vec<_>>();
+    for def_id in consts {
+        eval_const(lir_ctx, def_id)?;
+    }
+    Ok(())
+}
+
+/// Returns the value of the constant `def_id` of type `usize` (see
+/// `LirCtx::usize_ty`), e.g., the length of an array type.
+// It corresponds to `Const::eval_target_usize` in rustc_middle/src/ty/consts.rs
+pub fn eval_target_usize(lir_ctx: &mut LirCtx, def_id: DefId) -> InterpResult<u64> {
+    let ty = &lir_ctx.def(def_id).ret_and_args[RETURN_LOCAL].ty;
+    if *ty != lir_ctx.usize_ty() {
+        panic!(
+            "The constant {} is not a `usize` (its type is {:?})",
+            lir_ctx.def(def_id).metadata.name,
+            ty
+        );
+    }
+    match eval_const(lir_ctx, def_id)? {
+        ConstValue::Scalar(ConstScalar::Value(raw)) => Ok(raw.data as u64),
+        value => panic!("A `usize` constant evaluated to {:?}", value),
+    }
+}
+
+/// Returns `true` if a value of the given type can hold a pointer, whose address is only
+/// meaningful to the interpreter that computed the value.
+fn has_pointers(lir_ctx: &LirCtx, ty: &LirTy) -> bool {
+    match ty {
+        LirTy::Ptr(..) | LirTy::FnPtr(_) | LirTy::Slice(_) => true,
+        LirTy::Array(elem_ty, _) => has_pointers(lir_ctx, elem_ty),
+        LirTy::Tuple(field_tys) => field_tys.iter().any(|ty| has_pointers(lir_ctx, ty)),
+        LirTy::Adt(adt_id) => lir_ctx
+            .adt_def(*adt_id)
+            .variants
+            .iter()
+            .any(|variant| variant.fields.iter().any(|ty| has_pointers(lir_ctx, ty))),
+        _ => false,
+    }
+}
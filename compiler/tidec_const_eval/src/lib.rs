@@ -0,0 +1,10 @@
+//! The evaluation of the constants at compile time.
+//!
+//! A constant is a body of kind `LirItemKind::Const` (i.e., a `const fn` in the textual
+//! LIR), which takes no arguments and returns the value of the constant. It is run by
+//! the interpreter of `tidec_interp`, and its value is turned into a `ConstValue`, which
+//! is recorded in the `LirCtx` (see `LirCtx::const_value`). A frontend can then use it,
+//! e.g., as the length of an array type or as the initializer of a static.
+// It corresponds to rustc_const_eval/src/const_eval
+
+pub mod eval;
@@ -0,0 +1,145 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use tidec_utils::fingerprint::Fingerprint;
+use tracing::{debug, info, instrument, warn};
+
+use crate::fingerprint::UnitFingerprint;
+
+/// The default directory of the incremental compilation cache.
+pub const DEFAULT_INCREMENTAL_DIR: &str = "target/tidec-incremental";
+
+/// The first line of a manifest, which is bumped when its format changes.
+const MANIFEST_HEADER: &str = "tidec-incremental 2";
+
+#[derive(Debug, Clone)]
+/// The incremental compilation cache in a directory, as described in the crate
+/// documentation.
+pub struct IncrementalCache {
+    dir: PathBuf,
+}
+
+/// The manifest of a unit in the cache: its fingerprint and the path of its output.
+struct Manifest {
+    fingerprint: UnitFingerprint,
+    output: PathBuf,
+}
+
+impl IncrementalCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        IncrementalCache { dir: dir.into() }
+    }
+
+    /// Returns the path of the manifest of the given unit.
+    fn manifest_path(&self, unit_name: &str) -> PathBuf {
+        self.dir.join(format!("{}.fingerprint", unit_name))
+    }
+
+    /// Returns the path of the cached copy of the given output.
+    fn cached_output_path(&self, output: &Path) -> PathBuf {
+        self.dir.join(
+            output
+                .file_name()
+                .unwrap_or_else(|| panic!("The output {} is not a file", output.display())),
+        )
+    }
+
+    #[instrument(level = "debug", skip(self, fingerprint), fields(unit = %fingerprint.unit_name))]
+    /// Returns `true` if the cache holds the output of the unit with the given
+    /// fingerprint, after copying it to `output`, where the unit would be emitted
+    /// (which may differ from the previous run, e.g., for a temporary file). It
+    /// returns `false` if the unit has to be codegened again.
+    pub fn try_reuse(&self, fingerprint: &UnitFingerprint, output: &Path) -> bool {
+        let manifest = match self.read_manifest(&fingerprint.unit_name) {
+            Ok(manifest) => manifest,
+            Err(err) => {
+                debug!("The unit is not in the cache: {}", err);
+                return false;
+            }
+        };
+        if manifest.fingerprint.hash != fingerprint.hash {
+            let changed_bodies = fingerprint.changed_bodies(&manifest.fingerprint);
+            if changed_bodies.is_empty() {
+                debug!("The codegen inputs of the unit changed");
+            } else {
+                debug!("The bodies {} changed", changed_bodies.join(", "));
+            }
+            return false;
+        }
+
+        let cached_output = self.cached_output_path(&manifest.output);
+        let copy = || -> io::Result<u64> {
+            if let Some(dir) = output.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::copy(&cached_output, output)
+        };
+        if let Err(err) = copy() {
+            warn!("Could not reuse {}: {}", cached_output.display(), err);
+            return false;
+        }
+        info!("Reused {}", output.display());
+        true
+    }
+
+    #[instrument(level = "debug", skip(self, fingerprint), fields(unit = %fingerprint.unit_name))]
+    /// Stores the given output of the unit with the given fingerprint, to be reused
+    /// by the next runs.
+    pub fn save(&self, fingerprint: &UnitFingerprint, output: &Path) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::copy(output, self.cached_output_path(output))?;
+        // The manifest is written last, so that an output is never reused before
+        // it has been fully copied.
+        let mut manifest = format!(
+            "{}\nunit {}\noutput {}\n",
+            MANIFEST_HEADER,
+            fingerprint.hash,
+            output.display()
+        );
+        for (name, hash) in &fingerprint.body_hashes {
+            manifest.push_str(&format!("body {} {}\n", hash, name));
+        }
+        fs::write(self.manifest_path(&fingerprint.unit_name), manifest)
+    }
+
+    /// Reads the manifest of the given unit.
+    fn read_manifest(&self, unit_name: &str) -> io::Result<Manifest> {
+        let manifest = fs::read_to_string(self.manifest_path(unit_name))?;
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid manifest");
+        let parse_hash = |hash: &str| Fingerprint::from_hex(hash).ok_or_else(invalid);
+
+        let mut lines = manifest.lines();
+        if lines.next() != Some(MANIFEST_HEADER) {
+            return Err(invalid());
+        }
+        let hash = parse_hash(
+            lines
+                .next()
+                .and_then(|line| line.strip_prefix("unit "))
+                .ok_or_else(invalid)?,
+        )?;
+        let output = lines
+            .next()
+            .and_then(|line| line.strip_prefix("output "))
+            .ok_or_else(invalid)?;
+        let body_hashes = lines
+            .map(|line| {
+                let (hash, name) = line
+                    .strip_prefix("body ")
+                    .and_then(|body| body.split_once(' '))
+                    .ok_or_else(invalid)?;
+                Ok((name.to_string(), parse_hash(hash)?))
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(Manifest {
+            fingerprint: UnitFingerprint {
+                unit_name: unit_name.to_string(),
+                hash,
+                body_hashes,
+            },
+            output: PathBuf::from(output),
+        })
+    }
+}
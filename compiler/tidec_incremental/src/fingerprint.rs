@@ -0,0 +1,51 @@
+use std::hash::Hash;
+
+use tidec_lir::lir::{LirCtx, LirUnit};
+use tidec_utils::{fingerprint::Fingerprint, stable_hasher::StableHasher};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The fingerprint of a (codegen) unit, which changes whenever the code generated
+/// for the unit may change. It is the same across runs and hosts.
+pub struct UnitFingerprint {
+    /// The name of the unit, which identifies it in the cache.
+    pub unit_name: String,
+    /// The hash of the bodies and the imports of the unit, of the codegen inputs of the
+    /// context (see `LirCtx::hash_codegen_inputs`), and of the version of the compiler.
+    pub hash: Fingerprint,
+    /// The name and the hash of each body of the unit (including the declarations),
+    /// in the order of the unit.
+    pub body_hashes: Vec<(String, Fingerprint)>,
+}
+
+impl UnitFingerprint {
+    /// Returns the fingerprint of the given unit, codegened with the given context.
+    pub fn new(lir_ctx: &LirCtx, lir_unit: &LirUnit) -> Self {
+        let body_hashes: Vec<(String, Fingerprint)> = lir_unit
+            .bodies
+            .iter()
+            .map(|lir_body| (lir_body.metadata.name.clone(), lir_body.fingerprint()))
+            .collect();
+
+        let mut hasher = StableHasher::new();
+        env!("CARGO_PKG_VERSION").hash(&mut hasher);
+        lir_ctx.hash_codegen_inputs(&mut hasher);
+        body_hashes.hash(&mut hasher);
+        // The signatures of the imports give the ABI of the calls to them.
+        lir_unit.imports.hash(&mut hasher);
+        UnitFingerprint {
+            unit_name: lir_unit.metadata.unit_name.clone(),
+            hash: hasher.finish_fingerprint(),
+            body_hashes,
+        }
+    }
+
+    /// Returns the names of the bodies that are new or changed with respect to the
+    /// given (previous) fingerprint.
+    pub fn changed_bodies<'a>(&'a self, previous: &UnitFingerprint) -> Vec<&'a str> {
+        self.body_hashes
+            .iter()
+            .filter(|body_hash| !previous.body_hashes.contains(body_hash))
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+}
@@ -0,0 +1,12 @@
+//! The incremental compilation cache, which reuses the outputs of the codegen units
+//! whose bodies did not change since the previous run.
+//!
+//! A codegen unit is identified by its fingerprint (see `UnitFingerprint`), which
+//! hashes its bodies and everything else that affects their code. The cache directory
+//! (`target/tidec-incremental` by default) holds, for each codegen unit, the file
+//! emitted for it and a manifest with its fingerprint. A unit whose fingerprint
+//! matches the one of the manifest is not codegened again: its file is copied back.
+// It corresponds to rustc_incremental/src/persist
+
+pub mod cache;
+pub mod fingerprint;
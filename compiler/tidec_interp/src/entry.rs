@@ -0,0 +1,64 @@
+use std::error::Error;
+
+use tidec_abi::target::BackendKind;
+use tidec_codegen_ssa::backend::{BackendOutput, CodegenBackendDriver};
+use tidec_lir::{
+    lir::{LirCtx, LirUnit},
+    syntax::{LirTy, RETURN_LOCAL},
+};
+use tracing::instrument;
+
+use crate::{error::InterpResult, interpreter::Interpreter, unsupported};
+
+#[instrument(level = "info", skip(lir_ctx, lir_unit), fields(unit = %lir_unit.metadata.unit_name))]
+/// Interprets the `main` function of the given unit, and returns its return value as
+/// the exit code of the program. A `main` function returning `()` exits with `0`.
+pub fn interp_lir_unit(mut lir_ctx: LirCtx, lir_unit: LirUnit) -> InterpResult<i32> {
+    lir_ctx.define_unit(lir_unit);
+    let main = lir_ctx
+        .def_id_of("main")
+        .ok_or_else(|| unsupported!("The unit has no `main` function"))?;
+    let main_body = lir_ctx.def(main);
+    if main_body.ret_and_args.len() > 1 {
+        return Err(unsupported!("A `main` function with arguments"));
+    }
+    let ret_ty = main_body.ret_and_args[RETURN_LOCAL].ty.clone();
+
+    let mut interpreter = Interpreter::new(&lir_ctx);
+    let ret = interpreter.call(main, &[])?;
+    let ret_val = interpreter.memory().bytes_to_uint(&ret);
+    match ret_ty {
+        LirTy::Tuple(field_tys) if field_tys.is_empty() => Ok(0),
+        LirTy::I8 | LirTy::I16 | LirTy::I32 | LirTy::I64 | LirTy::I128 => {
+            Ok(crate::interpreter::sign_extend(ret_val, ret.len() as u64 * 8) as i32)
+        }
+        LirTy::Bool | LirTy::U8 | LirTy::U16 | LirTy::U32 | LirTy::U64 | LirTy::U128 => {
+            Ok(ret_val as i32)
+        }
+        ret_ty => Err(unsupported!("A `main` function returning {:?}", ret_ty)),
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+/// The interpreter backend, which runs the `main` function of the unit (see
+/// `interp_lir_unit`).
+pub struct InterpBackend;
+
+impl CodegenBackendDriver for InterpBackend {
+    fn name(&self) -> &'static str {
+        "interp"
+    }
+
+    fn backend_kind(&self) -> BackendKind {
+        BackendKind::Interp
+    }
+
+    fn codegen_lir_unit(
+        &self,
+        lir_ctx: LirCtx,
+        lir_unit: LirUnit,
+    ) -> Result<BackendOutput, Box<dyn Error>> {
+        let exit_code = interp_lir_unit(lir_ctx, lir_unit)?;
+        Ok(BackendOutput::Exited(exit_code))
+    }
+}
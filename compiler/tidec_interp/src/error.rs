@@ -0,0 +1,48 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// An error that stops the interpretation of a program.
+// It corresponds to `InterpErrorKind` in rustc_middle/src/mir/interpret/error.rs
+pub enum InterpError {
+    /// The program panicked (e.g., an `Assert` failed) with the given message.
+    Panic(String),
+    /// The program has undefined behavior, as described by the message. The
+    /// compiled program may do anything at this point.
+    UndefinedBehavior(String),
+    /// The program uses something that the interpreter does not support (e.g.,
+    /// a call to a foreign function).
+    Unsupported(String),
+    /// The call stack grew beyond `interpreter::STACK_LIMIT` frames.
+    StackOverflow,
+}
+
+impl fmt::Display for InterpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InterpError::Panic(msg) => write!(f, "panicked: {}", msg),
+            InterpError::UndefinedBehavior(msg) => write!(f, "undefined behavior: {}", msg),
+            InterpError::Unsupported(msg) => write!(f, "unsupported: {}", msg),
+            InterpError::StackOverflow => write!(f, "stack overflow"),
+        }
+    }
+}
+
+impl std::error::Error for InterpError {}
+
+pub type InterpResult<T> = Result<T, InterpError>;
+
+/// Returns an `InterpError::UndefinedBehavior` with the given formatted message.
+#[macro_export]
+macro_rules! ub {
+    ($($arg:tt)*) => {
+        $crate::error::InterpError::UndefinedBehavior(format!($($arg)*))
+    };
+}
+
+/// Returns an `InterpError::Unsupported` with the given formatted message.
+#[macro_export]
+macro_rules! unsupported {
+    ($($arg:tt)*) => {
+        $crate::error::InterpError::Unsupported(format!($($arg)*))
+    };
+}
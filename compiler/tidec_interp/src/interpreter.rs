@@ -0,0 +1,919 @@
+//! The interpreter of the LIR.
+//!
+//! It executes the bodies of a `LirCtx` statement by statement, on a stack of frames
+//! kept by the interpreter (so that a deep recursion does not overflow the stack of
+//! the compiler). Every local lives in its own allocation of the `Memory`, and the
+//! values are moved around as the bytes of their layout. Hence, the interpreter
+//! gives the LIR the same semantics as the codegen backends: the operations whose
+//! result is poison or undefined behavior in LLVM (e.g., a division by zero) are
+//! reported as `InterpError::UndefinedBehavior`.
+//!
+//! The memory is zero-initialized, so the reads of uninitialized memory are not
+//! detected.
+// It corresponds to `InterpCx` in rustc_const_eval/src/interpret/eval_context.rs
+
+use tidec_abi::{
+    layout::{Primitive, TagEncoding, TyAndLayout, VariantIdx, Variants},
+    size_and_align::Size,
+};
+use tidec_lir::{
+    basic_blocks::{BasicBlock, ENTRY_BLOCK},
+    lir::{DefId, LirBody, LirCtx},
+    syntax::{
+        BinOp, Callee, CastKind, ConstScalar, ConstValue, Intrinsic, LirTy, Local, Operand, Place,
+        PlaceTy, Projection, RValue, Statement, StatementKind, Terminator, TerminatorKind, UnOp,
+        RETURN_LOCAL,
+    },
+};
+use tidec_utils::{idx::Idx, index_vec::IdxVec};
+use tracing::{debug, instrument, trace};
+
+use crate::{
+    error::{InterpError, InterpResult},
+    memory::{AllocKind, Memory},
+    ub, unsupported,
+};
+
+/// The maximum number of frames on the stack.
+pub const STACK_LIMIT: usize = 10_000;
+
+/// The state of a function being executed.
+struct Frame<'ctx> {
+    /// The body of the function.
+    lir_body: &'ctx LirBody,
+    /// The address of each local, including the return value and the arguments.
+    locals: IdxVec<Local, u64>,
+    /// The block being executed.
+    bb: BasicBlock,
+    /// The index of the next statement to execute in `bb`. It is the number of the
+    /// statements of `bb` when the terminator is next.
+    statement_idx: usize,
+    /// The address where the caller expects the return value, and the block where
+    /// it continues (`None` if the callee never returns). It is `None` for the frame
+    /// of `Interpreter::call`.
+    return_to: Option<(u64, Option<BasicBlock>)>,
+}
+
+impl<'ctx> Frame<'ctx> {
+    /// Returns the type of the given local, which can be either the return value,
+    /// an argument, or any other local of the body.
+    fn local_ty(&self, local: Local) -> &'ctx LirTy {
+        let ret_and_args_len = self.lir_body.ret_and_args.len();
+        if local.idx() < ret_and_args_len {
+            &self.lir_body.ret_and_args[local].ty
+        } else {
+            &self.lir_body.locals[Local::new(local.idx() - ret_and_args_len)].ty
+        }
+    }
+}
+
+/// An interpreter of the bodies defined in a `LirCtx`. See the module documentation.
+pub struct Interpreter<'ctx> {
+    lir_ctx: &'ctx LirCtx,
+    memory: Memory,
+    stack: Vec<Frame<'ctx>>,
+}
+
+impl<'ctx> Interpreter<'ctx> {
+    pub fn new(lir_ctx: &'ctx LirCtx) -> Self {
+        Interpreter {
+            lir_ctx,
+            memory: Memory::new(lir_ctx.target().data_layout.endianess),
+            stack: Vec::new(),
+        }
+    }
+
+    pub fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    /// Calls the given function with the given arguments, each given as the bytes of
+    /// its layout, and returns the bytes of the return value.
+    ///
+    /// The memory written by the function (e.g., through a pointer argument) is kept,
+    /// so that it can be inspected after the call.
+    #[instrument(level = "debug", skip(self, args))]
+    pub fn call(&mut self, def_id: DefId, args: &[Vec<u8>]) -> InterpResult<Vec<u8>> {
+        assert!(self.stack.is_empty(), "The interpreter is already running");
+        let result = self
+            .push_frame(def_id, args.to_vec(), None)
+            .and_then(|()| self.run());
+        // On errors, the frames are dropped from where they stopped.
+        while let Some(frame) = self.stack.pop() {
+            self.deallocate_locals(&frame)?;
+        }
+        result
+    }
+
+    /// Executes the frames on the stack, until the first one returns.
+    fn run(&mut self) -> InterpResult<Vec<u8>> {
+        loop {
+            if let Some(ret) = self.step()? {
+                return Ok(ret);
+            }
+        }
+    }
+
+    /// Executes the next statement or terminator. It returns the return value of the
+    /// first frame when it returns.
+    fn step(&mut self) -> InterpResult<Option<Vec<u8>>> {
+        let frame = self.frame();
+        let lir_body = frame.lir_body;
+        let bb_data = &lir_body.basic_blocks[frame.bb];
+        match bb_data.statements.get(frame.statement_idx) {
+            Some(stmt) => {
+                self.eval_statement(stmt)?;
+                self.frame_mut().statement_idx += 1;
+                Ok(None)
+            }
+            None => self.eval_terminator(&bb_data.terminator),
+        }
+    }
+
+    fn frame(&self) -> &Frame<'ctx> {
+        self.stack.last().expect("The stack is empty")
+    }
+
+    fn frame_mut(&mut self) -> &mut Frame<'ctx> {
+        self.stack.last_mut().expect("The stack is empty")
+    }
+
+    /// Pushes the frame of a call to the given function, with its locals allocated
+    /// and its arguments written.
+    fn push_frame(
+        &mut self,
+        def_id: DefId,
+        args: Vec<Vec<u8>>,
+        return_to: Option<(u64, Option<BasicBlock>)>,
+    ) -> InterpResult<()> {
+        let lir_body = self.lir_ctx.callee(def_id);
+        let name = &lir_body.metadata.name;
+        if lir_body.basic_blocks.is_empty() {
+            return Err(unsupported!("Call of the foreign function {}", name));
+        }
+        if !lir_body.generics.is_empty() {
+            return Err(unsupported!("Call of the generic function {}", name));
+        }
+        if args.len() + 1 != lir_body.ret_and_args.len() {
+            return Err(ub!(
+                "Call of {} with {} arguments instead of {}",
+                name,
+                args.len(),
+                lir_body.ret_and_args.len() - 1
+            ));
+        }
+        if self.stack.len() == STACK_LIMIT {
+            return Err(InterpError::StackOverflow);
+        }
+        debug!("Call {} with args {:?}", name, args);
+
+        let locals = lir_body
+            .ret_and_args
+            .iter()
+            .chain(lir_body.locals.iter())
+            .map(|local_data| {
+                let layout = self.lir_ctx.layout_of(local_data.ty.clone());
+                self.memory
+                    .allocate(layout.size, layout.align.abi, AllocKind::Stack)
+            })
+            .collect::<IdxVec<Local, u64>>();
+        let frame = Frame {
+            lir_body,
+            locals,
+            bb: ENTRY_BLOCK,
+            statement_idx: 0,
+            return_to,
+        };
+        // The frame is pushed before the arguments are written, so that its locals
+        // are deallocated if the writes fail.
+        self.stack.push(frame);
+        for (i, arg) in args.iter().enumerate() {
+            let local = Local::new(i + 1);
+            let size = self
+                .lir_ctx
+                .layout_of(self.frame().local_ty(local).clone())
+                .size;
+            if arg.len() as u64 != size.bytes() {
+                return Err(ub!(
+                    "Argument of {} bytes for the parameter {:?} of {} bytes",
+                    arg.len(),
+                    local,
+                    size.bytes()
+                ));
+            }
+            self.memory.write(self.frame().locals[local], arg)?;
+        }
+        Ok(())
+    }
+
+    fn deallocate_locals(&mut self, frame: &Frame<'ctx>) -> InterpResult<()> {
+        frame
+            .locals
+            .iter()
+            .try_for_each(|address| self.memory.deallocate(*address))
+    }
+
+    fn eval_statement(&mut self, stmt: &Statement) -> InterpResult<()> {
+        trace!("Eval statement: {:?}", stmt);
+        match &stmt.kind {
+            StatementKind::Assign(assign) => {
+                let (place, rvalue) = &**assign;
+                let value = self.eval_rvalue(rvalue)?;
+                let (address, _) = self.eval_place(place)?;
+                self.memory.write(address, &value)
+            }
+            StatementKind::SetDiscriminant { place, variant_idx } => {
+                self.write_discriminant(place, *variant_idx)
+            }
+            StatementKind::InlineAsm(asm) => {
+                Err(unsupported!("Inline assembly `{}`", asm.template))
+            }
+        }
+    }
+
+    fn eval_terminator(&mut self, term: &Terminator) -> InterpResult<Option<Vec<u8>>> {
+        trace!("Eval terminator: {:?}", term);
+        match &term.kind {
+            TerminatorKind::Goto { target } => self.goto(*target),
+            TerminatorKind::Return => {
+                let frame = self.stack.pop().expect("The stack is empty");
+                let ret_size = self
+                    .lir_ctx
+                    .layout_of(frame.local_ty(RETURN_LOCAL).clone())
+                    .size;
+                let ret = self
+                    .memory
+                    .read(frame.locals[RETURN_LOCAL], ret_size)?
+                    .to_vec();
+                self.deallocate_locals(&frame)?;
+                match frame.return_to {
+                    Some((destination, Some(target))) => {
+                        self.memory.write(destination, &ret)?;
+                        self.goto(target);
+                    }
+                    Some((_, None)) => {
+                        return Err(ub!(
+                            "Return from {}, which never returns",
+                            frame.lir_body.metadata.name
+                        ));
+                    }
+                    None => return Ok(Some(ret)),
+                }
+            }
+            TerminatorKind::Unreachable => return Err(ub!("Entered unreachable code")),
+            TerminatorKind::Assert {
+                cond,
+                expected,
+                msg,
+                target,
+                ..
+            } => {
+                let (cond, _) = self.eval_operand(cond)?;
+                if (self.memory.bytes_to_uint(&cond) != 0) != *expected {
+                    return Err(InterpError::Panic(msg.description().to_string()));
+                }
+                self.goto(*target);
+            }
+            TerminatorKind::Call {
+                func,
+                args,
+                destination,
+                target,
+                ..
+            } => {
+                let def_id = match func {
+                    Callee::Direct(def_id) => *def_id,
+                    Callee::Generic(def_id, _) => {
+                        return Err(unsupported!(
+                            "Generic callee {:?} must be instantiated before interpretation",
+                            def_id
+                        ))
+                    }
+                    Callee::Indirect(fn_ptr) => {
+                        let (fn_ptr, _) = self.eval_operand(fn_ptr)?;
+                        self.memory
+                            .function_at(self.memory.bytes_to_uint(&fn_ptr) as u64)?
+                    }
+                };
+                let args = args
+                    .iter()
+                    .map(|arg| self.eval_operand(arg).map(|(value, _)| value))
+                    .collect::<InterpResult<Vec<_>>>()?;
+                let (destination, _) = self.eval_place(destination)?;
+                self.push_frame(def_id, args, Some((destination, *target)))?;
+            }
+            TerminatorKind::Drop { place, target, .. } => {
+                let (address, layout) = self.eval_place(place)?;
+                let Some(drop_glue) = self.lir_ctx.drop_glue(&layout.ty) else {
+                    self.goto(*target);
+                    return Ok(None);
+                };
+                let ptr_layout = self.lir_ctx.layout_of(LirTy::ptr_to(layout.ty.clone()));
+                let ptr = self
+                    .memory
+                    .uint_to_bytes(u128::from(address), ptr_layout.size);
+                // The drop glue returns `()`, so nothing is written to the place.
+                self.push_frame(drop_glue, vec![ptr], Some((address, Some(*target))))?;
+            }
+            // A panic stops the interpretation, so the cleanup blocks are never entered.
+            TerminatorKind::UnwindResume => return Err(unsupported!("Unwinding")),
+        }
+        Ok(None)
+    }
+
+    /// Continues the execution of the current frame at the start of `target`.
+    fn goto(&mut self, target: BasicBlock) {
+        let frame = self.frame_mut();
+        frame.bb = target;
+        frame.statement_idx = 0;
+    }
+
+    /// Returns the address of the given place, and its type and layout.
+    // It corresponds to `eval_place` in rustc_const_eval/src/interpret/place.rs
+    fn eval_place(&self, place: &Place) -> InterpResult<(u64, TyAndLayout<'ctx, LirTy>)> {
+        let frame = self.frame();
+        let mut address = frame.locals[place.local];
+        let mut place_ty = PlaceTy::from_ty(frame.local_ty(place.local).clone());
+        let mut layout = self.lir_ctx.layout_of(place_ty.ty.clone());
+
+        for projection in &place.projection {
+            place_ty = place_ty.projection_ty(self.lir_ctx, projection);
+            match *projection {
+                Projection::Deref => {
+                    address = self.memory.read_uint(address, layout.size)? as u64;
+                    if address == 0 {
+                        return Err(ub!("Dereference of a null pointer"));
+                    }
+                    layout = self.lir_ctx.layout_of(place_ty.ty.clone());
+                }
+                Projection::Field(field_idx) => {
+                    // The fields of an enum variant come after the tag, unless the tag
+                    // is a niche (see `PlaceRef::project_field` in tidec_codegen_ssa).
+                    let layout_field_idx = match &layout.ty {
+                        LirTy::Adt(adt_id)
+                            if self.lir_ctx.adt_def(*adt_id).is_enum()
+                                && self.lir_ctx.layout_of(layout.ty.clone()).has_tag_field() =>
+                        {
+                            field_idx.idx() + 1
+                        }
+                        _ => field_idx.idx(),
+                    };
+                    address += layout.field_offset(layout_field_idx).bytes();
+                    layout = self.lir_ctx.layout_of(place_ty.ty.clone());
+                }
+                Projection::Index(index) => {
+                    let index_layout = self.lir_ctx.layout_of(frame.local_ty(index).clone());
+                    let index = self
+                        .memory
+                        .read_uint(frame.locals[index], index_layout.size)?;
+                    let count = layout.fields.count();
+                    if index >= count as u128 {
+                        return Err(ub!("Index {} out of bounds of {} elements", index, count));
+                    }
+                    address += layout.field_offset(index as usize).bytes();
+                    layout = self.lir_ctx.layout_of(place_ty.ty.clone());
+                }
+                // The variant lives at the same address as the enum, only the layout changes.
+                Projection::Downcast(variant_idx) => layout = layout.for_variant(variant_idx),
+            }
+        }
+
+        Ok((address, layout))
+    }
+
+    /// Returns the bytes of the value of the given operand, and its type and layout.
+    fn eval_operand(
+        &mut self,
+        operand: &Operand,
+    ) -> InterpResult<(Vec<u8>, TyAndLayout<'ctx, LirTy>)> {
+        match operand {
+            // A move is a copy: the moved place is not reused by a well-formed body.
+            Operand::Copy(place) | Operand::Move(place) => {
+                let (address, layout) = self.eval_place(place)?;
+                let value = self.memory.read(address, layout.size)?.to_vec();
+                Ok((value, layout))
+            }
+            Operand::Const(const_operand) => {
+                let layout = self.lir_ctx.layout_of(const_operand.ty());
+                let value = self.eval_const(const_operand.value(), &layout)?;
+                Ok((value, layout))
+            }
+        }
+    }
+
+    /// Returns the bytes of the given constant, whose type and layout is `layout`.
+    fn eval_const(
+        &mut self,
+        const_val: &ConstValue,
+        layout: &TyAndLayout<'ctx, LirTy>,
+    ) -> InterpResult<Vec<u8>> {
+        let value = match const_val {
+            ConstValue::ZST => vec![],
+            ConstValue::Scalar(ConstScalar::Value(raw)) => self
+                .memory
+                .uint_to_bytes(raw.to_bits(layout.size), layout.size),
+            ConstValue::FnPtr(def_id) => {
+                let address = self.memory.function_address(*def_id);
+                self.memory.uint_to_bytes(u128::from(address), layout.size)
+            }
+            ConstValue::Array(elems) => {
+                let LirTy::Array(elem_ty, _) = &layout.ty else {
+                    panic!("Array constant of non-array type {:?}", layout.ty);
+                };
+                let elem_layout = self.lir_ctx.layout_of((**elem_ty).clone());
+                let mut value = vec![0; layout.size.bytes() as usize];
+                for (i, elem) in elems.iter().enumerate() {
+                    let offset = layout.field_offset(i).bytes() as usize;
+                    let elem = self.eval_const(elem, &elem_layout)?;
+                    value[offset..offset + elem.len()].copy_from_slice(&elem);
+                }
+                value
+            }
+            ConstValue::Slice { alloc_id, len } => {
+                let LirTy::Slice(elem_ty) = &layout.ty else {
+                    panic!("Slice constant of non-slice type {:?}", layout.ty);
+                };
+                let address = self.memory.global_address(self.lir_ctx, *alloc_id);
+                let [data_ty, len_ty] = self.lir_ctx.slice_field_tys(elem_ty);
+                let mut value = vec![0; layout.size.bytes() as usize];
+                for (i, (field, ty)) in [(u128::from(address), data_ty), (u128::from(*len), len_ty)]
+                    .into_iter()
+                    .enumerate()
+                {
+                    let offset = layout.field_offset(i).bytes() as usize;
+                    let field = self
+                        .memory
+                        .uint_to_bytes(field, self.lir_ctx.layout_of(ty).size);
+                    value[offset..offset + field.len()].copy_from_slice(&field);
+                }
+                value
+            }
+            ConstValue::Indirect { alloc_id, offset } => {
+                let address = self.memory.global_address(self.lir_ctx, *alloc_id);
+                self.memory
+                    .read(address + offset.bytes(), layout.size)?
+                    .to_vec()
+            }
+        };
+        Ok(value)
+    }
+
+    /// Returns the bytes of the value of the given rvalue.
+    fn eval_rvalue(&mut self, rvalue: &RValue) -> InterpResult<Vec<u8>> {
+        match rvalue {
+            RValue::Use(operand) => Ok(self.eval_operand(operand)?.0),
+            RValue::BinaryOp(bin_op, lhs, rhs) => {
+                let (lhs, layout) = self.eval_operand(lhs)?;
+                let (rhs, _) = self.eval_operand(rhs)?;
+                let lhs = self.memory.bytes_to_uint(&lhs);
+                let rhs = self.memory.bytes_to_uint(&rhs);
+                let value = eval_binop(*bin_op, lhs, rhs, &layout)?;
+                let result_layout = self.lir_ctx.layout_of(bin_op.ty(&layout.ty));
+                Ok(self.memory.uint_to_bytes(value, result_layout.size))
+            }
+            RValue::UnaryOp(un_op, operand) => {
+                let (operand, layout) = self.eval_operand(operand)?;
+                let operand = self.memory.bytes_to_uint(&operand);
+                let value = eval_unop(*un_op, operand, &layout)?;
+                Ok(self.memory.uint_to_bytes(value, layout.size))
+            }
+            RValue::Ref(place) => {
+                let (address, layout) = self.eval_place(place)?;
+                let ptr_layout = self.lir_ctx.layout_of(LirTy::ptr_to(layout.ty.clone()));
+                Ok(self
+                    .memory
+                    .uint_to_bytes(u128::from(address), ptr_layout.size))
+            }
+            RValue::Cast(cast_kind, operand, cast_ty) => {
+                let (operand, layout) = self.eval_operand(operand)?;
+                let cast_layout = self.lir_ctx.layout_of(cast_ty.clone());
+                if *cast_kind == CastKind::Transmute {
+                    if layout.size != cast_layout.size {
+                        return Err(ub!(
+                            "Transmute from {:?} to {:?}, of different sizes",
+                            layout.ty,
+                            cast_ty
+                        ));
+                    }
+                    return Ok(operand);
+                }
+                let operand = self.memory.bytes_to_uint(&operand);
+                let value = eval_cast(*cast_kind, operand, &layout, &cast_layout)?;
+                Ok(self.memory.uint_to_bytes(value, cast_layout.size))
+            }
+            RValue::Discriminant(place) => self.read_discriminant(place),
+            RValue::Intrinsic(intrinsic, args) => self.eval_intrinsic(*intrinsic, args),
+        }
+    }
+
+    /// Returns the bytes of the result of the given intrinsic.
+    fn eval_intrinsic(&mut self, intrinsic: Intrinsic, args: &[Operand]) -> InterpResult<Vec<u8>> {
+        let mut values = Vec::with_capacity(args.len());
+        let mut layouts = Vec::with_capacity(args.len());
+        for arg in args {
+            let (value, layout) = self.eval_operand(arg)?;
+            values.push(self.memory.bytes_to_uint(&value));
+            layouts.push(layout);
+        }
+        match intrinsic {
+            Intrinsic::Memcpy | Intrinsic::Memmove | Intrinsic::Memset => {
+                let dest = values[0] as u64;
+                let size = Size::from_bytes(values[2] as u64);
+                match intrinsic {
+                    Intrinsic::Memset => {
+                        let bytes = vec![values[1] as u8; size.bytes() as usize];
+                        self.memory.write(dest, &bytes)?;
+                    }
+                    _ => {
+                        let src = values[1] as u64;
+                        if intrinsic == Intrinsic::Memcpy
+                            && src < dest + size.bytes()
+                            && dest < src + size.bytes()
+                        {
+                            return Err(ub!(
+                                "memcpy of {} bytes between the overlapping {:#x} and {:#x}",
+                                size.bytes(),
+                                src,
+                                dest
+                            ));
+                        }
+                        self.memory.copy(src, dest, size)?;
+                    }
+                }
+                // The result is the unit, which has no bytes.
+                Ok(vec![])
+            }
+            Intrinsic::Sqrt | Intrinsic::Fabs | Intrinsic::Fma => {
+                let value = eval_float_intrinsic(intrinsic, &values, &layouts[0].ty)?;
+                Ok(self.memory.uint_to_bytes(value, layouts[0].size))
+            }
+            _ => {
+                let value = eval_int_intrinsic(intrinsic, &values, &layouts[0])?;
+                Ok(self.memory.uint_to_bytes(value, layouts[0].size))
+            }
+        }
+    }
+
+    /// Returns the bytes of the discriminant of the enum in the given place.
+    // It corresponds to `read_discriminant` in rustc_const_eval/src/interpret/discriminant.rs
+    fn read_discriminant(&mut self, place: &Place) -> InterpResult<Vec<u8>> {
+        let (address, layout) = self.eval_place(place)?;
+        let LirTy::Adt(adt_id) = &layout.ty else {
+            panic!("Type {:?} has no discriminant", layout.ty);
+        };
+        let discr_layout = self
+            .lir_ctx
+            .layout_of(self.lir_ctx.adt_def(*adt_id).discr_ty());
+        let discr = match &layout.variants {
+            // The variant is statically known (e.g., a struct).
+            Variants::Single { index } => index.idx() as u128,
+            Variants::Multiple {
+                tag,
+                tag_offset,
+                tag_encoding,
+                ..
+            } => {
+                let tag_size = self.primitive_size(*tag);
+                let tag_val = self
+                    .memory
+                    .read_uint(address + tag_offset.bytes(), tag_size)?;
+                match tag_encoding {
+                    // The value of the tag is the variant index.
+                    TagEncoding::Direct => tag_val,
+                    // The tag is `niche_start + (variant - niche_variants.start())` for the
+                    // niche variants, and any other value for the untagged variant.
+                    TagEncoding::Niche {
+                        untagged_variant,
+                        niche_variants,
+                        niche_start,
+                    } => {
+                        let relative = truncate(tag_val.wrapping_sub(*niche_start), tag_size);
+                        let relative_max =
+                            (niche_variants.end().idx() - niche_variants.start().idx()) as u128;
+                        if relative <= relative_max {
+                            niche_variants.start().idx() as u128 + relative
+                        } else {
+                            untagged_variant.idx() as u128
+                        }
+                    }
+                }
+            }
+        };
+        Ok(self.memory.uint_to_bytes(discr, discr_layout.size))
+    }
+
+    /// Writes the discriminant of the enum in the given place, making `variant_idx`
+    /// its active variant.
+    // It corresponds to `write_discriminant` in rustc_const_eval/src/interpret/discriminant.rs
+    fn write_discriminant(&mut self, place: &Place, variant_idx: VariantIdx) -> InterpResult<()> {
+        let (address, layout) = self.eval_place(place)?;
+        match &layout.variants {
+            Variants::Single { index } => {
+                assert_eq!(
+                    *index, variant_idx,
+                    "Cannot set the discriminant of a single-variant type to another variant"
+                );
+                Ok(())
+            }
+            Variants::Multiple {
+                tag,
+                tag_offset,
+                tag_encoding,
+                ..
+            } => {
+                let tag_value = match tag_encoding {
+                    TagEncoding::Direct => variant_idx.idx() as u128,
+                    // The untagged variant is identified by its data, so there is nothing to write.
+                    TagEncoding::Niche {
+                        untagged_variant, ..
+                    } if *untagged_variant == variant_idx => return Ok(()),
+                    TagEncoding::Niche {
+                        niche_variants,
+                        niche_start,
+                        ..
+                    } => {
+                        let relative = (variant_idx.idx() - niche_variants.start().idx()) as u128;
+                        relative.wrapping_add(*niche_start)
+                    }
+                };
+                let tag_size = self.primitive_size(*tag);
+                self.memory
+                    .write_uint(address + tag_offset.bytes(), tag_value, tag_size)
+            }
+        }
+    }
+
+    /// Returns the size of the given primitive, which is a tag.
+    fn primitive_size(&self, primitive: Primitive) -> Size {
+        match primitive {
+            Primitive::I8 | Primitive::U8 => Size::from_bytes(1),
+            Primitive::I16 | Primitive::U16 => Size::from_bytes(2),
+            Primitive::I32 | Primitive::U32 => Size::from_bytes(4),
+            Primitive::I64 | Primitive::U64 => Size::from_bytes(8),
+            Primitive::I128 | Primitive::U128 => Size::from_bytes(16),
+            Primitive::Pointer(addr_space) => Size::from_bits(
+                self.lir_ctx
+                    .target()
+                    .data_layout
+                    .pointer_size_in(addr_space),
+            ),
+            Primitive::F16 | Primitive::F32 | Primitive::F64 | Primitive::F128 => {
+                panic!("A tag cannot be a floating-point number")
+            }
+        }
+    }
+}
+
+/// Returns the width in bits of the given integer. A boolean is a 1-bit integer,
+/// even if it is stored as a byte (as in codegen).
+fn int_bits(layout: &TyAndLayout<LirTy>) -> u64 {
+    match layout.ty {
+        LirTy::Bool => 1,
+        _ => layout.size.bits(),
+    }
+}
+
+/// Keeps the lowest `size` bytes of `value`.
+fn truncate(value: u128, size: Size) -> u128 {
+    truncate_bits(value, size.bits())
+}
+
+/// Keeps the lowest `bits` bits of `value`.
+fn truncate_bits(value: u128, bits: u64) -> u128 {
+    match bits {
+        0 => 0,
+        128.. => value,
+        _ => value & ((1 << bits) - 1),
+    }
+}
+
+/// Interprets the lowest `bits` bits of `value` as a signed integer.
+pub(crate) fn sign_extend(value: u128, bits: u64) -> i128 {
+    let shift = 128 - bits;
+    ((value << shift) as i128) >> shift
+}
+
+/// Evaluates a binary operation between two integers, booleans or pointers, whose
+/// type and layout is `layout`.
+///
+/// The arithmetic is wrapping, as in codegen. The divisions and remainders by zero,
+/// the signed division of the minimum value by `-1` and the shifts by an amount
+/// greater than or equal to the bit width are undefined behavior.
+fn eval_binop(
+    bin_op: BinOp,
+    lhs: u128,
+    rhs: u128,
+    layout: &TyAndLayout<LirTy>,
+) -> InterpResult<u128> {
+    if layout.ty.is_floating_point() {
+        return Err(unsupported!("Binary operation {:?} on floats", bin_op));
+    }
+    let bits = int_bits(layout);
+    let is_signed = layout.ty.is_signed();
+    let (signed_lhs, signed_rhs) = (sign_extend(lhs, bits), sign_extend(rhs, bits));
+    let value = match bin_op {
+        BinOp::Add => lhs.wrapping_add(rhs),
+        BinOp::Sub => lhs.wrapping_sub(rhs),
+        BinOp::Mul => lhs.wrapping_mul(rhs),
+        BinOp::Div | BinOp::Rem if rhs == 0 => {
+            return Err(ub!("{:?} by zero", bin_op));
+        }
+        BinOp::Div | BinOp::Rem if is_signed => {
+            if signed_lhs == sign_extend(1 << (bits - 1), bits) && signed_rhs == -1 {
+                return Err(ub!("{:?} of the minimum value by -1", bin_op));
+            }
+            if bin_op == BinOp::Div {
+                (signed_lhs / signed_rhs) as u128
+            } else {
+                (signed_lhs % signed_rhs) as u128
+            }
+        }
+        BinOp::Div => lhs / rhs,
+        BinOp::Rem => lhs % rhs,
+        BinOp::BitXor => lhs ^ rhs,
+        BinOp::BitAnd => lhs & rhs,
+        BinOp::BitOr => lhs | rhs,
+        BinOp::Shl | BinOp::Shr if rhs >= u128::from(bits) => {
+            return Err(ub!(
+                "{:?} by {}, which overflows {} bits",
+                bin_op,
+                rhs,
+                bits
+            ));
+        }
+        BinOp::Shl => lhs << rhs,
+        BinOp::Shr if is_signed => (signed_lhs >> rhs) as u128,
+        BinOp::Shr => lhs >> rhs,
+        BinOp::Eq | BinOp::Lt | BinOp::Le | BinOp::Ne | BinOp::Ge | BinOp::Gt => {
+            let ordering = if is_signed {
+                signed_lhs.cmp(&signed_rhs)
+            } else {
+                lhs.cmp(&rhs)
+            };
+            let result = match bin_op {
+                BinOp::Eq => ordering.is_eq(),
+                BinOp::Ne => ordering.is_ne(),
+                BinOp::Lt => ordering.is_lt(),
+                BinOp::Le => ordering.is_le(),
+                BinOp::Gt => ordering.is_gt(),
+                _ => ordering.is_ge(),
+            };
+            return Ok(u128::from(result));
+        }
+    };
+    Ok(truncate_bits(value, bits))
+}
+
+/// Evaluates a unary operation on a boolean, an integer or a float, whose type and
+/// layout is `layout`.
+fn eval_unop(un_op: UnOp, operand: u128, layout: &TyAndLayout<LirTy>) -> InterpResult<u128> {
+    let bits = int_bits(layout);
+    let value = match un_op {
+        UnOp::Not => !operand,
+        // The negation of a float flips its sign bit.
+        UnOp::Neg if layout.ty.is_floating_point() => operand ^ (1 << (bits - 1)),
+        UnOp::Neg => operand.wrapping_neg(),
+    };
+    Ok(truncate_bits(value, bits))
+}
+
+/// Evaluates a cast of a scalar whose type and layout is `layout` to the type of
+/// `cast_layout`. The transmutes are evaluated by the caller.
+///
+/// Only the `F32` and `F64` floats are supported, and the conversion of a float that
+/// does not fit in the target integer is undefined behavior (it is poison in LLVM).
+fn eval_cast(
+    cast_kind: CastKind,
+    operand: u128,
+    layout: &TyAndLayout<LirTy>,
+    cast_layout: &TyAndLayout<LirTy>,
+) -> InterpResult<u128> {
+    let (ty, cast_ty) = (&layout.ty, &cast_layout.ty);
+    let value = match cast_kind {
+        CastKind::IntToInt if ty.is_signed() => sign_extend(operand, int_bits(layout)) as u128,
+        // The integers and the pointers are zero-extended or truncated.
+        CastKind::IntToInt | CastKind::PtrToInt | CastKind::IntToPtr | CastKind::PtrToPtr => {
+            operand
+        }
+        CastKind::FloatToInt => {
+            let value = float_value(operand, ty)?.trunc();
+            let bits = int_bits(cast_layout);
+            let (min, max) = if cast_ty.is_signed() {
+                let bound = 2f64.powi(bits as i32 - 1);
+                (-bound, bound)
+            } else {
+                (0.0, 2f64.powi(bits as i32))
+            };
+            if value.is_nan() || value < min || value >= max {
+                return Err(ub!(
+                    "Conversion of {} to {:?}, which does not fit",
+                    value,
+                    cast_ty
+                ));
+            }
+            if cast_ty.is_signed() {
+                value as i128 as u128
+            } else {
+                value as u128
+            }
+        }
+        CastKind::IntToFloat => {
+            let signed = sign_extend(operand, int_bits(layout));
+            match (cast_ty, ty.is_signed()) {
+                (LirTy::F32, true) => u128::from((signed as f32).to_bits()),
+                (LirTy::F32, false) => u128::from((operand as f32).to_bits()),
+                (LirTy::F64, true) => u128::from((signed as f64).to_bits()),
+                (LirTy::F64, false) => u128::from((operand as f64).to_bits()),
+                _ => return Err(unsupported!("Cast to {:?}", cast_ty)),
+            }
+        }
+        CastKind::FloatToFloat => {
+            let value = float_value(operand, ty)?;
+            match cast_ty {
+                LirTy::F32 => u128::from((value as f32).to_bits()),
+                LirTy::F64 => u128::from(value.to_bits()),
+                _ => return Err(unsupported!("Cast to {:?}", cast_ty)),
+            }
+        }
+        CastKind::Transmute => unreachable!("Transmutes are evaluated by the caller"),
+    };
+    Ok(truncate_bits(value, int_bits(cast_layout)))
+}
+
+/// Evaluates an intrinsic on integers, whose type and layout is `layout`.
+///
+/// As in LLVM, `ctlz` and `cttz` of zero return the bit width.
+fn eval_int_intrinsic(
+    intrinsic: Intrinsic,
+    values: &[u128],
+    layout: &TyAndLayout<LirTy>,
+) -> InterpResult<u128> {
+    let bits = int_bits(layout);
+    let unused_bits = 128 - bits as u32;
+    let value = values[0];
+    let value = match intrinsic {
+        Intrinsic::Ctpop => u128::from(value.count_ones()),
+        Intrinsic::Ctlz => u128::from(value.leading_zeros() - unused_bits),
+        Intrinsic::Cttz => u128::from(value.trailing_zeros().min(bits as u32)),
+        Intrinsic::Bswap if !bits.is_multiple_of(16) => {
+            return Err(unsupported!("bswap of {:?}, of {} bits", layout.ty, bits));
+        }
+        Intrinsic::Bswap => value.swap_bytes() >> unused_bits,
+        Intrinsic::Bitreverse => value.reverse_bits() >> unused_bits,
+        Intrinsic::SaturatingAdd | Intrinsic::SaturatingSub if layout.ty.is_signed() => {
+            let (lhs, rhs) = (sign_extend(value, bits), sign_extend(values[1], bits));
+            let result = if intrinsic == Intrinsic::SaturatingAdd {
+                lhs.saturating_add(rhs)
+            } else {
+                lhs.saturating_sub(rhs)
+            };
+            let (min, max) = (i128::MIN >> unused_bits, i128::MAX >> unused_bits);
+            result.clamp(min, max) as u128
+        }
+        Intrinsic::SaturatingAdd => value
+            .saturating_add(values[1])
+            .min(u128::MAX >> unused_bits),
+        Intrinsic::SaturatingSub => value.saturating_sub(values[1]),
+        _ => unreachable!("The intrinsic {:?} is not on integers", intrinsic),
+    };
+    Ok(truncate_bits(value, bits))
+}
+
+/// Evaluates an intrinsic on the floats of type `ty`, with the given raw bits. Only
+/// the `F32` and `F64` floats are supported.
+fn eval_float_intrinsic(intrinsic: Intrinsic, values: &[u128], ty: &LirTy) -> InterpResult<u128> {
+    let value = match ty {
+        LirTy::F32 => {
+            let arg = |i: usize| f32::from_bits(values[i] as u32);
+            let result = match intrinsic {
+                Intrinsic::Sqrt => arg(0).sqrt(),
+                Intrinsic::Fabs => arg(0).abs(),
+                Intrinsic::Fma => arg(0).mul_add(arg(1), arg(2)),
+                _ => unreachable!("The intrinsic {:?} is not on floats", intrinsic),
+            };
+            u128::from(result.to_bits())
+        }
+        LirTy::F64 => {
+            let arg = |i: usize| f64::from_bits(values[i] as u64);
+            let result = match intrinsic {
+                Intrinsic::Sqrt => arg(0).sqrt(),
+                Intrinsic::Fabs => arg(0).abs(),
+                Intrinsic::Fma => arg(0).mul_add(arg(1), arg(2)),
+                _ => unreachable!("The intrinsic {:?} is not on floats", intrinsic),
+            };
+            u128::from(result.to_bits())
+        }
+        _ => return Err(unsupported!("Intrinsic {} on {:?}", intrinsic.name(), ty)),
+    };
+    Ok(value)
+}
+
+/// Returns the value of the float with the given raw bits, if its type is `F32`
+/// or `F64`. An `F32` is converted (exactly) to an `f64`.
+fn float_value(value: u128, ty: &LirTy) -> InterpResult<f64> {
+    match ty {
+        LirTy::F32 => Ok(f64::from(f32::from_bits(value as u32))),
+        LirTy::F64 => Ok(f64::from_bits(value as u64)),
+        _ => Err(unsupported!("Float of type {:?}", ty)),
+    }
+}
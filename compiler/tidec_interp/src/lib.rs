@@ -0,0 +1,10 @@
+//! An interpreter of the LIR.
+//!
+//! It evaluates the bodies directly, instead of generating code for them. It is the
+//! reference semantics of the LIR, against which the codegen backends can be tested,
+//! and the foundation of the evaluation of constants at compile time.
+
+pub mod entry;
+pub mod error;
+pub mod interpreter;
+pub mod memory;
@@ -0,0 +1,241 @@
+//! The memory of the interpreter.
+//!
+//! The memory is a flat address space, where every allocation (a local, a global or
+//! a function) is placed at its own address. A pointer is thus a plain integer, stored
+//! in memory with the size of the pointers of the target, and the integer-pointer
+//! casts are no-ops. An access is checked against the bounds of the allocation the
+//! address belongs to, so that out-of-bounds and dangling accesses are reported.
+
+use std::collections::{BTreeMap, HashMap};
+
+use tidec_abi::{
+    size_and_align::{Align, Size},
+    target::Endianess,
+};
+use tidec_lir::{
+    lir::{DefId, LirCtx},
+    syntax::AllocId,
+};
+
+use crate::{error::InterpResult, ub};
+
+/// The address of the first allocation. The addresses below it are never allocated,
+/// so that a null pointer (or a small offset from it) is always dangling.
+const FIRST_ADDRESS: u64 = 0x1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// What an allocation holds.
+pub enum AllocKind {
+    /// A local of a frame. It is deallocated when the frame returns.
+    Stack,
+    /// An allocation of the `LirCtx` (e.g., the bytes of a string constant).
+    Global,
+    /// The code of a function, which is the target of its function pointers. It
+    /// cannot be read nor written.
+    Function(DefId),
+}
+
+#[derive(Debug)]
+/// A block of memory, placed at some address.
+pub struct Allocation {
+    pub bytes: Vec<u8>,
+    pub align: Align,
+    pub mutable: bool,
+    pub kind: AllocKind,
+}
+
+#[derive(Debug)]
+/// The memory of the interpreter. See the module documentation.
+pub struct Memory {
+    /// The allocations, by their address.
+    allocs: BTreeMap<u64, Allocation>,
+    /// The address after the last allocation.
+    next_address: u64,
+    /// The address of each allocation of the `LirCtx` that has been accessed.
+    globals: HashMap<AllocId, u64>,
+    /// The address of each function whose address has been taken.
+    functions: HashMap<DefId, u64>,
+    /// The endianness of the target, used to encode the scalars.
+    endianess: Endianess,
+}
+
+impl Memory {
+    pub fn new(endianess: Endianess) -> Self {
+        Memory {
+            allocs: BTreeMap::new(),
+            next_address: FIRST_ADDRESS,
+            globals: HashMap::new(),
+            functions: HashMap::new(),
+            endianess,
+        }
+    }
+
+    /// Adds an allocation of the given size and alignment, initialized with zeros,
+    /// and returns its address.
+    pub fn allocate(&mut self, size: Size, align: Align, kind: AllocKind) -> u64 {
+        let address = Size::from_bytes(self.next_address).align_to(align).bytes();
+        // Even the zero-sized allocations take one byte of the address space, so
+        // that the addresses of the allocations are distinct.
+        self.next_address = address + size.bytes().max(1);
+        self.allocs.insert(
+            address,
+            Allocation {
+                bytes: vec![0; size.bytes() as usize],
+                align,
+                mutable: true,
+                kind,
+            },
+        );
+        address
+    }
+
+    /// Removes the allocation at the given address.
+    pub fn deallocate(&mut self, address: u64) -> InterpResult<()> {
+        match self.allocs.remove(&address) {
+            Some(_) => Ok(()),
+            None => Err(ub!("Deallocation of the dangling pointer {:#x}", address)),
+        }
+    }
+
+    /// Returns the address of the given allocation of the `LirCtx`, copying it to
+    /// the memory the first time it is accessed.
+    pub fn global_address(&mut self, lir_ctx: &LirCtx, alloc_id: AllocId) -> u64 {
+        if let Some(address) = self.globals.get(&alloc_id) {
+            return *address;
+        }
+        let alloc = lir_ctx.alloc(alloc_id);
+        let address = self.allocate(alloc.size(), alloc.align, AllocKind::Global);
+        let global = self.allocs.get_mut(&address).unwrap();
+        global.bytes.copy_from_slice(&alloc.bytes);
+        global.mutable = alloc.mutable;
+        self.globals.insert(alloc_id, address);
+        address
+    }
+
+    /// Returns the address of the given function, that is, the value of its
+    /// function pointers.
+    pub fn function_address(&mut self, def_id: DefId) -> u64 {
+        if let Some(address) = self.functions.get(&def_id) {
+            return *address;
+        }
+        let address = self.allocate(Size::from_bytes(0), Align::ONE, AllocKind::Function(def_id));
+        self.functions.insert(def_id, address);
+        address
+    }
+
+    /// Returns the function whose address is the given one.
+    pub fn function_at(&self, address: u64) -> InterpResult<DefId> {
+        match self.allocs.get(&address) {
+            Some(Allocation {
+                kind: AllocKind::Function(def_id),
+                ..
+            }) => Ok(*def_id),
+            _ => Err(ub!(
+                "Call of {:#x}, which is not a function pointer",
+                address
+            )),
+        }
+    }
+
+    /// Returns the allocation which contains the `size` bytes at the given address,
+    /// and the offset of the address in it.
+    fn alloc_at(&self, address: u64, size: Size) -> InterpResult<(&Allocation, usize)> {
+        let (base, alloc) = self
+            .allocs
+            .range(..=address)
+            .next_back()
+            .ok_or_else(|| ub!("Access to the dangling pointer {:#x}", address))?;
+        let offset = address - base;
+        if offset + size.bytes() > alloc.bytes.len() as u64 {
+            return Err(ub!(
+                "Out-of-bounds access of {} bytes at {:#x}, in an allocation of {} bytes at {:#x}",
+                size.bytes(),
+                address,
+                alloc.bytes.len(),
+                base
+            ));
+        }
+        if let AllocKind::Function(def_id) = alloc.kind {
+            return Err(ub!("Access to the code of the function {:?}", def_id));
+        }
+        Ok((alloc, offset as usize))
+    }
+
+    /// Returns the `size` bytes at the given address.
+    pub fn read(&self, address: u64, size: Size) -> InterpResult<&[u8]> {
+        if size.bytes() == 0 {
+            return Ok(&[]);
+        }
+        let (alloc, offset) = self.alloc_at(address, size)?;
+        Ok(&alloc.bytes[offset..offset + size.bytes() as usize])
+    }
+
+    /// Writes the given bytes at the given address.
+    pub fn write(&mut self, address: u64, bytes: &[u8]) -> InterpResult<()> {
+        if bytes.is_empty() {
+            return Ok(());
+        }
+        let (alloc, offset) = self.alloc_at(address, Size::from_bytes(bytes.len()))?;
+        if !alloc.mutable {
+            return Err(ub!("Write to the immutable memory at {:#x}", address));
+        }
+        let base = address - offset as u64;
+        let alloc = self.allocs.get_mut(&base).unwrap();
+        alloc.bytes[offset..offset + bytes.len()].copy_from_slice(bytes);
+        Ok(())
+    }
+
+    /// Copies `size` bytes from `src` to `dest`. The two ranges may overlap.
+    pub fn copy(&mut self, src: u64, dest: u64, size: Size) -> InterpResult<()> {
+        let bytes = self.read(src, size)?.to_vec();
+        self.write(dest, &bytes)
+    }
+
+    /// Returns the unsigned integer encoded by the given bytes, in the target endianness.
+    pub fn bytes_to_uint(&self, bytes: &[u8]) -> u128 {
+        assert!(bytes.len() <= 16, "A scalar has at most 16 bytes");
+        let mut buf = [0; 16];
+        match self.endianess {
+            Endianess::Little => {
+                buf[..bytes.len()].copy_from_slice(bytes);
+                u128::from_le_bytes(buf)
+            }
+            Endianess::Big => {
+                buf[16 - bytes.len()..].copy_from_slice(bytes);
+                u128::from_be_bytes(buf)
+            }
+        }
+    }
+
+    /// Returns the `size` bytes encoding the given unsigned integer (truncated to
+    /// `size`), in the target endianness.
+    pub fn uint_to_bytes(&self, value: u128, size: Size) -> Vec<u8> {
+        let size = size.bytes() as usize;
+        assert!(size <= 16, "A scalar has at most 16 bytes");
+        match self.endianess {
+            Endianess::Little => value.to_le_bytes()[..size].to_vec(),
+            Endianess::Big => value.to_be_bytes()[16 - size..].to_vec(),
+        }
+    }
+
+    /// Reads the unsigned integer of `size` bytes at the given address.
+    pub fn read_uint(&self, address: u64, size: Size) -> InterpResult<u128> {
+        Ok(self.bytes_to_uint(self.read(address, size)?))
+    }
+
+    /// Writes the unsigned integer (truncated to `size` bytes) at the given address.
+    pub fn write_uint(&mut self, address: u64, value: u128, size: Size) -> InterpResult<()> {
+        let bytes = self.uint_to_bytes(value, size);
+        self.write(address, &bytes)
+    }
+
+    /// Returns the number of live allocations.
+    pub fn len(&self) -> usize {
+        self.allocs.len()
+    }
+
+    /// Returns `true` if there are no live allocations.
+    pub fn is_empty(&self) -> bool {
+        self.allocs.is_empty()
+    }
+}
@@ -0,0 +1,115 @@
+//! Functions returning fat-pointer-like values (i.e., scalar pairs), run through the
+//! parser and the interpreter.
+
+use std::sync::Arc;
+
+use tidec_abi::target::{BackendKind, LirTarget};
+use tidec_interp::entry::interp_lir_unit;
+use tidec_lir::{lir::LirCtx, parse::parse_lir_unit};
+use tidec_session::{config::Options, diagnostics::DiagCtxt, Session};
+
+/// Parses the given unit and interprets its `main` function, returning its exit code.
+fn run(src: &str) -> i32 {
+    let sess = Session::new(
+        LirTarget::new(BackendKind::Interp),
+        Options::default(),
+        DiagCtxt::new(),
+    );
+    let mut lir_ctx = LirCtx::new(Arc::new(sess));
+    let lir_unit = parse_lir_unit(&mut lir_ctx, "test", src).expect("Invalid test unit");
+    interp_lir_unit(lir_ctx, lir_unit).expect("The interpretation failed")
+}
+
+#[test]
+fn return_str() {
+    let src = r#"
+        fn greeting() -> &str {
+            bb0: {
+                _0 = const "hello";
+                return;
+            }
+        }
+
+        fn main() -> u64 {
+            let _1: &str;
+
+            bb0: {
+                _1 = greeting() -> bb1;
+            }
+
+            bb1: {
+                _0 = copy _1.1;
+                return;
+            }
+        }
+    "#;
+    assert_eq!(run(src), 5);
+}
+
+#[test]
+fn return_ptr_len_pair_built_in_memory() {
+    // The return value is built field by field, so it lives in memory and it is
+    // loaded as a pair when it is returned.
+    let src = r#"
+        fn split(_1: &str) -> (*u8, u64) {
+            bb0: {
+                _0.0 = copy _1.0;
+                _0.1 = copy _1.1;
+                return;
+            }
+        }
+
+        fn main() -> u8 {
+            let _1: (*u8, u64);
+            let _2: *u8;
+
+            bb0: {
+                _1 = split(const "tide") -> bb1;
+            }
+
+            bb1: {
+                _2 = copy _1.0;
+                _0 = copy (*_2);
+                return;
+            }
+        }
+    "#;
+    assert_eq!(run(src), b't' as i32);
+}
+
+#[test]
+fn return_str_through_nested_calls() {
+    // The pair returned by the callee is returned again, as an operand.
+    let src = r#"
+        fn inner() -> &str {
+            bb0: {
+                _0 = const "nested";
+                return;
+            }
+        }
+
+        fn outer() -> &str {
+            bb0: {
+                _0 = inner() -> bb1;
+            }
+
+            bb1: {
+                return;
+            }
+        }
+
+        fn main() -> u64 {
+            let _1: &str;
+
+            bb0: {
+                _1 = outer() -> bb1;
+            }
+
+            bb1: {
+                _0 = copy _1.1;
+                return;
+            }
+        }
+    "#;
+    assert_eq!(run(src), 6);
+}
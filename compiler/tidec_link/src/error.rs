@@ -0,0 +1,50 @@
+use std::{fmt, io};
+
+use crate::linker::LinkerFlavor;
+
+#[derive(Debug)]
+/// An error raised while linking.
+pub enum LinkError {
+    /// No linker of the given flavor has been found in the `PATH`.
+    LinkerNotFound(LinkerFlavor),
+    /// The linker of the given flavor cannot link the runtime of the sanitizers.
+    SanitizersNotSupported(LinkerFlavor),
+    /// The linker could not be run.
+    Io(io::Error),
+    /// The linker failed. It holds the command line and the output of the linker.
+    LinkerFailed { command: String, output: String },
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkError::LinkerNotFound(flavor) => write!(
+                f,
+                "no {:?} linker found in the PATH (set `TIDEC_LINKER` to choose one)",
+                flavor
+            ),
+            LinkError::SanitizersNotSupported(flavor) => write!(
+                f,
+                "the runtime of the sanitizers cannot be linked by the {:?} linker",
+                flavor
+            ),
+            LinkError::Io(err) => write!(f, "could not run the linker: {}", err),
+            LinkError::LinkerFailed { command, output } => {
+                write!(
+                    f,
+                    "linking with `{}` failed:\n{}",
+                    command,
+                    output.trim_end()
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for LinkError {}
+
+impl From<io::Error> for LinkError {
+    fn from(err: io::Error) -> Self {
+        LinkError::Io(err)
+    }
+}
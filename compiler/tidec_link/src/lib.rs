@@ -0,0 +1,6 @@
+//! The linking of the objects emitted by the backends into an executable or a shared
+//! library (see `EmitKind::Link`), by running the system linker.
+// It corresponds to rustc_codegen_ssa/src/back/link.rs and rustc_codegen_ssa/src/back/linker.rs
+
+pub mod error;
+pub mod linker;
@@ -0,0 +1,228 @@
+//! The detection of the system linker, and its command line for each target.
+//!
+//! The objects are linked by a C compiler driver (e.g., `cc`), which also links the
+//! C runtime, or by the MSVC linker on the `msvc` targets. A target is described by its
+//! triple, or it is the host if the triple is unknown (see `LirTarget::triple_or_host`).
+
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use tidec_abi::target::{LinkOutputKind, RelocModel, TargetTriple};
+use tidec_session::config::SanitizerSet;
+use tracing::{debug, instrument};
+
+use crate::error::LinkError;
+
+/// The environment variable that overrides the linker program.
+pub const LINKER_ENV_VAR: &str = "TIDEC_LINKER";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The command-line conventions of a linker.
+// It corresponds to `LinkerFlavor` in rustc_target/src/spec/mod.rs
+pub enum LinkerFlavor {
+    /// A C compiler driving a GNU-like linker (e.g., `cc`, `clang` or `gcc`).
+    Gnu,
+    /// A C compiler driving the Apple linker (e.g., `cc` or `clang`).
+    Darwin,
+    /// The MSVC linker or a compatible one (e.g., `link` or `lld-link`).
+    Msvc,
+}
+
+impl LinkerFlavor {
+    /// Returns the flavor of the linker of the given target, or of the host if `None`.
+    pub fn of_target(triple: Option<&TargetTriple>) -> Self {
+        let env = triple.map_or(host_env(), |triple| triple.env.as_str());
+        if env == "msvc" {
+            LinkerFlavor::Msvc
+        } else if is_darwin(target_os(triple)) {
+            LinkerFlavor::Darwin
+        } else {
+            LinkerFlavor::Gnu
+        }
+    }
+
+    /// Returns the programs of this flavor, in order of preference.
+    fn candidates(self) -> &'static [&'static str] {
+        match self {
+            LinkerFlavor::Gnu => &["cc", "clang", "gcc"],
+            LinkerFlavor::Darwin => &["cc", "clang"],
+            LinkerFlavor::Msvc => &["link", "lld-link"],
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A linker program, and the conventions of its command line.
+pub struct Linker {
+    pub program: PathBuf,
+    pub flavor: LinkerFlavor,
+}
+
+impl Linker {
+    #[instrument(level = "debug")]
+    /// Returns the linker of the given target (or of the host if `None`). It is the
+    /// program of the `TIDEC_LINKER` env variable if set, or else the first program of
+    /// the flavor of the target found in the `PATH`.
+    pub fn detect(triple: Option<&TargetTriple>) -> Result<Self, LinkError> {
+        let flavor = LinkerFlavor::of_target(triple);
+        if let Some(program) = std::env::var_os(LINKER_ENV_VAR) {
+            return Ok(Linker {
+                program: program.into(),
+                flavor,
+            });
+        }
+        flavor
+            .candidates()
+            .iter()
+            .find_map(|program| find_in_path(program))
+            .map(|program| Linker { program, flavor })
+            .ok_or(LinkError::LinkerNotFound(flavor))
+    }
+
+    /// Returns the command which links the given objects into `output`. An executable
+    /// is position independent unless the objects have the static relocation model.
+    /// The runtime of the given sanitizers is linked by the C compiler driver, as the
+    /// MSVC linker is rejected by `Linker::link` if any.
+    pub fn command(
+        &self,
+        triple: Option<&TargetTriple>,
+        objects: &[PathBuf],
+        output: &Path,
+        kind: LinkOutputKind,
+        reloc_model: Option<RelocModel>,
+        sanitizers: SanitizerSet,
+    ) -> Command {
+        let mut cmd = Command::new(&self.program);
+        if self.flavor != LinkerFlavor::Msvc && !sanitizers.is_empty() {
+            let names: Vec<&str> = SanitizerSet::NAMES
+                .into_iter()
+                .filter(|(sanitizer, _)| sanitizers.contains(*sanitizer))
+                .map(|(_, name)| name)
+                .collect();
+            cmd.arg(format!("-fsanitize={}", names.join(",")));
+        }
+        match self.flavor {
+            LinkerFlavor::Gnu => {
+                match (kind, reloc_model) {
+                    (LinkOutputKind::SharedLibrary, _) => {
+                        cmd.arg("-shared");
+                    }
+                    (LinkOutputKind::Executable, Some(RelocModel::Static)) => {
+                        cmd.arg("-no-pie");
+                    }
+                    (LinkOutputKind::Executable, _) => {}
+                }
+                cmd.args(objects).arg("-o").arg(output);
+            }
+            LinkerFlavor::Darwin => {
+                if let Some(triple) = triple {
+                    cmd.arg("-arch").arg(darwin_arch(&triple.arch));
+                }
+                if kind == LinkOutputKind::SharedLibrary {
+                    cmd.arg("-dynamiclib");
+                }
+                cmd.args(objects).arg("-o").arg(output);
+            }
+            LinkerFlavor::Msvc => {
+                cmd.arg("/NOLOGO");
+                match kind {
+                    LinkOutputKind::Executable => {
+                        // The C runtime, which calls `main`.
+                        cmd.arg("/DEFAULTLIB:libcmt");
+                    }
+                    LinkOutputKind::SharedLibrary => {
+                        cmd.arg("/DLL");
+                    }
+                }
+                let mut out_arg = OsString::from("/OUT:");
+                out_arg.push(output);
+                cmd.args(objects).arg(out_arg);
+            }
+        }
+        cmd
+    }
+
+    #[instrument(level = "info", skip(self, triple, objects))]
+    /// Links the given objects into `output`, which is a file of the given kind, with
+    /// the runtime of the given sanitizers.
+    pub fn link(
+        &self,
+        triple: Option<&TargetTriple>,
+        objects: &[PathBuf],
+        output: &Path,
+        kind: LinkOutputKind,
+        reloc_model: Option<RelocModel>,
+        sanitizers: SanitizerSet,
+    ) -> Result<(), LinkError> {
+        if self.flavor == LinkerFlavor::Msvc && !sanitizers.is_empty() {
+            return Err(LinkError::SanitizersNotSupported(self.flavor));
+        }
+        let mut cmd = self.command(triple, objects, output, kind, reloc_model, sanitizers);
+        debug!("Running the linker: {:?}", cmd);
+        let cmd_output = cmd.output()?;
+        if !cmd_output.status.success() {
+            let mut output = String::from_utf8_lossy(&cmd_output.stderr).into_owned();
+            output.push_str(&String::from_utf8_lossy(&cmd_output.stdout));
+            return Err(LinkError::LinkerFailed {
+                command: format!("{:?}", cmd),
+                output,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Returns the name of the file of the given kind produced by linking the unit
+/// `name`, for the given target (or the host if `None`).
+pub fn output_filename(name: &str, kind: LinkOutputKind, triple: Option<&TargetTriple>) -> String {
+    let os = target_os(triple);
+    match kind {
+        LinkOutputKind::Executable if os == "windows" => format!("{}.exe", name),
+        LinkOutputKind::Executable => name.to_string(),
+        LinkOutputKind::SharedLibrary if os == "windows" => format!("{}.dll", name),
+        LinkOutputKind::SharedLibrary if is_darwin(os) => format!("lib{}.dylib", name),
+        LinkOutputKind::SharedLibrary => format!("lib{}.so", name),
+    }
+}
+
+/// Returns the operating system of the given target, or of the host if `None`.
+fn target_os(triple: Option<&TargetTriple>) -> &str {
+    triple.map_or(std::env::consts::OS, |triple| triple.os.as_str())
+}
+
+/// Returns the environment of the host (e.g., `msvc`).
+fn host_env() -> &'static str {
+    if cfg!(target_env = "msvc") {
+        "msvc"
+    } else {
+        ""
+    }
+}
+
+fn is_darwin(os: &str) -> bool {
+    matches!(os, "darwin" | "macos" | "ios")
+}
+
+/// Returns the name of the given architecture for the `-arch` flag of the Apple tools.
+fn darwin_arch(arch: &str) -> &str {
+    match arch {
+        "aarch64" => "arm64",
+        arch => arch,
+    }
+}
+
+/// Returns the path of the given program in the `PATH`, if any.
+fn find_in_path(program: &str) -> Option<PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths).find_map(|dir| {
+        [
+            dir.join(program),
+            dir.join(format!("{}{}", program, std::env::consts::EXE_SUFFIX)),
+        ]
+        .into_iter()
+        .find(|path| path.is_file())
+    })
+}
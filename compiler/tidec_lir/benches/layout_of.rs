@@ -0,0 +1,107 @@
+//! Benchmarks of the layout queries on a body with thousands of locals.
+//!
+//! Run them with `cargo bench -p tidec_lir`. The `uncached` benchmark computes the
+//! layout of each local again, as `layout_of` did before it was cached (only the
+//! layouts of the fields are cached), while the `cold` and `warm` ones query
+//! `LirCtx::layout_of` on an empty and on a filled cache, respectively.
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use std::sync::Arc;
+
+use tidec_abi::target::{BackendKind, LirTarget};
+use tidec_lir::{layout_ctx::LayoutCtx, lir::LirCtx, parse::parse_lir_unit, syntax::LirTy};
+use tidec_session::{config::Options, diagnostics::DiagCtxt, Session};
+
+/// The number of locals of the benchmarked body.
+const LOCALS: usize = 5000;
+
+/// The types of the locals, which are repeated in order.
+const LOCAL_TYS: &[&str] = &[
+    "i32",
+    "(i32, u8)",
+    "Point",
+    "Shape",
+    "[Point; 4]",
+    "(Shape, *u8, [i64; 8])",
+    "&[u16]",
+    "Option",
+];
+
+/// Parses a unit with a body of `LOCALS` locals, and returns its context and the
+/// types of the locals.
+fn parse_body() -> (LirCtx, Vec<LirTy>) {
+    let mut src = String::from(
+        "struct Point { i32, i32 }\n\
+         enum Shape { Empty, Dot(Point), Line(Point, Point) }\n\
+         enum Option { None, Some(fn(i32) -> i32) }\n\
+         fn main() {\n",
+    );
+    for i in 0..LOCALS {
+        src.push_str(&format!(
+            "    let _{}: {};\n",
+            i + 1,
+            LOCAL_TYS[i % LOCAL_TYS.len()]
+        ));
+    }
+    src.push_str("    bb0: {\n        return;\n    }\n}\n");
+
+    let sess = Session::new(
+        LirTarget::new(BackendKind::Llvm),
+        Options::default(),
+        DiagCtxt::new(),
+    );
+    let mut lir_ctx = LirCtx::new(Arc::new(sess));
+    let lir_unit = parse_lir_unit(&mut lir_ctx, "bench", &src).expect("Invalid benchmark unit");
+    let local_tys = lir_unit.bodies.raw[0]
+        .locals
+        .iter()
+        .map(|local_data| local_data.ty.clone())
+        .collect();
+    (lir_ctx, local_tys)
+}
+
+fn bench_layout_of(c: &mut Criterion) {
+    let mut group = c.benchmark_group(format!("layout_of_{}_locals", LOCALS));
+
+    group.bench_function("uncached", |b| {
+        b.iter_batched(
+            parse_body,
+            |(lir_ctx, local_tys)| {
+                let layout_ctx = LayoutCtx::new(&lir_ctx);
+                for ty in &local_tys {
+                    black_box(layout_ctx.compute_layout(ty));
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.bench_function("cold", |b| {
+        b.iter_batched(
+            parse_body,
+            |(lir_ctx, local_tys)| {
+                for ty in local_tys {
+                    black_box(lir_ctx.layout_of(ty).size);
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    let (lir_ctx, local_tys) = parse_body();
+    for ty in &local_tys {
+        lir_ctx.layout_of(ty.clone());
+    }
+    group.bench_function("warm", |b| {
+        b.iter(|| {
+            for ty in &local_tys {
+                black_box(lir_ctx.layout_of(ty.clone()).size);
+            }
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_layout_of);
+criterion_main!(benches);
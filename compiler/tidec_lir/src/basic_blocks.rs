@@ -1,12 +1,18 @@
-use tidec_utils::idx::Idx;
+use tidec_utils::newtype_index;
 
 use crate::syntax::{Statement, Terminator};
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
-pub struct BasicBlock(usize);
-pub const ENTRY_BLOCK: BasicBlock = BasicBlock(0);
+newtype_index! {
+    /// The index of a basic block in a body.
+    pub struct BasicBlock {
+        debug_format = "bb{}";
+        niche;
+        const ENTRY_BLOCK = 0;
+    }
+}
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// The data of a basic block.
 ///
 /// A basic block is a sequence of statements that ends with a terminator.
@@ -14,24 +20,50 @@ pub const ENTRY_BLOCK: BasicBlock = BasicBlock(0);
 pub struct BasicBlockData {
     pub statements: Vec<Statement>,
     pub terminator: Terminator,
+    /// Whether the block is reached by the normal control flow or while unwinding.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub kind: BasicBlockKind,
 }
 
-////////// Trait implementations  //////////
-
-impl Idx for BasicBlock {
-    fn new(idx: usize) -> Self {
-        BasicBlock(idx)
+impl BasicBlockData {
+    /// Returns `true` if the block is only reached while unwinding.
+    pub fn is_cleanup(&self) -> bool {
+        self.kind == BasicBlockKind::Cleanup
     }
 
-    fn idx(&self) -> usize {
-        self.0
+    /// Returns the location of the terminator of the block, which is `bb`.
+    pub fn terminator_location(&self, bb: BasicBlock) -> Location {
+        Location {
+            block: bb,
+            statement_index: self.statements.len(),
+        }
     }
+}
 
-    fn incr(&mut self) {
-        self.0 += 1;
-    }
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// The location of a statement in a body, or of the terminator of a block if
+/// `statement_index` is the number of the statements of the block.
+// It corresponds to `Location` in rustc_middle/src/mir/mod.rs
+pub struct Location {
+    pub block: BasicBlock,
+    pub statement_index: usize,
+}
 
-    fn incr_by(&mut self, by: usize) {
-        self.0 += by;
-    }
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The kind of a basic block.
+///
+/// The cleanup blocks run while unwinding (e.g., to drop the locals of a function
+/// whose callee panicked), and end with a `resume` to continue unwinding to the
+/// caller. They are only reached by the unwind edges of the `Call` and `Assert`
+/// terminators, or by the other cleanup blocks.
+// It corresponds to `is_cleanup` of `BasicBlockData` in rustc_middle/src/mir/mod.rs
+pub enum BasicBlockKind {
+    /// A block of the normal control flow.
+    #[default]
+    Regular,
+    /// A block that runs while unwinding.
+    Cleanup,
 }
+
+////////// Trait implementations  //////////
@@ -0,0 +1,211 @@
+//! Analyses of the control-flow graph (CFG) of a body, whose nodes are the basic blocks
+//! and whose edges are given by the successors of the terminators.
+
+use tidec_utils::index_vec::IdxVec;
+
+use crate::basic_blocks::{BasicBlock, BasicBlockData, ENTRY_BLOCK};
+
+/// Returns, for each basic block, the blocks that can jump to it.
+///
+/// A block appears once for each edge to the successor (e.g., twice if it is both
+/// the target and the cleanup block of an `Assert`).
+pub fn predecessors(
+    basic_blocks: &IdxVec<BasicBlock, BasicBlockData>,
+) -> IdxVec<BasicBlock, Vec<BasicBlock>> {
+    let mut predecessors = IdxVec::from_elem_n(vec![], basic_blocks.len());
+    for (bb, bb_data) in basic_blocks.iter_enumerated() {
+        for successor in bb_data.terminator.successors() {
+            predecessors[successor].push(bb);
+        }
+    }
+    predecessors
+}
+
+/// Returns the basic blocks reachable from the entry block, in reverse postorder.
+///
+/// In reverse postorder, a block comes before its successors, unless the edge is a back
+/// edge (i.e., a loop). Hence, a block always comes after the blocks dominating it.
+// It corresponds to `reverse_postorder` in rustc_middle/src/mir/traversal.rs
+pub fn reverse_postorder(basic_blocks: &IdxVec<BasicBlock, BasicBlockData>) -> Vec<BasicBlock> {
+    if basic_blocks.is_empty() {
+        return vec![];
+    }
+
+    let mut visited = IdxVec::from_elem_n(false, basic_blocks.len());
+    let mut postorder = Vec::with_capacity(basic_blocks.len());
+    // A depth-first search, where each block on the stack keeps the successors
+    // still to visit.
+    visited[ENTRY_BLOCK] = true;
+    let mut stack = vec![(
+        ENTRY_BLOCK,
+        basic_blocks[ENTRY_BLOCK].terminator.successors(),
+    )];
+    while let Some((bb, successors)) = stack.last_mut() {
+        match successors.next() {
+            Some(successor) if !visited[successor] => {
+                visited[successor] = true;
+                stack.push((successor, basic_blocks[successor].terminator.successors()));
+            }
+            Some(_) => {}
+            None => {
+                postorder.push(*bb);
+                stack.pop();
+            }
+        }
+    }
+
+    postorder.reverse();
+    postorder
+}
+
+#[derive(Debug)]
+/// The dominator tree of a CFG.
+///
+/// A block `a` dominates a block `b` if every path from the entry block to `b` goes
+/// through `a`. The immediate dominator of `b` is its closest strict dominator.
+/// The blocks unreachable from the entry block have no dominators.
+// It corresponds to `Dominators` in rustc_data_structures/src/graph/dominators/mod.rs
+pub struct Dominators {
+    /// The immediate dominator of each reachable block. The entry block is its own
+    /// immediate dominator.
+    immediate_dominators: IdxVec<BasicBlock, Option<BasicBlock>>,
+    /// The index of each reachable block in reverse postorder.
+    rpo_numbers: IdxVec<BasicBlock, Option<usize>>,
+}
+
+impl Dominators {
+    /// Computes the dominator tree of the given basic blocks.
+    ///
+    /// It uses the iterative algorithm of Cooper, Harvey and Kennedy, described in
+    /// "A Simple, Fast Dominance Algorithm".
+    pub fn compute(basic_blocks: &IdxVec<BasicBlock, BasicBlockData>) -> Self {
+        let rpo = reverse_postorder(basic_blocks);
+        let predecessors = predecessors(basic_blocks);
+
+        let mut rpo_numbers = IdxVec::from_elem_n(None, basic_blocks.len());
+        for (rpo_number, bb) in rpo.iter().enumerate() {
+            rpo_numbers[*bb] = Some(rpo_number);
+        }
+
+        let mut immediate_dominators = IdxVec::from_elem_n(None, basic_blocks.len());
+        if let Some(entry) = rpo.first() {
+            immediate_dominators[*entry] = Some(*entry);
+        }
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for bb in rpo.iter().skip(1) {
+                // Only the predecessors processed so far (i.e., with a dominator) are
+                // considered. The unreachable ones are never processed.
+                let new_idom = predecessors[*bb]
+                    .iter()
+                    .copied()
+                    .filter(|pred| immediate_dominators[*pred].is_some())
+                    .reduce(|a, b| intersect(&immediate_dominators, &rpo_numbers, a, b));
+                if new_idom.is_some() && immediate_dominators[*bb] != new_idom {
+                    immediate_dominators[*bb] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+
+        Dominators {
+            immediate_dominators,
+            rpo_numbers,
+        }
+    }
+
+    /// Returns `true` if the block is reachable from the entry block.
+    pub fn is_reachable(&self, bb: BasicBlock) -> bool {
+        self.rpo_numbers[bb].is_some()
+    }
+
+    /// Returns the immediate dominator of the given block, or `None` if it is
+    /// the entry block or it is unreachable.
+    pub fn immediate_dominator(&self, bb: BasicBlock) -> Option<BasicBlock> {
+        self.immediate_dominators[bb].filter(|idom| *idom != bb)
+    }
+
+    /// Returns `true` if `a` dominates `b`. Every reachable block dominates itself.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `b` is unreachable.
+    pub fn dominates(&self, a: BasicBlock, b: BasicBlock) -> bool {
+        assert!(self.is_reachable(b), "{:?} is unreachable", b);
+        let mut bb = b;
+        loop {
+            if bb == a {
+                return true;
+            }
+            match self.immediate_dominator(bb) {
+                Some(idom) => bb = idom,
+                None => return false,
+            }
+        }
+    }
+
+    /// Returns the dominance frontier of each block, i.e., the reachable blocks that
+    /// it does not strictly dominate but with a predecessor that it dominates. They are
+    /// where the values defined in the block merge with the ones from the other paths
+    /// (e.g., with the phis of the SSA form).
+    ///
+    /// It uses the algorithm of Cooper, Harvey and Kennedy, described in "A Simple,
+    /// Fast Dominance Algorithm".
+    pub fn dominance_frontiers(
+        &self,
+        predecessors: &IdxVec<BasicBlock, Vec<BasicBlock>>,
+    ) -> IdxVec<BasicBlock, Vec<BasicBlock>> {
+        let mut frontiers = IdxVec::from_elem_n(vec![], predecessors.len());
+        for (bb, bb_predecessors) in predecessors.iter_enumerated() {
+            let Some(idom) = self.immediate_dominators[bb] else {
+                continue;
+            };
+            // The entry block is also entered by the function entry, so it is a join as
+            // soon as it has a predecessor (i.e., it is the header of a loop).
+            let is_entry = idom == bb;
+            if bb_predecessors.len() < 2 && !is_entry {
+                continue;
+            }
+            for pred in bb_predecessors {
+                if !self.is_reachable(*pred) {
+                    continue;
+                }
+                // The blocks from the predecessor up to the immediate dominator (which
+                // is included only for the entry block) dominate a predecessor of `bb`,
+                // but not `bb`.
+                let mut runner = *pred;
+                while runner != idom || is_entry {
+                    let frontier: &mut Vec<BasicBlock> = &mut frontiers[runner];
+                    if !frontier.contains(&bb) {
+                        frontier.push(bb);
+                    }
+                    if runner == idom {
+                        break;
+                    }
+                    runner = self.immediate_dominators[runner].unwrap();
+                }
+            }
+        }
+        frontiers
+    }
+}
+
+/// Returns the closest common dominator of the blocks `a` and `b`, walking up the
+/// (partial) dominator tree.
+fn intersect(
+    immediate_dominators: &IdxVec<BasicBlock, Option<BasicBlock>>,
+    rpo_numbers: &IdxVec<BasicBlock, Option<usize>>,
+    mut a: BasicBlock,
+    mut b: BasicBlock,
+) -> BasicBlock {
+    while a != b {
+        while rpo_numbers[a] > rpo_numbers[b] {
+            a = immediate_dominators[a].unwrap();
+        }
+        while rpo_numbers[b] > rpo_numbers[a] {
+            b = immediate_dominators[b].unwrap();
+        }
+    }
+    a
+}
@@ -0,0 +1,116 @@
+use tidec_utils::{bit_set::IdxBitSet, idx::Idx};
+
+use crate::{
+    basic_blocks::Location,
+    lir::LirBody,
+    syntax::{Local, Place, Projection, Statement, Terminator},
+    visit::{self, NonMutatingUseContext, PlaceContext, Visitor},
+};
+
+use super::{Analysis, Direction, GenKill};
+
+/// The locals that may be (maybe partially) initialized at a location, on some path.
+///
+/// The arguments are initialized on entry. A local is initialized when it (or a part of
+/// it, but not through a `Deref`) is written, and uninitialized when it is moved out
+/// as a whole (e.g., `move _1`). A dropped local is still initialized (see
+/// `TerminatorKind::Drop`).
+// It corresponds to `MaybeInitializedPlaces` in rustc_mir_dataflow/src/impls/initialized.rs
+pub struct MaybeInitializedLocals;
+
+impl Analysis for MaybeInitializedLocals {
+    type Domain = IdxBitSet<Local>;
+
+    const NAME: &'static str = "maybe_initialized";
+
+    const DIRECTION: Direction = Direction::Forward;
+
+    fn bottom_value(&self, lir_body: &LirBody) -> Self::Domain {
+        IdxBitSet::new_empty(lir_body.ret_and_args.len() + lir_body.locals.len())
+    }
+
+    fn initialize_start_block(&self, lir_body: &LirBody, state: &mut Self::Domain) {
+        // The first local is the return local, which is written by the body.
+        state.gen_all((1..lir_body.ret_and_args.len()).map(Local::new));
+    }
+
+    fn apply_statement_effect(
+        &self,
+        state: &mut Self::Domain,
+        statement: &Statement,
+        location: Location,
+    ) {
+        let mut transfer = TransferFunction::default();
+        transfer.visit_statement(statement, location);
+        transfer.apply(state);
+    }
+
+    fn apply_terminator_effect(
+        &self,
+        state: &mut Self::Domain,
+        terminator: &Terminator,
+        location: Location,
+    ) {
+        let mut transfer = TransferFunction::default();
+        transfer.visit_terminator(terminator, location);
+        transfer.apply(state);
+    }
+
+    fn apply_call_return_effect(&self, state: &mut Self::Domain, destination: &Place) {
+        if let Some(local) = initialized_local(destination) {
+            state.gen_(local);
+        }
+    }
+}
+
+/// Returns the local that is (partially) initialized by a write to the given place, if
+/// any. A write through a pointer does not initialize the local of the pointer.
+fn initialized_local(place: &Place) -> Option<Local> {
+    if place
+        .projection
+        .iter()
+        .any(|projection| matches!(projection, Projection::Deref))
+    {
+        None
+    } else {
+        Some(place.local)
+    }
+}
+
+#[derive(Default)]
+/// The locals initialized and moved out by a statement or a terminator.
+struct TransferFunction {
+    inits: Vec<Local>,
+    moves: Vec<Local>,
+}
+
+impl TransferFunction {
+    /// Applies the effect of the statement on the initialized locals: as the operands
+    /// are moved before the places are written, the moves are killed before the
+    /// initializations are generated.
+    fn apply(self, state: &mut IdxBitSet<Local>) {
+        state.kill_all(self.moves);
+        state.gen_all(self.inits);
+    }
+}
+
+impl Visitor for TransferFunction {
+    fn visit_place(&mut self, place: &Place, context: PlaceContext, location: Location) {
+        match context {
+            PlaceContext::NonMutatingUse(NonMutatingUseContext::Move) => {
+                if let Some(local) = place.try_local() {
+                    self.moves.push(local);
+                }
+            }
+            // The destination of a call is initialized on the return edge only.
+            PlaceContext::MutatingUse(visit::MutatingUseContext::Call) => {}
+            PlaceContext::MutatingUse(
+                visit::MutatingUseContext::Store
+                | visit::MutatingUseContext::AsmOutput
+                | visit::MutatingUseContext::SetDiscriminant,
+            ) => self.inits.extend(initialized_local(place)),
+            _ => {}
+        }
+        visit::super_place(self, place, context, location);
+    }
+}
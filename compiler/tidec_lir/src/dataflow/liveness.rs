@@ -0,0 +1,86 @@
+use tidec_utils::bit_set::IdxBitSet;
+
+use crate::{
+    basic_blocks::Location,
+    lir::LirBody,
+    syntax::{Local, Place, Statement, Terminator},
+    visit::{MutatingUseContext, PlaceContext, Visitor},
+};
+
+use super::{Analysis, Direction, GenKill};
+
+/// The locals that may be live at a location, i.e., whose current value may be read
+/// later, on some path.
+///
+/// A local is defined (killed) when it is overwritten as a whole (e.g., `_1 = ...`),
+/// and used (generated) when it is read, its address is taken, or only a part of it
+/// is written (e.g., `_1.0 = ...`). The return local is used by `return`.
+// It corresponds to `MaybeLiveLocals` in rustc_mir_dataflow/src/impls/liveness.rs
+pub struct MaybeLiveLocals;
+
+impl Analysis for MaybeLiveLocals {
+    type Domain = IdxBitSet<Local>;
+
+    const NAME: &'static str = "liveness";
+
+    const DIRECTION: Direction = Direction::Backward;
+
+    fn bottom_value(&self, lir_body: &LirBody) -> Self::Domain {
+        IdxBitSet::new_empty(lir_body.ret_and_args.len() + lir_body.locals.len())
+    }
+
+    fn apply_statement_effect(
+        &self,
+        state: &mut Self::Domain,
+        statement: &Statement,
+        location: Location,
+    ) {
+        let mut def_use = DefUse::default();
+        def_use.visit_statement(statement, location);
+        def_use.apply(state);
+    }
+
+    fn apply_terminator_effect(
+        &self,
+        state: &mut Self::Domain,
+        terminator: &Terminator,
+        location: Location,
+    ) {
+        let mut def_use = DefUse::default();
+        def_use.visit_terminator(terminator, location);
+        def_use.apply(state);
+    }
+
+    fn apply_call_return_effect(&self, state: &mut Self::Domain, destination: &Place) {
+        if let Some(local) = destination.try_local() {
+            state.kill(local);
+        }
+    }
+}
+
+#[derive(Default)]
+/// The locals defined and used by a statement or a terminator.
+struct DefUse {
+    defs: Vec<Local>,
+    uses: Vec<Local>,
+}
+
+impl DefUse {
+    /// Applies the effect of the statement on the live locals: as the uses are read
+    /// before the definitions are written, they are generated after the kills.
+    fn apply(self, state: &mut IdxBitSet<Local>) {
+        state.kill_all(self.defs);
+        state.gen_all(self.uses);
+    }
+}
+
+impl Visitor for DefUse {
+    fn visit_local(&mut self, local: Local, context: PlaceContext, _location: Location) {
+        match context {
+            // The destination of a call is defined on the return edge only.
+            PlaceContext::MutatingUse(MutatingUseContext::Call) => {}
+            context if context.is_place_assignment() => self.defs.push(local),
+            _ => self.uses.push(local),
+        }
+    }
+}
@@ -0,0 +1,394 @@
+//! A framework for the dataflow analyses of the LIR bodies, with the analyses built on it.
+//!
+//! An analysis (see [`Analysis`]) tells how each statement and terminator changes its
+//! state, a value of a join-semilattice (see [`JoinSemiLattice`]). The engine (see
+//! [`iterate_to_fixpoint`]) propagates the states through the CFG, forward or backward,
+//! joining them where the control flow merges, until they do not change anymore.
+//! Then, the state at any location can be queried from the [`Results`].
+//!
+//! The analyses whose state is a set (e.g., of locals) usually only add (gen) and
+//! remove (kill) elements, through the [`GenKill`] trait.
+//!
+//! ```
+//! use std::sync::Arc;
+//!
+//! use tidec_abi::target::{BackendKind, LirTarget};
+//! use tidec_lir::{
+//!     basic_blocks::{Location, ENTRY_BLOCK},
+//!     dataflow::{iterate_to_fixpoint, MaybeLiveLocals},
+//!     lir::LirCtx,
+//!     parse::parse_lir_unit,
+//!     syntax::Local,
+//! };
+//! use tidec_session::{config::Options, diagnostics::DiagCtxt, Session};
+//!
+//! let sess = Session::new(LirTarget::new(BackendKind::Llvm), Options::default(), DiagCtxt::new());
+//! let mut lir_ctx = LirCtx::new(Arc::new(sess));
+//! let src = "fn double(_1: i32) -> i32 {
+//!     let _2: i32;
+//!
+//!     bb0: {
+//!         _2 = Add(copy _1, copy _1);
+//!         _0 = copy _2;
+//!         return;
+//!     }
+//! }";
+//! let lir_unit = parse_lir_unit(&mut lir_ctx, "double", src).unwrap();
+//! let lir_body = &lir_unit.bodies.raw[0];
+//!
+//! let liveness = iterate_to_fixpoint(MaybeLiveLocals, lir_body);
+//! let location = |statement_index| Location { block: ENTRY_BLOCK, statement_index };
+//! let live = |statement_index| liveness.state_before(lir_body, location(statement_index));
+//! assert_eq!(live(0).iter().collect::<Vec<_>>(), [Local::from_u32(1)]);
+//! assert_eq!(live(1).iter().collect::<Vec<_>>(), [Local::from_u32(2)]);
+//! assert_eq!(live(2).iter().collect::<Vec<_>>(), [Local::from_u32(0)]);
+//! ```
+// It corresponds to rustc_mir_dataflow/src/framework/mod.rs
+
+mod initialized;
+mod liveness;
+
+pub use initialized::MaybeInitializedLocals;
+pub use liveness::MaybeLiveLocals;
+
+use std::collections::VecDeque;
+
+use tidec_utils::{bit_set::IdxBitSet, idx::Idx, index_vec::IdxVec};
+use tracing::{debug, instrument};
+
+use crate::{
+    basic_blocks::{BasicBlock, BasicBlockData, Location, ENTRY_BLOCK},
+    cfg,
+    lir::LirBody,
+    syntax::{Place, Statement, Terminator, TerminatorKind},
+};
+
+/// A lattice where any two elements have a least upper bound (their join).
+// It corresponds to `JoinSemiLattice` in rustc_mir_dataflow/src/framework/lattice.rs
+pub trait JoinSemiLattice: Eq {
+    /// Replaces `self` with its join with `other`. Returns `true` if `self` changed.
+    fn join(&mut self, other: &Self) -> bool;
+}
+
+/// The sets are joined by their union, so that they hold what holds on *some* path
+/// (e.g., the locals that *may* be live).
+impl<I: Idx> JoinSemiLattice for IdxBitSet<I> {
+    fn join(&mut self, other: &Self) -> bool {
+        self.union(other)
+    }
+}
+
+/// A set whose elements are added (gen) or removed (kill) by the statements.
+// It corresponds to `GenKill` in rustc_mir_dataflow/src/framework/mod.rs
+pub trait GenKill<T> {
+    fn gen_(&mut self, elem: T);
+
+    fn kill(&mut self, elem: T);
+
+    fn gen_all(&mut self, elems: impl IntoIterator<Item = T>) {
+        for elem in elems {
+            self.gen_(elem);
+        }
+    }
+
+    fn kill_all(&mut self, elems: impl IntoIterator<Item = T>) {
+        for elem in elems {
+            self.kill(elem);
+        }
+    }
+}
+
+impl<I: Idx> GenKill<I> for IdxBitSet<I> {
+    fn gen_(&mut self, elem: I) {
+        self.insert(elem);
+    }
+
+    fn kill(&mut self, elem: I) {
+        self.remove(elem);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The direction in which the states flow through the CFG.
+pub enum Direction {
+    /// From the entry block to the successors, e.g., for the initialized locals.
+    Forward,
+    /// From the returning blocks to the predecessors, e.g., for the live locals.
+    Backward,
+}
+
+/// A dataflow analysis, as described in the module documentation.
+// It corresponds to `Analysis` in rustc_mir_dataflow/src/framework/mod.rs
+pub trait Analysis {
+    /// The state of the analysis at a location.
+    type Domain: Clone + JoinSemiLattice;
+
+    /// The name of the analysis, used for logging.
+    const NAME: &'static str;
+
+    const DIRECTION: Direction;
+
+    /// Returns the initial state of every block, which is the bottom of the lattice
+    /// (e.g., the empty set).
+    fn bottom_value(&self, lir_body: &LirBody) -> Self::Domain;
+
+    /// Sets the state at the start of the entry block (e.g., the arguments are
+    /// initialized). It is only called for the forward analyses.
+    fn initialize_start_block(&self, _lir_body: &LirBody, _state: &mut Self::Domain) {}
+
+    /// Applies the effect of the given statement on the state.
+    fn apply_statement_effect(
+        &self,
+        state: &mut Self::Domain,
+        statement: &Statement,
+        location: Location,
+    );
+
+    /// Applies the effect of the given terminator on the state. The write of the
+    /// destination of a `Call` is not part of it (see `apply_call_return_effect`).
+    fn apply_terminator_effect(
+        &self,
+        state: &mut Self::Domain,
+        terminator: &Terminator,
+        location: Location,
+    );
+
+    /// Applies the effect of a `Call` returning into the given destination on the
+    /// state. It only holds on the edge to the `target` of the call, as the
+    /// destination is not written when the callee unwinds.
+    fn apply_call_return_effect(&self, _state: &mut Self::Domain, _destination: &Place) {}
+}
+
+/// The results of an analysis on a body: the state at the start of each block, in the
+/// direction of the analysis (i.e., at the end of the block for a backward analysis).
+// It corresponds to `Results` in rustc_mir_dataflow/src/framework/results.rs
+pub struct Results<A: Analysis> {
+    pub analysis: A,
+    entry_sets: IdxVec<BasicBlock, A::Domain>,
+}
+
+#[instrument(level = "debug", skip_all, fields(analysis = A::NAME, body = %lir_body.metadata.name))]
+/// Runs the given analysis on the body until its states reach a fixpoint.
+///
+/// The blocks are visited in reverse postorder for a forward analysis (and in
+/// postorder for a backward one), so that the states of a loop-free body are
+/// computed in a single visit of each block.
+// It corresponds to `iterate_to_fixpoint` in rustc_mir_dataflow/src/framework/mod.rs
+pub fn iterate_to_fixpoint<A: Analysis>(analysis: A, lir_body: &LirBody) -> Results<A> {
+    let basic_blocks = &lir_body.basic_blocks;
+    let bottom = analysis.bottom_value(lir_body);
+    let mut entry_sets = IdxVec::from_elem_n(bottom, basic_blocks.len());
+
+    let mut worklist: VecDeque<BasicBlock> = match A::DIRECTION {
+        Direction::Forward => {
+            if !basic_blocks.is_empty() {
+                analysis.initialize_start_block(lir_body, &mut entry_sets[ENTRY_BLOCK]);
+            }
+            cfg::reverse_postorder(basic_blocks).into()
+        }
+        Direction::Backward => {
+            let mut postorder = cfg::reverse_postorder(basic_blocks);
+            postorder.reverse();
+            // The unreachable blocks are visited too (last), so that their states are
+            // computed as well.
+            let reachable: IdxBitSet<BasicBlock> = postorder.iter().copied().collect();
+            postorder.extend(basic_blocks.indices().filter(|bb| !reachable.contains(*bb)));
+            postorder.into()
+        }
+    };
+    let mut in_worklist: IdxBitSet<BasicBlock> = worklist.iter().copied().collect();
+    in_worklist.ensure_domain(basic_blocks.len());
+    let predecessors = match A::DIRECTION {
+        Direction::Forward => IdxVec::new(),
+        Direction::Backward => cfg::predecessors(basic_blocks),
+    };
+
+    let mut visits = 0;
+    while let Some(bb) = worklist.pop_front() {
+        in_worklist.remove(bb);
+        visits += 1;
+        let mut state = entry_sets[bb].clone();
+        let mut propagate =
+            |entry_sets: &mut IdxVec<BasicBlock, A::Domain>, to: BasicBlock, state: &A::Domain| {
+                if entry_sets[to].join(state) && in_worklist.insert(to) {
+                    worklist.push_back(to);
+                }
+            };
+
+        let bb_data = &basic_blocks[bb];
+        match A::DIRECTION {
+            Direction::Forward => {
+                apply_block_effects(&analysis, &mut state, bb, bb_data);
+                for successor in bb_data.terminator.successors() {
+                    match call_return(&bb_data.terminator, successor) {
+                        Some(destination) => {
+                            let mut return_state = state.clone();
+                            analysis.apply_call_return_effect(&mut return_state, destination);
+                            propagate(&mut entry_sets, successor, &return_state);
+                        }
+                        None => propagate(&mut entry_sets, successor, &state),
+                    }
+                }
+            }
+            Direction::Backward => {
+                apply_block_effects_backward(&analysis, &mut state, bb, bb_data, None);
+                for pred in &predecessors[bb] {
+                    match call_return(&basic_blocks[*pred].terminator, bb) {
+                        Some(destination) => {
+                            let mut return_state = state.clone();
+                            analysis.apply_call_return_effect(&mut return_state, destination);
+                            propagate(&mut entry_sets, *pred, &return_state);
+                        }
+                        None => propagate(&mut entry_sets, *pred, &state),
+                    }
+                }
+            }
+        }
+    }
+    debug!(
+        "Reached the fixpoint after {} visits of {} blocks",
+        visits,
+        basic_blocks.len()
+    );
+
+    Results {
+        analysis,
+        entry_sets,
+    }
+}
+
+/// Returns the destination of the given terminator if it is a `Call` returning to
+/// `target`.
+fn call_return(terminator: &Terminator, target: BasicBlock) -> Option<&Place> {
+    match &terminator.kind {
+        TerminatorKind::Call {
+            destination,
+            target: Some(call_target),
+            ..
+        } if *call_target == target => Some(destination),
+        _ => None,
+    }
+}
+
+/// Applies the effects of the statements of the block, and then of its terminator.
+fn apply_block_effects<A: Analysis>(
+    analysis: &A,
+    state: &mut A::Domain,
+    bb: BasicBlock,
+    bb_data: &BasicBlockData,
+) {
+    for (statement_index, statement) in bb_data.statements.iter().enumerate() {
+        let location = Location {
+            block: bb,
+            statement_index,
+        };
+        analysis.apply_statement_effect(state, statement, location);
+    }
+    analysis.apply_terminator_effect(state, &bb_data.terminator, bb_data.terminator_location(bb));
+}
+
+/// Applies the effects of the terminator of the block, and then of its statements in
+/// reverse order, down to the statement `until` (excluded), if any.
+fn apply_block_effects_backward<A: Analysis>(
+    analysis: &A,
+    state: &mut A::Domain,
+    bb: BasicBlock,
+    bb_data: &BasicBlockData,
+    until: Option<usize>,
+) {
+    analysis.apply_terminator_effect(state, &bb_data.terminator, bb_data.terminator_location(bb));
+    let start = until.map_or(0, |until| until + 1);
+    for (statement_index, statement) in bb_data.statements.iter().enumerate().skip(start).rev() {
+        let location = Location {
+            block: bb,
+            statement_index,
+        };
+        analysis.apply_statement_effect(state, statement, location);
+    }
+}
+
+impl<A: Analysis> Results<A> {
+    /// Returns the state at the start of the given block, in the direction of the
+    /// analysis.
+    pub fn entry_set(&self, bb: BasicBlock) -> &A::Domain {
+        &self.entry_sets[bb]
+    }
+
+    /// Returns the state right before the statement (or terminator) at the given
+    /// location is executed.
+    pub fn state_before(&self, lir_body: &LirBody, location: Location) -> A::Domain {
+        self.state_at(lir_body, location, false)
+    }
+
+    /// Returns the state right after the statement (or terminator) at the given
+    /// location is executed. After a `Call`, the destination is not written yet (see
+    /// `Analysis::apply_call_return_effect`).
+    pub fn state_after(&self, lir_body: &LirBody, location: Location) -> A::Domain {
+        self.state_at(lir_body, location, true)
+    }
+
+    /// Returns the state before or after the given location, by applying the effects
+    /// of the block up to the location, in the direction of the analysis.
+    fn state_at(&self, lir_body: &LirBody, location: Location, after: bool) -> A::Domain {
+        let bb = location.block;
+        let bb_data = &lir_body.basic_blocks[bb];
+        let terminator_index = bb_data.statements.len();
+        assert!(
+            location.statement_index <= terminator_index,
+            "{:?} is out of {:?}",
+            location,
+            bb
+        );
+        let mut state = self.entry_sets[bb].clone();
+        match A::DIRECTION {
+            Direction::Forward => {
+                let end = location.statement_index + usize::from(after);
+                for (statement_index, statement) in bb_data.statements.iter().enumerate() {
+                    if statement_index >= end {
+                        return state;
+                    }
+                    let location = Location {
+                        block: bb,
+                        statement_index,
+                    };
+                    self.analysis
+                        .apply_statement_effect(&mut state, statement, location);
+                }
+                if end > terminator_index {
+                    self.analysis.apply_terminator_effect(
+                        &mut state,
+                        &bb_data.terminator,
+                        bb_data.terminator_location(bb),
+                    );
+                }
+            }
+            Direction::Backward => {
+                if location.statement_index == terminator_index {
+                    if !after {
+                        self.analysis.apply_terminator_effect(
+                            &mut state,
+                            &bb_data.terminator,
+                            bb_data.terminator_location(bb),
+                        );
+                    }
+                    return state;
+                }
+                apply_block_effects_backward(
+                    &self.analysis,
+                    &mut state,
+                    bb,
+                    bb_data,
+                    Some(location.statement_index),
+                );
+                if !after {
+                    self.analysis.apply_statement_effect(
+                        &mut state,
+                        &bb_data.statements[location.statement_index],
+                        location,
+                    );
+                }
+            }
+        }
+        state
+    }
+}
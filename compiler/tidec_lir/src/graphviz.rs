@@ -0,0 +1,98 @@
+//! An export of the control-flow graph (CFG) of the LIR bodies to Graphviz.
+
+use std::fmt::Write;
+
+use tidec_utils::idx::Idx;
+
+use crate::{
+    basic_blocks::BasicBlock,
+    lir::LirBody,
+    pretty::LirPrinter,
+    syntax::{Terminator, TerminatorKind, UnwindAction},
+};
+
+impl LirBody {
+    /// Returns the CFG of the body as a Graphviz digraph, which can be rendered with,
+    /// e.g., `dot -Tsvg main.dot -o main.svg`.
+    ///
+    /// Each basic block is a node, labelled with its statements and terminator as
+    /// printed by the given printer. Each edge goes from a block to a successor, and
+    /// it is labelled with its kind (e.g., `unwind`) when the block has more than one.
+    // It corresponds to `write_mir_fn_graphviz` in rustc_middle/src/mir/graphviz.rs
+    pub fn to_dot(&self, printer: &LirPrinter) -> String {
+        let mut dot = String::new();
+        writeln!(dot, "digraph {} {{", quote(&self.metadata.name)).unwrap();
+        writeln!(
+            dot,
+            "    node [shape=\"box\", fontname=\"monospace\", labeljust=\"l\"];"
+        )
+        .unwrap();
+
+        for (bb, bb_data) in self.basic_blocks.iter_enumerated() {
+            let cleanup = if bb_data.is_cleanup() {
+                " (cleanup)"
+            } else {
+                ""
+            };
+            let mut label = format!("bb{}{}:\\l", bb.idx(), cleanup);
+            for line in printer.block_to_lines(self, bb_data) {
+                label.push_str(&escape(&line));
+                label.push_str(";\\l");
+            }
+            writeln!(dot, "    bb{} [label=\"{}\"];", bb.idx(), label).unwrap();
+        }
+
+        for (bb, bb_data) in self.basic_blocks.iter_enumerated() {
+            for (successor, edge_label) in labelled_successors(&bb_data.terminator) {
+                write!(dot, "    bb{} -> bb{}", bb.idx(), successor.idx()).unwrap();
+                if let Some(edge_label) = edge_label {
+                    write!(dot, " [label=\"{}\"]", edge_label).unwrap();
+                }
+                writeln!(dot, ";").unwrap();
+            }
+        }
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+}
+
+/// Returns the successors of the terminator, with the label of the edge to
+/// each of them, if the terminator has more than one.
+fn labelled_successors(terminator: &Terminator) -> Vec<(BasicBlock, Option<&'static str>)> {
+    match &terminator.kind {
+        TerminatorKind::Assert {
+            target,
+            unwind: UnwindAction::Cleanup(cleanup),
+            ..
+        } => vec![(*target, Some("success")), (*cleanup, Some("unwind"))],
+        TerminatorKind::Call {
+            target: Some(target),
+            unwind: UnwindAction::Cleanup(cleanup),
+            ..
+        }
+        | TerminatorKind::Drop {
+            target,
+            unwind: UnwindAction::Cleanup(cleanup),
+            ..
+        } => vec![(*target, Some("return")), (*cleanup, Some("unwind"))],
+        TerminatorKind::Call {
+            target: None,
+            unwind: UnwindAction::Cleanup(cleanup),
+            ..
+        } => vec![(*cleanup, Some("unwind"))],
+        _ => terminator
+            .successors()
+            .map(|successor| (successor, None))
+            .collect(),
+    }
+}
+
+/// Escapes the characters of the given text that are special in a Graphviz string.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Returns the given text as a quoted Graphviz identifier.
+fn quote(text: &str) -> String {
+    format!("\"{}\"", escape(text))
+}
@@ -1,35 +1,199 @@
-use crate::lir::LirCtx;
+use crate::{
+    lir::LirCtx,
+    syntax::{AdtKind, LirTy},
+};
 use tidec_abi::{
-    layout::{BackendRepr, Layout, Primitive, TyAndLayout},
+    layout::{
+        BackendRepr, FieldsShape, Layout, Primitive, ReprOptions, Scalar, TyAbiInterface,
+        TyAndLayout, VariantIdx, Variants, WrappingRange,
+    },
     size_and_align::{AbiAndPrefAlign, Size},
 };
+use tidec_utils::idx::Idx;
 
 pub struct LayoutCtx<'a> {
-    _lir_ty_ctx: &'a LirCtx,
+    lir_ty_ctx: &'a LirCtx,
 }
 
 impl<'a> LayoutCtx<'a> {
     // It accepts the `LirTyCtx` because it contains the `TargetDataLayout`.
     pub fn new(lir_ty_ctx: &'a LirCtx) -> Self {
-        LayoutCtx {
-            _lir_ty_ctx: lir_ty_ctx,
-        }
+        LayoutCtx { lir_ty_ctx }
     }
 
-    /// Computes the layout for a given type. We should cache the results
-    /// to avoid recomputing the layout for the same type multiple times.
-    pub fn compute_layout<T>(&self, ty: T) -> TyAndLayout<T> {
-        let _ = ty;
-        // let data_layout = self.target.data_layout;
+    /// Computes the layout for a given type. The layouts of the types it is made of
+    /// are queried with `LirCtx::layout_of`, which caches them. Use `LirCtx::layout_of`
+    /// for the cached and interned layout of the type itself.
+    pub fn compute_layout(&self, ty: &LirTy) -> Layout {
+        // TODO(bruzzone): use the alignments of the `TargetDataLayout`.
+        // For now, primitives are naturally aligned (i.e., the alignment is equal to the size).
+        let scalar_with_range = |primitive: Primitive, size_in_bytes: u64, valid_range| {
+            Layout::scalar(
+                Scalar {
+                    primitive,
+                    valid_range,
+                },
+                Size::from_bytes(size_in_bytes),
+                AbiAndPrefAlign::new(size_in_bytes, size_in_bytes),
+            )
+        };
+        let scalar = |primitive: Primitive, size_in_bytes: u64| {
+            scalar_with_range(
+                primitive,
+                size_in_bytes,
+                WrappingRange::full(Size::from_bytes(size_in_bytes)),
+            )
+        };
 
-        // HARDCODE FOR TESTING an integer type
-        TyAndLayout {
-            ty,
-            layout: Layout {
-                size: Size::from_bits(32),
-                align: AbiAndPrefAlign::new(4, 4),
-                backend_repr: BackendRepr::Scalar(Primitive::I32),
-            },
+        match ty {
+            // A boolean is stored as a byte, where only the values 0 and 1 are valid.
+            LirTy::Bool => scalar_with_range(Primitive::U8, 1, WrappingRange { start: 0, end: 1 }),
+            LirTy::I8 => scalar(Primitive::I8, 1),
+            LirTy::I16 => scalar(Primitive::I16, 2),
+            LirTy::I32 => scalar(Primitive::I32, 4),
+            LirTy::I64 => scalar(Primitive::I64, 8),
+            LirTy::I128 => scalar(Primitive::I128, 16),
+            LirTy::U8 => scalar(Primitive::U8, 1),
+            LirTy::U16 => scalar(Primitive::U16, 2),
+            LirTy::U32 => scalar(Primitive::U32, 4),
+            LirTy::U64 => scalar(Primitive::U64, 8),
+            LirTy::U128 => scalar(Primitive::U128, 16),
+            LirTy::F16 => scalar(Primitive::F16, 2),
+            LirTy::F32 => scalar(Primitive::F32, 4),
+            LirTy::F64 => scalar(Primitive::F64, 8),
+            LirTy::F128 => scalar(Primitive::F128, 16),
+            LirTy::Ptr(_, addr_space) => {
+                let data_layout = &self.lir_ty_ctx.target().data_layout;
+                let pointer_size = data_layout.pointer_size_in(*addr_space);
+                scalar(Primitive::Pointer(*addr_space), pointer_size / 8)
+            }
+            // A function pointer is never null, so the null value is a niche.
+            LirTy::FnPtr(_) => {
+                let data_layout = &self.lir_ty_ctx.target().data_layout;
+                let addr_space = data_layout.instruction_address_space;
+                let pointer_size = data_layout.pointer_size_in(addr_space) / 8;
+                let valid_range = WrappingRange {
+                    start: 1,
+                    ..WrappingRange::full(Size::from_bytes(pointer_size))
+                };
+                scalar_with_range(Primitive::Pointer(addr_space), pointer_size, valid_range)
+            }
+            LirTy::Array(elem_ty, count) => {
+                let elem_layout = self.lir_ty_ctx.layout_of((**elem_ty).clone());
+                // The stride is the distance between the start of two consecutive elements.
+                let stride = elem_layout.size.align_to(elem_layout.align.abi);
+                Layout {
+                    size: Size::from_bytes(stride.bytes() * count),
+                    align: elem_layout.align,
+                    backend_repr: BackendRepr::Memory,
+                    fields: FieldsShape::Array {
+                        stride,
+                        count: *count,
+                    },
+                    variants: Variants::Single {
+                        index: VariantIdx::new(0),
+                    },
+                    // The niche of the first element, if any, is a niche of the array.
+                    largest_niche: (*count > 0)
+                        .then(|| elem_layout.layout.largest_niche.clone())
+                        .flatten(),
+                }
+            }
+            LirTy::Adt(adt_id) => {
+                let adt_def = self.lir_ty_ctx.adt_def(*adt_id);
+                match adt_def.kind {
+                    AdtKind::Struct => Layout::univariant(
+                        &self.field_layouts(&adt_def.non_enum_variant().fields.raw),
+                        adt_def.repr,
+                    ),
+                    AdtKind::Enum => {
+                        let tag_layout = self.lir_ty_ctx.layout_of(adt_def.discr_ty());
+                        let tag = tag_layout.backend_repr.to_primitive();
+                        let variants = adt_def
+                            .variants
+                            .iter()
+                            .map(|variant| self.field_layouts(&variant.fields.raw))
+                            .collect::<Vec<_>>();
+                        let tagged = Layout::tagged_union(tag, &tag_layout, &variants);
+                        // Prefer the niche encoding, unless it makes the enum bigger.
+                        match Layout::niche_filling(&variants) {
+                            Some(niche_filled)
+                                if niche_filled.size.bytes() <= tagged.size.bytes() =>
+                            {
+                                niche_filled
+                            }
+                            _ => tagged,
+                        }
+                    }
+                }
+            }
+            LirTy::Tuple(field_tys) => {
+                Layout::univariant(&self.field_layouts(field_tys), ReprOptions::default())
+            }
+            // The never type has no values, so it is never stored: it is a ZST, as `()`.
+            LirTy::Never => Layout::univariant(&[], ReprOptions::default()),
+            // A slice is laid out as the pair of its pointer and its length.
+            LirTy::Slice(elem_ty) => Layout::univariant(
+                &self.field_layouts(&self.lir_ty_ctx.slice_field_tys(elem_ty)),
+                ReprOptions::default(),
+            ),
+            LirTy::Param(idx) => {
+                panic!(
+                    "Generic parameter {} must be instantiated before layout",
+                    idx
+                )
+            }
+            LirTy::Metadata => panic!("Metadata type does not have a layout"),
         }
     }
+
+    /// Computes the layouts of the given fields.
+    fn field_layouts(&self, field_tys: &[LirTy]) -> Vec<Layout> {
+        field_tys
+            .iter()
+            .map(|field_ty| self.lir_ty_ctx.layout_of(field_ty.clone()).layout.clone())
+            .collect()
+    }
+}
+
+impl<'a> TyAbiInterface<'a, LirCtx> for LirTy {
+    fn fields(
+        this: &TyAndLayout<'a, LirTy>,
+        lir_ctx: &'a LirCtx,
+    ) -> Vec<(Size, TyAndLayout<'a, LirTy>)> {
+        let field_tys = match &this.ty {
+            LirTy::Tuple(field_tys) => field_tys.clone(),
+            LirTy::Slice(elem_ty) => lir_ctx.slice_field_tys(elem_ty).to_vec(),
+            LirTy::Array(elem_ty, count) => {
+                let elem_layout = lir_ctx.layout_of((**elem_ty).clone());
+                return (0..*count as usize)
+                    .map(|i| (this.field_offset(i), elem_layout.clone()))
+                    .collect();
+            }
+            LirTy::Adt(adt_id) => {
+                let adt_def = lir_ctx.adt_def(*adt_id);
+                match (adt_def.kind, &this.variants) {
+                    (AdtKind::Struct, _) => adt_def.non_enum_variant().fields.raw.clone(),
+                    // The fields of an enum are the ones of its variants.
+                    (AdtKind::Enum, Variants::Multiple { .. }) => vec![],
+                    (AdtKind::Enum, Variants::Single { index }) => {
+                        let fields = adt_def.variants[*index].fields.raw.iter().cloned();
+                        // The tag is the first field of the variants, unless it is a niche.
+                        if lir_ctx.layout_of(this.ty.clone()).has_tag_field() {
+                            std::iter::once(adt_def.discr_ty()).chain(fields).collect()
+                        } else {
+                            fields.collect()
+                        }
+                    }
+                }
+            }
+            _ => vec![],
+        };
+
+        field_tys
+            .into_iter()
+            .enumerate()
+            .map(|(i, field_ty)| (this.field_offset(i), lir_ctx.layout_of(field_ty)))
+            .collect()
+    }
 }
@@ -1,4 +1,13 @@
 pub mod basic_blocks;
+pub mod cfg;
+pub mod dataflow;
+pub mod graphviz;
 pub mod layout_ctx;
 pub mod lir;
+pub mod metadata;
+pub mod parse;
+pub mod pretty;
+pub mod ssa;
 pub mod syntax;
+pub mod transform;
+pub mod visit;
@@ -1,19 +1,45 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::sync::Arc;
+
 use crate::{
     basic_blocks::{BasicBlock, BasicBlockData},
     layout_ctx::LayoutCtx,
-    syntax::{Body, LirTy, Local, LocalData},
+    syntax::{
+        AdtDef, AdtId, AllocId, Allocation, Body, ConstValue, Generics, LirTy, Local, LocalData,
+    },
 };
+// The calling convention is defined in `tidec_abi`, as it is part of the `FnAbi`.
+pub use tidec_abi::calling_convention::CallConv;
 use tidec_abi::{
-    layout::TyAndLayout,
-    target::{BackendKind, LirTarget},
+    layout::{Layout, TyAndLayout},
+    size_and_align::Align,
+    target::{BackendKind, CodeModel, LirTarget, RelocModel},
+};
+use tidec_session::config::{
+    AliasMetadata, DebugInfo, EmitKind, Lto, OptLevel, SanitizerSet, SplitDebugInfo, StackProtector,
+};
+use tidec_session::Session;
+use tidec_span::{source_map::SourceMap, Span};
+use tidec_utils::{
+    arena::KeyedInterner, fingerprint::Fingerprint, idx::Idx, index_vec::IdxVec, newtype_index,
 };
-use tidec_utils::index_vec::IdxVec;
 use tracing::{debug, instrument};
 
-#[derive(Eq, PartialEq, Hash, Clone, Copy)]
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DefId(pub usize);
 
-#[derive(Clone, Copy)]
+newtype_index! {
+    /// An interned name in the `LirCtx` (see `LirCtx::intern_symbol`). Two symbols are
+    /// equal if and only if their names are equal.
+    // It corresponds to `Symbol` in rustc_span/src/symbol.rs
+    pub struct Symbol {}
+}
+
+#[derive(Debug, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Specifies the linkage of a symbol.
 /// All Global Variables and Functions have one of the following types of linkage.
 ///
@@ -79,7 +105,19 @@ pub enum Linkage {
     External,
 }
 
-#[derive(Clone, Copy)]
+impl Linkage {
+    /// Returns `true` if a definition with this linkage is in the symbol table of the
+    /// object file, so that the other objects can refer to it.
+    pub fn is_exported(self) -> bool {
+        !matches!(
+            self,
+            Linkage::Private | Linkage::Internal | Linkage::AvailableExternally
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Specifies the symbol visibility with regards to dynamic linking.
 /// All Global Variables and Functions have one of the following visibility styles.
 ///
@@ -104,6 +142,8 @@ pub enum Visibility {
     Protected,
 }
 
+#[derive(Debug, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// A user-callable item in LIR.
 pub enum LirItemKind {
     /// A function.
@@ -112,9 +152,14 @@ pub enum LirItemKind {
     Closure,
     /// A coroutine.
     Coroutine,
+    /// A constant, whose value is returned by the body. It takes no arguments and it is
+    /// evaluated at compile time (see `tidec_const_eval`).
+    // It corresponds to `DefKind::Const` in rustc_hir/src/def.rs
+    Const,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Specifies the significance of a global value's address, used for enabling
 /// optimizations related to constant merging and deduplication.
 ///
@@ -138,82 +183,8 @@ pub enum UnnamedAddress {
     Global,
 }
 
-#[derive(Clone, Copy)]
-/// The calling convention of a function.
-///
-/// The calling convention is a low-level detail that specifies how
-/// arguments are passed to a function and how the return value is obtained.
-/// It is important for the backend to know the calling convention in order
-/// to generate the correct code for function calls and returns.
-///
-/// For more details, see the LLVM documentation on calling conventions:
-/// https://llvm.org/docs/LangRef.html#call-conventions
-pub enum CallConv {
-    C = 0,
-    Rust = 1, // Added by Tidec
-    Fast = 8,
-    Cold = 9,
-    GHC = 10,
-    HiPE = 11,
-    AnyReg = 13,
-    PreserveMost = 14,
-    PreserveAll = 15,
-    Swift = 16,
-    CxxFastTls = 17,
-    Tail = 18,
-    CfguardCheck = 19,
-    SwiftTail = 20,
-    PreserveNone = 21,
-    FirstTargetCC = 63, // TODO: In the LLVM documentation it was 64. Overlapping with the X86StdCall
-    X86StdCall = 64,
-    X86FastCall = 65,
-    ArmApcs = 66,
-    ArmAapcs = 67,
-    ArmAapcsVfp = 68,
-    Msp430Intr = 69,
-    X86ThisCall = 70,
-    PtxKernel = 71,
-    PtxDevice = 72,
-    SpirFunc = 75,
-    SpirKernel = 76,
-    IntelOclBi = 77,
-    X86_64SysV = 78,
-    Win64 = 79,
-    X86VectorCall = 80,
-    DummyHhvm = 81,
-    DummyHhvmC = 82,
-    X86Intr = 83,
-    AvrIntr = 84,
-    AvrSignal = 85,
-    AvrBuiltin = 86,
-    AmdgpuVs = 87,
-    AmdgpuGs = 88,
-    AmdgpuPs = 89,
-    AmdgpuCs = 90,
-    AmdgpuKernel = 91,
-    X86RegCall = 92,
-    AmdgpuHs = 93,
-    Msp430Builtin = 94,
-    AmdgpuLs = 95,
-    AmdgpuEs = 96,
-    Aarch64VectorCall = 97,
-    Aarch64SveVectorCall = 98,
-    WasmEmscriptenInvoke = 99,
-    AmdgpuGfx = 100,
-    M68kIntr = 101,
-    Aarch64SmeAbiSupportRoutinesPreserveMostFromX0 = 102,
-    Aarch64SmeAbiSupportRoutinesPreserveMostFromX2 = 103,
-    AmdgpuCsChain = 104,
-    AmdgpuCsChainPreserve = 105,
-    M68kRtd = 106,
-    GRAAL = 107,
-    Arm64ecThunkX64 = 108,
-    Arm64ecThunkNative = 109,
-    RiscvVectorCall = 110,
-    Aarch64SmeAbiSupportRoutinesPreserveMostFromX1 = 111,
-    MaxID = 1023,
-}
-
+#[derive(Debug, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// The kind of a LIR body.
 // TODO(bruzzone): add other kinds of body; e.g. virtual function, fn pointer, etc.
 // See: rustc_middle::ty::InstanceKind
@@ -221,6 +192,73 @@ pub enum LirBodyKind {
     Item(LirItemKind),
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The set of the attributes of a function, which are hints to the optimizations
+/// (e.g., to the inliner) or constraints on the generated code.
+// It corresponds to `CodegenFnAttrFlags` and `InlineAttr` in rustc_middle/src/middle/codegen_fn_attrs.rs
+pub struct FnAttribute(u8);
+
+impl FnAttribute {
+    /// The function should be inlined, if it is small enough (see `transform::Inline`).
+    pub const INLINE: FnAttribute = FnAttribute(1 << 0);
+    /// The function should always be inlined, regardless of its size.
+    pub const ALWAYS_INLINE: FnAttribute = FnAttribute(1 << 1);
+    /// The function should never be inlined.
+    pub const NO_INLINE: FnAttribute = FnAttribute(1 << 2);
+    /// The function is rarely called.
+    pub const COLD: FnAttribute = FnAttribute(1 << 3);
+    /// The function has no prologue nor epilogue.
+    pub const NAKED: FnAttribute = FnAttribute(1 << 4);
+    /// The function never unwinds.
+    pub const NO_UNWIND: FnAttribute = FnAttribute(1 << 5);
+    /// The function is optimized for size, rather than for speed.
+    pub const OPT_SIZE: FnAttribute = FnAttribute(1 << 6);
+
+    /// The attributes, with their names in the textual LIR (e.g., `#[cold]`).
+    pub const NAMES: [(FnAttribute, &'static str); 7] = [
+        (FnAttribute::INLINE, "inline"),
+        (FnAttribute::ALWAYS_INLINE, "inline(always)"),
+        (FnAttribute::NO_INLINE, "inline(never)"),
+        (FnAttribute::COLD, "cold"),
+        (FnAttribute::NAKED, "naked"),
+        (FnAttribute::NO_UNWIND, "nounwind"),
+        (FnAttribute::OPT_SIZE, "optsize"),
+    ];
+
+    /// Returns `true` if all the attributes of `other` are in `self`.
+    pub fn contains(self, other: FnAttribute) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns `true` if any attribute of `other` is in `self`.
+    pub fn intersects(self, other: FnAttribute) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    pub fn insert(&mut self, other: FnAttribute) {
+        self.0 |= other.0;
+    }
+
+    /// Returns the names of the attributes in `self`, in the order of `NAMES`.
+    pub fn names(self) -> impl Iterator<Item = &'static str> {
+        FnAttribute::NAMES
+            .into_iter()
+            .filter(move |(attr, _)| self.contains(*attr))
+            .map(|(_, name)| name)
+    }
+}
+
+impl std::ops::BitOr for FnAttribute {
+    type Output = FnAttribute;
+
+    fn bitor(self, rhs: FnAttribute) -> FnAttribute {
+        FnAttribute(self.0 | rhs.0)
+    }
+}
+
+#[derive(Debug, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// The metadata of a LIR body (function).
 pub struct LirBodyMetadata {
     /// The definition ID of the function.
@@ -230,8 +268,8 @@ pub struct LirBodyMetadata {
     pub name: String,
     /// The kind of the body.
     pub kind: LirBodyKind,
-    /// If the function should be inlined.
-    pub inlined: bool,
+    /// The attributes of the function (e.g., its inlining hints).
+    pub attrs: FnAttribute,
     /// The linkage of the function.
     pub linkage: Linkage,
     /// The visibility of the function.
@@ -240,11 +278,27 @@ pub struct LirBodyMetadata {
     pub unnamed_address: UnnamedAddress,
     /// The calling convention of the function.
     pub call_conv: CallConv,
+    /// Whether the function takes more arguments than the ones of its `ret_and_args`
+    /// (e.g., `printf`). Only a declaration can be variadic.
+    // TODO(bruzzone): support the definition of variadic functions (i.e., `va_list`).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub is_variadic: bool,
+    /// The symbol name of the personality function, which drives the unwinding
+    /// through the cleanup blocks of the function (e.g., `rust_eh_personality`). It
+    /// is required if the function has cleanup blocks.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub personality: Option<String>,
+    /// The source of the function, or `Span::DUMMY` if it has been built by the compiler.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub span: Span,
 }
 
+#[derive(Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// The body of a function in LIR. A body could be a function, a closure, a coroutine, etc.
-/// A body is expected to be monomorphized and specialized, that is, when generic parameters are
-/// involved, each instantiation of the generics should have its own body.
+/// A body is expected to be monomorphized and specialized before codegen, that is, when generic
+/// parameters are involved, each instantiation of the generics should have its own body. The
+/// generic bodies are instantiated by the collector in `tidec_monomorphize`.
 ///
 /// Semantically, a body is a portion of code that constitutes a complete unit of execution.
 pub struct LirBody {
@@ -252,6 +306,10 @@ pub struct LirBody {
     // TODO(bruzzone): consider to detach the metadata from the body
     pub metadata: LirBodyMetadata,
 
+    /// The generic parameters of the function. A generic body is instantiated by the
+    /// monomorphization collector before codegen.
+    pub generics: Generics,
+
     /// The locals for return value and arguments of the function.
     /// The first local is the return value, and the rest are the arguments.
     pub ret_and_args: IdxVec<Local, LocalData>,
@@ -263,11 +321,70 @@ pub struct LirBody {
     pub basic_blocks: IdxVec<BasicBlock, BasicBlockData>,
 }
 
+impl LirBody {
+    /// Returns `true` if the body only declares a function defined elsewhere (e.g.,
+    /// in another codegen unit or in the C library), that is, if it has no blocks.
+    pub fn is_declaration(&self) -> bool {
+        self.basic_blocks.is_empty()
+    }
+
+    /// Returns `true` if the body computes a constant (see `LirItemKind::Const`).
+    pub fn is_const(&self) -> bool {
+        matches!(self.metadata.kind, LirBodyKind::Item(LirItemKind::Const))
+    }
+
+    /// Returns the declaration of the given local, which can be the return value, an
+    /// argument, or any other local of the body.
+    pub fn local_data(&self, local: Local) -> &LocalData {
+        match local.idx().checked_sub(self.ret_and_args.len()) {
+            Some(idx) => &self.locals[Local::new(idx)],
+            None => &self.ret_and_args[local],
+        }
+    }
+
+    /// Returns the name of the given local in the emitted code: its name, if it has one,
+    /// or else `_N`, as in the textual representation of the body.
+    pub fn local_name(&self, lir_ctx: &LirCtx, local: Local) -> String {
+        match self.local_data(local).name {
+            Some(name) => lir_ctx.symbol_str(name).to_string(),
+            None => format!("_{}", local.idx()),
+        }
+    }
+
+    /// Returns the number of the locals, including the return value and the arguments.
+    pub fn num_locals(&self) -> usize {
+        self.ret_and_args.len() + self.locals.len()
+    }
+
+    /// Returns the fingerprint of the whole body (i.e., its metadata, locals and
+    /// blocks), which is the same across runs and hosts.
+    pub fn fingerprint(&self) -> Fingerprint {
+        Fingerprint::of(self)
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// The metadata of a LIR unit (module).
 pub struct LirUnitMetadata {
     pub unit_name: String,
 }
 
+#[derive(Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A function of another unit that the unit uses (e.g., a function of a library,
+/// imported from its metadata, see `crate::metadata`). The backend declares it in
+/// the backend unit when it is first used, and the linker resolves it to the object
+/// of the other unit.
+// It corresponds to the `DefId`s of the other crates (i.e., with a `CrateNum` that is
+// not `LOCAL_CRATE`) in rustc_span/src/def_id.rs
+pub struct LirImport {
+    /// The name of the unit defining the function.
+    pub unit_name: String,
+    /// The declaration of the function, whose name is its symbol.
+    pub declaration: LirBody,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// The LIR unit (module).
 pub struct LirUnit {
     /// The metadata of the unit.
@@ -275,54 +392,420 @@ pub struct LirUnit {
 
     /// The functions in the unit.
     pub bodies: IdxVec<Body, LirBody>,
-}
 
-#[derive(Debug)]
-/// The kind of code to emit.
-pub enum EmitKind {
-    Object,
-    Assembly,
-}
-
-#[derive(Debug)]
-/// The arguments for LIR type context. Usually provided by the user.
-pub struct LirArgs {
-    pub emit_kind: EmitKind,
-    // TODO(bruzzone): add more arguments here
+    /// The import table, i.e., the functions of the other units used by the unit, by
+    /// their `DefId`, which is not the one of a body of the unit.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub imports: BTreeMap<DefId, LirImport>,
 }
 
 #[derive(Debug)]
 pub struct LirCtx {
-    target: LirTarget,
-    arguments: LirArgs,
-    // TODO(bruzzone): here we should have, other then an arena, also a HashMap from DefId
-    // to the body of the function.
+    /// The session of the compilation, shared by the contexts of all the units.
+    sess: Arc<Session>,
+    /// The definitions of the ADTs, referenced by `LirTy::Adt`.
+    adt_defs: IdxVec<AdtId, AdtDef>,
+    /// The allocations, referenced by `ConstValue::Indirect`.
+    allocs: IdxVec<AllocId, Allocation>,
+    /// The interned layouts, referenced by `TyAndLayout`, and the cache of the
+    /// layout of each type (see `LirCtx::layout_of`).
+    // TODO(bruzzone): intern the types too, so that `TyAndLayout` does not own its type.
+    layouts: KeyedInterner<LirTy, Layout>,
+    /// The interned names, referenced by `Symbol`.
+    symbols: IdxVec<Symbol, String>,
+    /// The symbol of each interned name.
+    symbol_ids: HashMap<String, Symbol>,
+    /// The bodies of the functions, by their `DefId` (see `LirCtx::define_body`).
+    bodies: BTreeMap<DefId, LirBody>,
+    /// The `DefId` of the functions, by the symbol of their name.
+    def_ids: HashMap<Symbol, DefId>,
+    /// The symbol of the name of each function, defined or imported, by its `DefId`.
+    def_symbols: HashMap<DefId, Symbol>,
+    /// The functions of the other units, by their `DefId` (see `LirUnit::imports`).
+    imports: BTreeMap<DefId, LirImport>,
+    /// The drop glue of the types that need to be dropped (see `TerminatorKind::Drop`).
+    drop_glues: HashMap<LirTy, DefId>,
+    /// The values of the constants evaluated so far, by the `DefId` of their body
+    /// (see `tidec_const_eval`).
+    const_values: HashMap<DefId, ConstValue>,
+    /// The source files of the bodies, which resolve their spans.
+    source_map: SourceMap,
 }
 
 impl LirCtx {
     #[instrument]
-    pub fn new(codegen_backend: BackendKind, emit_kind: EmitKind) -> Self {
-        let target = LirTarget::new(codegen_backend);
-        let arguments = LirArgs { emit_kind };
-        let ctx = LirCtx { target, arguments };
+    pub fn new(sess: Arc<Session>) -> Self {
+        let ctx = LirCtx {
+            sess,
+            adt_defs: IdxVec::new(),
+            allocs: IdxVec::new(),
+            layouts: KeyedInterner::new(),
+            symbols: IdxVec::new(),
+            symbol_ids: HashMap::new(),
+            bodies: BTreeMap::new(),
+            def_ids: HashMap::new(),
+            def_symbols: HashMap::new(),
+            imports: BTreeMap::new(),
+            drop_glues: HashMap::new(),
+            const_values: HashMap::new(),
+            source_map: SourceMap::new(),
+        };
         debug!("LirTyCtx created: {:?}", ctx);
         ctx
     }
 
+    /// Returns a context with the same session, ADTs, allocations, names, drop
+    /// glues, constant values and source files, but without bodies nor imports, e.g., to codegen a codegen unit on another
+    /// thread (as a context cannot be shared between threads). The layouts are computed
+    /// again.
+    pub fn fork(&self) -> LirCtx {
+        LirCtx {
+            sess: self.sess.clone(),
+            adt_defs: self.adt_defs.clone(),
+            allocs: self.allocs.clone(),
+            layouts: KeyedInterner::new(),
+            symbols: self.symbols.clone(),
+            symbol_ids: self.symbol_ids.clone(),
+            bodies: BTreeMap::new(),
+            def_ids: HashMap::new(),
+            def_symbols: HashMap::new(),
+            imports: BTreeMap::new(),
+            drop_glues: self.drop_glues.clone(),
+            const_values: self.const_values.clone(),
+            source_map: self.source_map.clone(),
+        }
+    }
+
+    pub fn sess(&self) -> &Session {
+        &self.sess
+    }
+
+    /// Returns a handle to the session, e.g., to keep using it after the context
+    /// has been consumed.
+    pub fn sess_arc(&self) -> Arc<Session> {
+        self.sess.clone()
+    }
+
     pub fn target(&self) -> &LirTarget {
-        &self.target
+        &self.sess.target
     }
 
-    pub fn layout_of(&self, ty: LirTy) -> TyAndLayout<LirTy> {
-        let layout_ctx = LayoutCtx::new(self);
-        layout_ctx.compute_layout(ty)
+    /// Returns the source files of the bodies (see `tidec_span::source_map`).
+    pub fn source_map(&self) -> &SourceMap {
+        &self.source_map
+    }
+
+    pub fn source_map_mut(&mut self) -> &mut SourceMap {
+        &mut self.source_map
+    }
+
+    /// Adds the given ADT definition and returns its identifier.
+    pub fn define_adt(&mut self, adt_def: AdtDef) -> AdtId {
+        self.adt_defs.push(adt_def)
+    }
+
+    /// Returns the definition of the given ADT.
+    pub fn adt_def(&self, adt_id: AdtId) -> &AdtDef {
+        &self.adt_defs[adt_id]
+    }
+
+    /// Adds the given allocation and returns its identifier.
+    pub fn create_alloc(&mut self, alloc: Allocation) -> AllocId {
+        self.allocs.push(alloc)
+    }
+
+    /// Returns the given allocation.
+    pub fn alloc(&self, alloc_id: AllocId) -> &Allocation {
+        &self.allocs[alloc_id]
+    }
+
+    /// Returns the symbol of the given name, interning it if needed.
+    pub fn intern_symbol(&mut self, name: &str) -> Symbol {
+        if let Some(symbol) = self.symbol_ids.get(name) {
+            return *symbol;
+        }
+        let symbol = self.symbols.push(name.to_string());
+        self.symbol_ids.insert(name.to_string(), symbol);
+        symbol
+    }
+
+    /// Returns the symbol of the given name, if it is interned.
+    pub fn lookup_symbol(&self, name: &str) -> Option<Symbol> {
+        self.symbol_ids.get(name).copied()
+    }
+
+    /// Returns the name of the given symbol.
+    pub fn symbol_str(&self, symbol: Symbol) -> &str {
+        &self.symbols[symbol]
+    }
+
+    /// Adds the given body to the definitions, by its `DefId` and its name.
+    ///
+    /// The bodies are usually defined once they are final, i.e., after the
+    /// monomorphization and the LIR passes, so that codegen can resolve the calls.
+    pub fn define_body(&mut self, lir_body: LirBody) {
+        let def_id = lir_body.metadata.def_id;
+        let symbol = self.intern_symbol(&lir_body.metadata.name);
+        if let Some(other) = self.def_ids.insert(symbol, def_id) {
+            panic!(
+                "The function {} is defined twice ({:?} and {:?})",
+                lir_body.metadata.name, other, def_id
+            );
+        }
+        self.def_symbols.insert(def_id, symbol);
+        if self.bodies.insert(def_id, lir_body).is_some() {
+            panic!("The definition {:?} is defined twice", def_id);
+        }
+    }
+
+    /// Moves the bodies of the given unit to the definitions (see `define_body`), and
+    /// its imports to the ones of the context, and returns the metadata of the unit.
+    pub fn define_unit(&mut self, lir_unit: LirUnit) -> LirUnitMetadata {
+        for lir_body in lir_unit.bodies {
+            self.define_body(lir_body);
+        }
+        for (def_id, import) in lir_unit.imports {
+            if self.bodies.contains_key(&def_id) {
+                panic!(
+                    "The import {} of {:?} is also defined",
+                    import.declaration.metadata.name, def_id
+                );
+            }
+            let symbol = self.intern_symbol(&import.declaration.metadata.name);
+            self.def_symbols.insert(def_id, symbol);
+            self.imports.insert(def_id, import);
+        }
+        lir_unit.metadata
+    }
+
+    /// Returns the import of the given function, if it is defined in another unit.
+    pub fn import(&self, def_id: DefId) -> Option<&LirImport> {
+        self.imports.get(&def_id)
+    }
+
+    /// Returns the imports of the context, i.e., the functions of the other units.
+    pub fn imports(&self) -> impl Iterator<Item = &LirImport> {
+        self.imports.values()
+    }
+
+    /// Returns the body of the given function, which is a declaration if the function
+    /// is imported from another unit (see `LirCtx::import`). It is used for the
+    /// signature of a callee, which may be defined in any unit.
+    pub fn callee(&self, def_id: DefId) -> &LirBody {
+        match self.import(def_id) {
+            Some(import) => &import.declaration,
+            None => self.def(def_id),
+        }
+    }
+
+    /// Returns the body of the given definition.
+    pub fn def(&self, def_id: DefId) -> &LirBody {
+        self.bodies
+            .get(&def_id)
+            .unwrap_or_else(|| panic!("The definition {:?} is not defined", def_id))
+    }
+
+    /// Returns the `DefId` of the function with the given name, if it is defined.
+    pub fn def_id_of(&self, name: &str) -> Option<DefId> {
+        self.def_ids.get(&self.lookup_symbol(name)?).copied()
+    }
+
+    /// Returns the symbol of the name of the given function, if it is defined or
+    /// imported. Unlike `lookup_symbol`, it does not hash the name.
+    pub fn def_symbol(&self, def_id: DefId) -> Option<Symbol> {
+        self.def_symbols.get(&def_id).copied()
+    }
+
+    /// Returns the defined bodies, ordered by their `DefId`.
+    pub fn defs(&self) -> impl Iterator<Item = &LirBody> {
+        self.bodies.values()
+    }
+
+    /// Registers the function `def_id` as the drop glue of the given type. It is called
+    /// with a pointer to the value to drop (i.e., its signature is `fn(*ty)`).
+    // It corresponds to the `drop_in_place` shims of rustc_mir_transform/src/shim.rs
+    pub fn register_drop_glue(&mut self, ty: LirTy, def_id: DefId) {
+        if let Some(other) = self.drop_glues.insert(ty.clone(), def_id) {
+            panic!(
+                "The type {:?} has two drop glues ({:?} and {:?})",
+                ty, other, def_id
+            );
+        }
+    }
+
+    /// Returns the drop glue of the given type, or `None` if it has nothing to drop.
+    pub fn drop_glue(&self, ty: &LirTy) -> Option<DefId> {
+        self.drop_glues.get(ty).copied()
+    }
+
+    /// Returns the type dropped by the function `def_id`, if it is a drop glue.
+    pub fn dropped_ty(&self, def_id: DefId) -> Option<&LirTy> {
+        self.drop_glues
+            .iter()
+            .find_map(|(ty, drop_glue)| (*drop_glue == def_id).then_some(ty))
+    }
+
+    /// Records the value of the constant computed by the body `def_id`.
+    pub fn record_const_value(&mut self, def_id: DefId, value: ConstValue) {
+        if self.const_values.insert(def_id, value).is_some() {
+            panic!("The constant {:?} is evaluated twice", def_id);
+        }
+    }
+
+    /// Returns the value of the constant computed by the body `def_id`, if it has
+    /// been evaluated.
+    pub fn const_value(&self, def_id: DefId) -> Option<&ConstValue> {
+        self.const_values.get(&def_id)
+    }
+
+    /// Adds an immutable allocation with the given bytes and returns a slice of them.
+    pub fn const_bytes(&mut self, bytes: impl Into<Vec<u8>>) -> ConstValue {
+        let alloc = Allocation::from_bytes(bytes, Align::ONE);
+        let len = alloc.size().bytes();
+        ConstValue::Slice {
+            alloc_id: self.create_alloc(alloc),
+            len,
+        }
+    }
+
+    /// Adds an immutable allocation with the UTF-8 bytes of the given string and
+    /// returns a constant of type `LirTy::str()`.
+    pub fn const_str(&mut self, s: &str) -> ConstValue {
+        self.const_bytes(s.as_bytes())
+    }
+
+    /// Returns the unsigned integer type with the size of a pointer (i.e., `usize`).
+    pub fn usize_ty(&self) -> LirTy {
+        match self.sess.target.data_layout.pointer_size {
+            16 => LirTy::U16,
+            32 => LirTy::U32,
+            64 => LirTy::U64,
+            size => panic!("Unsupported pointer size {}", size),
+        }
+    }
+
+    /// Returns the types of the two fields of a slice of the given element type:
+    /// the pointer to the first element and the number of elements.
+    pub fn slice_field_tys(&self, elem_ty: &LirTy) -> [LirTy; 2] {
+        [LirTy::ptr_to(elem_ty.clone()), self.usize_ty()]
+    }
+
+    /// Returns the layout of the given type. It is computed the first time that it
+    /// is queried, and cached for the next queries.
+    // It corresponds to the `layout_of` query in rustc_ty_utils/src/layout.rs
+    pub fn layout_of(&self, ty: LirTy) -> TyAndLayout<'_, LirTy> {
+        if let Some(layout) = self.layouts.get(&ty) {
+            return TyAndLayout { ty, layout };
+        }
+        let layout = LayoutCtx::new(self).compute_layout(&ty);
+        TyAndLayout {
+            layout: self.layouts.insert(ty.clone(), layout),
+            ty,
+        }
     }
 
     pub fn backend_kind(&self) -> &BackendKind {
-        &self.target.codegen_backend
+        &self.sess.target.codegen_backend
     }
 
     pub fn emit_kind(&self) -> &EmitKind {
-        &self.arguments.emit_kind
+        &self.sess.opts.emit_kind
+    }
+
+    pub fn opt_level(&self) -> OptLevel {
+        self.sess.opts.opt_level
+    }
+
+    pub fn pass_pipeline(&self) -> Option<&str> {
+        self.sess.opts.pass_pipeline.as_deref()
+    }
+
+    pub fn lto(&self) -> Lto {
+        self.sess.opts.lto
+    }
+
+    pub fn debuginfo(&self) -> DebugInfo {
+        self.sess.opts.debuginfo
+    }
+
+    pub fn dwarf_version(&self) -> Option<u32> {
+        self.sess.opts.dwarf_version
+    }
+
+    pub fn split_debuginfo(&self) -> SplitDebugInfo {
+        self.sess.opts.split_debuginfo
+    }
+
+    pub fn alias_metadata(&self) -> AliasMetadata {
+        self.sess.opts.alias_metadata
+    }
+
+    pub fn sanitizers(&self) -> SanitizerSet {
+        self.sess.opts.sanitizers
+    }
+
+    pub fn force_frame_pointers(&self) -> bool {
+        self.sess.opts.force_frame_pointers
+    }
+
+    pub fn stack_protector(&self) -> StackProtector {
+        self.sess.opts.stack_protector
+    }
+
+    pub fn bounds_checks(&self) -> bool {
+        self.sess.opts.bounds_checks
+    }
+
+    pub fn reloc_model(&self) -> Option<RelocModel> {
+        self.sess.opts.reloc_model
+    }
+
+    pub fn code_model(&self) -> Option<CodeModel> {
+        self.sess.opts.code_model
+    }
+
+    pub fn codegen_units(&self) -> usize {
+        self.sess.opts.codegen_units
+    }
+
+    pub fn inline_threshold(&self) -> usize {
+        self.sess.opts.inline_threshold
+    }
+
+    pub fn dump_lir_dir(&self) -> Option<&Path> {
+        self.sess.opts.dump_lir_dir.as_deref()
+    }
+
+    pub fn dump_dot_dir(&self) -> Option<&Path> {
+        self.sess.opts.dump_dot_dir.as_deref()
+    }
+
+    pub fn incremental_dir(&self) -> Option<&Path> {
+        self.sess.opts.incremental_dir.as_deref()
+    }
+
+    /// Feeds to the given hasher everything but the bodies that affects the code
+    /// generated for a body: the target, the options of the codegen, the ADTs, the
+    /// allocations and the drop glues. Together with `LirBody::fingerprint`, it tells whether the
+    /// code of a body may have changed (see `tidec_incremental`).
+    pub fn hash_codegen_inputs<H: Hasher>(&self, state: &mut H) {
+        self.sess.target.data_layout_string().hash(state);
+        self.sess.target.target_triple_string().hash(state);
+        self.sess.opts.emit_kind.hash(state);
+        self.sess.opts.opt_level.hash(state);
+        self.sess.opts.pass_pipeline.hash(state);
+        self.sess.opts.debuginfo.hash(state);
+        self.sess.opts.alias_metadata.hash(state);
+        self.sess.opts.sanitizers.hash(state);
+        self.sess.opts.reloc_model.hash(state);
+        self.sess.opts.code_model.hash(state);
+        self.adt_defs.hash(state);
+        self.allocs.hash(state);
+        // The drop glues are hashed in an order independent of the one of the map.
+        self.drop_glues
+            .iter()
+            .map(|drop_glue| Fingerprint::of(&drop_glue))
+            .fold(Fingerprint::ZERO, Fingerprint::combine_commutative)
+            .hash(state);
     }
 }
@@ -0,0 +1,113 @@
+//! The metadata of a unit (`<unit>.rmeta`), that is, its interface: the signatures of
+//! the functions it exports, and the ADTs they use. A unit compiled later can call
+//! these functions, which are linked from the object of the unit, once it is parsed
+//! with the metadata (see `parse::parse_lir_source_file_with_externs`).
+//!
+//! The metadata is written in the textual LIR (see `parse`), where the exported
+//! functions are declarations:
+//!
+//! ```text
+//! struct Point { i32, i32 }
+//!
+//! fn norm(_1: Point) -> i32;
+//! ```
+// It corresponds to the encoder of the crate metadata in rustc_metadata/src/rmeta/encoder.rs
+
+use std::collections::BTreeSet;
+
+use tidec_utils::index_vec::IdxVec;
+
+use crate::{
+    lir::{LirBody, LirBodyKind, LirCtx, LirItemKind, LirUnit},
+    pretty::LirPrinter,
+    syntax::{AdtId, LirTy},
+};
+
+/// The extension of the metadata files.
+pub const METADATA_EXTENSION: &str = "rmeta";
+
+/// Returns the metadata of the given unit. A function is exported if it is defined with
+/// an exported linkage (see `Linkage::is_exported`), unless it is generic or a constant.
+// TODO(bruzzone): export the bodies of the generic functions, so that the other units
+// can instantiate them, as rustc does with the optimized MIR.
+pub fn encode_metadata(lir_ctx: &LirCtx, lir_unit: &LirUnit) -> String {
+    let declarations: Vec<LirBody> = lir_unit
+        .bodies
+        .iter()
+        .filter(|lir_body| {
+            !lir_body.is_declaration()
+                && lir_body.metadata.linkage.is_exported()
+                && lir_body.generics.is_empty()
+                && matches!(
+                    lir_body.metadata.kind,
+                    LirBodyKind::Item(LirItemKind::Function)
+                )
+        })
+        .map(|lir_body| LirBody {
+            metadata: lir_body.metadata.clone(),
+            generics: lir_body.generics.clone(),
+            ret_and_args: lir_body.ret_and_args.clone(),
+            locals: IdxVec::new(),
+            basic_blocks: IdxVec::new(),
+        })
+        .collect();
+
+    // The ADTs are defined before their uses, so they are written in the order of
+    // their ids.
+    let mut adts = BTreeSet::new();
+    for declaration in &declarations {
+        for local_data in declaration.ret_and_args.iter() {
+            collect_adts(lir_ctx, &local_data.ty, &mut adts);
+        }
+        if let Some(ty) = lir_ctx.dropped_ty(declaration.metadata.def_id) {
+            collect_adts(lir_ctx, ty, &mut adts);
+        }
+    }
+
+    let printer = LirPrinter::new(lir_ctx, lir_unit);
+    let mut out = String::new();
+    for adt_id in &adts {
+        printer
+            .write_adt_def(&mut out, *adt_id)
+            .expect("Writing to a String cannot fail");
+    }
+    if !adts.is_empty() && !declarations.is_empty() {
+        out.push('\n');
+    }
+    for declaration in &declarations {
+        printer
+            .write_body(&mut out, declaration)
+            .expect("Writing to a String cannot fail");
+    }
+    out
+}
+
+/// Adds the ADTs used by the given type to `adts`, including the ones of their fields.
+fn collect_adts(lir_ctx: &LirCtx, ty: &LirTy, adts: &mut BTreeSet<AdtId>) {
+    match ty {
+        LirTy::Adt(adt_id) => {
+            if !adts.insert(*adt_id) {
+                return;
+            }
+            for variant in lir_ctx.adt_def(*adt_id).variants.iter() {
+                for field_ty in variant.fields.iter() {
+                    collect_adts(lir_ctx, field_ty, adts);
+                }
+            }
+        }
+        LirTy::Ptr(ty, _) | LirTy::Array(ty, _) | LirTy::Slice(ty) => {
+            collect_adts(lir_ctx, ty, adts)
+        }
+        LirTy::Tuple(field_tys) => {
+            for field_ty in field_tys {
+                collect_adts(lir_ctx, field_ty, adts);
+            }
+        }
+        LirTy::FnPtr(fn_sig) => {
+            for ty in std::iter::once(&fn_sig.ret).chain(&fn_sig.args) {
+                collect_adts(lir_ctx, ty, adts);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,322 @@
+use std::fmt;
+
+use super::ParseError;
+
+#[derive(Debug, Clone, PartialEq)]
+/// The kind of a token of the textual LIR.
+pub enum TokenKind {
+    /// An identifier or a keyword (e.g., `fn`, `_1`, `bb0`, `i32`).
+    Ident(String),
+    /// A numeric literal, with an optional type suffix (e.g., `7`, `7_i32`, `1.5_f64`).
+    Number {
+        digits: String,
+        suffix: Option<String>,
+    },
+    /// A string literal (e.g., `"hi"`), with its escapes resolved.
+    Str(String),
+    /// A byte string literal (e.g., `b"hi"`), with its escapes resolved.
+    ByteStr(Vec<u8>),
+    OpenParen,
+    CloseParen,
+    OpenBrace,
+    CloseBrace,
+    OpenBracket,
+    CloseBracket,
+    Comma,
+    Semi,
+    Colon,
+    Eq,
+    Dot,
+    Star,
+    Amp,
+    Bang,
+    Minus,
+    Pound,
+    Lt,
+    Gt,
+    /// The `->` arrow.
+    Arrow,
+    /// The `...` of the variadic signatures.
+    Ellipsis,
+    /// The end of the input.
+    Eof,
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let punct = match self {
+            TokenKind::Ident(ident) => return write!(f, "`{}`", ident),
+            TokenKind::Number { digits, suffix } => {
+                return match suffix {
+                    Some(suffix) => write!(f, "`{}_{}`", digits, suffix),
+                    None => write!(f, "`{}`", digits),
+                };
+            }
+            TokenKind::Str(s) => return write!(f, "{:?}", s),
+            TokenKind::ByteStr(_) => return write!(f, "byte string"),
+            TokenKind::Eof => return write!(f, "end of input"),
+            TokenKind::OpenParen => "(",
+            TokenKind::CloseParen => ")",
+            TokenKind::OpenBrace => "{",
+            TokenKind::CloseBrace => "}",
+            TokenKind::OpenBracket => "[",
+            TokenKind::CloseBracket => "]",
+            TokenKind::Comma => ",",
+            TokenKind::Semi => ";",
+            TokenKind::Colon => ":",
+            TokenKind::Eq => "=",
+            TokenKind::Dot => ".",
+            TokenKind::Star => "*",
+            TokenKind::Amp => "&",
+            TokenKind::Bang => "!",
+            TokenKind::Minus => "-",
+            TokenKind::Pound => "#",
+            TokenKind::Lt => "<",
+            TokenKind::Gt => ">",
+            TokenKind::Arrow => "->",
+            TokenKind::Ellipsis => "...",
+        };
+        write!(f, "`{}`", punct)
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A token, with the position where it starts and its byte range in the source.
+pub struct Token {
+    pub kind: TokenKind,
+    pub line: usize,
+    pub column: usize,
+    pub lo: u32,
+    pub hi: u32,
+}
+
+/// Splits the given source into tokens. Whitespaces and line comments (`// ...`)
+/// are skipped. The last token is always `TokenKind::Eof`.
+pub fn tokenize(src: &str) -> Result<Vec<Token>, ParseError> {
+    Lexer {
+        chars: src.chars().collect(),
+        pos: 0,
+        byte_pos: 0,
+        line: 1,
+        column: 1,
+    }
+    .tokenize()
+}
+
+struct Lexer {
+    chars: Vec<char>,
+    pos: usize,
+    /// The byte offset of `pos` in the source.
+    byte_pos: u32,
+    line: usize,
+    column: usize,
+}
+
+impl Lexer {
+    fn tokenize(mut self) -> Result<Vec<Token>, ParseError> {
+        let mut tokens: Vec<Token> = vec![];
+        loop {
+            self.skip_trivia();
+            let (line, column, lo) = (self.line, self.column, self.byte_pos);
+            let Some(c) = self.peek(0) else {
+                tokens.push(Token {
+                    kind: TokenKind::Eof,
+                    line,
+                    column,
+                    lo,
+                    hi: lo,
+                });
+                return Ok(tokens);
+            };
+
+            let kind = match c {
+                '(' => self.punct(TokenKind::OpenParen),
+                ')' => self.punct(TokenKind::CloseParen),
+                '{' => self.punct(TokenKind::OpenBrace),
+                '}' => self.punct(TokenKind::CloseBrace),
+                '[' => self.punct(TokenKind::OpenBracket),
+                ']' => self.punct(TokenKind::CloseBracket),
+                ',' => self.punct(TokenKind::Comma),
+                ';' => self.punct(TokenKind::Semi),
+                ':' => self.punct(TokenKind::Colon),
+                '=' => self.punct(TokenKind::Eq),
+                '.' if self.peek(1) == Some('.') && self.peek(2) == Some('.') => {
+                    self.bump();
+                    self.bump();
+                    self.punct(TokenKind::Ellipsis)
+                }
+                '.' => self.punct(TokenKind::Dot),
+                '*' => self.punct(TokenKind::Star),
+                '&' => self.punct(TokenKind::Amp),
+                '!' => self.punct(TokenKind::Bang),
+                '#' => self.punct(TokenKind::Pound),
+                '<' => self.punct(TokenKind::Lt),
+                '>' => self.punct(TokenKind::Gt),
+                '-' if self.peek(1) == Some('>') => {
+                    self.bump();
+                    self.punct(TokenKind::Arrow)
+                }
+                '-' => self.punct(TokenKind::Minus),
+                '"' => {
+                    let bytes = self.string(line, column)?;
+                    TokenKind::Str(String::from_utf8(bytes).map_err(|_| {
+                        ParseError::new(line, column, "Invalid UTF-8 in a string literal")
+                    })?)
+                }
+                'b' if self.peek(1) == Some('"') => {
+                    self.bump();
+                    TokenKind::ByteStr(self.string(line, column)?)
+                }
+                c if c.is_ascii_digit() => {
+                    // The fractional part is not lexed after a dot, so that the
+                    // fields in `_1.0.1` are not mistaken for a float.
+                    let after_dot = matches!(
+                        tokens.last(),
+                        Some(Token {
+                            kind: TokenKind::Dot,
+                            ..
+                        })
+                    );
+                    self.number(!after_dot)
+                }
+                c if c.is_alphabetic() || c == '_' => TokenKind::Ident(self.ident()),
+                c => {
+                    return Err(ParseError::new(
+                        line,
+                        column,
+                        format!("Unexpected character `{}`", c),
+                    ));
+                }
+            };
+            tokens.push(Token {
+                kind,
+                line,
+                column,
+                lo,
+                hi: self.byte_pos,
+            });
+        }
+    }
+
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek(0)?;
+        self.pos += 1;
+        self.byte_pos += c.len_utf8() as u32;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn punct(&mut self, kind: TokenKind) -> TokenKind {
+        self.bump();
+        kind
+    }
+
+    /// Skips whitespaces and line comments.
+    fn skip_trivia(&mut self) {
+        loop {
+            match self.peek(0) {
+                Some(c) if c.is_whitespace() => {
+                    self.bump();
+                }
+                Some('/') if self.peek(1) == Some('/') => {
+                    while self.peek(0).is_some_and(|c| c != '\n') {
+                        self.bump();
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn ident(&mut self) -> String {
+        let mut ident = String::new();
+        while let Some(c) = self.peek(0).filter(|c| c.is_alphanumeric() || *c == '_') {
+            ident.push(c);
+            self.bump();
+        }
+        ident
+    }
+
+    /// Lexes a number. If `fractional` is `true`, a fractional part (e.g., `.5`)
+    /// is lexed as part of the number.
+    fn number(&mut self, fractional: bool) -> TokenKind {
+        let mut digits = String::new();
+        while let Some(c) = self.peek(0).filter(|c| c.is_ascii_digit()) {
+            digits.push(c);
+            self.bump();
+        }
+        if fractional
+            && self.peek(0) == Some('.')
+            && self.peek(1).is_some_and(|c| c.is_ascii_digit())
+        {
+            digits.push('.');
+            self.bump();
+            while let Some(c) = self.peek(0).filter(|c| c.is_ascii_digit()) {
+                digits.push(c);
+                self.bump();
+            }
+        }
+        let suffix = if self.peek(0) == Some('_') && self.peek(1).is_some_and(char::is_alphabetic) {
+            self.bump();
+            Some(self.ident())
+        } else {
+            None
+        };
+        TokenKind::Number { digits, suffix }
+    }
+
+    /// Lexes a string literal, starting at the opening quote, and returns its bytes.
+    /// Characters are encoded in UTF-8, while `\xNN` escapes are raw bytes.
+    /// The token starts at `line` and `column`.
+    fn string(&mut self, line: usize, column: usize) -> Result<Vec<u8>, ParseError> {
+        let unterminated = || ParseError::new(line, column, "Unterminated string literal");
+        self.bump();
+        let mut bytes = vec![];
+        loop {
+            let c = match self.bump().ok_or_else(unterminated)? {
+                '"' => return Ok(bytes),
+                '\\' => match self.bump().ok_or_else(unterminated)? {
+                    'n' => '\n',
+                    'r' => '\r',
+                    't' => '\t',
+                    '0' => '\0',
+                    '\\' => '\\',
+                    '"' => '"',
+                    'x' => {
+                        let hex = [self.bump(), self.bump()]
+                            .into_iter()
+                            .collect::<Option<String>>()
+                            .ok_or_else(unterminated)?;
+                        let byte = u8::from_str_radix(&hex, 16).map_err(|_| {
+                            ParseError::new(
+                                self.line,
+                                self.column,
+                                format!("Invalid escape `\\x{}`", hex),
+                            )
+                        })?;
+                        bytes.push(byte);
+                        continue;
+                    }
+                    c => {
+                        return Err(ParseError::new(
+                            self.line,
+                            self.column,
+                            format!("Unknown escape `\\{}`", c),
+                        ));
+                    }
+                },
+                c => c,
+            };
+            bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+        }
+    }
+}
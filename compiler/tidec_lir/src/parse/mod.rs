@@ -0,0 +1,200 @@
+//! A parser for the textual representation of the LIR.
+//!
+//! It allows to write LIR programs in `.lir` files, instead of building the
+//! `LirBody`s by hand. The syntax is close to the one of the MIR dumps of rustc:
+//!
+//! ```text
+//! // A struct has its field types between braces. An enum has variants with
+//! // (optional) fields between parentheses. The fields are reordered to minimize
+//! // the padding, unless the struct is `#[repr(C)]`.
+//! #[repr(C)]
+//! struct Point { i32, i32 }
+//! enum Shape { Empty, Dot(Point) }
+//!
+//! // The arguments are the locals right after the return local `_0`. A local can be
+//! // named, e.g., in the emitted code, with `as`.
+//! fn add(_1 as lhs: i32, _2 as rhs: i32) -> i32 {
+//!     bb0: {
+//!         _0 = Add(copy _1, copy _2);
+//!         return;
+//!     }
+//! }
+//!
+//! fn main() -> i32 {
+//!     let mut _1: Point;
+//!     let _2: &str;
+//!
+//!     bb0: {
+//!         _1.0 = const 3_i32;
+//!         _1.1 = const 4_i32;
+//!         _2 = const "hi";
+//!         _0 = add(copy _1.0, copy _1.1) -> bb1;
+//!     }
+//!
+//!     bb1: {
+//!         return;
+//!     }
+//! }
+//! ```
+//!
+//! The other locals are declared with `let`, and both locals and basic blocks must be
+//! numbered in order. Functions can be referenced before their definition, while ADTs
+//! must be defined before their use.
+//!
+//! - Types: `bool`, `i8`..`i128`, `u8`..`u128`, `f16`..`f128`, `*T`, `[T; N]`, `()`,
+//!   `(T,)`, `(T, U)`, `fn(T, U) -> R`, `&[T]`, `&str`, `!` and the names of the ADTs. A
+//!   pointer into a non-default address space is written `*addrspace(N) T`.
+//! - Places: `_1`, `(*_1)`, `_1.0`, `_1[_2]` and `(_1 as Variant)`.
+//! - Operands: `copy P`, `move P` and `const C`, where a constant is a suffixed number
+//!   (`7_i32`, `-1_i64`, `1.5_f64`), `true`, `false`, `()`, a string (`"hi"`), a byte
+//!   string (`b"hi"`), an array (`[1_u8, 2_u8]`) or the name of a function.
+//! - RValues: an operand, `BinOp(A, B)` (e.g., `Add`, `Lt`), `Not(A)`, `Neg(A)`, `&P`,
+//!   `A as T (CastKind)`, `discriminant(P)` and `intrinsic::name(A, ...)` (e.g.,
+//!   `intrinsic::ctpop(A)`).
+//! - Statements: `P = RValue;`, `discriminant(P) = N;` and inline assembly, e.g.,
+//!   `asm!("mov {0}, {1}", out(reg) _1, in("eax") copy _2, clobbers("rcx"), options(nomem));`.
+//!   The operands are `in(R) A`, `out(R) P` and `inout(R) A => P`, where the register
+//!   `R` is a class (e.g., `reg`) or an explicit register (e.g., `"eax"`), and `P` can be
+//!   `_` to discard the output. The clobbers and the options are optional.
+//! - Terminators: `goto -> bbN;`, `return;`, `unreachable;`, `P = f(A, B) -> bbN;`
+//!   (a direct call, or an indirect one if `f` is an operand), `drop(P) -> bbN;` and
+//!   `assert(A, AssertKind) -> bbN;`. The condition of an assert is expected to be
+//!   `true`, unless it is negated (`assert(!A, ...)`), and it can be hinted as `likely`
+//!   or `unlikely` to hold (`assert(A, AssertKind, likely)`). Its unwind action is given with
+//!   `-> [success: bbN, unwind: continue | unreachable | bbM]`, and the one of a call
+//!   or a drop with `-> [return: bbN, unwind: ...]`. A call whose destination is of
+//!   type `!` never returns, so it has no target: `P = f(A);` or `P = f(A) -> [unwind: ...];`.
+//! - Cleanup blocks: `bbN (cleanup): { ... }`. They are only reached by unwinding
+//!   (i.e., they are the `unwind: bbM` of a terminator), and they can end with
+//!   `resume;` to continue unwinding in the caller.
+//!
+//! A function without a body, e.g., `fn puts(_1: *i8) -> i32;`, is a declaration of a
+//! function defined elsewhere (e.g., in the C library), which can be called as the
+//! other ones. A declaration can be variadic, e.g., `fn printf(_1: *i8, ...) -> i32;`,
+//! and so can be a function pointer type, e.g., `fn(*i8, ...) -> i32`.
+//!
+//! The functions of the units compiled before (e.g., of a library) are called through
+//! their metadata (see `crate::metadata`), which is parsed before the unit with
+//! `parse_lir_source_file_with_externs`. Then, the unit can use the ADTs and call the
+//! functions of the metadata, which are its imports (see `LirUnit::imports`).
+//!
+//! A function can be generic over some types, e.g., `fn id<T>(_1: T) -> T`, whose
+//! names can be used as types in its body. A generic function can only be called
+//! directly with all its type arguments, e.g., `_0 = id::<i32>(copy _1) -> bb1;`.
+//!
+//! A constant is a function without arguments marked with `const`, e.g.,
+//! `const fn LEN() -> u64 { ... }`, whose body computes its value at compile time
+//! (see `tidec_const_eval`).
+//!
+//! A function can be marked with the attributes of `FnAttribute::NAMES`:
+//! `#[inline]` (so that it is considered by the inliner, see `transform::Inline`),
+//! `#[inline(always)]`, `#[inline(never)]`, `#[cold]`, `#[naked]`, `#[nounwind]` and
+//! `#[optsize]`. At most one of the inline attributes can be given. A function with
+//! cleanup blocks must also be given its personality function, e.g.,
+//! `#[personality("__gxx_personality_v0")]`. A function `fn(_1: *T)` can be registered
+//! as the drop glue of `T` with `#[drop_glue(T)]`. The rest of the metadata of the
+//! bodies is not part of the syntax yet: every body is an external C function with
+//! default visibility.
+// TODO(bruzzone): parse the rest of the metadata of the bodies (e.g., as attributes).
+
+mod lexer;
+mod parser;
+
+use std::fmt;
+use std::path::Path;
+
+use crate::lir::{LirCtx, LirUnit};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// An error found while parsing the textual LIR, with the position where it occurs.
+pub struct ParseError {
+    /// The line of the error, starting from 1.
+    pub line: usize,
+    /// The column of the error, starting from 1.
+    pub column: usize,
+    /// The description of the error.
+    pub message: String,
+}
+
+impl ParseError {
+    pub fn new(line: usize, column: usize, message: impl Into<String>) -> Self {
+        ParseError {
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses the given source into a LIR unit named `unit_name`. The source is added to
+/// the source map of the `LirCtx` as the file `<unit_name>.lir`.
+///
+/// The ADTs and the allocations (e.g., of the string constants) of the unit are
+/// added to the given `LirCtx`.
+pub fn parse_lir_unit(
+    lir_ctx: &mut LirCtx,
+    unit_name: &str,
+    src: &str,
+) -> Result<LirUnit, ParseError> {
+    parse_lir_source_file(
+        lir_ctx,
+        unit_name,
+        format!("{}.lir", unit_name),
+        src.to_string(),
+    )
+}
+
+/// Parses the given source file (e.g., named after its path) into a LIR unit named
+/// `unit_name`, as `parse_lir_unit`. The spans of the unit refer to the file, which is
+/// added to the source map of the `LirCtx`.
+pub fn parse_lir_source_file(
+    lir_ctx: &mut LirCtx,
+    unit_name: &str,
+    file_name: String,
+    src: String,
+) -> Result<LirUnit, ParseError> {
+    parse_lir_source_file_with_externs(lir_ctx, unit_name, file_name, src, vec![])
+}
+
+/// Parses the given source file into a LIR unit, as `parse_lir_source_file`, after the
+/// given metadata files (see `crate::metadata`), each with its name and its source.
+/// The functions of a metadata file are imported from the unit named after the file
+/// (e.g., `lib` for `path/lib.rmeta`). The message of an error in a metadata file
+/// names the file.
+pub fn parse_lir_source_file_with_externs(
+    lir_ctx: &mut LirCtx,
+    unit_name: &str,
+    file_name: String,
+    src: String,
+    externs: Vec<(String, String)>,
+) -> Result<LirUnit, ParseError> {
+    let mut extern_files = vec![];
+    for (extern_name, extern_src) in externs {
+        let extern_unit_name = Path::new(&extern_name)
+            .file_stem()
+            .map_or(extern_name.clone(), |stem| {
+                stem.to_string_lossy().into_owned()
+            });
+        let tokens = lexer::tokenize(&extern_src).map_err(|err| ParseError {
+            message: format!("{} (in {})", err.message, extern_name),
+            ..err
+        })?;
+        let file = lir_ctx
+            .source_map_mut()
+            .new_source_file(extern_name, extern_src);
+        extern_files.push((extern_unit_name, file, tokens));
+    }
+    let tokens = lexer::tokenize(&src)?;
+    let file = lir_ctx.source_map_mut().new_source_file(file_name, src);
+    let mut parser = parser::Parser::new(lir_ctx, file, tokens);
+    parser.parse_externs(extern_files)?;
+    parser.parse_unit(unit_name)
+}
@@ -0,0 +1,1872 @@
+use std::collections::{BTreeMap, HashMap};
+
+use tidec_abi::{
+    layout::{ReprOptions, VariantIdx},
+    target::AddressSpace,
+};
+use tidec_span::{FileId, Span};
+use tidec_utils::{idx::Idx, index_vec::IdxVec};
+
+use super::{
+    lexer::{Token, TokenKind},
+    ParseError,
+};
+use crate::{
+    basic_blocks::{BasicBlock, BasicBlockData, BasicBlockKind, ENTRY_BLOCK},
+    lir::{
+        CallConv, DefId, FnAttribute, Linkage, LirBody, LirBodyKind, LirBodyMetadata, LirCtx,
+        LirImport, LirItemKind, LirUnit, LirUnitMetadata, Symbol, UnnamedAddress, Visibility,
+    },
+    syntax::{
+        AdtDef, AdtId, AdtKind, AssertKind, BinOp, BranchHint, Callee, CastKind, ConstOperand,
+        ConstScalar, ConstValue, FieldIdx, FnSig, GenericArgs, Generics, InlineAsm,
+        InlineAsmOperand, InlineAsmOptions, InlineAsmRegOrRegClass, Intrinsic, LirTy, Local,
+        LocalData, Operand, Place, PlaceTy, Projection, RValue, RawScalarValue, Statement,
+        StatementKind, Terminator, TerminatorKind, UnOp, UnwindAction, VariantDef, RETURN_LOCAL,
+    },
+};
+
+type PResult<T> = Result<T, ParseError>;
+
+/// A function whose header has been parsed, but whose body has not.
+struct FnHeader {
+    name: String,
+    def_id: DefId,
+    /// `LirItemKind::Const` for a `const fn`, and `LirItemKind::Function` otherwise.
+    kind: LirItemKind,
+    /// The attributes of the function (e.g., `#[inline]`).
+    attrs: FnAttribute,
+    /// Whether the signature ends with `...`.
+    is_variadic: bool,
+    /// The personality function, given by `#[personality("name")]`.
+    personality: Option<String>,
+    generics: Generics,
+    ret_and_args: IdxVec<Local, LocalData>,
+    /// The index of the `fn` keyword.
+    fn_pos: usize,
+    /// The index of the token that opens the body, or of the `;` of a declaration.
+    body_start: usize,
+}
+
+#[derive(Clone, PartialEq, Eq)]
+/// An attribute of an item.
+enum Attr {
+    /// An attribute of a function (e.g., `#[inline]` or `#[cold]`).
+    Fn(FnAttribute),
+    /// `#[personality("name")]`, allowed on functions.
+    Personality(String),
+    /// `#[drop_glue(T)]`, allowed on the functions that drop a `T` (see
+    /// `LirCtx::register_drop_glue`).
+    DropGlue(LirTy),
+    /// `#[repr(C)]`, allowed on structs.
+    ReprC,
+}
+
+/// An item of a basic block, whose span is set once its `;` is parsed.
+enum BlockItem {
+    Statement(StatementKind),
+    Terminator(TerminatorKind),
+}
+
+/// A recursive-descent parser over the tokens of a unit.
+///
+/// The unit is parsed in two phases: first the ADTs and the headers of the functions,
+/// skipping the bodies, and then the bodies. This way, a body can reference the
+/// functions defined after it.
+pub struct Parser<'a> {
+    lir_ctx: &'a mut LirCtx,
+    /// The source file of the tokens, in the source map of `lir_ctx`.
+    file: FileId,
+    tokens: Vec<Token>,
+    pos: usize,
+    /// The ADTs defined so far, by name.
+    adts: HashMap<String, AdtId>,
+    /// The functions of the unit, by the symbol of their name, with the number of
+    /// their generic parameters.
+    fns: HashMap<Symbol, (DefId, FnSig, usize)>,
+    /// The generic parameters of the function being parsed.
+    generics: Generics,
+    /// The locals of the body being parsed, including the return value and the arguments.
+    locals: IdxVec<Local, LocalData>,
+    /// The basic blocks referenced by the body being parsed, with the token that
+    /// references them and whether they are referenced by an unwind edge. They are
+    /// checked once all the blocks are known.
+    block_refs: Vec<(BasicBlock, usize, bool)>,
+    /// The functions declared by the metadata files parsed before the unit (see
+    /// `parse_externs`), which are the imports of the unit.
+    imports: BTreeMap<DefId, LirImport>,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(lir_ctx: &'a mut LirCtx, file: FileId, tokens: Vec<Token>) -> Self {
+        Parser {
+            lir_ctx,
+            file,
+            tokens,
+            pos: 0,
+            adts: HashMap::new(),
+            fns: HashMap::new(),
+            generics: Generics::default(),
+            locals: IdxVec::new(),
+            block_refs: vec![],
+            imports: BTreeMap::new(),
+        }
+    }
+
+    pub fn parse_unit(mut self, unit_name: &str) -> PResult<LirUnit> {
+        let bodies = self.parse_items()?;
+        Ok(LirUnit {
+            metadata: LirUnitMetadata {
+                unit_name: unit_name.to_string(),
+            },
+            bodies: IdxVec::from_raw(bodies),
+            imports: std::mem::take(&mut self.imports),
+        })
+    }
+
+    /// Parses the given metadata files (see `crate::metadata`), each with the name of
+    /// its unit and its tokens, before the unit. Their ADTs and functions can be used
+    /// by the unit, which imports the functions (see `LirUnit::imports`). The message
+    /// of an error names the file.
+    pub fn parse_externs(&mut self, externs: Vec<(String, FileId, Vec<Token>)>) -> PResult<()> {
+        let unit_file = self.file;
+        let unit_tokens = std::mem::take(&mut self.tokens);
+        for (extern_unit_name, file, tokens) in externs {
+            self.file = file;
+            self.tokens = tokens;
+            self.pos = 0;
+            let in_file = |err: ParseError, lir_ctx: &LirCtx| ParseError {
+                message: format!(
+                    "{} (in {})",
+                    err.message,
+                    lir_ctx.source_map().file(file).name
+                ),
+                ..err
+            };
+            let bodies = self
+                .parse_items()
+                .map_err(|err| in_file(err, self.lir_ctx))?;
+            if let Some(lir_body) = bodies.iter().find(|lir_body| !lir_body.is_declaration()) {
+                let (line, column) = self
+                    .lir_ctx
+                    .source_map()
+                    .lookup(lir_body.metadata.span)
+                    .map_or((1, 1), |loc| (loc.line as usize, loc.col as usize));
+                let err = ParseError::new(
+                    line,
+                    column,
+                    format!(
+                        "The metadata defines the body of `{}`",
+                        lir_body.metadata.name
+                    ),
+                );
+                return Err(in_file(err, self.lir_ctx));
+            }
+            for declaration in bodies {
+                let import = LirImport {
+                    unit_name: extern_unit_name.clone(),
+                    declaration,
+                };
+                self.imports
+                    .insert(import.declaration.metadata.def_id, import);
+            }
+        }
+        self.file = unit_file;
+        self.tokens = unit_tokens;
+        self.pos = 0;
+        Ok(())
+    }
+
+    /// Parses the items of the tokens, and returns the bodies of the functions.
+    fn parse_items(&mut self) -> PResult<Vec<LirBody>> {
+        let mut headers = vec![];
+        while *self.peek() != TokenKind::Eof {
+            let mut attrs = vec![];
+            while let Some(attr) = self.parse_attr()? {
+                attrs.push(attr);
+            }
+            let item_pos = self.pos;
+            let mut item = self.expect_ident()?;
+            let kind = if item == "const" {
+                self.expect_keyword("fn")?;
+                item = "fn".to_string();
+                LirItemKind::Const
+            } else {
+                LirItemKind::Function
+            };
+            let mut fn_attrs = FnAttribute::default();
+            let mut personality = None;
+            let mut drop_glue = None;
+            let mut repr = ReprOptions::default();
+            for (attr_pos, attr) in attrs {
+                match (item.as_str(), attr) {
+                    ("fn", Attr::Fn(attr)) => {
+                        let inline_attrs = FnAttribute::INLINE
+                            | FnAttribute::ALWAYS_INLINE
+                            | FnAttribute::NO_INLINE;
+                        if inline_attrs.contains(attr) && fn_attrs.intersects(inline_attrs) {
+                            return Err(self.error_at(attr_pos, "Conflicting inline attributes"));
+                        }
+                        fn_attrs.insert(attr);
+                    }
+                    ("fn", Attr::Personality(name)) => {
+                        if personality.replace(name).is_some() {
+                            return Err(self.error_at(attr_pos, "Duplicate personality attribute"));
+                        }
+                    }
+                    ("fn", Attr::DropGlue(ty)) => {
+                        if drop_glue.replace((attr_pos, ty)).is_some() {
+                            return Err(self.error_at(attr_pos, "Duplicate drop glue attribute"));
+                        }
+                    }
+                    ("struct", Attr::ReprC) => repr.c = true,
+                    (_, Attr::Fn(attr)) => {
+                        let name = attr.names().next().unwrap_or_default();
+                        return Err(self.error_at(
+                            item_pos,
+                            format!("`#[{}]` is only allowed on functions", name),
+                        ));
+                    }
+                    (_, Attr::Personality(_)) => {
+                        return Err(self.error_at(
+                            item_pos,
+                            "`#[personality(...)]` is only allowed on functions",
+                        ));
+                    }
+                    (_, Attr::DropGlue(_)) => {
+                        return Err(self.error_at(
+                            item_pos,
+                            "`#[drop_glue(...)]` is only allowed on functions",
+                        ));
+                    }
+                    (_, Attr::ReprC) => {
+                        return Err(
+                            self.error_at(item_pos, "`#[repr(C)]` is only allowed on structs")
+                        );
+                    }
+                }
+            }
+            match item.as_str() {
+                "fn" => {
+                    let header = self.parse_fn_header(kind, fn_attrs, personality)?;
+                    if let Some((attr_pos, ty)) = drop_glue {
+                        self.register_drop_glue(attr_pos, ty, &header)?;
+                    }
+                    headers.push(header);
+                }
+                "struct" => self.parse_struct(repr)?,
+                "enum" => self.parse_enum()?,
+                item => {
+                    return Err(self.error_at(
+                        self.pos - 1,
+                        format!(
+                            "Expected an item (`struct`, `enum`, `fn` or `const fn`), found `{}`",
+                            item
+                        ),
+                    ));
+                }
+            }
+        }
+
+        headers
+            .into_iter()
+            .map(|header| self.parse_fn_body(header))
+            .collect()
+    }
+
+    ////////// Items //////////
+
+    /// Parses `struct Name { T, U }`, after the `struct` keyword.
+    fn parse_struct(&mut self, repr: ReprOptions) -> PResult<()> {
+        let name_pos = self.pos;
+        let name = self.expect_ident()?;
+        self.expect(TokenKind::OpenBrace)?;
+        let fields = self.parse_comma_separated(TokenKind::CloseBrace, Self::parse_ty)?;
+        let variant = VariantDef {
+            name: name.clone(),
+            fields: IdxVec::from_raw(fields),
+        };
+        self.define_adt(name_pos, name, AdtKind::Struct, repr, vec![variant])
+    }
+
+    /// Parses `enum Name { A, B(T, U) }`, after the `enum` keyword.
+    fn parse_enum(&mut self) -> PResult<()> {
+        let name_pos = self.pos;
+        let name = self.expect_ident()?;
+        self.expect(TokenKind::OpenBrace)?;
+        let variants = self.parse_comma_separated(TokenKind::CloseBrace, |this| {
+            let name = this.expect_ident()?;
+            let fields = if this.eat(&TokenKind::OpenParen) {
+                this.parse_comma_separated(TokenKind::CloseParen, Self::parse_ty)?
+            } else {
+                vec![]
+            };
+            Ok(VariantDef {
+                name,
+                fields: IdxVec::from_raw(fields),
+            })
+        })?;
+        self.define_adt(
+            name_pos,
+            name,
+            AdtKind::Enum,
+            ReprOptions::default(),
+            variants,
+        )
+    }
+
+    fn define_adt(
+        &mut self,
+        name_pos: usize,
+        name: String,
+        kind: AdtKind,
+        repr: ReprOptions,
+        variants: Vec<VariantDef>,
+    ) -> PResult<()> {
+        if self.adts.contains_key(&name) {
+            return Err(self.error_at(name_pos, format!("ADT `{}` is defined twice", name)));
+        }
+        let adt_id = self.lir_ctx.define_adt(AdtDef {
+            name: name.clone(),
+            kind,
+            repr,
+            variants: IdxVec::from_raw(variants),
+        });
+        self.adts.insert(name, adt_id);
+        Ok(())
+    }
+
+    /// Parses an optional attribute, e.g., `#[inline]` or `#[repr(C)]`, and returns it
+    /// with the index of its name. The attributes of the functions are listed in
+    /// `FnAttribute::NAMES`.
+    fn parse_attr(&mut self) -> PResult<Option<(usize, Attr)>> {
+        if !self.eat(&TokenKind::Pound) {
+            return Ok(None);
+        }
+        self.expect(TokenKind::OpenBracket)?;
+        let attr_pos = self.pos;
+        let attr = match self.expect_ident()?.as_str() {
+            "inline" if self.eat(&TokenKind::OpenParen) => {
+                let hint_pos = self.pos;
+                let attr = match self.expect_ident()?.as_str() {
+                    "always" => FnAttribute::ALWAYS_INLINE,
+                    "never" => FnAttribute::NO_INLINE,
+                    hint => {
+                        return Err(
+                            self.error_at(hint_pos, format!("Unknown inline hint `{}`", hint))
+                        );
+                    }
+                };
+                self.expect(TokenKind::CloseParen)?;
+                Attr::Fn(attr)
+            }
+            "inline" => Attr::Fn(FnAttribute::INLINE),
+            "cold" => Attr::Fn(FnAttribute::COLD),
+            "naked" => Attr::Fn(FnAttribute::NAKED),
+            "nounwind" => Attr::Fn(FnAttribute::NO_UNWIND),
+            "optsize" => Attr::Fn(FnAttribute::OPT_SIZE),
+            "personality" => {
+                self.expect(TokenKind::OpenParen)?;
+                let name = self.expect_str()?;
+                self.expect(TokenKind::CloseParen)?;
+                Attr::Personality(name)
+            }
+            "drop_glue" => {
+                self.expect(TokenKind::OpenParen)?;
+                let ty = self.parse_ty()?;
+                self.expect(TokenKind::CloseParen)?;
+                Attr::DropGlue(ty)
+            }
+            "repr" => {
+                self.expect(TokenKind::OpenParen)?;
+                let repr_pos = self.pos;
+                let repr = self.expect_ident()?;
+                if repr != "C" {
+                    return Err(self.error_at(repr_pos, format!("Unknown repr `{}`", repr)));
+                }
+                self.expect(TokenKind::CloseParen)?;
+                Attr::ReprC
+            }
+            attr => {
+                return Err(self.error_at(attr_pos, format!("Unknown attribute `{}`", attr)));
+            }
+        };
+        self.expect(TokenKind::CloseBracket)?;
+        Ok(Some((attr_pos, attr)))
+    }
+
+    /// Registers the function of the given header as the drop glue of `ty`, given by
+    /// the attribute at `attr_pos`. Its signature must be `fn(*ty)`.
+    fn register_drop_glue(&mut self, attr_pos: usize, ty: LirTy, header: &FnHeader) -> PResult<()> {
+        let expected_args = [LirTy::ptr_to(ty.clone())];
+        let args = header.ret_and_args.raw[1..]
+            .iter()
+            .map(|local_data| &local_data.ty);
+        if !header.generics.is_empty()
+            || header.ret_and_args[RETURN_LOCAL].ty != LirTy::unit()
+            || !args.eq(expected_args.iter())
+        {
+            return Err(self.error_at(
+                header.fn_pos,
+                format!(
+                    "The drop glue `{}` of {:?} must be a non-generic `fn(*{:?})`",
+                    header.name, ty, ty
+                ),
+            ));
+        }
+        if self.lir_ctx.drop_glue(&ty).is_some() {
+            return Err(self.error_at(attr_pos, format!("The type {:?} has two drop glues", ty)));
+        }
+        self.lir_ctx.register_drop_glue(ty, header.def_id);
+        Ok(())
+    }
+
+    /// Returns the function with the given name, if it is declared.
+    fn lookup_fn(&self, name: &str) -> Option<&(DefId, FnSig, usize)> {
+        self.fns.get(&self.lir_ctx.lookup_symbol(name)?)
+    }
+
+    /// Parses `fn name<T, U>(_1: T, mut _2: U) -> R`, after the `fn` keyword, and skips
+    /// the body. The generic parameters are optional. A declaration has a `;` instead
+    /// of the body, and it cannot be generic. A constant (i.e., a `const fn`) must have
+    /// a body, and no arguments nor generic parameters.
+    fn parse_fn_header(
+        &mut self,
+        kind: LirItemKind,
+        attrs: FnAttribute,
+        personality: Option<String>,
+    ) -> PResult<FnHeader> {
+        let fn_pos = self.pos - 1;
+        let name_pos = self.pos;
+        let name = self.expect_ident()?;
+        if self.lookup_fn(&name).is_some() {
+            return Err(self.error_at(name_pos, format!("Function `{}` is defined twice", name)));
+        }
+        let params = if self.eat(&TokenKind::Lt) {
+            self.parse_comma_separated(TokenKind::Gt, |this| this.expect_ident())?
+        } else {
+            vec![]
+        };
+        self.generics = Generics { params };
+
+        self.expect(TokenKind::OpenParen)?;
+        let (args, is_variadic) = self.parse_sig_args(|this| {
+            let (local_pos, mutable, local, name) = this.parse_local_decl()?;
+            if local.idx() == 0 {
+                return Err(this.error_at(local_pos, "The return local `_0` is not an argument"));
+            }
+            this.expect(TokenKind::Colon)?;
+            Ok((
+                local_pos,
+                local,
+                LocalData {
+                    ty: this.parse_ty()?,
+                    mutable,
+                    name,
+                },
+            ))
+        })?;
+        let ret = if self.eat(&TokenKind::Arrow) {
+            self.parse_ty()?
+        } else {
+            LirTy::unit()
+        };
+
+        let mut ret_and_args = IdxVec::new();
+        ret_and_args.push(LocalData {
+            ty: ret.clone(),
+            mutable: false,
+            name: None,
+        });
+        for (local_pos, local, local_data) in args {
+            if local != ret_and_args.next_index() {
+                return Err(self.error_at(
+                    local_pos,
+                    format!("Expected the local `_{}`", ret_and_args.next_index().idx()),
+                ));
+            }
+            ret_and_args.push(local_data);
+        }
+
+        let def_id = DefId(self.fns.len());
+        let sig = FnSig {
+            ret,
+            args: ret_and_args
+                .iter()
+                .skip(1)
+                .map(|local_data| local_data.ty.clone())
+                .collect(),
+            is_variadic,
+        };
+        let generics = std::mem::take(&mut self.generics);
+        let symbol = self.lir_ctx.intern_symbol(&name);
+        self.fns
+            .insert(symbol, (def_id, sig, generics.params.len()));
+
+        let body_start = self.pos;
+        let is_declaration = self.eat(&TokenKind::Semi);
+        if is_declaration {
+            if !generics.is_empty() {
+                return Err(self.error_at(
+                    fn_pos,
+                    format!("The declaration of `{}` cannot be generic", name),
+                ));
+            }
+        } else {
+            if is_variadic {
+                return Err(self.error_at(
+                    fn_pos,
+                    format!(
+                        "Only a declaration can be variadic, but `{}` has a body",
+                        name
+                    ),
+                ));
+            }
+            self.skip_braced()?;
+        }
+        if matches!(kind, LirItemKind::Const)
+            && (is_declaration || !generics.is_empty() || ret_and_args.len() > 1)
+        {
+            return Err(self.error_at(
+                fn_pos,
+                format!(
+                    "The constant `{}` must have a body, and no arguments nor generic parameters",
+                    name
+                ),
+            ));
+        }
+        Ok(FnHeader {
+            name,
+            def_id,
+            kind,
+            attrs,
+            is_variadic,
+            personality,
+            generics,
+            ret_and_args,
+            fn_pos,
+            body_start,
+        })
+    }
+
+    /// Skips a block delimited by braces, including the nested ones.
+    fn skip_braced(&mut self) -> PResult<()> {
+        self.expect(TokenKind::OpenBrace)?;
+        let mut depth = 1;
+        while depth > 0 {
+            match self.bump().kind {
+                TokenKind::OpenBrace => depth += 1,
+                TokenKind::CloseBrace => depth -= 1,
+                TokenKind::Eof => {
+                    return Err(self.error_at(self.pos - 1, "Unclosed `{` at the end of input"));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_fn_body(&mut self, mut header: FnHeader) -> PResult<LirBody> {
+        self.pos = header.body_start;
+        let arg_count = header.ret_and_args.len();
+        self.locals = std::mem::replace(&mut header.ret_and_args, IdxVec::new());
+        self.generics = std::mem::take(&mut header.generics);
+        self.block_refs.clear();
+        if self.eat(&TokenKind::Semi) {
+            return Ok(self.finish_fn_body(header, arg_count, IdxVec::new()));
+        }
+        self.expect(TokenKind::OpenBrace)?;
+
+        while self.eat_keyword("let") {
+            let (local_pos, mutable, local, name) = self.parse_local_decl()?;
+            if local != self.locals.next_index() {
+                return Err(self.error_at(
+                    local_pos,
+                    format!("Expected the local `_{}`", self.locals.next_index().idx()),
+                ));
+            }
+            self.expect(TokenKind::Colon)?;
+            let ty = self.parse_ty()?;
+            self.expect(TokenKind::Semi)?;
+            self.locals.push(LocalData { ty, mutable, name });
+        }
+
+        let mut basic_blocks: IdxVec<BasicBlock, BasicBlockData> = IdxVec::new();
+        // The position of the first token of each block.
+        let mut block_starts: IdxVec<BasicBlock, usize> = IdxVec::new();
+        while !self.eat(&TokenKind::CloseBrace) {
+            let block_pos = self.pos;
+            let block = self.parse_block()?;
+            if block != basic_blocks.next_index() {
+                return Err(self.error_at(
+                    block_pos,
+                    format!("Expected the block `bb{}`", basic_blocks.next_index().idx()),
+                ));
+            }
+            let kind = if self.eat(&TokenKind::OpenParen) {
+                self.expect_keyword("cleanup")?;
+                self.expect(TokenKind::CloseParen)?;
+                if block == ENTRY_BLOCK {
+                    return Err(
+                        self.error_at(block_pos, "The entry block cannot be a cleanup block")
+                    );
+                }
+                BasicBlockKind::Cleanup
+            } else {
+                BasicBlockKind::Regular
+            };
+            self.expect(TokenKind::Colon)?;
+            block_starts.push(block_pos);
+            basic_blocks.push(self.parse_block_data(kind)?);
+        }
+        if basic_blocks.is_empty() {
+            return Err(self.error_at(self.pos - 1, "A body must have at least one block"));
+        }
+        for &(block, pos, is_unwind) in &self.block_refs {
+            let Some(target) = basic_blocks.raw.get(block.idx()) else {
+                return Err(self.error_at(pos, format!("Unknown block `bb{}`", block.idx())));
+            };
+            // The block of the terminator that references the target.
+            let source =
+                BasicBlock::new(block_starts.raw.partition_point(|start| *start < pos) - 1);
+            if is_unwind && !target.is_cleanup() {
+                return Err(self.error_at(
+                    pos,
+                    format!(
+                        "The unwind block `bb{}` is not a cleanup block",
+                        block.idx()
+                    ),
+                ));
+            }
+            if !is_unwind && target.is_cleanup() != basic_blocks[source].is_cleanup() {
+                let message = if target.is_cleanup() {
+                    format!(
+                        "The cleanup block `bb{}` is reached by the normal control flow",
+                        block.idx()
+                    )
+                } else {
+                    format!(
+                        "The cleanup block `bb{}` jumps to the regular block `bb{}`",
+                        source.idx(),
+                        block.idx()
+                    )
+                };
+                return Err(self.error_at(pos, message));
+            }
+        }
+        if header.personality.is_none() && basic_blocks.iter().any(BasicBlockData::is_cleanup) {
+            return Err(self.error_at(
+                header.fn_pos,
+                "A function with cleanup blocks must have a `#[personality(...)]`",
+            ));
+        }
+
+        Ok(self.finish_fn_body(header, arg_count, basic_blocks))
+    }
+
+    /// Returns the body of the given header with the parsed locals and the given
+    /// blocks, which are empty for a declaration.
+    fn finish_fn_body(
+        &mut self,
+        header: FnHeader,
+        arg_count: usize,
+        basic_blocks: IdxVec<BasicBlock, BasicBlockData>,
+    ) -> LirBody {
+        let mut locals = std::mem::take(&mut self.locals);
+        let ret_and_args = IdxVec::from_raw(locals.raw.drain(..arg_count).collect());
+        LirBody {
+            metadata: LirBodyMetadata {
+                def_id: header.def_id,
+                name: header.name,
+                kind: LirBodyKind::Item(header.kind),
+                attrs: header.attrs,
+                linkage: Linkage::External,
+                visibility: Visibility::Default,
+                unnamed_address: UnnamedAddress::None,
+                call_conv: CallConv::C,
+                is_variadic: header.is_variadic,
+                personality: header.personality,
+                span: self.span_from(header.fn_pos),
+            },
+            generics: std::mem::take(&mut self.generics),
+            ret_and_args,
+            locals,
+            basic_blocks,
+        }
+    }
+
+    /// Parses `[mut] _N`, returning the position of the local.
+    fn parse_local_decl(&mut self) -> PResult<(usize, bool, Local, Option<Symbol>)> {
+        let mutable = self.eat_keyword("mut");
+        let local_pos = self.pos;
+        let local = self.parse_numbered("_", "local")?;
+        let name = if self.eat_keyword("as") {
+            let name = self.expect_ident()?;
+            Some(self.lir_ctx.intern_symbol(&name))
+        } else {
+            None
+        };
+        Ok((local_pos, mutable, Local::new(local), name))
+    }
+
+    ////////// Basic blocks //////////
+
+    /// Parses the statements and the terminator of a block of the given kind, between
+    /// braces.
+    fn parse_block_data(&mut self, kind: BasicBlockKind) -> PResult<BasicBlockData> {
+        self.expect(TokenKind::OpenBrace)?;
+        let mut statements = vec![];
+        loop {
+            let item_pos = self.pos;
+            match self.parse_block_item()? {
+                BlockItem::Statement(kind) => statements.push(Statement {
+                    span: self.span_from(item_pos),
+                    kind,
+                }),
+                BlockItem::Terminator(terminator_kind) => {
+                    if let TerminatorKind::UnwindResume = terminator_kind {
+                        if kind != BasicBlockKind::Cleanup {
+                            return Err(self
+                                .error_at(item_pos, "`resume` is only allowed in cleanup blocks"));
+                        }
+                    }
+                    self.expect(TokenKind::Semi)?;
+                    let terminator = Terminator {
+                        span: self.span_from(item_pos),
+                        kind: terminator_kind,
+                    };
+                    self.expect(TokenKind::CloseBrace)?;
+                    return Ok(BasicBlockData {
+                        statements,
+                        terminator,
+                        kind,
+                    });
+                }
+            }
+        }
+    }
+
+    fn parse_block_item(&mut self) -> PResult<BlockItem> {
+        if self.eat_keyword("goto") {
+            self.expect(TokenKind::Arrow)?;
+            let target = self.parse_block_ref()?;
+            return Ok(BlockItem::Terminator(TerminatorKind::Goto { target }));
+        }
+        if self.eat_keyword("return") {
+            return Ok(BlockItem::Terminator(TerminatorKind::Return));
+        }
+        if self.eat_keyword("unreachable") {
+            return Ok(BlockItem::Terminator(TerminatorKind::Unreachable));
+        }
+        if self.eat_keyword("resume") {
+            return Ok(BlockItem::Terminator(TerminatorKind::UnwindResume));
+        }
+        if self.eat_keyword("assert") {
+            return self.parse_assert().map(BlockItem::Terminator);
+        }
+        if self.is_keyword("drop") && self.peek_nth(1) == &TokenKind::OpenParen {
+            self.bump();
+            return self.parse_drop().map(BlockItem::Terminator);
+        }
+        if self.is_keyword("discriminant") && self.peek_nth(1) == &TokenKind::OpenParen {
+            self.bump();
+            return self.parse_set_discriminant().map(BlockItem::Statement);
+        }
+        if self.is_keyword("asm") && self.peek_nth(1) == &TokenKind::Bang {
+            self.bump();
+            self.bump();
+            return self.parse_inline_asm().map(BlockItem::Statement);
+        }
+
+        let (place, place_ty) = self.parse_place()?;
+        self.expect(TokenKind::Eq)?;
+        self.parse_assign_or_call(place, &place_ty.ty)
+    }
+
+    /// Parses `(A, AssertKind) -> target`, after the `assert` keyword. The kind can be
+    /// followed by the hint `likely` or `unlikely` (e.g., `(A, Overflow, likely)`).
+    fn parse_assert(&mut self) -> PResult<TerminatorKind> {
+        self.expect(TokenKind::OpenParen)?;
+        let expected = !self.eat(&TokenKind::Bang);
+        let cond = self.parse_operand()?;
+        self.expect(TokenKind::Comma)?;
+        let kind_pos = self.pos;
+        let msg = match self.expect_ident()?.as_str() {
+            "Overflow" => AssertKind::Overflow,
+            "DivisionByZero" => AssertKind::DivisionByZero,
+            "RemainderByZero" => AssertKind::RemainderByZero,
+            "BoundsCheck" => AssertKind::BoundsCheck,
+            kind => {
+                return Err(self.error_at(kind_pos, format!("Unknown assert kind `{}`", kind)));
+            }
+        };
+        let hint = if self.eat(&TokenKind::Comma) {
+            let hint_pos = self.pos;
+            match self.expect_ident()?.as_str() {
+                "likely" => Some(BranchHint::Likely),
+                "unlikely" => Some(BranchHint::Unlikely),
+                hint => {
+                    return Err(self.error_at(hint_pos, format!("Unknown branch hint `{}`", hint)));
+                }
+            }
+        } else {
+            None
+        };
+        self.expect(TokenKind::CloseParen)?;
+        self.expect(TokenKind::Arrow)?;
+        let (target, unwind) = self.parse_target_and_unwind("success")?;
+        Ok(TerminatorKind::Assert {
+            cond,
+            expected,
+            msg,
+            target,
+            unwind,
+            hint,
+        })
+    }
+
+    /// Parses `(P) -> target`, after the `drop` keyword.
+    fn parse_drop(&mut self) -> PResult<TerminatorKind> {
+        self.expect(TokenKind::OpenParen)?;
+        let (place, _) = self.parse_place()?;
+        self.expect(TokenKind::CloseParen)?;
+        self.expect(TokenKind::Arrow)?;
+        let (target, unwind) = self.parse_target_and_unwind("return")?;
+        Ok(TerminatorKind::Drop {
+            place,
+            target,
+            unwind,
+        })
+    }
+
+    /// Parses the successors of a terminator that can unwind, after its `->`: either
+    /// `bbN`, which continues unwinding to the caller, or
+    /// `[target_kw: bbN, unwind: continue | unreachable | bbM]`.
+    fn parse_target_and_unwind(&mut self, target_kw: &str) -> PResult<(BasicBlock, UnwindAction)> {
+        if !self.eat(&TokenKind::OpenBracket) {
+            return Ok((self.parse_block_ref()?, UnwindAction::Continue));
+        }
+        self.expect_keyword(target_kw)?;
+        self.expect(TokenKind::Colon)?;
+        let target = self.parse_block_ref()?;
+        self.expect(TokenKind::Comma)?;
+        self.expect_keyword("unwind")?;
+        self.expect(TokenKind::Colon)?;
+        let unwind = self.parse_unwind_action()?;
+        self.expect(TokenKind::CloseBracket)?;
+        Ok((target, unwind))
+    }
+
+    /// Parses an unwind action: `continue`, `unreachable` or a cleanup block `bbN`.
+    fn parse_unwind_action(&mut self) -> PResult<UnwindAction> {
+        if self.eat_keyword("continue") {
+            Ok(UnwindAction::Continue)
+        } else if self.eat_keyword("unreachable") {
+            Ok(UnwindAction::Unreachable)
+        } else {
+            let pos = self.pos;
+            let cleanup = self.parse_block()?;
+            self.block_refs.push((cleanup, pos, true));
+            Ok(UnwindAction::Cleanup(cleanup))
+        }
+    }
+
+    /// Parses `(P) = N;`, after the `discriminant` keyword.
+    fn parse_set_discriminant(&mut self) -> PResult<StatementKind> {
+        self.expect(TokenKind::OpenParen)?;
+        let place_pos = self.pos;
+        let (place, place_ty) = self.parse_place()?;
+        self.expect(TokenKind::CloseParen)?;
+        self.expect(TokenKind::Eq)?;
+        let variant_pos = self.pos;
+        let variant_idx = self.parse_index()?;
+        self.expect(TokenKind::Semi)?;
+
+        let variant_count = match &place_ty.ty {
+            LirTy::Adt(adt_id) if self.lir_ctx.adt_def(*adt_id).is_enum() => {
+                self.lir_ctx.adt_def(*adt_id).variants.len()
+            }
+            ty => {
+                return Err(self.error_at(
+                    place_pos,
+                    format!("Cannot set the discriminant of type {:?}", ty),
+                ));
+            }
+        };
+        if variant_idx >= variant_count {
+            return Err(self.error_at(
+                variant_pos,
+                format!("The enum has only {} variants", variant_count),
+            ));
+        }
+        Ok(StatementKind::SetDiscriminant {
+            place: Box::new(place),
+            variant_idx: VariantIdx::new(variant_idx),
+        })
+    }
+
+    /// Parses `("template", operands, clobbers("rcx"), options(nomem));`, after `asm!`.
+    /// The clobbers and the options are optional.
+    fn parse_inline_asm(&mut self) -> PResult<StatementKind> {
+        self.expect(TokenKind::OpenParen)?;
+        let template_pos = self.pos;
+        let template = self.expect_str()?;
+        let mut operands = vec![];
+        let mut clobbers = vec![];
+        let mut options = InlineAsmOptions::default();
+        while self.eat(&TokenKind::Comma) {
+            if *self.peek() == TokenKind::CloseParen {
+                break;
+            }
+            let item_pos = self.pos;
+            match self.expect_ident()?.as_str() {
+                "clobbers" => {
+                    self.expect(TokenKind::OpenParen)?;
+                    clobbers =
+                        self.parse_comma_separated(TokenKind::CloseParen, Self::expect_str)?;
+                }
+                "options" => {
+                    self.expect(TokenKind::OpenParen)?;
+                    let names = self.parse_comma_separated(TokenKind::CloseParen, |this| {
+                        Ok((this.pos, this.expect_ident()?))
+                    })?;
+                    for (name_pos, name) in names {
+                        let Some((option, _)) = InlineAsmOptions::NAMES
+                            .into_iter()
+                            .find(|(_, option_name)| *option_name == name)
+                        else {
+                            return Err(
+                                self.error_at(name_pos, format!("Unknown asm option `{}`", name))
+                            );
+                        };
+                        options.insert(option);
+                    }
+                }
+                _ if !clobbers.is_empty() || options != InlineAsmOptions::default() => {
+                    return Err(self.error_at(
+                        item_pos,
+                        "The operands must precede the clobbers and the options",
+                    ));
+                }
+                dir @ ("in" | "out" | "inout") => {
+                    operands.push(self.parse_inline_asm_operand(dir)?)
+                }
+                item => {
+                    return Err(self.error_at(
+                        item_pos,
+                        format!(
+                            "Expected an asm operand, clobbers or options, found `{}`",
+                            item
+                        ),
+                    ));
+                }
+            }
+        }
+        self.expect(TokenKind::CloseParen)?;
+        self.expect(TokenKind::Semi)?;
+
+        if let Err(message) = check_asm_template(&template, operands.len()) {
+            return Err(self.error_at(template_pos, message));
+        }
+        if options.contains(InlineAsmOptions::NOMEM | InlineAsmOptions::READONLY) {
+            return Err(self.error_at(
+                template_pos,
+                "The options `nomem` and `readonly` are mutually exclusive",
+            ));
+        }
+        if options.contains(InlineAsmOptions::PURE)
+            && (!operands.iter().any(|operand| operand.out_place().is_some())
+                || !options.contains(InlineAsmOptions::NOMEM)
+                    && !options.contains(InlineAsmOptions::READONLY))
+        {
+            return Err(self.error_at(
+                template_pos,
+                "A `pure` asm needs an output, and either `nomem` or `readonly`",
+            ));
+        }
+        Ok(StatementKind::InlineAsm(Box::new(InlineAsm {
+            template,
+            operands,
+            clobbers,
+            options,
+        })))
+    }
+
+    /// Parses `(reg) A`, `(reg) P` or `(reg) A => P` after `in`, `out` or `inout`, where
+    /// the register is either a class (e.g., `reg`) or an explicit one (e.g., `"eax"`).
+    /// The output place can be `_`, if the output is discarded.
+    fn parse_inline_asm_operand(&mut self, dir: &str) -> PResult<InlineAsmOperand> {
+        self.expect(TokenKind::OpenParen)?;
+        let reg = match self.peek().clone() {
+            TokenKind::Str(reg) => {
+                self.bump();
+                InlineAsmRegOrRegClass::Reg(reg)
+            }
+            _ => InlineAsmRegOrRegClass::RegClass(self.expect_ident()?),
+        };
+        self.expect(TokenKind::CloseParen)?;
+        let parse_out_place = |this: &mut Self| -> PResult<Option<Place>> {
+            if this.eat_keyword("_") {
+                return Ok(None);
+            }
+            Ok(Some(this.parse_place()?.0))
+        };
+        match dir {
+            "in" => Ok(InlineAsmOperand::In {
+                reg,
+                value: self.parse_operand()?,
+            }),
+            "out" => {
+                let place_pos = self.pos;
+                let place = parse_out_place(self)?;
+                if place.is_none() && matches!(reg, InlineAsmRegOrRegClass::RegClass(_)) {
+                    return Err(
+                        self.error_at(place_pos, "A discarded output needs an explicit register")
+                    );
+                }
+                Ok(InlineAsmOperand::Out { reg, place })
+            }
+            "inout" => {
+                let in_value = self.parse_operand()?;
+                self.expect(TokenKind::Eq)?;
+                self.expect(TokenKind::Gt)?;
+                Ok(InlineAsmOperand::InOut {
+                    reg,
+                    in_value,
+                    out_place: parse_out_place(self)?,
+                })
+            }
+            _ => unreachable!("Unknown asm operand direction `{}`", dir),
+        }
+    }
+
+    /// Parses the right-hand side of `place = ...`, that is, either an rvalue of an
+    /// assignment or a call. The place is of type `destination_ty`.
+    fn parse_assign_or_call(
+        &mut self,
+        destination: Place,
+        destination_ty: &LirTy,
+    ) -> PResult<BlockItem> {
+        if let TokenKind::Ident(ident) = self.peek().clone() {
+            if self.peek_nth(1) == &TokenKind::OpenParen {
+                if let Some(rvalue) = self.parse_op_rvalue(&ident)? {
+                    self.expect(TokenKind::Semi)?;
+                    return Ok(assign(destination, rvalue));
+                }
+                if let Some((def_id, _, generic_count)) = self.lookup_fn(&ident) {
+                    if *generic_count > 0 {
+                        return Err(self.error_at(
+                            self.pos,
+                            format!(
+                                "The generic function `{}` must be called with `::<...>`",
+                                ident
+                            ),
+                        ));
+                    }
+                    let func = Callee::Direct(*def_id);
+                    self.bump();
+                    return self
+                        .parse_call(func, destination, destination_ty)
+                        .map(BlockItem::Terminator);
+                }
+            }
+            if self.peek_nth(1) == &TokenKind::Colon && self.peek_nth(2) == &TokenKind::Colon {
+                if ident == "intrinsic" {
+                    let rvalue = self.parse_intrinsic()?;
+                    self.expect(TokenKind::Semi)?;
+                    return Ok(assign(destination, rvalue));
+                }
+                return self
+                    .parse_generic_call(&ident, destination, destination_ty)
+                    .map(BlockItem::Terminator);
+            }
+        }
+
+        if self.eat(&TokenKind::Amp) {
+            let (place, _) = self.parse_place()?;
+            self.expect(TokenKind::Semi)?;
+            return Ok(assign(destination, RValue::Ref(place)));
+        }
+
+        let operand = self.parse_operand()?;
+        if *self.peek() == TokenKind::OpenParen {
+            let func = Callee::Indirect(operand);
+            return self
+                .parse_call(func, destination, destination_ty)
+                .map(BlockItem::Terminator);
+        }
+        let rvalue = if self.eat_keyword("as") {
+            let ty = self.parse_ty()?;
+            self.expect(TokenKind::OpenParen)?;
+            let kind_pos = self.pos;
+            let kind = match self.expect_ident()?.as_str() {
+                "IntToInt" => CastKind::IntToInt,
+                "FloatToInt" => CastKind::FloatToInt,
+                "IntToFloat" => CastKind::IntToFloat,
+                "FloatToFloat" => CastKind::FloatToFloat,
+                "PtrToInt" => CastKind::PtrToInt,
+                "IntToPtr" => CastKind::IntToPtr,
+                "PtrToPtr" => CastKind::PtrToPtr,
+                "Transmute" => CastKind::Transmute,
+                kind => {
+                    return Err(self.error_at(kind_pos, format!("Unknown cast kind `{}`", kind)));
+                }
+            };
+            self.expect(TokenKind::CloseParen)?;
+            RValue::Cast(kind, operand, ty)
+        } else {
+            RValue::Use(operand)
+        };
+        self.expect(TokenKind::Semi)?;
+        Ok(assign(destination, rvalue))
+    }
+
+    /// Parses `intrinsic::name(A, ...)`.
+    fn parse_intrinsic(&mut self) -> PResult<RValue> {
+        self.bump();
+        self.expect(TokenKind::Colon)?;
+        self.expect(TokenKind::Colon)?;
+        let name_pos = self.pos;
+        let name = self.expect_ident()?;
+        let Some(intrinsic) = Intrinsic::from_name(&name) else {
+            return Err(self.error_at(name_pos, format!("Unknown intrinsic `{}`", name)));
+        };
+        self.expect(TokenKind::OpenParen)?;
+        let args = self.parse_comma_separated(TokenKind::CloseParen, Self::parse_operand)?;
+        if args.len() != intrinsic.arg_count() {
+            return Err(self.error_at(
+                name_pos,
+                format!(
+                    "The intrinsic `{}` takes {} arguments, but {} were given",
+                    name,
+                    intrinsic.arg_count(),
+                    args.len()
+                ),
+            ));
+        }
+        Ok(RValue::Intrinsic(intrinsic, args))
+    }
+
+    /// Parses the operations written as `Name(...)` (e.g., `Add(A, B)`), if `name` is one
+    /// of them. Otherwise, it returns `None` without consuming any token.
+    fn parse_op_rvalue(&mut self, name: &str) -> PResult<Option<RValue>> {
+        let bin_op = match name {
+            "Add" => BinOp::Add,
+            "Sub" => BinOp::Sub,
+            "Mul" => BinOp::Mul,
+            "Div" => BinOp::Div,
+            "Rem" => BinOp::Rem,
+            "BitXor" => BinOp::BitXor,
+            "BitAnd" => BinOp::BitAnd,
+            "BitOr" => BinOp::BitOr,
+            "Shl" => BinOp::Shl,
+            "Shr" => BinOp::Shr,
+            "Eq" => BinOp::Eq,
+            "Lt" => BinOp::Lt,
+            "Le" => BinOp::Le,
+            "Ne" => BinOp::Ne,
+            "Ge" => BinOp::Ge,
+            "Gt" => BinOp::Gt,
+            "Not" | "Neg" => {
+                let un_op = if name == "Not" { UnOp::Not } else { UnOp::Neg };
+                self.bump();
+                self.expect(TokenKind::OpenParen)?;
+                let operand = self.parse_operand()?;
+                self.expect(TokenKind::CloseParen)?;
+                return Ok(Some(RValue::UnaryOp(un_op, operand)));
+            }
+            "discriminant" => {
+                self.bump();
+                self.expect(TokenKind::OpenParen)?;
+                let (place, _) = self.parse_place()?;
+                self.expect(TokenKind::CloseParen)?;
+                return Ok(Some(RValue::Discriminant(place)));
+            }
+            _ => return Ok(None),
+        };
+        self.bump();
+        self.expect(TokenKind::OpenParen)?;
+        let lhs = self.parse_operand()?;
+        self.expect(TokenKind::Comma)?;
+        let rhs = self.parse_operand()?;
+        self.expect(TokenKind::CloseParen)?;
+        Ok(Some(RValue::BinaryOp(bin_op, lhs, rhs)))
+    }
+
+    /// Parses `name::<T, U>(A, B) -> target`, a call to an instance of a generic function.
+    fn parse_generic_call(
+        &mut self,
+        name: &str,
+        destination: Place,
+        destination_ty: &LirTy,
+    ) -> PResult<TerminatorKind> {
+        let name_pos = self.pos;
+        let Some((def_id, _, generic_count)) = self.lookup_fn(name).cloned() else {
+            return Err(self.error_at(name_pos, format!("Unknown function `{}`", name)));
+        };
+        self.bump();
+        self.expect(TokenKind::Colon)?;
+        self.expect(TokenKind::Colon)?;
+        self.expect(TokenKind::Lt)?;
+        let args = self.parse_comma_separated(TokenKind::Gt, Self::parse_ty)?;
+        if args.len() != generic_count {
+            return Err(self.error_at(
+                name_pos,
+                format!(
+                    "`{}` expects {} generic arguments, found {}",
+                    name,
+                    generic_count,
+                    args.len()
+                ),
+            ));
+        }
+        self.parse_call(
+            Callee::Generic(def_id, GenericArgs(args)),
+            destination,
+            destination_ty,
+        )
+    }
+
+    /// Parses `(A, B) -> target`, or `(A, B) -> [return: target, unwind: action]`,
+    /// after the callee. A call whose destination is of type `!` has no target: it is
+    /// `(A, B)`, or `(A, B) -> [unwind: action]`.
+    fn parse_call(
+        &mut self,
+        func: Callee,
+        destination: Place,
+        destination_ty: &LirTy,
+    ) -> PResult<TerminatorKind> {
+        self.expect(TokenKind::OpenParen)?;
+        let args =
+            self.parse_comma_separated(TokenKind::CloseParen, |this| this.parse_operand())?;
+        let (target, unwind) = if *destination_ty != LirTy::Never {
+            self.expect(TokenKind::Arrow)?;
+            let (target, unwind) = self.parse_target_and_unwind("return")?;
+            (Some(target), unwind)
+        } else if self.eat(&TokenKind::Arrow) {
+            self.expect(TokenKind::OpenBracket)?;
+            self.expect_keyword("unwind")?;
+            self.expect(TokenKind::Colon)?;
+            let unwind = self.parse_unwind_action()?;
+            self.expect(TokenKind::CloseBracket)?;
+            (None, unwind)
+        } else {
+            (None, UnwindAction::Continue)
+        };
+        Ok(TerminatorKind::Call {
+            func,
+            args,
+            destination,
+            target,
+            unwind,
+        })
+    }
+
+    fn parse_block_ref(&mut self) -> PResult<BasicBlock> {
+        let pos = self.pos;
+        let block = self.parse_block()?;
+        self.block_refs.push((block, pos, false));
+        Ok(block)
+    }
+
+    fn parse_block(&mut self) -> PResult<BasicBlock> {
+        self.parse_numbered("bb", "basic block")
+            .map(BasicBlock::new)
+    }
+
+    ////////// Places and operands //////////
+
+    /// Parses a place, returning it with its type.
+    fn parse_place(&mut self) -> PResult<(Place, PlaceTy)> {
+        let (mut place, mut place_ty) = if self.eat(&TokenKind::OpenParen) {
+            let proj_pos = self.pos;
+            let (mut place, place_ty, projection) = if self.eat(&TokenKind::Star) {
+                let (place, place_ty) = self.parse_place()?;
+                (place, place_ty, Projection::Deref)
+            } else {
+                let (place, place_ty) = self.parse_place()?;
+                self.expect_keyword("as")?;
+                let variant_idx = self.parse_variant(&place_ty)?;
+                (place, place_ty, Projection::Downcast(variant_idx))
+            };
+            self.expect(TokenKind::CloseParen)?;
+            let place_ty = self.project(&mut place, &place_ty, projection, proj_pos)?;
+            (place, place_ty)
+        } else {
+            let local_pos = self.pos;
+            let local = Local::new(self.parse_numbered("_", "local")?);
+            let Some(local_data) = self.locals.get(local) else {
+                return Err(self.error_at(local_pos, format!("Unknown local `_{}`", local.idx())));
+            };
+            let place_ty = PlaceTy::from_ty(local_data.ty.clone());
+            (Place::from(local), place_ty)
+        };
+
+        loop {
+            let proj_pos = self.pos;
+            let projection = if self.eat(&TokenKind::Dot) {
+                Projection::Field(FieldIdx::new(self.parse_index()?))
+            } else if self.eat(&TokenKind::OpenBracket) {
+                let index_pos = self.pos;
+                let index = Local::new(self.parse_numbered("_", "local")?);
+                if self.locals.get(index).is_none() {
+                    return Err(
+                        self.error_at(index_pos, format!("Unknown local `_{}`", index.idx()))
+                    );
+                }
+                self.expect(TokenKind::CloseBracket)?;
+                Projection::Index(index)
+            } else {
+                return Ok((place, place_ty));
+            };
+            place_ty = self.project(&mut place, &place_ty, projection, proj_pos)?;
+        }
+    }
+
+    /// Parses the name of a variant of the enum `place_ty`.
+    fn parse_variant(&mut self, place_ty: &PlaceTy) -> PResult<VariantIdx> {
+        let name_pos = self.pos;
+        let name = self.expect_ident()?;
+        let LirTy::Adt(adt_id) = place_ty.ty else {
+            return Err(self.error_at(name_pos, format!("Type {:?} is not an enum", place_ty.ty)));
+        };
+        let adt_def = self.lir_ctx.adt_def(adt_id);
+        adt_def
+            .variants
+            .iter_enumerated()
+            .find(|(_, variant)| variant.name == name)
+            .map(|(variant_idx, _)| variant_idx)
+            .ok_or_else(|| {
+                self.error_at(
+                    name_pos,
+                    format!("`{}` has no variant named `{}`", adt_def.name, name),
+                )
+            })
+    }
+
+    /// Appends the given projection to the place, whose type is `place_ty`, and returns
+    /// the type of the projected place. The projection starts at the token `pos`.
+    fn project(
+        &self,
+        place: &mut Place,
+        place_ty: &PlaceTy,
+        projection: Projection,
+        pos: usize,
+    ) -> PResult<PlaceTy> {
+        // `PlaceTy::projection_ty` panics on invalid projections, so they are
+        // reported as errors here.
+        let is_valid = match (&place_ty.ty, &projection) {
+            (LirTy::Ptr(..), Projection::Deref) => true,
+            (LirTy::Array(..), Projection::Index(_)) => true,
+            (LirTy::Adt(adt_id), Projection::Field(field_idx)) => {
+                let adt_def = self.lir_ctx.adt_def(*adt_id);
+                let variant = match place_ty.variant_idx {
+                    Some(variant_idx) => Some(&adt_def.variants[variant_idx]),
+                    None if !adt_def.is_enum() => Some(adt_def.non_enum_variant()),
+                    None => None,
+                };
+                variant.is_some_and(|variant| field_idx.idx() < variant.fields.len())
+            }
+            (LirTy::Tuple(field_tys), Projection::Field(field_idx)) => {
+                field_idx.idx() < field_tys.len()
+            }
+            (LirTy::Slice(_), Projection::Field(field_idx)) => field_idx.idx() < 2,
+            (LirTy::Adt(adt_id), Projection::Downcast(_)) => {
+                self.lir_ctx.adt_def(*adt_id).is_enum()
+            }
+            _ => false,
+        };
+        if !is_valid {
+            return Err(self.error_at(
+                pos,
+                format!(
+                    "Cannot apply projection {:?} to type {:?}",
+                    projection, place_ty.ty
+                ),
+            ));
+        }
+        place.projection.push(projection);
+        Ok(place_ty.projection_ty(self.lir_ctx, &projection))
+    }
+
+    fn parse_operand(&mut self) -> PResult<Operand> {
+        let keyword_pos = self.pos;
+        match self.expect_ident()?.as_str() {
+            "copy" => Ok(Operand::Copy(self.parse_place()?.0)),
+            "move" => Ok(Operand::Move(self.parse_place()?.0)),
+            "const" => Ok(Operand::Const(self.parse_const()?)),
+            keyword => Err(self.error_at(
+                keyword_pos,
+                format!(
+                    "Expected an operand (`copy`, `move` or `const`), found `{}`",
+                    keyword
+                ),
+            )),
+        }
+    }
+
+    /// Parses a constant, after the `const` keyword.
+    fn parse_const(&mut self) -> PResult<ConstOperand> {
+        let const_pos = self.pos;
+        let negative = self.eat(&TokenKind::Minus);
+        let token = self.bump();
+        let (value, ty) = match token.kind {
+            TokenKind::Number { digits, suffix } => {
+                let Some(suffix) = suffix else {
+                    return Err(self.error_at(
+                        const_pos,
+                        format!(
+                            "The constant `{}` has no type suffix (e.g., `_i32`)",
+                            digits
+                        ),
+                    ));
+                };
+                let ty = self.prim_ty(&suffix).ok_or_else(|| {
+                    self.error_at(const_pos, format!("Unknown type suffix `_{}`", suffix))
+                })?;
+                let data = self.parse_scalar_data(&digits, negative, &ty, const_pos)?;
+                (self.scalar(data, &ty), ty)
+            }
+            _ if negative => {
+                return Err(self.error_at(const_pos + 1, "Expected a number after `-`"));
+            }
+            TokenKind::Ident(ident) if ident == "true" || ident == "false" => (
+                self.scalar((ident == "true") as u128, &LirTy::Bool),
+                LirTy::Bool,
+            ),
+            TokenKind::Ident(ident) => match self.lookup_fn(&ident) {
+                Some((_, _, generic_count)) if *generic_count > 0 => {
+                    return Err(self.error_at(
+                        const_pos,
+                        format!("The generic function `{}` cannot be a constant", ident),
+                    ));
+                }
+                Some((def_id, sig, _)) => (
+                    ConstValue::FnPtr(*def_id),
+                    LirTy::FnPtr(Box::new(sig.clone())),
+                ),
+                None => {
+                    return Err(self.error_at(const_pos, format!("Unknown function `{}`", ident)));
+                }
+            },
+            TokenKind::OpenParen => {
+                self.expect(TokenKind::CloseParen)?;
+                (ConstValue::ZST, LirTy::unit())
+            }
+            TokenKind::Str(s) => (self.lir_ctx.const_str(&s), LirTy::str()),
+            TokenKind::ByteStr(bytes) => (
+                self.lir_ctx.const_bytes(bytes),
+                LirTy::Slice(Box::new(LirTy::U8)),
+            ),
+            TokenKind::OpenBracket => {
+                let elems = self.parse_comma_separated(TokenKind::CloseBracket, |this| {
+                    let elem_pos = this.pos;
+                    Ok((elem_pos, this.parse_const()?))
+                })?;
+                let Some((_, first)) = elems.first() else {
+                    return Err(self.error_at(const_pos, "Empty array constants are not supported"));
+                };
+                let elem_ty = first.ty();
+                if let Some((elem_pos, elem)) = elems.iter().find(|(_, elem)| elem.ty() != elem_ty)
+                {
+                    return Err(self.error_at(
+                        *elem_pos,
+                        format!(
+                            "Expected an element of type {:?}, found {:?}",
+                            elem_ty,
+                            elem.ty()
+                        ),
+                    ));
+                }
+                let ty = LirTy::Array(Box::new(elem_ty), elems.len() as u64);
+                let elems = elems
+                    .into_iter()
+                    .map(|(_, elem)| elem.value().clone())
+                    .collect();
+                (ConstValue::Array(elems), ty)
+            }
+            kind => {
+                return Err(
+                    self.error_at(const_pos, format!("Expected a constant, found {}", kind))
+                );
+            }
+        };
+        Ok(ConstOperand::Value(value, ty))
+    }
+
+    /// Returns the bits of the numeric constant `digits` (negated if `negative`) of
+    /// type `ty`, checking that it fits the type.
+    fn parse_scalar_data(
+        &self,
+        digits: &str,
+        negative: bool,
+        ty: &LirTy,
+        pos: usize,
+    ) -> PResult<u128> {
+        let invalid = || self.error_at(pos, format!("Invalid constant of type {:?}", ty));
+        match ty {
+            LirTy::F32 => {
+                let value = digits.parse::<f32>().map_err(|_| invalid())?;
+                Ok(if negative { -value } else { value }.to_bits() as u128)
+            }
+            LirTy::F64 => {
+                let value = digits.parse::<f64>().map_err(|_| invalid())?;
+                Ok(if negative { -value } else { value }.to_bits() as u128)
+            }
+            LirTy::F16 | LirTy::F128 => Err(self.error_at(
+                pos,
+                format!("Constants of type {:?} are not supported yet", ty),
+            )),
+            _ => {
+                let value = digits.parse::<u128>().map_err(|_| invalid())?;
+                let bits = self.lir_ctx.layout_of(ty.clone()).size.bytes() * 8;
+                let max = if ty.is_signed() {
+                    // The magnitude of the minimum is one more than the maximum.
+                    (1u128 << (bits - 1)) - (!negative as u128)
+                } else if negative {
+                    0
+                } else {
+                    u128::MAX >> (128 - bits)
+                };
+                if value > max {
+                    return Err(
+                        self.error_at(pos, format!("The constant does not fit the type {:?}", ty))
+                    );
+                }
+                Ok(if negative {
+                    value.wrapping_neg()
+                } else {
+                    value
+                })
+            }
+        }
+    }
+
+    /// Returns the scalar constant of type `ty` with the given bits, truncated to its size.
+    fn scalar(&self, data: u128, ty: &LirTy) -> ConstValue {
+        let size = self.lir_ctx.layout_of(ty.clone()).size.bytes();
+        ConstValue::Scalar(ConstScalar::Value(RawScalarValue {
+            data: data & (u128::MAX >> (128 - size * 8)),
+            size: std::num::NonZero::new(size as u8).unwrap(),
+        }))
+    }
+
+    ////////// Types //////////
+
+    fn parse_ty(&mut self) -> PResult<LirTy> {
+        let ty_pos = self.pos;
+        let ty = match self.bump().kind {
+            TokenKind::Ident(ident) if ident == "fn" => {
+                self.expect(TokenKind::OpenParen)?;
+                let (args, is_variadic) = self.parse_sig_args(Self::parse_ty)?;
+                let ret = if self.eat(&TokenKind::Arrow) {
+                    self.parse_ty()?
+                } else {
+                    LirTy::unit()
+                };
+                LirTy::FnPtr(Box::new(FnSig {
+                    ret,
+                    args,
+                    is_variadic,
+                }))
+            }
+            TokenKind::Bang => LirTy::Never,
+            TokenKind::Ident(ident) => match self.prim_ty(&ident) {
+                Some(ty) => ty,
+                None if self.generics.params.contains(&ident) => {
+                    let idx = self
+                        .generics
+                        .params
+                        .iter()
+                        .position(|param| *param == ident);
+                    LirTy::Param(idx.unwrap())
+                }
+                None => match self.adts.get(&ident) {
+                    Some(adt_id) => LirTy::Adt(*adt_id),
+                    None => {
+                        return Err(self.error_at(ty_pos, format!("Unknown type `{}`", ident)));
+                    }
+                },
+            },
+            TokenKind::Star => {
+                let addr_space = if self.eat_keyword("addrspace") {
+                    self.expect(TokenKind::OpenParen)?;
+                    let addr_space_pos = self.pos;
+                    let addr_space = u32::try_from(self.parse_index()?)
+                        .map_err(|_| self.error_at(addr_space_pos, "Invalid address space"))?;
+                    self.expect(TokenKind::CloseParen)?;
+                    AddressSpace(addr_space)
+                } else {
+                    AddressSpace::DATA
+                };
+                LirTy::Ptr(Box::new(self.parse_ty()?), addr_space)
+            }
+            TokenKind::Amp => {
+                if self.eat_keyword("str") {
+                    LirTy::str()
+                } else {
+                    self.expect(TokenKind::OpenBracket)?;
+                    let elem_ty = self.parse_ty()?;
+                    self.expect(TokenKind::CloseBracket)?;
+                    LirTy::Slice(Box::new(elem_ty))
+                }
+            }
+            TokenKind::OpenBracket => {
+                let elem_ty = self.parse_ty()?;
+                self.expect(TokenKind::Semi)?;
+                let count = self.parse_index()?;
+                self.expect(TokenKind::CloseBracket)?;
+                LirTy::Array(Box::new(elem_ty), count as u64)
+            }
+            TokenKind::OpenParen => {
+                let mut field_tys = vec![];
+                let mut trailing_comma = false;
+                while !self.eat(&TokenKind::CloseParen) {
+                    field_tys.push(self.parse_ty()?);
+                    trailing_comma = self.eat(&TokenKind::Comma);
+                    if !trailing_comma {
+                        self.expect(TokenKind::CloseParen)?;
+                        break;
+                    }
+                }
+                // As in Rust, `(T)` is `T`, while `(T,)` is a tuple.
+                if field_tys.len() == 1 && !trailing_comma {
+                    field_tys.pop().unwrap()
+                } else {
+                    LirTy::Tuple(field_tys)
+                }
+            }
+            kind => return Err(self.error_at(ty_pos, format!("Expected a type, found {}", kind))),
+        };
+        Ok(ty)
+    }
+
+    /// Returns the primitive type with the given name, if any.
+    fn prim_ty(&self, name: &str) -> Option<LirTy> {
+        let ty = match name {
+            "bool" => LirTy::Bool,
+            "i8" => LirTy::I8,
+            "i16" => LirTy::I16,
+            "i32" => LirTy::I32,
+            "i64" => LirTy::I64,
+            "i128" => LirTy::I128,
+            "isize" => match self.lir_ctx.usize_ty() {
+                LirTy::U16 => LirTy::I16,
+                LirTy::U32 => LirTy::I32,
+                _ => LirTy::I64,
+            },
+            "u8" => LirTy::U8,
+            "u16" => LirTy::U16,
+            "u32" => LirTy::U32,
+            "u64" => LirTy::U64,
+            "u128" => LirTy::U128,
+            "usize" => self.lir_ctx.usize_ty(),
+            "f16" => LirTy::F16,
+            "f32" => LirTy::F32,
+            "f64" => LirTy::F64,
+            "f128" => LirTy::F128,
+            _ => return None,
+        };
+        Some(ty)
+    }
+
+    ////////// Tokens //////////
+
+    fn peek(&self) -> &TokenKind {
+        self.peek_nth(0)
+    }
+
+    fn peek_nth(&self, n: usize) -> &TokenKind {
+        // The last token is always `Eof`.
+        let idx = (self.pos + n).min(self.tokens.len() - 1);
+        &self.tokens[idx].kind
+    }
+
+    fn bump(&mut self) -> Token {
+        let token = self.tokens[self.pos].clone();
+        if token.kind != TokenKind::Eof {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn eat(&mut self, kind: &TokenKind) -> bool {
+        let found = self.peek() == kind;
+        if found {
+            self.bump();
+        }
+        found
+    }
+
+    fn expect(&mut self, kind: TokenKind) -> PResult<()> {
+        if self.eat(&kind) {
+            Ok(())
+        } else {
+            Err(self.error_at(
+                self.pos,
+                format!("Expected {}, found {}", kind, self.peek()),
+            ))
+        }
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), TokenKind::Ident(ident) if ident == keyword)
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.is_keyword(keyword);
+        if found {
+            self.bump();
+        }
+        found
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> PResult<()> {
+        if self.eat_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(self.error_at(
+                self.pos,
+                format!("Expected `{}`, found {}", keyword, self.peek()),
+            ))
+        }
+    }
+
+    fn expect_str(&mut self) -> PResult<String> {
+        match self.peek().clone() {
+            TokenKind::Str(s) => {
+                self.bump();
+                Ok(s)
+            }
+            kind => Err(self.error_at(self.pos, format!("Expected a string, found {}", kind))),
+        }
+    }
+
+    fn expect_ident(&mut self) -> PResult<String> {
+        match self.peek().clone() {
+            TokenKind::Ident(ident) => {
+                self.bump();
+                Ok(ident)
+            }
+            kind => Err(self.error_at(self.pos, format!("Expected an identifier, found {}", kind))),
+        }
+    }
+
+    /// Parses an unsuffixed number (e.g., a field index).
+    fn parse_index(&mut self) -> PResult<usize> {
+        match self.peek().clone() {
+            TokenKind::Number {
+                digits,
+                suffix: None,
+            } if !digits.contains('.') => {
+                let index = digits
+                    .parse()
+                    .map_err(|_| self.error_at(self.pos, format!("Invalid index `{}`", digits)))?;
+                self.bump();
+                Ok(index)
+            }
+            kind => Err(self.error_at(self.pos, format!("Expected an index, found {}", kind))),
+        }
+    }
+
+    /// Parses an identifier made of the given prefix and a number (e.g., `_1` or `bb0`),
+    /// returning the number. `what` describes the identifier in the errors.
+    fn parse_numbered(&mut self, prefix: &str, what: &str) -> PResult<usize> {
+        let pos = self.pos;
+        let ident = self.expect_ident()?;
+        ident
+            .strip_prefix(prefix)
+            .and_then(|n| n.parse().ok())
+            .ok_or_else(|| self.error_at(pos, format!("Expected a {}, found `{}`", what, ident)))
+    }
+
+    /// Parses a list of elements separated by commas (with an optional trailing comma),
+    /// up to the given closing token.
+    fn parse_comma_separated<T>(
+        &mut self,
+        close: TokenKind,
+        mut parse_elem: impl FnMut(&mut Self) -> PResult<T>,
+    ) -> PResult<Vec<T>> {
+        let mut elems = vec![];
+        while !self.eat(&close) {
+            elems.push(parse_elem(self)?);
+            if !self.eat(&TokenKind::Comma) {
+                self.expect(close)?;
+                break;
+            }
+        }
+        Ok(elems)
+    }
+
+    /// Parses the comma-separated arguments of a signature up to the `)`, where the
+    /// last one can be `...`. It returns the arguments and whether the signature is
+    /// variadic.
+    fn parse_sig_args<T>(
+        &mut self,
+        mut parse_arg: impl FnMut(&mut Self) -> PResult<T>,
+    ) -> PResult<(Vec<T>, bool)> {
+        let mut args = vec![];
+        while !self.eat(&TokenKind::CloseParen) {
+            if self.eat(&TokenKind::Ellipsis) {
+                self.expect(TokenKind::CloseParen)?;
+                return Ok((args, true));
+            }
+            args.push(parse_arg(self)?);
+            if !self.eat(&TokenKind::Comma) {
+                self.expect(TokenKind::CloseParen)?;
+                break;
+            }
+        }
+        Ok((args, false))
+    }
+
+    /// Returns the span from the token `start` to the last parsed token.
+    fn span_from(&self, start: usize) -> Span {
+        let end = self.pos.max(start + 1) - 1;
+        Span::new(self.file, self.tokens[start].lo, self.tokens[end].hi)
+    }
+
+    /// Returns an error at the position of the token `pos`.
+    fn error_at(&self, pos: usize, message: impl Into<String>) -> ParseError {
+        let token = &self.tokens[pos.min(self.tokens.len() - 1)];
+        ParseError::new(token.line, token.column, message)
+    }
+}
+
+/// Checks that the braces of the given template of an inline assembly are escaped,
+/// or else that they enclose the index of one of its `operand_count` operands.
+fn check_asm_template(template: &str, operand_count: usize) -> Result<(), String> {
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.next_if_eq(&'{').is_some() => {}
+            '}' if chars.next_if_eq(&'}').is_some() => {}
+            '{' => {
+                let mut idx = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => idx.push(c),
+                        None => return Err("Unterminated `{` in the asm template".to_string()),
+                    }
+                }
+                match idx.parse::<usize>() {
+                    Ok(idx) if idx < operand_count => {}
+                    _ => {
+                        return Err(format!(
+                            "Invalid operand `{{{}}}` in the asm template ({} operands)",
+                            idx, operand_count
+                        ));
+                    }
+                }
+            }
+            '}' => return Err("Unmatched `}` in the asm template".to_string()),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn assign(place: Place, rvalue: RValue) -> BlockItem {
+    BlockItem::Statement(StatementKind::Assign(Box::new((place, rvalue))))
+}
@@ -0,0 +1,757 @@
+//! A printer of the LIR bodies in their textual representation (see `parse`).
+//!
+//! The printed bodies use the same syntax accepted by the parser, except for the
+//! values that it cannot express (e.g., NaNs, `f16` constants or `Indirect` constants).
+
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+
+use tidec_abi::target::AddressSpace;
+use tidec_utils::{idx::Idx, index_vec::IdxVec};
+
+use crate::{
+    basic_blocks::{BasicBlock, BasicBlockData},
+    lir::{DefId, LirBody, LirCtx, LirUnit},
+    syntax::{
+        AdtId, Callee, ConstOperand, ConstScalar, ConstValue, Generics, InlineAsm,
+        InlineAsmOperand, InlineAsmOptions, InlineAsmRegOrRegClass, LirTy, Local, LocalData,
+        Operand, Place, PlaceTy, Projection, RValue, Statement, StatementKind, Terminator,
+        TerminatorKind, UnwindAction, RETURN_LOCAL,
+    },
+};
+
+/// Prints the bodies of a unit. The names of the functions of the unit (including
+/// its imports) are used for the direct calls and the function pointers.
+// It corresponds to `write_mir_fn` in rustc_middle/src/mir/pretty.rs
+pub struct LirPrinter<'a> {
+    lir_ctx: &'a LirCtx,
+    fn_names: HashMap<DefId, String>,
+}
+
+impl<'a> LirPrinter<'a> {
+    pub fn new(lir_ctx: &'a LirCtx, lir_unit: &LirUnit) -> Self {
+        let fn_names = lir_unit
+            .bodies
+            .iter()
+            .chain(lir_unit.imports.values().map(|import| &import.declaration))
+            .map(|lir_body| (lir_body.metadata.def_id, lir_body.metadata.name.clone()))
+            .collect();
+        LirPrinter { lir_ctx, fn_names }
+    }
+
+    /// Returns a printer that names the functions after the bodies defined in `lir_ctx`,
+    /// for when the unit is not at hand (e.g., while a body is being codegened).
+    pub fn from_defs(lir_ctx: &'a LirCtx) -> Self {
+        let fn_names = lir_ctx
+            .defs()
+            .chain(lir_ctx.imports().map(|import| &import.declaration))
+            .map(|lir_body| (lir_body.metadata.def_id, lir_body.metadata.name.clone()))
+            .collect();
+        LirPrinter { lir_ctx, fn_names }
+    }
+
+    /// Returns the textual representation of the given body.
+    pub fn body_to_string(&self, lir_body: &LirBody) -> String {
+        let mut out = String::new();
+        self.write_body(&mut out, lir_body)
+            .expect("Writing to a String cannot fail");
+        out
+    }
+
+    /// Writes the textual representation of the given body.
+    pub fn write_body(&self, w: &mut impl Write, lir_body: &LirBody) -> fmt::Result {
+        BodyPrinter {
+            printer: self,
+            lir_body,
+        }
+        .write_body(w)
+    }
+
+    /// Returns the textual representation of the statements and the terminator of
+    /// the given block of `lir_body`, one per line and without the trailing `;`.
+    pub fn block_to_lines(&self, lir_body: &LirBody, bb_data: &BasicBlockData) -> Vec<String> {
+        let body_printer = BodyPrinter {
+            printer: self,
+            lir_body,
+        };
+        let mut lines: Vec<String> = bb_data
+            .statements
+            .iter()
+            .map(|stmt| {
+                let mut line = String::new();
+                body_printer
+                    .write_statement(&mut line, stmt)
+                    .expect("Writing to a String cannot fail");
+                line
+            })
+            .collect();
+        let mut line = String::new();
+        body_printer
+            .write_terminator(&mut line, &bb_data.terminator)
+            .expect("Writing to a String cannot fail");
+        lines.push(line);
+        lines
+    }
+
+    /// Returns the textual representation of the given type, which must not
+    /// contain generic parameters.
+    pub fn ty_to_string(&self, ty: &LirTy) -> String {
+        let mut out = String::new();
+        self.write_ty(&mut out, ty, &Generics::default())
+            .expect("Writing to a String cannot fail");
+        out
+    }
+
+    /// Writes the definition of the given ADT, e.g., `struct Point { i32, i32 }`.
+    pub fn write_adt_def(&self, w: &mut impl Write, adt_id: AdtId) -> fmt::Result {
+        let adt_def = self.lir_ctx.adt_def(adt_id);
+        if adt_def.repr.c {
+            writeln!(w, "#[repr(C)]")?;
+        }
+        if !adt_def.is_enum() {
+            write!(w, "struct {} {{", adt_def.name)?;
+            let fields = &adt_def.non_enum_variant().fields.raw;
+            if !fields.is_empty() {
+                w.write_char(' ')?;
+                self.write_comma_separated(w, fields, |w, ty| {
+                    self.write_ty(w, ty, &Generics::default())
+                })?;
+                w.write_char(' ')?;
+            }
+            return writeln!(w, "}}");
+        }
+        write!(w, "enum {} {{", adt_def.name)?;
+        if adt_def.variants.is_empty() {
+            return writeln!(w, "}}");
+        }
+        w.write_char(' ')?;
+        self.write_comma_separated(w, &adt_def.variants.raw, |w, variant| {
+            w.write_str(&variant.name)?;
+            if variant.fields.is_empty() {
+                return Ok(());
+            }
+            w.write_char('(')?;
+            self.write_comma_separated(w, &variant.fields.raw, |w, ty| {
+                self.write_ty(w, ty, &Generics::default())
+            })?;
+            w.write_char(')')
+        })?;
+        writeln!(w, " }}")
+    }
+
+    fn fn_name(&self, def_id: DefId) -> String {
+        self.fn_names
+            .get(&def_id)
+            .cloned()
+            .unwrap_or_else(|| format!("{:?}", def_id))
+    }
+
+    /// Writes the given type. The type parameters are named after the given generics.
+    fn write_ty(&self, w: &mut impl Write, ty: &LirTy, generics: &Generics) -> fmt::Result {
+        if let Some(name) = prim_ty_name(ty) {
+            return w.write_str(name);
+        }
+        match ty {
+            LirTy::Ptr(pointee, addr_space) => {
+                w.write_char('*')?;
+                if *addr_space != AddressSpace::DATA {
+                    write!(w, "addrspace({}) ", addr_space.0)?;
+                }
+                self.write_ty(w, pointee, generics)
+            }
+            LirTy::Array(elem_ty, count) => {
+                w.write_char('[')?;
+                self.write_ty(w, elem_ty, generics)?;
+                write!(w, "; {}]", count)
+            }
+            LirTy::Adt(adt_id) => w.write_str(&self.lir_ctx.adt_def(*adt_id).name),
+            LirTy::Tuple(field_tys) => {
+                w.write_char('(')?;
+                self.write_comma_separated(w, field_tys, |w, ty| self.write_ty(w, ty, generics))?;
+                if field_tys.len() == 1 {
+                    w.write_char(',')?;
+                }
+                w.write_char(')')
+            }
+            LirTy::FnPtr(fn_sig) => {
+                w.write_str("fn(")?;
+                self.write_comma_separated(w, &fn_sig.args, |w, ty| {
+                    self.write_ty(w, ty, generics)
+                })?;
+                write_variadic(w, fn_sig.is_variadic, fn_sig.args.is_empty())?;
+                w.write_char(')')?;
+                if fn_sig.ret != LirTy::unit() {
+                    w.write_str(" -> ")?;
+                    self.write_ty(w, &fn_sig.ret, generics)?;
+                }
+                Ok(())
+            }
+            LirTy::Slice(elem_ty) => {
+                w.write_str("&[")?;
+                self.write_ty(w, elem_ty, generics)?;
+                w.write_char(']')
+            }
+            LirTy::Param(idx) => match generics.params.get(*idx) {
+                Some(name) => w.write_str(name),
+                None => write!(w, "T{}", idx),
+            },
+            LirTy::Metadata => w.write_str("metadata"),
+            _ => unreachable!("{:?} is a primitive type", ty),
+        }
+    }
+
+    fn write_const(&self, w: &mut impl Write, value: &ConstValue, ty: &LirTy) -> fmt::Result {
+        match value {
+            ConstValue::ZST => w.write_str("()"),
+            ConstValue::Scalar(ConstScalar::Value(raw)) => {
+                let data = raw.data;
+                let bits = u32::from(raw.size.get()) * 8;
+                match ty {
+                    LirTy::Bool => write!(w, "{}", data != 0),
+                    LirTy::F32 => write!(w, "{}_f32", f32::from_bits(data as u32)),
+                    LirTy::F64 => write!(w, "{}_f64", f64::from_bits(data as u64)),
+                    _ if ty.is_signed() => {
+                        let shift = 128 - bits;
+                        let value = ((data << shift) as i128) >> shift;
+                        write!(w, "{}_{}", value, prim_ty_name(ty).unwrap())
+                    }
+                    _ => match prim_ty_name(ty) {
+                        Some(name) => write!(w, "{}_{}", data, name),
+                        None => write!(w, "{:#x}", data),
+                    },
+                }
+            }
+            ConstValue::Array(elems) => {
+                let LirTy::Array(elem_ty, _) = ty else {
+                    panic!("Array constant of type {:?}", ty);
+                };
+                w.write_char('[')?;
+                self.write_comma_separated(w, elems, |w, elem| self.write_const(w, elem, elem_ty))?;
+                w.write_char(']')
+            }
+            ConstValue::FnPtr(def_id) => w.write_str(&self.fn_name(*def_id)),
+            ConstValue::Slice { alloc_id, len } => {
+                let bytes = &self.lir_ctx.alloc(*alloc_id).bytes;
+                match (ty, bytes.get(..*len as usize)) {
+                    (LirTy::Slice(elem_ty), Some(bytes)) if **elem_ty == LirTy::U8 => {
+                        match std::str::from_utf8(bytes) {
+                            Ok(s) => write_str_literal(w, s),
+                            Err(_) => write_byte_str_literal(w, bytes),
+                        }
+                    }
+                    _ => write!(w, "alloc{}[..{}]", alloc_id.idx(), len),
+                }
+            }
+            ConstValue::Indirect { alloc_id, offset } => {
+                write!(w, "alloc{}+{}", alloc_id.idx(), offset.bytes())
+            }
+        }
+    }
+
+    fn write_comma_separated<T, W: Write>(
+        &self,
+        w: &mut W,
+        items: &[T],
+        mut write_item: impl FnMut(&mut W, &T) -> fmt::Result,
+    ) -> fmt::Result {
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                w.write_str(", ")?;
+            }
+            write_item(w, item)?;
+        }
+        Ok(())
+    }
+}
+
+/// The lines of a body in its textual representation (see `LirPrinter::write_body`),
+/// starting from `1`. They are the source locations of the debug info of a body
+/// without a source (i.e., whose span is a dummy), whose source file is the printed body.
+pub struct BodyLines {
+    /// The line of the signature of the body.
+    fn_line: u32,
+    /// The number of the return value and the arguments, which are declared in the
+    /// signature, while the other locals are declared on their own line.
+    arg_count: usize,
+    /// The line of the first statement (or of the terminator) of each block.
+    block_lines: IdxVec<BasicBlock, u32>,
+}
+
+impl BodyLines {
+    pub fn new(lir_ctx: &LirCtx, lir_body: &LirBody) -> Self {
+        // It mirrors the lines written by `BodyPrinter::write_body`.
+        // The attributes (and the personality and the drop glue) are written one per
+        // line, before the signature.
+        let metadata = &lir_body.metadata;
+        let fn_line = 1
+            + metadata.attrs.names().count() as u32
+            + metadata.personality.is_some() as u32
+            + lir_ctx.dropped_ty(metadata.def_id).is_some() as u32;
+        let mut line = fn_line + lir_body.locals.len() as u32;
+        let mut block_lines = IdxVec::new();
+        for (bb, bb_data) in lir_body.basic_blocks.iter_enumerated() {
+            if bb.idx() > 0 || !lir_body.locals.is_empty() {
+                line += 1;
+            }
+            // The `bbN: {` line.
+            line += 1;
+            block_lines.push(line + 1);
+            // The statements, the terminator and the closing brace.
+            line += bb_data.statements.len() as u32 + 2;
+        }
+        BodyLines {
+            fn_line,
+            arg_count: lir_body.ret_and_args.len(),
+            block_lines,
+        }
+    }
+
+    /// Returns the line of the signature of the body.
+    pub fn fn_line(&self) -> u32 {
+        self.fn_line
+    }
+
+    /// Returns the line where the given local is declared.
+    pub fn local_line(&self, local: Local) -> u32 {
+        match local.idx().checked_sub(self.arg_count) {
+            Some(idx) => self.fn_line + 1 + idx as u32,
+            None => self.fn_line,
+        }
+    }
+
+    /// Returns the line of the `idx`-th statement of the given block, where the
+    /// terminator follows the statements.
+    pub fn statement_line(&self, bb: BasicBlock, idx: usize) -> u32 {
+        self.block_lines[bb] + idx as u32
+    }
+}
+
+/// Prints a single body. The types of its locals are needed to print the names
+/// of the variants in the downcasts.
+struct BodyPrinter<'a, 'p> {
+    printer: &'p LirPrinter<'a>,
+    lir_body: &'p LirBody,
+}
+
+impl BodyPrinter<'_, '_> {
+    fn write_body(&self, w: &mut impl Write) -> fmt::Result {
+        let printer = self.printer;
+        let metadata = &self.lir_body.metadata;
+        for attr in metadata.attrs.names() {
+            writeln!(w, "#[{}]", attr)?;
+        }
+        if let Some(personality) = &metadata.personality {
+            w.write_str("#[personality(")?;
+            write_str_literal(w, personality)?;
+            writeln!(w, ")]")?;
+        }
+        if let Some(ty) = printer.lir_ctx.dropped_ty(metadata.def_id) {
+            w.write_str("#[drop_glue(")?;
+            self.write_ty(w, ty)?;
+            writeln!(w, ")]")?;
+        }
+        if self.lir_body.is_const() {
+            w.write_str("const ")?;
+        }
+        write!(w, "fn {}", metadata.name)?;
+        if !self.lir_body.generics.is_empty() {
+            w.write_char('<')?;
+            printer.write_comma_separated(w, &self.lir_body.generics.params, |w, name| {
+                w.write_str(name)
+            })?;
+            w.write_char('>')?;
+        }
+        w.write_char('(')?;
+        let args: Vec<_> = self
+            .lir_body
+            .ret_and_args
+            .iter_enumerated()
+            .skip(1)
+            .collect();
+        printer.write_comma_separated(w, &args, |w, (local, local_data)| {
+            self.write_local_decl(w, *local, local_data)
+        })?;
+        write_variadic(w, metadata.is_variadic, args.is_empty())?;
+        w.write_char(')')?;
+        let ret_ty = &self.lir_body.ret_and_args[RETURN_LOCAL].ty;
+        if *ret_ty != LirTy::unit() {
+            w.write_str(" -> ")?;
+            self.write_ty(w, ret_ty)?;
+        }
+        if self.lir_body.is_declaration() {
+            return writeln!(w, ";");
+        }
+        writeln!(w, " {{")?;
+
+        let local_offset = self.lir_body.ret_and_args.len();
+        for (local, local_data) in self.lir_body.locals.iter_enumerated() {
+            w.write_str("    let ")?;
+            self.write_local_decl(w, Local::new(local.idx() + local_offset), local_data)?;
+            writeln!(w, ";")?;
+        }
+
+        for (bb, bb_data) in self.lir_body.basic_blocks.iter_enumerated() {
+            if bb.idx() > 0 || !self.lir_body.locals.is_empty() {
+                writeln!(w)?;
+            }
+            let cleanup = if bb_data.is_cleanup() {
+                " (cleanup)"
+            } else {
+                ""
+            };
+            writeln!(w, "    bb{}{}: {{", bb.idx(), cleanup)?;
+            self.write_block(w, bb_data)?;
+            writeln!(w, "    }}")?;
+        }
+        writeln!(w, "}}")
+    }
+
+    fn write_ty(&self, w: &mut impl Write, ty: &LirTy) -> fmt::Result {
+        self.printer.write_ty(w, ty, &self.lir_body.generics)
+    }
+
+    fn write_local_decl(
+        &self,
+        w: &mut impl Write,
+        local: Local,
+        local_data: &LocalData,
+    ) -> fmt::Result {
+        if local_data.mutable {
+            w.write_str("mut ")?;
+        }
+        write!(w, "_{}", local.idx())?;
+        if let Some(name) = local_data.name {
+            write!(w, " as {}", self.printer.lir_ctx.symbol_str(name))?;
+        }
+        w.write_str(": ")?;
+        self.write_ty(w, &local_data.ty)
+    }
+
+    fn write_block(&self, w: &mut impl Write, bb_data: &BasicBlockData) -> fmt::Result {
+        for stmt in &bb_data.statements {
+            w.write_str("        ")?;
+            self.write_statement(w, stmt)?;
+            writeln!(w, ";")?;
+        }
+        w.write_str("        ")?;
+        self.write_terminator(w, &bb_data.terminator)?;
+        writeln!(w, ";")
+    }
+
+    fn write_statement(&self, w: &mut impl Write, stmt: &Statement) -> fmt::Result {
+        match &stmt.kind {
+            StatementKind::Assign(assign) => {
+                let (place, rvalue) = &**assign;
+                self.write_place(w, place)?;
+                w.write_str(" = ")?;
+                self.write_rvalue(w, rvalue)
+            }
+            StatementKind::SetDiscriminant { place, variant_idx } => {
+                w.write_str("discriminant(")?;
+                self.write_place(w, place)?;
+                write!(w, ") = {}", variant_idx.idx())
+            }
+            StatementKind::InlineAsm(asm) => self.write_inline_asm(w, asm),
+        }
+    }
+
+    /// Writes `asm!("template", operands, clobbers("rcx"), options(nomem))`, where the
+    /// clobbers and the options are omitted if there are none.
+    fn write_inline_asm(&self, w: &mut impl Write, asm: &InlineAsm) -> fmt::Result {
+        w.write_str("asm!(")?;
+        write_str_literal(w, &asm.template)?;
+        for operand in &asm.operands {
+            w.write_str(", ")?;
+            let (dir, reg) = match operand {
+                InlineAsmOperand::In { reg, .. } => ("in", reg),
+                InlineAsmOperand::Out { reg, .. } => ("out", reg),
+                InlineAsmOperand::InOut { reg, .. } => ("inout", reg),
+            };
+            write!(w, "{}(", dir)?;
+            match reg {
+                InlineAsmRegOrRegClass::Reg(reg) => write_str_literal(w, reg)?,
+                InlineAsmRegOrRegClass::RegClass(class) => w.write_str(class)?,
+            }
+            w.write_str(") ")?;
+            if let Some(value) = operand.in_value() {
+                self.write_operand(w, value)?;
+            }
+            if let InlineAsmOperand::InOut { .. } = operand {
+                w.write_str(" => ")?;
+            }
+            if !matches!(operand, InlineAsmOperand::In { .. }) {
+                match operand.out_place() {
+                    Some(place) => self.write_place(w, place)?,
+                    None => w.write_char('_')?,
+                }
+            }
+        }
+        if !asm.clobbers.is_empty() {
+            w.write_str(", clobbers(")?;
+            self.printer
+                .write_comma_separated(w, &asm.clobbers, |w, reg| write_str_literal(w, reg))?;
+            w.write_char(')')?;
+        }
+        if asm.options != InlineAsmOptions::default() {
+            let options: Vec<_> = asm.options.names().collect();
+            write!(w, ", options({})", options.join(", "))?;
+        }
+        w.write_char(')')
+    }
+
+    fn write_terminator(&self, w: &mut impl Write, terminator: &Terminator) -> fmt::Result {
+        match &terminator.kind {
+            TerminatorKind::Goto { target } => write!(w, "goto -> bb{}", target.idx()),
+            TerminatorKind::Return => w.write_str("return"),
+            TerminatorKind::Unreachable => w.write_str("unreachable"),
+            TerminatorKind::Assert {
+                cond,
+                expected,
+                msg,
+                target,
+                unwind,
+                hint,
+            } => {
+                w.write_str("assert(")?;
+                if !expected {
+                    w.write_char('!')?;
+                }
+                self.write_operand(w, cond)?;
+                write!(w, ", {:?}", msg)?;
+                if let Some(hint) = hint {
+                    write!(w, ", {}", hint.name())?;
+                }
+                w.write_str(") -> ")?;
+                write_target_and_unwind(w, "success", *target, *unwind)
+            }
+            TerminatorKind::Call {
+                func,
+                args,
+                destination,
+                target,
+                unwind,
+            } => {
+                self.write_place(w, destination)?;
+                w.write_str(" = ")?;
+                match func {
+                    Callee::Direct(def_id) => w.write_str(&self.printer.fn_name(*def_id))?,
+                    Callee::Indirect(fn_ptr) => self.write_operand(w, fn_ptr)?,
+                    Callee::Generic(def_id, generic_args) => {
+                        write!(w, "{}::<", self.printer.fn_name(*def_id))?;
+                        self.printer
+                            .write_comma_separated(w, &generic_args.0, |w, ty| {
+                                self.write_ty(w, ty)
+                            })?;
+                        w.write_char('>')?;
+                    }
+                }
+                w.write_char('(')?;
+                self.printer
+                    .write_comma_separated(w, args, |w, arg| self.write_operand(w, arg))?;
+                w.write_char(')')?;
+                match (target, unwind) {
+                    (Some(target), _) => {
+                        w.write_str(" -> ")?;
+                        write_target_and_unwind(w, "return", *target, *unwind)
+                    }
+                    (None, UnwindAction::Continue) => Ok(()),
+                    (None, UnwindAction::Unreachable) => w.write_str(" -> [unwind: unreachable]"),
+                    (None, UnwindAction::Cleanup(cleanup)) => {
+                        write!(w, " -> [unwind: bb{}]", cleanup.idx())
+                    }
+                }
+            }
+            TerminatorKind::Drop {
+                place,
+                target,
+                unwind,
+            } => {
+                w.write_str("drop(")?;
+                self.write_place(w, place)?;
+                w.write_str(") -> ")?;
+                write_target_and_unwind(w, "return", *target, *unwind)
+            }
+            TerminatorKind::UnwindResume => w.write_str("resume"),
+        }
+    }
+
+    fn write_rvalue(&self, w: &mut impl Write, rvalue: &RValue) -> fmt::Result {
+        match rvalue {
+            RValue::Use(operand) => self.write_operand(w, operand),
+            RValue::BinaryOp(bin_op, lhs, rhs) => {
+                write!(w, "{:?}(", bin_op)?;
+                self.write_operand(w, lhs)?;
+                w.write_str(", ")?;
+                self.write_operand(w, rhs)?;
+                w.write_char(')')
+            }
+            RValue::UnaryOp(un_op, operand) => {
+                write!(w, "{:?}(", un_op)?;
+                self.write_operand(w, operand)?;
+                w.write_char(')')
+            }
+            RValue::Ref(place) => {
+                w.write_char('&')?;
+                self.write_place(w, place)
+            }
+            RValue::Cast(cast_kind, operand, ty) => {
+                self.write_operand(w, operand)?;
+                w.write_str(" as ")?;
+                self.write_ty(w, ty)?;
+                write!(w, " ({:?})", cast_kind)
+            }
+            RValue::Discriminant(place) => {
+                w.write_str("discriminant(")?;
+                self.write_place(w, place)?;
+                w.write_char(')')
+            }
+            RValue::Intrinsic(intrinsic, args) => {
+                write!(w, "intrinsic::{}(", intrinsic.name())?;
+                self.printer
+                    .write_comma_separated(w, args, |w, arg| self.write_operand(w, arg))?;
+                w.write_char(')')
+            }
+        }
+    }
+
+    fn write_operand(&self, w: &mut impl Write, operand: &Operand) -> fmt::Result {
+        match operand {
+            Operand::Copy(place) => {
+                w.write_str("copy ")?;
+                self.write_place(w, place)
+            }
+            Operand::Move(place) => {
+                w.write_str("move ")?;
+                self.write_place(w, place)
+            }
+            Operand::Const(ConstOperand::Value(value, ty)) => {
+                w.write_str("const ")?;
+                self.printer.write_const(w, value, ty)
+            }
+        }
+    }
+
+    fn write_place(&self, w: &mut impl Write, place: &Place) -> fmt::Result {
+        let lir_ctx = self.printer.lir_ctx;
+        let mut place_ty = PlaceTy::from_ty(self.local_ty(place.local).clone());
+        let mut out = format!("_{}", place.local.idx());
+        for projection in &place.projection {
+            out = match projection {
+                Projection::Deref => format!("(*{})", out),
+                Projection::Field(field_idx) => format!("{}.{}", out, field_idx.idx()),
+                Projection::Index(local) => format!("{}[_{}]", out, local.idx()),
+                Projection::Downcast(variant_idx) => {
+                    let LirTy::Adt(adt_id) = &place_ty.ty else {
+                        panic!("Downcast of type {:?}", place_ty.ty);
+                    };
+                    let variant = &lir_ctx.adt_def(*adt_id).variants[*variant_idx];
+                    format!("({} as {})", out, variant.name)
+                }
+            };
+            place_ty = place_ty.projection_ty(lir_ctx, projection);
+        }
+        w.write_str(&out)
+    }
+
+    fn local_ty(&self, local: Local) -> &LirTy {
+        let ret_and_args_len = self.lir_body.ret_and_args.len();
+        if local.idx() < ret_and_args_len {
+            &self.lir_body.ret_and_args[local].ty
+        } else {
+            &self.lir_body.locals[Local::new(local.idx() - ret_and_args_len)].ty
+        }
+    }
+}
+
+/// Returns the name of the given primitive type (e.g., `i32`), if it is one.
+fn prim_ty_name(ty: &LirTy) -> Option<&'static str> {
+    let name = match ty {
+        LirTy::Bool => "bool",
+        LirTy::I8 => "i8",
+        LirTy::I16 => "i16",
+        LirTy::I32 => "i32",
+        LirTy::I64 => "i64",
+        LirTy::I128 => "i128",
+        LirTy::U8 => "u8",
+        LirTy::U16 => "u16",
+        LirTy::U32 => "u32",
+        LirTy::U64 => "u64",
+        LirTy::U128 => "u128",
+        LirTy::F16 => "f16",
+        LirTy::F32 => "f32",
+        LirTy::F64 => "f64",
+        LirTy::F128 => "f128",
+        LirTy::Never => "!",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// Writes the successors of a terminator that can unwind, that is, `bbN` if it
+/// continues unwinding to the caller, or `[target_kw: bbN, unwind: action]`.
+fn write_target_and_unwind(
+    w: &mut impl Write,
+    target_kw: &str,
+    target: BasicBlock,
+    unwind: UnwindAction,
+) -> fmt::Result {
+    match unwind {
+        UnwindAction::Continue => write!(w, "bb{}", target.idx()),
+        UnwindAction::Unreachable => {
+            write!(
+                w,
+                "[{}: bb{}, unwind: unreachable]",
+                target_kw,
+                target.idx()
+            )
+        }
+        UnwindAction::Cleanup(cleanup) => write!(
+            w,
+            "[{}: bb{}, unwind: bb{}]",
+            target_kw,
+            target.idx(),
+            cleanup.idx()
+        ),
+    }
+}
+
+/// Writes the `...` of a variadic signature, after its arguments (if any).
+fn write_variadic(w: &mut impl Write, is_variadic: bool, no_args: bool) -> fmt::Result {
+    match (is_variadic, no_args) {
+        (false, _) => Ok(()),
+        (true, true) => w.write_str("..."),
+        (true, false) => w.write_str(", ..."),
+    }
+}
+
+/// Writes a string literal, escaping the characters that the lexer escapes.
+fn write_str_literal(w: &mut impl Write, s: &str) -> fmt::Result {
+    w.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '\n' => w.write_str("\\n")?,
+            '\r' => w.write_str("\\r")?,
+            '\t' => w.write_str("\\t")?,
+            '\0' => w.write_str("\\0")?,
+            '\\' => w.write_str("\\\\")?,
+            '"' => w.write_str("\\\"")?,
+            c if c.is_ascii_control() => write!(w, "\\x{:02x}", c as u32)?,
+            c => w.write_char(c)?,
+        }
+    }
+    w.write_char('"')
+}
+
+/// Writes a byte string literal. The non-printable bytes are escaped as `\xNN`.
+fn write_byte_str_literal(w: &mut impl Write, bytes: &[u8]) -> fmt::Result {
+    w.write_str("b\"")?;
+    for byte in bytes {
+        match byte {
+            b'\\' => w.write_str("\\\\")?,
+            b'"' => w.write_str("\\\"")?,
+            b' '..=b'~' => w.write_char(*byte as char)?,
+            _ => write!(w, "\\x{:02x}", byte)?,
+        }
+    }
+    w.write_char('"')
+}
@@ -0,0 +1,151 @@
+//! The definitions and the uses of the locals of a body, from which the analyses find
+//! the locals in SSA form (i.e., defined once, before all their uses).
+//!
+//! The body is walked by `visit_defs_and_uses`, which tells a `DefUseVisitor` where
+//! each local is defined and used, and which locals are accessed through their memory.
+
+use tidec_utils::index_vec::IdxVec;
+
+use crate::{
+    basic_blocks::{BasicBlock, Location, ENTRY_BLOCK},
+    cfg::{self, Dominators},
+    lir::LirBody,
+    syntax::{Local, Place, Projection, TerminatorKind},
+    visit::{MutatingUseContext, PlaceContext, Visitor},
+};
+
+#[derive(Debug, Clone, Copy)]
+/// Where a local is defined.
+// It corresponds to `DefLocation` in rustc_middle/src/mir/mod.rs
+pub enum DefLocation {
+    /// The local is an argument, defined on entry.
+    Argument,
+    /// The local is assigned by the statement (or the inline assembly) at the location.
+    Assignment(Location),
+    /// The local is the destination of the call terminating the block `call`, written
+    /// on the edge to `target` (if the callee returns).
+    CallReturn {
+        call: BasicBlock,
+        target: Option<BasicBlock>,
+    },
+}
+
+impl DefLocation {
+    /// Returns the block at whose end the local holds the value of the definition, if
+    /// any. A block has the value of a call return at its end, as the value is written
+    /// before jumping to the target.
+    pub fn block(&self) -> Option<BasicBlock> {
+        match self {
+            DefLocation::Argument => Some(ENTRY_BLOCK),
+            DefLocation::Assignment(location) => Some(location.block),
+            DefLocation::CallReturn { call, target } => target.map(|_| *call),
+        }
+    }
+
+    /// Returns `true` if the definition happens before the given location on every path
+    /// to it.
+    pub fn dominates(
+        &self,
+        location: Location,
+        dominators: &Dominators,
+        predecessors: &IdxVec<BasicBlock, Vec<BasicBlock>>,
+    ) -> bool {
+        match *self {
+            DefLocation::Argument => true,
+            DefLocation::Assignment(def) if def.block == location.block => {
+                def.statement_index < location.statement_index
+            }
+            DefLocation::Assignment(def) => dominators.dominates(def.block, location.block),
+            // The target is entered only from the call, so the edge dominates the
+            // blocks that the target dominates.
+            DefLocation::CallReturn {
+                call,
+                target: Some(target),
+            } => {
+                predecessors[target].as_slice() == [call]
+                    && dominators.dominates(target, location.block)
+            }
+            DefLocation::CallReturn { target: None, .. } => false,
+        }
+    }
+}
+
+/// What `visit_defs_and_uses` tells about the locals of a body. The definitions of the
+/// arguments (on entry) are not visited.
+pub trait DefUseVisitor {
+    /// The local is defined at the given location.
+    fn visit_def(&mut self, local: Local, def: DefLocation);
+
+    /// The value of the local is used at the given location (e.g., read, or
+    /// dereferenced).
+    fn visit_use(&mut self, local: Local, location: Location);
+
+    /// The local is accessed through its memory for the given reason (e.g., its address
+    /// is taken), so it is not a single value.
+    fn visit_memory_access(&mut self, local: Local, reason: &str);
+}
+
+/// Visits the definitions and the uses of the locals of the body (see `DefUseVisitor`).
+/// The reachable blocks are visited in reverse postorder, so that the definitions are
+/// visited before the uses they dominate.
+pub fn visit_defs_and_uses(lir_body: &LirBody, visitor: &mut impl DefUseVisitor) {
+    let mut walker = DefUseWalker { lir_body, visitor };
+    for bb in cfg::reverse_postorder(&lir_body.basic_blocks) {
+        walker.visit_basic_block_data(bb, &lir_body.basic_blocks[bb]);
+    }
+}
+
+/// The LIR visitor behind `visit_defs_and_uses`.
+struct DefUseWalker<'a, V> {
+    lir_body: &'a LirBody,
+    visitor: &'a mut V,
+}
+
+impl<V: DefUseVisitor> Visitor for DefUseWalker<'_, V> {
+    fn visit_place(&mut self, place: &Place, context: PlaceContext, location: Location) {
+        match place.projection.first() {
+            None => self.visit_local(place.local, context, location),
+            // Dereferencing a pointer only needs its value, not its address.
+            Some(Projection::Deref) => self.visitor.visit_use(place.local, location),
+            Some(_) => self.visitor.visit_memory_access(place.local, "projected"),
+        }
+        for projection in &place.projection {
+            if let Projection::Index(index) = projection {
+                self.visitor.visit_use(*index, location);
+            }
+        }
+    }
+
+    fn visit_local(&mut self, local: Local, context: PlaceContext, location: Location) {
+        match context {
+            PlaceContext::MutatingUse(
+                MutatingUseContext::Store | MutatingUseContext::AsmOutput,
+            ) => self
+                .visitor
+                .visit_def(local, DefLocation::Assignment(location)),
+            PlaceContext::MutatingUse(MutatingUseContext::Call) => {
+                let terminator = &self.lir_body.basic_blocks[location.block].terminator;
+                let TerminatorKind::Call { target, .. } = terminator.kind else {
+                    unreachable!("Only a call defines its destination")
+                };
+                self.visitor.visit_def(
+                    local,
+                    DefLocation::CallReturn {
+                        call: location.block,
+                        target,
+                    },
+                );
+            }
+            PlaceContext::MutatingUse(MutatingUseContext::AddressOf) => {
+                self.visitor.visit_memory_access(local, "borrowed")
+            }
+            PlaceContext::MutatingUse(MutatingUseContext::Drop) => {
+                self.visitor.visit_memory_access(local, "dropped")
+            }
+            PlaceContext::MutatingUse(
+                MutatingUseContext::SetDiscriminant | MutatingUseContext::Projection,
+            ) => self.visitor.visit_memory_access(local, "written in part"),
+            PlaceContext::NonMutatingUse(_) => self.visitor.visit_use(local, location),
+        }
+    }
+}
@@ -1,40 +1,354 @@
 use std::num::NonZero;
 
-use tidec_abi::size_and_align::Size;
-use tidec_utils::idx::Idx;
+use tidec_abi::{
+    layout::{ReprOptions, VariantIdx},
+    size_and_align::{Align, Size},
+    target::AddressSpace,
+};
+use tidec_span::Span;
+use tidec_utils::{idx::Idx, index_vec::IdxVec, newtype_index};
 
-#[derive(Debug, Copy, Clone)]
+use crate::basic_blocks::BasicBlock;
+use crate::lir::{DefId, LirCtx, Symbol};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum LirTy {
+    /// A boolean. It is the result type of comparisons.
+    Bool,
+
     I8,
     I16,
     I32,
     I64,
     I128,
 
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+
+    F16,
+    F32,
+    F64,
+    F128,
+
+    /// A raw pointer to a value of the given type, living in the given address space.
+    ///
+    /// The pointee type is only used to type the places reached through the pointer
+    /// (e.g., by `Deref`). At codegen level, pointers are opaque.
+    Ptr(Box<LirTy>, AddressSpace),
+
+    /// A fixed-size array of the given number of elements of the given type.
+    Array(Box<LirTy>, u64),
+
+    /// An algebraic data type (e.g., a struct or an enum). Its definition is stored in the `LirCtx`.
+    Adt(AdtId),
+
+    /// A tuple of the given types. The empty tuple `()` is the unit type, a ZST.
+    Tuple(Vec<LirTy>),
+
+    /// A pointer to a function with the given signature. It is never null.
+    FnPtr(Box<FnSig>),
+
+    /// A reference to a contiguous sequence of elements of the given type (e.g., `&[T]`).
+    ///
+    /// It is a fat pointer, made of the address of the first element and the number
+    /// of elements. A string (`&str`) is a slice of `U8`s.
+    Slice(Box<LirTy>),
+
+    /// The never type `!`, which has no values. A function returning `!` never returns
+    /// (e.g., `abort`), so a call of it has no `target` block.
+    Never,
+
+    /// The generic parameter with the given index of the enclosing body (see
+    /// `LirBody::generics`). It is replaced by a concrete type when the body is
+    /// instantiated, so it never reaches codegen.
+    Param(usize),
+
     // https://llvm.org/docs/TypeMetadata.html
     Metadata,
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
-/// A `Local` variable in the LIR.
+impl LirTy {
+    /// Returns `true` if the type is a signed integer.
+    pub fn is_signed(&self) -> bool {
+        matches!(
+            self,
+            LirTy::I8 | LirTy::I16 | LirTy::I32 | LirTy::I64 | LirTy::I128
+        )
+    }
+
+    /// Returns `true` if the type is a floating-point type.
+    pub fn is_floating_point(&self) -> bool {
+        matches!(self, LirTy::F16 | LirTy::F32 | LirTy::F64 | LirTy::F128)
+    }
+
+    /// Returns the type of a variadic argument of this type, after the C default
+    /// argument promotions: `bool` and the integers smaller than `i32` become `i32`,
+    /// while `f16` and `f32` become `f64`.
+    pub fn c_variadic_promoted(&self) -> LirTy {
+        match self {
+            LirTy::Bool | LirTy::I8 | LirTy::I16 | LirTy::U8 | LirTy::U16 => LirTy::I32,
+            LirTy::F16 | LirTy::F32 => LirTy::F64,
+            _ => self.clone(),
+        }
+    }
+
+    /// Returns the unit type `()`.
+    pub fn unit() -> LirTy {
+        LirTy::Tuple(vec![])
+    }
+
+    /// Returns a pointer to the given type in the default address space.
+    pub fn ptr_to(pointee: LirTy) -> LirTy {
+        LirTy::Ptr(Box::new(pointee), AddressSpace::DATA)
+    }
+
+    /// Returns the type of a string, that is, a slice of UTF-8 bytes.
+    pub fn str() -> LirTy {
+        LirTy::Slice(Box::new(LirTy::U8))
+    }
+
+    /// Returns `true` if the type contains a generic parameter.
+    pub fn has_params(&self) -> bool {
+        match self {
+            LirTy::Param(_) => true,
+            LirTy::Ptr(ty, _) | LirTy::Array(ty, _) | LirTy::Slice(ty) => ty.has_params(),
+            LirTy::Tuple(field_tys) => field_tys.iter().any(LirTy::has_params),
+            LirTy::FnPtr(fn_sig) => {
+                fn_sig.ret.has_params() || fn_sig.args.iter().any(LirTy::has_params)
+            }
+            _ => false,
+        }
+    }
+
+    /// Replaces the generic parameters in the type with the given arguments.
+    // It corresponds to `EarlyBinder::instantiate` in rustc_middle/src/ty/generic_args.rs
+    pub fn instantiate(&self, args: &GenericArgs) -> LirTy {
+        match self {
+            LirTy::Param(idx) => args
+                .0
+                .get(*idx)
+                .cloned()
+                .unwrap_or_else(|| panic!("Missing generic argument {} in {:?}", idx, args)),
+            LirTy::Ptr(pointee, addr_space) => {
+                LirTy::Ptr(Box::new(pointee.instantiate(args)), *addr_space)
+            }
+            LirTy::Array(elem_ty, count) => {
+                LirTy::Array(Box::new(elem_ty.instantiate(args)), *count)
+            }
+            LirTy::Slice(elem_ty) => LirTy::Slice(Box::new(elem_ty.instantiate(args))),
+            LirTy::Tuple(field_tys) => {
+                LirTy::Tuple(field_tys.iter().map(|ty| ty.instantiate(args)).collect())
+            }
+            LirTy::FnPtr(fn_sig) => LirTy::FnPtr(Box::new(FnSig {
+                ret: fn_sig.ret.instantiate(args),
+                args: fn_sig.args.iter().map(|ty| ty.instantiate(args)).collect(),
+                is_variadic: fn_sig.is_variadic,
+            })),
+            // TODO(bruzzone): ADTs are not generic yet.
+            _ => self.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The generic (type) parameters of a body. The `i`-th parameter is `LirTy::Param(i)`.
 ///
-/// `Local` acts as an index into the set of local variables declared within a function or
-/// basic block. These variables include user-declared bindings, temporaries created
-/// during compilation, and compiler-generated variables such as those for intermediate
-/// values or storage management.
+/// A body with generic parameters is not codegened as is: it is instantiated once for
+/// each list of generic arguments it is called with (see `Callee::Generic`).
+pub struct Generics {
+    /// The names of the parameters, used when the body is printed.
+    pub params: Vec<String>,
+}
+
+impl Generics {
+    /// Returns `true` if there are no generic parameters.
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The generic arguments of an instance of a generic body. The `i`-th argument
+/// replaces `LirTy::Param(i)`.
+// It corresponds to `GenericArgs` in rustc_middle/src/ty/generic_args.rs
+pub struct GenericArgs(pub Vec<LirTy>);
+
+impl GenericArgs {
+    /// Returns `true` if any argument contains a generic parameter.
+    pub fn has_params(&self) -> bool {
+        self.0.iter().any(LirTy::has_params)
+    }
+
+    /// Replaces the generic parameters in the arguments with the given arguments.
+    pub fn instantiate(&self, args: &GenericArgs) -> GenericArgs {
+        GenericArgs(self.0.iter().map(|ty| ty.instantiate(args)).collect())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The signature of a function, that is, the types of its return value and arguments.
+pub struct FnSig {
+    /// The type of the return value.
+    pub ret: LirTy,
+    /// The types of the arguments.
+    pub args: Vec<LirTy>,
+    /// Whether the function takes more arguments than `args` (e.g., `printf`).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub is_variadic: bool,
+}
+
+impl FnSig {
+    /// Returns the return value and the arguments of the signature as locals,
+    /// laid out as the `ret_and_args` of a body with this signature.
+    pub fn ret_and_args(&self) -> IdxVec<Local, LocalData> {
+        std::iter::once(&self.ret)
+            .chain(&self.args)
+            .map(|ty| LocalData {
+                ty: ty.clone(),
+                mutable: false,
+                name: None,
+            })
+            .collect()
+    }
+}
+
+newtype_index! {
+    /// The identifier of an allocation in the `LirCtx`. It is the provenance of the
+    /// constants that live in memory (see `ConstValue::Indirect`).
+    pub struct AllocId {}
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A block of memory known at compile time, such as the bytes of a constant.
 ///
-/// The index (`usize`) identifies the local variable uniquely within its context.
-/// The zeroth local (`Local(0)`) often refers to the return place of a function.
-pub struct Local(usize);
-pub const RETURN_LOCAL: Local = Local(0);
+/// At codegen level, every allocation is emitted as a global.
+// It corresponds to `Allocation` in rustc_middle/src/mir/interpret/allocation.rs
+pub struct Allocation {
+    /// The raw bytes of the allocation, in the target endianness.
+    pub bytes: Vec<u8>,
+    /// The alignment of the allocation.
+    pub align: Align,
+    /// Whether the allocation can be written to.
+    pub mutable: bool,
+}
+
+impl Allocation {
+    /// Returns an immutable allocation with the given bytes and alignment.
+    pub fn from_bytes(bytes: impl Into<Vec<u8>>, align: Align) -> Self {
+        Allocation {
+            bytes: bytes.into(),
+            align,
+            mutable: false,
+        }
+    }
+
+    /// Returns the size of the allocation.
+    pub fn size(&self) -> Size {
+        Size::from_bytes(self.bytes.len())
+    }
+}
+
+newtype_index! {
+    /// The identifier of an algebraic data type (ADT) definition in the `LirCtx`.
+    pub struct AdtId {}
+}
+
+#[derive(Debug, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The definition of an algebraic data type (ADT).
+///
+/// A struct has exactly one variant, while an enum can have any number of variants.
+/// The fields of a variant are reordered to minimize the padding, unless the ADT is
+/// `#[repr(C)]` (see `Layout::univariant`).
+pub struct AdtDef {
+    /// The name of the ADT. It is used, for instance, to name the backend type.
+    pub name: String,
+    /// The kind of the ADT.
+    pub kind: AdtKind,
+    /// The options of the representation of the ADT.
+    pub repr: ReprOptions,
+    /// The variants of the ADT.
+    pub variants: IdxVec<VariantIdx, VariantDef>,
+}
+
+impl AdtDef {
+    /// Returns `true` if the ADT is an enum.
+    pub fn is_enum(&self) -> bool {
+        matches!(self.kind, AdtKind::Enum)
+    }
+
+    /// Returns the only variant of a struct.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the ADT is an enum.
+    pub fn non_enum_variant(&self) -> &VariantDef {
+        assert!(!self.is_enum(), "{} is an enum", self.name);
+        &self.variants[VariantIdx::new(0)]
+    }
+
+    /// Returns the type of the discriminant of an enum. That is, the smallest
+    /// unsigned integer that can hold the index of every variant.
+    pub fn discr_ty(&self) -> LirTy {
+        match self.variants.len() {
+            0..=0x100 => LirTy::U8,
+            0x101..=0x1_0000 => LirTy::U16,
+            _ => LirTy::U32,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The kind of an ADT.
+pub enum AdtKind {
+    Struct,
+    Enum,
+}
+
+#[derive(Debug, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A variant of an ADT.
+pub struct VariantDef {
+    /// The name of the variant.
+    pub name: String,
+    /// The types of the fields, in declaration order.
+    pub fields: IdxVec<FieldIdx, LirTy>,
+}
+
+newtype_index! {
+    /// A `Local` variable in the LIR.
+    ///
+    /// `Local` acts as an index into the set of local variables declared within a function or
+    /// basic block. These variables include user-declared bindings, temporaries created
+    /// during compilation, and compiler-generated variables such as those for intermediate
+    /// values or storage management.
+    ///
+    /// The index identifies the local variable uniquely within its context.
+    /// The zeroth local (`_0`) often refers to the return place of a function.
+    pub struct Local {
+        debug_format = "_{}";
+        niche;
+        const RETURN_LOCAL = 0;
+    }
+}
 
 impl Local {
     pub fn next(&self) -> Local {
-        Local(self.0 + 1)
+        Local::from_u32(self.as_u32() + 1)
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Represents a memory location (or "place") within LIR that can be used
 /// as the target of assignments or the source of loads.
 ///
@@ -68,6 +382,15 @@ pub struct Place {
 }
 
 impl Place {
+    /// Returns the type of the place, given the type of its base local.
+    pub fn ty(&self, lir_ctx: &LirCtx, local_ty: LirTy) -> PlaceTy {
+        self.projection
+            .iter()
+            .fold(PlaceTy::from_ty(local_ty), |place_ty, projection| {
+                place_ty.projection_ty(lir_ctx, projection)
+            })
+    }
+
     #[inline]
     pub fn try_local(&self) -> Option<Local> {
         if self.projection.is_empty() {
@@ -78,7 +401,8 @@ impl Place {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Represents a single step in a `Place` projection path.
 ///
 /// A `Projection` allows navigation into more complex data structures
@@ -90,26 +414,109 @@ impl Place {
 /// - Dereferencing a pointer (e.g., `*p`)
 /// - Indexing into an array or slice (e.g., `[i]`)
 ///
-/// TODO: This enum is currently a placeholder and should be extended with
-/// specific variants such as `Field`, `Deref`, `Index`, etc.
+/// For example,
+/// ```text
+/// (*_1).0[_2] // [Deref, Field(0), Index(_2)]
+/// (_1 as Some).0 // [Downcast(1), Field(0)]
+/// ```
 pub enum Projection {
-    Todo,
+    /// Dereferences a pointer. The resulting place is the pointee.
+    Deref,
+    /// Accesses the field with the given index of an aggregate.
+    Field(FieldIdx),
+    /// Indexes into an array. The index is the value of the given local,
+    /// which must be of type `usize`.
+    Index(Local),
+    /// Views an enum as the given variant, so that the fields of the variant
+    /// can be accessed.
+    Downcast(VariantIdx),
 }
 
-#[derive(Eq, PartialEq)]
-/// A body identifier in the LIR. A body can be a function, a closure, etc.
-pub struct Body(usize);
+#[derive(Debug, Clone)]
+/// The type of a place. A place can also refer to a specific variant of an enum
+/// (after a `Downcast`), whose fields can then be projected.
+pub struct PlaceTy {
+    pub ty: LirTy,
+    /// The variant of the enum, if the place is downcast.
+    pub variant_idx: Option<VariantIdx>,
+}
 
-#[derive(Debug)]
+impl PlaceTy {
+    pub fn from_ty(ty: LirTy) -> Self {
+        PlaceTy {
+            ty,
+            variant_idx: None,
+        }
+    }
+
+    /// Returns the type of the place obtained by applying the given projection
+    /// to a place of this type. The `LirCtx` is used to look up ADT definitions.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the projection cannot be applied to this type.
+    pub fn projection_ty(&self, lir_ctx: &LirCtx, projection: &Projection) -> PlaceTy {
+        match (&self.ty, projection) {
+            (LirTy::Ptr(pointee, _), Projection::Deref) => PlaceTy::from_ty((**pointee).clone()),
+            (LirTy::Array(elem_ty, _), Projection::Index(_)) => {
+                PlaceTy::from_ty((**elem_ty).clone())
+            }
+            (LirTy::Adt(adt_id), Projection::Field(field_idx)) => {
+                let adt_def = lir_ctx.adt_def(*adt_id);
+                let variant = match self.variant_idx {
+                    Some(variant_idx) => &adt_def.variants[variant_idx],
+                    None => adt_def.non_enum_variant(),
+                };
+                PlaceTy::from_ty(variant.fields[*field_idx].clone())
+            }
+            (LirTy::Tuple(field_tys), Projection::Field(field_idx)) => {
+                PlaceTy::from_ty(field_tys[field_idx.idx()].clone())
+            }
+            (LirTy::Slice(elem_ty), Projection::Field(field_idx)) => {
+                let [data_ty, len_ty] = lir_ctx.slice_field_tys(elem_ty);
+                match field_idx.idx() {
+                    0 => PlaceTy::from_ty(data_ty),
+                    1 => PlaceTy::from_ty(len_ty),
+                    _ => panic!("A slice has only two fields, got {:?}", field_idx),
+                }
+            }
+            (LirTy::Adt(adt_id), Projection::Downcast(variant_idx))
+                if lir_ctx.adt_def(*adt_id).is_enum() =>
+            {
+                PlaceTy {
+                    ty: self.ty.clone(),
+                    variant_idx: Some(*variant_idx),
+                }
+            }
+            _ => panic!(
+                "Cannot apply projection {:?} to type {:?}",
+                projection, self
+            ),
+        }
+    }
+}
+
+newtype_index! {
+    /// The index of a field in an aggregate (e.g., a struct or a tuple).
+    pub struct FieldIdx {}
+}
+
+newtype_index! {
+    /// A body identifier in the LIR. A body can be a function, a closure, etc.
+    pub struct Body {}
+}
+
+#[derive(Debug, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Represents a right-hand side (RValue) in LIR during code generation.
 ///
 /// An `RValue` is something that can be **evaluated to produce a value**.  
 /// It corresponds to expressions on the right-hand side of assignments or
 /// the values returned by function calls in source code.
 ///
-/// This enum is currently minimal and only supports **constant values** (`Const`).
-/// Other kinds of RValues, such as copies, moves, or references, may be added
-/// in the future.
+/// The simplest `RValue` is the `Use` of an [`Operand`]: a constant, or a copy
+/// or move of a place. More complex RValues (e.g., binary operations) are
+/// built on top of operands.
 ///
 /// For example,
 /// ```rust
@@ -119,18 +526,274 @@ pub struct Body(usize);
 /// let s = "hi";      // `"hi"` is an operand (a fat pointer and length)
 /// ```
 pub enum RValue {
+    /// Uses an operand as is. That is, a constant, or a copy or move of a place.
+    Use(Operand),
+    /// A binary operation between two operands.
+    ///
+    /// Both operands must have the same type. See [`BinOp::ty`] for the
+    /// type of the result.
+    BinaryOp(BinOp, Operand, Operand),
+    /// A unary operation on an operand. The result has the same type of the operand.
+    UnaryOp(UnOp, Operand),
+    /// Takes the address of a place. The result is a pointer (`LirTy::Ptr`).
+    ///
+    /// The referenced local is forced to live in memory.
+    ///
+    /// For example,
+    /// ```text
+    /// _2 = &_1
+    /// ```
+    Ref(Place),
+    /// Converts an operand to the given type.
+    ///
+    /// See [`CastKind`] for the supported conversions.
+    ///
+    /// For example,
+    /// ```text
+    /// _2 = _1 as i64 (IntToInt)
+    /// ```
+    Cast(CastKind, Operand, LirTy),
+    /// Reads the discriminant of an enum. That is, the index of its active variant.
+    /// The result has the type of the discriminant (see [`AdtDef::discr_ty`]).
+    ///
+    /// For example,
+    /// ```text
+    /// _2 = discriminant(_1)
+    /// ```
+    Discriminant(Place),
+    /// Calls an intrinsic of the compiler with the given arguments. See [`Intrinsic::ty`]
+    /// for the type of the result.
+    ///
+    /// For example,
+    /// ```text
+    /// _2 = intrinsic::ctpop(copy _1)
+    /// ```
+    Intrinsic(Intrinsic, Vec<Operand>),
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The kind of a cast.
+pub enum CastKind {
+    /// Converts between integers (and booleans). If the target is wider, the value is
+    /// sign-extended when the source is signed and zero-extended otherwise. If the
+    /// target is narrower, the value is truncated.
+    IntToInt,
+    /// Converts a floating-point number to an integer, rounding towards zero.
+    FloatToInt,
+    /// Converts an integer to the nearest floating-point number.
+    IntToFloat,
+    /// Converts between floating-point numbers, extending or truncating the precision.
+    FloatToFloat,
+    /// Converts a pointer to an integer (its address).
+    PtrToInt,
+    /// Converts an integer (an address) to a pointer.
+    IntToPtr,
+    /// Converts between pointer types.
+    PtrToPtr,
+    /// Reinterprets the bits of the operand as the target type.
+    /// Both types must have the same size.
+    Transmute,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// An intrinsic of the compiler, that is, an operation lowered by the backends (e.g.,
+/// to an `llvm.*` intrinsic) instead of being called.
+// It corresponds to the intrinsics in rustc_codegen_ssa/src/mir/intrinsic.rs
+pub enum Intrinsic {
+    /// `memcpy(dst, src, len)` copies `len` bytes (a `usize`) from the pointer `src`
+    /// to the pointer `dst`. The two regions must not overlap.
+    Memcpy,
+    /// `memmove(dst, src, len)`, as `memcpy`, but the regions may overlap.
+    Memmove,
+    /// `memset(dst, val, len)` writes the byte `val` (a `u8`) to `len` bytes from `dst`.
+    Memset,
+    /// `ctpop(x)` counts the bits of the integer `x` that are set.
+    Ctpop,
+    /// `ctlz(x)` counts the leading zeros of the integer `x` (its width, if `x` is zero).
+    Ctlz,
+    /// `cttz(x)` counts the trailing zeros of the integer `x` (its width, if `x` is zero).
+    Cttz,
+    /// `bswap(x)` reverses the bytes of the integer `x`, which has an even number of
+    /// bytes.
+    Bswap,
+    /// `bitreverse(x)` reverses the bits of the integer `x`.
+    Bitreverse,
+    /// `saturating_add(x, y)` adds two integers, clamping the result to the bounds of
+    /// their type.
+    SaturatingAdd,
+    /// `saturating_sub(x, y)` subtracts two integers, clamping the result to the bounds
+    /// of their type.
+    SaturatingSub,
+    /// `sqrt(x)` returns the square root of the float `x`.
+    Sqrt,
+    /// `fabs(x)` returns the absolute value of the float `x`.
+    Fabs,
+    /// `fma(x, y, z)` returns `x * y + z` for floats, with a single rounding.
+    Fma,
+}
+
+impl Intrinsic {
+    /// The intrinsics, with their names in the textual LIR (e.g., `intrinsic::ctpop`).
+    pub const NAMES: [(Intrinsic, &'static str); 13] = [
+        (Intrinsic::Memcpy, "memcpy"),
+        (Intrinsic::Memmove, "memmove"),
+        (Intrinsic::Memset, "memset"),
+        (Intrinsic::Ctpop, "ctpop"),
+        (Intrinsic::Ctlz, "ctlz"),
+        (Intrinsic::Cttz, "cttz"),
+        (Intrinsic::Bswap, "bswap"),
+        (Intrinsic::Bitreverse, "bitreverse"),
+        (Intrinsic::SaturatingAdd, "saturating_add"),
+        (Intrinsic::SaturatingSub, "saturating_sub"),
+        (Intrinsic::Sqrt, "sqrt"),
+        (Intrinsic::Fabs, "fabs"),
+        (Intrinsic::Fma, "fma"),
+    ];
+
+    /// Returns the intrinsic with the given name, if any.
+    pub fn from_name(name: &str) -> Option<Intrinsic> {
+        Intrinsic::NAMES
+            .into_iter()
+            .find(|(_, intrinsic_name)| *intrinsic_name == name)
+            .map(|(intrinsic, _)| intrinsic)
+    }
+
+    pub fn name(self) -> &'static str {
+        Intrinsic::NAMES
+            .into_iter()
+            .find(|(intrinsic, _)| *intrinsic == self)
+            .map(|(_, name)| name)
+            .expect("Every intrinsic has a name")
+    }
+
+    /// Returns the number of the arguments of the intrinsic.
+    pub fn arg_count(self) -> usize {
+        match self {
+            Intrinsic::Ctpop
+            | Intrinsic::Ctlz
+            | Intrinsic::Cttz
+            | Intrinsic::Bswap
+            | Intrinsic::Bitreverse
+            | Intrinsic::Sqrt
+            | Intrinsic::Fabs => 1,
+            Intrinsic::SaturatingAdd | Intrinsic::SaturatingSub => 2,
+            Intrinsic::Memcpy | Intrinsic::Memmove | Intrinsic::Memset | Intrinsic::Fma => 3,
+        }
+    }
+
+    /// Returns the type of the result of the intrinsic, given the type of its first
+    /// argument. The memory intrinsics return `()`, while the others return a value
+    /// of the type of their arguments.
+    pub fn ty(self, arg_ty: &LirTy) -> LirTy {
+        match self {
+            Intrinsic::Memcpy | Intrinsic::Memmove | Intrinsic::Memset => LirTy::unit(),
+            _ => arg_ty.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// An operand of an `RValue` or a terminator. That is, a value that can be used
+/// directly in a computation.
+///
+/// For example,
+/// ```text
+/// _3 = Add(copy _1, const 1_i32)
+/// _4 = foo(move _2) -> bb1
+/// ```
+pub enum Operand {
+    /// Copies the value stored in the place. The place remains valid.
+    Copy(Place),
+    /// Moves the value out of the place. After this, the place is considered
+    /// uninitialized. At codegen level, a move is lowered as a copy.
+    Move(Place),
     /// A constant value.
     ///
     /// Wraps a `ConstOperand`, which represents a constant known at compile-time.
     /// This includes literals (`42`, `"hi"`), const functions, and other compile-time
     /// evaluable values.
-    ///
-    /// TODO: Consider separating this into a dedicated `Operand` enum with variants like
-    /// `Const`, `Copy`, and `Move` for clarity and future extensibility.
     Const(ConstOperand),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A binary operator.
+///
+/// The semantics of the arithmetic operators depend on the signedness of
+/// the operands (e.g., `Div` is a signed or unsigned division).
+pub enum BinOp {
+    /// The `+` operator (wrapping addition).
+    Add,
+    /// The `-` operator (wrapping subtraction).
+    Sub,
+    /// The `*` operator (wrapping multiplication).
+    Mul,
+    /// The `/` operator. Division by zero is undefined behavior.
+    Div,
+    /// The `%` operator. Remainder by zero is undefined behavior.
+    Rem,
+    /// The `^` operator (bitwise xor).
+    BitXor,
+    /// The `&` operator (bitwise and).
+    BitAnd,
+    /// The `|` operator (bitwise or).
+    BitOr,
+    /// The `<<` operator.
+    Shl,
+    /// The `>>` operator. It is an arithmetic shift for signed integers
+    /// and a logical shift for unsigned integers.
+    Shr,
+    /// The `==` operator.
+    Eq,
+    /// The `<` operator.
+    Lt,
+    /// The `<=` operator.
+    Le,
+    /// The `!=` operator.
+    Ne,
+    /// The `>=` operator.
+    Ge,
+    /// The `>` operator.
+    Gt,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A unary operator.
+pub enum UnOp {
+    /// The `!` operator. It is the logical negation for booleans and the
+    /// bitwise negation for integers.
+    Not,
+    /// The `-` operator. It is the arithmetic negation for signed integers
+    /// and floating-point numbers.
+    Neg,
+}
+
+impl BinOp {
+    /// Returns `true` if the operator is a comparison.
+    pub fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            BinOp::Eq | BinOp::Lt | BinOp::Le | BinOp::Ne | BinOp::Ge | BinOp::Gt
+        )
+    }
+
+    /// Returns the type of the result of the operation, given the type of
+    /// the left-hand side operand.
+    pub fn ty(&self, lhs_ty: &LirTy) -> LirTy {
+        if self.is_comparison() {
+            LirTy::Bool
+        } else {
+            lhs_ty.clone()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 // TODO(bruzzone): Add more variants for different constant types.
 pub enum ConstOperand {
     /// A constant value that can be used in the LIR.
@@ -141,21 +804,21 @@ impl ConstOperand {
     /// Returns the type of the constant operand.
     pub fn ty(&self) -> LirTy {
         match self {
-            ConstOperand::Value(_, ty) => *ty,
+            ConstOperand::Value(_, ty) => ty.clone(),
         }
     }
 
-    pub fn value(&self) -> ConstValue {
+    pub fn value(&self) -> &ConstValue {
         match self {
-            ConstOperand::Value(val, _) => *val,
+            ConstOperand::Value(val, _) => val,
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Represents a constant value.
-// TODO(bruzzone): Add indirect variant. A value not representable by the other variants; needs to be stored in-memory.
-// TODO(bruzzone): Add slice variant for strings, arrays, etc. We could use the `Invariant` variant
+// TODO(bruzzone): Add slice variant for strings, byte strings, etc. We could use the `Invariant` variant
 // to avoid this optimization.
 pub enum ConstValue {
     /// A constant value that is a zero-sized type (ZST).
@@ -163,58 +826,78 @@ pub enum ConstValue {
     /// A constant scalar value.
     /// The consts with this variant have typically a layout that is compatible with scalar types, such as integers, floats, or pointers. That is, the backend representation of the constant is a scalar value.
     Scalar(ConstScalar),
-    // A value that cannot be represented directly by the other variants,
-    // and thus must be stored in memory.
-    //
-    // This is used for constants such as strings, slices, and large or
-    // aggregate values that do not fit into a single scalar or scalar pair.
-    //
-    // # Fields
-    //
-    // * [`alloc_id`] — An abstract identifier for the allocation backing
-    //   this value. Unlike a real machine pointer, an [`AllocId`] refers
-    //   to a constant allocation managed by the compiler. This indirection
-    //   ensures that when a "raw constant" (which is basically just an
-    //   `AllocId`) is turned into a [`ConstValue`] and later converted
-    //   back, the identity of the original allocation is preserved.
-    //
-    // * [`offset`] — A byte offset into the referenced allocation. This
-    //   allows an `Indirect` constant to represent a subslice or substring
-    //   within a larger allocation, rather than always starting at the
-    //   beginning. For example, a slice `&arr[3..]` would use the same
-    //   `AllocId` as `arr`, but with a nonzero offset.
-    //
-    // # Notes
-    //
-    // * This variant must **not** be used for scalars or zero-sized types
-    //   (those are handled by other variants).
-    // * It is perfectly valid, however, for `&str` or other slice types
-    //   to be represented as `Indirect`.
-    //
-    // # Example
-    //
-    // ```rust
-    // // For `const S: &str = "hi";`
-    // // tidec creates a global allocation containing the bytes [104, 105],
-    // // assigns it an `AllocId`, and represents `S` as:
-    //
-    // ConstValue::Indirect {
-    //     alloc_id: <id of "hi">,
-    //     offset: 0,
-    // }
-    // ```
-    // Indirect {
-    //     /// The backing memory of the value. This may cover more than just
-    //     /// the bytes of the current value, e.g. when pointing into a larger
-    //     /// `ConstValue`. The `AllocId` is an abstract identifier for
-    //     /// the allocation.
-    //     alloc_id: AllocId,
-    //     /// The byte offset into the referenced allocation.
-    //     offset: u64,
-    // },
+    /// A constant array, given element by element.
+    ///
+    /// For example, `[1_i32, 2, 3]` is an `Array` of three `Scalar`s of type `LirTy::I32`.
+    Array(Vec<ConstValue>),
+    /// The address of the body identified by the given `DefId`. Its type is a `LirTy::FnPtr`
+    /// whose signature matches the one of the body.
+    FnPtr(DefId),
+    /// A slice of `len` elements, starting at the beginning of the given allocation.
+    /// Its type is a `LirTy::Slice`, and it is lowered to a (pointer, length) pair.
+    ///
+    /// For example, `"hi"` is a `Slice` of length 2 into an allocation with the
+    /// bytes `[104, 105]` (see `LirCtx::const_str`).
+    // It corresponds to `ConstValue::Slice` in rustc_middle/src/mir/consts.rs
+    Slice {
+        /// The allocation holding the elements of the slice.
+        alloc_id: AllocId,
+        /// The number of elements of the slice.
+        len: u64,
+    },
+    /// A value that cannot be represented directly by the other variants,
+    /// and thus must be stored in memory.
+    ///
+    /// This is used for constants such as strings, slices, and large or
+    /// aggregate values that do not fit into a single scalar or scalar pair.
+    ///
+    /// # Fields
+    ///
+    /// * [`alloc_id`] — An abstract identifier for the allocation backing
+    ///   this value. Unlike a real machine pointer, an [`AllocId`] refers
+    ///   to a constant allocation managed by the compiler. This indirection
+    ///   ensures that when a "raw constant" (which is basically just an
+    ///   `AllocId`) is turned into a [`ConstValue`] and later converted
+    ///   back, the identity of the original allocation is preserved.
+    ///
+    /// * [`offset`] — A byte offset into the referenced allocation. This
+    ///   allows an `Indirect` constant to represent a subslice or substring
+    ///   within a larger allocation, rather than always starting at the
+    ///   beginning. For example, a slice `&arr[3..]` would use the same
+    ///   `AllocId` as `arr`, but with a nonzero offset.
+    ///
+    /// # Notes
+    ///
+    /// * This variant must **not** be used for scalars or zero-sized types
+    ///   (those are handled by other variants).
+    /// * It is perfectly valid, however, for `&str` or other slice types
+    ///   to be represented as `Indirect`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// // For `const S: &str = "hi";`
+    /// // tidec creates a global allocation containing the bytes [104, 105],
+    /// // assigns it an `AllocId`, and represents `S` as:
+    ///
+    /// ConstValue::Indirect {
+    ///     alloc_id: <id of "hi">,
+    ///     offset: 0,
+    /// }
+    /// ```
+    Indirect {
+        /// The backing memory of the value. This may cover more than just
+        /// the bytes of the current value, e.g. when pointing into a larger
+        /// `ConstValue`. The `AllocId` is an abstract identifier for
+        /// the allocation.
+        alloc_id: AllocId,
+        /// The byte offset into the referenced allocation.
+        offset: Size,
+    },
 }
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Represents a constant scalar value.
 // TODO(bruzzone): Add pointer variant for constants that are pointers to other constants or memory locations.
 pub enum ConstScalar {
@@ -257,7 +940,8 @@ pub enum ConstScalar {
     // },
 }
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// A compact representation of the raw bytes of a scalar value.
 ///
 /// This type is used in tide's value model (e.g. in [`Scalar`]) to represent
@@ -341,70 +1025,515 @@ impl RawScalarValue {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LocalData {
     pub ty: LirTy,
     pub mutable: bool,
+    /// The name of the local in the source, if any (e.g., `_1 as x` in the textual
+    /// LIR). It only names the local in the emitted code and in its debug info.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub name: Option<Symbol>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// A statement in a basic block.
 ///
 /// A statement is an operation that does not transfer control to another block (i.e., it is not a
 /// terminator of a basic block). It is a part of the block's execution.
-pub enum Statement {
+pub struct Statement {
+    /// The source of the statement, or `Span::DUMMY` if it has been built by the compiler.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub span: Span,
+    pub kind: StatementKind,
+}
+
+impl Statement {
+    /// Returns a statement of the given kind without a source.
+    pub fn dummy(kind: StatementKind) -> Self {
+        Statement {
+            span: Span::DUMMY,
+            kind,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The kind of a `Statement`.
+pub enum StatementKind {
     // An assignment statement. We use a Box to keep the size small.
     Assign(Box<(Place, RValue)>),
+    /// Writes the discriminant of the enum in `place`, making `variant_idx` its active
+    /// variant. The fields of the variant are expected to be written separately.
+    ///
+    /// For example,
+    /// ```text
+    /// discriminant(_1) = 1
+    /// ```
+    SetDiscriminant {
+        place: Box<Place>,
+        variant_idx: VariantIdx,
+    },
+    /// Runs a snippet of inline assembly, which reads its input operands and writes
+    /// its output places.
+    ///
+    /// For example,
+    /// ```text
+    /// asm!("add {0}, {1}", inout(reg) copy _1 => _2, in(reg) copy _3, options(pure, nomem))
+    /// ```
+    InlineAsm(Box<InlineAsm>),
+}
+
+#[derive(Debug, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A snippet of inline assembly.
+// It corresponds to `TerminatorKind::InlineAsm` in rustc_middle/src/mir/syntax.rs
+pub struct InlineAsm {
+    /// The assembly code, in the syntax of the target. The operands are referred to
+    /// by their position, as `{0}`, `{1}`, etc., and the braces are escaped as `{{`
+    /// and `}}`.
+    pub template: String,
+    /// The operands, in the order of the references in the template.
+    pub operands: Vec<InlineAsmOperand>,
+    /// The registers (e.g., `"rcx"`) overwritten by the assembly, besides the ones of
+    /// the outputs.
+    pub clobbers: Vec<String>,
+    pub options: InlineAsmOptions,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The register of an operand of the inline assembly.
+// It corresponds to `InlineAsmRegOrRegClass` in rustc_target/src/asm/mod.rs
+pub enum InlineAsmRegOrRegClass {
+    /// An explicit register (e.g., `"eax"`).
+    Reg(String),
+    /// Any register of the given class (e.g., `reg`), chosen by the backend.
+    RegClass(String),
+}
+
+#[derive(Debug, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// An operand of the inline assembly.
+// It corresponds to `InlineAsmOperand` in rustc_middle/src/mir/syntax.rs
+pub enum InlineAsmOperand {
+    /// A value read by the assembly, e.g., `in(reg) copy _1`.
+    In {
+        reg: InlineAsmRegOrRegClass,
+        value: Operand,
+    },
+    /// A value written by the assembly, e.g., `out(reg) _1`. If the place is `None`
+    /// (i.e., `out("eax") _`), the register is only clobbered.
+    Out {
+        reg: InlineAsmRegOrRegClass,
+        place: Option<Place>,
+    },
+    /// A value read and then overwritten by the assembly in the same register, e.g.,
+    /// `inout(reg) copy _1 => _2`. If the place is `None`, the result is discarded.
+    InOut {
+        reg: InlineAsmRegOrRegClass,
+        in_value: Operand,
+        out_place: Option<Place>,
+    },
+}
+
+impl InlineAsmOperand {
+    pub fn reg(&self) -> &InlineAsmRegOrRegClass {
+        match self {
+            InlineAsmOperand::In { reg, .. }
+            | InlineAsmOperand::Out { reg, .. }
+            | InlineAsmOperand::InOut { reg, .. } => reg,
+        }
+    }
+
+    /// Returns the value read by the operand, if any.
+    pub fn in_value(&self) -> Option<&Operand> {
+        match self {
+            InlineAsmOperand::In { value, .. } => Some(value),
+            InlineAsmOperand::InOut { in_value, .. } => Some(in_value),
+            InlineAsmOperand::Out { .. } => None,
+        }
+    }
+
+    /// Returns the place written by the operand, if any.
+    pub fn out_place(&self) -> Option<&Place> {
+        match self {
+            InlineAsmOperand::Out { place, .. } => place.as_ref(),
+            InlineAsmOperand::InOut { out_place, .. } => out_place.as_ref(),
+            InlineAsmOperand::In { .. } => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The set of the options of an inline assembly, which tell what it does besides
+/// reading its inputs and writing its outputs.
+// It corresponds to `InlineAsmOptions` in rustc_ast/src/ast.rs
+pub struct InlineAsmOptions(u8);
+
+impl InlineAsmOptions {
+    /// The assembly has no side effect, and its outputs only depend on its inputs
+    /// (and on the memory, unless `NOMEM`). It may be removed or deduplicated.
+    pub const PURE: InlineAsmOptions = InlineAsmOptions(1 << 0);
+    /// The assembly does not access the memory.
+    pub const NOMEM: InlineAsmOptions = InlineAsmOptions(1 << 1);
+    /// The assembly does not write the memory.
+    pub const READONLY: InlineAsmOptions = InlineAsmOptions(1 << 2);
+    /// The assembly does not change the flags (e.g., the condition codes).
+    pub const PRESERVES_FLAGS: InlineAsmOptions = InlineAsmOptions(1 << 3);
+    /// The assembly does not push to the stack, so the stack does not need to be
+    /// aligned for it.
+    pub const NOSTACK: InlineAsmOptions = InlineAsmOptions(1 << 4);
+    /// On x86, the assembly uses the AT&T syntax instead of the Intel one.
+    pub const ATT_SYNTAX: InlineAsmOptions = InlineAsmOptions(1 << 5);
+
+    /// The options, with their names in the textual LIR (e.g., `options(nomem)`).
+    pub const NAMES: [(InlineAsmOptions, &'static str); 6] = [
+        (InlineAsmOptions::PURE, "pure"),
+        (InlineAsmOptions::NOMEM, "nomem"),
+        (InlineAsmOptions::READONLY, "readonly"),
+        (InlineAsmOptions::PRESERVES_FLAGS, "preserves_flags"),
+        (InlineAsmOptions::NOSTACK, "nostack"),
+        (InlineAsmOptions::ATT_SYNTAX, "att_syntax"),
+    ];
+
+    /// Returns `true` if all the options of `other` are in `self`.
+    pub fn contains(self, other: InlineAsmOptions) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn insert(&mut self, other: InlineAsmOptions) {
+        self.0 |= other.0;
+    }
+
+    /// Returns the names of the options in `self`, in the order of `NAMES`.
+    pub fn names(self) -> impl Iterator<Item = &'static str> {
+        InlineAsmOptions::NAMES
+            .into_iter()
+            .filter(move |(option, _)| self.contains(*option))
+            .map(|(_, name)| name)
+    }
+}
+
+impl std::ops::BitOr for InlineAsmOptions {
+    type Output = InlineAsmOptions;
+
+    fn bitor(self, rhs: InlineAsmOptions) -> InlineAsmOptions {
+        InlineAsmOptions(self.0 | rhs.0)
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// The terminator of a basic block.
 ///
 /// The terminator of a basic block is the last statement of the block.
 /// It is an operation that ends the block and transfers control to another block.
-pub enum Terminator {
+pub struct Terminator {
+    /// The source of the terminator, or `Span::DUMMY` if it has been built by the compiler.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub span: Span,
+    pub kind: TerminatorKind,
+}
+
+impl Terminator {
+    /// Returns a terminator of the given kind without a source.
+    pub fn dummy(kind: TerminatorKind) -> Self {
+        Terminator {
+            span: Span::DUMMY,
+            kind,
+        }
+    }
+
+    /// Returns the blocks the control flow can continue at after this terminator.
+    pub fn successors(&self) -> impl Iterator<Item = BasicBlock> {
+        self.kind.successors()
+    }
+
+    /// Returns the successors of the terminator, mutably.
+    pub fn successors_mut(&mut self) -> impl Iterator<Item = &mut BasicBlock> {
+        self.kind.successors_mut()
+    }
+}
+
+#[derive(Debug, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The kind of a `Terminator`.
+pub enum TerminatorKind {
+    /// Jumps to the `target` block unconditionally.
+    Goto { target: BasicBlock },
     /// Returns from the function.
     ///
     /// The semantics of return is, at least, assign the value in the current
     /// return place (`Local(0)`) to the place specified, via a `Call` terminator
     /// by the caller.
     Return,
+    /// Indicates that the end of the block is never reached.
+    ///
+    /// Reaching this terminator at runtime is undefined behavior. It is used, for
+    /// instance, for dead branches and after calls to diverging functions.
+    Unreachable,
+    /// Checks that `cond` evaluates to `expected`.
+    ///
+    /// If the check succeeds, the control flow continues at the `target` block.
+    /// Otherwise, the panic handler is invoked with the message described by `msg`.
+    /// The panic handler never returns; if it unwinds, the `unwind` action is taken.
+    ///
+    /// The check can be hinted as `likely` or `unlikely` to succeed, so that the backend
+    /// weights the two branches accordingly.
+    ///
+    /// For example,
+    /// ```text
+    /// assert(_2 == const true, "attempt to add with overflow") -> [success: bb1, unwind: continue]
+    /// ```
+    Assert {
+        /// The condition to check. It is considered `true` if it is not zero.
+        cond: Operand,
+        /// The expected value of the condition.
+        expected: bool,
+        /// The kind of check, used to emit the panic message.
+        msg: AssertKind,
+        /// The block to jump to if the check succeeds.
+        target: BasicBlock,
+        /// What to do if the panic handler unwinds.
+        unwind: UnwindAction,
+        /// How likely the check is to succeed, if known.
+        #[cfg_attr(feature = "serde", serde(default))]
+        hint: Option<BranchHint>,
+    },
+    /// Calls a function.
+    ///
+    /// The arguments are evaluated, the callee is invoked, and its return value
+    /// is written to the `destination` place. Then, the control flow continues
+    /// at the `target` block. If the callee unwinds, the `unwind` action is taken.
+    /// A call of a diverging function (i.e., whose destination is of type `!`) has
+    /// no `target`.
+    ///
+    /// For example,
+    /// ```text
+    /// _1 = foo(const 1_i32, const 2_i32) -> [return: bb1, unwind: bb2]
+    /// _2 = abort() -> [unwind: bb2]
+    /// ```
+    Call {
+        /// The function being called.
+        func: Callee,
+        /// The arguments passed to the function.
+        args: Vec<Operand>,
+        /// The place where the return value is written to.
+        destination: Place,
+        /// The block to jump to after the call returns, or `None` if the callee
+        /// never returns.
+        target: Option<BasicBlock>,
+        /// What to do if the callee unwinds.
+        unwind: UnwindAction,
+    },
+    /// Drops the value in `place`, by calling the drop glue of its type (see
+    /// `LirCtx::register_drop_glue`) with a pointer to it. A type without drop glue
+    /// has nothing to drop. Then, the control flow continues at the `target` block. If
+    /// the drop glue unwinds, the `unwind` action is taken.
+    ///
+    /// The place is not deinitialized: it must not be used after the drop.
+    ///
+    /// For example,
+    /// ```text
+    /// drop(_1) -> [return: bb1, unwind: bb2]
+    /// ```
+    // It corresponds to `Drop` in rustc_middle/src/mir/syntax.rs
+    Drop {
+        /// The place whose value is dropped.
+        place: Place,
+        /// The block to jump to after the drop.
+        target: BasicBlock,
+        /// What to do if the drop glue unwinds.
+        unwind: UnwindAction,
+    },
+    /// Continues unwinding to the caller, at the end of a cleanup block.
+    ///
+    /// For example,
+    /// ```text
+    /// resume
+    /// ```
+    // It corresponds to `UnwindResume` in rustc_middle/src/mir/syntax.rs
+    UnwindResume,
 }
 
-////////// Trait implementations  //////////
+impl TerminatorKind {
+    /// Returns the blocks the control flow can continue at after a terminator of this kind.
+    pub fn successors(&self) -> impl Iterator<Item = BasicBlock> {
+        let (target, cleanup) = match self {
+            TerminatorKind::Goto { target } => (Some(*target), None),
+            TerminatorKind::Assert { target, unwind, .. }
+            | TerminatorKind::Drop { target, unwind, .. } => (Some(*target), unwind.cleanup()),
+            TerminatorKind::Call { target, unwind, .. } => (*target, unwind.cleanup()),
+            TerminatorKind::Return | TerminatorKind::Unreachable | TerminatorKind::UnwindResume => {
+                (None, None)
+            }
+        };
+        target.into_iter().chain(cleanup)
+    }
 
-impl Idx for Local {
-    fn new(idx: usize) -> Self {
-        Local(idx)
+    /// Returns the successors of the terminator (see `successors`), mutably.
+    pub fn successors_mut(&mut self) -> impl Iterator<Item = &mut BasicBlock> {
+        let (target, unwind) = match self {
+            TerminatorKind::Goto { target } => (Some(target), None),
+            TerminatorKind::Assert { target, unwind, .. }
+            | TerminatorKind::Drop { target, unwind, .. } => (Some(target), Some(unwind)),
+            TerminatorKind::Call { target, unwind, .. } => (target.as_mut(), Some(unwind)),
+            TerminatorKind::Return | TerminatorKind::Unreachable | TerminatorKind::UnwindResume => {
+                (None, None)
+            }
+        };
+        target
+            .into_iter()
+            .chain(unwind.and_then(UnwindAction::cleanup_mut))
     }
 
-    fn idx(&self) -> usize {
-        self.0
+    /// Returns the unwind action of the terminator, if it can unwind.
+    pub fn unwind(&self) -> Option<&UnwindAction> {
+        match self {
+            TerminatorKind::Assert { unwind, .. }
+            | TerminatorKind::Call { unwind, .. }
+            | TerminatorKind::Drop { unwind, .. } => Some(unwind),
+            TerminatorKind::Goto { .. }
+            | TerminatorKind::Return
+            | TerminatorKind::Unreachable
+            | TerminatorKind::UnwindResume => None,
+        }
     }
 
-    fn incr(&mut self) {
-        self.0 += 1;
+    /// Returns the unwind action of the terminator, mutably, if it can unwind.
+    pub fn unwind_mut(&mut self) -> Option<&mut UnwindAction> {
+        match self {
+            TerminatorKind::Assert { unwind, .. }
+            | TerminatorKind::Call { unwind, .. }
+            | TerminatorKind::Drop { unwind, .. } => Some(unwind),
+            TerminatorKind::Goto { .. }
+            | TerminatorKind::Return
+            | TerminatorKind::Unreachable
+            | TerminatorKind::UnwindResume => None,
+        }
     }
+}
 
-    fn incr_by(&mut self, by: usize) {
-        self.0 += by;
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The kind of a failed `Assert` terminator.
+///
+/// Each kind has an associated static message that is passed to the panic handler.
+pub enum AssertKind {
+    /// An arithmetic operation overflowed.
+    Overflow,
+    /// A division by zero.
+    DivisionByZero,
+    /// A remainder by zero.
+    RemainderByZero,
+    /// An index out of bounds.
+    BoundsCheck,
+}
+
+impl AssertKind {
+    /// Returns the message passed to the panic handler when the assertion fails.
+    pub fn description(&self) -> &'static str {
+        match self {
+            AssertKind::Overflow => "attempt to compute with overflow",
+            AssertKind::DivisionByZero => "attempt to divide by zero",
+            AssertKind::RemainderByZero => {
+                "attempt to calculate the remainder with a divisor of zero"
+            }
+            AssertKind::BoundsCheck => "index out of bounds",
+        }
     }
 }
 
-impl Idx for Body {
-    fn new(idx: usize) -> Self {
-        Body(idx)
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A hint on how likely a branch is to be taken. The backend turns it into branch
+/// weights (e.g., the `!prof` metadata of LLVM), so that the hot path is laid out
+/// first after the optimizations.
+// It corresponds to the `likely` and `unlikely` intrinsics in library/core/src/intrinsics/mod.rs
+pub enum BranchHint {
+    /// The branch is likely to be taken.
+    Likely,
+    /// The branch is unlikely to be taken.
+    Unlikely,
+}
+
+impl BranchHint {
+    /// Returns the hint of the other branch of a two-way branch.
+    pub fn invert(self) -> BranchHint {
+        match self {
+            BranchHint::Likely => BranchHint::Unlikely,
+            BranchHint::Unlikely => BranchHint::Likely,
+        }
+    }
+
+    /// Returns the name of the hint in the textual LIR.
+    pub fn name(self) -> &'static str {
+        match self {
+            BranchHint::Likely => "likely",
+            BranchHint::Unlikely => "unlikely",
+        }
     }
+}
 
-    fn idx(&self) -> usize {
-        self.0
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The action to take when a call (e.g., to the panic handler) unwinds.
+pub enum UnwindAction {
+    /// Continue unwinding to the caller.
+    Continue,
+    /// Unwinding is not possible. That is, reaching this point is undefined behavior.
+    Unreachable,
+    /// Jump to the given cleanup block.
+    Cleanup(BasicBlock),
+}
+
+impl UnwindAction {
+    /// Returns the cleanup block to jump to, if any.
+    pub fn cleanup(&self) -> Option<BasicBlock> {
+        match self {
+            UnwindAction::Cleanup(cleanup) => Some(*cleanup),
+            UnwindAction::Continue | UnwindAction::Unreachable => None,
+        }
     }
 
-    fn incr(&mut self) {
-        self.0 += 1;
+    /// Returns the cleanup block to jump to, mutably, if any.
+    pub fn cleanup_mut(&mut self) -> Option<&mut BasicBlock> {
+        match self {
+            UnwindAction::Cleanup(cleanup) => Some(cleanup),
+            UnwindAction::Continue | UnwindAction::Unreachable => None,
+        }
     }
+}
+
+#[derive(Debug, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The callee of a `Call` terminator.
+pub enum Callee {
+    /// A direct call to the body identified by the given `DefId`.
+    /// The body must be declared (or defined) in the current unit.
+    Direct(DefId),
+    /// A direct call to the instance of the generic body identified by the given `DefId`
+    /// with the given generic arguments.
+    ///
+    /// It is replaced by a `Direct` call to the instance by the monomorphization
+    /// collector, so it never reaches codegen.
+    Generic(DefId, GenericArgs),
+    /// An indirect call through the given operand, which must be a `LirTy::FnPtr`.
+    /// The signature of the function pointer determines the ABI of the call.
+    Indirect(Operand),
+}
+
+////////// Trait implementations  //////////
 
-    fn incr_by(&mut self, by: usize) {
-        self.0 += by;
+impl From<Local> for Place {
+    fn from(local: Local) -> Self {
+        Place {
+            local,
+            projection: vec![],
+        }
     }
 }
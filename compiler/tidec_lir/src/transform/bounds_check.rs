@@ -0,0 +1,269 @@
+use std::num::NonZero;
+
+use tidec_span::Span;
+use tidec_utils::idx::Idx;
+use tracing::debug;
+
+use crate::{
+    basic_blocks::{BasicBlock, BasicBlockData},
+    lir::{LirBody, LirCtx},
+    syntax::{
+        AssertKind, BinOp, BranchHint, Callee, ConstOperand, ConstScalar, ConstValue,
+        InlineAsmOperand, LirTy, Local, LocalData, Operand, Place, PlaceTy, Projection, RValue,
+        RawScalarValue, Statement, StatementKind, Terminator, TerminatorKind, UnwindAction,
+    },
+};
+
+use super::LirPass;
+
+/// Checks that the index of every `Index` projection is in the bounds of the indexed
+/// array, before the statement or the terminator that uses it.
+///
+/// The block is split before the projection: it computes `Lt(copy idx, const len)`
+/// and ends with a `likely` `Assert` of kind `AssertKind::BoundsCheck`, which invokes the
+/// panic handler if the index is out of bounds, while the rest of the block is moved
+/// to a new block. The comparison of a constant index is folded by `ConstFold`.
+///
+/// The pass is only run if `LirCtx::bounds_checks` is set, so that the frontends of
+/// memory-safe languages do not have to emit the checks themselves.
+// It corresponds to the bounds checks built in rustc_mir_build/src/builder/expr/as_place.rs
+pub struct BoundsCheck;
+
+impl LirPass for BoundsCheck {
+    fn name(&self) -> &'static str {
+        "BoundsCheck"
+    }
+
+    fn run_pass(&self, lir_ctx: &LirCtx, lir_body: &mut LirBody) {
+        // The blocks added by the splits are already checked.
+        for bb in 0..lir_body.basic_blocks.len() {
+            check_block(lir_ctx, lir_body, BasicBlock::new(bb));
+        }
+    }
+}
+
+/// An index to check: the local holding it and the length of the indexed array.
+struct IndexCheck {
+    index: Local,
+    len: u64,
+}
+
+/// Inserts the checks of the indices used by the statements and the terminator of
+/// the given block, splitting it before each check.
+fn check_block(lir_ctx: &LirCtx, lir_body: &mut LirBody, bb: BasicBlock) {
+    let statements = std::mem::take(&mut lir_body.basic_blocks[bb].statements);
+    let terminator = std::mem::replace(
+        &mut lir_body.basic_blocks[bb].terminator,
+        Terminator::dummy(TerminatorKind::Unreachable),
+    );
+    let mut splitter = BlockSplitter {
+        lir_ctx,
+        bb,
+        statements: Vec::with_capacity(statements.len()),
+    };
+    for stmt in statements {
+        for check in statement_checks(lir_ctx, lir_body, &stmt.kind) {
+            splitter.insert_check(lir_body, check, stmt.span);
+        }
+        splitter.statements.push(stmt);
+    }
+    for check in terminator_checks(lir_ctx, lir_body, &terminator.kind) {
+        splitter.insert_check(lir_body, check, terminator.span);
+    }
+    let bb_data = &mut lir_body.basic_blocks[splitter.bb];
+    bb_data.statements = splitter.statements;
+    bb_data.terminator = terminator;
+}
+
+/// The block being filled with the statements of a split block.
+struct BlockSplitter<'a> {
+    lir_ctx: &'a LirCtx,
+    /// The current block, which receives the next statements.
+    bb: BasicBlock,
+    /// The statements of the current block so far.
+    statements: Vec<Statement>,
+}
+
+impl BlockSplitter<'_> {
+    /// Ends the current block with the check of the given index, and continues in a new
+    /// block if the index is in bounds.
+    fn insert_check(&mut self, lir_body: &mut LirBody, check: IndexCheck, span: Span) {
+        debug!(
+            "Checking the index {:?} against the length {} in {:?}",
+            check.index, check.len, self.bb
+        );
+        let usize_ty = self.lir_ctx.usize_ty();
+        let usize_size = self.lir_ctx.layout_of(usize_ty.clone()).size.bytes() as u8;
+        let len = ConstOperand::Value(
+            ConstValue::Scalar(ConstScalar::Value(RawScalarValue {
+                data: u128::from(check.len),
+                size: NonZero::new(usize_size).expect("A `usize` is not zero-sized"),
+            })),
+            usize_ty,
+        );
+        let in_bounds = Local::new(lir_body.ret_and_args.len() + lir_body.locals.len());
+        lir_body.locals.push(LocalData {
+            ty: LirTy::Bool,
+            mutable: false,
+            name: None,
+        });
+        self.statements.push(Statement {
+            span,
+            kind: StatementKind::Assign(Box::new((
+                in_bounds.into(),
+                RValue::BinaryOp(
+                    BinOp::Lt,
+                    Operand::Copy(check.index.into()),
+                    Operand::Const(len),
+                ),
+            ))),
+        });
+
+        let kind = lir_body.basic_blocks[self.bb].kind;
+        let target = lir_body.basic_blocks.push(BasicBlockData {
+            statements: vec![],
+            terminator: Terminator::dummy(TerminatorKind::Unreachable),
+            kind,
+        });
+        // The panic handler cannot unwind out of a cleanup block.
+        // TODO(bruzzone): unwind to the cleanup of the enclosing scope, once the LIR
+        // records it, so that the locals are dropped.
+        let unwind = if lir_body.basic_blocks[self.bb].is_cleanup() {
+            UnwindAction::Unreachable
+        } else {
+            UnwindAction::Continue
+        };
+        let bb_data = &mut lir_body.basic_blocks[self.bb];
+        bb_data.statements = std::mem::take(&mut self.statements);
+        bb_data.terminator = Terminator {
+            span,
+            kind: TerminatorKind::Assert {
+                cond: Operand::Move(in_bounds.into()),
+                expected: true,
+                msg: AssertKind::BoundsCheck,
+                target,
+                unwind,
+                hint: Some(BranchHint::Likely),
+            },
+        };
+        self.bb = target;
+    }
+}
+
+/// Returns the indices to check before the given statement.
+fn statement_checks(lir_ctx: &LirCtx, lir_body: &LirBody, kind: &StatementKind) -> Vec<IndexCheck> {
+    let mut checks = vec![];
+    match kind {
+        StatementKind::Assign(assign) => {
+            let (place, rvalue) = &**assign;
+            match rvalue {
+                RValue::Use(operand)
+                | RValue::UnaryOp(_, operand)
+                | RValue::Cast(_, operand, _) => {
+                    operand_checks(lir_ctx, lir_body, operand, &mut checks)
+                }
+                RValue::BinaryOp(_, lhs, rhs) => {
+                    operand_checks(lir_ctx, lir_body, lhs, &mut checks);
+                    operand_checks(lir_ctx, lir_body, rhs, &mut checks);
+                }
+                RValue::Ref(place) | RValue::Discriminant(place) => {
+                    place_checks(lir_ctx, lir_body, place, &mut checks)
+                }
+                RValue::Intrinsic(_, args) => {
+                    for arg in args {
+                        operand_checks(lir_ctx, lir_body, arg, &mut checks);
+                    }
+                }
+            }
+            place_checks(lir_ctx, lir_body, place, &mut checks);
+        }
+        StatementKind::SetDiscriminant { place, .. } => {
+            place_checks(lir_ctx, lir_body, place, &mut checks)
+        }
+        StatementKind::InlineAsm(asm) => {
+            for operand in &asm.operands {
+                match operand {
+                    InlineAsmOperand::In { value, .. } => {
+                        operand_checks(lir_ctx, lir_body, value, &mut checks)
+                    }
+                    InlineAsmOperand::Out { place, .. } => {
+                        if let Some(place) = place {
+                            place_checks(lir_ctx, lir_body, place, &mut checks);
+                        }
+                    }
+                    InlineAsmOperand::InOut {
+                        in_value,
+                        out_place,
+                        ..
+                    } => {
+                        operand_checks(lir_ctx, lir_body, in_value, &mut checks);
+                        if let Some(place) = out_place {
+                            place_checks(lir_ctx, lir_body, place, &mut checks);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    checks
+}
+
+/// Returns the indices to check before the given terminator.
+fn terminator_checks(
+    lir_ctx: &LirCtx,
+    lir_body: &LirBody,
+    kind: &TerminatorKind,
+) -> Vec<IndexCheck> {
+    let mut checks = vec![];
+    match kind {
+        TerminatorKind::Call {
+            func,
+            args,
+            destination,
+            ..
+        } => {
+            if let Callee::Indirect(fn_ptr) = func {
+                operand_checks(lir_ctx, lir_body, fn_ptr, &mut checks);
+            }
+            for arg in args {
+                operand_checks(lir_ctx, lir_body, arg, &mut checks);
+            }
+            place_checks(lir_ctx, lir_body, destination, &mut checks);
+        }
+        TerminatorKind::Assert { cond, .. } => operand_checks(lir_ctx, lir_body, cond, &mut checks),
+        TerminatorKind::Drop { place, .. } => place_checks(lir_ctx, lir_body, place, &mut checks),
+        TerminatorKind::Goto { .. }
+        | TerminatorKind::Return
+        | TerminatorKind::Unreachable
+        | TerminatorKind::UnwindResume => {}
+    }
+    checks
+}
+
+fn operand_checks(
+    lir_ctx: &LirCtx,
+    lir_body: &LirBody,
+    operand: &Operand,
+    checks: &mut Vec<IndexCheck>,
+) {
+    match operand {
+        Operand::Copy(place) | Operand::Move(place) => {
+            place_checks(lir_ctx, lir_body, place, checks)
+        }
+        Operand::Const(_) => {}
+    }
+}
+
+/// Adds the checks of the `Index` projections of the given place, in order.
+fn place_checks(lir_ctx: &LirCtx, lir_body: &LirBody, place: &Place, checks: &mut Vec<IndexCheck>) {
+    let mut place_ty = PlaceTy::from_ty(lir_body.local_data(place.local).ty.clone());
+    for projection in &place.projection {
+        if let (LirTy::Array(_, len), Projection::Index(index)) = (&place_ty.ty, projection) {
+            checks.push(IndexCheck {
+                index: *index,
+                len: *len,
+            });
+        }
+        place_ty = place_ty.projection_ty(lir_ctx, projection);
+    }
+}
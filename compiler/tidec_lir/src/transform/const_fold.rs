@@ -0,0 +1,475 @@
+use std::num::NonZero;
+
+use tidec_utils::{idx::Idx, index_vec::IdxVec};
+use tracing::debug;
+
+use crate::{
+    basic_blocks::{BasicBlock, BasicBlockData},
+    cfg::{self, Dominators},
+    lir::{LirBody, LirCtx},
+    syntax::{
+        BinOp, Callee, CastKind, ConstOperand, ConstScalar, ConstValue, InlineAsmOperand, LirTy,
+        Local, Operand, RValue, RawScalarValue, StatementKind, TerminatorKind, UnOp,
+    },
+};
+
+use super::LirPass;
+
+/// Evaluates the `BinaryOp`, `UnaryOp` and `Cast` rvalues whose operands are scalar
+/// constants, and replaces them with the resulting constant.
+///
+/// The constants are also propagated: a local assigned only once, with a constant,
+/// is replaced by the constant in the uses dominated by the assignment. This is
+/// repeated until nothing changes, so that chains of operations are folded entirely.
+///
+/// The operations whose result is undefined behavior or poison at runtime (e.g.,
+/// a division by zero or a shift by the bit width) are left as they are.
+// It corresponds to `ConstProp` in rustc_mir_transform/src/const_prop.rs
+pub struct ConstFold;
+
+impl LirPass for ConstFold {
+    fn name(&self) -> &'static str {
+        "ConstFold"
+    }
+
+    fn run_pass(&self, _lir_ctx: &LirCtx, lir_body: &mut LirBody) {
+        let dominators = Dominators::compute(&lir_body.basic_blocks);
+        let rpo = cfg::reverse_postorder(&lir_body.basic_blocks);
+        loop {
+            let mut changed = false;
+            for bb in &rpo {
+                changed |= fold_rvalues(&mut lir_body.basic_blocks[*bb]);
+            }
+            let consts = const_locals(lir_body);
+            for bb in &rpo {
+                changed |=
+                    propagate_consts(*bb, &mut lir_body.basic_blocks[*bb], &consts, &dominators);
+            }
+            if !changed {
+                return;
+            }
+        }
+    }
+}
+
+/// A constant assigned to a local, with the position of the assignment.
+#[derive(Clone)]
+struct ConstDef {
+    bb: BasicBlock,
+    statement_idx: usize,
+    value: ConstOperand,
+}
+
+/// Replaces the foldable rvalues of the given block with their value.
+/// Returns `true` if any rvalue has been folded.
+fn fold_rvalues(bb_data: &mut BasicBlockData) -> bool {
+    let mut changed = false;
+    for stmt in &mut bb_data.statements {
+        let StatementKind::Assign(assign) = &mut stmt.kind else {
+            continue;
+        };
+        if let Some(value) = eval_rvalue(&assign.1) {
+            debug!("Folded {:?} to {:?}", assign.1, value);
+            assign.1 = RValue::Use(Operand::Const(value));
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// Returns, for each local, the constant it is assigned with, if it is the only
+/// assignment of the local.
+///
+/// The return local and the arguments are never constant, as they are written
+/// outside the body. The same holds for the locals that are borrowed, written
+/// through a projection, by a call or by an inline assembly, and for the dropped
+/// ones, whose drop glue gets a pointer to them.
+fn const_locals(lir_body: &LirBody) -> IdxVec<Local, Option<ConstDef>> {
+    let num_locals = lir_body.ret_and_args.len() + lir_body.locals.len();
+    let mut defs: IdxVec<Local, Option<ConstDef>> = IdxVec::from_elem_n(None, num_locals);
+    let mut excluded: IdxVec<Local, bool> = IdxVec::from_fn_n(
+        |local: Local| local.idx() < lir_body.ret_and_args.len(),
+        num_locals,
+    );
+
+    for (bb, bb_data) in lir_body.basic_blocks.iter_enumerated() {
+        for (statement_idx, stmt) in bb_data.statements.iter().enumerate() {
+            match &stmt.kind {
+                StatementKind::Assign(assign) => {
+                    let (place, rvalue) = &**assign;
+                    if let RValue::Ref(borrowed) = rvalue {
+                        excluded[borrowed.local] = true;
+                    }
+                    let local = place.local;
+                    match (place.try_local(), rvalue, &defs[local]) {
+                        (Some(_), RValue::Use(Operand::Const(value)), None) if is_scalar(value) => {
+                            defs[local] = Some(ConstDef {
+                                bb,
+                                statement_idx,
+                                value: value.clone(),
+                            });
+                        }
+                        _ => excluded[local] = true,
+                    }
+                }
+                StatementKind::SetDiscriminant { place, .. } => excluded[place.local] = true,
+                StatementKind::InlineAsm(asm) => {
+                    for place in asm.operands.iter().filter_map(InlineAsmOperand::out_place) {
+                        excluded[place.local] = true;
+                    }
+                }
+            }
+        }
+        match &bb_data.terminator.kind {
+            TerminatorKind::Call { destination, .. } => excluded[destination.local] = true,
+            TerminatorKind::Drop { place, .. } => excluded[place.local] = true,
+            _ => {}
+        }
+    }
+
+    for (local, def) in defs.iter_enumerated_mut() {
+        if excluded[local] {
+            *def = None;
+        }
+    }
+    defs
+}
+
+/// Replaces the uses of the constant locals in the given block with their value,
+/// when the assignment of the local dominates the use.
+/// Returns `true` if any use has been replaced.
+fn propagate_consts(
+    bb: BasicBlock,
+    bb_data: &mut BasicBlockData,
+    consts: &IdxVec<Local, Option<ConstDef>>,
+    dominators: &Dominators,
+) -> bool {
+    let mut changed = false;
+    let mut propagate = |use_idx: usize, operand: &mut Operand| {
+        let (Operand::Copy(place) | Operand::Move(place)) = operand else {
+            return;
+        };
+        let Some(def) = place.try_local().and_then(|local| consts[local].as_ref()) else {
+            return;
+        };
+        let dominates = if def.bb == bb {
+            def.statement_idx < use_idx
+        } else {
+            dominators.dominates(def.bb, bb)
+        };
+        if dominates {
+            debug!("Propagated {:?} into {:?}", def.value, operand);
+            *operand = Operand::Const(def.value.clone());
+            changed = true;
+        }
+    };
+
+    for (statement_idx, stmt) in bb_data.statements.iter_mut().enumerate() {
+        let StatementKind::Assign(assign) = &mut stmt.kind else {
+            continue;
+        };
+        match &mut assign.1 {
+            RValue::Use(operand) | RValue::UnaryOp(_, operand) | RValue::Cast(_, operand, _) => {
+                propagate(statement_idx, operand)
+            }
+            RValue::BinaryOp(_, lhs, rhs) => {
+                propagate(statement_idx, lhs);
+                propagate(statement_idx, rhs);
+            }
+            RValue::Intrinsic(_, args) => {
+                for arg in args {
+                    propagate(statement_idx, arg);
+                }
+            }
+            RValue::Ref(_) | RValue::Discriminant(_) => {}
+        }
+    }
+
+    let terminator_idx = bb_data.statements.len();
+    match &mut bb_data.terminator.kind {
+        TerminatorKind::Assert { cond, .. } => propagate(terminator_idx, cond),
+        TerminatorKind::Call { func, args, .. } => {
+            if let Callee::Indirect(fn_ptr) = func {
+                propagate(terminator_idx, fn_ptr);
+            }
+            for arg in args {
+                propagate(terminator_idx, arg);
+            }
+        }
+        TerminatorKind::Goto { .. }
+        | TerminatorKind::Return
+        | TerminatorKind::Unreachable
+        | TerminatorKind::Drop { .. }
+        | TerminatorKind::UnwindResume => {}
+    }
+    changed
+}
+
+/// Returns `true` if the constant is a scalar (e.g., an integer or a float).
+fn is_scalar(value: &ConstOperand) -> bool {
+    matches!(value.value(), ConstValue::Scalar(_))
+}
+
+/// Returns the raw bits of the operand, if it is a scalar constant, and its type.
+fn const_scalar(operand: &Operand) -> Option<(u128, LirTy)> {
+    match operand {
+        Operand::Const(ConstOperand::Value(ConstValue::Scalar(ConstScalar::Value(raw)), ty)) => {
+            Some((raw.data, ty.clone()))
+        }
+        _ => None,
+    }
+}
+
+/// Builds a scalar constant of the given type from its raw bits.
+fn scalar_const(data: u128, ty: LirTy) -> Option<ConstOperand> {
+    let size = NonZero::new(scalar_size(&ty)?).unwrap();
+    let raw = RawScalarValue {
+        data: truncate(data, u32::from(size.get()) * 8),
+        size,
+    };
+    Some(ConstOperand::Value(
+        ConstValue::Scalar(ConstScalar::Value(raw)),
+        ty,
+    ))
+}
+
+/// Returns the size in bytes of the given type, if it is a boolean, an integer
+/// or a float.
+fn scalar_size(ty: &LirTy) -> Option<u8> {
+    match ty {
+        LirTy::Bool | LirTy::I8 | LirTy::U8 => Some(1),
+        LirTy::I16 | LirTy::U16 | LirTy::F16 => Some(2),
+        LirTy::I32 | LirTy::U32 | LirTy::F32 => Some(4),
+        LirTy::I64 | LirTy::U64 | LirTy::F64 => Some(8),
+        LirTy::I128 | LirTy::U128 | LirTy::F128 => Some(16),
+        _ => None,
+    }
+}
+
+/// Returns the number of bits of the given type, if it is an integer.
+fn int_bits(ty: &LirTy) -> Option<u32> {
+    if ty.is_floating_point() || *ty == LirTy::Bool {
+        return None;
+    }
+    scalar_size(ty).map(|size| u32::from(size) * 8)
+}
+
+/// Keeps the lowest `bits` bits of `data`.
+fn truncate(data: u128, bits: u32) -> u128 {
+    let shift = 128 - bits;
+    (data << shift) >> shift
+}
+
+/// Interprets the lowest `bits` bits of `data` as a signed integer.
+fn sign_extend(data: u128, bits: u32) -> i128 {
+    let shift = 128 - bits;
+    ((data << shift) as i128) >> shift
+}
+
+/// Evaluates the rvalue, if its operands are constants and its result is defined.
+fn eval_rvalue(rvalue: &RValue) -> Option<ConstOperand> {
+    match rvalue {
+        RValue::BinaryOp(bin_op, lhs, rhs) => {
+            let (lhs, ty) = const_scalar(lhs)?;
+            let (rhs, _) = const_scalar(rhs)?;
+            eval_binop(*bin_op, lhs, rhs, ty)
+        }
+        RValue::UnaryOp(un_op, operand) => {
+            let (operand, ty) = const_scalar(operand)?;
+            eval_unop(*un_op, operand, ty)
+        }
+        RValue::Cast(cast_kind, operand, target_ty) => {
+            let (operand, ty) = const_scalar(operand)?;
+            eval_cast(*cast_kind, operand, &ty, target_ty.clone())
+        }
+        // TODO(bruzzone): evaluate the intrinsics on the integers (e.g., `ctpop`).
+        RValue::Use(_) | RValue::Ref(_) | RValue::Discriminant(_) | RValue::Intrinsic(..) => None,
+    }
+}
+
+/// Evaluates a binary operation between integers or booleans of type `ty`.
+///
+/// The arithmetic is wrapping, as in codegen. Divisions and remainders by zero,
+/// the signed division of the minimum value by `-1` and shifts by an amount
+/// greater than or equal to the bit width are not evaluated.
+fn eval_binop(bin_op: BinOp, lhs: u128, rhs: u128, ty: LirTy) -> Option<ConstOperand> {
+    if ty == LirTy::Bool {
+        let value = match bin_op {
+            BinOp::BitAnd => lhs & rhs,
+            BinOp::BitOr => lhs | rhs,
+            BinOp::BitXor => lhs ^ rhs,
+            _ if bin_op.is_comparison() => eval_comparison(bin_op, lhs.cmp(&rhs)),
+            _ => return None,
+        };
+        return scalar_const(value, LirTy::Bool);
+    }
+
+    let bits = int_bits(&ty)?;
+    let is_signed = ty.is_signed();
+    let (signed_lhs, signed_rhs) = (sign_extend(lhs, bits), sign_extend(rhs, bits));
+    if bin_op.is_comparison() {
+        let ordering = if is_signed {
+            signed_lhs.cmp(&signed_rhs)
+        } else {
+            lhs.cmp(&rhs)
+        };
+        return scalar_const(eval_comparison(bin_op, ordering), LirTy::Bool);
+    }
+
+    let value = match bin_op {
+        BinOp::Add => lhs.wrapping_add(rhs),
+        BinOp::Sub => lhs.wrapping_sub(rhs),
+        BinOp::Mul => lhs.wrapping_mul(rhs),
+        BinOp::Div | BinOp::Rem if rhs == 0 => return None,
+        BinOp::Div | BinOp::Rem if is_signed => {
+            let min = sign_extend(1 << (bits - 1), bits);
+            if signed_lhs == min && signed_rhs == -1 {
+                return None;
+            }
+            if bin_op == BinOp::Div {
+                (signed_lhs / signed_rhs) as u128
+            } else {
+                (signed_lhs % signed_rhs) as u128
+            }
+        }
+        BinOp::Div => lhs / rhs,
+        BinOp::Rem => lhs % rhs,
+        BinOp::BitXor => lhs ^ rhs,
+        BinOp::BitAnd => lhs & rhs,
+        BinOp::BitOr => lhs | rhs,
+        BinOp::Shl | BinOp::Shr if rhs >= u128::from(bits) => return None,
+        BinOp::Shl => lhs << rhs,
+        BinOp::Shr if is_signed => (signed_lhs >> rhs) as u128,
+        BinOp::Shr => lhs >> rhs,
+        BinOp::Eq | BinOp::Lt | BinOp::Le | BinOp::Ne | BinOp::Ge | BinOp::Gt => {
+            unreachable!("Comparisons are evaluated above")
+        }
+    };
+    scalar_const(value, ty)
+}
+
+/// Returns the boolean result (`0` or `1`) of a comparison, given the ordering
+/// of its operands.
+fn eval_comparison(bin_op: BinOp, ordering: std::cmp::Ordering) -> u128 {
+    let result = match bin_op {
+        BinOp::Eq => ordering.is_eq(),
+        BinOp::Ne => ordering.is_ne(),
+        BinOp::Lt => ordering.is_lt(),
+        BinOp::Le => ordering.is_le(),
+        BinOp::Gt => ordering.is_gt(),
+        BinOp::Ge => ordering.is_ge(),
+        _ => panic!("{:?} is not a comparison", bin_op),
+    };
+    u128::from(result)
+}
+
+/// Evaluates a unary operation on a boolean, an integer or a float of type `ty`.
+fn eval_unop(un_op: UnOp, operand: u128, ty: LirTy) -> Option<ConstOperand> {
+    let value = match (un_op, &ty) {
+        (UnOp::Not, LirTy::Bool) => operand ^ 1,
+        // The negation of a float flips its sign bit.
+        (UnOp::Neg, LirTy::F32) => operand ^ (1 << 31),
+        (UnOp::Neg, LirTy::F64) => operand ^ (1 << 63),
+        (UnOp::Not, _) if int_bits(&ty).is_some() => !operand,
+        (UnOp::Neg, _) if int_bits(&ty).is_some() => operand.wrapping_neg(),
+        _ => return None,
+    };
+    scalar_const(value, ty)
+}
+
+/// Evaluates a cast of a scalar of type `ty` to `target_ty`.
+///
+/// Only the casts between booleans, integers, `F32` and `F64` are evaluated.
+/// The conversions of NaNs and out-of-range floats to integers are not evaluated.
+fn eval_cast(
+    cast_kind: CastKind,
+    operand: u128,
+    ty: &LirTy,
+    target_ty: LirTy,
+) -> Option<ConstOperand> {
+    let is_int_or_bool = |ty: &LirTy| *ty == LirTy::Bool || int_bits(ty).is_some();
+    let value = match cast_kind {
+        CastKind::IntToInt if is_int_or_bool(ty) => {
+            let target_bits = int_bits(&target_ty)?;
+            let bits = u32::from(scalar_size(ty)?) * 8;
+            if ty.is_signed() {
+                truncate(sign_extend(operand, bits) as u128, target_bits)
+            } else {
+                truncate(operand, target_bits)
+            }
+        }
+        CastKind::IntToFloat if is_int_or_bool(ty) => {
+            let bits = u32::from(scalar_size(ty)?) * 8;
+            match target_ty {
+                LirTy::F32 => {
+                    let value = if ty.is_signed() {
+                        sign_extend(operand, bits) as f32
+                    } else {
+                        operand as f32
+                    };
+                    // Only a `u128` can overflow an `f32`.
+                    if value.is_infinite() {
+                        return None;
+                    }
+                    u128::from(value.to_bits())
+                }
+                LirTy::F64 if ty.is_signed() => {
+                    u128::from((sign_extend(operand, bits) as f64).to_bits())
+                }
+                LirTy::F64 => u128::from((operand as f64).to_bits()),
+                _ => return None,
+            }
+        }
+        CastKind::FloatToInt => {
+            let value = float_value(operand, ty)?.trunc();
+            let target_bits = int_bits(&target_ty)?;
+            if target_ty.is_signed() {
+                let bound = 2f64.powi(target_bits as i32 - 1);
+                if value.is_nan() || value < -bound || value >= bound {
+                    return None;
+                }
+                value as i128 as u128
+            } else {
+                let bound = 2f64.powi(target_bits as i32);
+                if value.is_nan() || value <= -1.0 || value >= bound {
+                    return None;
+                }
+                value as u128
+            }
+        }
+        CastKind::FloatToFloat => {
+            let value = float_value(operand, ty)?;
+            match target_ty {
+                LirTy::F32 => u128::from((value as f32).to_bits()),
+                LirTy::F64 => u128::from(value.to_bits()),
+                _ => return None,
+            }
+        }
+        CastKind::Transmute => {
+            // A boolean must be `0` or `1`, so it is not a valid target for any bits.
+            let is_valid =
+                |ty: &LirTy| matches!(ty, LirTy::F32 | LirTy::F64) || int_bits(ty).is_some();
+            if !is_valid(ty) || !is_valid(&target_ty) || scalar_size(ty) != scalar_size(&target_ty)
+            {
+                return None;
+            }
+            operand
+        }
+        CastKind::IntToInt
+        | CastKind::IntToFloat
+        | CastKind::PtrToInt
+        | CastKind::IntToPtr
+        | CastKind::PtrToPtr => return None,
+    };
+    scalar_const(value, target_ty)
+}
+
+/// Returns the value of the float with the given raw bits, if its type is
+/// `F32` or `F64`. An `F32` is converted (exactly) to an `f64`.
+fn float_value(data: u128, ty: &LirTy) -> Option<f64> {
+    match ty {
+        LirTy::F32 => Some(f64::from(f32::from_bits(data as u32))),
+        LirTy::F64 => Some(f64::from_bits(data as u64)),
+        _ => None,
+    }
+}
@@ -0,0 +1,200 @@
+use tidec_utils::{idx::Idx, index_vec::IdxVec};
+use tracing::debug;
+
+use crate::{
+    basic_blocks::{BasicBlock, Location},
+    cfg::{self, Dominators},
+    lir::{LirBody, LirCtx},
+    ssa::{self, DefLocation, DefUseVisitor},
+    syntax::{Local, Operand, RValue, StatementKind, RETURN_LOCAL},
+    visit,
+};
+
+use super::LirPass;
+
+/// Replaces the locals that are copies of other locals with the copied ones. Then, it
+/// removes the assignments that became trivial (e.g., `_1 = copy _1`) and the locals
+/// that are not used anymore.
+///
+/// A local `_2` is a copy of `_1` if its only assignment is `_2 = copy _1` (or
+/// `move _1`), and both locals are in SSA form (see `SsaLocals`). Then, `_1` has the
+/// same value as `_2` wherever `_2` is used. The chains of copies (e.g., `_3 = copy _2`)
+/// are replaced by their head.
+// It corresponds to `CopyProp` in rustc_mir_transform/src/copy_prop.rs
+pub struct CopyProp;
+
+impl LirPass for CopyProp {
+    fn name(&self) -> &'static str {
+        "CopyProp"
+    }
+
+    fn run_pass(&self, _lir_ctx: &LirCtx, lir_body: &mut LirBody) {
+        let heads = copy_heads(lir_body);
+        if heads.iter_enumerated().any(|(local, head)| *head != local) {
+            visit::visit_locals_mut(lir_body, |local| *local = heads[*local]);
+        }
+        remove_trivial_assignments(lir_body);
+        remove_unused_locals(lir_body);
+    }
+}
+
+/// Returns, for each local, the local it is a copy of (see `CopyProp`), or itself.
+fn copy_heads(lir_body: &LirBody) -> IdxVec<Local, Local> {
+    let ssa_locals = SsaLocals::new(lir_body);
+    let mut heads = IdxVec::from_fn_n(|local| local, lir_body.num_locals());
+    // In reverse postorder, the assignment of a local comes before its copies, so the
+    // head of the copied local is already known.
+    for bb in cfg::reverse_postorder(&lir_body.basic_blocks) {
+        for stmt in &lir_body.basic_blocks[bb].statements {
+            let StatementKind::Assign(assign) = &stmt.kind else {
+                continue;
+            };
+            let RValue::Use(Operand::Copy(source) | Operand::Move(source)) = &assign.1 else {
+                continue;
+            };
+            let (Some(local), Some(source)) = (assign.0.try_local(), source.try_local()) else {
+                continue;
+            };
+            if ssa_locals.is_ssa(local)
+                && ssa_locals.is_ssa(source)
+                && lir_body.local_data(local).ty == lir_body.local_data(source).ty
+            {
+                debug!("Local {:?} is a copy of {:?}", local, heads[source]);
+                heads[local] = heads[source];
+            }
+        }
+    }
+    heads
+}
+
+/// Removes the assignments of a local to itself (e.g., `_1 = copy _1`).
+fn remove_trivial_assignments(lir_body: &mut LirBody) {
+    for bb_data in lir_body.basic_blocks.iter_mut() {
+        bb_data.statements.retain(|stmt| {
+            let StatementKind::Assign(assign) = &stmt.kind else {
+                return true;
+            };
+            let RValue::Use(Operand::Copy(source) | Operand::Move(source)) = &assign.1 else {
+                return true;
+            };
+            let is_trivial = assign
+                .0
+                .try_local()
+                .is_some_and(|local| source.try_local() == Some(local));
+            if is_trivial {
+                debug!("Removed the trivial assignment {:?}", stmt);
+            }
+            !is_trivial
+        });
+    }
+}
+
+/// Removes the locals that do not appear in the body anymore, renumbering the others.
+/// The return local and the arguments are always kept, as they are the signature.
+// It corresponds to `SimplifyLocals` in rustc_mir_transform/src/simplify.rs
+fn remove_unused_locals(lir_body: &mut LirBody) {
+    let mut used = IdxVec::from_elem_n(false, lir_body.num_locals());
+    visit::visit_locals_mut(lir_body, |local| used[*local] = true);
+
+    let arg_count = lir_body.ret_and_args.len();
+    let mut new_locals: IdxVec<Local, Option<Local>> = IdxVec::from_elem_n(None, used.len());
+    let mut next = Local::new(arg_count);
+    for (local, is_used) in used.iter_enumerated() {
+        if local.idx() < arg_count {
+            new_locals[local] = Some(local);
+        } else if *is_used {
+            new_locals[local] = Some(next);
+            next.incr();
+        }
+    }
+    if next.idx() == used.len() {
+        return;
+    }
+
+    debug!(
+        "Removed {} unused locals of {}",
+        used.len() - next.idx(),
+        lir_body.metadata.name
+    );
+    lir_body.locals = std::mem::take(&mut lir_body.locals)
+        .into_iter_enumerated()
+        .filter(|(local, _)| used[Local::new(local.idx() + arg_count)])
+        .map(|(_, local_data)| local_data)
+        .collect();
+    visit::visit_locals_mut(lir_body, |local| {
+        *local = new_locals[*local].expect("The local is used");
+    });
+}
+
+/// The locals in SSA form, i.e., assigned at most once (the arguments on entry) before
+/// all their uses (i.e., the assignment dominates them), and whose address is never
+/// taken. So, a local in SSA form has the same value wherever it is used.
+// It corresponds to `SsaLocals` in rustc_mir_transform/src/ssa.rs
+struct SsaLocals {
+    dominators: Dominators,
+    predecessors: IdxVec<BasicBlock, Vec<BasicBlock>>,
+    states: IdxVec<Local, SsaState>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum SsaState {
+    /// No assignment of the local has been found so far.
+    Unassigned,
+    /// The local is assigned only at the given location so far.
+    Assigned(DefLocation),
+    /// The local is not in SSA form.
+    NotSsa,
+}
+
+impl SsaLocals {
+    fn new(lir_body: &LirBody) -> Self {
+        let arg_count = lir_body.ret_and_args.len();
+        // The return local is read by the caller, after the body.
+        let states = IdxVec::from_fn_n(
+            |local: Local| {
+                if local == RETURN_LOCAL {
+                    SsaState::NotSsa
+                } else if local.idx() < arg_count {
+                    SsaState::Assigned(DefLocation::Argument)
+                } else {
+                    SsaState::Unassigned
+                }
+            },
+            lir_body.num_locals(),
+        );
+        let mut ssa_locals = SsaLocals {
+            dominators: Dominators::compute(&lir_body.basic_blocks),
+            predecessors: cfg::predecessors(&lir_body.basic_blocks),
+            states,
+        };
+        ssa::visit_defs_and_uses(lir_body, &mut ssa_locals);
+        ssa_locals
+    }
+
+    fn is_ssa(&self, local: Local) -> bool {
+        matches!(self.states[local], SsaState::Assigned(_))
+    }
+}
+
+impl DefUseVisitor for SsaLocals {
+    fn visit_def(&mut self, local: Local, def: DefLocation) {
+        self.states[local] = match self.states[local] {
+            SsaState::Unassigned => SsaState::Assigned(def),
+            SsaState::Assigned(_) | SsaState::NotSsa => SsaState::NotSsa,
+        };
+    }
+
+    fn visit_use(&mut self, local: Local, location: Location) {
+        let SsaState::Assigned(def) = self.states[local] else {
+            self.states[local] = SsaState::NotSsa;
+            return;
+        };
+        if !def.dominates(location, &self.dominators, &self.predecessors) {
+            self.states[local] = SsaState::NotSsa;
+        }
+    }
+
+    fn visit_memory_access(&mut self, local: Local, _reason: &str) {
+        self.states[local] = SsaState::NotSsa;
+    }
+}
@@ -0,0 +1,374 @@
+use std::collections::HashMap;
+
+use tidec_utils::{idx::Idx, index_vec::IdxVec};
+use tracing::debug;
+
+use crate::{
+    basic_blocks::{BasicBlock, BasicBlockData, BasicBlockKind, ENTRY_BLOCK},
+    lir::{DefId, FnAttribute, LirBody, LirCtx, LirUnit},
+    syntax::{
+        Callee, InlineAsmOperand, Local, LocalData, Operand, Place, Projection, RValue, Statement,
+        StatementKind, Terminator, TerminatorKind, UnwindAction, RETURN_LOCAL,
+    },
+};
+
+use super::LirPass;
+
+/// Splices the bodies marked with `FnAttribute::INLINE` into their callers, at the
+/// `Call` terminators that call them directly.
+///
+/// A body is inlined only if its size (i.e., the number of its statements and
+/// terminators) does not exceed `LirCtx::inline_threshold`, unless it is marked with
+/// `FnAttribute::ALWAYS_INLINE`. A threshold of `0` disables inlining. The bodies are inlined
+/// as they are in the unit before the pass, and the calls in the inlined blocks are
+/// not inlined again. Hence, recursive functions are never unrolled.
+// It corresponds to `Inline` in rustc_mir_transform/src/inline.rs
+pub struct Inline {
+    /// The bodies that can be inlined, by their `DefId`.
+    candidates: HashMap<DefId, InlineCandidate>,
+}
+
+/// A copy of the locals and the basic blocks of a body that can be inlined.
+struct InlineCandidate {
+    /// The personality function of the body, needed by its cleanup blocks.
+    personality: Option<String>,
+    ret_and_args: IdxVec<Local, LocalData>,
+    locals: IdxVec<Local, LocalData>,
+    basic_blocks: IdxVec<BasicBlock, BasicBlockData>,
+}
+
+impl Inline {
+    /// Collects the bodies of the unit that can be inlined.
+    pub fn new(lir_ctx: &LirCtx, lir_unit: &LirUnit) -> Self {
+        let candidates = lir_unit
+            .bodies
+            .iter()
+            .filter(|lir_body| {
+                let attrs = lir_body.metadata.attrs;
+                let threshold = lir_ctx.inline_threshold();
+                threshold > 0
+                    && !lir_body.basic_blocks.is_empty()
+                    && (attrs.contains(FnAttribute::ALWAYS_INLINE)
+                        || attrs.contains(FnAttribute::INLINE)
+                            && body_size(&lir_body.basic_blocks) <= threshold)
+            })
+            .map(|lir_body| {
+                let candidate = InlineCandidate {
+                    personality: lir_body.metadata.personality.clone(),
+                    ret_and_args: lir_body.ret_and_args.iter().cloned().collect(),
+                    locals: lir_body.locals.iter().cloned().collect(),
+                    basic_blocks: lir_body.basic_blocks.iter().cloned().collect(),
+                };
+                (lir_body.metadata.def_id, candidate)
+            })
+            .collect();
+        Inline { candidates }
+    }
+}
+
+impl LirPass for Inline {
+    fn name(&self) -> &'static str {
+        "Inline"
+    }
+
+    fn run_pass(&self, _lir_ctx: &LirCtx, lir_body: &mut LirBody) {
+        // The blocks appended by the inlining are not visited.
+        for bb in (0..lir_body.basic_blocks.len()).map(BasicBlock::new) {
+            let TerminatorKind::Call {
+                func: Callee::Direct(def_id),
+                args,
+                ..
+            } = &lir_body.basic_blocks[bb].terminator.kind
+            else {
+                continue;
+            };
+            if *def_id == lir_body.metadata.def_id {
+                continue;
+            }
+            let Some(callee) = self.candidates.get(def_id) else {
+                continue;
+            };
+            if args.len() + 1 != callee.ret_and_args.len() {
+                debug!(
+                    "Not inlining {:?} in {}: mismatched number of arguments",
+                    def_id, lir_body.metadata.name
+                );
+                continue;
+            }
+            // The cleanup blocks of the callee need its personality function, which
+            // becomes the one of the caller.
+            let has_cleanup = callee.basic_blocks.iter().any(BasicBlockData::is_cleanup);
+            if has_cleanup {
+                match &lir_body.metadata.personality {
+                    Some(personality) if Some(personality) != callee.personality.as_ref() => {
+                        debug!(
+                            "Not inlining {:?} in {}: mismatched personality functions",
+                            def_id, lir_body.metadata.name
+                        );
+                        continue;
+                    }
+                    Some(_) => {}
+                    None => lir_body.metadata.personality = callee.personality.clone(),
+                }
+            }
+            debug!(
+                "Inlining {:?} in {} at {:?}",
+                def_id, lir_body.metadata.name, bb
+            );
+            inline_call(lir_body, bb, callee);
+        }
+    }
+}
+
+/// Returns the size of a body, as the number of its statements and terminators.
+fn body_size(basic_blocks: &IdxVec<BasicBlock, BasicBlockData>) -> usize {
+    basic_blocks
+        .iter()
+        .map(|bb_data| bb_data.statements.len() + 1)
+        .sum()
+}
+
+/// Replaces the `Call` terminator of the block `bb` with the body of the callee.
+///
+/// The locals of the callee (including its return local and arguments) are appended
+/// to the locals of the caller, and its blocks to the blocks of the caller. The
+/// arguments are assigned to the locals of the callee before jumping to its entry
+/// block, and every `Return` of the callee assigns the return local to the
+/// destination of the call before jumping to the target of the call. The callee
+/// unwinds as the call does: its `UnwindResume`s and its calls that continue
+/// unwinding take the unwind action of the call.
+///
+/// The inlined statements keep the spans of the callee, while the statements added
+/// in place of the call (or of a `Return`) take the span of the replaced terminator.
+fn inline_call(lir_body: &mut LirBody, bb: BasicBlock, callee: &InlineCandidate) {
+    let call = std::mem::replace(
+        &mut lir_body.basic_blocks[bb].terminator,
+        Terminator::dummy(TerminatorKind::Unreachable),
+    );
+    let TerminatorKind::Call {
+        args,
+        destination,
+        target,
+        unwind,
+        ..
+    } = call.kind
+    else {
+        panic!("Expected a call terminator in {:?}", bb);
+    };
+
+    let integrator = Integrator {
+        local_offset: lir_body.ret_and_args.len() + lir_body.locals.len(),
+        block_offset: lir_body.basic_blocks.len(),
+        destination,
+        target,
+        unwind,
+        in_cleanup_block: lir_body.basic_blocks[bb].is_cleanup(),
+    };
+    for local_data in callee.ret_and_args.iter().chain(callee.locals.iter()) {
+        lir_body.locals.push(local_data.clone());
+    }
+    for bb_data in callee.basic_blocks.iter() {
+        lir_body
+            .basic_blocks
+            .push(integrator.integrate_block(bb_data.clone()));
+    }
+
+    let bb_data = &mut lir_body.basic_blocks[bb];
+    for (arg_idx, arg) in args.into_iter().enumerate() {
+        let arg_local = integrator.map_local(Local::new(arg_idx + 1));
+        bb_data.statements.push(Statement {
+            span: call.span,
+            kind: StatementKind::Assign(Box::new((arg_local.into(), RValue::Use(arg)))),
+        });
+    }
+    bb_data.terminator = Terminator {
+        span: call.span,
+        kind: TerminatorKind::Goto {
+            target: integrator.map_block(ENTRY_BLOCK),
+        },
+    };
+}
+
+/// Renumbers the locals and the blocks of an inlined body, so that they follow
+/// the ones of the caller.
+// It corresponds to `Integrator` in rustc_mir_transform/src/inline.rs
+struct Integrator {
+    /// The number of locals of the caller before the inlining.
+    local_offset: usize,
+    /// The number of blocks of the caller before the inlining.
+    block_offset: usize,
+    /// The destination of the inlined call.
+    destination: Place,
+    /// The target of the inlined call, or `None` if the callee never returns.
+    target: Option<BasicBlock>,
+    /// The unwind action of the inlined call.
+    unwind: UnwindAction,
+    /// Whether the call is in a cleanup block, in which case all the inlined blocks
+    /// are cleanup blocks.
+    in_cleanup_block: bool,
+}
+
+impl Integrator {
+    fn map_local(&self, local: Local) -> Local {
+        Local::new(local.idx() + self.local_offset)
+    }
+
+    fn map_block(&self, bb: BasicBlock) -> BasicBlock {
+        BasicBlock::new(bb.idx() + self.block_offset)
+    }
+
+    fn map_unwind(&self, unwind: UnwindAction) -> UnwindAction {
+        match unwind {
+            UnwindAction::Continue => self.unwind,
+            UnwindAction::Unreachable => UnwindAction::Unreachable,
+            UnwindAction::Cleanup(cleanup) => UnwindAction::Cleanup(self.map_block(cleanup)),
+        }
+    }
+
+    fn integrate_block(&self, mut bb_data: BasicBlockData) -> BasicBlockData {
+        if self.in_cleanup_block {
+            bb_data.kind = BasicBlockKind::Cleanup;
+        }
+        for stmt in &mut bb_data.statements {
+            match &mut stmt.kind {
+                StatementKind::Assign(assign) => {
+                    let (place, rvalue) = &mut **assign;
+                    self.integrate_place(place);
+                    self.integrate_rvalue(rvalue);
+                }
+                StatementKind::SetDiscriminant { place, .. } => self.integrate_place(place),
+                StatementKind::InlineAsm(asm) => {
+                    for operand in &mut asm.operands {
+                        match operand {
+                            InlineAsmOperand::In { value, .. } => self.integrate_operand(value),
+                            InlineAsmOperand::Out { place, .. } => {
+                                if let Some(place) = place {
+                                    self.integrate_place(place);
+                                }
+                            }
+                            InlineAsmOperand::InOut {
+                                in_value,
+                                out_place,
+                                ..
+                            } => {
+                                self.integrate_operand(in_value);
+                                if let Some(place) = out_place {
+                                    self.integrate_place(place);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        let span = bb_data.terminator.span;
+        match &mut bb_data.terminator.kind {
+            TerminatorKind::Goto { target } => *target = self.map_block(*target),
+            // A diverging callee never returns.
+            TerminatorKind::Return if self.target.is_none() => {
+                bb_data.terminator = Terminator {
+                    span,
+                    kind: TerminatorKind::Unreachable,
+                };
+            }
+            TerminatorKind::Return => {
+                let ret_local = self.map_local(RETURN_LOCAL);
+                bb_data.statements.push(Statement {
+                    span,
+                    kind: StatementKind::Assign(Box::new((
+                        self.destination.clone(),
+                        RValue::Use(Operand::Move(ret_local.into())),
+                    ))),
+                });
+                bb_data.terminator = Terminator {
+                    span,
+                    kind: TerminatorKind::Goto {
+                        target: self.target.expect("The call has a target"),
+                    },
+                };
+            }
+            TerminatorKind::Unreachable => {}
+            TerminatorKind::Assert {
+                cond,
+                target,
+                unwind,
+                ..
+            } => {
+                self.integrate_operand(cond);
+                *target = self.map_block(*target);
+                *unwind = self.map_unwind(*unwind);
+            }
+            TerminatorKind::Call {
+                func,
+                args,
+                destination,
+                target,
+                unwind,
+            } => {
+                if let Callee::Indirect(fn_ptr) = func {
+                    self.integrate_operand(fn_ptr);
+                }
+                for arg in args {
+                    self.integrate_operand(arg);
+                }
+                self.integrate_place(destination);
+                *target = target.map(|target| self.map_block(target));
+                *unwind = self.map_unwind(*unwind);
+            }
+            TerminatorKind::Drop {
+                place,
+                target,
+                unwind,
+            } => {
+                self.integrate_place(place);
+                *target = self.map_block(*target);
+                *unwind = self.map_unwind(*unwind);
+            }
+            TerminatorKind::UnwindResume => match self.unwind {
+                UnwindAction::Continue => {}
+                UnwindAction::Unreachable => {
+                    bb_data.terminator.kind = TerminatorKind::Unreachable;
+                }
+                UnwindAction::Cleanup(cleanup) => {
+                    bb_data.terminator.kind = TerminatorKind::Goto { target: cleanup };
+                }
+            },
+        }
+        bb_data
+    }
+
+    fn integrate_rvalue(&self, rvalue: &mut RValue) {
+        match rvalue {
+            RValue::Use(operand) | RValue::UnaryOp(_, operand) | RValue::Cast(_, operand, _) => {
+                self.integrate_operand(operand)
+            }
+            RValue::BinaryOp(_, lhs, rhs) => {
+                self.integrate_operand(lhs);
+                self.integrate_operand(rhs);
+            }
+            RValue::Ref(place) | RValue::Discriminant(place) => self.integrate_place(place),
+            RValue::Intrinsic(_, args) => {
+                for arg in args {
+                    self.integrate_operand(arg);
+                }
+            }
+        }
+    }
+
+    fn integrate_operand(&self, operand: &mut Operand) {
+        match operand {
+            Operand::Copy(place) | Operand::Move(place) => self.integrate_place(place),
+            Operand::Const(_) => {}
+        }
+    }
+
+    fn integrate_place(&self, place: &mut Place) {
+        place.local = self.map_local(place.local);
+        for projection in &mut place.projection {
+            if let Projection::Index(local) = projection {
+                *local = self.map_local(*local);
+            }
+        }
+    }
+}
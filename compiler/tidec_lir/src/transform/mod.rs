@@ -0,0 +1,114 @@
+//! Transformations of the LIR bodies (e.g., optimizations), run before codegen.
+//!
+//! Each transformation is a [`LirPass`], which rewrites a body in place.
+//! The passes enabled by default are run on a whole unit by [`run_lir_passes`].
+
+mod bounds_check;
+mod const_fold;
+mod copy_prop;
+mod inline;
+mod simplify_cfg;
+
+pub use bounds_check::BoundsCheck;
+pub use const_fold::ConstFold;
+pub use copy_prop::CopyProp;
+pub use inline::Inline;
+pub use simplify_cfg::SimplifyCfg;
+
+use std::{io, path::Path};
+
+use tracing::{debug, debug_span, instrument};
+
+use crate::{
+    lir::{LirBody, LirCtx, LirUnit},
+    pretty::LirPrinter,
+};
+
+/// A transformation of a LIR body.
+///
+/// A pass must preserve the semantics of the body. That is, the body must
+/// still be valid LIR after the pass.
+// It corresponds to `MirPass` in rustc_mir_transform/src/pass_manager.rs
+pub trait LirPass {
+    /// The name of the pass, used for logging.
+    fn name(&self) -> &'static str;
+
+    /// Runs the pass on the given body.
+    fn run_pass(&self, lir_ctx: &LirCtx, lir_body: &mut LirBody);
+}
+
+#[instrument(level = "debug", skip(lir_ctx, lir_unit))]
+/// Runs the default passes on all the bodies of the given unit.
+///
+/// The bounds checks (if `LirCtx::bounds_checks` is set) are inserted first, so that
+/// the inlined bodies are already checked. Then, the inlining runs, so that the
+/// constants passed as arguments are folded in the inlined bodies. The copies of the
+/// locals (e.g., of the arguments and the return values of the inlined bodies) are
+/// propagated before the folding, which then sees the constants through them. Finally,
+/// the CFG is simplified (e.g., the blocks of the inlined bodies are merged with the
+/// ones around them).
+pub fn run_lir_passes(lir_ctx: &LirCtx, lir_unit: &mut LirUnit) {
+    if lir_ctx.bounds_checks() {
+        run_passes(lir_ctx, lir_unit, &[&BoundsCheck]);
+    }
+    let inline = Inline::new(lir_ctx, lir_unit);
+    run_passes(
+        lir_ctx,
+        lir_unit,
+        &[&inline, &CopyProp, &ConstFold, &SimplifyCfg],
+    );
+}
+
+/// Runs the given passes, in order, on all the bodies of the given unit.
+///
+/// If `LirCtx::dump_lir_dir` is set, every body is dumped after each pass. If the
+/// directory cannot be created or written, a warning is reported and the dumps are
+/// skipped.
+pub fn run_passes(lir_ctx: &LirCtx, lir_unit: &mut LirUnit, passes: &[&dyn LirPass]) {
+    let dcx = lir_ctx.sess().dcx();
+    let mut dump =
+        lir_ctx
+            .dump_lir_dir()
+            .and_then(|dump_dir| match std::fs::create_dir_all(dump_dir) {
+                Ok(()) => Some((LirPrinter::new(lir_ctx, lir_unit), dump_dir)),
+                Err(err) => {
+                    dcx.warn(format_args!(
+                        "Cannot create the directory {}, the LIR is not dumped: {}",
+                        dump_dir.display(),
+                        err
+                    ));
+                    None
+                }
+            });
+
+    for lir_body in lir_unit.bodies.iter_mut() {
+        for pass in passes {
+            let _span = debug_span!("lir_pass", pass = pass.name()).entered();
+            debug!("Running pass {} on {}", pass.name(), lir_body.metadata.name);
+            pass.run_pass(lir_ctx, lir_body);
+            if let Some((printer, dump_dir)) = &dump {
+                if let Err(err) = dump_lir_body(printer, dump_dir, pass.name(), lir_body) {
+                    dcx.warn(format_args!(
+                        "Cannot dump the LIR to {}, the next dumps are skipped: {}",
+                        dump_dir.display(),
+                        err
+                    ));
+                    dump = None;
+                }
+            }
+        }
+    }
+}
+
+/// Writes the given body to `<dump_dir>/<body>.<pass>.lir`.
+// It corresponds to `dump_mir` in rustc_middle/src/mir/pretty.rs
+fn dump_lir_body(
+    printer: &LirPrinter,
+    dump_dir: &Path,
+    pass_name: &str,
+    lir_body: &LirBody,
+) -> io::Result<()> {
+    let path = dump_dir.join(format!("{}.{}.lir", lir_body.metadata.name, pass_name));
+    debug!("Dumping {} to {}", lir_body.metadata.name, path.display());
+    std::fs::write(&path, printer.body_to_string(lir_body))
+}
@@ -0,0 +1,138 @@
+use tidec_utils::{bit_set::IdxBitSet, idx::Idx, index_vec::IdxVec};
+use tracing::debug;
+
+use crate::{
+    basic_blocks::{BasicBlock, BasicBlockData, ENTRY_BLOCK},
+    cfg,
+    lir::{LirBody, LirCtx},
+    syntax::{Terminator, TerminatorKind},
+};
+
+use super::LirPass;
+
+/// Simplifies the control-flow graph of a body, keeping the same paths through its
+/// statements:
+/// - The jumps to an empty block that only jumps to another one (e.g.,
+///   `bb1: { goto -> bb2; }`) are threaded to the final target.
+/// - A block that jumps to a block with no other predecessor is merged with it, so
+///   that a straight line of blocks becomes a single block.
+/// - The blocks unreachable from the entry block are removed, and the others are
+///   renumbered in order.
+// It corresponds to `SimplifyCfg` in rustc_mir_transform/src/simplify.rs
+pub struct SimplifyCfg;
+
+impl LirPass for SimplifyCfg {
+    fn name(&self) -> &'static str {
+        "SimplifyCfg"
+    }
+
+    fn run_pass(&self, _lir_ctx: &LirCtx, lir_body: &mut LirBody) {
+        if lir_body.is_declaration() {
+            return;
+        }
+        let basic_blocks = &mut lir_body.basic_blocks;
+        thread_jumps(basic_blocks);
+        // The blocks skipped by the jumps are removed first, as they would be counted
+        // as predecessors by the merging.
+        remove_unreachable_blocks(basic_blocks);
+        merge_blocks(basic_blocks);
+        remove_unreachable_blocks(basic_blocks);
+    }
+}
+
+/// Redirects the jumps to the empty blocks that only jump to another block to the end
+/// of the chain of such blocks.
+fn thread_jumps(basic_blocks: &mut IdxVec<BasicBlock, BasicBlockData>) {
+    let final_targets: IdxVec<BasicBlock, BasicBlock> = basic_blocks
+        .indices()
+        .map(|bb| final_target(basic_blocks, bb))
+        .collect();
+    for (bb, bb_data) in basic_blocks.iter_enumerated_mut() {
+        for successor in bb_data.terminator.successors_mut() {
+            if final_targets[*successor] != *successor {
+                debug!(
+                    "Threaded the jump from {:?} to {:?} to {:?}",
+                    bb, successor, final_targets[*successor]
+                );
+                *successor = final_targets[*successor];
+            }
+        }
+    }
+}
+
+/// Returns the block where the chain of the empty blocks that only jump, starting at
+/// `bb`, ends. A chain that loops forever (e.g., `bb1: { goto -> bb1; }`) ends at `bb`.
+fn final_target(basic_blocks: &IdxVec<BasicBlock, BasicBlockData>, bb: BasicBlock) -> BasicBlock {
+    let mut target = bb;
+    for _ in 0..basic_blocks.len() {
+        let bb_data = &basic_blocks[target];
+        match bb_data.terminator.kind {
+            TerminatorKind::Goto { target: next } if bb_data.statements.is_empty() => target = next,
+            _ => return target,
+        }
+    }
+    bb
+}
+
+/// Merges the blocks ending with a `Goto` with their target, if they are its only
+/// predecessor. The merged targets are left empty and unreachable.
+fn merge_blocks(basic_blocks: &mut IdxVec<BasicBlock, BasicBlockData>) {
+    let mut predecessor_counts: IdxVec<BasicBlock, usize> = cfg::predecessors(basic_blocks)
+        .iter()
+        .map(Vec::len)
+        .collect();
+    // The entry block is also entered by the function entry.
+    predecessor_counts[ENTRY_BLOCK] += 1;
+
+    for bb in (0..basic_blocks.len()).map(BasicBlock::new) {
+        // The merged terminator may be another `Goto`, so the line goes on.
+        while let TerminatorKind::Goto { target } = basic_blocks[bb].terminator.kind {
+            if target == bb
+                || predecessor_counts[target] != 1
+                || basic_blocks[target].kind != basic_blocks[bb].kind
+            {
+                break;
+            }
+            debug!("Merged {:?} into {:?}", target, bb);
+            let empty = BasicBlockData {
+                statements: vec![],
+                terminator: Terminator::dummy(TerminatorKind::Unreachable),
+                kind: basic_blocks[bb].kind,
+            };
+            let merged = std::mem::replace(&mut basic_blocks[target], empty);
+            predecessor_counts[target] = 0;
+            let bb_data = &mut basic_blocks[bb];
+            bb_data.statements.extend(merged.statements);
+            bb_data.terminator = merged.terminator;
+        }
+    }
+}
+
+/// Removes the blocks unreachable from the entry block, and renumbers the others,
+/// keeping their order.
+fn remove_unreachable_blocks(basic_blocks: &mut IdxVec<BasicBlock, BasicBlockData>) {
+    let reachable: IdxBitSet<BasicBlock> =
+        cfg::reverse_postorder(basic_blocks).into_iter().collect();
+    if reachable.count() == basic_blocks.len() {
+        return;
+    }
+
+    let mut new_blocks = IdxVec::from_elem_n(None, basic_blocks.len());
+    for (new_bb, bb) in reachable.iter().enumerate() {
+        new_blocks[bb] = Some(BasicBlock::new(new_bb));
+    }
+    debug!(
+        "Removed {} unreachable blocks",
+        basic_blocks.len() - reachable.count()
+    );
+    *basic_blocks = std::mem::take(basic_blocks)
+        .into_iter_enumerated()
+        .filter(|(bb, _)| reachable.contains(*bb))
+        .map(|(_, bb_data)| bb_data)
+        .collect();
+    for bb_data in basic_blocks.iter_mut() {
+        for successor in bb_data.terminator.successors_mut() {
+            *successor = new_blocks[*successor].expect("The successor is reachable");
+        }
+    }
+}
@@ -0,0 +1,387 @@
+//! A visitor of the LIR bodies, which walks their statements and terminators down to
+//! the places and the locals, telling how each of them is used (see `PlaceContext`).
+//!
+//! Each `visit_*` method of the `Visitor` trait calls the corresponding `super_*`
+//! function by default, which visits the components. A visitor overrides the methods
+//! of what it is interested in, calling the `super_*` function to keep walking.
+
+use crate::{
+    basic_blocks::{BasicBlock, BasicBlockData, Location},
+    lir::LirBody,
+    syntax::{
+        Callee, InlineAsmOperand, Local, Operand, Place, Projection, RValue, Statement,
+        StatementKind, Terminator, TerminatorKind, RETURN_LOCAL,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// How a place (or its base local) is used.
+// It corresponds to `PlaceContext` in rustc_middle/src/mir/visit.rs
+pub enum PlaceContext {
+    /// The place is read, but not written.
+    NonMutatingUse(NonMutatingUseContext),
+    /// The place is written, or it may be written later (e.g., through its address).
+    MutatingUse(MutatingUseContext),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NonMutatingUseContext {
+    /// The place is copied, e.g., `copy _1`, or used as an index, e.g., `_2[_1]`.
+    Copy,
+    /// The place is moved, e.g., `move _1`.
+    Move,
+    /// The discriminant of the place is read, e.g., `discriminant(_1)`.
+    Inspect,
+    /// The base local of a place with projections that is read, e.g., `_1` in
+    /// `copy _1.0` or in `copy (*_1)`.
+    Projection,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MutatingUseContext {
+    /// The place is assigned, e.g., `_1 = ...`.
+    Store,
+    /// The place is the destination of a call, written when the callee returns.
+    Call,
+    /// The place is an output of an inline assembly.
+    AsmOutput,
+    /// The discriminant of the place is written, e.g., `discriminant(_1) = 0`.
+    SetDiscriminant,
+    /// The place is dropped.
+    Drop,
+    /// The address of the place is taken, e.g., `&_1`.
+    AddressOf,
+    /// The base local of a place with projections that is written, e.g., `_1` in
+    /// `_1.0 = ...` or in `(*_1) = ...`.
+    Projection,
+}
+
+impl PlaceContext {
+    pub fn is_mutating_use(self) -> bool {
+        matches!(self, PlaceContext::MutatingUse(_))
+    }
+
+    /// Returns `true` if the whole place is overwritten, regardless of its value.
+    pub fn is_place_assignment(self) -> bool {
+        matches!(
+            self,
+            PlaceContext::MutatingUse(
+                MutatingUseContext::Store
+                    | MutatingUseContext::Call
+                    | MutatingUseContext::AsmOutput
+            )
+        )
+    }
+}
+
+/// A visitor of the LIR, as described in the module documentation.
+// It corresponds to `Visitor` in rustc_middle/src/mir/visit.rs
+pub trait Visitor {
+    fn visit_body(&mut self, lir_body: &LirBody) {
+        super_body(self, lir_body);
+    }
+
+    fn visit_basic_block_data(&mut self, bb: BasicBlock, bb_data: &BasicBlockData) {
+        super_basic_block_data(self, bb, bb_data);
+    }
+
+    fn visit_statement(&mut self, statement: &Statement, location: Location) {
+        super_statement(self, statement, location);
+    }
+
+    fn visit_assign(&mut self, place: &Place, rvalue: &RValue, location: Location) {
+        super_assign(self, place, rvalue, location);
+    }
+
+    fn visit_terminator(&mut self, terminator: &Terminator, location: Location) {
+        super_terminator(self, terminator, location);
+    }
+
+    fn visit_rvalue(&mut self, rvalue: &RValue, location: Location) {
+        super_rvalue(self, rvalue, location);
+    }
+
+    fn visit_operand(&mut self, operand: &Operand, location: Location) {
+        super_operand(self, operand, location);
+    }
+
+    fn visit_place(&mut self, place: &Place, context: PlaceContext, location: Location) {
+        super_place(self, place, context, location);
+    }
+
+    fn visit_local(&mut self, _local: Local, _context: PlaceContext, _location: Location) {}
+}
+
+pub fn super_body<V: Visitor + ?Sized>(visitor: &mut V, lir_body: &LirBody) {
+    for (bb, bb_data) in lir_body.basic_blocks.iter_enumerated() {
+        visitor.visit_basic_block_data(bb, bb_data);
+    }
+}
+
+pub fn super_basic_block_data<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    bb: BasicBlock,
+    bb_data: &BasicBlockData,
+) {
+    for (statement_index, statement) in bb_data.statements.iter().enumerate() {
+        visitor.visit_statement(
+            statement,
+            Location {
+                block: bb,
+                statement_index,
+            },
+        );
+    }
+    visitor.visit_terminator(&bb_data.terminator, bb_data.terminator_location(bb));
+}
+
+pub fn super_statement<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    statement: &Statement,
+    location: Location,
+) {
+    match &statement.kind {
+        StatementKind::Assign(assign) => visitor.visit_assign(&assign.0, &assign.1, location),
+        StatementKind::SetDiscriminant { place, .. } => visitor.visit_place(
+            place,
+            PlaceContext::MutatingUse(MutatingUseContext::SetDiscriminant),
+            location,
+        ),
+        StatementKind::InlineAsm(asm) => {
+            for operand in &asm.operands {
+                if let Some(value) = operand.in_value() {
+                    visitor.visit_operand(value, location);
+                }
+                if let Some(place) = operand.out_place() {
+                    visitor.visit_place(
+                        place,
+                        PlaceContext::MutatingUse(MutatingUseContext::AsmOutput),
+                        location,
+                    );
+                }
+            }
+        }
+    }
+}
+
+pub fn super_assign<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    place: &Place,
+    rvalue: &RValue,
+    location: Location,
+) {
+    visitor.visit_place(
+        place,
+        PlaceContext::MutatingUse(MutatingUseContext::Store),
+        location,
+    );
+    visitor.visit_rvalue(rvalue, location);
+}
+
+pub fn super_terminator<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    terminator: &Terminator,
+    location: Location,
+) {
+    match &terminator.kind {
+        TerminatorKind::Assert { cond, .. } => visitor.visit_operand(cond, location),
+        TerminatorKind::Call {
+            func,
+            args,
+            destination,
+            ..
+        } => {
+            if let Callee::Indirect(fn_ptr) = func {
+                visitor.visit_operand(fn_ptr, location);
+            }
+            for arg in args {
+                visitor.visit_operand(arg, location);
+            }
+            visitor.visit_place(
+                destination,
+                PlaceContext::MutatingUse(MutatingUseContext::Call),
+                location,
+            );
+        }
+        TerminatorKind::Drop { place, .. } => visitor.visit_place(
+            place,
+            PlaceContext::MutatingUse(MutatingUseContext::Drop),
+            location,
+        ),
+        TerminatorKind::Return => visitor.visit_place(
+            &Place::from(RETURN_LOCAL),
+            PlaceContext::NonMutatingUse(NonMutatingUseContext::Move),
+            location,
+        ),
+        TerminatorKind::Goto { .. }
+        | TerminatorKind::Unreachable
+        | TerminatorKind::UnwindResume => {}
+    }
+}
+
+pub fn super_rvalue<V: Visitor + ?Sized>(visitor: &mut V, rvalue: &RValue, location: Location) {
+    match rvalue {
+        RValue::Use(operand) | RValue::UnaryOp(_, operand) | RValue::Cast(_, operand, _) => {
+            visitor.visit_operand(operand, location)
+        }
+        RValue::BinaryOp(_, lhs, rhs) => {
+            visitor.visit_operand(lhs, location);
+            visitor.visit_operand(rhs, location);
+        }
+        RValue::Intrinsic(_, args) => {
+            for arg in args {
+                visitor.visit_operand(arg, location);
+            }
+        }
+        RValue::Ref(place) => visitor.visit_place(
+            place,
+            PlaceContext::MutatingUse(MutatingUseContext::AddressOf),
+            location,
+        ),
+        RValue::Discriminant(place) => visitor.visit_place(
+            place,
+            PlaceContext::NonMutatingUse(NonMutatingUseContext::Inspect),
+            location,
+        ),
+    }
+}
+
+pub fn super_operand<V: Visitor + ?Sized>(visitor: &mut V, operand: &Operand, location: Location) {
+    match operand {
+        Operand::Copy(place) => visitor.visit_place(
+            place,
+            PlaceContext::NonMutatingUse(NonMutatingUseContext::Copy),
+            location,
+        ),
+        Operand::Move(place) => visitor.visit_place(
+            place,
+            PlaceContext::NonMutatingUse(NonMutatingUseContext::Move),
+            location,
+        ),
+        Operand::Const(_) => {}
+    }
+}
+
+/// Visits the base local of the place, with the `Projection` context if the place has
+/// projections, and the locals used as indices.
+pub fn super_place<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    place: &Place,
+    context: PlaceContext,
+    location: Location,
+) {
+    let local_context = match (place.projection.is_empty(), context.is_mutating_use()) {
+        (true, _) => context,
+        (false, true) => PlaceContext::MutatingUse(MutatingUseContext::Projection),
+        (false, false) => PlaceContext::NonMutatingUse(NonMutatingUseContext::Projection),
+    };
+    visitor.visit_local(place.local, local_context, location);
+    for projection in &place.projection {
+        if let Projection::Index(index) = projection {
+            visitor.visit_local(
+                *index,
+                PlaceContext::NonMutatingUse(NonMutatingUseContext::Copy),
+                location,
+            );
+        }
+    }
+}
+
+/// Calls `f` on each local of the body, which can replace it: the base locals of the
+/// places and the locals used as indices. It is the mutable counterpart of the
+/// `Visitor`, for the passes that rename the locals (e.g., `transform::CopyProp`).
+///
+/// The return local read by a `Return` is implicit, so it is not visited.
+pub fn visit_locals_mut(lir_body: &mut LirBody, mut f: impl FnMut(&mut Local)) {
+    let mut visit_place = |place: &mut Place| {
+        f(&mut place.local);
+        for projection in &mut place.projection {
+            if let Projection::Index(index) = projection {
+                f(index);
+            }
+        }
+    };
+    for bb_data in lir_body.basic_blocks.iter_mut() {
+        for statement in &mut bb_data.statements {
+            match &mut statement.kind {
+                StatementKind::Assign(assign) => {
+                    let (place, rvalue) = &mut **assign;
+                    visit_place(place);
+                    match rvalue {
+                        RValue::Use(operand)
+                        | RValue::UnaryOp(_, operand)
+                        | RValue::Cast(_, operand, _) => {
+                            operand_place_mut(operand, &mut visit_place)
+                        }
+                        RValue::BinaryOp(_, lhs, rhs) => {
+                            operand_place_mut(lhs, &mut visit_place);
+                            operand_place_mut(rhs, &mut visit_place);
+                        }
+                        RValue::Intrinsic(_, args) => {
+                            for arg in args {
+                                operand_place_mut(arg, &mut visit_place);
+                            }
+                        }
+                        RValue::Ref(place) | RValue::Discriminant(place) => visit_place(place),
+                    }
+                }
+                StatementKind::SetDiscriminant { place, .. } => visit_place(place),
+                StatementKind::InlineAsm(asm) => {
+                    for operand in &mut asm.operands {
+                        match operand {
+                            InlineAsmOperand::In { value, .. } => {
+                                operand_place_mut(value, &mut visit_place)
+                            }
+                            InlineAsmOperand::Out { place, .. } => {
+                                if let Some(place) = place {
+                                    visit_place(place);
+                                }
+                            }
+                            InlineAsmOperand::InOut {
+                                in_value,
+                                out_place,
+                                ..
+                            } => {
+                                operand_place_mut(in_value, &mut visit_place);
+                                if let Some(place) = out_place {
+                                    visit_place(place);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        match &mut bb_data.terminator.kind {
+            TerminatorKind::Assert { cond, .. } => operand_place_mut(cond, &mut visit_place),
+            TerminatorKind::Call {
+                func,
+                args,
+                destination,
+                ..
+            } => {
+                if let Callee::Indirect(fn_ptr) = func {
+                    operand_place_mut(fn_ptr, &mut visit_place);
+                }
+                for arg in args {
+                    operand_place_mut(arg, &mut visit_place);
+                }
+                visit_place(destination);
+            }
+            TerminatorKind::Drop { place, .. } => visit_place(place),
+            TerminatorKind::Goto { .. }
+            | TerminatorKind::Return
+            | TerminatorKind::Unreachable
+            | TerminatorKind::UnwindResume => {}
+        }
+    }
+}
+
+/// Calls `visit_place` on the place read by the operand, if any.
+fn operand_place_mut(operand: &mut Operand, visit_place: &mut impl FnMut(&mut Place)) {
+    match operand {
+        Operand::Copy(place) | Operand::Move(place) => visit_place(place),
+        Operand::Const(_) => {}
+    }
+}
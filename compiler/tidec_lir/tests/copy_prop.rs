@@ -0,0 +1,148 @@
+//! The copy propagation (see `transform::CopyProp`), run on parsed bodies.
+
+use std::sync::Arc;
+
+use tidec_abi::target::{BackendKind, LirTarget};
+use tidec_lir::{
+    lir::LirCtx,
+    parse::parse_lir_unit,
+    pretty::LirPrinter,
+    transform::{CopyProp, LirPass},
+};
+use tidec_session::{config::Options, diagnostics::DiagCtxt, Session};
+
+/// Parses the given unit, runs `CopyProp` on its bodies and checks that they are
+/// printed as `expected`, ignoring the indentation and the empty lines.
+fn check(src: &str, expected: &str) {
+    let sess = Session::new(
+        LirTarget::new(BackendKind::Interp),
+        Options::default(),
+        DiagCtxt::new(),
+    );
+    let mut lir_ctx = LirCtx::new(Arc::new(sess));
+    let mut lir_unit = parse_lir_unit(&mut lir_ctx, "test", src).expect("Invalid test unit");
+    for lir_body in lir_unit.bodies.iter_mut() {
+        CopyProp.run_pass(&lir_ctx, lir_body);
+    }
+    let printer = LirPrinter::new(&lir_ctx, &lir_unit);
+    let printed: String = lir_unit
+        .bodies
+        .iter()
+        .map(|lir_body| printer.body_to_string(lir_body))
+        .collect();
+    let lines = |s: &str| -> Vec<String> {
+        s.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(String::from)
+            .collect()
+    };
+    assert_eq!(
+        lines(&printed),
+        lines(expected),
+        "Printed body:\n{}",
+        printed
+    );
+}
+
+#[test]
+fn propagate_a_chain_of_copies() {
+    check(
+        r#"
+        fn f(_1: u64) -> u64 {
+            let _2: u64;
+            let _3: u64;
+
+            bb0: {
+                _2 = copy _1;
+                _3 = copy _2;
+                _0 = Add(copy _3, copy _2);
+                return;
+            }
+        }
+        "#,
+        r#"
+        fn f(_1: u64) -> u64 {
+            bb0: {
+                _0 = Add(copy _1, copy _1);
+                return;
+            }
+        }
+        "#,
+    );
+}
+
+#[test]
+fn keep_the_copy_of_a_reassigned_local() {
+    // `_3` is not a copy of `_2`, which has another value after the reassignment.
+    let src = r#"
+        fn f(_1: u64) -> u64 {
+            let _2: u64;
+            let _3: u64;
+
+            bb0: {
+                _2 = copy _1;
+                _3 = copy _2;
+                _2 = Add(copy _2, const 1_u64);
+                _0 = Add(copy _3, copy _2);
+                return;
+            }
+        }
+    "#;
+    check(src, src);
+}
+
+#[test]
+fn keep_the_copy_of_a_borrowed_local() {
+    // `_2` can be written through `_4`, so `_3` is not a copy of it.
+    let src = r#"
+        fn f(_1: u64) -> u64 {
+            let _2: u64;
+            let _3: u64;
+            let _4: *u64;
+
+            bb0: {
+                _2 = copy _1;
+                _4 = &_2;
+                _3 = copy _2;
+                _0 = Add(copy _3, copy (*_4));
+                return;
+            }
+        }
+    "#;
+    check(src, src);
+}
+
+#[test]
+fn renumber_the_remaining_locals() {
+    check(
+        r#"
+        fn f(_1: u64) -> u64 {
+            let _2: u64;
+            let _3: u64;
+            let _4: bool;
+
+            bb0: {
+                _2 = copy _1;
+                _3 = Mul(copy _2, copy _2);
+                _4 = Lt(copy _3, copy _1);
+                _0 = copy _3;
+                return;
+            }
+        }
+        "#,
+        r#"
+        fn f(_1: u64) -> u64 {
+            let _2: u64;
+            let _3: bool;
+
+            bb0: {
+                _2 = Mul(copy _1, copy _1);
+                _3 = Lt(copy _2, copy _1);
+                _0 = copy _2;
+                return;
+            }
+        }
+        "#,
+    );
+}
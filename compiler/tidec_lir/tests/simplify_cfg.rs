@@ -0,0 +1,231 @@
+//! The simplification of the CFG (see `transform::SimplifyCfg`), run on parsed bodies.
+
+use std::sync::Arc;
+
+use tidec_abi::target::{BackendKind, LirTarget};
+use tidec_lir::{
+    basic_blocks::{BasicBlock, BasicBlockKind},
+    lir::{LirBody, LirCtx},
+    parse::parse_lir_unit,
+    pretty::LirPrinter,
+    transform::{LirPass, SimplifyCfg},
+};
+use tidec_session::{config::Options, diagnostics::DiagCtxt, Session};
+use tidec_utils::idx::Idx;
+
+/// Parses the given unit, applies `edit` to its bodies, runs `SimplifyCfg` on them and
+/// checks that they are printed as `expected`, ignoring the indentation and the empty
+/// lines.
+fn check_with(src: &str, expected: &str, edit: impl Fn(&mut LirBody)) {
+    let sess = Session::new(
+        LirTarget::new(BackendKind::Interp),
+        Options::default(),
+        DiagCtxt::new(),
+    );
+    let mut lir_ctx = LirCtx::new(Arc::new(sess));
+    let mut lir_unit = parse_lir_unit(&mut lir_ctx, "test", src).expect("Invalid test unit");
+    for lir_body in lir_unit.bodies.iter_mut() {
+        edit(lir_body);
+        SimplifyCfg.run_pass(&lir_ctx, lir_body);
+    }
+    let printer = LirPrinter::new(&lir_ctx, &lir_unit);
+    let printed: String = lir_unit
+        .bodies
+        .iter()
+        .map(|lir_body| printer.body_to_string(lir_body))
+        .collect();
+    let lines = |s: &str| -> Vec<String> {
+        s.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(String::from)
+            .collect()
+    };
+    assert_eq!(
+        lines(&printed),
+        lines(expected),
+        "Printed body:\n{}",
+        printed
+    );
+}
+
+fn check(src: &str, expected: &str) {
+    check_with(src, expected, |_| {});
+}
+
+#[test]
+fn thread_a_chain_of_gotos() {
+    // `bb3` has two predecessors, so the chain cannot be merged into a block, and the
+    // call of `bb0` is redirected past it.
+    check(
+        r#"
+        fn f() -> u64 {
+            bb0: {
+                _0 = g() -> bb1;
+            }
+
+            bb1: {
+                goto -> bb2;
+            }
+
+            bb2: {
+                goto -> bb3;
+            }
+
+            bb3: {
+                _0 = g() -> bb4;
+            }
+
+            bb4: {
+                _0 = Add(copy _0, const 1_u64);
+                goto -> bb3;
+            }
+        }
+
+        fn g() -> u64;
+        "#,
+        r#"
+        fn f() -> u64 {
+            bb0: {
+                _0 = g() -> bb1;
+            }
+
+            bb1: {
+                _0 = g() -> bb2;
+            }
+
+            bb2: {
+                _0 = Add(copy _0, const 1_u64);
+                goto -> bb1;
+            }
+        }
+
+        fn g() -> u64;
+        "#,
+    );
+}
+
+#[test]
+fn keep_an_empty_self_loop() {
+    let src = r#"
+        fn f() -> u64 {
+            bb0: {
+                _0 = g() -> bb1;
+            }
+
+            bb1: {
+                goto -> bb1;
+            }
+        }
+
+        fn g() -> u64;
+    "#;
+    check(src, src);
+}
+
+#[test]
+fn do_not_merge_a_cleanup_block_into_a_regular_one() {
+    // The parser rejects a `goto` from a regular block to a cleanup one, so `bb1` is
+    // made a cleanup block after parsing.
+    let src = r#"
+        fn f() -> u64 {
+            bb0: {
+                _0 = const 1_u64;
+                goto -> bb1;
+            }
+
+            bb1: {
+                _0 = Add(copy _0, const 1_u64);
+                return;
+            }
+        }
+    "#;
+    let expected = r#"
+        fn f() -> u64 {
+            bb0: {
+                _0 = const 1_u64;
+                goto -> bb1;
+            }
+
+            bb1 (cleanup): {
+                _0 = Add(copy _0, const 1_u64);
+                return;
+            }
+        }
+    "#;
+    check_with(src, expected, |lir_body| {
+        lir_body.basic_blocks[BasicBlock::new(1)].kind = BasicBlockKind::Cleanup;
+    });
+}
+
+#[test]
+fn do_not_merge_the_entry_block() {
+    // `bb0` has `bb1` as its only predecessor, but it is also entered by the function
+    // entry.
+    let src = r#"
+        fn f() -> u64 {
+            bb0: {
+                _0 = g() -> bb1;
+            }
+
+            bb1: {
+                _0 = Add(copy _0, const 1_u64);
+                goto -> bb0;
+            }
+        }
+
+        fn g() -> u64;
+    "#;
+    check(src, src);
+}
+
+#[test]
+fn remove_the_unreachable_blocks() {
+    check(
+        r#"
+        fn f() -> u64 {
+            bb0: {
+                _0 = g() -> bb2;
+            }
+
+            bb1: {
+                _0 = const 1_u64;
+                return;
+            }
+
+            bb2: {
+                _0 = g() -> bb4;
+            }
+
+            bb3: {
+                unreachable;
+            }
+
+            bb4: {
+                _0 = Add(copy _0, const 1_u64);
+                return;
+            }
+        }
+
+        fn g() -> u64;
+        "#,
+        r#"
+        fn f() -> u64 {
+            bb0: {
+                _0 = g() -> bb1;
+            }
+
+            bb1: {
+                _0 = g() -> bb2;
+            }
+
+            bb2: {
+                _0 = Add(copy _0, const 1_u64);
+                return;
+            }
+        }
+
+        fn g() -> u64;
+        "#,
+    );
+}
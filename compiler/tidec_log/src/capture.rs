@@ -0,0 +1,89 @@
+//! The logger of the tests, which captures the logs of a test in memory, to assert
+//! against them (see `Logger::init_for_tests`).
+
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use tracing::subscriber::DefaultGuard;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::fmt::format::FmtSpan;
+
+use crate::Logger;
+
+#[derive(Debug, Clone, Default)]
+/// The in-memory buffer where the logs are written.
+struct CaptureBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for CaptureBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for CaptureBuffer {
+    type Writer = CaptureBuffer;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+/// The logs captured on the current thread, until it is dropped. The logs are
+/// written without time and color, one line for each event, followed by its fields.
+pub struct CapturedLogs {
+    buffer: CaptureBuffer,
+    /// The guard of the subscriber of the thread, which is uninstalled when dropped.
+    _guard: DefaultGuard,
+}
+
+impl CapturedLogs {
+    /// Returns the logs captured so far.
+    pub fn contents(&self) -> String {
+        let buffer = self.buffer.0.lock().unwrap_or_else(PoisonError::into_inner);
+        String::from_utf8_lossy(&buffer).into_owned()
+    }
+
+    /// Whether the logs captured so far contain the given string.
+    pub fn contains(&self, needle: &str) -> bool {
+        self.contents().contains(needle)
+    }
+
+    /// Clears the logs captured so far.
+    pub fn clear(&self) {
+        self.buffer
+            .0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+}
+
+impl Logger {
+    /// Captures the logs of all the levels on the current thread, until the returned
+    /// `CapturedLogs` is dropped. Unlike `Logger::init_logger`, the subscriber is
+    /// not global, so each test captures its own logs, even if the tests run on
+    /// different threads.
+    pub fn init_for_tests() -> CapturedLogs {
+        let buffer = CaptureBuffer::default();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::TRACE)
+            .with_span_events(FmtSpan::NEW | FmtSpan::CLOSE)
+            .with_target(true)
+            .with_ansi(false)
+            .without_time()
+            .with_writer(buffer.clone())
+            .finish();
+        CapturedLogs {
+            buffer,
+            _guard: tracing::subscriber::set_default(subscriber),
+        }
+    }
+}
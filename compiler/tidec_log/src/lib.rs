@@ -2,9 +2,13 @@
 //!
 //! The allowed environment variables are:
 //! - `<PREFIX>_LOG`: The log level. This can be "debug", "info", "warn", "error", or "trace".
-//! - `<PREFIX>_LOG_COLOR`: The color setting. This can be "always", "never", or "auto".
-//! - `<PREFIX>_LOG_WRITER`: The log writer. This can be "stdout", "stderr", or a file path. If the file path does not exist, it will be created.
+//! - `<PREFIX>_LOG_COLOR`: The color setting. This can be "always", "never", or "auto" (where only the writers to a terminal use color).
+//! - `<PREFIX>_LOG_WRITER`: The log writers, as a comma-separated list of "stdout", "stderr", or file paths (e.g., "stderr,build.log"), where the logs are written at the same time. If a file path does not exist, it will be created.
+//! - `<PREFIX>_LOG_WRITER_MODE`: How the log files are written, as a comma-separated list of "append", "rotate=SIZE" and "timestamp" (see `writer`). The file is written by a background thread, so that logging does not stall the compilation.
 //! - `<PREFIX>_LOG_LINE_NUMBERS`: Whether to show line numbers in the log. This can be "1" or "0".
+//! - `<PREFIX>_LOG_FILE_NAMES`: Whether to show file names in the log. This can be "1" or "0".
+//! - `<PREFIX>_LOG_SPAN_TIMINGS`: Whether to time all the spans, including the `trace` ones, and log their aggregated timings when `Logger::flush` is called (see `time_passes`). This can be "1" or "0".
+//! - `<PREFIX>_LOG_TRACE_FILE`: A file path where the spans up to the `debug` level are written as a Chrome trace (e.g., `trace.json`), which can be loaded in `chrome://tracing` or in Perfetto. The trace is complete once `Logger::flush` is called.
 //!
 //! The `<PREFIX>` is a prefix that can be set to any string. It is used to customize the log configuration for different tools. For example, `tidec` uses `TIDEC` as the prefix.
 //!
@@ -42,6 +46,29 @@
 //!
 //! This allows you to simply run `cargo run` and still see debug output.
 //!
+//! The configuration can also be built without the environment variables:
+//!
+//! ```rust
+//! use tidec_log::{ColorChoice, Level, LogWriter, LoggerConfig};
+//!
+//! let config = LoggerConfig::builder()
+//!     .filter(Level::DEBUG)
+//!     .writer(LogWriter::Stdout)
+//!     .color(ColorChoice::Auto)
+//!     .line_numbers(true)
+//!     .build();
+//! let _ = tidec_log::Logger::init_logger(config, tidec_log::FallbackDefaultEnv::No);
+//! ```
+//!
+//! In the tests, the logs can be captured instead, to assert that some events
+//! fired:
+//!
+//! ```rust
+//! let logs = tidec_log::Logger::init_for_tests();
+//! tracing::debug!("Running pass Inline on main");
+//! assert!(logs.contains("Running pass Inline on main"));
+//! ```
+//!
 //! ---
 //!
 //! The `tidec_log` crate exists as a minimal, self-contained logger setup,
@@ -50,16 +77,48 @@
 //! components like `tidec_lir`, without requiring full rebuilds of the entire
 //! compiler stack.
 
-use std::{env::VarError, fmt::Debug, fs::File, io::IsTerminal, path::PathBuf};
+pub mod capture;
+pub mod time_passes;
+pub mod writer;
+
+pub use tracing::Level;
+
+use std::{
+    env::VarError,
+    fmt::Debug,
+    fs::File,
+    io::IsTerminal,
+    path::PathBuf,
+    sync::{
+        Mutex, PoisonError,
+        atomic::{AtomicBool, Ordering},
+    },
+};
 use tracing::Subscriber;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_chrome::{ChromeLayerBuilder, FlushGuard};
 use tracing_subscriber::{
     EnvFilter, Layer,
+    filter::LevelFilter,
     fmt::{format::FmtSpan, layer},
     prelude::*,
     registry::LookupSpan,
     util::TryInitError,
 };
 
+use crate::time_passes::TimePassesLayer;
+use crate::writer::{RollingFile, WriterMode};
+
+/// The guard of the Chrome trace, which writes the end of the trace when dropped.
+static TRACE_GUARD: Mutex<Option<FlushGuard>> = Mutex::new(None);
+
+/// Whether the timings of the spans are logged when the logger is flushed.
+static SPAN_TIMINGS: AtomicBool = AtomicBool::new(false);
+
+/// The guards of the threads writing the log files, which write the pending logs when
+/// dropped.
+static WRITER_GUARDS: Mutex<Vec<WorkerGuard>> = Mutex::new(Vec::new());
+
 /// The ZST (zero-sized type) for the logger.
 pub struct Logger;
 
@@ -75,22 +134,124 @@ pub enum LogWriter {
     File(PathBuf),
 }
 
-/// The configuration for the logger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// Whether to use color in the logger.
+pub enum ColorChoice {
+    /// Always use color.
+    Always,
+    /// Never use color.
+    Never,
+    /// Use color if the writer is a terminal.
+    #[default]
+    Auto,
+}
+
+#[derive(Debug)]
+/// The configuration for the logger. It is built with `LoggerConfig::builder`, or
+/// read from the environment variables with `LoggerConfig::from_prefix`.
 pub struct LoggerConfig {
-    /// The writer for the logger.
-    pub log_writer: LogWriter,
-    /// The filter for the logger.
-    /// This is a string that can be "debug", "info", "warn", "error", or "trace".
-    pub filter: Result<String, VarError>,
+    /// The writers for the logger, where the logs are written at the same time.
+    pub log_writers: Vec<LogWriter>,
+    /// How the log files are written, if any.
+    pub writer_mode: WriterMode,
+    /// The filter for the logger, with the syntax of `EnvFilter` (e.g., "debug" or
+    /// "tidec_lir=trace"). If `None`, see `FallbackDefaultEnv`.
+    pub filter: Option<String>,
     /// Whether to use color in the logger.
-    /// This is a string that can be "always", "never", or "auto".
-    pub color: Result<String, VarError>,
+    pub color: ColorChoice,
     /// Whether to show line numbers in the logger.
-    /// If this is set to "1", line numbers will be shown otherwise they will not.
-    pub line_numbers: Result<String, VarError>,
+    pub line_numbers: bool,
     /// Whether to show file names in the logger.
-    /// If this is set to "1", file names will be shown otherwise they will not.
-    pub file_names: Result<String, VarError>,
+    pub file_names: bool,
+    /// Whether to time the spans up to the `debug` level, whatever the filter (see
+    /// `time_passes`).
+    pub time_passes: bool,
+    /// Whether to time all the spans, and log their timings when the logger is
+    /// flushed.
+    pub span_timings: bool,
+    /// The file where the spans are written as a Chrome trace, if any.
+    pub trace_file: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+/// The builder of a `LoggerConfig`. By default, the logs are written to stderr,
+/// without a filter, and with color if stderr is a terminal.
+pub struct LoggerConfigBuilder {
+    config: LoggerConfig,
+}
+
+impl LoggerConfigBuilder {
+    /// Sets the writer for the logger, replacing the other writers.
+    pub fn writer(mut self, log_writer: LogWriter) -> Self {
+        self.config.log_writers = vec![log_writer];
+        self
+    }
+
+    /// Sets the writers for the logger, where the logs are written at the same time.
+    pub fn writers(mut self, log_writers: impl IntoIterator<Item = LogWriter>) -> Self {
+        self.config.log_writers = log_writers.into_iter().collect();
+        self
+    }
+
+    /// Adds a writer for the logger, where the logs are also written.
+    pub fn add_writer(mut self, log_writer: LogWriter) -> Self {
+        self.config.log_writers.push(log_writer);
+        self
+    }
+
+    /// Sets how the log files are written, if any.
+    pub fn writer_mode(mut self, writer_mode: WriterMode) -> Self {
+        self.config.writer_mode = writer_mode;
+        self
+    }
+
+    /// Sets the filter for the logger, either a level (e.g., `Level::DEBUG`) or a
+    /// string with the syntax of `EnvFilter`.
+    pub fn filter(mut self, filter: impl ToString) -> Self {
+        self.config.filter = Some(filter.to_string());
+        self
+    }
+
+    /// Sets whether to use color in the logger.
+    pub fn color(mut self, color: ColorChoice) -> Self {
+        self.config.color = color;
+        self
+    }
+
+    /// Sets whether to show line numbers in the logger.
+    pub fn line_numbers(mut self, line_numbers: bool) -> Self {
+        self.config.line_numbers = line_numbers;
+        self
+    }
+
+    /// Sets whether to show file names in the logger.
+    pub fn file_names(mut self, file_names: bool) -> Self {
+        self.config.file_names = file_names;
+        self
+    }
+
+    /// Sets whether to time the spans (see `time_passes`).
+    pub fn time_passes(mut self, time_passes: bool) -> Self {
+        self.config.time_passes = time_passes;
+        self
+    }
+
+    /// Sets whether to time all the spans, and log their timings when the logger is
+    /// flushed.
+    pub fn span_timings(mut self, span_timings: bool) -> Self {
+        self.config.span_timings = span_timings;
+        self
+    }
+
+    /// Sets the file where the spans are written as a Chrome trace.
+    pub fn trace_file(mut self, trace_file: impl Into<PathBuf>) -> Self {
+        self.config.trace_file = Some(trace_file.into());
+        self
+    }
+
+    pub fn build(self) -> LoggerConfig {
+        self.config
+    }
 }
 
 #[derive(Debug)]
@@ -98,10 +259,15 @@ pub struct LoggerConfig {
 pub enum LogError {
     /// The color value is not valid.
     ColorNotValid(String),
+    /// The writer mode is not valid.
+    WriterModeNotValid(String),
     /// The color value is not a valid unicode string.
     NotUnicode(String),
     /// Wrapping an IO error.
     IoError(std::io::Error),
+    /// The global logger has already been initialized (e.g., by a previous call to
+    /// `Logger::init_logger`).
+    AlreadyInitialized,
     /// Wrapping a TryInitError.
     TryInitError(TryInitError),
 }
@@ -117,38 +283,90 @@ pub enum FallbackDefaultEnv {
 }
 
 impl LoggerConfig {
-    /// Create a new logger configuration from the given environment variable.
-    pub fn from_prefix(prefix_env_var: &str) -> Result<Self, VarError> {
-        let filter = std::env::var(format!("{}_LOG", prefix_env_var));
-        let color = std::env::var(format!("{}_LOG_COLOR", prefix_env_var));
-        let log_writer = std::env::var(format!("{}_LOG_WRITER", prefix_env_var))
-            .map(|s| match s.as_str() {
+    pub fn builder() -> LoggerConfigBuilder {
+        LoggerConfigBuilder {
+            config: LoggerConfig {
+                log_writers: vec![LogWriter::Stderr],
+                writer_mode: WriterMode::default(),
+                filter: None,
+                color: ColorChoice::Auto,
+                line_numbers: false,
+                file_names: false,
+                time_passes: false,
+                span_timings: false,
+                trace_file: None,
+            },
+        }
+    }
+
+    /// Create a new logger configuration from the environment variables of the
+    /// given prefix (see the crate documentation).
+    pub fn from_prefix(prefix_env_var: &str) -> Result<Self, LogError> {
+        let var = |suffix: &str| match std::env::var(format!("{}{}", prefix_env_var, suffix)) {
+            Ok(value) => Ok(Some(value)),
+            Err(VarError::NotPresent) => Ok(None),
+            Err(VarError::NotUnicode(os_string)) => Err(LogError::NotUnicode(
+                os_string.to_string_lossy().to_string(),
+            )),
+        };
+
+        let mut builder = LoggerConfig::builder();
+        if let Some(filter) = var("_LOG")? {
+            builder = builder.filter(filter);
+        }
+        if let Some(color) = var("_LOG_COLOR")? {
+            builder = builder.color(match color.as_str() {
+                "always" => ColorChoice::Always,
+                "never" => ColorChoice::Never,
+                "auto" => ColorChoice::Auto,
+                e => return Err(LogError::ColorNotValid(e.to_string())),
+            });
+        }
+        if let Some(log_writers) = var("_LOG_WRITER")? {
+            let log_writers = log_writers.split(',').map(|log_writer| match log_writer {
                 "stdout" => LogWriter::Stdout,
                 "stderr" => LogWriter::Stderr,
-                _ => LogWriter::File(s.into()),
-            })
-            .unwrap_or(LogWriter::Stderr);
-        let line_numbers = std::env::var(format!("{}_LOG_LINE_NUMBERS", prefix_env_var));
-        let file_names = std::env::var(format!("{}_LOG_FILE_NAMES", prefix_env_var));
-
-        Ok(LoggerConfig {
-            filter,
-            color,
-            log_writer,
-            line_numbers,
-            file_names,
-        })
+                _ => LogWriter::File(log_writer.into()),
+            });
+            builder = builder.writers(log_writers);
+        }
+        if let Some(writer_mode) = var("_LOG_WRITER_MODE")? {
+            builder = builder.writer_mode(
+                WriterMode::parse(&writer_mode).map_err(LogError::WriterModeNotValid)?,
+            );
+        }
+        if let Some(line_numbers) = var("_LOG_LINE_NUMBERS")? {
+            builder = builder.line_numbers(line_numbers == "1");
+        }
+        if let Some(file_names) = var("_LOG_FILE_NAMES")? {
+            builder = builder.file_names(file_names == "1");
+        }
+        if let Some(span_timings) = var("_LOG_SPAN_TIMINGS")? {
+            builder = builder.span_timings(span_timings == "1");
+        }
+        if let Some(trace_file) = std::env::var_os(format!("{}_LOG_TRACE_FILE", prefix_env_var)) {
+            builder = builder.trace_file(trace_file);
+        }
+        Ok(builder.build())
     }
 }
 
 impl Logger {
+    /// Initializes the global logger with the given configuration. If a log file or
+    /// the trace file cannot be created, it warns and logs to stderr or does not
+    /// trace instead. It fails if the global logger has already been initialized.
     pub fn init_logger(
         cfg: LoggerConfig,
         fallback_default_env: FallbackDefaultEnv,
     ) -> Result<(), LogError> {
+        // Checked first, as the guards of the current logger must not be replaced.
+        if tracing::dispatcher::has_been_set() {
+            return Err(LogError::AlreadyInitialized);
+        }
+
         let filter = match cfg.filter {
-            Ok(filter) => EnvFilter::new(filter),
-            Err(_) => {
+            Some(filter) => EnvFilter::new(filter),
+            None => {
                 if let FallbackDefaultEnv::Yes = fallback_default_env {
                     EnvFilter::from_default_env()
                 } else {
@@ -157,49 +375,104 @@ impl Logger {
             }
         };
 
-        let color_log = match cfg.color {
-            Ok(color) => match color.as_str() {
-                "always" => true,
-                "never" => false,
-                "auto" => std::io::stderr().is_terminal(),
-                e => return Err(LogError::ColorNotValid(e.to_string())),
-            },
-            Err(VarError::NotPresent) => std::io::stderr().is_terminal(),
-            Err(VarError::NotUnicode(os_string)) => {
-                return Err(LogError::NotUnicode(
-                    os_string.to_string_lossy().to_string(),
-                ));
+        // Each writer has its own layer, and the filter applies to all of them.
+        let layers = cfg
+            .log_writers
+            .into_iter()
+            .map(|log_writer| {
+                let create_layer = |log_writer| {
+                    Self::create_layer(
+                        log_writer,
+                        cfg.writer_mode,
+                        cfg.color,
+                        cfg.line_numbers,
+                        cfg.file_names,
+                    )
+                };
+                let path = match &log_writer {
+                    LogWriter::File(path) => Some(path.clone()),
+                    LogWriter::Stdout | LogWriter::Stderr => None,
+                };
+                create_layer(log_writer).or_else(|err| {
+                    // Only a file can fail to be created, so the fallback cannot fail.
+                    eprintln!(
+                        "warning: cannot create the log file {}: {}, logging to stderr instead",
+                        path.unwrap_or_default().display(),
+                        err
+                    );
+                    create_layer(LogWriter::Stderr)
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        // The filter only applies to the logs, as the spans are timed anyway.
+        // The timings of the spans are shared by `--time-passes` and the span timings,
+        // where the latter also time the `trace` spans (e.g., of the builder).
+        let time_passes_layer = (cfg.time_passes || cfg.span_timings).then(|| {
+            TimePassesLayer.with_filter(if cfg.span_timings {
+                LevelFilter::TRACE
+            } else {
+                LevelFilter::DEBUG
+            })
+        });
+        SPAN_TIMINGS.store(cfg.span_timings, Ordering::Relaxed);
+        // As for the timings, the trace has all the spans up to the `debug` level.
+        let chrome_layer = match cfg.trace_file.map(|path| (File::create(&path), path)) {
+            Some((Ok(trace_file), _)) => {
+                let (chrome_layer, guard) = ChromeLayerBuilder::new()
+                    .writer(trace_file)
+                    .include_args(true)
+                    .build();
+                *TRACE_GUARD.lock().unwrap_or_else(PoisonError::into_inner) = Some(guard);
+                Some(chrome_layer.with_filter(LevelFilter::DEBUG))
             }
+            Some((Err(err), path)) => {
+                eprintln!(
+                    "warning: cannot create the trace file {}: {}, not tracing",
+                    path.display(),
+                    err
+                );
+                None
+            }
+            None => None,
         };
-
-        let line_numbers = match cfg.line_numbers {
-            Ok(line_numbers) => &line_numbers == "1",
-            Err(_) => false,
-        };
-
-        let file_names = match cfg.file_names {
-            Ok(file_names) => &file_names == "1",
-            Err(_) => false,
-        };
-
-        let layer = Self::create_layer(cfg.log_writer, color_log, line_numbers, file_names);
         // Here we can add other layers
 
         let subscriber = tracing_subscriber::Registry::default()
-            .with(filter)
-            .with(layer);
+            .with(layers.with_filter(filter))
+            .with(time_passes_layer)
+            .with(chrome_layer);
 
-        let _ = subscriber.try_init().map_err(LogError::TryInitError);
+        subscriber.try_init().map_err(LogError::TryInitError)
+    }
 
-        Ok(())
+    /// Logs the timings of the spans, if `span_timings` is set, and writes the pending
+    /// logs to the log files and the end of the Chrome trace, if any, before exiting.
+    /// The logs and the spans afterwards are not written to the files.
+    pub fn flush() {
+        // The timings are logged once, before the logs are written.
+        if SPAN_TIMINGS.swap(false, Ordering::Relaxed) {
+            time_passes::log_summary();
+        }
+        // Dropping the guards waits for the logs and the trace to be written.
+        WRITER_GUARDS
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+        drop(
+            TRACE_GUARD
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .take(),
+        );
     }
 
     fn create_layer<S>(
         log_writer: LogWriter,
-        color_log: bool,
+        writer_mode: WriterMode,
+        color: ColorChoice,
         line_numbers: bool,
         file_names: bool,
-    ) -> Box<dyn Layer<S> + Send + Sync + 'static>
+    ) -> Result<Box<dyn Layer<S> + Send + Sync + 'static>, LogError>
     where
         S: Subscriber,
         for<'a> S: LookupSpan<'a>,
@@ -208,17 +481,36 @@ impl Logger {
             .with_span_events(FmtSpan::NEW | FmtSpan::CLOSE) // FmtSpan::FULL
             .with_target(true)
             .with_file(file_names)
-            .with_ansi(color_log)
             .with_line_number(line_numbers);
 
-        match log_writer {
+        // With `ColorChoice::Auto`, only the writers to a terminal use color.
+        let is_terminal = match log_writer {
+            LogWriter::Stdout => std::io::stdout().is_terminal(),
+            LogWriter::Stderr => std::io::stderr().is_terminal(),
+            LogWriter::File(_) => false,
+        };
+        let layer = layer.with_ansi(match color {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => is_terminal,
+        });
+
+        Ok(match log_writer {
             LogWriter::Stdout => Box::new(layer.with_writer(std::io::stdout)),
             LogWriter::Stderr => Box::new(layer.with_writer(std::io::stderr)),
             LogWriter::File(path) => {
-                let file = File::create(path).expect("Failed to create log file");
-                Box::new(layer.with_writer(file))
+                let file = RollingFile::open(path, writer_mode).map_err(LogError::IoError)?;
+                // The writer blocks when its buffer is full, instead of dropping the logs.
+                let (writer, guard) = tracing_appender::non_blocking::NonBlockingBuilder::default()
+                    .lossy(false)
+                    .finish(file);
+                WRITER_GUARDS
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .push(guard);
+                Box::new(layer.with_writer(writer))
             }
-        }
+        })
     }
 }
 
@@ -228,8 +520,10 @@ impl std::fmt::Display for LogError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             LogError::ColorNotValid(s) => write!(f, "Color not valid: {}", s),
+            LogError::WriterModeNotValid(s) => write!(f, "Writer mode not valid: {}", s),
             LogError::NotUnicode(s) => write!(f, "Not unicode: {}", s),
             LogError::IoError(e) => write!(f, "IO error: {}", e),
+            LogError::AlreadyInitialized => write!(f, "Logger already initialized"),
             LogError::TryInitError(e) => write!(f, "TryInit error: {:?}", e),
         }
     }
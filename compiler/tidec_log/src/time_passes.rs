@@ -0,0 +1,174 @@
+//! A layer that times the spans, to find out where the compilation spends its time
+//! (e.g., `--time-passes` of `tidec`).
+//!
+//! Each span is timed under its name, followed by the value of its `pass` field, if
+//! any (e.g., `lir_pass(Inline)`). The time of a span is the wall time during which
+//! it is entered, so it includes the time of the spans it contains, and the time of
+//! the spans of the same name on different threads is added up. The timings of all
+//! the spans closed so far are printed by `print_summary`, or logged by
+//! `Logger::flush` with `<PREFIX>_LOG_SPAN_TIMINGS`.
+// It corresponds to `-Z time-passes` in rustc_data_structures/src/profiling.rs
+
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::{Subscriber, info};
+use tracing_subscriber::Layer;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+
+/// The timings of the closed spans, by their label.
+static TIMINGS: Mutex<Option<HashMap<String, Timing>>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Copy, Default)]
+/// The time spent in the spans of a label.
+struct Timing {
+    /// The number of the spans.
+    count: usize,
+    total: Duration,
+}
+
+/// The timing of an open span, stored in its extensions.
+struct SpanTiming {
+    label: String,
+    /// When the span was entered, if it is entered.
+    entered_at: Option<Instant>,
+    /// The time spent in the span so far.
+    busy: Duration,
+}
+
+#[derive(Default)]
+/// Finds the value of the `pass` field of a span.
+struct PassVisitor(Option<String>);
+
+impl Visit for PassVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "pass" {
+            self.0 = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if field.name() == "pass" {
+            self.0 = Some(format!("{:?}", value));
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+/// The layer that times the spans, as described in the module documentation.
+pub struct TimePassesLayer;
+
+impl<S> Layer<S> for TimePassesLayer
+where
+    S: Subscriber,
+    for<'a> S: LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut visitor = PassVisitor::default();
+        attrs.record(&mut visitor);
+        let name = attrs.metadata().name();
+        let label = match visitor.0 {
+            Some(pass) => format!("{}({})", name, pass),
+            None => name.to_string(),
+        };
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanTiming {
+                label,
+                entered_at: None,
+                busy: Duration::ZERO,
+            });
+        }
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        if let Some(timing) = span.extensions_mut().get_mut::<SpanTiming>() {
+            timing.entered_at = Some(Instant::now());
+        }
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        let Some(timing) = extensions.get_mut::<SpanTiming>() else {
+            return;
+        };
+        if let Some(entered_at) = timing.entered_at.take() {
+            timing.busy += entered_at.elapsed();
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(span_timing) = span.extensions_mut().remove::<SpanTiming>() else {
+            return;
+        };
+        let mut timings = TIMINGS.lock().unwrap_or_else(PoisonError::into_inner);
+        let timing = timings
+            .get_or_insert_with(HashMap::new)
+            .entry(span_timing.label)
+            .or_default();
+        timing.count += 1;
+        timing.total += span_timing.busy;
+    }
+}
+
+/// The table of the timings of the spans closed so far, the slowest first.
+pub struct Summary(Vec<(String, Timing)>);
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:>12}  {:>8}  span", "time", "count")?;
+        for (label, timing) in &self.0 {
+            writeln!(
+                f,
+                "{:>10.3}ms  {:>8}  {}",
+                timing.total.as_secs_f64() * 1000.0,
+                timing.count,
+                label
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the timings of the spans closed so far.
+pub fn summary() -> Summary {
+    let timings = TIMINGS.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut timings: Vec<_> = timings
+        .iter()
+        .flatten()
+        .map(|(label, timing)| (label.clone(), *timing))
+        .collect();
+    timings.sort_by(|(label_a, a), (label_b, b)| b.total.cmp(&a.total).then(label_a.cmp(label_b)));
+    Summary(timings)
+}
+
+/// Prints the timings of the spans closed so far on the standard error.
+pub fn print_summary() {
+    eprint!("{}", summary());
+}
+
+/// Logs the timings of the spans closed so far, an event for each label.
+pub(crate) fn log_summary() {
+    for (label, timing) in summary().0 {
+        info!(
+            target: "span_timings",
+            "{}: {:.3}ms in {} spans",
+            label,
+            timing.total.as_secs_f64() * 1000.0,
+            timing.count
+        );
+    }
+}
@@ -0,0 +1,276 @@
+//! The file writer of the logger, configured by `<PREFIX>_LOG_WRITER_MODE` (see the
+//! crate documentation).
+//!
+//! The mode is a comma-separated list of:
+//! - `append`: the logs are appended to the file, instead of truncating it.
+//! - `rotate=SIZE`: once the file would exceed `SIZE` bytes (with an optional `K`,
+//!   `M` or `G` suffix, e.g., `rotate=10M`), it is renamed to `<file>.<N>`, with the
+//!   first free `N` starting from 1, and the logs go on in a new file.
+//! - `timestamp`: the UTC time at which the logger is initialized is added to the
+//!   file name, e.g., `tidec.log` becomes `tidec.20250102T030405.log`.
+//!
+//! By default, the file is truncated and never rotated, and no timestamp is added.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, Default)]
+/// How the log file is written.
+pub struct WriterMode {
+    /// Whether the logs are appended to the file, instead of truncating it.
+    pub append: bool,
+    /// The size in bytes over which the file is rotated, if any.
+    pub max_size: Option<u64>,
+    /// Whether a timestamp is added to the file name.
+    pub timestamp: bool,
+}
+
+impl WriterMode {
+    /// Parses the mode from its comma-separated list. It returns the invalid item
+    /// on errors.
+    pub fn parse(mode: &str) -> Result<Self, String> {
+        let mut writer_mode = WriterMode::default();
+        for item in mode
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+        {
+            if item == "append" {
+                writer_mode.append = true;
+            } else if item == "timestamp" {
+                writer_mode.timestamp = true;
+            } else if let Some(size) = item.strip_prefix("rotate=") {
+                writer_mode.max_size = Some(parse_size(size).ok_or_else(|| item.to_string())?);
+            } else {
+                return Err(item.to_string());
+            }
+        }
+        Ok(writer_mode)
+    }
+}
+
+/// Parses a size in bytes with an optional `K`, `M` or `G` suffix.
+fn parse_size(size: &str) -> Option<u64> {
+    let (digits, unit) = match size.char_indices().last()? {
+        (i, 'K' | 'k') => (&size[..i], 1 << 10),
+        (i, 'M' | 'm') => (&size[..i], 1 << 20),
+        (i, 'G' | 'g') => (&size[..i], 1 << 30),
+        _ => (size, 1),
+    };
+    digits
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(unit))
+        .filter(|&n| n > 0)
+}
+
+/// A log file, written as described in the module documentation.
+pub struct RollingFile {
+    path: PathBuf,
+    file: File,
+    /// The size of the file so far.
+    size: u64,
+    max_size: Option<u64>,
+}
+
+impl RollingFile {
+    /// Opens the log file of the given path with the given mode.
+    pub fn open(path: PathBuf, mode: WriterMode) -> io::Result<Self> {
+        let path = if mode.timestamp {
+            timestamped(&path, SystemTime::now())
+        } else {
+            path
+        };
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(mode.append)
+            .truncate(!mode.append)
+            .open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(RollingFile {
+            path,
+            file,
+            size,
+            max_size: mode.max_size,
+        })
+    }
+
+    /// Renames the file to `<file>.<N>`, with the first free `N`, and opens a new one.
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        let rotated_path = (1..)
+            .map(|n| {
+                let mut rotated_path = self.path.clone().into_os_string();
+                rotated_path.push(format!(".{}", n));
+                PathBuf::from(rotated_path)
+            })
+            .find(|rotated_path| !rotated_path.exists())
+            .unwrap();
+        fs::rename(&self.path, rotated_path)?;
+        self.file = File::create(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RollingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // A single write larger than the maximum size is written anyway, alone.
+        let size = self.size;
+        if self
+            .max_size
+            .is_some_and(|max_size| size > 0 && size + buf.len() as u64 > max_size)
+        {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Adds the given time to the file name of the path, before its extension.
+fn timestamped(path: &Path, time: SystemTime) -> PathBuf {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    let secs_of_day = secs % 86400;
+    let timestamp = format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60
+    );
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let file_name = match path.extension() {
+        Some(extension) => format!("{}.{}.{}", stem, timestamp, extension.to_string_lossy()),
+        None => format!("{}.{}", stem, timestamp),
+    };
+    path.with_file_name(file_name)
+}
+
+/// Returns the year, the month and the day of the given number of days since the
+/// UNIX epoch, in the proleptic Gregorian calendar.
+// It corresponds to `civil_from_days` in http://howardhinnant.github.io/date_algorithms.html
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a new empty directory for the files of the given test.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("tidec_log-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn parse_writer_mode() {
+        let mode = WriterMode::parse("append, rotate=10M,timestamp").unwrap();
+        assert!(mode.append);
+        assert_eq!(mode.max_size, Some(10 << 20));
+        assert!(mode.timestamp);
+
+        let mode = WriterMode::parse("").unwrap();
+        assert!(!mode.append && !mode.timestamp);
+        assert_eq!(mode.max_size, None);
+
+        assert_eq!(
+            WriterMode::parse("append,truncate").unwrap_err(),
+            "truncate"
+        );
+        assert_eq!(WriterMode::parse("rotate=0").unwrap_err(), "rotate=0");
+    }
+
+    #[test]
+    fn parse_size_suffixes() {
+        assert_eq!(parse_size("512"), Some(512));
+        assert_eq!(parse_size("4K"), Some(4 << 10));
+        assert_eq!(parse_size("4k"), Some(4 << 10));
+        assert_eq!(parse_size("3M"), Some(3 << 20));
+        assert_eq!(parse_size("2G"), Some(2 << 30));
+        assert_eq!(parse_size(""), None);
+        assert_eq!(parse_size("K"), None);
+        assert_eq!(parse_size("0"), None);
+        assert_eq!(parse_size("10T"), None);
+        assert_eq!(parse_size("-1"), None);
+        assert_eq!(parse_size(&format!("{}G", u64::MAX)), None);
+    }
+
+    #[test]
+    fn rotate_to_the_first_free_number() {
+        let dir = test_dir("rotate");
+        let path = dir.join("tidec.log");
+        let mode = WriterMode {
+            max_size: Some(8),
+            ..WriterMode::default()
+        };
+        let mut file = RollingFile::open(path.clone(), mode).unwrap();
+        file.write_all(b"first\n").unwrap();
+        file.write_all(b"second\n").unwrap();
+        file.write_all(b"third\n").unwrap();
+        // A write larger than the maximum size is written alone.
+        file.write_all(b"a long fourth line\n").unwrap();
+        file.flush().unwrap();
+
+        let read = |name: &str| fs::read_to_string(dir.join(name)).unwrap();
+        assert_eq!(read("tidec.log.1"), "first\n");
+        assert_eq!(read("tidec.log.2"), "second\n");
+        assert_eq!(read("tidec.log.3"), "third\n");
+        assert_eq!(read("tidec.log"), "a long fourth line\n");
+        assert!(!dir.join("tidec.log.4").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn timestamped_file_names() {
+        // 2025-01-02T03:04:05Z
+        let time = UNIX_EPOCH + std::time::Duration::from_secs(1_735_787_045);
+        assert_eq!(
+            timestamped(Path::new("logs/tidec.log"), time),
+            Path::new("logs/tidec.20250102T030405.log")
+        );
+        assert_eq!(
+            timestamped(Path::new("tidec"), time),
+            Path::new("tidec.20250102T030405")
+        );
+        assert_eq!(
+            timestamped(Path::new("tidec.log"), UNIX_EPOCH),
+            Path::new("tidec.19700101T000000.log")
+        );
+    }
+
+    #[test]
+    fn civil_dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        // 2000-02-29, a leap day of a year divisible by 400.
+        assert_eq!(civil_from_days(11016), (2000, 2, 29));
+        assert_eq!(civil_from_days(11017), (2000, 3, 1));
+    }
+}
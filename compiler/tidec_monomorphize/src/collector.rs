@@ -0,0 +1,284 @@
+//! The collector of the instances of the generic bodies of a unit.
+//!
+//! The roots of the collection are the non-generic bodies. For each body in the
+//! worklist, every `Callee::Generic` call is replaced by a direct call to the
+//! instance of the callee with the given generic arguments. An instance is created
+//! (and added to the worklist) the first time it is called, by replacing the generic
+//! parameters in a copy of the generic body. The generic bodies are not part of the
+//! resulting unit, hence the ones that are never called are dropped.
+
+use std::collections::HashMap;
+
+use tidec_lir::{
+    basic_blocks::BasicBlockData,
+    lir::{DefId, LirBody, LirBodyMetadata, LirCtx, LirUnit},
+    pretty::LirPrinter,
+    syntax::{
+        Callee, ConstOperand, GenericArgs, Generics, InlineAsmOperand, LocalData, Operand, RValue,
+        StatementKind, TerminatorKind,
+    },
+};
+use tidec_utils::index_vec::IdxVec;
+use tracing::{debug, instrument};
+
+/// The maximum depth of the instances, i.e., of the chain of generic calls that
+/// leads from a root to an instance. It stops the generic recursions that create
+/// a new instance at each call (e.g., `f::<T>` calling `f::<*T>`).
+// It corresponds to `recursion_limit` in rustc_monomorphize/src/collector.rs
+pub const RECURSION_LIMIT: usize = 64;
+
+#[instrument(level = "debug", skip(lir_ctx, lir_unit))]
+/// Returns the given unit with the generic bodies replaced by their instances.
+///
+/// The instances get a new `DefId` and a name mangled from the name of the generic
+/// body and the generic arguments (e.g., `id$LT$i32$GT$` for `id::<i32>`).
+// It corresponds to `collect_crate_mono_items` in rustc_monomorphize/src/collector.rs
+pub fn collect_and_monomorphize(lir_ctx: &LirCtx, lir_unit: LirUnit) -> LirUnit {
+    let printer = LirPrinter::new(lir_ctx, &lir_unit);
+    let next_def_id = lir_unit
+        .bodies
+        .iter()
+        .map(|lir_body| lir_body.metadata.def_id)
+        .chain(lir_unit.imports.keys().copied())
+        .map(|def_id| def_id.0 + 1)
+        .max()
+        .unwrap_or(0);
+
+    let mut generic_bodies = HashMap::new();
+    let mut worklist = Vec::new();
+    for lir_body in lir_unit.bodies {
+        if lir_body.generics.is_empty() {
+            worklist.push((lir_body, 0));
+        } else {
+            generic_bodies.insert(lir_body.metadata.def_id, lir_body);
+        }
+    }
+    // The roots are visited in the order of the unit.
+    worklist.reverse();
+
+    let mut collector = Collector {
+        printer,
+        generic_bodies,
+        instances: HashMap::new(),
+        next_def_id,
+        worklist,
+    };
+    let mut bodies = IdxVec::new();
+    while let Some((mut lir_body, depth)) = collector.worklist.pop() {
+        collector.collect_body(&mut lir_body, depth);
+        bodies.push(lir_body);
+    }
+
+    LirUnit {
+        metadata: lir_unit.metadata,
+        bodies,
+        imports: lir_unit.imports,
+    }
+}
+
+struct Collector<'a> {
+    /// The printer used to mangle the generic arguments into the names of the instances.
+    printer: LirPrinter<'a>,
+    /// The generic bodies of the unit, by their `DefId`.
+    generic_bodies: HashMap<DefId, LirBody>,
+    /// The `DefId` of the instances created so far.
+    instances: HashMap<(DefId, GenericArgs), DefId>,
+    /// The `DefId` of the next instance.
+    next_def_id: usize,
+    /// The bodies to visit, with their depth.
+    worklist: Vec<(LirBody, usize)>,
+}
+
+impl Collector<'_> {
+    /// Replaces the generic calls of the given (non-generic) body with direct calls
+    /// to their instances.
+    fn collect_body(&mut self, lir_body: &mut LirBody, depth: usize) {
+        for bb_data in lir_body.basic_blocks.iter_mut() {
+            let TerminatorKind::Call { func, .. } = &mut bb_data.terminator.kind else {
+                continue;
+            };
+            let Callee::Generic(def_id, generic_args) = func else {
+                continue;
+            };
+            if generic_args.has_params() {
+                panic!(
+                    "Generic arguments {:?} of the call to {:?} in {} are not concrete",
+                    generic_args, def_id, lir_body.metadata.name
+                );
+            }
+            let instance = self.instance(*def_id, generic_args.clone(), depth + 1);
+            *func = Callee::Direct(instance);
+        }
+    }
+
+    /// Returns the `DefId` of the instance of the generic body `def_id` with the given
+    /// arguments. The instance is created and added to the worklist if it is new.
+    fn instance(&mut self, def_id: DefId, generic_args: GenericArgs, depth: usize) -> DefId {
+        let key = (def_id, generic_args);
+        if let Some(instance) = self.instances.get(&key) {
+            return *instance;
+        }
+        let (def_id, generic_args) = key;
+
+        let generic_body = self.generic_bodies.get(&def_id).unwrap_or_else(|| {
+            panic!(
+                "Cannot instantiate {:?}: it is not a generic body of the unit",
+                def_id
+            )
+        });
+        if depth > RECURSION_LIMIT {
+            panic!(
+                "Reached the recursion limit ({}) while instantiating {}",
+                RECURSION_LIMIT, generic_body.metadata.name
+            );
+        }
+        if generic_args.0.len() != generic_body.generics.params.len() {
+            panic!(
+                "{} expects {} generic arguments, but {} were given",
+                generic_body.metadata.name,
+                generic_body.generics.params.len(),
+                generic_args.0.len()
+            );
+        }
+
+        let instance = DefId(self.next_def_id);
+        self.next_def_id += 1;
+        let name = self.mangle(&generic_body.metadata.name, &generic_args);
+        debug!("Instantiating {} as {:?}", name, instance);
+        let instance_body = instantiate_body(generic_body, instance, name, &generic_args);
+        self.instances.insert((def_id, generic_args), instance);
+        self.worklist.push((instance_body, depth));
+        instance
+    }
+
+    /// Returns the name of an instance, mangled as the legacy symbol names of rustc.
+    // It corresponds to `SymbolPrinter` in rustc_symbol_mangling/src/legacy.rs
+    fn mangle(&self, name: &str, generic_args: &GenericArgs) -> String {
+        let args: Vec<_> = generic_args
+            .0
+            .iter()
+            .map(|ty| self.printer.ty_to_string(ty))
+            .collect();
+        let mut mangled = name.to_string();
+        for c in format!("<{}>", args.join(",")).chars() {
+            match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '_' => mangled.push(c),
+                '<' => mangled.push_str("$LT$"),
+                '>' => mangled.push_str("$GT$"),
+                ',' => mangled.push_str("$C$"),
+                '*' => mangled.push_str("$BP$"),
+                '&' => mangled.push_str("$RF$"),
+                '(' => mangled.push_str("$LP$"),
+                ')' => mangled.push_str("$RP$"),
+                ' ' => {}
+                _ => mangled.push_str(&format!("$u{:x}$", c as u32)),
+            }
+        }
+        mangled
+    }
+}
+
+/// Returns a copy of the generic body, with the generic parameters replaced by
+/// the given arguments.
+fn instantiate_body(
+    generic_body: &LirBody,
+    def_id: DefId,
+    name: String,
+    generic_args: &GenericArgs,
+) -> LirBody {
+    let instantiate_local = |local_data: &LocalData| LocalData {
+        ty: local_data.ty.instantiate(generic_args),
+        ..local_data.clone()
+    };
+    LirBody {
+        metadata: LirBodyMetadata {
+            def_id,
+            name,
+            ..generic_body.metadata.clone()
+        },
+        generics: Generics::default(),
+        ret_and_args: generic_body
+            .ret_and_args
+            .iter()
+            .map(instantiate_local)
+            .collect(),
+        locals: generic_body.locals.iter().map(instantiate_local).collect(),
+        basic_blocks: generic_body
+            .basic_blocks
+            .iter()
+            .map(|bb_data| instantiate_block(bb_data.clone(), generic_args))
+            .collect(),
+    }
+}
+
+/// Replaces the generic parameters in the types of the given block.
+fn instantiate_block(mut bb_data: BasicBlockData, generic_args: &GenericArgs) -> BasicBlockData {
+    for stmt in &mut bb_data.statements {
+        match &mut stmt.kind {
+            StatementKind::Assign(assign) => instantiate_rvalue(&mut assign.1, generic_args),
+            StatementKind::SetDiscriminant { .. } => {}
+            StatementKind::InlineAsm(asm) => {
+                for operand in &mut asm.operands {
+                    if let InlineAsmOperand::In { value, .. }
+                    | InlineAsmOperand::InOut {
+                        in_value: value, ..
+                    } = operand
+                    {
+                        instantiate_operand(value, generic_args);
+                    }
+                }
+            }
+        }
+    }
+
+    match &mut bb_data.terminator.kind {
+        TerminatorKind::Goto { .. }
+        | TerminatorKind::Return
+        | TerminatorKind::Unreachable
+        | TerminatorKind::Drop { .. }
+        | TerminatorKind::UnwindResume => {}
+        TerminatorKind::Assert { cond, .. } => instantiate_operand(cond, generic_args),
+        TerminatorKind::Call { func, args, .. } => {
+            match func {
+                Callee::Direct(_) => {}
+                Callee::Generic(_, callee_args) => {
+                    *callee_args = callee_args.instantiate(generic_args)
+                }
+                Callee::Indirect(fn_ptr) => instantiate_operand(fn_ptr, generic_args),
+            }
+            for arg in args {
+                instantiate_operand(arg, generic_args);
+            }
+        }
+    }
+    bb_data
+}
+
+fn instantiate_rvalue(rvalue: &mut RValue, generic_args: &GenericArgs) {
+    match rvalue {
+        RValue::Use(operand) | RValue::UnaryOp(_, operand) => {
+            instantiate_operand(operand, generic_args)
+        }
+        RValue::Cast(_, operand, ty) => {
+            instantiate_operand(operand, generic_args);
+            *ty = ty.instantiate(generic_args);
+        }
+        RValue::BinaryOp(_, lhs, rhs) => {
+            instantiate_operand(lhs, generic_args);
+            instantiate_operand(rhs, generic_args);
+        }
+        RValue::Intrinsic(_, args) => {
+            for arg in args {
+                instantiate_operand(arg, generic_args);
+            }
+        }
+        RValue::Ref(_) | RValue::Discriminant(_) => {}
+    }
+}
+
+fn instantiate_operand(operand: &mut Operand, generic_args: &GenericArgs) {
+    match operand {
+        Operand::Copy(_) | Operand::Move(_) => {}
+        Operand::Const(ConstOperand::Value(_, ty)) => *ty = ty.instantiate(generic_args),
+    }
+}
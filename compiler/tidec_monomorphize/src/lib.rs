@@ -0,0 +1,9 @@
+//! The monomorphization of the LIR units.
+//!
+//! The generic bodies of a unit cannot be codegened as they are. The collector
+//! walks the calls reachable from the non-generic bodies and instantiates each
+//! generic body once for every list of generic arguments it is called with. Then,
+//! the partitioner splits the unit into the codegen units compiled in parallel.
+
+pub mod collector;
+pub mod partitioning;
@@ -0,0 +1,240 @@
+//! The partitioning of a unit into codegen units, which are compiled in parallel.
+//!
+//! Each defined body is placed in the codegen unit selected by the stable hash of its
+//! `DefId`, so that the partitioning depends neither on the host nor on the order of
+//! the bodies. A codegen unit also declares the bodies that its bodies reference but
+//! that it does not define (i.e., the ones of the other codegen units, and the
+//! declarations of the unit). A body with a local linkage referenced from another
+//! codegen unit is made external, with a hidden visibility, so that it can be linked
+//! across the codegen units without being exported by the linked output. A codegen
+//! unit imports the functions of the other units that its bodies reference (see
+//! `LirUnit::imports`).
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use tidec_lir::{
+    lir::{DefId, Linkage, LirBody, LirCtx, LirImport, LirUnit, LirUnitMetadata, Visibility},
+    syntax::{
+        Callee, ConstOperand, ConstValue, Generics, InlineAsmOperand, Local, Operand, RValue,
+        StatementKind, TerminatorKind,
+    },
+};
+use tidec_utils::{fingerprint::Fingerprint, idx::Idx, index_vec::IdxVec};
+use tracing::{debug, instrument};
+
+#[instrument(level = "debug", skip(lir_ctx, lir_unit), fields(unit = %lir_unit.metadata.unit_name))]
+/// Splits the given unit into at most `codegen_units` codegen units, as described in
+/// the module documentation. The codegen units without bodies are dropped, and the
+/// other ones are named `<unit>-cgu.<idx>`. A single codegen unit is the unit itself.
+// It corresponds to `partition` in rustc_monomorphize/src/partitioning.rs
+// TODO(bruzzone): the bodies made external may clash with the ones of the other
+// units linked with this one. They should be given a name unique to the unit.
+pub fn partition(lir_ctx: &LirCtx, lir_unit: LirUnit, codegen_units: usize) -> Vec<LirUnit> {
+    if codegen_units <= 1 {
+        return vec![lir_unit];
+    }
+
+    let LirUnit {
+        metadata,
+        bodies,
+        imports,
+    } = lir_unit;
+    let mut lir_bodies: Vec<LirBody> = bodies.into_iter().collect();
+    // The codegen unit of each body, or `None` if it is a declaration.
+    let cgu_of: HashMap<DefId, Option<usize>> = lir_bodies
+        .iter()
+        .map(|lir_body| {
+            let def_id = lir_body.metadata.def_id;
+            let cgu = (!lir_body.is_declaration()).then(|| cgu_of(def_id, codegen_units));
+            (def_id, cgu)
+        })
+        .collect();
+
+    let mut declared = vec![HashSet::new(); codegen_units];
+    let mut imported = vec![BTreeSet::new(); codegen_units];
+    let mut exported = HashSet::new();
+    for lir_body in &lir_bodies {
+        let Some(cgu) = cgu_of[&lir_body.metadata.def_id] else {
+            continue;
+        };
+        for def_id in references(lir_ctx, lir_body) {
+            match cgu_of.get(&def_id) {
+                Some(Some(callee_cgu)) if *callee_cgu == cgu => {}
+                Some(Some(_)) => {
+                    exported.insert(def_id);
+                    declared[cgu].insert(def_id);
+                }
+                Some(None) => {
+                    declared[cgu].insert(def_id);
+                }
+                None if imports.contains_key(&def_id) => {
+                    imported[cgu].insert(def_id);
+                }
+                None => panic!(
+                    "{} references {:?}, which is not declared in the unit",
+                    lir_body.metadata.name, def_id
+                ),
+            }
+        }
+    }
+
+    for lir_body in &mut lir_bodies {
+        let metadata = &mut lir_body.metadata;
+        if exported.contains(&metadata.def_id)
+            && matches!(metadata.linkage, Linkage::Private | Linkage::Internal)
+        {
+            debug!("Exporting {} to the other codegen units", metadata.name);
+            metadata.linkage = Linkage::External;
+            metadata.visibility = Visibility::Hidden;
+        }
+    }
+
+    let mut cgu_bodies: Vec<IdxVec<_, LirBody>> =
+        (0..codegen_units).map(|_| IdxVec::new()).collect();
+    for lir_body in lir_bodies {
+        for (cgu, declared) in declared.iter().enumerate() {
+            if declared.contains(&lir_body.metadata.def_id) {
+                cgu_bodies[cgu].push(declaration_of(&lir_body));
+            }
+        }
+        if let Some(cgu) = cgu_of[&lir_body.metadata.def_id] {
+            cgu_bodies[cgu].push(lir_body);
+        }
+    }
+
+    cgu_bodies
+        .into_iter()
+        .zip(imported)
+        .enumerate()
+        .filter(|(_, (bodies, _))| bodies.iter().any(|lir_body| !lir_body.is_declaration()))
+        .map(|(cgu, (bodies, imported))| LirUnit {
+            metadata: LirUnitMetadata {
+                unit_name: format!("{}-cgu.{}", metadata.unit_name, cgu),
+            },
+            bodies,
+            imports: imported
+                .into_iter()
+                .map(|def_id| {
+                    let import = &imports[&def_id];
+                    let import = LirImport {
+                        unit_name: import.unit_name.clone(),
+                        declaration: declaration_of(&import.declaration),
+                    };
+                    (def_id, import)
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// Returns the codegen unit of the body with the given `DefId`.
+fn cgu_of(def_id: DefId, codegen_units: usize) -> usize {
+    (Fingerprint::of(&def_id).to_smaller_hash() % codegen_units as u64) as usize
+}
+
+/// Returns a declaration of the given body, to be referenced from another codegen unit.
+fn declaration_of(lir_body: &LirBody) -> LirBody {
+    let mut metadata = lir_body.metadata.clone();
+    // A declaration can only have an external linkage.
+    if !matches!(metadata.linkage, Linkage::ExternWeak) {
+        metadata.linkage = Linkage::External;
+    }
+    LirBody {
+        metadata,
+        generics: Generics::default(),
+        ret_and_args: lir_body.ret_and_args.clone(),
+        locals: IdxVec::new(),
+        basic_blocks: IdxVec::new(),
+    }
+}
+
+/// Returns the bodies referenced by the given body, by a call, a function pointer or
+/// the drop glue of a dropped place.
+fn references(lir_ctx: &LirCtx, lir_body: &LirBody) -> BTreeSet<DefId> {
+    let mut def_ids = BTreeSet::new();
+    for bb_data in lir_body.basic_blocks.iter() {
+        for stmt in &bb_data.statements {
+            match &stmt.kind {
+                StatementKind::Assign(assign) => match &assign.1 {
+                    RValue::Use(operand)
+                    | RValue::UnaryOp(_, operand)
+                    | RValue::Cast(_, operand, _) => operand_references(operand, &mut def_ids),
+                    RValue::BinaryOp(_, lhs, rhs) => {
+                        operand_references(lhs, &mut def_ids);
+                        operand_references(rhs, &mut def_ids);
+                    }
+                    RValue::Intrinsic(_, args) => {
+                        for arg in args {
+                            operand_references(arg, &mut def_ids);
+                        }
+                    }
+                    RValue::Ref(_) | RValue::Discriminant(_) => {}
+                },
+                StatementKind::SetDiscriminant { .. } => {}
+                StatementKind::InlineAsm(asm) => {
+                    for value in asm.operands.iter().filter_map(InlineAsmOperand::in_value) {
+                        operand_references(value, &mut def_ids);
+                    }
+                }
+            }
+        }
+
+        match &bb_data.terminator.kind {
+            TerminatorKind::Goto { .. }
+            | TerminatorKind::Return
+            | TerminatorKind::Unreachable
+            | TerminatorKind::UnwindResume => {}
+            TerminatorKind::Assert { cond, .. } => operand_references(cond, &mut def_ids),
+            TerminatorKind::Call { func, args, .. } => {
+                match func {
+                    Callee::Direct(def_id) => {
+                        def_ids.insert(*def_id);
+                    }
+                    Callee::Generic(def_id, _) => panic!(
+                        "The generic call to {:?} in {} has not been monomorphized",
+                        def_id, lir_body.metadata.name
+                    ),
+                    Callee::Indirect(fn_ptr) => operand_references(fn_ptr, &mut def_ids),
+                }
+                for arg in args {
+                    operand_references(arg, &mut def_ids);
+                }
+            }
+            TerminatorKind::Drop { place, .. } => {
+                let ret_and_args_len = lir_body.ret_and_args.len();
+                let local_ty = if place.local.idx() < ret_and_args_len {
+                    &lir_body.ret_and_args[place.local].ty
+                } else {
+                    &lir_body.locals[Local::new(place.local.idx() - ret_and_args_len)].ty
+                };
+                let place_ty = place.ty(lir_ctx, local_ty.clone());
+                def_ids.extend(lir_ctx.drop_glue(&place_ty.ty));
+            }
+        }
+    }
+    def_ids
+}
+
+fn operand_references(operand: &Operand, def_ids: &mut BTreeSet<DefId>) {
+    match operand {
+        Operand::Copy(_) | Operand::Move(_) => {}
+        Operand::Const(ConstOperand::Value(value, _)) => const_references(value, def_ids),
+    }
+}
+
+fn const_references(value: &ConstValue, def_ids: &mut BTreeSet<DefId>) {
+    match value {
+        ConstValue::FnPtr(def_id) => {
+            def_ids.insert(*def_id);
+        }
+        ConstValue::Array(elems) => {
+            for elem in elems {
+                const_references(elem, def_ids);
+            }
+        }
+        ConstValue::ZST
+        | ConstValue::Scalar(_)
+        | ConstValue::Slice { .. }
+        | ConstValue::Indirect { .. } => {}
+    }
+}
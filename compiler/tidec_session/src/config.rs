@@ -0,0 +1,281 @@
+//! The options of a compilation (see `Options`).
+// It corresponds to rustc_session/src/config.rs
+
+use std::path::PathBuf;
+
+use tidec_abi::target::{CodeModel, LinkOutputKind, RelocModel};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// The kind of code to emit.
+pub enum EmitKind {
+    Object,
+    Assembly,
+    /// The objects of the units, linked by the system linker (see `tidec_link`).
+    Link(LinkOutputKind),
+}
+
+impl EmitKind {
+    /// Returns the extension of the file emitted for each module (e.g., `o` for
+    /// the objects, which are then linked into the output of `EmitKind::Link`).
+    pub fn module_extension(self) -> &'static str {
+        match self {
+            EmitKind::Object | EmitKind::Link(_) => "o",
+            EmitKind::Assembly => "s",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
+/// The optimization level of the generated code.
+// It corresponds to `OptLevel` in rustc_session/src/config.rs
+pub enum OptLevel {
+    /// `-O0`
+    No,
+    /// `-O1`
+    Less,
+    /// `-O2`
+    #[default]
+    More,
+    /// `-O3`
+    Aggressive,
+    /// `-Os`
+    Size,
+    /// `-Oz`
+    SizeMin,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+/// The link-time optimization of the units compiled together.
+// It corresponds to `Lto` in rustc_session/src/config.rs
+pub enum Lto {
+    /// Each unit is optimized and emitted on its own.
+    #[default]
+    No,
+    /// The units are linked into one module, optimized with the ThinLTO pipelines,
+    /// which are cheaper than the full ones.
+    Thin,
+    /// The units are linked into one module, which is optimized as a whole.
+    Fat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
+/// The amount of debug info (e.g., DWARF) emitted for the bodies. The source of a body
+/// without a span is its textual representation (see `tidec_lir::pretty::BodyLines`).
+// It corresponds to `DebugInfo` in rustc_session/src/config.rs
+pub enum DebugInfo {
+    /// No debug info.
+    #[default]
+    None,
+    /// Only the scopes of the functions and the locations of the instructions, which
+    /// are enough for backtraces and stepping.
+    LineTablesOnly,
+    /// Also the local variables and their types.
+    Full,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
+/// Where the debug info of an object is written.
+// It corresponds to `SplitDebuginfo` in rustc_target/src/spec/mod.rs
+// TODO(bruzzone): add the `unpacked` mode, which writes the debug info in a `.dwo` file
+// next to the object, once the LLVM backend can set the split DWARF file of the target
+// machine (which neither inkwell nor the LLVM C API expose). Then add the `packed` mode,
+// which packages the `.dwo` files into a `.dwp`.
+pub enum SplitDebugInfo {
+    /// In the object, with the code.
+    #[default]
+    Off,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
+/// The metadata attached to the loads and stores of the scalars, which tells the
+/// backend which memory accesses cannot alias, so that it can reorder or remove them.
+pub enum AliasMetadata {
+    /// No metadata: every access may alias any other one.
+    #[default]
+    None,
+    /// Type-based alias analysis: the accesses to scalars of different kinds (i.e.,
+    /// integers of different sizes, floats of different sizes and pointers) do not
+    /// alias. The signedness of an integer does not matter.
+    ///
+    /// It assumes that the memory written as a scalar is never read as a scalar of
+    /// another kind (e.g., through a pointer cast), as the strict aliasing of C.
+    // TODO(bruzzone): add the `noalias` scopes of the arguments that do not alias.
+    Tbaa,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+/// The set of the sanitizers the generated code is instrumented for. Their runtime is
+/// linked with the objects by `tidec_link` (e.g., with `-fsanitize=address`).
+// It corresponds to `SanitizerSet` in rustc_target/src/spec/mod.rs
+pub struct SanitizerSet(u8);
+
+impl SanitizerSet {
+    /// AddressSanitizer: the out-of-bounds and use-after-free accesses to the memory.
+    pub const ADDRESS: SanitizerSet = SanitizerSet(1 << 0);
+    /// UndefinedBehaviorSanitizer: the undefined behavior that can be checked on the
+    /// generated code (i.e., the out-of-bounds accesses to the objects of known size).
+    pub const UNDEFINED: SanitizerSet = SanitizerSet(1 << 1);
+
+    /// The sanitizers, with their names on the command line (e.g., `address`).
+    pub const NAMES: [(SanitizerSet, &'static str); 2] = [
+        (SanitizerSet::ADDRESS, "address"),
+        (SanitizerSet::UNDEFINED, "undefined"),
+    ];
+
+    /// Returns `true` if all the sanitizers of `other` are in `self`.
+    pub fn contains(self, other: SanitizerSet) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn insert(&mut self, other: SanitizerSet) {
+        self.0 |= other.0;
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns the sanitizer with the given name, if any.
+    pub fn from_name(name: &str) -> Option<SanitizerSet> {
+        SanitizerSet::NAMES
+            .into_iter()
+            .find_map(|(sanitizer, sanitizer_name)| (sanitizer_name == name).then_some(sanitizer))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
+/// The functions whose stack is protected by a canary, which is checked before
+/// returning to detect the buffer overflows that overwrite the return address.
+// It corresponds to `StackProtector` in rustc_target/src/spec/mod.rs
+pub enum StackProtector {
+    /// No function is protected.
+    #[default]
+    None,
+    /// The functions with a large character array (as `-fstack-protector` of GCC).
+    Basic,
+    /// The functions with an array of any type, or whose locals have their address
+    /// taken (as `-fstack-protector-strong` of GCC).
+    Strong,
+    /// All the functions (as `-fstack-protector-all` of GCC).
+    All,
+}
+
+impl std::ops::BitOr for SanitizerSet {
+    type Output = SanitizerSet;
+
+    fn bitor(self, rhs: SanitizerSet) -> SanitizerSet {
+        SanitizerSet(self.0 | rhs.0)
+    }
+}
+
+#[derive(Debug, Clone)]
+/// The options of the compilation, usually given on the command line.
+// It corresponds to `Options` in rustc_session/src/config.rs
+pub struct Options {
+    /// The kind of code to emit.
+    pub emit_kind: EmitKind,
+    /// Whether the metadata of each unit is written to `<out-dir>/<unit>.rmeta`, so
+    /// that the units compiled later can call its functions (see
+    /// `tidec_lir::metadata`).
+    pub emit_metadata: bool,
+    /// The metadata files of the units compiled before, whose functions the units can
+    /// call (`--extern`).
+    pub externs: Vec<PathBuf>,
+    /// The path of the output (`-o`). If not given, the output is named after the
+    /// first unit. It is ignored if more than one file is emitted (e.g., an object
+    /// for each codegen unit), as there is no single output to name.
+    pub output: Option<PathBuf>,
+    /// The directory where the outputs are written (`--out-dir`). If not given, the
+    /// current directory.
+    pub out_dir: Option<PathBuf>,
+    /// The optimization level of the generated code.
+    pub opt_level: OptLevel,
+    /// The pipeline of passes run by the backend before emission, in the syntax of
+    /// the backend (e.g., `default<O2>,instcount` for LLVM). If not given, the
+    /// backend runs the default pipeline of `opt_level`.
+    pub pass_pipeline: Option<String>,
+    /// The link-time optimization of the units compiled together.
+    pub lto: Lto,
+    /// The number of codegen units a unit is split into, which are compiled in
+    /// parallel (see `tidec_monomorphize::partitioning`). It is at least `1`.
+    pub codegen_units: usize,
+    /// The maximum size (in statements and terminators) of a body inlined into
+    /// its callers. Only the bodies marked with `FnAttribute::INLINE` are considered,
+    /// besides the `ALWAYS_INLINE` ones of any size, and `0` disables inlining. See
+    /// `tidec_lir::transform::Inline`.
+    pub inline_threshold: usize,
+    /// The directory where the LIR of every body is dumped after each pass, if any.
+    /// The body `foo` after the pass `Inline` is dumped to `<dir>/foo.Inline.lir`.
+    pub dump_lir_dir: Option<PathBuf>,
+    /// The directory where the CFG of every body is written as a Graphviz file
+    /// (`<dir>/<body>.dot`) before codegen, if any.
+    pub dump_dot_dir: Option<PathBuf>,
+    /// The directory of the incremental compilation cache, if any. The objects of the
+    /// codegen units whose bodies did not change since the previous run are reused
+    /// from it, instead of being codegened again (see `tidec_incremental`).
+    pub incremental_dir: Option<PathBuf>,
+    /// The amount of debug info emitted for the bodies.
+    pub debuginfo: DebugInfo,
+    /// The version of the DWARF debug info (from `2` to `5`). If not given, the
+    /// default of the backend.
+    pub dwarf_version: Option<u32>,
+    /// Where the debug info is written.
+    pub split_debuginfo: SplitDebugInfo,
+    /// The alias metadata attached to the memory accesses.
+    pub alias_metadata: AliasMetadata,
+    /// The sanitizers the generated code is instrumented for.
+    pub sanitizers: SanitizerSet,
+    /// Whether every function keeps the frame pointer, even if the target allows to
+    /// omit it, so that the profilers and the debuggers can walk the stack.
+    pub force_frame_pointers: bool,
+    /// The functions whose stack is protected by a canary.
+    pub stack_protector: StackProtector,
+    /// Whether the indices of the `Index` projections are checked against the length
+    /// of the indexed arrays (see `tidec_lir::transform::BoundsCheck`).
+    pub bounds_checks: bool,
+    /// The relocation model of the generated code. If not given, the objects linked
+    /// into an output are position independent (as the C compilers link
+    /// position-independent executables by default), and the other ones use the
+    /// default of the target.
+    pub reloc_model: Option<RelocModel>,
+    /// The code model of the generated code. If not given, the default of the target.
+    pub code_model: Option<CodeModel>,
+    /// Whether the time spent in each pass (i.e., in each span of the logger, see
+    /// `tidec_log::time_passes`) is printed at the end of the compilation.
+    pub time_passes: bool,
+    // TODO(bruzzone): add more arguments here
+}
+
+/// The default value of `Options::inline_threshold`.
+pub const DEFAULT_INLINE_THRESHOLD: usize = 50;
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            emit_kind: EmitKind::Object,
+            emit_metadata: false,
+            externs: vec![],
+            output: None,
+            out_dir: None,
+            opt_level: OptLevel::default(),
+            pass_pipeline: None,
+            lto: Lto::default(),
+            codegen_units: 1,
+            inline_threshold: DEFAULT_INLINE_THRESHOLD,
+            dump_lir_dir: None,
+            dump_dot_dir: None,
+            incremental_dir: None,
+            debuginfo: DebugInfo::default(),
+            dwarf_version: None,
+            split_debuginfo: SplitDebugInfo::default(),
+            alias_metadata: AliasMetadata::default(),
+            sanitizers: SanitizerSet::default(),
+            force_frame_pointers: false,
+            stack_protector: StackProtector::default(),
+            bounds_checks: false,
+            reloc_model: None,
+            code_model: None,
+            time_passes: false,
+        }
+    }
+}
@@ -0,0 +1,58 @@
+//! The diagnostics handler of a session, which reports the errors and the warnings
+//! to the user.
+// It corresponds to rustc_errors/src/lib.rs
+
+use std::fmt::Display;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use tidec_log::Logger;
+
+#[derive(Debug, Default)]
+/// Reports the diagnostics on the standard error and counts the errors, so that
+/// the compilation can go on after an error (e.g., to report the other ones) and
+/// stop before emitting anything. It can be shared between threads.
+// It corresponds to `DiagCtxt` in rustc_errors/src/lib.rs
+pub struct DiagCtxt {
+    err_count: AtomicUsize,
+}
+
+impl DiagCtxt {
+    pub fn new() -> Self {
+        DiagCtxt::default()
+    }
+
+    /// Reports an error.
+    pub fn err(&self, msg: impl Display) {
+        eprintln!("error: {}", msg);
+        self.err_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Reports a warning.
+    pub fn warn(&self, msg: impl Display) {
+        eprintln!("warning: {}", msg);
+    }
+
+    /// Reports an error and exits, as the compilation cannot go on.
+    pub fn fatal(&self, msg: impl Display) -> ! {
+        self.err(msg);
+        Logger::flush();
+        std::process::exit(1);
+    }
+
+    /// Returns the number of the errors reported so far.
+    pub fn err_count(&self) -> usize {
+        self.err_count.load(Ordering::Relaxed)
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.err_count() > 0
+    }
+
+    /// Exits if any error has been reported.
+    pub fn abort_if_errors(&self) {
+        if self.has_errors() {
+            Logger::flush();
+            std::process::exit(1);
+        }
+    }
+}
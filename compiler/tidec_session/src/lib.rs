@@ -0,0 +1,104 @@
+//! The session of a compilation, i.e., the state shared by all of its phases:
+//! the target, the options, the diagnostics handler and the paths of the outputs.
+//!
+//! The driver creates a single `Session` from the command line and shares it (in an
+//! `Arc`) with the `LirCtx` of every unit, which is where the rest of the compiler
+//! (e.g., the codegen contexts) reads it from.
+// It corresponds to rustc_session/src/lib.rs
+
+pub mod config;
+pub mod diagnostics;
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use tidec_abi::target::LirTarget;
+use tidec_log::{FallbackDefaultEnv, LogError, Logger, LoggerConfig, time_passes};
+
+use crate::config::{EmitKind, Options};
+use crate::diagnostics::DiagCtxt;
+
+#[derive(Debug)]
+/// The state shared by all the phases of a compilation.
+// It corresponds to `Session` in rustc_session/src/session.rs
+pub struct Session {
+    /// The target of the generated code.
+    pub target: LirTarget,
+    pub opts: Options,
+    dcx: DiagCtxt,
+}
+
+impl Session {
+    /// Creates a session, which keeps reporting the diagnostics to `dcx` (e.g., the
+    /// handler used while parsing the command line).
+    pub fn new(target: LirTarget, opts: Options, dcx: DiagCtxt) -> Self {
+        Session { target, opts, dcx }
+    }
+
+    /// Returns the diagnostics handler.
+    pub fn dcx(&self) -> &DiagCtxt {
+        &self.dcx
+    }
+
+    /// Returns the directory where the outputs are written (i.e., `--out-dir`, or
+    /// else the current directory).
+    pub fn out_dir(&self) -> &Path {
+        self.opts.out_dir.as_deref().unwrap_or(Path::new(""))
+    }
+
+    /// Returns the path of the output, i.e., `-o`, or else the given file name in
+    /// the output directory.
+    pub fn output_path(&self, file_name: &str) -> PathBuf {
+        self.opts
+            .output
+            .clone()
+            .unwrap_or_else(|| self.out_dir().join(file_name))
+    }
+
+    /// Returns the directory of the temporary files of the compilation, in the
+    /// output directory. It is named after the process, so that the compilations
+    /// running at the same time do not share it.
+    pub fn temps_dir(&self) -> PathBuf {
+        self.out_dir()
+            .join(format!("tidec-temps-{}", std::process::id()))
+    }
+
+    /// Returns the path of the file with the given extension (e.g., `o`) emitted for
+    /// the given module. The files emitted to be linked into the output (see
+    /// `EmitKind::Link`) are temporary files, while the other ones are outputs.
+    pub fn module_output_path(&self, module_name: &str, extension: &str) -> PathBuf {
+        let file_name = format!("{}.{}", module_name, extension);
+        match self.opts.emit_kind {
+            EmitKind::Object | EmitKind::Assembly => self.out_dir().join(file_name),
+            EmitKind::Link(_) => self.temps_dir().join(file_name),
+        }
+    }
+
+    /// Removes the temporary files of the compilation, once they are not needed
+    /// anymore (e.g., after linking).
+    pub fn remove_temps(&self) -> io::Result<()> {
+        match std::fs::remove_dir_all(self.temps_dir()) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+
+    /// Initializes the logger of the compiler, configured by the `TIDEC_LOG*` env
+    /// variables (see `tidec_log`), which also times the passes if `time_passes` is
+    /// set. The logger is global to the process, so it is initialized once.
+    pub fn init_logger(&self) -> Result<(), LogError> {
+        let mut config = LoggerConfig::from_prefix("TIDEC")?;
+        config.time_passes = self.opts.time_passes;
+        Logger::init_logger(config, FallbackDefaultEnv::No)
+    }
+
+    /// Prints the time spent in each pass, if `time_passes` is set, and writes the
+    /// pending logs and the end of the Chrome trace of the logger, if any. It is
+    /// called once, at the end of the compilation.
+    pub fn finish_profiling(&self) {
+        if self.opts.time_passes {
+            time_passes::print_summary();
+        }
+        Logger::flush();
+    }
+}
@@ -0,0 +1,62 @@
+//! The source locations of the LIR.
+//!
+//! A `Span` is a byte range in a source file registered in a `SourceMap`, which maps
+//! it back to a file name, a line and a column (e.g., for the debug info and the
+//! diagnostics). The LIR built by the compiler itself (e.g., by a transformation)
+//! has no source, and its spans are `Span::DUMMY`.
+// It corresponds to rustc_span/src/lib.rs
+
+pub mod source_map;
+
+use tidec_utils::newtype_index;
+
+newtype_index! {
+    /// The identifier of a source file in a `SourceMap`.
+    pub struct FileId {}
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A range of bytes, `lo..hi`, in a source file.
+// It corresponds to `SpanData` in rustc_span/src/lib.rs
+pub struct Span {
+    pub file: FileId,
+    pub lo: u32,
+    pub hi: u32,
+}
+
+impl Span {
+    /// The span of the code without a source (e.g., built by the compiler).
+    pub const DUMMY: Span = Span {
+        file: FileId::from_u32(FileId::MAX_AS_U32),
+        lo: 0,
+        hi: 0,
+    };
+
+    pub fn new(file: FileId, lo: u32, hi: u32) -> Self {
+        assert!(lo <= hi, "The span {}..{} is reversed", lo, hi);
+        Span { file, lo, hi }
+    }
+
+    pub fn is_dummy(&self) -> bool {
+        self.file == Span::DUMMY.file
+    }
+
+    /// Returns the span from the start of `self` to the end of `end`. If either span
+    /// is a dummy (or they are in different files), the other one is returned.
+    pub fn to(self, end: Span) -> Span {
+        if end.is_dummy() || self.file != end.file {
+            self
+        } else if self.is_dummy() {
+            end
+        } else {
+            Span::new(self.file, self.lo.min(end.lo), self.hi.max(end.hi))
+        }
+    }
+}
+
+impl Default for Span {
+    fn default() -> Self {
+        Span::DUMMY
+    }
+}
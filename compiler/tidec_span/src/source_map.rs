@@ -0,0 +1,106 @@
+//! The source files of the LIR, which resolve the spans to lines and columns.
+// It corresponds to rustc_span/src/source_map.rs
+
+use std::sync::Arc;
+
+use tidec_utils::index_vec::IdxVec;
+
+use crate::{FileId, Span};
+
+#[derive(Debug)]
+/// A source file, e.g., a `.lir` file parsed by `tidec_lir::parser`.
+pub struct SourceFile {
+    pub name: String,
+    pub src: String,
+    /// The byte offset of the start of each line.
+    line_starts: Vec<u32>,
+}
+
+impl SourceFile {
+    pub fn new(name: String, src: String) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(src.match_indices('\n').map(|(pos, _)| pos as u32 + 1))
+            .collect();
+        SourceFile {
+            name,
+            src,
+            line_starts,
+        }
+    }
+
+    /// Returns the line and the column (both starting from 1) of the given byte
+    /// offset. The column counts the characters, not the bytes.
+    pub fn line_col(&self, pos: u32) -> (u32, u32) {
+        let line = self.line_starts.partition_point(|start| *start <= pos) - 1;
+        let line_start = self.line_starts[line] as usize;
+        let end = (pos as usize).min(self.src.len());
+        let col = self.src[line_start..end].chars().count();
+        (line as u32 + 1, col as u32 + 1)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A resolved position in a source file.
+pub struct Loc {
+    pub file: FileId,
+    /// The line, starting from 1.
+    pub line: u32,
+    /// The column, starting from 1.
+    pub col: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+/// The source files of a `LirCtx`. The files are shared, so that cloning the map
+/// (e.g., when the context is forked for a codegen unit) is cheap.
+pub struct SourceMap {
+    files: IdxVec<FileId, Arc<SourceFile>>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        SourceMap::default()
+    }
+
+    /// Registers a source file with the given name (e.g., its path) and contents.
+    pub fn new_source_file(&mut self, name: String, src: String) -> FileId {
+        self.files.push(Arc::new(SourceFile::new(name, src)))
+    }
+
+    /// Returns the source file with the given id. It panics if it is not registered.
+    pub fn file(&self, file: FileId) -> &SourceFile {
+        self.files
+            .get(file)
+            .unwrap_or_else(|| panic!("The source file {:?} is not registered", file))
+    }
+
+    /// Returns the position of the start of the given span, or `None` if it is a dummy.
+    pub fn lookup(&self, span: Span) -> Option<Loc> {
+        if span.is_dummy() {
+            return None;
+        }
+        let (line, col) = self.file(span.file).line_col(span.lo);
+        Some(Loc {
+            file: span.file,
+            line,
+            col,
+        })
+    }
+
+    /// Returns the source code covered by the given span, if it is not a dummy.
+    pub fn span_to_snippet(&self, span: Span) -> Option<&str> {
+        if span.is_dummy() {
+            return None;
+        }
+        self.file(span.file)
+            .src
+            .get(span.lo as usize..span.hi as usize)
+    }
+
+    /// Returns the given span as `file:line:col`, as printed in the diagnostics.
+    pub fn span_to_string(&self, span: Span) -> String {
+        match self.lookup(span) {
+            Some(loc) => format!("{}:{}:{}", self.file(loc.file).name, loc.line, loc.col),
+            None => "<unknown>".to_string(),
+        }
+    }
+}
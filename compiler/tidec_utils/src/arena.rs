@@ -0,0 +1,226 @@
+//! Typed arenas and interners.
+//!
+//! It is inspired by the `TypedArena` type and the interners of the `rustc` compiler.
+//! An arena owns the values allocated in it, and hands out shared references to them
+//! that live as long as the arena. The values are never moved nor dropped before the
+//! arena, hence the references can be held without cloning the values.
+
+use std::{
+    borrow::Borrow,
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    fmt,
+    hash::{Hash, Hasher},
+};
+
+/// The capacity of the first chunk of an arena. Each next chunk doubles it.
+const FIRST_CHUNK_CAPACITY: usize = 64;
+
+/// An arena of values of type `T`.
+///
+/// The values are stored in chunks. A chunk is never reallocated (it is replaced by
+/// a new, bigger chunk once full), so the allocated values never move.
+pub struct TypedArena<T> {
+    chunks: RefCell<Vec<Vec<T>>>,
+}
+
+impl<T> Default for TypedArena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> TypedArena<T> {
+    /// Constructs a new, empty arena.
+    pub fn new() -> Self {
+        TypedArena {
+            chunks: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Moves the given value into the arena and returns a reference to it.
+    pub fn alloc(&self, value: T) -> &T {
+        let mut chunks = self.chunks.borrow_mut();
+        let needs_chunk = chunks
+            .last()
+            .is_none_or(|chunk| chunk.len() == chunk.capacity());
+        if needs_chunk {
+            let capacity = chunks
+                .last()
+                .map_or(FIRST_CHUNK_CAPACITY, |chunk| chunk.capacity() * 2);
+            chunks.push(Vec::with_capacity(capacity));
+        }
+        let chunk = chunks.last_mut().unwrap();
+        chunk.push(value);
+        let value: *const T = chunk.last().unwrap();
+        // SAFETY: the chunk has not been reallocated by the push, as it had spare
+        // capacity, and it is never reallocated nor dropped until the arena is dropped.
+        // Hence, the value lives (and does not move) as long as `self`.
+        unsafe { &*value }
+    }
+
+    /// Returns the number of values allocated in the arena.
+    pub fn len(&self) -> usize {
+        self.chunks.borrow().iter().map(Vec::len).sum()
+    }
+
+    /// Returns `true` if no value has been allocated in the arena.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T> fmt::Debug for TypedArena<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TypedArena")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+/// An interner of values of type `T`, backed by a `TypedArena`.
+///
+/// Interning two equal values returns the same reference, so each distinct value
+/// is stored only once.
+// It corresponds to `InternedSet` in rustc_middle/src/ty/context.rs
+pub struct Interner<T> {
+    arena: TypedArena<T>,
+    set: RefCell<HashSet<InternedRef<T>>>,
+}
+
+impl<T> Default for Interner<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Interner<T> {
+    /// Constructs a new, empty interner.
+    pub fn new() -> Self {
+        Interner {
+            arena: TypedArena::new(),
+            set: RefCell::new(HashSet::new()),
+        }
+    }
+
+    /// Returns the number of distinct values interned so far.
+    pub fn len(&self) -> usize {
+        self.arena.len()
+    }
+
+    /// Returns `true` if no value has been interned.
+    pub fn is_empty(&self) -> bool {
+        self.arena.is_empty()
+    }
+}
+
+impl<T: Eq + Hash> Interner<T> {
+    /// Returns a reference to the interned value equal to the given one, moving
+    /// the value into the arena if it has not been interned yet.
+    pub fn intern(&self, value: T) -> &T {
+        if let Some(interned) = self.set.borrow().get(&value) {
+            // SAFETY: the interned values are allocated in `self.arena`.
+            return unsafe { &*interned.0 };
+        }
+        let interned = self.arena.alloc(value);
+        self.set.borrow_mut().insert(InternedRef(interned));
+        interned
+    }
+}
+
+impl<T> fmt::Debug for Interner<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Interner")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+/// An `Interner` which also memoizes the interned value computed for a key.
+///
+/// It is used for the queries whose results are interned, such as the layout
+/// of a type: the result is computed once per key, and stored once per value.
+// It corresponds to the query caches in rustc_query_system/src/query/caches.rs
+pub struct KeyedInterner<K, T> {
+    interner: Interner<T>,
+    cache: RefCell<HashMap<K, InternedRef<T>>>,
+}
+
+impl<K, T> Default for KeyedInterner<K, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, T> KeyedInterner<K, T> {
+    /// Constructs a new, empty interner.
+    pub fn new() -> Self {
+        KeyedInterner {
+            interner: Interner::new(),
+            cache: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the number of keys whose value is memoized.
+    pub fn cached_len(&self) -> usize {
+        self.cache.borrow().len()
+    }
+}
+
+impl<K: Eq + Hash, T: Eq + Hash> KeyedInterner<K, T> {
+    /// Returns the value memoized for the given key, if any.
+    pub fn get(&self, key: &K) -> Option<&T> {
+        let cache = self.cache.borrow();
+        // SAFETY: the memoized values are allocated in the arena of `self.interner`.
+        cache.get(key).map(|interned| unsafe { &*interned.0 })
+    }
+
+    /// Interns the given value and memoizes it for the given key. It returns the
+    /// interned value, which may have been interned before for another key.
+    pub fn insert(&self, key: K, value: T) -> &T {
+        let interned = self.interner.intern(value);
+        self.cache.borrow_mut().insert(key, InternedRef(interned));
+        interned
+    }
+}
+
+// SAFETY: the `InternedRef`s of an interner point into its own arena, so they move
+// with it, and a moved interner can only be used by its new thread.
+unsafe impl<T: Send> Send for Interner<T> {}
+
+// SAFETY: see the implementation for `Interner`.
+unsafe impl<K: Send, T: Send> Send for KeyedInterner<K, T> {}
+
+impl<K, T> fmt::Debug for KeyedInterner<K, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyedInterner")
+            .field("len", &self.interner.len())
+            .field("cached_len", &self.cached_len())
+            .finish()
+    }
+}
+
+/// A pointer to a value in the arena of an `Interner`, which is hashed and compared
+/// by the value it points to.
+struct InternedRef<T>(*const T);
+
+impl<T> Borrow<T> for InternedRef<T> {
+    fn borrow(&self) -> &T {
+        // SAFETY: an `InternedRef` lives in the set of the interner that owns the value.
+        unsafe { &*self.0 }
+    }
+}
+
+impl<T: PartialEq> PartialEq for InternedRef<T> {
+    fn eq(&self, other: &Self) -> bool {
+        Borrow::<T>::borrow(self) == Borrow::<T>::borrow(other)
+    }
+}
+
+impl<T: Eq> Eq for InternedRef<T> {}
+
+impl<T: Hash> Hash for InternedRef<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Borrow::<T>::borrow(self).hash(state)
+    }
+}
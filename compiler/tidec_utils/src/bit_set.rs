@@ -0,0 +1,488 @@
+//! Sets of indices, e.g., of the locals or the basic blocks of a body, for the
+//! dataflow analyses.
+//!
+//! It is inspired by the `DenseBitSet` and `HybridBitSet` types from the `rustc`
+//! compiler.
+//!
+//! ```
+//! use tidec_utils::{bit_set::{IdxBitSet, IdxSet}, newtype_index};
+//!
+//! newtype_index! {
+//!     pub struct Local {}
+//! }
+//!
+//! let local = Local::from_u32;
+//! let mut live: IdxBitSet<Local> = [local(1), local(70)].into_iter().collect();
+//! live.union(&[local(2), local(130)].into_iter().collect());
+//! live.subtract(&[local(70)].into_iter().collect());
+//! assert_eq!(live.iter().collect::<Vec<_>>(), [local(1), local(2), local(130)]);
+//!
+//! // The set becomes dense after `SPARSE_MAX` indices.
+//! let mut set = IdxSet::new_empty(1000);
+//! (0..20).for_each(|i| _ = set.insert(local(i)));
+//! set.intersect(&IdxSet::Dense(live));
+//! assert_eq!(set.iter().collect::<Vec<_>>(), [local(1), local(2)]);
+//! ```
+
+use crate::idx::Idx;
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+};
+
+type Word = u64;
+const WORD_BITS: usize = Word::BITS as usize;
+
+/// The number of indices over which an `IdxSet` becomes dense.
+const SPARSE_MAX: usize = 8;
+
+#[inline]
+fn num_words(domain_size: usize) -> usize {
+    domain_size.div_ceil(WORD_BITS)
+}
+
+#[inline]
+fn word_index_and_mask(idx: usize) -> (usize, Word) {
+    (idx / WORD_BITS, 1 << (idx % WORD_BITS))
+}
+
+/// A dense set of the indices of type `I` in `0..domain_size`, with a bit for each
+/// index. The domain grows when an index outside of it is inserted, and the sets
+/// with different domains can be combined.
+// The traits are implemented by hand, as deriving them would require them on `I`.
+pub struct IdxBitSet<I: Idx> {
+    domain_size: usize,
+    words: Vec<Word>,
+    _marker: PhantomData<fn(&I)>,
+}
+
+impl<I: Idx> IdxBitSet<I> {
+    /// Creates an empty set of the given domain.
+    pub fn new_empty(domain_size: usize) -> Self {
+        IdxBitSet {
+            domain_size,
+            words: vec![0; num_words(domain_size)],
+            _marker: PhantomData,
+        }
+    }
+
+    /// Creates a set with all the indices of the given domain.
+    pub fn new_filled(domain_size: usize) -> Self {
+        let mut set = IdxBitSet {
+            domain_size,
+            words: vec![!0; num_words(domain_size)],
+            _marker: PhantomData,
+        };
+        set.clear_excess_bits();
+        set
+    }
+
+    #[inline]
+    pub fn domain_size(&self) -> usize {
+        self.domain_size
+    }
+
+    /// Grows the domain to at least `domain_size`.
+    pub fn ensure_domain(&mut self, domain_size: usize) {
+        if self.domain_size < domain_size {
+            self.domain_size = domain_size;
+            self.words.resize(num_words(domain_size), 0);
+        }
+    }
+
+    /// Clears the bits of the last word that are outside of the domain.
+    fn clear_excess_bits(&mut self) {
+        let excess_bits = self.domain_size % WORD_BITS;
+        if excess_bits > 0 {
+            let last = self.words.len() - 1;
+            self.words[last] &= (1 << excess_bits) - 1;
+        }
+    }
+
+    #[inline]
+    pub fn contains(&self, elem: I) -> bool {
+        let (word_index, mask) = word_index_and_mask(elem.idx());
+        self.words
+            .get(word_index)
+            .is_some_and(|&word| word & mask != 0)
+    }
+
+    /// Inserts the given index, growing the domain if needed. It returns `true` if
+    /// the set has changed.
+    #[inline]
+    pub fn insert(&mut self, elem: I) -> bool {
+        self.ensure_domain(elem.idx() + 1);
+        let (word_index, mask) = word_index_and_mask(elem.idx());
+        let word = &mut self.words[word_index];
+        let changed = *word & mask == 0;
+        *word |= mask;
+        changed
+    }
+
+    /// Removes the given index. It returns `true` if the set has changed.
+    #[inline]
+    pub fn remove(&mut self, elem: I) -> bool {
+        let (word_index, mask) = word_index_and_mask(elem.idx());
+        match self.words.get_mut(word_index) {
+            Some(word) => {
+                let changed = *word & mask != 0;
+                *word &= !mask;
+                changed
+            }
+            None => false,
+        }
+    }
+
+    /// Inserts all the indices of the domain.
+    pub fn insert_all(&mut self) {
+        self.words.fill(!0);
+        self.clear_excess_bits();
+    }
+
+    /// Removes all the indices, keeping the domain.
+    pub fn clear(&mut self) {
+        self.words.fill(0);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|&word| word == 0)
+    }
+
+    /// Returns the number of the indices in the set.
+    pub fn count(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// Inserts the indices of `other`, growing the domain if needed. It returns
+    /// `true` if the set has changed.
+    pub fn union(&mut self, other: &IdxBitSet<I>) -> bool {
+        self.ensure_domain(other.domain_size);
+        self.combine(other, |a, b| a | b)
+    }
+
+    /// Removes the indices that are not in `other`. It returns `true` if the set has
+    /// changed.
+    pub fn intersect(&mut self, other: &IdxBitSet<I>) -> bool {
+        let mut changed = self.combine(other, |a, b| a & b);
+        // The indices outside of the domain of `other` are not in it.
+        for word in self.words.iter_mut().skip(other.words.len()) {
+            changed |= *word != 0;
+            *word = 0;
+        }
+        changed
+    }
+
+    /// Removes the indices of `other`. It returns `true` if the set has changed.
+    pub fn subtract(&mut self, other: &IdxBitSet<I>) -> bool {
+        self.combine(other, |a, b| a & !b)
+    }
+
+    /// Returns `true` if all the indices of `other` are in the set.
+    pub fn is_superset(&self, other: &IdxBitSet<I>) -> bool {
+        other.words.iter().enumerate().all(|(i, &word)| {
+            let own = self.words.get(i).copied().unwrap_or(0);
+            own & word == word
+        })
+    }
+
+    /// Combines the words of the set with the ones of `other`, in the domain of both.
+    fn combine(&mut self, other: &IdxBitSet<I>, op: impl Fn(Word, Word) -> Word) -> bool {
+        let mut changed = false;
+        for (word, &other_word) in self.words.iter_mut().zip(&other.words) {
+            let new_word = op(*word, other_word);
+            changed |= new_word != *word;
+            *word = new_word;
+        }
+        changed
+    }
+
+    /// Returns the indices of the set, in increasing order.
+    pub fn iter(&self) -> BitIter<'_, I> {
+        BitIter {
+            words: self.words.iter().enumerate(),
+            word: 0,
+            offset: 0,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<I: Idx> Clone for IdxBitSet<I> {
+    fn clone(&self) -> Self {
+        IdxBitSet {
+            domain_size: self.domain_size,
+            words: self.words.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<I: Idx> PartialEq for IdxBitSet<I> {
+    fn eq(&self, other: &Self) -> bool {
+        self.domain_size == other.domain_size && self.words == other.words
+    }
+}
+
+impl<I: Idx> Eq for IdxBitSet<I> {}
+
+impl<I: Idx> Hash for IdxBitSet<I> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.domain_size.hash(state);
+        self.words.hash(state);
+    }
+}
+
+impl<I: Idx + fmt::Debug> fmt::Debug for IdxBitSet<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<I: Idx> FromIterator<I> for IdxBitSet<I> {
+    fn from_iter<T: IntoIterator<Item = I>>(iter: T) -> Self {
+        let mut set = IdxBitSet::new_empty(0);
+        for elem in iter {
+            set.insert(elem);
+        }
+        set
+    }
+}
+
+/// The iterator over the indices of an `IdxBitSet`.
+pub struct BitIter<'a, I: Idx> {
+    words: std::iter::Enumerate<std::slice::Iter<'a, Word>>,
+    /// The bits of the current word that are left.
+    word: Word,
+    /// The index of the first bit of the current word.
+    offset: usize,
+    _marker: PhantomData<fn() -> I>,
+}
+
+impl<I: Idx> Iterator for BitIter<'_, I> {
+    type Item = I;
+
+    fn next(&mut self) -> Option<I> {
+        while self.word == 0 {
+            let (word_index, &word) = self.words.next()?;
+            self.word = word;
+            self.offset = word_index * WORD_BITS;
+        }
+        let bit = self.word.trailing_zeros() as usize;
+        // Clears the lowest bit that is set.
+        self.word &= self.word - 1;
+        Some(I::new(self.offset + bit))
+    }
+}
+
+/// A sparse set of at most `SPARSE_MAX` indices of type `I`, in a sorted list.
+pub struct SparseIdxSet<I: Idx> {
+    domain_size: usize,
+    elems: Vec<usize>,
+    _marker: PhantomData<fn(&I)>,
+}
+
+/// A set of the indices of type `I` in `0..domain_size`, which is a sorted list of
+/// indices while it is small, and an `IdxBitSet` otherwise. It is suited to the
+/// sets of a large domain that are often small (e.g., the locals live at a point
+/// of a large body).
+// It corresponds to `HybridBitSet` in rustc_index/src/bit_set.rs
+pub enum IdxSet<I: Idx> {
+    Sparse(SparseIdxSet<I>),
+    Dense(IdxBitSet<I>),
+}
+
+impl<I: Idx> IdxSet<I> {
+    /// Creates an empty set of the given domain.
+    pub fn new_empty(domain_size: usize) -> Self {
+        IdxSet::Sparse(SparseIdxSet {
+            domain_size,
+            elems: Vec::new(),
+            _marker: PhantomData,
+        })
+    }
+
+    pub fn domain_size(&self) -> usize {
+        match self {
+            IdxSet::Sparse(sparse) => sparse.domain_size,
+            IdxSet::Dense(dense) => dense.domain_size(),
+        }
+    }
+
+    pub fn contains(&self, elem: I) -> bool {
+        match self {
+            IdxSet::Sparse(sparse) => sparse.elems.binary_search(&elem.idx()).is_ok(),
+            IdxSet::Dense(dense) => dense.contains(elem),
+        }
+    }
+
+    /// Inserts the given index, growing the domain if needed, and becoming dense if
+    /// the set is too large. It returns `true` if the set has changed.
+    pub fn insert(&mut self, elem: I) -> bool {
+        match self {
+            IdxSet::Sparse(sparse) => {
+                sparse.domain_size = sparse.domain_size.max(elem.idx() + 1);
+                let Err(pos) = sparse.elems.binary_search(&elem.idx()) else {
+                    return false;
+                };
+                if sparse.elems.len() < SPARSE_MAX {
+                    sparse.elems.insert(pos, elem.idx());
+                    return true;
+                }
+                let mut dense = self.to_dense();
+                dense.insert(elem);
+                *self = IdxSet::Dense(dense);
+                true
+            }
+            IdxSet::Dense(dense) => dense.insert(elem),
+        }
+    }
+
+    /// Removes the given index. It returns `true` if the set has changed.
+    pub fn remove(&mut self, elem: I) -> bool {
+        match self {
+            IdxSet::Sparse(sparse) => match sparse.elems.binary_search(&elem.idx()) {
+                Ok(pos) => {
+                    sparse.elems.remove(pos);
+                    true
+                }
+                Err(_) => false,
+            },
+            IdxSet::Dense(dense) => dense.remove(elem),
+        }
+    }
+
+    /// Removes all the indices, keeping the domain.
+    pub fn clear(&mut self) {
+        *self = IdxSet::new_empty(self.domain_size());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        match self {
+            IdxSet::Sparse(sparse) => sparse.elems.is_empty(),
+            IdxSet::Dense(dense) => dense.is_empty(),
+        }
+    }
+
+    /// Returns the number of the indices in the set.
+    pub fn count(&self) -> usize {
+        match self {
+            IdxSet::Sparse(sparse) => sparse.elems.len(),
+            IdxSet::Dense(dense) => dense.count(),
+        }
+    }
+
+    /// Returns the set as an `IdxBitSet`.
+    pub fn to_dense(&self) -> IdxBitSet<I> {
+        match self {
+            IdxSet::Sparse(sparse) => {
+                let mut dense = IdxBitSet::new_empty(sparse.domain_size);
+                for &elem in &sparse.elems {
+                    dense.insert(I::new(elem));
+                }
+                dense
+            }
+            IdxSet::Dense(dense) => dense.clone(),
+        }
+    }
+
+    /// Inserts the indices of `other`, growing the domain if needed. It returns
+    /// `true` if the set has changed.
+    pub fn union(&mut self, other: &IdxSet<I>) -> bool {
+        match (&mut *self, other) {
+            (IdxSet::Dense(dense), IdxSet::Dense(other)) => dense.union(other),
+            (_, IdxSet::Sparse(other)) => {
+                let mut changed = false;
+                for &elem in &other.elems {
+                    changed |= self.insert(I::new(elem));
+                }
+                match self {
+                    IdxSet::Sparse(sparse) => {
+                        sparse.domain_size = sparse.domain_size.max(other.domain_size)
+                    }
+                    IdxSet::Dense(dense) => dense.ensure_domain(other.domain_size),
+                }
+                changed
+            }
+            (IdxSet::Sparse(..), IdxSet::Dense(other)) => {
+                let mut dense = self.to_dense();
+                let changed = dense.union(other);
+                *self = IdxSet::Dense(dense);
+                changed
+            }
+        }
+    }
+
+    /// Removes the indices that are not in `other`. It returns `true` if the set has
+    /// changed.
+    pub fn intersect(&mut self, other: &IdxSet<I>) -> bool {
+        match (&mut *self, other) {
+            (IdxSet::Dense(dense), IdxSet::Dense(other)) => dense.intersect(other),
+            (IdxSet::Sparse(sparse), _) => {
+                let len = sparse.elems.len();
+                sparse.elems.retain(|&elem| other.contains(I::new(elem)));
+                sparse.elems.len() != len
+            }
+            (IdxSet::Dense(dense), IdxSet::Sparse(..)) => dense.intersect(&other.to_dense()),
+        }
+    }
+
+    /// Removes the indices of `other`. It returns `true` if the set has changed.
+    pub fn subtract(&mut self, other: &IdxSet<I>) -> bool {
+        match (&mut *self, other) {
+            (IdxSet::Dense(dense), IdxSet::Dense(other)) => dense.subtract(other),
+            (IdxSet::Sparse(sparse), _) => {
+                let len = sparse.elems.len();
+                sparse.elems.retain(|&elem| !other.contains(I::new(elem)));
+                sparse.elems.len() != len
+            }
+            (IdxSet::Dense(dense), IdxSet::Sparse(other)) => {
+                let mut changed = false;
+                for &elem in &other.elems {
+                    changed |= dense.remove(I::new(elem));
+                }
+                changed
+            }
+        }
+    }
+
+    /// Returns the indices of the set, in increasing order.
+    pub fn iter(&self) -> impl Iterator<Item = I> + '_ {
+        let (sparse, dense) = match self {
+            IdxSet::Sparse(sparse) => (Some(sparse.elems.iter().map(|&elem| I::new(elem))), None),
+            IdxSet::Dense(dense) => (None, Some(dense.iter())),
+        };
+        sparse
+            .into_iter()
+            .flatten()
+            .chain(dense.into_iter().flatten())
+    }
+}
+
+impl<I: Idx> Clone for SparseIdxSet<I> {
+    fn clone(&self) -> Self {
+        SparseIdxSet {
+            domain_size: self.domain_size,
+            elems: self.elems.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<I: Idx> Clone for IdxSet<I> {
+    fn clone(&self) -> Self {
+        match self {
+            IdxSet::Sparse(sparse) => IdxSet::Sparse(sparse.clone()),
+            IdxSet::Dense(dense) => IdxSet::Dense(dense.clone()),
+        }
+    }
+}
+
+impl<I: Idx + fmt::Debug> fmt::Debug for IdxSet<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
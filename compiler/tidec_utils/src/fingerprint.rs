@@ -0,0 +1,103 @@
+//! A 128-bit hash that is stable across runs and hosts (see `StableHasher`), to tell
+//! whether something changed, e.g., a body between two incremental compilations.
+//!
+//! ```
+//! use tidec_utils::fingerprint::Fingerprint;
+//!
+//! let fingerprint = Fingerprint::of(&("main", 42u32));
+//! assert_eq!(fingerprint, Fingerprint::of(&("main", 42u32)));
+//! assert_ne!(fingerprint, Fingerprint::of(&("main", 43u32)));
+//! assert_eq!(Fingerprint::from_hex(&fingerprint.to_hex()), Some(fingerprint));
+//! ```
+
+use std::fmt;
+use std::hash::Hash;
+
+use crate::stable_hasher::StableHasher;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+/// The stable 128-bit hash of a value, as two 64-bit halves.
+// It corresponds to `Fingerprint` in rustc_data_structures/src/fingerprint.rs
+pub struct Fingerprint(u64, u64);
+
+impl Fingerprint {
+    pub const ZERO: Fingerprint = Fingerprint(0, 0);
+
+    #[inline]
+    pub const fn new(high: u64, low: u64) -> Self {
+        Fingerprint(high, low)
+    }
+
+    /// Returns the fingerprint of the given value, hashed with a `StableHasher`.
+    pub fn of<T: Hash + ?Sized>(value: &T) -> Self {
+        let mut hasher = StableHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish_fingerprint()
+    }
+
+    #[inline]
+    pub const fn as_u128(self) -> u128 {
+        ((self.0 as u128) << 64) | self.1 as u128
+    }
+
+    /// Returns a 64-bit hash of the fingerprint, e.g., to pick one of a few buckets.
+    #[inline]
+    pub const fn to_smaller_hash(self) -> u64 {
+        self.0 ^ self.1
+    }
+
+    /// Combines the fingerprint with another one, where the order matters: it is
+    /// used for the fingerprint of a sequence of values.
+    #[inline]
+    pub const fn combine(self, other: Fingerprint) -> Self {
+        // It is the `hash_combine` of Boost, on each half.
+        Fingerprint(
+            self.0.wrapping_mul(3).wrapping_add(other.0),
+            self.1.wrapping_mul(3).wrapping_add(other.1),
+        )
+    }
+
+    /// Combines the fingerprint with another one, where the order does not matter: it
+    /// is used for the fingerprint of a set of values, e.g., of the entries of a
+    /// `HashMap`, without sorting them.
+    #[inline]
+    pub const fn combine_commutative(self, other: Fingerprint) -> Self {
+        let sum = self.as_u128().wrapping_add(other.as_u128());
+        Fingerprint((sum >> 64) as u64, sum as u64)
+    }
+
+    /// Returns the fingerprint as 32 hexadecimal digits.
+    pub fn to_hex(self) -> String {
+        self.to_string()
+    }
+
+    /// Parses a fingerprint from its 32 hexadecimal digits (see `to_hex`).
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        if hex.len() != 32 {
+            return None;
+        }
+        let value = u128::from_str_radix(hex, 16).ok()?;
+        Some(Fingerprint((value >> 64) as u64, value as u64))
+    }
+}
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:032x}", self.as_u128())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Fingerprint {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_hex())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Fingerprint {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        Fingerprint::from_hex(&hex).ok_or_else(|| serde::de::Error::custom("invalid fingerprint"))
+    }
+}
@@ -3,6 +3,9 @@
 //! It is inspired by the `IndexSlice` type from the `rustc` compiler.
 
 use crate::idx::{Idx, IntoSliceIdx};
+use crate::index_vec::IdxVec;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
 use std::{
     marker::PhantomData,
     ops::{Index, IndexMut},
@@ -159,6 +162,42 @@ impl<I: Idx, T> IdxSlice<I, T> {
     }
 }
 
+// The parallel iterators, e.g., to codegen the bodies or to analyze the blocks of a
+// body in parallel.
+#[cfg(feature = "rayon")]
+impl<I: Idx + Send, T: Sync> IdxSlice<I, T> {
+    #[inline]
+    pub fn par_iter(&self) -> rayon::slice::Iter<'_, T> {
+        self.raw.par_iter()
+    }
+
+    #[inline]
+    pub fn par_iter_enumerated(&self) -> impl rayon::iter::IndexedParallelIterator<Item = (I, &T)> {
+        self.raw.par_iter().enumerate().map(|(n, t)| (I::new(n), t))
+    }
+
+    #[inline]
+    pub fn par_iter_mut(&mut self) -> rayon::slice::IterMut<'_, T>
+    where
+        T: Send,
+    {
+        self.raw.par_iter_mut()
+    }
+
+    #[inline]
+    pub fn par_iter_enumerated_mut(
+        &mut self,
+    ) -> impl rayon::iter::IndexedParallelIterator<Item = (I, &mut T)>
+    where
+        T: Send,
+    {
+        self.raw
+            .par_iter_mut()
+            .enumerate()
+            .map(|(n, t)| (I::new(n), t))
+    }
+}
+
 ////////// Trait implementations  //////////
 
 impl<I: Idx, T, R: IntoSliceIdx<I, [T]>> Index<R> for IdxSlice<I, T> {
@@ -196,3 +235,20 @@ impl<'a, I: Idx, T> IntoIterator for &'a mut IdxSlice<I, T> {
         self.raw.iter_mut()
     }
 }
+
+impl<I: Idx, T: Clone> ToOwned for IdxSlice<I, T> {
+    type Owned = IdxVec<I, T>;
+
+    fn to_owned(&self) -> IdxVec<I, T> {
+        IdxVec::from_raw(self.raw.to_owned())
+    }
+}
+
+// As an `IdxVec`, an `IdxSlice` is serialized as a plain sequence. It is deserialized
+// as an `IdxVec`, as it is unsized.
+#[cfg(feature = "serde")]
+impl<I: Idx, T: serde::Serialize> serde::Serialize for IdxSlice<I, T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.raw.serialize(serializer)
+    }
+}
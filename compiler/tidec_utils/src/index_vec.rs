@@ -226,6 +226,12 @@ impl<I: Idx, T> BorrowMut<IdxSlice<I, T>> for IdxVec<I, T> {
     }
 }
 
+impl<I: Idx, T: Clone> Clone for IdxVec<I, T> {
+    fn clone(&self) -> Self {
+        IdxVec::from_raw(self.raw.clone())
+    }
+}
+
 impl<I: Idx, T> FromIterator<T> for IdxVec<I, T> {
     #[inline]
     fn from_iter<J>(iter: J) -> Self
@@ -236,6 +242,20 @@ impl<I: Idx, T> FromIterator<T> for IdxVec<I, T> {
     }
 }
 
+impl<I: Idx, T> Extend<T> for IdxVec<I, T> {
+    #[inline]
+    fn extend<J: IntoIterator<Item = T>>(&mut self, iter: J) {
+        self.raw.extend(iter);
+    }
+}
+
+impl<'a, I: Idx, T: Copy + 'a> Extend<&'a T> for IdxVec<I, T> {
+    #[inline]
+    fn extend<J: IntoIterator<Item = &'a T>>(&mut self, iter: J) {
+        self.raw.extend(iter);
+    }
+}
+
 impl<I: Idx, T> IntoIterator for IdxVec<I, T> {
     type Item = T;
     type IntoIter = vec::IntoIter<T>;
@@ -265,3 +285,18 @@ impl<'a, I: Idx, T> IntoIterator for &'a mut IdxVec<I, T> {
         self.iter_mut()
     }
 }
+
+// An `IdxVec` is serialized as a plain sequence, as its indices are implicit.
+#[cfg(feature = "serde")]
+impl<I: Idx, T: serde::Serialize> serde::Serialize for IdxVec<I, T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.raw.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, I: Idx, T: serde::Deserialize<'de>> serde::Deserialize<'de> for IdxVec<I, T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::deserialize(deserializer).map(IdxVec::from_raw)
+    }
+}
@@ -1,4 +1,11 @@
+pub mod arena;
+pub mod bit_set;
+pub mod fingerprint;
 pub mod idx;
 pub mod index_slice;
 pub mod index_vec;
+pub mod sorted_map;
+pub mod stable_hasher;
+
+mod newtype_index; // the macro is exported at the crate root
 mod variadic_log_macros; // to expose the macros `pub` is not needed
@@ -0,0 +1,175 @@
+/// Defines a newtype of `u32` that implements `Idx`, to index an `IdxVec`.
+///
+/// ```
+/// tidec_utils::newtype_index! {
+///     /// The index of a basic block.
+///     pub struct BasicBlock {
+///         debug_format = "bb{}";
+///         niche;
+///         const ENTRY_BLOCK = 0;
+///     }
+/// }
+///
+/// assert_eq!(format!("{:?}", ENTRY_BLOCK), "bb0");
+/// assert_eq!(std::mem::size_of::<Option<BasicBlock>>(), 4);
+/// assert!(BasicBlock::from_u32(0) < BasicBlock::from_u32(1));
+/// ```
+///
+/// The newtype is `Clone`, `Copy`, `Eq`, `Ord` and `Hash`, and it is (de)serialized
+/// as its `u32` with the `serde` feature of the crate that defines it. The items in
+/// the braces are optional, but they must be given in this order:
+/// - `debug_format = "..."`: the format of its `Debug`, with a `{}` for the index
+///   (by default, the one of a tuple struct, e.g., `BasicBlock(0)`).
+/// - `niche`: `u32::MAX` is not a valid index, so that `Option` of the newtype has
+///   the size of a `u32`.
+/// - `const NAME = VALUE;`: constants of the newtype with the given index.
+// It corresponds to `newtype_index!` in rustc_index_macros/src/newtype.rs
+#[macro_export]
+macro_rules! newtype_index {
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident {
+            $(debug_format = $debug_format:literal;)?
+            niche;
+            $(const $const_name:ident = $const_value:expr;)*
+        }
+    ) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        $vis struct $name(::std::num::NonZeroU32);
+
+        impl $name {
+            /// The largest index, as `u32::MAX` is the niche.
+            $vis const MAX_AS_U32: u32 = u32::MAX - 1;
+
+            /// Returns the index of the given value. It panics if it is larger than
+            /// `MAX_AS_U32`.
+            #[inline]
+            $vis const fn from_u32(value: u32) -> Self {
+                // The index is stored plus one, so that `u32::MAX` is the zero niche and
+                // the derived order is the one of the indices.
+                match value.checked_add(1) {
+                    Some(shifted) => match ::std::num::NonZeroU32::new(shifted) {
+                        Some(shifted) => $name(shifted),
+                        None => unreachable!(),
+                    },
+                    None => panic!(concat!("Index out of range for ", stringify!($name))),
+                }
+            }
+
+            #[inline]
+            $vis const fn as_u32(self) -> u32 {
+                self.0.get() - 1
+            }
+        }
+
+        $crate::newtype_index!(@common $name, $vis, [$($debug_format)?], [$($const_name = $const_value;)*]);
+    };
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident {
+            $(debug_format = $debug_format:literal;)?
+            $(const $const_name:ident = $const_value:expr;)*
+        }
+    ) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        $vis struct $name(u32);
+
+        impl $name {
+            /// The largest index.
+            $vis const MAX_AS_U32: u32 = u32::MAX;
+
+            #[inline]
+            $vis const fn from_u32(value: u32) -> Self {
+                $name(value)
+            }
+
+            #[inline]
+            $vis const fn as_u32(self) -> u32 {
+                self.0
+            }
+        }
+
+        $crate::newtype_index!(@common $name, $vis, [$($debug_format)?], [$($const_name = $const_value;)*]);
+    };
+    (@common $name:ident, $vis:vis, [$($debug_format:literal)?], [$($const_name:ident = $const_value:expr;)*]) => {
+        $($vis const $const_name: $name = $name::from_u32($const_value);)*
+
+        impl $name {
+            /// Returns the index of the given value. It panics if it is larger than
+            /// `MAX_AS_U32`.
+            #[inline]
+            $vis fn from_usize(value: usize) -> Self {
+                match u32::try_from(value) {
+                    Ok(value) if value <= Self::MAX_AS_U32 => Self::from_u32(value),
+                    _ => panic!(
+                        "Index {} out of range for {}",
+                        value,
+                        stringify!($name)
+                    ),
+                }
+            }
+
+            #[inline]
+            $vis const fn as_usize(self) -> usize {
+                self.as_u32() as usize
+            }
+        }
+
+        impl $crate::idx::Idx for $name {
+            #[inline]
+            fn new(idx: usize) -> Self {
+                Self::from_usize(idx)
+            }
+
+            #[inline]
+            fn idx(&self) -> usize {
+                self.as_usize()
+            }
+
+            #[inline]
+            fn incr(&mut self) {
+                self.incr_by(1);
+            }
+
+            #[inline]
+            fn incr_by(&mut self, by: usize) {
+                *self = Self::from_usize(self.as_usize() + by);
+            }
+        }
+
+        impl ::std::fmt::Debug for $name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                $crate::newtype_index!(@debug f, $name, self.as_u32(), [$($debug_format)?])
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl serde::Serialize for $name {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                self.as_u32().serialize(serializer)
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let value = u32::deserialize(deserializer)?;
+                if value > Self::MAX_AS_U32 {
+                    return Err(serde::de::Error::custom(concat!(
+                        "Index out of range for ",
+                        stringify!($name)
+                    )));
+                }
+                Ok(Self::from_u32(value))
+            }
+        }
+    };
+    (@debug $f:ident, $name:ident, $value:expr, [$debug_format:literal]) => {
+        write!($f, $debug_format, $value)
+    };
+    (@debug $f:ident, $name:ident, $value:expr, []) => {
+        write!($f, concat!(stringify!($name), "({})"), $value)
+    };
+}
@@ -0,0 +1,230 @@
+//! Maps keyed by an index type, sorted by their keys, for the sparse keys (where an
+//! `IdxVec` would waste memory) that are mostly read (e.g., the predecessors of a
+//! few basic blocks, or the symbols of the `DefId`s).
+//!
+//! It is inspired by the `SortedMap` and `SortedIndexMultiMap` types from the `rustc`
+//! compiler. The insertions are linear, as the entries are kept in a sorted `Vec`,
+//! while the lookups are binary searches.
+//!
+//! ```
+//! use tidec_utils::{newtype_index, sorted_map::{IdxMap, IdxMultiMap}};
+//!
+//! newtype_index! {
+//!     pub struct BasicBlock {}
+//! }
+//!
+//! let bb = BasicBlock::from_u32;
+//! let names: IdxMap<_, _> = [(bb(7), "exit"), (bb(0), "entry")].into_iter().collect();
+//! assert_eq!(names[&bb(7)], "exit");
+//! assert_eq!(names.keys().copied().collect::<Vec<_>>(), [bb(0), bb(7)]);
+//!
+//! let mut predecessors = IdxMultiMap::new();
+//! predecessors.insert(bb(7), bb(3));
+//! predecessors.insert(bb(2), bb(0));
+//! predecessors.insert(bb(7), bb(1));
+//! assert_eq!(predecessors.get_by_key(&bb(7)).copied().collect::<Vec<_>>(), [bb(3), bb(1)]);
+//! ```
+
+use crate::idx::Idx;
+use std::ops::Index;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// A map from the indices of type `I` to `V`, sorted by the indices.
+// It corresponds to `SortedMap` in rustc_data_structures/src/sorted_map.rs
+pub struct IdxMap<I: Idx, V> {
+    entries: Vec<(I, V)>,
+}
+
+impl<I: Idx, V> Default for IdxMap<I, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I: Idx, V> IdxMap<I, V> {
+    pub const fn new() -> Self {
+        IdxMap {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Returns the position of the given key, or the one where it would be inserted.
+    #[inline]
+    fn lookup(&self, key: &I) -> Result<usize, usize> {
+        self.entries
+            .binary_search_by_key(&key.idx(), |(entry_key, _)| entry_key.idx())
+    }
+
+    /// Inserts the value of the given key, and returns the previous one, if any.
+    pub fn insert(&mut self, key: I, value: V) -> Option<V> {
+        match self.lookup(&key) {
+            Ok(pos) => Some(std::mem::replace(&mut self.entries[pos].1, value)),
+            Err(pos) => {
+                self.entries.insert(pos, (key, value));
+                None
+            }
+        }
+    }
+
+    /// Removes the value of the given key, and returns it, if any.
+    pub fn remove(&mut self, key: &I) -> Option<V> {
+        self.lookup(key).ok().map(|pos| self.entries.remove(pos).1)
+    }
+
+    #[inline]
+    pub fn get(&self, key: &I) -> Option<&V> {
+        self.lookup(key).ok().map(|pos| &self.entries[pos].1)
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, key: &I) -> Option<&mut V> {
+        self.lookup(key).ok().map(|pos| &mut self.entries[pos].1)
+    }
+
+    /// Returns the value of the given key, inserting the one of `default` if there
+    /// is none.
+    pub fn get_or_insert_with(&mut self, key: I, default: impl FnOnce() -> V) -> &mut V {
+        let pos = match self.lookup(&key) {
+            Ok(pos) => pos,
+            Err(pos) => {
+                self.entries.insert(pos, (key, default()));
+                pos
+            }
+        };
+        &mut self.entries[pos].1
+    }
+
+    #[inline]
+    pub fn contains_key(&self, key: &I) -> bool {
+        self.lookup(key).is_ok()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the entries, sorted by their keys.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&I, &V)> + ExactSizeIterator {
+        self.entries.iter().map(|(key, value)| (key, value))
+    }
+
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &I> + ExactSizeIterator {
+        self.entries.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &V> + ExactSizeIterator {
+        self.entries.iter().map(|(_, value)| value)
+    }
+}
+
+impl<I: Idx, V> Index<&I> for IdxMap<I, V> {
+    type Output = V;
+
+    fn index(&self, key: &I) -> &V {
+        self.get(key)
+            .unwrap_or_else(|| panic!("No entry for the index {} in the map", key.idx()))
+    }
+}
+
+impl<I: Idx, V> FromIterator<(I, V)> for IdxMap<I, V> {
+    /// Creates the map from the given entries, where the last value of a key wins.
+    fn from_iter<T: IntoIterator<Item = (I, V)>>(iter: T) -> Self {
+        let mut entries: Vec<(I, V)> = iter.into_iter().collect();
+        // The sort is stable, so the last value of a key is the last of its entries.
+        entries.sort_by_key(|(key, _)| key.idx());
+        entries.reverse();
+        entries.dedup_by_key(|(key, _)| key.idx());
+        entries.reverse();
+        IdxMap { entries }
+    }
+}
+
+impl<I: Idx, V> IntoIterator for IdxMap<I, V> {
+    type Item = (I, V);
+    type IntoIter = std::vec::IntoIter<(I, V)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// A map from the indices of type `I` to any number of `V`s, sorted by the indices,
+/// where the values of a key are in the order in which they were inserted.
+// It corresponds to `SortedIndexMultiMap` in rustc_data_structures/src/sorted_map/index_map.rs
+pub struct IdxMultiMap<I: Idx, V> {
+    entries: Vec<(I, V)>,
+}
+
+impl<I: Idx, V> Default for IdxMultiMap<I, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I: Idx, V> IdxMultiMap<I, V> {
+    pub const fn new() -> Self {
+        IdxMultiMap {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Returns the range of the entries of the given key.
+    fn lookup(&self, key: &I) -> std::ops::Range<usize> {
+        let start = self
+            .entries
+            .partition_point(|(entry_key, _)| entry_key.idx() < key.idx());
+        let len =
+            self.entries[start..].partition_point(|(entry_key, _)| entry_key.idx() == key.idx());
+        start..start + len
+    }
+
+    /// Adds a value to the given key, after its other values.
+    pub fn insert(&mut self, key: I, value: V) {
+        let end = self.lookup(&key).end;
+        self.entries.insert(end, (key, value));
+    }
+
+    /// Returns the values of the given key, in the order in which they were inserted.
+    pub fn get_by_key(&self, key: &I) -> impl DoubleEndedIterator<Item = &V> + ExactSizeIterator {
+        self.entries[self.lookup(key)]
+            .iter()
+            .map(|(_, value)| value)
+    }
+
+    #[inline]
+    pub fn contains_key(&self, key: &I) -> bool {
+        !self.lookup(key).is_empty()
+    }
+
+    /// Returns the number of the values, of all the keys.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the entries, sorted by their keys.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&I, &V)> + ExactSizeIterator {
+        self.entries.iter().map(|(key, value)| (key, value))
+    }
+}
+
+impl<I: Idx, V> FromIterator<(I, V)> for IdxMultiMap<I, V> {
+    fn from_iter<T: IntoIterator<Item = (I, V)>>(iter: T) -> Self {
+        let mut entries: Vec<(I, V)> = iter.into_iter().collect();
+        // The sort is stable, so the values of a key keep their order.
+        entries.sort_by_key(|(key, _)| key.idx());
+        IdxMultiMap { entries }
+    }
+}
@@ -0,0 +1,72 @@
+//! A hasher whose results are stable across runs, platforms and compiler versions,
+//! unlike the `DefaultHasher` of the standard library. It is used when a hash decides
+//! something observable, such as the codegen unit of a body.
+// It corresponds to `StableHasher` in rustc_data_structures/src/stable_hasher.rs
+
+use std::hash::Hasher;
+
+use crate::fingerprint::Fingerprint;
+
+const FNV_OFFSET_BASIS: u128 = 0x6c62_272e_07bb_0142_62b8_2175_6295_c58d;
+const FNV_PRIME: u128 = 0x0000_0000_0100_0000_0000_0000_0000_013b;
+
+#[derive(Debug, Clone)]
+/// The 128-bit FNV-1a hash. The integers are hashed in little-endian order, so that
+/// the hash does not depend on the endianness of the host.
+pub struct StableHasher {
+    state: u128,
+}
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StableHasher {
+    pub fn new() -> Self {
+        StableHasher {
+            state: FNV_OFFSET_BASIS,
+        }
+    }
+
+    /// Returns the whole 128-bit hash, while `finish` folds it into 64 bits.
+    pub fn finish_fingerprint(&self) -> Fingerprint {
+        Fingerprint::new((self.state >> 64) as u64, self.state as u64)
+    }
+}
+
+impl Hasher for StableHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.state ^= *byte as u128;
+            self.state = self.state.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        // The `usize`s are hashed as `u64`s, so that the hash does not depend on the
+        // pointer size of the host.
+        self.write(&(i as u64).to_le_bytes());
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn finish(&self) -> u64 {
+        self.finish_fingerprint().to_smaller_hash()
+    }
+}